@@ -31,6 +31,56 @@ pub enum Command {
     /// Viewing and managing stats
     #[command(subcommand)]
     Stats(StatsCommand),
+
+    /// Manage the artifacts of the deployments of a project
+    #[command(subcommand)]
+    Artifacts(ArtifactsCommand),
+
+    /// Manage the Docker hosts projects are placed on
+    #[command(subcommand)]
+    Nodes(NodesCommand),
+
+    /// Move a project to another node
+    Migrate {
+        /// Project to move
+        #[arg(long)]
+        project: ProjectName,
+
+        /// Node to move the project to, instead of the one with the fewest projects
+        #[arg(long)]
+        node: Option<String>,
+    },
+
+    /// View the limits of the requests to a project, or override those of the gateway
+    Limits {
+        /// Project to manage the limits of
+        #[arg(long)]
+        project: ProjectName,
+
+        /// Size in MiB the body of a request may have
+        #[arg(long)]
+        max_request_body_mib: Option<u64>,
+
+        /// Seconds the project has to start responding to a request
+        #[arg(long)]
+        response_timeout: Option<u64>,
+
+        /// Seconds the body of a request or of a response may go without data
+        #[arg(long)]
+        idle_timeout: Option<u64>,
+
+        /// Requests each client may make a second, or 0 to not limit them
+        #[arg(long)]
+        requests_per_second: Option<u32>,
+
+        /// Requests each client may make at once
+        #[arg(long)]
+        burst: Option<u32>,
+
+        /// Go back to the limits of the gateway
+        #[arg(long, conflicts_with_all = ["max_request_body_mib", "response_timeout", "idle_timeout", "requests_per_second", "burst"])]
+        reset: bool,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -99,6 +149,73 @@ pub enum StatsCommand {
     },
 }
 
+#[derive(Subcommand, Debug)]
+pub enum NodesCommand {
+    /// View the nodes and how many projects are on each
+    List,
+
+    /// Let projects be placed on another Docker host
+    Add {
+        /// Name of the node
+        #[arg(long)]
+        name: String,
+
+        /// Path to the socket of the Docker daemon of the node, or its tcp:// address
+        #[arg(long)]
+        docker_host: String,
+    },
+
+    /// Forget about a node which has no projects left
+    Remove {
+        /// Name of the node
+        #[arg(long)]
+        name: String,
+    },
+
+    /// Keep new projects off a node and move its projects to the other nodes
+    Drain {
+        /// Name of the node
+        #[arg(long)]
+        name: String,
+    },
+
+    /// Let new projects on a node again
+    Undrain {
+        /// Name of the node
+        #[arg(long)]
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ArtifactsCommand {
+    /// View which artifacts of a project are kept, or override it
+    Retention {
+        /// Project to manage the artifacts of
+        #[arg(long)]
+        project: ProjectName,
+
+        /// Keep the artifacts of this many of the latest deployments of each service
+        #[arg(long)]
+        keep: Option<u32>,
+
+        /// Size in MiB the artifacts and build caches of the project may take up
+        #[arg(long)]
+        max_mib: Option<u64>,
+
+        /// Go back to the retention of the deployer
+        #[arg(long, conflicts_with_all = ["keep", "max_mib"])]
+        reset: bool,
+    },
+
+    /// Remove the artifacts a project does not keep right away, and show the space it reclaimed
+    Sweep {
+        /// Project to sweep the artifacts of
+        #[arg(long)]
+        project: ProjectName,
+    },
+}
+
 fn load_credentials(s: &str) -> Result<serde_json::Value, Error> {
     let credentials = fs::read_to_string(PathBuf::from(s))?;
     serde_json::from_str(&credentials).map_err(|err| Error::from(io::Error::from(err)))
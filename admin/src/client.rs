@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
 use serde::{de::DeserializeOwned, Serialize};
 use shuttle_common::{
-    models::{project, stats, ToJson},
+    models::{artifacts, node, project, stats, ToJson},
     project::ProjectName,
 };
 use tracing::trace;
@@ -74,6 +74,98 @@ impl Client {
             .await
     }
 
+    pub async fn get_artifact_retention(
+        &self,
+        project_name: &ProjectName,
+    ) -> Result<artifacts::Retention> {
+        let path = format!("/projects/{project_name}/artifacts/retention");
+        self.get(&path).await
+    }
+
+    pub async fn set_artifact_retention(
+        &self,
+        project_name: &ProjectName,
+        retention: &artifacts::Retention,
+    ) -> Result<artifacts::Retention> {
+        let path = format!("/projects/{project_name}/artifacts/retention");
+        self.put(&path, retention).await
+    }
+
+    pub async fn reset_artifact_retention(
+        &self,
+        project_name: &ProjectName,
+    ) -> Result<artifacts::Retention> {
+        let path = format!("/projects/{project_name}/artifacts/retention");
+        self.delete(&path, Option::<String>::None).await
+    }
+
+    pub async fn sweep_artifacts(&self, project_name: &ProjectName) -> Result<artifacts::Sweep> {
+        let path = format!("/projects/{project_name}/artifacts/sweep");
+        self.post(&path, Option::<String>::None).await
+    }
+
+    pub async fn get_proxy_limits(
+        &self,
+        project_name: &ProjectName,
+    ) -> Result<project::ProxyLimits> {
+        let path = format!("/admin/projects/{project_name}/limits");
+        self.get(&path).await
+    }
+
+    pub async fn set_proxy_limits(
+        &self,
+        project_name: &ProjectName,
+        limits: &project::ProxyLimits,
+    ) -> Result<project::ProxyLimits> {
+        let path = format!("/admin/projects/{project_name}/limits");
+        self.put(&path, limits).await
+    }
+
+    pub async fn reset_proxy_limits(
+        &self,
+        project_name: &ProjectName,
+    ) -> Result<project::ProxyLimits> {
+        let path = format!("/admin/projects/{project_name}/limits");
+        self.delete(&path, Option::<String>::None).await
+    }
+
+    pub async fn get_nodes(&self) -> Result<Vec<node::Response>> {
+        self.get("/admin/nodes").await
+    }
+
+    pub async fn add_node(&self, name: &str, docker_host: &str) -> Result<node::Response> {
+        let create = node::Create {
+            name: name.to_string(),
+            docker_host: docker_host.to_string(),
+        };
+
+        self.post("/admin/nodes", Some(create)).await
+    }
+
+    pub async fn remove_node(&self, name: &str) -> Result<node::Response> {
+        let path = format!("/admin/nodes/{name}");
+        self.delete(&path, Option::<String>::None).await
+    }
+
+    pub async fn drain_node(&self, name: &str) -> Result<Vec<node::Migration>> {
+        let path = format!("/admin/nodes/{name}/drain");
+        self.post(&path, Option::<String>::None).await
+    }
+
+    pub async fn undrain_node(&self, name: &str) -> Result<node::Response> {
+        let path = format!("/admin/nodes/{name}/drain");
+        self.delete(&path, Option::<String>::None).await
+    }
+
+    pub async fn migrate_project(
+        &self,
+        project_name: &ProjectName,
+        node: Option<String>,
+    ) -> Result<node::Migration> {
+        let path = format!("/admin/projects/{project_name}/migrate");
+        self.post(&path, Some(node::Migrate { node })).await
+    }
+
     async fn post<T: Serialize, R: DeserializeOwned>(
         &self,
         path: &str,
@@ -98,6 +190,21 @@ impl Client {
             .context("failed to extract json body from post response")
     }
 
+    async fn put<T: Serialize, R: DeserializeOwned>(&self, path: &str, body: &T) -> Result<R> {
+        trace!(self.api_key, "using api key");
+
+        reqwest::Client::new()
+            .put(format!("{}{}", self.api_url, path))
+            .bearer_auth(&self.api_key)
+            .json(body)
+            .send()
+            .await
+            .context("failed to make put request")?
+            .to_json()
+            .await
+            .context("failed to extract json body from put response")
+    }
+
     async fn delete<T: Serialize, R: DeserializeOwned>(
         &self,
         path: &str,
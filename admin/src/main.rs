@@ -1,6 +1,6 @@
 use clap::Parser;
 use shuttle_admin::{
-    args::{AcmeCommand, Args, Command, StatsCommand},
+    args::{AcmeCommand, Args, ArtifactsCommand, Command, NodesCommand, StatsCommand},
     client::Client,
     config::get_api_key,
 };
@@ -161,6 +161,150 @@ async fn main() {
                 resp.builds_count, has_capacity
             )
         }
+        Command::Artifacts(ArtifactsCommand::Retention {
+            project,
+            keep,
+            max_mib,
+            reset,
+        }) => {
+            let retention = if reset {
+                client
+                    .reset_artifact_retention(&project)
+                    .await
+                    .expect("to reset the artifact retention")
+            } else if keep.is_some() || max_mib.is_some() {
+                let mut retention = client
+                    .get_artifact_retention(&project)
+                    .await
+                    .expect("to get the artifact retention");
+
+                if let Some(keep) = keep {
+                    retention.keep_deployments = keep;
+                }
+                if let Some(max_mib) = max_mib {
+                    retention.max_bytes = Some(max_mib * 1024 * 1024);
+                }
+
+                client
+                    .set_artifact_retention(&project, &retention)
+                    .await
+                    .expect("to set the artifact retention")
+            } else {
+                client
+                    .get_artifact_retention(&project)
+                    .await
+                    .expect("to get the artifact retention")
+            };
+
+            retention.to_string()
+        }
+        Command::Limits {
+            project,
+            max_request_body_mib,
+            response_timeout,
+            idle_timeout,
+            requests_per_second,
+            burst,
+            reset,
+        } => {
+            let limits = if reset {
+                client
+                    .reset_proxy_limits(&project)
+                    .await
+                    .expect("to reset the proxy limits")
+            } else if max_request_body_mib.is_some()
+                || response_timeout.is_some()
+                || idle_timeout.is_some()
+                || requests_per_second.is_some()
+                || burst.is_some()
+            {
+                let mut limits = client
+                    .get_proxy_limits(&project)
+                    .await
+                    .expect("to get the proxy limits");
+
+                if let Some(max_request_body_mib) = max_request_body_mib {
+                    limits.max_request_body_bytes = max_request_body_mib * 1024 * 1024;
+                }
+                if let Some(response_timeout) = response_timeout {
+                    limits.response_timeout_secs = response_timeout;
+                }
+                if let Some(idle_timeout) = idle_timeout {
+                    limits.idle_timeout_secs = idle_timeout;
+                }
+                if let Some(requests_per_second) = requests_per_second {
+                    limits.requests_per_second = requests_per_second;
+                }
+                if let Some(burst) = burst {
+                    limits.burst = burst;
+                }
+
+                client
+                    .set_proxy_limits(&project, &limits)
+                    .await
+                    .expect("to set the proxy limits")
+            } else {
+                client
+                    .get_proxy_limits(&project)
+                    .await
+                    .expect("to get the proxy limits")
+            };
+
+            limits.to_string()
+        }
+        Command::Nodes(NodesCommand::List) => {
+            let nodes = client.get_nodes().await.expect("to get the nodes");
+
+            let mut res = String::new();
+            for node in nodes {
+                writeln!(res, "{node}").unwrap();
+            }
+
+            res
+        }
+        Command::Nodes(NodesCommand::Add { name, docker_host }) => client
+            .add_node(&name, &docker_host)
+            .await
+            .expect("to add the node")
+            .to_string(),
+        Command::Nodes(NodesCommand::Remove { name }) => {
+            client.remove_node(&name).await.expect("to remove the node");
+
+            format!("Removed node {name}")
+        }
+        Command::Nodes(NodesCommand::Drain { name }) => {
+            let migrations = client.drain_node(&name).await.expect("to drain the node");
+
+            let mut res = format!("Draining node {name}");
+            for migration in migrations {
+                write!(res, "\n  {migration}").unwrap();
+            }
+
+            res
+        }
+        Command::Nodes(NodesCommand::Undrain { name }) => client
+            .undrain_node(&name)
+            .await
+            .expect("to undrain the node")
+            .to_string(),
+        Command::Migrate { project, node } => client
+            .migrate_project(&project, node)
+            .await
+            .expect("to migrate the project")
+            .to_string(),
+        Command::Artifacts(ArtifactsCommand::Sweep { project }) => {
+            let sweep = client
+                .sweep_artifacts(&project)
+                .await
+                .expect("to sweep the artifacts");
+
+            let mut res = sweep.to_string();
+            for id in &sweep.deployments {
+                write!(res, "\n  {id}").unwrap();
+            }
+
+            res
+        }
     };
 
     println!("{res}");
@@ -16,14 +16,15 @@ use sqlx::SqlitePool;
 use tracing::field;
 
 use crate::{
+    device::DeviceAuthorizations,
     secrets::{EdDsaManager, KeyManager},
     user::{UserManagement, UserManager},
     COOKIE_EXPIRATION,
 };
 
 use super::handlers::{
-    convert_cookie, convert_key, get_public_key, get_user, login, logout, post_user,
-    put_user_reset_key, refresh_token,
+    approve_device, convert_cookie, convert_key, get_public_key, get_user, login, logout,
+    poll_device, post_user, put_user_reset_key, refresh_token, start_device,
 };
 
 pub type UserManagerState = Arc<Box<dyn UserManagement>>;
@@ -33,6 +34,7 @@ pub type KeyManagerState = Arc<Box<dyn KeyManager>>;
 pub struct RouterState {
     pub user_manager: UserManagerState,
     pub key_manager: KeyManagerState,
+    pub device_authorizations: DeviceAuthorizations,
 }
 
 // Allow getting a user management state directly
@@ -49,6 +51,13 @@ impl FromRef<RouterState> for KeyManagerState {
     }
 }
 
+// Allow getting the pending device logins directly
+impl FromRef<RouterState> for DeviceAuthorizations {
+    fn from_ref(router_state: &RouterState) -> Self {
+        router_state.device_authorizations.clone()
+    }
+}
+
 pub struct ApiBuilder {
     router: Router<RouterState>,
     pool: Option<SqlitePool>,
@@ -66,6 +75,9 @@ impl ApiBuilder {
         let router = Router::new()
             .route("/login", post(login))
             .route("/logout", post(logout))
+            .route("/login/device", post(start_device))
+            .route("/login/device/token", post(poll_device))
+            .route("/login/device/approve", post(approve_device))
             .route("/auth/session", get(convert_cookie))
             .route("/auth/key", get(convert_key))
             .route("/auth/refresh", post(refresh_token))
@@ -122,6 +134,7 @@ impl ApiBuilder {
         let state = RouterState {
             user_manager: Arc::new(Box::new(user_manager)),
             key_manager: Arc::new(Box::new(key_manager)),
+            device_authorizations: DeviceAuthorizations::default(),
         };
 
         self.router.layer(session_layer).with_state(state)
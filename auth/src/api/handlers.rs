@@ -1,9 +1,12 @@
 use crate::{
+    device::{DeviceAuthorizations, DEVICE_CODE_EXPIRATION, DEVICE_POLL_INTERVAL},
     error::Error,
     user::{AccountName, AccountTier, Admin, Key, User},
+    DEVICE_VERIFICATION_URL,
 };
 use axum::{
     extract::{Path, State},
+    response::{IntoResponse, Response},
     Json,
 };
 use axum_sessions::extractors::{ReadableSession, WritableSession};
@@ -103,6 +106,7 @@ pub(crate) async fn convert_key(
     State(RouterState {
         key_manager,
         user_manager,
+        ..
     }): State<RouterState>,
     key: Key,
 ) -> Result<Json<shuttle_common::backends::auth::ConvertResponse>, StatusCode> {
@@ -122,6 +126,67 @@ pub(crate) async fn convert_key(
     Ok(Json(response))
 }
 
+/// Start a login on a device without a browser, which the user approves in a browser.
+pub(crate) async fn start_device(
+    State(device_authorizations): State<DeviceAuthorizations>,
+) -> Json<user::DeviceAuthorizationResponse> {
+    let (device_code, user_code) = device_authorizations.start();
+
+    Json(user::DeviceAuthorizationResponse {
+        verification_uri_complete: format!("{DEVICE_VERIFICATION_URL}?code={user_code}"),
+        device_code,
+        user_code,
+        verification_uri: DEVICE_VERIFICATION_URL.to_string(),
+        expires_in: DEVICE_CODE_EXPIRATION.as_secs(),
+        interval: DEVICE_POLL_INTERVAL.as_secs(),
+    })
+}
+
+/// Get the API key of the user who approved a device login, or 202 Accepted while it is not
+/// approved yet.
+pub(crate) async fn poll_device(
+    State(RouterState {
+        user_manager,
+        device_authorizations,
+        ..
+    }): State<RouterState>,
+    Json(request): Json<user::DeviceTokenRequest>,
+) -> Result<Response, Error> {
+    let Some(account_name) = device_authorizations.poll(&request.device_code)? else {
+        return Ok(StatusCode::ACCEPTED.into_response());
+    };
+
+    let user = user_manager.get_user(account_name).await?;
+
+    Ok(Json(user::DeviceTokenResponse {
+        api_key: user.key.as_ref().to_string(),
+    })
+    .into_response())
+}
+
+/// Approve a device login for the user logged in to the browser.
+pub(crate) async fn approve_device(
+    session: ReadableSession,
+    State(RouterState {
+        user_manager,
+        device_authorizations,
+        ..
+    }): State<RouterState>,
+    key: Option<Key>,
+    Json(request): Json<user::DeviceApproveRequest>,
+) -> Result<(), Error> {
+    let account_name = match session.get::<String>("account_name") {
+        Some(account_name) => account_name.into(),
+
+        None => match key {
+            Some(key) => user_manager.get_user_by_key(key.into()).await?.name,
+            None => return Err(Error::Unauthorized),
+        },
+    };
+
+    device_authorizations.approve(&request.user_code, account_name)
+}
+
 pub(crate) async fn refresh_token() {}
 
 pub(crate) async fn get_public_key(State(key_manager): State<KeyManagerState>) -> Vec<u8> {
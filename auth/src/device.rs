@@ -0,0 +1,100 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use rand::{distributions::Alphanumeric, seq::SliceRandom, Rng};
+
+use crate::{error::Error, user::AccountName};
+
+/// How long a device has to be approved for before its codes expire
+pub const DEVICE_CODE_EXPIRATION: Duration = Duration::from_secs(60 * 10);
+
+/// How long devices should wait between polls
+pub const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The letters user codes are made of, without vowels so that they cannot spell words, and without
+/// letters which are easily confused
+const USER_CODE_ALPHABET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+
+/// Logins started on devices without a browser, which wait for a user to approve them in a browser,
+/// as in the OAuth device authorization grant
+#[derive(Clone, Default)]
+pub struct DeviceAuthorizations {
+    pending: Arc<Mutex<HashMap<String, DeviceAuthorization>>>,
+}
+
+struct DeviceAuthorization {
+    user_code: String,
+    expires_at: Instant,
+    /// The account which approved the login, once it was approved
+    approved_by: Option<AccountName>,
+}
+
+impl DeviceAuthorizations {
+    /// Start a login, returning the device code the device polls with and the user code the user
+    /// approves it with
+    pub fn start(&self) -> (String, String) {
+        let mut rng = rand::thread_rng();
+        let device_code: String = (&mut rng)
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        let user_code: String = (0..8)
+            .map(|_| *USER_CODE_ALPHABET.choose(&mut rng).unwrap() as char)
+            .collect();
+        let user_code = format!("{}-{}", &user_code[..4], &user_code[4..]);
+
+        let mut pending = self.pending.lock().unwrap();
+        let now = Instant::now();
+        pending.retain(|_, authorization| authorization.expires_at > now);
+        pending.insert(
+            device_code.clone(),
+            DeviceAuthorization {
+                user_code: user_code.clone(),
+                expires_at: now + DEVICE_CODE_EXPIRATION,
+                approved_by: None,
+            },
+        );
+
+        (device_code, user_code)
+    }
+
+    /// Approve the login with `user_code` for the account `account_name`
+    pub fn approve(&self, user_code: &str, account_name: AccountName) -> Result<(), Error> {
+        let user_code = user_code.trim().to_uppercase();
+        let mut pending = self.pending.lock().unwrap();
+
+        let authorization = pending
+            .values_mut()
+            .find(|authorization| {
+                authorization.user_code == user_code && authorization.expires_at > Instant::now()
+            })
+            .ok_or(Error::DeviceCodeExpired)?;
+        authorization.approved_by = Some(account_name);
+
+        Ok(())
+    }
+
+    /// Get the account which approved the login of `device_code`, which ends the login, or `None`
+    /// while it is still waiting to be approved
+    pub fn poll(&self, device_code: &str) -> Result<Option<AccountName>, Error> {
+        let mut pending = self.pending.lock().unwrap();
+
+        match pending.get(device_code) {
+            Some(authorization) if authorization.expires_at <= Instant::now() => {
+                pending.remove(device_code);
+                Err(Error::DeviceCodeExpired)
+            }
+            Some(DeviceAuthorization {
+                approved_by: None, ..
+            }) => Ok(None),
+            Some(_) => Ok(pending
+                .remove(device_code)
+                .and_then(|authorization| authorization.approved_by)),
+            None => Err(Error::DeviceCodeExpired),
+        }
+    }
+}
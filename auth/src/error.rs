@@ -17,6 +17,8 @@ pub enum Error {
     Unauthorized,
     #[error("Forbidden.")]
     Forbidden,
+    #[error("The login code is unknown or has expired.")]
+    DeviceCodeExpired,
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
     #[error(transparent)]
@@ -40,6 +42,7 @@ impl IntoResponse for Error {
     fn into_response(self) -> Response {
         let code = match self {
             Error::Forbidden => StatusCode::FORBIDDEN,
+            Error::DeviceCodeExpired => StatusCode::GONE,
             Error::Unauthorized | Error::KeyMissing => StatusCode::UNAUTHORIZED,
             Error::Database(_) | Error::UserNotFound => StatusCode::NOT_FOUND,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
@@ -1,5 +1,6 @@
 mod api;
 mod args;
+mod device;
 mod error;
 mod secrets;
 mod user;
@@ -22,6 +23,9 @@ pub use args::{Args, Commands, InitArgs};
 
 pub const COOKIE_EXPIRATION: Duration = Duration::from_secs(60 * 60 * 24); // One day
 
+/// The page where users approve logins started on other devices, like `cargo shuttle login`
+pub const DEVICE_VERIFICATION_URL: &str = "https://console.shuttle.rs/device";
+
 pub static MIGRATIONS: Migrator = sqlx::migrate!("./migrations");
 
 pub async fn start(pool: SqlitePool, args: StartArgs) -> io::Result<()> {
@@ -0,0 +1,85 @@
+use axum::body::Body;
+use hyper::http::{header::AUTHORIZATION, Request, StatusCode};
+use serde_json::{self, json, Value};
+
+use crate::helpers::app;
+
+#[tokio::test]
+async fn device_flow() {
+    let app = app().await;
+
+    let response = app.post_user("device-user", "basic").await;
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let user: Value = serde_json::from_slice(&body).unwrap();
+    let key = user["key"].as_str().unwrap();
+
+    // Start a login from the CLI
+    let request = Request::builder()
+        .uri("/login/device")
+        .method("POST")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.send_request(request).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let authorization: Value = serde_json::from_slice(&body).unwrap();
+    let device_code = authorization["device_code"].as_str().unwrap();
+    let user_code = authorization["user_code"].as_str().unwrap();
+
+    let poll = || {
+        Request::builder()
+            .uri("/login/device/token")
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                serde_json::to_vec(&json!({ "device_code": device_code })).unwrap(),
+            ))
+            .unwrap()
+    };
+
+    // The login waits for the user
+    let response = app.send_request(poll()).await;
+
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    // Approving needs the user to be logged in
+    let approve = |auth: Option<&str>| {
+        let mut builder = Request::builder()
+            .uri("/login/device/approve")
+            .method("POST")
+            .header("Content-Type", "application/json");
+        if let Some(auth) = auth {
+            builder = builder.header(AUTHORIZATION, format!("Bearer {auth}"));
+        }
+
+        builder
+            .body(Body::from(
+                serde_json::to_vec(&json!({ "user_code": user_code })).unwrap(),
+            ))
+            .unwrap()
+    };
+    let response = app.send_request(approve(None)).await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app.send_request(approve(Some(key))).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The CLI gets the API key of the user who approved it
+    let response = app.send_request(poll()).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let token: Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(token["api_key"], key);
+
+    // A device code can only be used once
+    let response = app.send_request(poll()).await;
+
+    assert_eq!(response.status(), StatusCode::GONE);
+}
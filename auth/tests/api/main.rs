@@ -1,4 +1,5 @@
 mod auth;
+mod device;
 mod helpers;
 mod session;
 mod users;
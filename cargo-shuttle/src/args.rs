@@ -7,15 +7,27 @@ use std::{
 
 use anyhow::Context;
 use cargo_metadata::MetadataCommand;
+use chrono::{DateTime, Duration, Utc};
 use clap::{
-    builder::{OsStringValueParser, PossibleValue, TypedValueParser},
+    builder::{BoolishValueParser, OsStringValueParser, PossibleValue, TypedValueParser},
     Parser, ValueEnum,
 };
 use clap_complete::Shell;
-use shuttle_common::{models::project::IDLE_MINUTES, project::ProjectName};
+use shuttle_common::{
+    deployment::State as DeploymentState,
+    log::{Level, Source},
+    models::{
+        access::Cidr,
+        project::{maintenance_status, IDLE_MINUTES},
+        service::Routing,
+        webhook::Event,
+    },
+    project::{EnvironmentName, ProjectName},
+};
+use shuttle_service::builder::BuildOptions;
 use uuid::Uuid;
 
-use crate::init::Template;
+use crate::init::{Resource, Template};
 
 #[derive(Parser)]
 #[command(
@@ -35,10 +47,34 @@ pub struct Args {
     /// (allows targeting a custom deployed instance for this command only, mainly for development)
     #[arg(long, env = "SHUTTLE_API")]
     pub api_url: Option<String>,
+    /// Use this environment of the project, like `staging`, which has its own deployments,
+    /// resources and secrets, and is served at `{environment}--{project}`
+    #[arg(global = true, long = "env", env = "SHUTTLE_ENV")]
+    pub environment: Option<EnvironmentName>,
+    /// Use the API key and API URL saved for this profile, like an account on a self-hosted
+    /// deployer, instead of the default ones
+    #[arg(global = true, long, env = "SHUTTLE_PROFILE", value_parser = parse_profile)]
+    pub profile: Option<String>,
+    /// How to print the results of commands
+    #[arg(global = true, long, value_enum, default_value_t = OutputMode::Table)]
+    pub output: OutputMode,
+    /// Never prompt, and answer yes to every confirmation. Prompts are also turned off when the
+    /// `CI` environment variable is set, but confirmations then fail unless this is passed
+    #[arg(global = true, long, short)]
+    pub yes: bool,
     #[command(subcommand)]
     pub cmd: Command,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Tables and messages for people
+    #[default]
+    Table,
+    /// JSON for scripts, with everything else printed to stderr
+    Json,
+}
+
 // Common args for subcommands that deal with projects.
 #[derive(Parser, Debug)]
 pub struct ProjectArgs {
@@ -96,10 +132,21 @@ pub enum Command {
     /// Manage deployments of a shuttle service
     #[command(subcommand)]
     Deployment(DeploymentCommand),
-    /// View the status of a shuttle service
+    /// View the status of the project, its service, resources and recent errors
     Status,
     /// Stop this shuttle service
     Stop,
+    /// View how many replicas serve the running deployment of this service, or scale it
+    Scale {
+        /// Run this many replicas of the deployment, which take turns serving its requests
+        #[arg(long)]
+        replicas: Option<u32>,
+        /// How the replicas take turns: `round-robin`, `sticky` to keep each client on a replica with
+        /// a cookie, `sticky:<cookie>` to name the cookie, or `hash:<header>` to send the requests
+        /// with the same value of a header to the same replica
+        #[arg(long)]
+        routing: Option<Routing>,
+    },
     /// View the logs of a deployment in this shuttle service
     Logs {
         /// Deployment ID to get logs for. Defaults to currently running deployment
@@ -110,6 +157,29 @@ pub enum Command {
         #[arg(short, long)]
         /// Follow log output
         follow: bool,
+        /// Only show the logs after this time, either like `2023-05-01T12:00:00Z` or a duration
+        /// before now like `30s`, `10m`, `2h` or `1d`
+        #[arg(long, value_parser = parse_since)]
+        since: Option<DateTime<Utc>>,
+        /// Only show the logs up to this time, in the same forms as `--since`
+        #[arg(long, value_parser = parse_since)]
+        until: Option<DateTime<Utc>>,
+        /// Only show this many of the most recent logs
+        #[arg(long)]
+        tail: Option<usize>,
+        /// Only show the logs at this level or a more severe one: trace, debug, info, warn or error
+        #[arg(long)]
+        level: Option<Level>,
+        /// Only show the logs of this tracing target, like `my_app::db`, and the modules in it
+        #[arg(long)]
+        target: Option<String>,
+        /// Only show the logs from this source: tracing for the logs of `tracing`, stdout and
+        /// stderr for the lines the service printed, or access for the requests made to it
+        #[arg(long)]
+        source: Option<Source>,
+        /// Only show the logs with a message matching this regular expression
+        #[arg(long)]
+        grep: Option<String>,
     },
     /// List or manage projects on shuttle
     #[command(subcommand)]
@@ -117,25 +187,104 @@ pub enum Command {
     /// Manage resources of a shuttle project
     #[command(subcommand)]
     Resource(ResourceCommand),
+    /// View the volume this service keeps across its deployments
+    #[command(subcommand)]
+    Storage(StorageCommand),
+    /// View the values the persist instance of this service keeps in production
+    #[command(subcommand)]
+    Persist(PersistCommand),
+    /// View the CPU, memory, requests and restarts of the running deployment of this service
+    Metrics(MetricsArgs),
+    /// Forward a local port to the private port of this service, or to one of its databases, so
+    /// that they can be reached with tools like curl or psql without being exposed publicly
+    Proxy(ProxyArgs),
+    /// View the scheduled jobs of the running deployment of this service, and how they ran
+    Cron {
+        #[command(subcommand)]
+        /// What to view, lists the jobs by default
+        cmd: Option<CronCommand>,
+    },
     /// Manage secrets for this shuttle service
-    Secrets,
-    /// Remove cargo build artifacts in the shuttle environment
-    Clean,
+    Secrets {
+        #[command(subcommand)]
+        /// What to do with the secrets, lists them by default
+        cmd: Option<SecretsCommand>,
+    },
+    /// Manage the environment variables the runtime of this shuttle service is started with
+    Env {
+        #[command(subcommand)]
+        /// What to do with the environment variables, lists them by default
+        cmd: Option<EnvCommand>,
+    },
+    /// Manage the webhooks which are called when the deployments of this project change
+    Webhooks {
+        #[command(subcommand)]
+        /// What to do with the webhooks, lists them by default
+        cmd: Option<WebhooksCommand>,
+    },
+    /// Manage the custom domain this project is served on
+    Domain {
+        #[command(subcommand)]
+        /// What to do with the custom domain, lists it by default
+        cmd: Option<DomainCommand>,
+    },
+    /// Manage which clients the gateway lets through to the service, like to keep a staging
+    /// environment private
+    Access {
+        #[command(subcommand)]
+        /// What to do with the rules for the clients, shows them by default
+        cmd: Option<AccessCommand>,
+    },
+    /// Manage the responses the gateway caches for the paths in the `[http.cache]` table of the
+    /// Shuttle.toml of this service
+    #[command(subcommand)]
+    Cache(CacheCommand),
+    /// Remove the cargo build artifacts of this project
+    Clean {
+        /// Purge the build cache of this project in the shuttle environment instead, with its
+        /// incremental compilation state, for when a build keeps failing there but not locally
+        #[arg(long)]
+        remote: bool,
+    },
     /// Login to the shuttle platform
     Login(LoginArgs),
     /// Log out of the shuttle platform
     Logout(LogoutArgs),
     /// Generate shell completions
-    Generate {
+    #[command(subcommand)]
+    Generate(GenerateCommand),
+    /// Print the values an argument of this kind can be completed with, one per line
+    #[command(name = "__complete", hide = true)]
+    Complete { kind: CompletionKind },
+    /// Open an issue on GitHub and provide feedback
+    Feedback,
+    /// Check that everything shuttle needs is installed and working, and how to fix what is not
+    Doctor,
+    /// Upgrade cargo-shuttle to its latest release
+    Upgrade(UpgradeArgs),
+}
+
+#[derive(Parser)]
+pub enum GenerateCommand {
+    /// Generate shell completions, which also complete project names and deployment IDs in bash,
+    /// zsh and fish
+    Completions {
         /// Which shell
-        #[arg(short, long, env, default_value_t = Shell::Bash)]
+        #[arg(default_value_t = Shell::Bash)]
         shell: Shell,
         /// Output to a file (stdout by default)
         #[arg(short, long, env)]
-        output: Option<PathBuf>,
+        output_file: Option<PathBuf>,
     },
-    /// Open an issue on GitHub and provide feedback
-    Feedback,
+}
+
+/// Arguments whose values are looked up when they are completed
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// Names of the projects of the user
+    Projects,
+    /// IDs of the most recent deployments of this service
+    Deployments,
 }
 
 #[derive(Parser)]
@@ -147,20 +296,219 @@ pub enum DeploymentCommand {
         page: u32,
 
         #[arg(long, default_value = "10")]
-        /// How many projects per page to display
+        /// How many deployments per page to display
         limit: u32,
+
+        #[arg(long)]
+        /// Only list the deployments in this state, like `running`, `crashed` or `built`
+        state: Option<DeploymentState>,
+
+        #[arg(long, value_parser = parse_since)]
+        /// Only list the deployments last updated after this time, either like
+        /// `2023-05-01T12:00:00Z` or a duration before now like `30s`, `10m`, `2h` or `1d`
+        since: Option<DateTime<Utc>>,
+
+        #[arg(long, value_parser = parse_since)]
+        /// Only list the deployments last updated up to this time, in the same forms as `--since`
+        until: Option<DateTime<Utc>>,
     },
     /// View status of a deployment
     Status {
         /// ID of deployment to get status for
         id: Uuid,
     },
+    /// Run a previous deployment again, without building it
+    Rollback {
+        #[arg(long)]
+        /// ID of the deployment to roll back to, defaults to the one which ran before the current one
+        to: Option<Uuid>,
+    },
+    /// Cancel a deployment which is queued or building, or reject one which is pending approval
+    Cancel {
+        /// ID of the deployment to cancel
+        id: Uuid,
+    },
+    /// Approve a deployment which is pending approval to run
+    Approve {
+        /// ID of the deployment to approve
+        id: Uuid,
+    },
+    /// View whether deployments are held after they are built until they are approved, or choose it
+    RequireApproval {
+        /// Whether deployments have to be approved, like `on` or `off`
+        #[arg(value_parser = BoolishValueParser::new())]
+        required: Option<bool>,
+    },
 }
 
 #[derive(Parser)]
 pub enum ResourceCommand {
     /// List all the resources for a project
     List,
+    /// Delete a resource of this service, deprovisioning it if it is a database
+    Delete {
+        /// Type of the resource, as shown by `cargo shuttle resource list`, like `database::shared::postgres`
+        resource_type: String,
+    },
+}
+
+#[derive(Parser)]
+pub enum StorageCommand {
+    /// Show how much of its quota the volume of this service takes up
+    Usage,
+}
+
+#[derive(Parser)]
+pub enum PersistCommand {
+    /// List the keys of the persist instance of this service, and the size of their values
+    Ls,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct UpgradeArgs {
+    /// Upgrade to the latest prerelease, which has new features before they are released
+    #[arg(long)]
+    pub preview: bool,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct MetricsArgs {
+    /// How far back to show the metrics for, like `10m`, `6h` or `7d`. They are kept for a week
+    #[arg(long, short, default_value = "1h", value_parser = parse_window)]
+    pub window: Duration,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct ProxyArgs {
+    /// Local port to listen on, a free one is picked by default
+    #[arg(long, short, default_value_t = 0)]
+    pub port: u16,
+    /// Type of the database to forward to instead of the service, as shown by
+    /// `cargo shuttle resource list`, like `database::shared::postgres`
+    #[arg(long)]
+    pub resource: Option<String>,
+}
+
+#[derive(Parser)]
+pub enum SecretsCommand {
+    /// List the keys of the secrets of this service
+    List,
+    /// Set secrets of this service, which it gets the next time it is started
+    Set {
+        /// The secrets to set, like `API_KEY=hunter2`
+        #[arg(required = true, value_parser = parse_secret)]
+        secrets: Vec<(String, String)>,
+    },
+    /// Remove secrets from this service
+    Rm {
+        /// Keys of the secrets to remove
+        #[arg(required = true)]
+        keys: Vec<String>,
+    },
+}
+
+#[derive(Parser)]
+pub enum EnvCommand {
+    /// List the environment variables of this service
+    List,
+    /// Set environment variables of this service, which it gets the next time it is started
+    Set {
+        /// The variables to set, like `LOG_LEVEL=debug`
+        #[arg(required = true, value_parser = parse_env_var)]
+        vars: Vec<(String, String)>,
+    },
+    /// Remove environment variables from this service
+    Unset {
+        /// Names of the variables to remove
+        #[arg(required = true)]
+        keys: Vec<String>,
+    },
+}
+
+#[derive(Parser)]
+pub enum WebhooksCommand {
+    /// List the webhooks of this project
+    List,
+    /// Register a webhook, which gets a signed JSON payload when a deployment gets into a state it
+    /// is registered for
+    Add {
+        /// The http or https URL to POST the payloads to
+        url: String,
+        /// An event to call the webhook for, like `deploy_failed` or `crashed`. Can be given more
+        /// than once, and the webhook is called for every event when it is not given.
+        #[arg(long = "event")]
+        events: Vec<Event>,
+    },
+    /// Remove webhooks from this project
+    Rm {
+        /// Ids of the webhooks to remove
+        #[arg(required = true)]
+        ids: Vec<Uuid>,
+    },
+}
+
+#[derive(Parser)]
+pub enum DomainCommand {
+    /// List the custom domains of this project
+    List,
+    /// Serve this project on a custom domain over HTTPS. The domain needs a CNAME record pointing
+    /// to the shuttle domain of the project first, and the project is restarted to take it.
+    Add {
+        /// The domain, like `example.com` or `api.example.com`
+        fqdn: String,
+    },
+}
+
+#[derive(Parser)]
+pub enum AccessCommand {
+    /// Show which clients are let through to the service
+    Show,
+    /// Replace the rules for the clients of the service
+    Set {
+        /// Only let the clients at this address or in this range, like `10.0.0.0/8`, through.
+        /// Can be given more than once
+        #[arg(long)]
+        allow: Vec<Cidr>,
+        /// Refuse the clients at this address or in this range, even when they are allowed. Can
+        /// be given more than once
+        #[arg(long)]
+        deny: Vec<Cidr>,
+        /// Ask the clients for HTTP basic auth with this username and password, like
+        /// `admin:hunter2`
+        #[arg(long, value_parser = parse_basic, conflicts_with = "bearer")]
+        basic: Option<(String, String)>,
+        /// Ask the clients for this token in an `Authorization: Bearer` header
+        #[arg(long)]
+        bearer: Option<String>,
+    },
+    /// Let every client through to the service again
+    Clear,
+}
+
+#[derive(Parser)]
+pub enum CacheCommand {
+    /// Drop the cached responses, so that the next requests get fresh ones from the service
+    Purge {
+        /// Only drop the responses to the paths starting with this, like `/posts`
+        #[arg(long)]
+        path: Option<String>,
+    },
+}
+
+#[derive(Parser)]
+pub enum CronCommand {
+    /// List the scheduled jobs from the Shuttle.toml of the running deployment, with when they
+    /// run next and how they ran last
+    List,
+    /// List the latest runs of the scheduled jobs, newest first
+    Runs {
+        /// Only list the runs of the job with this name
+        #[arg(long)]
+        name: Option<String>,
+        /// How many runs to list
+        #[arg(long, default_value_t = 50)]
+        limit: u32,
+    },
 }
 
 #[derive(Parser)]
@@ -175,8 +523,8 @@ pub enum ProjectCommand {
     },
     /// Destroy this project's environment (container) on shuttle
     Stop,
-    /// Destroy and create an environment for this project on shuttle
-    Restart(ProjectStartArgs),
+    /// Restart this project's environment on shuttle, keeping its deployments and resources
+    Restart(ProjectRestartArgs),
     /// List all projects belonging to the calling account
     List {
         #[arg(long, default_value = "1")]
@@ -187,6 +535,45 @@ pub enum ProjectCommand {
         /// How many projects per page to display
         limit: u32,
     },
+    /// Serve a page instead of the service of this project while working on it
+    Maintenance {
+        #[command(subcommand)]
+        cmd: MaintenanceCommand,
+    },
+    /// Forward raw TCP, like that of MQTT or game servers, from a port of shuttle to this project
+    Tcp {
+        #[command(subcommand)]
+        cmd: TcpCommand,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub enum MaintenanceCommand {
+    /// Serve a maintenance page instead of the service of this project
+    On {
+        /// Status the page is served with
+        #[arg(long, default_value_t = maintenance_status())]
+        status: u16,
+        /// HTML file to serve instead of the maintenance page of shuttle
+        #[arg(long)]
+        page: Option<PathBuf>,
+    },
+    /// Go back to serving the service of this project
+    Off,
+}
+
+#[derive(Parser, Debug)]
+pub enum TcpCommand {
+    /// Get a port of shuttle which forwards raw TCP to a port the services of this project listen on
+    Expose {
+        /// Port the services of this project listen on
+        port: u16,
+    },
+    /// Stop forwarding raw TCP from a port of shuttle
+    Close {
+        /// Port of shuttle, like the one `cargo shuttle project status` shows
+        port: u16,
+    },
 }
 
 #[derive(Parser, Debug)]
@@ -197,9 +584,17 @@ pub struct ProjectStartArgs {
     pub idle_minutes: u64,
 }
 
+#[derive(Parser, Debug)]
+pub struct ProjectRestartArgs {
+    #[arg(long)]
+    /// Change how long to wait before putting the project in an idle state due to inactivity,
+    /// which destroys and creates the environment instead. 0 means the project will never idle
+    pub idle_minutes: Option<u64>,
+}
+
 #[derive(Parser, Clone, Debug)]
 pub struct LoginArgs {
-    /// API key for the shuttle platform
+    /// API key for the shuttle platform, instead of approving the login in a browser, like in CI
     #[arg(long)]
     pub api_key: Option<String>,
 }
@@ -215,9 +610,53 @@ pub struct DeployArgs {
     /// Allow deployment with uncommited files
     #[arg(long)]
     pub allow_dirty: bool,
+    /// Show all the logs of the deployment as it is built and started, instead of its progress
+    #[arg(long, short)]
+    pub verbose: bool,
     /// Don't run pre-deploy tests
     #[arg(long)]
     pub no_test: bool,
+    /// Package of the service to deploy, for workspaces with several services
+    #[arg(long, short = 'p')]
+    pub package: Option<String>,
+    /// Fail the build if Cargo.lock is missing or needs to be updated
+    #[arg(long)]
+    pub locked: bool,
+    /// Build without accessing the network
+    #[arg(long)]
+    pub offline: bool,
+    /// Show how the service would be built, without building or deploying it
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Show what would change compared to what is running, like new resources and secrets, and
+    /// ask for confirmation before deploying
+    #[arg(long, conflicts_with = "dry_run")]
+    pub preview: bool,
+    /// List the files which would be uploaded, which leaves out the ones matched by .gitignore
+    /// and .shuttleignore files, without deploying
+    #[arg(long, conflicts_with_all = ["dry_run", "preview"])]
+    pub show_archive: bool,
+    /// Deploy this executable or wasm module, which was built beforehand, instead of building the
+    /// service on Shuttle
+    #[arg(long, conflicts_with = "dry_run")]
+    pub artifact: Option<PathBuf>,
+    /// The target triple the artifact was built for, when it is not the one of this machine
+    #[arg(long, requires = "artifact")]
+    pub target: Option<String>,
+    /// How to compress the project before uploading it
+    #[arg(long, value_enum, default_value_t = ArchiveCompression::Zstd)]
+    pub compression: ArchiveCompression,
+    #[command(flatten)]
+    pub feature_args: FeatureArgs,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArchiveCompression {
+    /// Smaller, and faster to compress
+    #[default]
+    Zstd,
+    /// For self-hosted deployers which cannot extract zstd archives yet
+    Gzip,
 }
 
 #[derive(Parser, Debug)]
@@ -231,6 +670,45 @@ pub struct RunArgs {
     /// Use release mode for building the project.
     #[arg(long, short = 'r')]
     pub release: bool,
+    /// Rebuild and restart the services when the files of the project change
+    #[arg(long, short = 'w')]
+    pub watch: bool,
+    /// Only run the service in this package, for workspaces with several services
+    #[arg(long, short = 'p')]
+    pub package: Option<String>,
+    /// Fail the build if Cargo.lock is missing or needs to be updated
+    #[arg(long)]
+    pub locked: bool,
+    /// Build without accessing the network
+    #[arg(long)]
+    pub offline: bool,
+    #[command(flatten)]
+    pub feature_args: FeatureArgs,
+}
+
+/// Args to select the cargo features a service is built with
+#[derive(Parser, Clone, Debug, Default)]
+pub struct FeatureArgs {
+    /// Comma separated list of features to activate
+    #[arg(long, short = 'F', value_delimiter = ',')]
+    pub features: Vec<String>,
+    /// Activate all available features
+    #[arg(long)]
+    pub all_features: bool,
+    /// Do not activate the `default` feature
+    #[arg(long)]
+    pub no_default_features: bool,
+}
+
+impl From<&FeatureArgs> for BuildOptions {
+    fn from(args: &FeatureArgs) -> Self {
+        Self {
+            features: args.features.clone(),
+            all_features: args.all_features,
+            no_default_features: args.no_default_features,
+            ..Default::default()
+        }
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -238,6 +716,14 @@ pub struct InitArgs {
     /// Initialize the project with a template
     #[arg(long, short, value_enum)]
     pub template: Option<InitTemplateArg>,
+    /// Initialize the project from a template in a git repository instead, like
+    /// `https://github.com/user/repo` or `gh:user/repo`. `{{project-name}}` and `{{crate_name}}`
+    /// in its files are replaced with the name of the project
+    #[arg(long, conflicts_with_all = ["template", "resources"])]
+    pub from: Option<String>,
+    /// Resources to take in the main function of the template, like `--resources postgres,secrets`
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub resources: Vec<InitResourceArg>,
     /// Whether to create the environment for this project on shuttle
     #[arg(long)]
     pub create_env: bool,
@@ -276,6 +762,16 @@ pub enum InitTemplateArg {
     None,
 }
 
+#[derive(ValueEnum, Clone, Debug)]
+pub enum InitResourceArg {
+    /// A shared Postgres database, through shuttle-shared-db and sqlx
+    Postgres,
+    /// Secrets from a Secrets.toml, through shuttle-secrets
+    Secrets,
+    /// Storage for serializable data, through shuttle-persist
+    Persist,
+}
+
 impl InitArgs {
     /// `None` -> No template chosen, ask for it
     ///
@@ -301,6 +797,98 @@ impl InitArgs {
             }
         })
     }
+
+    pub fn resources(&self) -> Vec<Resource> {
+        self.resources
+            .iter()
+            .map(|resource| match resource {
+                InitResourceArg::Postgres => Resource::Postgres,
+                InitResourceArg::Secrets => Resource::Secrets,
+                InitResourceArg::Persist => Resource::Persist,
+            })
+            .collect()
+    }
+}
+
+/// Parse a time like `2023-05-01T12:00:00Z`, or a duration before now like `10m`
+fn parse_since(since: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(since) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    let duration = parse_duration(since).ok_or_else(|| {
+        format!("`{since}` is neither a time like 2023-05-01T12:00:00Z nor a duration like 10m")
+    })?;
+
+    Ok(Utc::now() - duration)
+}
+
+/// Parse a window of time like `10m`, `2h` or `7d`, which ends now
+fn parse_window(window: &str) -> Result<Duration, String> {
+    match parse_duration(window) {
+        Some(duration) if duration > Duration::zero() => Ok(duration),
+        _ => Err(format!("`{window}` is not a duration like 10m, 2h or 7d")),
+    }
+}
+
+/// Parse a duration like `30s`, `10m`, `2h` or `1d`
+fn parse_duration(duration: &str) -> Option<Duration> {
+    let split = duration.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = duration.split_at(split);
+    let amount: i64 = amount.parse().ok()?;
+
+    match unit {
+        "s" => Some(Duration::seconds(amount)),
+        "m" => Some(Duration::minutes(amount)),
+        "h" => Some(Duration::hours(amount)),
+        "d" => Some(Duration::days(amount)),
+        _ => None,
+    }
+}
+
+/// Parse a secret like `KEY=VALUE`
+fn parse_secret(secret: &str) -> Result<(String, String), String> {
+    match secret.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("`{secret}` is not a secret like KEY=VALUE")),
+    }
+}
+
+/// Parse the credentials of basic auth like `USERNAME:PASSWORD`
+fn parse_basic(credentials: &str) -> Result<(String, String), String> {
+    match credentials.split_once(':') {
+        Some((username, password)) if !username.is_empty() && !password.is_empty() => {
+            Ok((username.to_string(), password.to_string()))
+        }
+        _ => Err(format!(
+            "`{credentials}` is not a username and password like USERNAME:PASSWORD"
+        )),
+    }
+}
+
+/// Parse an environment variable like `KEY=VALUE`
+fn parse_env_var(var: &str) -> Result<(String, String), String> {
+    match var.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!(
+            "`{var}` is not an environment variable like KEY=VALUE"
+        )),
+    }
+}
+
+/// Parse the name of a profile, which is used as a key in the configuration file
+fn parse_profile(profile: &str) -> Result<String, String> {
+    if !profile.is_empty()
+        && profile
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Ok(profile.to_string())
+    } else {
+        Err(format!(
+            "`{profile}` is not a profile name, which can only have letters, numbers, `-` and `_`"
+        ))
+    }
 }
 
 /// Helper function to parse and return the absolute path
@@ -331,6 +919,8 @@ mod tests {
     fn test_init_args_framework() {
         let init_args = InitArgs {
             template: Some(InitTemplateArg::Axum),
+            from: None,
+            resources: vec![],
             create_env: false,
             login_args: LoginArgs { api_key: None },
             path: PathBuf::new(),
@@ -338,6 +928,8 @@ mod tests {
         assert_eq!(init_args.framework(), Some(Template::Axum));
         let init_args = InitArgs {
             template: Some(InitTemplateArg::None),
+            from: None,
+            resources: vec![],
             create_env: false,
             login_args: LoginArgs { api_key: None },
             path: PathBuf::new(),
@@ -345,6 +937,8 @@ mod tests {
         assert_eq!(init_args.framework(), Some(Template::None));
         let init_args = InitArgs {
             template: None,
+            from: None,
+            resources: vec![],
             create_env: false,
             login_args: LoginArgs { api_key: None },
             path: PathBuf::new(),
@@ -352,6 +946,214 @@ mod tests {
         assert_eq!(init_args.framework(), None);
     }
 
+    #[test]
+    fn since() {
+        assert_eq!(
+            parse_since("2023-05-01T12:00:00Z").unwrap().to_rfc3339(),
+            "2023-05-01T12:00:00+00:00"
+        );
+
+        let ten_minutes_ago = parse_since("10m").unwrap();
+        let age = Utc::now() - ten_minutes_ago;
+        assert!(age >= Duration::minutes(10) && age < Duration::minutes(11));
+
+        assert!(parse_since("10").is_err());
+        assert!(parse_since("10y").is_err());
+        assert!(parse_since("yesterday").is_err());
+    }
+
+    #[test]
+    fn window() {
+        assert_eq!(parse_window("6h").unwrap(), Duration::hours(6));
+        assert_eq!(parse_window("7d").unwrap(), Duration::days(7));
+
+        assert!(parse_window("0m").is_err());
+        assert!(parse_window("2023-05-01T12:00:00Z").is_err());
+
+        match Args::parse_from(["cargo-shuttle", "metrics"]).cmd {
+            Command::Metrics(MetricsArgs { window }) => assert_eq!(window, Duration::hours(1)),
+            _ => panic!("expected the metrics command"),
+        }
+    }
+
+    #[test]
+    fn logs_filters() {
+        let args = Args::parse_from([
+            "cargo-shuttle",
+            "logs",
+            "--level",
+            "WARN",
+            "--target",
+            "my_app::db",
+            "--source",
+            "stderr",
+            "--grep",
+            "timed? out",
+        ]);
+
+        match args.cmd {
+            Command::Logs {
+                level,
+                target,
+                source,
+                grep,
+                until,
+                ..
+            } => {
+                assert_eq!(level, Some(Level::Warn));
+                assert_eq!(target.as_deref(), Some("my_app::db"));
+                assert_eq!(source, Some(Source::Stderr));
+                assert_eq!(grep.as_deref(), Some("timed? out"));
+                assert_eq!(until, None);
+            }
+            _ => panic!("expected the logs command"),
+        }
+
+        assert!(Args::try_parse_from(["cargo-shuttle", "logs", "--level", "loud"]).is_err());
+    }
+
+    #[test]
+    fn deployment_list_filters() {
+        let args = Args::parse_from([
+            "cargo-shuttle",
+            "deployment",
+            "list",
+            "--state",
+            "crashed",
+            "--since",
+            "2023-05-01T12:00:00Z",
+        ]);
+
+        match args.cmd {
+            Command::Deployment(DeploymentCommand::List {
+                page,
+                limit,
+                state,
+                since,
+                until,
+            }) => {
+                assert_eq!((page, limit), (1, 10));
+                assert_eq!(state, Some(DeploymentState::Crashed));
+                assert_eq!(since, Some("2023-05-01T12:00:00Z".parse().unwrap()));
+                assert_eq!(until, None);
+            }
+            _ => panic!("expected the deployment list command"),
+        }
+
+        assert!(
+            Args::try_parse_from(["cargo-shuttle", "deployment", "list", "--state", "gone"])
+                .is_err()
+        );
+
+        let args = Args::parse_from([
+            "cargo-shuttle",
+            "deployment",
+            "list",
+            "--state",
+            "pending-approval",
+        ]);
+        match args.cmd {
+            Command::Deployment(DeploymentCommand::List { state, .. }) => {
+                assert_eq!(state, Some(DeploymentState::PendingApproval))
+            }
+            _ => panic!("expected the deployment list command"),
+        }
+    }
+
+    #[test]
+    fn require_approval() {
+        for (value, expected) in [
+            (Some("on"), Some(true)),
+            (Some("false"), Some(false)),
+            (None, None),
+        ] {
+            let args = Args::parse_from(
+                ["cargo-shuttle", "deployment", "require-approval"]
+                    .into_iter()
+                    .chain(value),
+            );
+
+            match args.cmd {
+                Command::Deployment(DeploymentCommand::RequireApproval { required }) => {
+                    assert_eq!(required, expected)
+                }
+                _ => panic!("expected the require-approval command"),
+            }
+        }
+    }
+
+    #[test]
+    fn secret() {
+        assert_eq!(
+            parse_secret("API_KEY=hunter2").unwrap(),
+            ("API_KEY".to_string(), "hunter2".to_string())
+        );
+        assert_eq!(
+            parse_secret("URL=postgres://host?sslmode=require").unwrap(),
+            (
+                "URL".to_string(),
+                "postgres://host?sslmode=require".to_string()
+            )
+        );
+        assert_eq!(
+            parse_secret("EMPTY=").unwrap(),
+            ("EMPTY".to_string(), String::new())
+        );
+
+        assert!(parse_secret("API_KEY").is_err());
+        assert!(parse_secret("=hunter2").is_err());
+    }
+
+    #[test]
+    fn output() {
+        let args = Args::parse_from(["cargo-shuttle", "status"]);
+        assert_eq!(args.output, OutputMode::Table);
+
+        // The flag can come after the subcommand
+        let args = Args::parse_from(["cargo-shuttle", "deployment", "list", "--output", "json"]);
+        assert_eq!(args.output, OutputMode::Json);
+    }
+
+    #[test]
+    fn completions() {
+        let args = Args::parse_from(["cargo-shuttle", "generate", "completions", "zsh"]);
+        assert!(matches!(
+            args.cmd,
+            Command::Generate(GenerateCommand::Completions {
+                shell: Shell::Zsh,
+                output_file: None
+            })
+        ));
+
+        // Where the completions go does not clash with the global output mode
+        let args = Args::parse_from([
+            "cargo-shuttle",
+            "generate",
+            "completions",
+            "fish",
+            "--output-file",
+            "shuttle.fish",
+            "--output",
+            "json",
+        ]);
+        assert_eq!(args.output, OutputMode::Json);
+        assert!(matches!(
+            args.cmd,
+            Command::Generate(GenerateCommand::Completions {
+                shell: Shell::Fish,
+                output_file: Some(path)
+            }) if path == std::path::Path::new("shuttle.fish")
+        ));
+
+        let args = Args::parse_from(["cargo-shuttle", "__complete", "deployments"]);
+        assert!(matches!(
+            args.cmd,
+            Command::Complete {
+                kind: CompletionKind::Deployments
+            }
+        ));
+    }
+
     #[test]
     fn workspace_path() {
         let project_args = ProjectArgs {
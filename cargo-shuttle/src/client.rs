@@ -1,25 +1,126 @@
+use std::collections::BTreeMap;
 use std::fmt::Write;
+use std::sync::Once;
 
 use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use crossterm::style::Stylize;
 use headers::{Authorization, HeaderMapExt};
-use reqwest::Response;
+use reqwest::{Response, StatusCode};
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, RequestBuilder};
 use reqwest_retry::policies::ExponentialBackoff;
 use reqwest_retry::RetryTransientMiddleware;
 use serde::{Deserialize, Serialize};
-use shuttle_common::models::{deployment, project, secret, service, ToJson};
-use shuttle_common::project::ProjectName;
+use shuttle_common::deployment::State;
+use shuttle_common::log::{Level, Source};
+use shuttle_common::models::{
+    access, cache, cron, deployment, domain, env, metrics, persist, project, secret, service,
+    storage, upload, user, webhook, ToJson,
+};
+use shuttle_common::project::{EnvironmentName, ProjectName};
 use shuttle_common::{resource, ApiKey, ApiUrl, LogItem};
 use tokio::net::TcpStream;
 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
 use tracing::error;
+use url::form_urlencoded;
 use uuid::Uuid;
 
+use crate::args::DeployArgs;
+use crate::doctor::{compatible, ApiStatus};
+use crate::git::GitMetadata;
+
+/// The header the API sends its version in with every response
+const VERSION_HEADER: &str = "x-shuttle-version";
+
+/// The W3C trace context header, which starts the trace of a deployment here so that the spans of
+/// every service which handles it are in the same trace
+const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// Which logs of a deployment to get
+#[derive(Clone, Debug, Default)]
+pub struct LogsQuery {
+    /// Only the logs after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only the logs up to this time
+    pub until: Option<DateTime<Utc>>,
+    /// Only this many of the most recent logs
+    pub tail: Option<usize>,
+    /// Only the logs at this level or a more severe one
+    pub level: Option<Level>,
+    /// Only the logs of this tracing target and the modules in it
+    pub target: Option<String>,
+    /// Only the logs from this source
+    pub source: Option<Source>,
+    /// Only the logs with a message matching this regular expression
+    pub grep: Option<String>,
+}
+
+impl LogsQuery {
+    fn to_query_string(&self) -> String {
+        let mut query = Vec::new();
+        let encode =
+            |value: &str| form_urlencoded::byte_serialize(value.as_bytes()).collect::<String>();
+
+        // The `Z` form of the offset needs no escaping in a URL, unlike `+00:00`
+        if let Some(since) = self.since {
+            query.push(format!(
+                "since={}",
+                since.to_rfc3339_opts(SecondsFormat::Micros, true)
+            ));
+        }
+
+        if let Some(until) = self.until {
+            query.push(format!(
+                "until={}",
+                until.to_rfc3339_opts(SecondsFormat::Micros, true)
+            ));
+        }
+
+        if let Some(tail) = self.tail {
+            query.push(format!("tail={tail}"));
+        }
+
+        if let Some(level) = &self.level {
+            query.push(format!("level={}", level.as_str()));
+        }
+
+        if let Some(target) = &self.target {
+            query.push(format!("target={}", encode(target)));
+        }
+
+        if let Some(source) = &self.source {
+            query.push(format!("source={source}"));
+        }
+
+        if let Some(grep) = &self.grep {
+            query.push(format!("grep={}", encode(grep)));
+        }
+
+        if query.is_empty() {
+            String::new()
+        } else {
+            format!("?{}", query.join("&"))
+        }
+    }
+}
+
+/// Which deployments of a service to list
+#[derive(Clone, Debug, Default)]
+pub struct DeploymentsFilter {
+    /// Only the deployments in this state
+    pub state: Option<State>,
+    /// Only the deployments last updated after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only the deployments last updated up to this time
+    pub until: Option<DateTime<Utc>>,
+}
+
 #[derive(Clone)]
 pub struct Client {
     api_url: ApiUrl,
     api_key: Option<ApiKey>,
+    environment: Option<EnvironmentName>,
 }
 
 impl Client {
@@ -27,41 +128,132 @@ impl Client {
         Self {
             api_url,
             api_key: None,
+            environment: None,
         }
     }
 
+    pub fn api_url(&self) -> &ApiUrl {
+        &self.api_url
+    }
+
     pub fn set_api_key(&mut self, api_key: ApiKey) {
         self.api_key = Some(api_key);
     }
 
+    /// Use the service of `environment` instead of the one named after the project
+    pub fn set_environment(&mut self, environment: Option<EnvironmentName>) {
+        self.environment = environment;
+    }
+
+    fn service_name(&self, project: &ProjectName) -> String {
+        project.service_name(self.environment.as_ref())
+    }
+
+    /// Deploy the archive which was uploaded with the hash `upload`
     pub async fn deploy(
         &self,
-        data: Vec<u8>,
+        upload: &str,
         project: &ProjectName,
-        no_test: bool,
+        args: &DeployArgs,
+        git: Option<&GitMetadata>,
+        source_hash: &str,
     ) -> Result<deployment::Response> {
         let mut path = format!(
             "/projects/{}/services/{}",
             project.as_str(),
-            project.as_str()
+            &self.service_name(project)
         );
 
-        if no_test {
-            let _ = write!(path, "?no-test");
+        let mut query = vec![format!("upload={upload}")];
+
+        if args.no_test {
+            query.push("no-test".to_string());
         }
 
+        if let Some(package) = &args.package {
+            query.push(format!("package={package}"));
+        }
+
+        if !args.feature_args.features.is_empty() {
+            query.push(format!("features={}", args.feature_args.features.join(",")));
+        }
+
+        if args.feature_args.all_features {
+            query.push("all-features".to_string());
+        }
+
+        if args.feature_args.no_default_features {
+            query.push("no-default-features".to_string());
+        }
+
+        if args.locked {
+            query.push("locked".to_string());
+        }
+
+        if args.offline {
+            query.push("offline".to_string());
+        }
+
+        if args.artifact.is_some() {
+            query.push("artifact".to_string());
+        }
+
+        if let Some(git) = git {
+            let encode =
+                |value: &str| form_urlencoded::byte_serialize(value.as_bytes()).collect::<String>();
+
+            query.push(format!("git-commit-id={}", encode(&git.commit_id)));
+            query.push(format!("git-commit-msg={}", encode(&git.commit_msg)));
+            if let Some(branch) = &git.branch {
+                query.push(format!("git-branch={}", encode(branch)));
+            }
+            query.push(format!("git-dirty={}", git.dirty));
+        }
+
+        query.push(format!("source-hash={source_hash}"));
+
+        let _ = write!(path, "?{}", query.join("&"));
+
         let url = format!("{}{}", self.api_url, path);
+        let builder = self
+            .set_builder_auth(Self::get_retry_client().post(url))
+            .header(TRACEPARENT_HEADER, traceparent());
 
-        let mut builder = Self::get_retry_client().post(url);
+        Self::send(builder)
+            .await
+            .context("failed to send deployment to the Shuttle server")?
+            .to_json()
+            .await
+    }
+
+    /// Find out how much of the archive with the SHA-256 hash `hash` was uploaded already
+    pub async fn get_upload(&self, project: &ProjectName, hash: &str) -> Result<upload::Response> {
+        let path = format!("/projects/{}/uploads/{hash}", project.as_str());
+
+        self.get(path).await
+    }
+
+    /// Upload the part of the archive with the SHA-256 hash `hash` which starts at `offset`
+    pub async fn upload_chunk(
+        &self,
+        project: &ProjectName,
+        hash: &str,
+        offset: u64,
+        chunk: Vec<u8>,
+    ) -> Result<upload::Response> {
+        let url = format!(
+            "{}/projects/{}/uploads/{hash}?offset={offset}",
+            self.api_url,
+            project.as_str()
+        );
+
+        let mut builder = Self::get_retry_client().patch(url);
 
         builder = self.set_builder_auth(builder);
 
-        builder
-            .body(data)
-            .header("Transfer-Encoding", "chunked")
-            .send()
+        Self::send(builder.body(chunk))
             .await
-            .context("failed to send deployment to the Shuttle server")?
+            .context("failed to upload a part of the archive")?
             .to_json()
             .await
     }
@@ -70,17 +262,68 @@ impl Client {
         let path = format!(
             "/projects/{}/services/{}",
             project.as_str(),
-            project.as_str()
+            &self.service_name(project)
         );
 
         self.delete(path).await
     }
 
+    pub async fn get_replicas(&self, project: &ProjectName) -> Result<service::Replicas> {
+        let path = format!(
+            "/projects/{}/services/{}/replicas",
+            project.as_str(),
+            &self.service_name(project)
+        );
+
+        self.get(path).await
+    }
+
+    pub async fn scale_service(
+        &self,
+        project: &ProjectName,
+        replicas: u32,
+        routing: Option<service::Routing>,
+    ) -> Result<service::Replicas> {
+        let path = format!(
+            "/projects/{}/services/{}/replicas",
+            project.as_str(),
+            &self.service_name(project)
+        );
+
+        self.put(path, Some(service::Replicas { replicas, routing }))
+            .await
+            .context("failed to scale the service")?
+            .to_json()
+            .await
+    }
+
+    pub async fn rollback_service(
+        &self,
+        project: &ProjectName,
+        to: Option<&Uuid>,
+    ) -> Result<deployment::Response> {
+        let mut path = format!(
+            "/projects/{}/services/{}/rollback",
+            project.as_str(),
+            &self.service_name(project)
+        );
+
+        if let Some(to) = to {
+            let _ = write!(path, "?to={to}");
+        }
+
+        self.post(path, Option::<String>::None)
+            .await
+            .context("failed to roll back the service")?
+            .to_json()
+            .await
+    }
+
     pub async fn get_service(&self, project: &ProjectName) -> Result<service::Summary> {
         let path = format!(
             "/projects/{}/services/{}",
             project.as_str(),
-            project.as_str()
+            &self.service_name(project)
         );
 
         self.get(path).await
@@ -93,12 +336,29 @@ impl Client {
         let path = format!(
             "/projects/{}/services/{}/resources",
             project.as_str(),
-            project.as_str(),
+            &self.service_name(project),
         );
 
         self.get(path).await
     }
 
+    pub async fn delete_service_resource(
+        &self,
+        project: &ProjectName,
+        resource_type: &str,
+    ) -> Result<resource::Response> {
+        let resource_type: String =
+            form_urlencoded::byte_serialize(resource_type.as_bytes()).collect();
+        let path = format!(
+            "/projects/{}/services/{}/resources/{}",
+            project.as_str(),
+            &self.service_name(project),
+            resource_type
+        );
+
+        self.delete(path).await
+    }
+
     pub async fn create_project(
         &self,
         project: &ProjectName,
@@ -129,12 +389,26 @@ impl Client {
         self.get(path).await
     }
 
+    pub async fn get_api_status(&self) -> Result<ApiStatus> {
+        self.get("/".to_string()).await
+    }
+
     pub async fn get_projects_list(&self, page: u32, limit: u32) -> Result<Vec<project::Response>> {
         let path = format!("/projects?page={}&limit={}", page.saturating_sub(1), limit);
 
         self.get(path).await
     }
 
+    pub async fn restart_project(&self, project: &ProjectName) -> Result<project::Response> {
+        let path = format!("/projects/{}/restart", project.as_str());
+
+        self.post(path, Option::<String>::None)
+            .await
+            .context("failed to make restart project request")?
+            .to_json()
+            .await
+    }
+
     pub async fn delete_project(&self, project: &ProjectName) -> Result<project::Response> {
         let path = format!("/projects/{}", project.as_str());
 
@@ -145,21 +419,246 @@ impl Client {
         let path = format!(
             "/projects/{}/secrets/{}",
             project.as_str(),
-            project.as_str()
+            &self.service_name(project)
         );
 
         self.get(path).await
     }
 
+    pub async fn set_secrets(
+        &self,
+        project: &ProjectName,
+        secrets: &BTreeMap<String, String>,
+    ) -> Result<Vec<secret::Response>> {
+        let path = format!(
+            "/projects/{}/secrets/{}",
+            project.as_str(),
+            &self.service_name(project)
+        );
+
+        self.put(path, Some(secrets))
+            .await
+            .context("failed to set secrets")?
+            .to_json()
+            .await
+    }
+
+    pub async fn delete_secret(
+        &self,
+        project: &ProjectName,
+        key: &str,
+    ) -> Result<secret::Response> {
+        let key: String = form_urlencoded::byte_serialize(key.as_bytes()).collect();
+        let path = format!(
+            "/projects/{}/secrets/{}/{}",
+            project.as_str(),
+            &self.service_name(project),
+            key
+        );
+
+        self.delete(path).await
+    }
+
+    pub async fn get_env_vars(&self, project: &ProjectName) -> Result<Vec<env::Response>> {
+        let path = format!(
+            "/projects/{}/env/{}",
+            project.as_str(),
+            &self.service_name(project)
+        );
+
+        self.get(path).await
+    }
+
+    pub async fn set_env_vars(
+        &self,
+        project: &ProjectName,
+        vars: &BTreeMap<String, String>,
+    ) -> Result<Vec<env::Response>> {
+        let path = format!(
+            "/projects/{}/env/{}",
+            project.as_str(),
+            &self.service_name(project)
+        );
+
+        self.put(path, Some(vars))
+            .await
+            .context("failed to set environment variables")?
+            .to_json()
+            .await
+    }
+
+    pub async fn delete_env_var(&self, project: &ProjectName, key: &str) -> Result<env::Response> {
+        let key: String = form_urlencoded::byte_serialize(key.as_bytes()).collect();
+        let path = format!(
+            "/projects/{}/env/{}/{}",
+            project.as_str(),
+            &self.service_name(project),
+            key
+        );
+
+        self.delete(path).await
+    }
+
+    pub async fn get_webhooks(&self, project: &ProjectName) -> Result<Vec<webhook::Response>> {
+        let path = format!("/projects/{}/webhooks", project.as_str());
+
+        self.get(path).await
+    }
+
+    pub async fn create_webhook(
+        &self,
+        project: &ProjectName,
+        request: &webhook::Request,
+    ) -> Result<webhook::Created> {
+        let path = format!("/projects/{}/webhooks", project.as_str());
+
+        self.post(path, Some(request))
+            .await
+            .context("failed to register webhook")?
+            .to_json()
+            .await
+    }
+
+    pub async fn delete_webhook(
+        &self,
+        project: &ProjectName,
+        webhook_id: &Uuid,
+    ) -> Result<webhook::Response> {
+        let path = format!("/projects/{}/webhooks/{}", project.as_str(), webhook_id);
+
+        self.delete(path).await
+    }
+
+    pub async fn get_domains(&self, project: &ProjectName) -> Result<Vec<domain::Response>> {
+        let path = format!("/projects/{}/domains", project.as_str());
+
+        self.get(path).await
+    }
+
+    pub async fn add_domain(&self, project: &ProjectName, fqdn: &str) -> Result<domain::Response> {
+        let path = format!("/projects/{}/domains/{}", project.as_str(), fqdn);
+
+        self.post(path, Option::<String>::None)
+            .await
+            .context("failed to add custom domain")?
+            .to_json()
+            .await
+    }
+
+    pub async fn get_access_rules(&self, project: &ProjectName) -> Result<access::Response> {
+        let path = format!(
+            "/projects/{}/access/{}",
+            project.as_str(),
+            &self.service_name(project)
+        );
+
+        self.get(path).await
+    }
+
+    pub async fn set_access_rules(
+        &self,
+        project: &ProjectName,
+        rules: &access::Request,
+    ) -> Result<access::Response> {
+        let path = format!(
+            "/projects/{}/access/{}",
+            project.as_str(),
+            &self.service_name(project)
+        );
+
+        self.put(path, Some(rules))
+            .await
+            .context("failed to set the access rules")?
+            .to_json()
+            .await
+    }
+
+    pub async fn clear_access_rules(&self, project: &ProjectName) -> Result<access::Response> {
+        let path = format!(
+            "/projects/{}/access/{}",
+            project.as_str(),
+            &self.service_name(project)
+        );
+
+        self.delete(path).await
+    }
+
+    pub async fn purge_cache(
+        &self,
+        project: &ProjectName,
+        path: Option<&str>,
+    ) -> Result<cache::Response> {
+        let mut path_query = format!(
+            "/projects/{}/cache/{}",
+            project.as_str(),
+            &self.service_name(project)
+        );
+        if let Some(path) = path {
+            let path: String = form_urlencoded::byte_serialize(path.as_bytes()).collect();
+            path_query.push_str(&format!("?path={path}"));
+        }
+
+        self.delete(path_query).await
+    }
+
+    pub async fn set_maintenance(
+        &self,
+        project: &ProjectName,
+        maintenance: &project::Maintenance,
+    ) -> Result<project::Maintenance> {
+        let path = format!("/projects/{}/maintenance", project.as_str());
+
+        self.put(path, Some(maintenance))
+            .await
+            .context("failed to put the project under maintenance")?
+            .to_json()
+            .await
+    }
+
+    pub async fn end_maintenance(
+        &self,
+        project: &ProjectName,
+    ) -> Result<Option<project::Maintenance>> {
+        let path = format!("/projects/{}/maintenance", project.as_str());
+
+        self.delete(path).await
+    }
+
+    pub async fn expose_tcp_port(
+        &self,
+        project: &ProjectName,
+        target_port: u16,
+    ) -> Result<project::TcpEndpoint> {
+        let path = format!("/projects/{}/tcp", project.as_str());
+
+        self.post(path, Some(project::TcpRequest { target_port }))
+            .await
+            .context("failed to forward raw TCP to the project")?
+            .to_json()
+            .await
+    }
+
+    pub async fn close_tcp_port(
+        &self,
+        project: &ProjectName,
+        port: u16,
+    ) -> Result<project::TcpEndpoint> {
+        let path = format!("/projects/{}/tcp/{port}", project.as_str());
+
+        self.delete(path).await
+    }
+
     pub async fn get_logs(
         &self,
         project: &ProjectName,
         deployment_id: &Uuid,
+        query: &LogsQuery,
     ) -> Result<Vec<LogItem>> {
         let path = format!(
-            "/projects/{}/deployments/{}/logs",
+            "/projects/{}/deployments/{}/logs{}",
             project.as_str(),
-            deployment_id
+            deployment_id,
+            query.to_query_string()
         );
 
         self.get(path).await
@@ -169,29 +668,146 @@ impl Client {
         &self,
         project: &ProjectName,
         deployment_id: &Uuid,
+        query: &LogsQuery,
     ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
         let path = format!(
-            "/projects/{}/ws/deployments/{}/logs",
+            "/projects/{}/ws/deployments/{}/logs{}",
             project.as_str(),
-            deployment_id
+            deployment_id,
+            query.to_query_string()
         );
 
         self.ws_get(path).await
     }
 
+    /// Open a tunnel to the private port of the running service, or to its `resource_type`
+    /// database, which carries the bytes of one connection in binary messages
+    pub async fn get_tunnel_ws(
+        &self,
+        project: &ProjectName,
+        resource_type: Option<&str>,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+        let mut path = format!(
+            "/projects/{}/ws/services/{}/tunnel",
+            project.as_str(),
+            self.service_name(project)
+        );
+        if let Some(resource_type) = resource_type {
+            let resource_type: String =
+                form_urlencoded::byte_serialize(resource_type.as_bytes()).collect();
+            let _ = write!(path, "?resource={resource_type}");
+        }
+
+        self.ws_get(path).await
+    }
+
+    /// Get the metrics of the running deployment of this service, sampled after `since`
+    pub async fn get_metrics(
+        &self,
+        project: &ProjectName,
+        since: DateTime<Utc>,
+    ) -> Result<metrics::Response> {
+        let path = format!(
+            "/projects/{}/services/{}/metrics?since={}",
+            project.as_str(),
+            self.service_name(project),
+            since.to_rfc3339_opts(SecondsFormat::Micros, true)
+        );
+
+        self.get(path).await
+    }
+
+    pub async fn get_storage_usage(&self, project: &ProjectName) -> Result<storage::Usage> {
+        let path = format!(
+            "/projects/{}/services/{}/storage",
+            project.as_str(),
+            self.service_name(project)
+        );
+
+        self.get(path).await
+    }
+
+    pub async fn get_persist_keys(&self, project: &ProjectName) -> Result<persist::Response> {
+        let path = format!(
+            "/projects/{}/services/{}/persist",
+            project.as_str(),
+            self.service_name(project)
+        );
+
+        self.get(path).await
+    }
+
+    pub async fn get_cron_jobs(&self, project: &ProjectName) -> Result<Vec<cron::Response>> {
+        let path = format!(
+            "/projects/{}/services/{}/cron",
+            project.as_str(),
+            self.service_name(project)
+        );
+
+        self.get(path).await
+    }
+
+    pub async fn get_cron_runs(
+        &self,
+        project: &ProjectName,
+        name: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<cron::Run>> {
+        let mut path = format!(
+            "/projects/{}/services/{}/cron/runs?limit={limit}",
+            project.as_str(),
+            self.service_name(project)
+        );
+
+        if let Some(name) = name {
+            path.push_str(&format!(
+                "&name={}",
+                form_urlencoded::byte_serialize(name.as_bytes()).collect::<String>()
+            ));
+        }
+
+        self.get(path).await
+    }
+
     pub async fn get_deployments(
         &self,
         project: &ProjectName,
         page: u32,
         limit: u32,
+        filter: &DeploymentsFilter,
     ) -> Result<Vec<deployment::Response>> {
-        let path = format!(
+        let mut path = format!(
             "/projects/{}/deployments?page={}&limit={}",
             project.as_str(),
             page.saturating_sub(1),
             limit,
         );
 
+        if self.environment.is_some() {
+            let _ = write!(path, "&service={}", self.service_name(project));
+        }
+
+        if let Some(state) = &filter.state {
+            let _ = write!(path, "&state={state}");
+        }
+
+        // The `Z` form of the offset needs no escaping in a URL, unlike `+00:00`
+        if let Some(since) = filter.since {
+            let _ = write!(
+                path,
+                "&since={}",
+                since.to_rfc3339_opts(SecondsFormat::Micros, true)
+            );
+        }
+
+        if let Some(until) = filter.until {
+            let _ = write!(
+                path,
+                "&until={}",
+                until.to_rfc3339_opts(SecondsFormat::Micros, true)
+            );
+        }
+
         self.get(path).await
     }
 
@@ -209,6 +825,91 @@ impl Client {
         self.get(path).await
     }
 
+    pub async fn approve_deployment(
+        &self,
+        project: &ProjectName,
+        deployment_id: &Uuid,
+    ) -> Result<deployment::Response> {
+        let path = format!(
+            "/projects/{}/deployments/{}/approve",
+            project.as_str(),
+            deployment_id
+        );
+
+        self.post(path, Option::<String>::None)
+            .await
+            .context("failed to approve the deployment")?
+            .to_json()
+            .await
+    }
+
+    pub async fn get_approval(&self, project: &ProjectName) -> Result<deployment::Approval> {
+        let path = format!("/projects/{}/approval", project.as_str());
+
+        self.get(path).await
+    }
+
+    pub async fn set_approval(
+        &self,
+        project: &ProjectName,
+        approval: deployment::Approval,
+    ) -> Result<deployment::Approval> {
+        let path = format!("/projects/{}/approval", project.as_str());
+
+        self.put(path, Some(approval))
+            .await
+            .context("failed to choose whether deployments need approval")?
+            .to_json()
+            .await
+    }
+
+    pub async fn cancel_deployment(
+        &self,
+        project: &ProjectName,
+        deployment_id: &Uuid,
+    ) -> Result<deployment::Response> {
+        let path = format!(
+            "/projects/{}/deployments/{}/cancel",
+            project.as_str(),
+            deployment_id
+        );
+
+        self.post(path, Option::<String>::None)
+            .await
+            .context("failed to cancel the deployment")?
+            .to_json()
+            .await
+    }
+
+    pub async fn start_device_login(&self) -> Result<user::DeviceAuthorizationResponse> {
+        self.post("/login/device".to_string(), Option::<()>::None)
+            .await
+            .context("failed to start the login")?
+            .to_json()
+            .await
+    }
+
+    /// Get the API key of a login started with [Self::start_device_login], or `None` while the
+    /// user has not approved it yet
+    pub async fn poll_device_login(
+        &self,
+        device_code: &str,
+    ) -> Result<Option<user::DeviceTokenResponse>> {
+        let request = user::DeviceTokenRequest {
+            device_code: device_code.to_string(),
+        };
+        let response = self
+            .post("/login/device/token".to_string(), Some(request))
+            .await
+            .context("failed to check on the login")?;
+
+        if response.status() == StatusCode::ACCEPTED {
+            Ok(None)
+        } else {
+            response.to_json().await.map(Some)
+        }
+    }
+
     pub async fn reset_api_key(&self) -> Result<Response> {
         self.put("/users/reset-api-key".into(), Option::<()>::None)
             .await
@@ -242,8 +943,7 @@ impl Client {
 
         builder = self.set_builder_auth(builder);
 
-        builder
-            .send()
+        Self::send(builder)
             .await
             .context("failed to make get request")?
             .to_json()
@@ -263,7 +963,7 @@ impl Client {
             builder = builder.header("Content-Type", "application/json");
         }
 
-        Ok(builder.send().await?)
+        Ok(Self::send(builder).await?)
     }
 
     async fn put<T: Serialize>(&self, path: String, body: Option<T>) -> Result<Response> {
@@ -279,7 +979,7 @@ impl Client {
             builder = builder.header("Content-Type", "application/json");
         }
 
-        Ok(builder.send().await?)
+        Ok(Self::send(builder).await?)
     }
 
     async fn delete<M>(&self, path: String) -> Result<M>
@@ -292,14 +992,42 @@ impl Client {
 
         builder = self.set_builder_auth(builder);
 
-        builder
-            .send()
+        Self::send(builder)
             .await
             .context("failed to make delete request")?
             .to_json()
             .await
     }
 
+    /// Send the request, warning once when the response comes from an API at a version this CLI
+    /// is not compatible with
+    async fn send(builder: RequestBuilder) -> reqwest_middleware::Result<Response> {
+        static VERSION_CHECK: Once = Once::new();
+
+        let response = builder.send().await?;
+
+        if let Some(api_version) = response
+            .headers()
+            .get(VERSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            VERSION_CHECK.call_once(|| {
+                let cli_version = env!("CARGO_PKG_VERSION");
+                if !compatible(cli_version, api_version) {
+                    eprintln!(
+                        "{}",
+                        format!(
+                            "Warning: cargo-shuttle {cli_version} may not work with the API, which is at version {api_version}. Run `cargo shuttle upgrade` to update it."
+                        )
+                        .yellow()
+                    );
+                }
+            });
+        }
+
+        Ok(response)
+    }
+
     fn set_builder_auth(&self, builder: RequestBuilder) -> RequestBuilder {
         if let Some(ref api_key) = self.api_key {
             builder.bearer_auth(api_key.as_ref())
@@ -316,3 +1044,32 @@ impl Client {
             .build()
     }
 }
+
+/// Start a new trace, sampled so that every service which handles the request records its spans
+fn traceparent() -> String {
+    let trace_id = Uuid::new_v4().simple().to_string();
+    let span_id = Uuid::new_v4().simple().to_string();
+
+    format!("00-{trace_id}-{}-01", &span_id[..16])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::traceparent;
+
+    #[test]
+    fn traceparents() {
+        let traceparent = traceparent();
+        let parts: Vec<_> = traceparent.split('-').collect();
+
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[0], "00");
+        assert_eq!(parts[1].len(), 32);
+        assert_eq!(parts[2].len(), 16);
+        assert_eq!(parts[3], "01");
+        assert!(parts[1..3]
+            .iter()
+            .all(|part| part.chars().all(|c| c.is_ascii_hexdigit())));
+        assert_ne!(traceparent, super::traceparent());
+    }
+}
@@ -0,0 +1,95 @@
+use clap_complete::Shell;
+
+/// Completions of arguments whose values only the API knows, like project names and deployment
+/// IDs, to append to the completions clap generates for `shell`
+///
+/// They get the values from the hidden `__complete` command, passing on the arguments which
+/// change where it looks, and fall back to the generated completions for everything else.
+pub fn dynamic_completions(shell: Shell) -> Option<&'static str> {
+    match shell {
+        Shell::Bash => Some(BASH),
+        Shell::Zsh => Some(ZSH),
+        Shell::Fish => Some(FISH),
+        _ => None,
+    }
+}
+
+const BASH: &str = r#"
+_cargo-shuttle_dynamic() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    local prev="${COMP_WORDS[COMP_CWORD-1]}"
+    local kind=""
+
+    case "${prev}" in
+        --name) kind="projects" ;;
+        --to|logs) kind="deployments" ;;
+        status|cancel) [[ "${COMP_WORDS[COMP_CWORD-2]}" == "deployment" ]] && kind="deployments" ;;
+    esac
+
+    if [[ -n "${kind}" && "${cur}" != -* ]]; then
+        local args=() i
+        for (( i = 1; i < COMP_CWORD; i++ )); do
+            case "${COMP_WORDS[i]}" in
+                --name|--env|--working-directory|--api-url) args+=("${COMP_WORDS[i]}" "${COMP_WORDS[i+1]}") ;;
+            esac
+        done
+
+        COMPREPLY=( $(compgen -W "$(cargo-shuttle "${args[@]}" __complete "${kind}" 2>/dev/null)" -- "${cur}") )
+        return 0
+    fi
+
+    _cargo-shuttle "$@"
+}
+
+complete -F _cargo-shuttle_dynamic -o bashdefault -o default cargo-shuttle
+"#;
+
+const ZSH: &str = r#"
+_cargo-shuttle_dynamic() {
+    local kind=""
+
+    case "${words[CURRENT-1]}" in
+        --name) kind="projects" ;;
+        --to|logs) kind="deployments" ;;
+        status|cancel) [[ "${words[CURRENT-2]}" == "deployment" ]] && kind="deployments" ;;
+    esac
+
+    if [[ -n "${kind}" && "${words[CURRENT]}" != -* ]]; then
+        local -a args candidates
+        local i
+        for (( i = 2; i < CURRENT; i++ )); do
+            case "${words[i]}" in
+                --name|--env|--working-directory|--api-url) args+=("${words[i]}" "${words[i+1]}") ;;
+            esac
+        done
+
+        candidates=(${(f)"$(cargo-shuttle "${args[@]}" __complete "${kind}" 2>/dev/null)"})
+        compadd -a candidates
+        return
+    fi
+
+    _cargo-shuttle "$@"
+}
+
+compdef _cargo-shuttle_dynamic cargo-shuttle
+"#;
+
+const FISH: &str = r#"
+function __fish_cargo_shuttle_candidates
+    set -l tokens (commandline -opc)
+    set -l args
+    for i in (seq 2 (count $tokens))
+        switch $tokens[$i]
+            case --name --env --working-directory --api-url
+                set -a args $tokens[$i] $tokens[(math $i + 1)]
+        end
+    end
+
+    cargo-shuttle $args __complete $argv 2>/dev/null
+end
+
+complete -c cargo-shuttle -l name -x -a "(__fish_cargo_shuttle_candidates projects)"
+complete -c cargo-shuttle -n "__fish_seen_subcommand_from logs" -x -a "(__fish_cargo_shuttle_candidates deployments)"
+complete -c cargo-shuttle -n "__fish_seen_subcommand_from deployment; and __fish_seen_subcommand_from status cancel" -x -a "(__fish_cargo_shuttle_candidates deployments)"
+complete -c cargo-shuttle -n "__fish_seen_subcommand_from rollback" -l to -x -a "(__fish_cargo_shuttle_candidates deployments)"
+"#;
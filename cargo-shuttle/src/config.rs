@@ -1,14 +1,18 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
-use shuttle_common::project::ProjectName;
+use shuttle_common::project::{EnvironmentName, ProjectName};
+use shuttle_common::shuttle_toml::ShuttleToml;
 use shuttle_common::{ApiKey, ApiUrl, API_URL_DEFAULT};
-use tracing::trace;
+use tracing::{debug, trace};
 
 use crate::args::ProjectArgs;
+use crate::failure::Failure;
 
 /// Helper trait for dispatching fs ops for different config files
 pub trait ConfigManager: Sized {
@@ -124,23 +128,68 @@ impl ConfigManager for LocalConfigManager {
 pub struct GlobalConfig {
     api_key: Option<String>,
     pub api_url: Option<ApiUrl>,
+    /// Other accounts or APIs, like a self-hosted one, to switch to with `--profile`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    profiles: BTreeMap<String, Profile>,
+}
+
+/// An account on an API, which is used instead of the default one when its name is given with
+/// `--profile`
+#[derive(Deserialize, Serialize, Default)]
+pub struct Profile {
+    api_key: Option<String>,
+    api_url: Option<ApiUrl>,
 }
 
 impl GlobalConfig {
-    pub fn api_key(&self) -> Option<Result<ApiKey>> {
-        self.api_key.as_ref().map(|key| ApiKey::parse(key))
+    /// The API key of `profile`, or of the default profile when it is `None`
+    pub fn api_key(&self, profile: Option<&str>) -> Option<Result<ApiKey>> {
+        let api_key = match profile {
+            Some(profile) => self.profiles.get(profile)?.api_key.as_ref(),
+            None => self.api_key.as_ref(),
+        };
+
+        api_key.map(|key| ApiKey::parse(key))
     }
 
-    pub fn set_api_key(&mut self, api_key: ApiKey) -> Option<String> {
-        self.api_key.replace(api_key.as_ref().to_string())
+    pub fn set_api_key(&mut self, profile: Option<&str>, api_key: ApiKey) -> Option<String> {
+        let key = api_key.as_ref().to_string();
+
+        match profile {
+            Some(profile) => self
+                .profiles
+                .entry(profile.to_string())
+                .or_default()
+                .api_key
+                .replace(key),
+            None => self.api_key.replace(key),
+        }
     }
 
-    pub fn clear_api_key(&mut self) {
-        self.api_key = None;
+    pub fn clear_api_key(&mut self, profile: Option<&str>) {
+        match profile {
+            Some(profile) => {
+                if let Some(profile) = self.profiles.get_mut(profile) {
+                    profile.api_key = None;
+                }
+            }
+            None => self.api_key = None,
+        }
     }
 
-    pub fn api_url(&self) -> Option<ApiUrl> {
-        self.api_url.clone()
+    /// The API URL of `profile`, or of the default profile when it is `None`
+    pub fn api_url(&self, profile: Option<&str>) -> Option<ApiUrl> {
+        match profile {
+            Some(profile) => self.profiles.get(profile)?.api_url.clone(),
+            None => self.api_url.clone(),
+        }
+    }
+
+    pub fn set_api_url(&mut self, profile: &str, api_url: ApiUrl) {
+        self.profiles
+            .entry(profile.to_string())
+            .or_default()
+            .api_url = Some(api_url);
     }
 }
 
@@ -226,6 +275,39 @@ where
     }
 }
 
+/// The service and user the API key is saved under in the keychain of the OS
+const KEYRING_SERVICE: &str = "shuttle";
+const KEYRING_USER: &str = "api-key";
+
+/// The entry of the API key of `profile` in the keychain of the OS, if this OS has a keychain which
+/// can be used
+fn keyring_entry(profile: Option<&str>) -> Option<keyring::Entry> {
+    let user = match profile {
+        Some(profile) => format!("{KEYRING_USER}:{profile}"),
+        None => KEYRING_USER.to_string(),
+    };
+
+    keyring::Entry::new(KEYRING_SERVICE, &user)
+        .map_err(|error| debug!(%error, "the keychain cannot be used"))
+        .ok()
+}
+
+/// Where the API key was saved
+pub enum CredentialStore {
+    Keychain,
+    /// The global configuration file at this path, when there is no keychain
+    File(PathBuf),
+}
+
+impl Display for CredentialStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CredentialStore::Keychain => write!(f, "the keychain of your OS"),
+            CredentialStore::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
 /// A wrapper around our two sources of configuration and overrides:
 /// - Global config
 /// - Local config
@@ -233,6 +315,8 @@ pub struct RequestContext {
     global: Config<GlobalConfigManager, GlobalConfig>,
     project: Option<Config<LocalConfigManager, ProjectConfig>>,
     api_url: Option<String>,
+    environment: Option<EnvironmentName>,
+    profile: Option<String>,
 }
 
 impl RequestContext {
@@ -249,6 +333,8 @@ impl RequestContext {
             global,
             project: None,
             api_url: None,
+            environment: None,
+            profile: None,
         })
     }
 
@@ -273,6 +359,7 @@ impl RequestContext {
             .workspace_path()
             .unwrap_or(project_args.working_directory.clone());
 
+        let shuttle_toml_path = workspace_path.join("Shuttle.toml");
         let local_manager = LocalConfigManager::new(workspace_path, "Shuttle.toml".to_string());
         let mut project = Config::new(local_manager);
 
@@ -280,6 +367,8 @@ impl RequestContext {
             project.replace(ProjectConfig::default());
         } else {
             trace!("found a local Shuttle.toml");
+            // Point out mistakes in Shuttle.toml here instead of after the upload
+            ShuttleToml::from_path(&shuttle_toml_path)?;
             project.open()?;
         }
 
@@ -313,10 +402,28 @@ impl RequestContext {
         self.api_url = api_url;
     }
 
+    pub fn set_environment(&mut self, environment: Option<EnvironmentName>) {
+        self.environment = environment;
+    }
+
+    /// Get the environment of the project to use, unless it is the main service of the project
+    pub fn environment(&self) -> Option<&EnvironmentName> {
+        self.environment.as_ref()
+    }
+
+    /// Use the API key and API URL of `profile` instead of the default ones
+    pub fn set_profile(&mut self, profile: Option<String>) {
+        self.profile = profile;
+    }
+
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
     pub fn api_url(&self) -> ApiUrl {
         if let Some(api_url) = self.api_url.clone() {
             api_url
-        } else if let Some(api_url) = self.global.as_ref().unwrap().api_url() {
+        } else if let Some(api_url) = self.global.as_ref().unwrap().api_url(self.profile()) {
             api_url
         } else {
             API_URL_DEFAULT.to_string()
@@ -324,23 +431,34 @@ impl RequestContext {
     }
 
     /// Get the API key from the `SHUTTLE_API_KEY` env variable, or
-    /// otherwise from the global configuration. Returns an error if
+    /// otherwise from the keychain or the global configuration. Returns an error if
     /// an API key is not set.
     pub fn api_key(&self) -> Result<ApiKey> {
         let api_key = std::env::var("SHUTTLE_API_KEY");
 
         if let Ok(key) = api_key {
             ApiKey::parse(&key).context("environment variable SHUTTLE_API_KEY is invalid")
+        } else if let Some(key) =
+            keyring_entry(self.profile()).and_then(|entry| entry.get_password().ok())
+        {
+            ApiKey::parse(&key).context("the API key in the keychain is invalid")
         } else {
-            match self.global.as_ref().unwrap().api_key() {
+            match self.global.as_ref().unwrap().api_key(self.profile()) {
                 Some(key) => key,
-                None => Err(anyhow!(
-                    "Configuration file: `{}`",
-                    self.global.manager.path().display()
-                )
-                .context(anyhow!(
-                    "No valid API key found, try logging in first with:\n\tcargo shuttle login"
-                ))),
+                None => {
+                    let login = match self.profile() {
+                        Some(profile) => format!("cargo shuttle login --profile {profile}"),
+                        None => "cargo shuttle login".to_string(),
+                    };
+
+                    Err(anyhow!(
+                        "Configuration file: `{}`",
+                        self.global.manager.path().display()
+                    )
+                    .context(Failure::Auth(format!(
+                        "No valid API key found, try logging in first with:\n\t{login}"
+                    ))))
+                }
             }
         }
     }
@@ -358,15 +476,48 @@ impl RequestContext {
             .as_path()
     }
 
-    /// Set the API key to the global configuration. Will persist the file.
-    pub fn set_api_key(&mut self, api_key: ApiKey) -> Result<()> {
-        self.global.as_mut().unwrap().set_api_key(api_key);
+    /// Save the API key of the profile in the keychain, or in the global configuration when there
+    /// is no keychain. The API URL given for this command is saved in the profile too, unless it
+    /// is the default one. Will persist the file.
+    pub fn set_api_key(&mut self, api_key: ApiKey) -> Result<CredentialStore> {
+        let profile = self.profile.as_deref();
+        let global = self.global.as_mut().unwrap();
+
+        if let (Some(profile), Some(api_url)) = (profile, &self.api_url) {
+            global.set_api_url(profile, api_url.clone());
+        }
+
+        let store = match keyring_entry(profile).map(|entry| entry.set_password(api_key.as_ref())) {
+            Some(Ok(())) => {
+                // Do not leave an older key behind in plain text
+                global.clear_api_key(profile);
+                CredentialStore::Keychain
+            }
+            Some(Err(error)) => {
+                debug!(%error, "failed to save the API key in the keychain");
+                global.set_api_key(profile, api_key);
+                CredentialStore::File(self.global.manager.path())
+            }
+            None => {
+                global.set_api_key(profile, api_key);
+                CredentialStore::File(self.global.manager.path())
+            }
+        };
+
         self.global.save()?;
-        Ok(())
+        Ok(store)
     }
 
     pub fn clear_api_key(&mut self) -> Result<()> {
-        self.global.as_mut().unwrap().clear_api_key();
+        let profile = self.profile.as_deref();
+
+        if let Some(Err(error)) = keyring_entry(profile).map(|entry| entry.delete_password()) {
+            if !matches!(error, keyring::Error::NoEntry) {
+                debug!(%error, "failed to remove the API key from the keychain");
+            }
+        }
+
+        self.global.as_mut().unwrap().clear_api_key(profile);
         self.global.save()?;
         Ok(())
     }
@@ -390,11 +541,11 @@ impl RequestContext {
 mod tests {
     use std::{path::PathBuf, str::FromStr};
 
-    use shuttle_common::project::ProjectName;
+    use shuttle_common::{project::ProjectName, ApiKey};
 
     use crate::{args::ProjectArgs, config::RequestContext};
 
-    use super::{Config, LocalConfigManager, ProjectConfig};
+    use super::{Config, GlobalConfig, LocalConfigManager, ProjectConfig};
 
     fn path_from_workspace_root(path: &str) -> PathBuf {
         PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap())
@@ -430,6 +581,41 @@ mod tests {
         assert_eq!(unwrap_project_name(&local_config), "workspace");
     }
 
+    #[test]
+    fn profiles() {
+        let mut config: GlobalConfig = toml::from_str(
+            r#"
+api_key = "defaultkey123456"
+
+[profiles.local]
+api_url = "http://localhost:8001"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.api_url(None), None);
+        assert_eq!(
+            config.api_url(Some("local")).as_deref(),
+            Some("http://localhost:8001")
+        );
+        assert!(config.api_key(Some("local")).is_none());
+        assert!(config.api_key(Some("unknown")).is_none());
+
+        config.set_api_key(Some("work"), ApiKey::parse("workkey123456789").unwrap());
+        config.clear_api_key(Some("unknown"));
+
+        let config: GlobalConfig = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(
+            config.api_key(None).unwrap().unwrap().as_ref(),
+            "defaultkey123456"
+        );
+        assert_eq!(
+            config.api_key(Some("work")).unwrap().unwrap().as_ref(),
+            "workkey123456789"
+        );
+        assert!(!config.profiles.contains_key("unknown"));
+    }
+
     #[test]
     fn setting_name_overrides_name_in_config() {
         let project_args = ProjectArgs {
@@ -0,0 +1,234 @@
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+
+use bollard::Docker;
+use crossterm::style::Stylize;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use shuttle_service::builder::{has_active_target, rustc_version, NEXT_TARGET};
+
+use crate::client::Client;
+
+/// The status the API reports at its root
+#[derive(Deserialize)]
+pub struct ApiStatus {
+    pub status: String,
+    /// Older APIs do not report their version
+    pub version: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Ok,
+    /// Something which only breaks some projects or commands
+    Warning,
+    Failed,
+}
+
+/// The result of checking one thing shuttle needs to work
+#[derive(Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub outcome: Outcome,
+    pub details: String,
+    /// What to do to fix the problem
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, details: impl Into<String>) -> Self {
+        Self {
+            name,
+            outcome: Outcome::Ok,
+            details: details.into(),
+            fix: None,
+        }
+    }
+
+    fn warning(name: &'static str, details: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            outcome: Outcome::Warning,
+            details: details.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn failed(name: &'static str, details: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            outcome: Outcome::Failed,
+            details: details.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Everything `cargo shuttle doctor` found
+#[derive(Serialize)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// Check the toolchain in `working_directory`, Docker, and the API `client` talks to, with
+    /// the API key it has if the user is logged in
+    pub async fn diagnose(working_directory: &Path, client: &Client, logged_in: bool) -> Self {
+        let mut checks = vec![
+            check_rustc(working_directory).await,
+            check_wasm_target(working_directory).await,
+            check_docker().await,
+        ];
+
+        match client.get_api_status().await {
+            Ok(status) => {
+                checks.push(Check::ok(
+                    "API",
+                    format!("reachable at {}, {}", client.api_url(), status.status),
+                ));
+                checks.push(check_version(status.version.as_deref()));
+                checks.push(check_login(client, logged_in).await);
+            }
+            Err(error) => checks.push(Check::failed(
+                "API",
+                format!("could not reach {}: {error}", client.api_url()),
+                "Check your network connection, and the `SHUTTLE_API` environment variable or `--api-url` if you set them",
+            )),
+        }
+
+        Self { checks }
+    }
+
+    pub fn has_failures(&self) -> bool {
+        self.checks
+            .iter()
+            .any(|check| check.outcome == Outcome::Failed)
+    }
+}
+
+async fn check_rustc(working_directory: &Path) -> Check {
+    match rustc_version(working_directory).await {
+        Some(version) => Check::ok("Rust toolchain", version),
+        None => Check::failed(
+            "Rust toolchain",
+            "rustc could not be run",
+            "Install Rust with rustup from https://rustup.rs",
+        ),
+    }
+}
+
+async fn check_wasm_target(working_directory: &Path) -> Check {
+    match has_active_target(working_directory, NEXT_TARGET).await {
+        Some(true) => Check::ok("wasm target", format!("{NEXT_TARGET} is installed")),
+        Some(false) => Check::warning(
+            "wasm target",
+            format!("{NEXT_TARGET} is not installed, which shuttle-next projects need"),
+            format!("Run `rustup target add {NEXT_TARGET}`"),
+        ),
+        None => Check::warning(
+            "wasm target",
+            "the installed targets are not known, since Rust was not installed with rustup",
+            format!("Make sure {NEXT_TARGET} is installed if you use shuttle-next"),
+        ),
+    }
+}
+
+async fn check_docker() -> Check {
+    let fix = "Install and start Docker, which `cargo shuttle run` needs to provision databases";
+
+    let docker = match Docker::connect_with_local_defaults() {
+        Ok(docker) => docker,
+        Err(error) => return Check::warning("Docker", error.to_string(), fix),
+    };
+
+    match docker.version().await {
+        Ok(version) => Check::ok(
+            "Docker",
+            format!("running version {}", version.version.unwrap_or_default()),
+        ),
+        Err(error) => Check::warning("Docker", format!("not reachable: {error}"), fix),
+    }
+}
+
+fn check_version(server_version: Option<&str>) -> Check {
+    let cli_version = env!("CARGO_PKG_VERSION");
+
+    let Some(server_version) = server_version else {
+        return Check::ok("Version", format!("cargo-shuttle {cli_version}"));
+    };
+
+    if compatible(cli_version, server_version) {
+        Check::ok(
+            "Version",
+            format!("cargo-shuttle {cli_version} works with the API {server_version}"),
+        )
+    } else {
+        Check::failed(
+            "Version",
+            format!("cargo-shuttle {cli_version} does not match the API {server_version}"),
+            format!(
+                "Run `cargo install cargo-shuttle@{server_version}`, and use the same version of shuttle-runtime in your project"
+            ),
+        )
+    }
+}
+
+async fn check_login(client: &Client, logged_in: bool) -> Check {
+    if !logged_in {
+        return Check::failed("Login", "not logged in", "Run `cargo shuttle login`");
+    }
+
+    match client.get_projects_list(1, 1).await {
+        Ok(_) => Check::ok("Login", "the API key is valid"),
+        Err(error) => Check::failed(
+            "Login",
+            format!("the API key was not accepted: {error}"),
+            "Run `cargo shuttle login` to set a new API key",
+        ),
+    }
+}
+
+/// Whether the CLI at `cli_version` can talk to the API at `server_version`, which is when they
+/// have the same major version, or the same minor version before 1.0
+pub fn compatible(cli_version: &str, server_version: &str) -> bool {
+    match (Version::parse(cli_version), Version::parse(server_version)) {
+        (Ok(cli), Ok(server)) if cli.major == 0 => server.major == 0 && cli.minor == server.minor,
+        (Ok(cli), Ok(server)) => cli.major == server.major,
+        _ => false,
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let mark = match check.outcome {
+                Outcome::Ok => "✓".green(),
+                Outcome::Warning => "!".yellow(),
+                Outcome::Failed => "✗".red(),
+            };
+            writeln!(f, "{mark} {}: {}", check.name.bold(), check.details)?;
+
+            if let Some(fix) = &check.fix {
+                writeln!(f, "    {fix}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::compatible;
+
+    #[test]
+    fn version() {
+        assert!(compatible("0.17.0", "0.17.2"));
+        assert!(!compatible("0.16.0", "0.17.0"));
+        assert!(!compatible("0.17.0", "1.17.0"));
+        assert!(compatible("1.2.0", "1.5.3"));
+        assert!(!compatible("1.2.0", "2.0.0"));
+        assert!(!compatible("0.17.0", "not a version"));
+    }
+}
@@ -0,0 +1,82 @@
+use std::fmt::{self, Display, Formatter};
+
+use shuttle_common::models::error::ApiError;
+
+/// The exit code of a command which failed in a way that has no code of its own
+pub const EXIT_FAILURE: i32 = 1;
+/// The exit code when there is no API key, or the API does not accept it
+pub const EXIT_AUTH_FAILURE: i32 = 3;
+/// The exit code when the project could not be built, locally or by shuttle
+pub const EXIT_BUILD_FAILURE: i32 = 4;
+/// The exit code when the project was built, but the deployment did not start running
+pub const EXIT_DEPLOYMENT_FAILURE: i32 = 5;
+
+/// An error which has its own exit code, so that scripts like CI pipelines can tell it apart
+/// without reading the error message
+#[derive(Debug)]
+pub enum Failure {
+    Auth(String),
+    Build(String),
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Auth(message) | Failure::Build(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// The exit code for a command which failed with `error`
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    match error.downcast_ref::<Failure>() {
+        Some(Failure::Auth(_)) => return EXIT_AUTH_FAILURE,
+        Some(Failure::Build(_)) => return EXIT_BUILD_FAILURE,
+        None => {}
+    }
+
+    let unauthorized = error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<ApiError>())
+        .any(|api_error| matches!(api_error.status_code, 401 | 403));
+
+    if unauthorized {
+        EXIT_AUTH_FAILURE
+    } else {
+        EXIT_FAILURE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{anyhow, Context};
+    use shuttle_common::models::error::ApiError;
+
+    use super::{exit_code, Failure, EXIT_AUTH_FAILURE, EXIT_BUILD_FAILURE, EXIT_FAILURE};
+
+    #[test]
+    fn exit_codes() {
+        let not_logged_in = anyhow!("Configuration file: config.toml")
+            .context(Failure::Auth("No valid API key found".to_string()));
+        assert_eq!(exit_code(&not_logged_in), EXIT_AUTH_FAILURE);
+
+        let unauthorized = anyhow::Error::from(ApiError {
+            message: "API key is missing".to_string(),
+            status_code: 401,
+        })
+        .context("failed to get the projects");
+        assert_eq!(exit_code(&unauthorized), EXIT_AUTH_FAILURE);
+
+        let build = anyhow::Error::from(Failure::Build("Build failed".to_string()));
+        assert_eq!(exit_code(&build), EXIT_BUILD_FAILURE);
+
+        let not_found: anyhow::Result<()> = Err(ApiError {
+            message: "project not found".to_string(),
+            status_code: 404,
+        })
+        .context("failed to get the project");
+        assert_eq!(exit_code(&not_found.unwrap_err()), EXIT_FAILURE);
+    }
+}
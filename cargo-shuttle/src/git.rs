@@ -0,0 +1,124 @@
+use std::path::Path;
+
+use git2::{Repository, StatusOptions};
+use tracing::{debug, trace};
+
+/// The state of the git repository a deployment is made from, so that it can be traced back to
+/// the code it runs
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GitMetadata {
+    pub commit_id: String,
+    /// The summary line of the commit message
+    pub commit_msg: String,
+    /// The checked out branch, unless the HEAD is detached
+    pub branch: Option<String>,
+    /// Whether there are changes in `working_directory` which were not committed
+    pub dirty: bool,
+}
+
+impl GitMetadata {
+    /// Get the state of the repository holding `working_directory`, if there is one with a commit
+    pub fn discover(working_directory: &Path) -> Option<Self> {
+        let repo = match Repository::discover(working_directory) {
+            Ok(repo) => repo,
+            Err(error) => {
+                trace!(%error, "not deploying from a git repository");
+                return None;
+            }
+        };
+
+        let head = match repo.head() {
+            Ok(head) => head,
+            Err(error) => {
+                debug!(%error, "git repository has no HEAD");
+                return None;
+            }
+        };
+        let commit = head.peel_to_commit().ok()?;
+        let branch = if head.is_branch() {
+            head.shorthand().map(ToString::to_string)
+        } else {
+            None
+        };
+
+        let dirty = match uncommitted_changes(&repo, working_directory) {
+            Ok(count) => count > 0,
+            Err(error) => {
+                debug!(%error, "failed to get the status of the git repository");
+                return None;
+            }
+        };
+
+        Some(Self {
+            commit_id: commit.id().to_string(),
+            commit_msg: commit.summary().unwrap_or_default().to_string(),
+            branch,
+            dirty,
+        })
+    }
+}
+
+/// Count the files in `working_directory` which were changed since the last commit
+fn uncommitted_changes(repo: &Repository, working_directory: &Path) -> anyhow::Result<usize> {
+    let Some(repo_path) = repo.workdir() else {
+        // A bare repository has no working directory to be dirty
+        return Ok(0);
+    };
+    let repo_path = dunce::canonicalize(repo_path)?;
+    let pathspec = working_directory
+        .strip_prefix(&repo_path)
+        .unwrap_or(Path::new(""));
+
+    let mut status_options = StatusOptions::new();
+    status_options.pathspec(pathspec).include_untracked(true);
+
+    Ok(repo.statuses(Some(&mut status_options))?.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use git2::{Repository, Signature};
+    use tempfile::tempdir;
+
+    use super::GitMetadata;
+
+    #[test]
+    fn discover() {
+        let dir = tempdir().unwrap();
+        let path = dunce::canonicalize(dir.path()).unwrap();
+        assert_eq!(GitMetadata::discover(&path), None);
+
+        let repo = Repository::init(&path).unwrap();
+        fs::write(path.join("main.rs"), "fn main() {}").unwrap();
+
+        // A repository without commits has nothing to trace back to
+        assert_eq!(GitMetadata::discover(&path), None);
+
+        let mut index = repo.index().unwrap();
+        index.add_path("main.rs".as_ref()).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("test", "test@example.com").unwrap();
+        let commit_id = repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                "Initial commit\n\nWith a body",
+                &tree,
+                &[],
+            )
+            .unwrap();
+        index.write().unwrap();
+
+        let metadata = GitMetadata::discover(&path).unwrap();
+        assert_eq!(metadata.commit_id, commit_id.to_string());
+        assert_eq!(metadata.commit_msg, "Initial commit");
+        assert!(metadata.branch.is_some());
+        assert!(!metadata.dirty);
+
+        fs::write(path.join("main.rs"), "fn main() { println!() }").unwrap();
+        assert!(GitMetadata::discover(&path).unwrap().dirty);
+    }
+}
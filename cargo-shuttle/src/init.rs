@@ -1,9 +1,11 @@
-use std::fs::{read_to_string, File};
+use std::fs::{read_to_string, File, OpenOptions};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use cargo_edit::{find, get_latest_dependency, registry_url};
+use git2::Repository;
+use ignore::WalkBuilder;
 use indoc::indoc;
 use shuttle_common::project::ProjectName;
 use toml_edit::{value, Array, Document, Table};
@@ -49,6 +51,96 @@ impl Template {
     }
 }
 
+/// A resource a new project can be set up with, by taking it as a parameter of its
+/// `#[shuttle_runtime::main]` function
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::Display, strum::EnumIter)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Resource {
+    Postgres,
+    Secrets,
+    Persist,
+}
+
+impl Resource {
+    /// Writes the dependencies needed to use this resource to `Cargo.toml`
+    fn set_cargo_dependencies(
+        &self,
+        dependencies: &mut Table,
+        manifest_path: &Path,
+        url: &Url,
+        get_dependency_version_fn: GetDependencyVersionFn,
+    ) {
+        match self {
+            Self::Postgres => {
+                set_inline_table_dependency_version(
+                    "shuttle-shared-db",
+                    dependencies,
+                    manifest_path,
+                    url,
+                    true,
+                    get_dependency_version_fn,
+                );
+                set_inline_table_dependency_features(
+                    "shuttle-shared-db",
+                    dependencies,
+                    vec!["postgres".to_string()],
+                );
+
+                set_inline_table_dependency_version(
+                    "sqlx",
+                    dependencies,
+                    manifest_path,
+                    url,
+                    false,
+                    get_dependency_version_fn,
+                );
+                set_inline_table_dependency_features(
+                    "sqlx",
+                    dependencies,
+                    vec![
+                        "runtime-tokio-native-tls".to_string(),
+                        "postgres".to_string(),
+                    ],
+                );
+            }
+            Self::Secrets => set_key_value_dependency_version(
+                "shuttle-secrets",
+                dependencies,
+                manifest_path,
+                url,
+                false,
+                get_dependency_version_fn,
+            ),
+            Self::Persist => set_key_value_dependency_version(
+                "shuttle-persist",
+                dependencies,
+                manifest_path,
+                url,
+                true,
+                get_dependency_version_fn,
+            ),
+        }
+    }
+
+    /// The attribute the resource is requested with
+    fn attribute(&self) -> &'static str {
+        match self {
+            Self::Postgres => "#[shuttle_shared_db::Postgres]",
+            Self::Secrets => "#[shuttle_secrets::Secrets]",
+            Self::Persist => "#[shuttle_persist::Persist]",
+        }
+    }
+
+    /// The parameter of the main function the resource is passed in, without its attribute
+    fn parameter(&self) -> &'static str {
+        match self {
+            Self::Postgres => "_pool: sqlx::PgPool",
+            Self::Secrets => "_secret_store: shuttle_secrets::SecretStore",
+            Self::Persist => "_persist: shuttle_persist::PersistInstance",
+        }
+    }
+}
+
 pub trait ShuttleInit {
     fn set_cargo_dependencies(
         &self,
@@ -884,7 +976,12 @@ pub fn cargo_init(path: PathBuf, name: ProjectName) -> Result<()> {
 }
 
 /// Performs shuttle init on the existing files generated by `cargo init [path]`.
-pub fn cargo_shuttle_init(path: PathBuf, framework: Template) -> Result<()> {
+pub fn cargo_shuttle_init(
+    path: PathBuf,
+    name: &ProjectName,
+    framework: Template,
+    resources: &[Resource],
+) -> Result<()> {
     println!(r#"     Setting up "{framework}" template"#);
     let cargo_toml_path = path.join("Cargo.toml");
     let mut cargo_doc = read_to_string(cargo_toml_path.clone())
@@ -904,6 +1001,14 @@ pub fn cargo_shuttle_init(path: PathBuf, framework: Template) -> Result<()> {
     let url = registry_url(manifest_path.as_path(), None).expect("Could not find registry URL");
 
     let init_config = framework.init_config();
+    let boilerplate = init_config.get_boilerplate_code_for_framework();
+
+    // Resources can only be wired into a main function
+    let resources = if boilerplate.is_empty() {
+        &[]
+    } else {
+        resources
+    };
 
     set_key_value_dependency_version(
         "shuttle-runtime",
@@ -922,6 +1027,16 @@ pub fn cargo_shuttle_init(path: PathBuf, framework: Template) -> Result<()> {
         get_latest_dependency_version,
     );
 
+    for resource in resources {
+        println!(r#"      Adding "{resource}" resource"#);
+        resource.set_cargo_dependencies(
+            dependencies,
+            &manifest_path,
+            &url,
+            get_latest_dependency_version,
+        );
+    }
+
     // Truncate Cargo.toml and write the updated `Document` to it
     let mut cargo_toml = File::create(cargo_toml_path)?;
 
@@ -929,9 +1044,182 @@ pub fn cargo_shuttle_init(path: PathBuf, framework: Template) -> Result<()> {
 
     // Write boilerplate to `src/main.rs` file
     let main_path = path.join("src").join("main.rs");
-    let boilerplate = init_config.get_boilerplate_code_for_framework();
     if !boilerplate.is_empty() {
-        write_main_file(boilerplate, &main_path)?;
+        let code = wire_resources(boilerplate, resources);
+        write_main_file(&code, &main_path)?;
+
+        if code.contains(Resource::Secrets.attribute()) {
+            write_secrets_file(&path)?;
+        }
+    }
+
+    write_shuttle_toml(&path, name)?;
+
+    Ok(())
+}
+
+/// Creates the project at `path` from the template in the git repository `from`, which is either
+/// the URL of a repository or a GitHub repository like `gh:user/repo`
+pub fn clone_template(from: &str, path: &Path, name: &ProjectName) -> Result<()> {
+    let url = template_url(from);
+    println!(r#"    Creating project "{name}" in {path:?} from {url}"#);
+
+    Repository::clone(&url, path).with_context(|| format!("failed to clone {url}"))?;
+
+    // The project starts its own history instead of continuing the one of the template
+    std::fs::remove_dir_all(path.join(".git"))?;
+    Repository::init(path)?;
+
+    fill_placeholders(path, name)?;
+    set_shuttle_toml_name(path, name)?;
+
+    Ok(())
+}
+
+/// The URL to clone the template `from`, expanding `gh:user/repo` to a GitHub URL
+fn template_url(from: &str) -> String {
+    match from.strip_prefix("gh:") {
+        Some(repo) => format!("https://github.com/{}.git", repo.trim_end_matches(".git")),
+        None => from.to_string(),
+    }
+}
+
+/// Replaces the placeholders of templates in the files at `path`, which are the ones cargo-generate
+/// uses: `{{project-name}}` and `{{crate_name}}`
+fn fill_placeholders(path: &Path, name: &ProjectName) -> Result<()> {
+    let crate_name = name.as_str().replace('-', "_");
+
+    for entry in WalkBuilder::new(path).hidden(false).build() {
+        let entry = entry?;
+        if !entry
+            .file_type()
+            .map_or(false, |file_type| file_type.is_file())
+            || entry.path().components().any(|c| c.as_os_str() == ".git")
+        {
+            continue;
+        }
+
+        // Binary files cannot have placeholders
+        let Ok(contents) = read_to_string(entry.path()) else {
+            continue;
+        };
+
+        if contents.contains("{{") {
+            let filled = contents
+                .replace("{{project-name}}", name.as_str())
+                .replace("{{crate_name}}", &crate_name);
+
+            if filled != contents {
+                std::fs::write(entry.path(), filled)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Sets the project name in the `Shuttle.toml` at `path`, keeping everything else the template has
+/// in it
+fn set_shuttle_toml_name(path: &Path, name: &ProjectName) -> Result<()> {
+    let shuttle_toml_path = path.join("Shuttle.toml");
+    let mut shuttle_toml = if shuttle_toml_path.exists() {
+        read_to_string(&shuttle_toml_path)?
+            .parse::<Document>()
+            .context("failed to parse the Shuttle.toml of the template")?
+    } else {
+        Document::new()
+    };
+
+    shuttle_toml["name"] = value(name.as_str());
+
+    std::fs::write(shuttle_toml_path, shuttle_toml.to_string())?;
+
+    Ok(())
+}
+
+/// Adds a parameter for each of `resources` to the `#[shuttle_runtime::main]` function of
+/// `boilerplate`, unless the function already takes it
+fn wire_resources(boilerplate: &str, resources: &[Resource]) -> String {
+    let parameters: Vec<_> = resources
+        .iter()
+        .filter(|resource| !boilerplate.contains(resource.attribute()))
+        .map(|resource| format!("{} {}", resource.attribute(), resource.parameter()))
+        .collect();
+
+    if parameters.is_empty() {
+        return boilerplate.to_string();
+    }
+
+    let Some(main) = boilerplate.find("#[shuttle_runtime::main]") else {
+        return boilerplate.to_string();
+    };
+    let Some(open) = boilerplate[main..].find('(').map(|index| main + index + 1) else {
+        return boilerplate.to_string();
+    };
+    let Some(close) = boilerplate[open..].find(") ->").map(|index| open + index) else {
+        return boilerplate.to_string();
+    };
+
+    // Put every parameter on its own line, like rustfmt does when they do not fit on one
+    let existing = boilerplate[open..close].trim().trim_end_matches(',');
+    let mut signature = String::new();
+    for parameter in parameters
+        .iter()
+        .map(String::as_str)
+        .chain((!existing.is_empty()).then_some(existing))
+    {
+        signature.push_str("\n    ");
+        signature.push_str(parameter);
+        signature.push(',');
+    }
+    signature.push('\n');
+
+    format!(
+        "{}{}{}",
+        &boilerplate[..open],
+        signature,
+        &boilerplate[close..]
+    )
+}
+
+/// Writes a `Shuttle.toml` which keeps the project name, even if the crate is renamed
+fn write_shuttle_toml(path: &Path, name: &ProjectName) -> Result<()> {
+    let shuttle_toml_path = path.join("Shuttle.toml");
+    if shuttle_toml_path.exists() {
+        return Ok(());
+    }
+
+    let mut shuttle_toml = Document::new();
+    shuttle_toml["name"] = value(name.as_str());
+
+    std::fs::write(shuttle_toml_path, shuttle_toml.to_string())?;
+
+    Ok(())
+}
+
+/// Writes an empty `Secrets.toml` for the service to read its secrets from, and keeps it out of git
+fn write_secrets_file(path: &Path) -> Result<()> {
+    let secrets_path = path.join("Secrets.toml");
+    if !secrets_path.exists() {
+        std::fs::write(
+            secrets_path,
+            indoc! {r#"
+            # The secrets of the service, which should not be committed. For example:
+            # API_KEY = "hunter2"
+            "#},
+        )?;
+    }
+
+    let gitignore_path = path.join(".gitignore");
+    if gitignore_path.exists() {
+        let gitignore = read_to_string(&gitignore_path)?;
+        if !gitignore.lines().any(|line| line == "Secrets*.toml") {
+            let mut gitignore_file = OpenOptions::new().append(true).open(gitignore_path)?;
+            if !gitignore.is_empty() && !gitignore.ends_with('\n') {
+                writeln!(gitignore_file)?;
+            }
+            writeln!(gitignore_file, "Secrets*.toml")?;
+        }
     }
 
     Ok(())
@@ -1000,7 +1288,7 @@ fn get_latest_dependency_version(
 }
 
 /// Writes `boilerplate` code to the specified `main.rs` file path.
-pub fn write_main_file(boilerplate: &'static str, main_path: &Path) -> Result<()> {
+pub fn write_main_file(boilerplate: &str, main_path: &Path) -> Result<()> {
     let mut main_file = File::create(main_path)?;
     main_file.write_all(boilerplate.as_bytes())?;
 
@@ -1009,8 +1297,54 @@ pub fn write_main_file(boilerplate: &'static str, main_path: &Path) -> Result<()
 
 #[cfg(test)]
 mod shuttle_init_tests {
+    use tempfile::Builder;
+
     use super::*;
 
+    #[test]
+    fn test_template_url() {
+        assert_eq!(
+            template_url("gh:shuttle-hq/shuttle-examples"),
+            "https://github.com/shuttle-hq/shuttle-examples.git"
+        );
+        assert_eq!(
+            template_url("https://gitlab.com/user/template.git"),
+            "https://gitlab.com/user/template.git"
+        );
+    }
+
+    #[test]
+    fn test_fill_placeholders() {
+        let dir = Builder::new().prefix("template").tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"{{project-name}}\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("main.rs"),
+            "use {{crate_name}}::run;\nlet x = \"{{not a placeholder}}\";\n",
+        )
+        .unwrap();
+
+        let name: ProjectName = "my-project".parse().unwrap();
+        fill_placeholders(dir.path(), &name).unwrap();
+        set_shuttle_toml_name(dir.path(), &name).unwrap();
+
+        assert_eq!(
+            read_to_string(dir.path().join("Cargo.toml")).unwrap(),
+            "[package]\nname = \"my-project\"\n"
+        );
+        assert_eq!(
+            read_to_string(dir.path().join("main.rs")).unwrap(),
+            "use my_project::run;\nlet x = \"{{not a placeholder}}\";\n"
+        );
+        assert_eq!(
+            read_to_string(dir.path().join("Shuttle.toml")).unwrap(),
+            "name = \"my-project\"\n"
+        );
+    }
+
     fn cargo_toml_factory() -> Document {
         indoc! {r#"
             [dependencies]
@@ -1477,6 +1811,61 @@ mod shuttle_init_tests {
 
     // TODO: unignore this test when we publish shuttle-rocket
     #[ignore]
+    #[test]
+    fn test_set_cargo_dependencies_postgres() {
+        let mut cargo_toml = cargo_toml_factory();
+        let dependencies = cargo_toml["dependencies"].as_table_mut().unwrap();
+        let manifest_path = PathBuf::new();
+        let url = Url::parse("https://shuttle.rs").unwrap();
+
+        Resource::Postgres.set_cargo_dependencies(
+            dependencies,
+            &manifest_path,
+            &url,
+            mock_get_latest_dependency_version,
+        );
+
+        let expected = indoc! {r#"
+            [dependencies]
+            shuttle-shared-db = { version = "1.0", features = ["postgres"] }
+            sqlx = { version = "1.0", features = ["runtime-tokio-native-tls", "postgres"] }
+        "#};
+
+        assert_eq!(cargo_toml.to_string(), expected);
+    }
+
+    #[test]
+    fn test_wire_resources() {
+        let axum = ShuttleInitAxum.get_boilerplate_code_for_framework();
+        assert_eq!(wire_resources(axum, &[]), axum);
+
+        let code = wire_resources(axum, &[Resource::Postgres, Resource::Persist]);
+        assert!(code.contains(indoc! {r#"
+            #[shuttle_runtime::main]
+            async fn axum(
+                #[shuttle_shared_db::Postgres] _pool: sqlx::PgPool,
+                #[shuttle_persist::Persist] _persist: shuttle_persist::PersistInstance,
+            ) -> shuttle_axum::ShuttleAxum {"#}));
+
+        let actix_web = ShuttleInitActixWeb.get_boilerplate_code_for_framework();
+        let code = wire_resources(actix_web, &[Resource::Secrets]);
+        assert!(code.contains(indoc! {r#"
+            async fn actix_web(
+                #[shuttle_secrets::Secrets] _secret_store: shuttle_secrets::SecretStore,
+            ) -> ShuttleActixWeb"#}));
+
+        // The secrets poise already takes are kept
+        let poise = ShuttleInitPoise.get_boilerplate_code_for_framework();
+        assert_eq!(wire_resources(poise, &[Resource::Secrets]), poise);
+
+        let code = wire_resources(poise, &[Resource::Postgres]);
+        assert!(code.contains(indoc! {r#"
+            async fn poise(
+                #[shuttle_shared_db::Postgres] _pool: sqlx::PgPool,
+                #[shuttle_secrets::Secrets] secret_store: SecretStore,
+            ) -> ShuttlePoise<Data, Error> {"#}));
+    }
+
     #[test]
     /// Makes sure that Rocket uses allow_prerelease flag when fetching the latest version
     fn test_get_latest_dependency_version_rocket() {
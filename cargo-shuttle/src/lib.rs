@@ -1,17 +1,30 @@
 mod args;
 mod client;
+mod completions;
 pub mod config;
+mod doctor;
+mod failure;
+mod git;
 mod init;
+mod preview;
+mod progress;
 mod provisioner_server;
+mod proxy;
+mod status;
+mod upgrade;
+mod vendor;
 
 use args::LogoutArgs;
-use indicatif::ProgressBar;
+use indicatif::{HumanBytes, ProgressBar};
 use shuttle_common::claims::{ClaimService, InjectPropagation};
-use shuttle_common::models::deployment::get_deployments_table;
+use shuttle_common::deployment::Phase;
+use shuttle_common::models::deployment::{get_deployments_table, Approval};
 use shuttle_common::models::project::IDLE_MINUTES;
 use shuttle_common::models::resource::get_resources_table;
+use shuttle_common::models::service::Routing;
 use shuttle_common::project::ProjectName;
-use shuttle_common::{resource, ApiKey};
+use shuttle_common::shuttle_toml::ShuttleToml;
+use shuttle_common::{resource, ApiKey, LogItem};
 use shuttle_proto::runtime::runtime_client::RuntimeClient;
 use shuttle_proto::runtime::{self, LoadRequest, StartRequest, StopRequest, SubscribeLogsRequest};
 
@@ -23,52 +36,111 @@ use tonic::Status;
 use std::collections::HashMap;
 use std::ffi::OsString;
 use std::fs::{read_to_string, File};
-use std::io::stdout;
+use std::io::{self, stdout, Write as _};
 use std::net::{Ipv4Addr, SocketAddr};
 use std::path::{Path, PathBuf};
 
-use std::process::exit;
+use std::process::{exit, Stdio};
 use std::str::FromStr;
 
 use anyhow::{anyhow, bail, Context, Result};
-pub use args::{Args, Command, DeployArgs, InitArgs, LoginArgs, ProjectArgs, RunArgs};
+use args::{ArchiveCompression, CompletionKind, GenerateCommand};
+pub use args::{
+    Args, Command, DeployArgs, FeatureArgs, InitArgs, LoginArgs, OutputMode, ProjectArgs, RunArgs,
+};
 use cargo_metadata::Message;
+use chrono::Utc;
 use clap::CommandFactory;
 use clap_complete::{generate, Shell};
 use config::RequestContext;
-use crossterm::style::Stylize;
-use dialoguer::{theme::ColorfulTheme, Confirm, FuzzySelect, Input, Password};
+use crossterm::style::{Color, Stylize};
+use crossterm::tty::IsTty;
+use dialoguer::{theme::ColorfulTheme, Confirm, FuzzySelect, Input, MultiSelect, Password};
+pub use failure::exit_code;
 use flate2::write::GzEncoder;
 use flate2::Compression;
 use futures::{StreamExt, TryFutureExt};
 use git2::{Repository, StatusOptions};
 use ignore::overrides::OverrideBuilder;
 use ignore::WalkBuilder;
-use shuttle_common::models::{project, secret};
-use shuttle_service::builder::{build_workspace, BuiltService};
-use std::fmt::Write;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use shuttle_common::models::{
+    access, cron, deployment, domain, env, metrics, persist, project, secret, webhook,
+};
+use shuttle_service::builder::{
+    build_workspace, build_workspace_watch, clean_crate, plan_workspace, ArtifactMetadata,
+    BuildError, BuildEvent, BuildOptions, BuiltService, CancellationToken, ARTIFACT_METADATA_PATH,
+    ARTIFACT_PATH,
+};
+use std::fmt::{Display, Write};
 use strum::IntoEnumIterator;
 use tar::Builder;
 use tracing::{debug, error, trace, warn};
 use uuid::Uuid;
 
-use crate::args::{DeploymentCommand, ProjectCommand, ProjectStartArgs, ResourceCommand};
-use crate::client::Client;
+use crate::args::{
+    AccessCommand, CacheCommand, CronCommand, DeploymentCommand, DomainCommand, EnvCommand,
+    MaintenanceCommand, MetricsArgs, PersistCommand, ProjectCommand, ProjectRestartArgs,
+    ProjectStartArgs, ProxyArgs, ResourceCommand, SecretsCommand, StorageCommand, TcpCommand,
+    UpgradeArgs, WebhooksCommand,
+};
+use crate::client::{Client, DeploymentsFilter, LogsQuery};
+use crate::doctor::Report;
+use crate::failure::{Failure, EXIT_BUILD_FAILURE, EXIT_DEPLOYMENT_FAILURE};
+use crate::git::GitMetadata;
+use crate::progress::DeployProgress;
 use crate::provisioner_server::LocalProvisioner;
+use crate::status::StatusReport;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const MANIFEST_DIR: &str = env!("CARGO_MANIFEST_DIR");
 const SHUTTLE_LOGIN_URL: &str = "https://shuttle.rs/login";
+
+/// How many times in a row following logs may fail to connect before giving up
+const MAX_RECONNECT_ATTEMPTS: u32 = 10;
+const MAX_RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(30);
 const SHUTTLE_GH_ISSUE_URL: &str = "https://github.com/shuttle-hq/shuttle/issues/new";
+/// Files with the syntax of .gitignore, for files to leave out of deploys but keep in git
+const SHUTTLE_IGNORE_FILENAME: &str = ".shuttleignore";
+/// How many projects or deployments are offered when completing their argument
+const COMPLETION_CANDIDATES: u32 = 20;
+/// The size of the chunks projects are uploaded in, so that a failed upload resumes after the
+/// chunks which arrived
+const UPLOAD_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+/// How many times in a row uploading a chunk may fail before giving up
+const MAX_UPLOAD_RETRIES: u64 = 5;
+/// How long a deployment which started running is waited on to be reported healthy, which is a bit
+/// longer than the deployer tries for
+const HEALTHY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(35);
+
+/// The colours of the labels of the services whose logs are interleaved
+const LOG_LABEL_COLORS: [Color; 5] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Blue,
+    Color::Green,
+];
 
 pub struct Shuttle {
     ctx: RequestContext,
+    output: OutputMode,
+    /// Whether confirmations are answered with yes without asking
+    yes: bool,
+    /// Whether the user can be prompted
+    interactive: bool,
 }
 
 impl Shuttle {
     pub fn new() -> Result<Self> {
         let ctx = RequestContext::load_global()?;
-        Ok(Self { ctx })
+        Ok(Self {
+            ctx,
+            output: OutputMode::default(),
+            yes: false,
+            interactive: true,
+        })
     }
 
     pub async fn run(mut self, mut args: Args) -> Result<CommandOutcome> {
@@ -80,16 +152,29 @@ impl Shuttle {
             Command::Deploy(..)
                 | Command::Deployment(..)
                 | Command::Resource(..)
+                | Command::Storage(..)
+                | Command::Persist(..)
+                | Command::Metrics(..)
+                | Command::Proxy(..)
                 | Command::Project(
                     // ProjectCommand::List does not need to know which project we are in
                     ProjectCommand::Start { .. }
                         | ProjectCommand::Stop { .. }
                         | ProjectCommand::Restart { .. }
                         | ProjectCommand::Status { .. }
+                        | ProjectCommand::Maintenance { .. }
+                        | ProjectCommand::Tcp { .. }
                 )
                 | Command::Stop
-                | Command::Clean
-                | Command::Secrets
+                | Command::Scale { .. }
+                | Command::Clean { .. }
+                | Command::Cron { .. }
+                | Command::Secrets { .. }
+                | Command::Env { .. }
+                | Command::Webhooks { .. }
+                | Command::Domain { .. }
+                | Command::Access { .. }
+                | Command::Cache(..)
                 | Command::Status
                 | Command::Logs { .. }
                 | Command::Run(..)
@@ -98,36 +183,174 @@ impl Shuttle {
         }
 
         self.ctx.set_api_url(args.api_url);
+        self.ctx.set_environment(args.environment);
+        self.ctx.set_profile(args.profile);
+        self.output = args.output;
+        self.yes = args.yes;
+        self.interactive = !args.yes && !is_ci() && io::stdin().is_tty();
 
         match args.cmd {
             Command::Init(init_args) => self.init(init_args, args.project_args).await,
-            Command::Generate { shell, output } => self.complete(shell, output).await,
+            Command::Generate(GenerateCommand::Completions { shell, output_file }) => {
+                self.complete(shell, output_file).await
+            }
+            Command::Complete { kind } => self.completion_candidates(kind, args.project_args).await,
             Command::Login(login_args) => self.login(login_args).await,
             Command::Logout(logout_args) => self.logout(logout_args).await,
             Command::Feedback => self.feedback().await,
+            Command::Doctor => self.doctor(&args.project_args).await,
+            Command::Upgrade(upgrade_args) => self.upgrade(upgrade_args).await,
+            Command::Run(run_args) if run_args.watch => self.local_run_watch(run_args).await,
             Command::Run(run_args) => self.local_run(run_args).await,
+            Command::Deploy(deploy_args) if deploy_args.dry_run => {
+                self.deploy_dry_run(deploy_args).await
+            }
+            Command::Deploy(deploy_args) if deploy_args.show_archive => {
+                self.deploy_show_archive(deploy_args).await
+            }
             Command::Deploy(deploy_args) => {
                 return self.deploy(&self.client()?, deploy_args).await;
             }
             Command::Status => self.status(&self.client()?).await,
-            Command::Logs { id, latest, follow } => {
-                self.logs(&self.client()?, id, latest, follow).await
+            Command::Logs {
+                id,
+                latest,
+                follow,
+                since,
+                until,
+                tail,
+                level,
+                target,
+                source,
+                grep,
+            } => {
+                self.logs(
+                    &self.client()?,
+                    id,
+                    latest,
+                    follow,
+                    LogsQuery {
+                        since,
+                        until,
+                        tail,
+                        level,
+                        target,
+                        source,
+                        grep,
+                    },
+                )
+                .await
             }
-            Command::Deployment(DeploymentCommand::List { page, limit }) => {
-                self.deployments_list(&self.client()?, page, limit).await
+            Command::Deployment(DeploymentCommand::List {
+                page,
+                limit,
+                state,
+                since,
+                until,
+            }) => {
+                self.deployments_list(
+                    &self.client()?,
+                    page,
+                    limit,
+                    DeploymentsFilter {
+                        state,
+                        since,
+                        until,
+                    },
+                )
+                .await
             }
             Command::Deployment(DeploymentCommand::Status { id }) => {
                 self.deployment_get(&self.client()?, id).await
             }
+            Command::Deployment(DeploymentCommand::Rollback { to }) => {
+                self.deployment_rollback(&self.client()?, to).await
+            }
+            Command::Deployment(DeploymentCommand::Cancel { id }) => {
+                self.deployment_cancel(&self.client()?, id).await
+            }
+            Command::Deployment(DeploymentCommand::Approve { id }) => {
+                self.deployment_approve(&self.client()?, id).await
+            }
+            Command::Deployment(DeploymentCommand::RequireApproval { required }) => {
+                self.deployment_require_approval(&self.client()?, required)
+                    .await
+            }
             Command::Resource(ResourceCommand::List) => self.resources_list(&self.client()?).await,
+            Command::Resource(ResourceCommand::Delete { resource_type }) => {
+                self.resource_delete(&self.client()?, &resource_type).await
+            }
+            Command::Storage(StorageCommand::Usage) => self.storage_usage(&self.client()?).await,
+            Command::Persist(PersistCommand::Ls) => self.persist_ls(&self.client()?).await,
+            Command::Metrics(metrics_args) => self.metrics(&self.client()?, metrics_args).await,
+            Command::Proxy(proxy_args) => self.proxy(&self.client()?, proxy_args).await,
             Command::Stop => self.stop(&self.client()?).await,
-            Command::Clean => self.clean(&self.client()?).await,
-            Command::Secrets => self.secrets(&self.client()?).await,
+            Command::Scale { replicas, routing } => {
+                self.scale(&self.client()?, replicas, routing).await
+            }
+            Command::Clean { remote: true } => self.clean(&self.client()?).await,
+            Command::Clean { remote: false } => self.clean_local().await,
+            Command::Cron { cmd } => match cmd.unwrap_or(CronCommand::List) {
+                CronCommand::List => self.cron_jobs(&self.client()?).await,
+                CronCommand::Runs { name, limit } => {
+                    self.cron_runs(&self.client()?, name, limit).await
+                }
+            },
+            Command::Secrets { cmd } => match cmd.unwrap_or(SecretsCommand::List) {
+                SecretsCommand::List => self.secrets(&self.client()?).await,
+                SecretsCommand::Set { secrets } => self.secrets_set(&self.client()?, secrets).await,
+                SecretsCommand::Rm { keys } => self.secrets_rm(&self.client()?, keys).await,
+            },
+            Command::Env { cmd } => match cmd.unwrap_or(EnvCommand::List) {
+                EnvCommand::List => self.env_vars(&self.client()?).await,
+                EnvCommand::Set { vars } => self.env_vars_set(&self.client()?, vars).await,
+                EnvCommand::Unset { keys } => self.env_vars_unset(&self.client()?, keys).await,
+            },
+            Command::Webhooks { cmd } => match cmd.unwrap_or(WebhooksCommand::List) {
+                WebhooksCommand::List => self.webhooks(&self.client()?).await,
+                WebhooksCommand::Add { url, events } => {
+                    self.webhooks_add(&self.client()?, url, events).await
+                }
+                WebhooksCommand::Rm { ids } => self.webhooks_rm(&self.client()?, ids).await,
+            },
+            Command::Domain { cmd } => match cmd.unwrap_or(DomainCommand::List) {
+                DomainCommand::List => self.domains(&self.client()?).await,
+                DomainCommand::Add { fqdn } => self.domain_add(&self.client()?, fqdn).await,
+            },
+            Command::Access { cmd } => match cmd.unwrap_or(AccessCommand::Show) {
+                AccessCommand::Show => self.access(&self.client()?).await,
+                AccessCommand::Set {
+                    allow,
+                    deny,
+                    basic,
+                    bearer,
+                } => {
+                    let guard = match (basic, bearer) {
+                        (Some((username, password)), _) => {
+                            Some(access::Guard::Basic { username, password })
+                        }
+                        (None, Some(token)) => Some(access::Guard::Bearer { token }),
+                        (None, None) => None,
+                    };
+
+                    self.access_set(&self.client()?, access::Request { allow, deny, guard })
+                        .await
+                }
+                AccessCommand::Clear => self.access_clear(&self.client()?).await,
+            },
+            Command::Cache(CacheCommand::Purge { path }) => {
+                self.cache_purge(&self.client()?, path).await
+            }
             Command::Project(ProjectCommand::Start(ProjectStartArgs { idle_minutes })) => {
                 self.project_create(&self.client()?, idle_minutes).await
             }
-            Command::Project(ProjectCommand::Restart(ProjectStartArgs { idle_minutes })) => {
-                self.project_recreate(&self.client()?, idle_minutes).await
+            Command::Project(ProjectCommand::Restart(ProjectRestartArgs { idle_minutes })) => {
+                match idle_minutes {
+                    Some(idle_minutes) => {
+                        self.project_recreate(&self.client()?, idle_minutes).await
+                    }
+                    None => self.project_restart(&self.client()?).await,
+                }
             }
             Command::Project(ProjectCommand::Status { follow }) => {
                 self.project_status(&self.client()?, follow).await
@@ -136,6 +359,16 @@ impl Shuttle {
                 self.projects_list(&self.client()?, page, limit).await
             }
             Command::Project(ProjectCommand::Stop) => self.project_delete(&self.client()?).await,
+            Command::Project(ProjectCommand::Maintenance { cmd }) => match cmd {
+                MaintenanceCommand::On { status, page } => {
+                    self.maintenance_on(&self.client()?, status, page).await
+                }
+                MaintenanceCommand::Off => self.maintenance_off(&self.client()?).await,
+            },
+            Command::Project(ProjectCommand::Tcp { cmd }) => match cmd {
+                TcpCommand::Expose { port } => self.tcp_expose(&self.client()?, port).await,
+                TcpCommand::Close { port } => self.tcp_close(&self.client()?, port).await,
+            },
         }
         .map(|_| CommandOutcome::Ok)
     }
@@ -143,15 +376,67 @@ impl Shuttle {
     fn client(&self) -> Result<Client> {
         let mut client = Client::new(self.ctx.api_url());
         client.set_api_key(self.ctx.api_key()?);
+        client.set_environment(self.ctx.environment().cloned());
         Ok(client)
     }
 
+    /// Print the result of a command, as JSON when it is asked for or else the way `table` shows it
+    fn print_result<T: Serialize>(&self, value: &T, table: impl FnOnce() -> String) -> Result<()> {
+        match self.output {
+            OutputMode::Table => println!("{}", table()),
+            OutputMode::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        }
+
+        Ok(())
+    }
+
+    /// Print a log line, which is a JSON object on its own line when JSON is asked for
+    fn print_log(&self, log: &LogItem) -> Result<()> {
+        match self.output {
+            OutputMode::Table => println!("{log}"),
+            OutputMode::Json => println!("{}", serde_json::to_string(log)?),
+        }
+
+        Ok(())
+    }
+
+    /// Ask the user to confirm `prompt`, which `--yes` answers without asking
+    fn confirm(&self, prompt: String) -> Result<bool> {
+        if self.yes {
+            return Ok(true);
+        }
+
+        if !self.interactive {
+            bail!("Cannot ask \"{prompt}\" without a terminal. Pass `--yes` to confirm it");
+        }
+
+        let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(prompt)
+            .default(false)
+            .interact()?;
+
+        Ok(confirmed)
+    }
+
+    /// Print a message for people, which goes to stderr so that stdout only has the JSON when it
+    /// is asked for
+    fn print_message(&self, message: impl Display) {
+        match self.output {
+            OutputMode::Table => println!("{message}"),
+            OutputMode::Json => eprintln!("{message}"),
+        }
+    }
+
     /// Log in, initialize a project and potentially create the Shuttle environment for it.
     ///
-    /// If both a project name and framework are passed as arguments, it will run without any extra
-    /// interaction.
+    /// If both a project name and framework, or a template repository, are passed as arguments, it
+    /// will run without any extra interaction.
     async fn init(&mut self, args: InitArgs, mut project_args: ProjectArgs) -> Result<()> {
-        let interactive = project_args.name.is_none() || args.framework().is_none();
+        let interactive =
+            project_args.name.is_none() || (args.framework().is_none() && args.from.is_none());
+        if interactive && !self.interactive {
+            bail!("Cannot prompt for the project without a terminal. Pass `--name` and a template like `--template axum`");
+        }
 
         let theme = ColorfulTheme::default();
 
@@ -199,29 +484,52 @@ impl Shuttle {
             args.path.clone()
         };
 
-        // 4. Ask for the framework
-        let framework = match args.framework() {
-            Some(framework) => framework,
-            None => {
-                println!(
-                    "Shuttle works with a range of web frameworks. Which one do you want to use?"
-                );
-                let frameworks = init::Template::iter().collect::<Vec<_>>();
-                let index = FuzzySelect::with_theme(&theme)
-                    .items(&frameworks)
-                    .default(0)
+        let name = project_args.name.clone().unwrap();
+        if let Some(from) = &args.from {
+            // 4. Initialize locally from the template repository, which comes with its own
+            // framework and resources
+            init::clone_template(from, &path, &name)?;
+        } else {
+            // 4. Ask for the framework
+            let framework = match args.framework() {
+                Some(framework) => framework,
+                None => {
+                    println!(
+                        "Shuttle works with a range of web frameworks. Which one do you want to use?"
+                    );
+                    let frameworks = init::Template::iter().collect::<Vec<_>>();
+                    let index = FuzzySelect::with_theme(&theme)
+                        .items(&frameworks)
+                        .default(0)
+                        .interact()?;
+                    println!();
+                    frameworks[index]
+                }
+            };
+
+            // 5. Ask for the resources
+            let resources = if interactive
+                && args.resources.is_empty()
+                && framework != init::Template::None
+            {
+                println!("Which resources does your service need? They can also be added later.");
+                let resources = init::Resource::iter().collect::<Vec<_>>();
+                let indices = MultiSelect::with_theme(&theme)
+                    .items(&resources)
                     .interact()?;
                 println!();
-                frameworks[index]
-            }
-        };
+                indices.into_iter().map(|index| resources[index]).collect()
+            } else {
+                args.resources()
+            };
 
-        // 5. Initialize locally
-        init::cargo_init(path.clone(), project_args.name.clone().unwrap())?;
-        init::cargo_shuttle_init(path.clone(), framework)?;
+            // 6. Initialize locally
+            init::cargo_init(path.clone(), name.clone())?;
+            init::cargo_shuttle_init(path.clone(), &name, framework, &resources)?;
+        }
         println!();
 
-        // 6. Confirm that the user wants to create the project environment on Shuttle
+        // 7. Confirm that the user wants to create the project environment on Shuttle
         let should_create_environment = if !interactive {
             args.create_env
         } else if args.create_env {
@@ -266,26 +574,132 @@ impl Shuttle {
         Ok(())
     }
 
-    /// Log in with the given API key or after prompting the user for one.
+    /// Diagnose the problems which keep shuttle from working on this machine
+    async fn doctor(&self, project_args: &ProjectArgs) -> Result<()> {
+        // The API key is checked by the diagnosis, so not being logged in is not an error here
+        let api_key = self.ctx.api_key().ok();
+        let logged_in = api_key.is_some();
+
+        let mut client = Client::new(self.ctx.api_url());
+        if let Some(api_key) = api_key {
+            client.set_api_key(api_key);
+        }
+
+        let report = Report::diagnose(&project_args.working_directory, &client, logged_in).await;
+
+        self.print_result(&report, || report.to_string())?;
+        if report.has_failures() {
+            self.print_message(
+                "If these fixes do not help, open an issue with this output with `cargo shuttle feedback`",
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn upgrade(&self, args: UpgradeArgs) -> Result<()> {
+        let current = semver::Version::parse(VERSION)?;
+        let release = upgrade::latest_release(args.preview).await?;
+        let latest = release.version()?;
+        let upgraded = latest > current;
+
+        if upgraded {
+            self.print_message(format!(
+                "Upgrading cargo-shuttle from {current} to {}",
+                latest.to_string().bold()
+            ));
+            let path = upgrade::install(&release).await?;
+            self.print_message(format!(
+                "Installed cargo-shuttle {latest} at {}. Update shuttle-runtime and the other shuttle crates of your projects to {latest} too.",
+                path.display()
+            ));
+        } else {
+            self.print_message(format!("cargo-shuttle {current} is up to date"));
+        }
+
+        if self.output == OutputMode::Json {
+            println!(
+                "{}",
+                serde_json::json!({
+                    "previous": current.to_string(),
+                    "latest": latest.to_string(),
+                    "upgraded": upgraded,
+                })
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Log in with the given API key or else by approving this device in a browser.
     async fn login(&mut self, login_args: LoginArgs) -> Result<()> {
-        let api_key_str = match login_args.api_key {
-            Some(api_key) => api_key,
-            None => {
-                let _ = webbrowser::open(SHUTTLE_LOGIN_URL);
-                println!("If your browser did not automatically open, go to {SHUTTLE_LOGIN_URL}");
+        let api_key = match login_args.api_key {
+            Some(api_key) => ApiKey::parse(&api_key)?,
+            None if !self.interactive => {
+                return Err(Failure::Auth(
+                    "Cannot log in without a terminal. Pass an API key with `--api-key`, or set it in the SHUTTLE_API_KEY environment variable".to_string(),
+                )
+                .into())
+            }
+            None => self.device_login().await?,
+        };
+
+        let store = self.ctx.set_api_key(api_key)?;
+        match self.ctx.profile() {
+            Some(profile) => println!(
+                "Logged in to shuttle with the profile `{profile}`. The API key is saved in {store}."
+            ),
+            None => println!("Logged in to shuttle. The API key is saved in {store}."),
+        }
 
-                Password::with_theme(&ColorfulTheme::default())
-                    .with_prompt("API key")
-                    .validate_with(|input: &String| ApiKey::parse(input).map(|_| {}))
-                    .interact()?
+        Ok(())
+    }
+
+    /// Get an API key by having the user approve this device in a browser, or by prompting for
+    /// one when the API cannot do that.
+    async fn device_login(&self) -> Result<ApiKey> {
+        let client = Client::new(self.ctx.api_url());
+        let authorization = match client.start_device_login().await {
+            Ok(authorization) => authorization,
+            Err(error) => {
+                debug!(%error, "failed to start a device login, prompting for an API key");
+                return prompt_api_key();
             }
         };
 
-        let api_key = ApiKey::parse(&api_key_str)?;
+        let _ = webbrowser::open(&authorization.verification_uri_complete);
+        println!(
+            "If your browser did not automatically open, go to {} and enter the code {}",
+            authorization.verification_uri,
+            authorization.user_code.clone().bold()
+        );
 
-        self.ctx.set_api_key(api_key)?;
+        let progress_bar = create_spinner();
+        progress_bar.set_message("Waiting for the login to be approved");
+
+        let expires_at =
+            std::time::Instant::now() + std::time::Duration::from_secs(authorization.expires_in);
+        let interval = std::time::Duration::from_secs(authorization.interval.max(1));
+        let token = loop {
+            if std::time::Instant::now() > expires_at {
+                progress_bar.finish_and_clear();
+                bail!("The login code expired, run `cargo shuttle login` again");
+            }
 
-        Ok(())
+            tokio::time::sleep(interval).await;
+
+            match client.poll_device_login(&authorization.device_code).await {
+                Ok(Some(token)) => break token,
+                Ok(None) => {}
+                Err(error) => {
+                    progress_bar.finish_and_clear();
+                    return Err(error);
+                }
+            }
+        };
+        progress_bar.finish_and_clear();
+
+        ApiKey::parse(&token.api_key)
     }
 
     async fn logout(&mut self, logout_args: LogoutArgs) -> Result<()> {
@@ -329,36 +743,421 @@ impl Shuttle {
         }
         progress_bar.finish_and_clear();
 
-        println!("{}\n{}", "Successfully stopped service".bold(), service);
-        println!("Run `cargo shuttle deploy` to re-deploy your service.");
+        self.print_message("Successfully stopped service".bold());
+        self.print_result(&service, || service.to_string())?;
+        self.print_message("Run `cargo shuttle deploy` to re-deploy your service.");
+
+        Ok(())
+    }
+
+    async fn scale(
+        &self,
+        client: &Client,
+        replicas: Option<u32>,
+        routing: Option<Routing>,
+    ) -> Result<()> {
+        let proj_name = self.ctx.project_name();
+        let scaled = match (replicas, routing) {
+            (Some(replicas), routing) => client.scale_service(proj_name, replicas, routing).await?,
+            // Changing how the replicas take turns keeps how many there are
+            (None, Some(routing)) => {
+                let current = client.get_replicas(proj_name).await?;
+                client
+                    .scale_service(proj_name, current.replicas, Some(routing))
+                    .await?
+            }
+            (None, None) => client.get_replicas(proj_name).await?,
+        };
+
+        self.print_result(&scaled, || {
+            format!(
+                "{} is served by {} replicas, routed {}",
+                proj_name.service_name(self.ctx.environment()).bold(),
+                scaled.replicas,
+                scaled.routing.clone().unwrap_or_default(),
+            )
+        })?;
+
+        if replicas.is_some() {
+            self.print_message(
+                "The replicas of the running deployment are started or stopped in the background.",
+            );
+        }
 
         Ok(())
     }
 
     async fn complete(&self, shell: Shell, output: Option<PathBuf>) -> Result<()> {
         let name = env!("CARGO_PKG_NAME");
-        let mut app = Command::command();
-        match output {
-            Some(v) => generate(shell, &mut app, name, &mut File::create(v)?),
-            None => generate(shell, &mut app, name, &mut stdout()),
+        let mut app = Args::command();
+        let mut output: Box<dyn io::Write> = match output {
+            Some(v) => Box::new(File::create(v)?),
+            None => Box::new(stdout()),
+        };
+
+        generate(shell, &mut app, name, &mut output);
+        if let Some(dynamic) = completions::dynamic_completions(shell) {
+            output.write_all(dynamic.as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Print the values an argument of the kind `kind` can be completed with, for the completions
+    /// of the shells to call
+    async fn completion_candidates(
+        &mut self,
+        kind: CompletionKind,
+        mut project_args: ProjectArgs,
+    ) -> Result<()> {
+        let candidates: Vec<String> = match kind {
+            CompletionKind::Projects => self
+                .client()?
+                .get_projects_list(1, COMPLETION_CANDIDATES)
+                .await?
+                .into_iter()
+                .map(|project| project.name)
+                .collect(),
+            CompletionKind::Deployments => {
+                self.load_project(&mut project_args)?;
+
+                self.client()?
+                    .get_deployments(
+                        self.ctx.project_name(),
+                        1,
+                        COMPLETION_CANDIDATES,
+                        &DeploymentsFilter::default(),
+                    )
+                    .await?
+                    .into_iter()
+                    .map(|deployment| deployment.id.to_string())
+                    .collect()
+            }
         };
 
+        for candidate in candidates {
+            println!("{candidate}");
+        }
+
         Ok(())
     }
 
     async fn status(&self, client: &Client) -> Result<()> {
-        let summary = client.get_service(self.ctx.project_name()).await?;
+        let project_name = self.ctx.project_name();
+        let project = client.get_project(project_name).await?;
+
+        // The service is only there once it was deployed, and only while the project is running
+        let service = client
+            .get_service(project_name)
+            .await
+            .map_err(|error| debug!(%error, "failed to get the service"))
+            .ok();
+        let resources = if service.is_some() {
+            client
+                .get_service_resources(project_name)
+                .await
+                .map_err(|error| debug!(%error, "failed to get the resources"))
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let logs = match service
+            .as_ref()
+            .and_then(|service| service.deployment.as_ref())
+        {
+            Some(deployment) => client
+                .get_logs(project_name, &deployment.id, &LogsQuery::default())
+                .await
+                .map_err(|error| debug!(%error, "failed to get the logs"))
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+
+        let report = StatusReport::new(project, service, resources, logs);
+
+        self.print_result(&report, || report.to_string())?;
+
+        Ok(())
+    }
+
+    async fn cron_jobs(&self, client: &Client) -> Result<()> {
+        let jobs = client.get_cron_jobs(self.ctx.project_name()).await?;
 
-        println!("{summary}");
+        self.print_result(&jobs, || cron::get_jobs_table(&jobs))?;
+
+        Ok(())
+    }
+
+    async fn cron_runs(&self, client: &Client, name: Option<String>, limit: u32) -> Result<()> {
+        let runs = client
+            .get_cron_runs(self.ctx.project_name(), name.as_deref(), limit)
+            .await?;
+
+        self.print_result(&runs, || cron::get_runs_table(&runs))?;
 
         Ok(())
     }
 
     async fn secrets(&self, client: &Client) -> Result<()> {
         let secrets = client.get_secrets(self.ctx.project_name()).await?;
-        let table = secret::get_table(&secrets);
 
-        println!("{table}");
+        self.print_result(&secrets, || secret::get_table(&secrets))?;
+
+        Ok(())
+    }
+
+    async fn secrets_set(&self, client: &Client, secrets: Vec<(String, String)>) -> Result<()> {
+        let secrets = client
+            .set_secrets(self.ctx.project_name(), &secrets.into_iter().collect())
+            .await?;
+
+        self.print_result(&secrets, || secret::get_table(&secrets))?;
+        self.print_message("The service gets the new secrets the next time it is started.");
+
+        Ok(())
+    }
+
+    async fn secrets_rm(&self, client: &Client, keys: Vec<String>) -> Result<()> {
+        let mut removed = Vec::with_capacity(keys.len());
+        for key in keys {
+            removed.push(client.delete_secret(self.ctx.project_name(), &key).await?);
+            self.print_message(format!("Removed the secret {}", key.bold()));
+        }
+
+        if self.output == OutputMode::Json {
+            println!("{}", serde_json::to_string_pretty(&removed)?);
+        }
+
+        Ok(())
+    }
+
+    async fn env_vars(&self, client: &Client) -> Result<()> {
+        let vars = client.get_env_vars(self.ctx.project_name()).await?;
+
+        self.print_result(&vars, || env::get_table(&vars))?;
+
+        Ok(())
+    }
+
+    async fn env_vars_set(&self, client: &Client, vars: Vec<(String, String)>) -> Result<()> {
+        let vars = client
+            .set_env_vars(self.ctx.project_name(), &vars.into_iter().collect())
+            .await?;
+
+        self.print_result(&vars, || env::get_table(&vars))?;
+        self.print_message(
+            "The service gets the new environment variables the next time it is started.",
+        );
+
+        Ok(())
+    }
+
+    async fn env_vars_unset(&self, client: &Client, keys: Vec<String>) -> Result<()> {
+        let mut removed = Vec::with_capacity(keys.len());
+        for key in keys {
+            removed.push(client.delete_env_var(self.ctx.project_name(), &key).await?);
+            self.print_message(format!("Removed the environment variable {}", key.bold()));
+        }
+
+        if self.output == OutputMode::Json {
+            println!("{}", serde_json::to_string_pretty(&removed)?);
+        }
+
+        Ok(())
+    }
+
+    async fn webhooks(&self, client: &Client) -> Result<()> {
+        let webhooks = client.get_webhooks(self.ctx.project_name()).await?;
+
+        self.print_result(&webhooks, || webhook::get_table(&webhooks))?;
+
+        Ok(())
+    }
+
+    async fn webhooks_add(
+        &self,
+        client: &Client,
+        url: String,
+        events: Vec<webhook::Event>,
+    ) -> Result<()> {
+        let created = client
+            .create_webhook(self.ctx.project_name(), &webhook::Request { url, events })
+            .await?;
+
+        self.print_result(&created, || {
+            format!(
+                "Registered webhook {}. Its payloads are signed with this secret, which is not shown again:\n\n    {}\n\nThe {} header of a payload is like `t=<timestamp>,v1=<signature>`, where the signature is the hex encoded HMAC-SHA256 of `<timestamp>.<body>` with the secret.",
+                created.webhook.id.to_string().bold(),
+                created.secret.clone().bold(),
+                webhook::SIGNATURE_HEADER,
+            )
+        })?;
+
+        Ok(())
+    }
+
+    async fn webhooks_rm(&self, client: &Client, ids: Vec<Uuid>) -> Result<()> {
+        let mut removed = Vec::with_capacity(ids.len());
+        for id in ids {
+            removed.push(client.delete_webhook(self.ctx.project_name(), &id).await?);
+            self.print_message(format!("Removed webhook {}", id.to_string().bold()));
+        }
+
+        if self.output == OutputMode::Json {
+            println!("{}", serde_json::to_string_pretty(&removed)?);
+        }
+
+        Ok(())
+    }
+
+    async fn domains(&self, client: &Client) -> Result<()> {
+        let domains = client.get_domains(self.ctx.project_name()).await?;
+
+        self.print_result(&domains, || domain::get_table(&domains))?;
+
+        Ok(())
+    }
+
+    async fn access(&self, client: &Client) -> Result<()> {
+        let rules = client.get_access_rules(self.ctx.project_name()).await?;
+
+        self.print_result(&rules, || rules.to_string())?;
+
+        Ok(())
+    }
+
+    async fn access_set(&self, client: &Client, request: access::Request) -> Result<()> {
+        let rules = client
+            .set_access_rules(self.ctx.project_name(), &request)
+            .await?;
+
+        self.print_result(&rules, || {
+            format!(
+                "Set the rules for the clients of {}:\n{rules}",
+                rules.service
+            )
+        })?;
+
+        Ok(())
+    }
+
+    async fn access_clear(&self, client: &Client) -> Result<()> {
+        let rules = client.clear_access_rules(self.ctx.project_name()).await?;
+
+        self.print_result(&rules, || {
+            format!("Every client is let through to {} again.", rules.service)
+        })?;
+
+        Ok(())
+    }
+
+    async fn cache_purge(&self, client: &Client, path: Option<String>) -> Result<()> {
+        let purged = client
+            .purge_cache(self.ctx.project_name(), path.as_deref())
+            .await?;
+
+        self.print_result(&purged, || purged.to_string())?;
+
+        Ok(())
+    }
+
+    async fn domain_add(&self, client: &Client, fqdn: String) -> Result<()> {
+        self.print_message(format!(
+            "Requesting a certificate for {}, this can take a minute...",
+            fqdn.clone().bold()
+        ));
+
+        let added = client.add_domain(self.ctx.project_name(), &fqdn).await?;
+
+        self.print_result(&added, || {
+            format!(
+                "{} now serves {} over HTTPS. Its certificate is renewed before it expires on {}.",
+                added.fqdn.clone().bold(),
+                added.project_name,
+                added.certificate_expires_at.format("%Y-%m-%d"),
+            )
+        })?;
+
+        Ok(())
+    }
+
+    async fn maintenance_on(
+        &self,
+        client: &Client,
+        status: u16,
+        page: Option<PathBuf>,
+    ) -> Result<()> {
+        let page = page
+            .map(|path| {
+                read_to_string(&path)
+                    .with_context(|| format!("failed to read the page at {}", path.display()))
+            })
+            .transpose()?;
+
+        let maintenance = client
+            .set_maintenance(
+                self.ctx.project_name(),
+                &project::Maintenance { status, page },
+            )
+            .await?;
+
+        self.print_result(&maintenance, || {
+            format!(
+                "{} is under maintenance, its visitors get {} with a status of {}. Run `cargo shuttle project maintenance off` to serve it again.",
+                self.ctx.project_name(),
+                if maintenance.page.is_some() {
+                    "your page"
+                } else {
+                    "the maintenance page of shuttle"
+                },
+                maintenance.status,
+            )
+        })?;
+
+        Ok(())
+    }
+
+    async fn maintenance_off(&self, client: &Client) -> Result<()> {
+        let ended = client.end_maintenance(self.ctx.project_name()).await?;
+
+        self.print_result(&ended, || {
+            if ended.is_some() {
+                format!("{} is served again.", self.ctx.project_name())
+            } else {
+                format!("{} was not under maintenance.", self.ctx.project_name())
+            }
+        })?;
+
+        Ok(())
+    }
+
+    async fn tcp_expose(&self, client: &Client, port: u16) -> Result<()> {
+        let endpoint = client
+            .expose_tcp_port(self.ctx.project_name(), port)
+            .await?;
+
+        self.print_result(&endpoint, || {
+            format!(
+                "Clients of {} connect to {}, and are forwarded to port {} of your services. Run `cargo shuttle project tcp close {}` to stop forwarding them.",
+                self.ctx.project_name(),
+                endpoint.address,
+                endpoint.target_port,
+                endpoint.port,
+            )
+        })?;
+
+        Ok(())
+    }
+
+    async fn tcp_close(&self, client: &Client, port: u16) -> Result<()> {
+        let endpoint = client.close_tcp_port(self.ctx.project_name(), port).await?;
+
+        self.print_result(&endpoint, || {
+            format!(
+                "Stopped forwarding {} to port {} of your services.",
+                endpoint.address, endpoint.target_port
+            )
+        })?;
 
         Ok(())
     }
@@ -366,11 +1165,46 @@ impl Shuttle {
     async fn clean(&self, client: &Client) -> Result<()> {
         let lines = client.clean_project(self.ctx.project_name()).await?;
 
-        for line in lines {
-            println!("{line}");
+        self.print_result(&lines, || lines.join("\n"))?;
+        self.print_message("Cleaning done!");
+
+        Ok(())
+    }
+
+    async fn clean_local(&self) -> Result<()> {
+        let working_directory = self.ctx.working_directory();
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let mut removed_paths = 0;
+        let mut bytes_freed = 0;
+
+        // `cargo shuttle run` builds in either profile
+        for release_mode in [false, true] {
+            let summary = clean_crate(
+                working_directory,
+                release_mode,
+                tx.clone(),
+                CancellationToken::new(),
+            )
+            .await?;
+
+            removed_paths += summary.removed_paths.len();
+            bytes_freed += summary.bytes_freed;
         }
 
-        println!("Cleaning done!");
+        let mut lines: Vec<String> = rx
+            .try_iter()
+            .filter_map(|message| match message {
+                Message::TextLine(line) => Some(line),
+                _ => None,
+            })
+            .collect();
+        lines.push(format!(
+            "Removed {removed_paths} paths, freeing {}",
+            HumanBytes(bytes_freed)
+        ));
+
+        self.print_result(&lines, || lines.join("\n"))?;
+        self.print_message("Cleaning done!");
 
         Ok(())
     }
@@ -381,6 +1215,7 @@ impl Shuttle {
         id: Option<Uuid>,
         latest: bool,
         follow: bool,
+        query: LogsQuery,
     ) -> Result<()> {
         let id = if let Some(id) = id {
             id
@@ -389,7 +1224,9 @@ impl Shuttle {
 
             if latest {
                 // Find latest deployment (not always an active one)
-                let deployments = client.get_deployments(proj_name, 0, u32::MAX).await?;
+                let deployments = client
+                    .get_deployments(proj_name, 0, u32::MAX, &DeploymentsFilter::default())
+                    .await?;
                 let most_recent = deployments.last().context(format!(
                     "Could not find any deployments for '{proj_name}'. Try passing a deployment ID manually",
                 ))?;
@@ -406,38 +1243,83 @@ impl Shuttle {
         };
 
         if follow {
-            let mut stream = client.get_logs_ws(self.ctx.project_name(), &id).await?;
+            self.follow_logs(client, id, query).await?;
+        } else {
+            let logs = client
+                .get_logs(self.ctx.project_name(), &id, &query)
+                .await?;
+
+            for log in logs.iter() {
+                self.print_log(log)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Print the logs of a deployment as they come, connecting again when the connection drops
+    async fn follow_logs(&self, client: &Client, id: Uuid, mut query: LogsQuery) -> Result<()> {
+        let mut failed_attempts = 0;
+
+        loop {
+            let mut stream = match client
+                .get_logs_ws(self.ctx.project_name(), &id, &query)
+                .await
+            {
+                Ok(stream) => {
+                    failed_attempts = 0;
+                    stream
+                }
+                Err(error) if failed_attempts < MAX_RECONNECT_ATTEMPTS => {
+                    failed_attempts += 1;
+                    debug!(%error, failed_attempts, "failed to connect to the log stream");
+                    tokio::time::sleep(reconnect_delay(failed_attempts)).await;
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
 
             while let Some(Ok(msg)) = stream.next().await {
                 if let tokio_tungstenite::tungstenite::Message::Text(line) = msg {
                     let log_item: shuttle_common::LogItem =
                         serde_json::from_str(&line).expect("to parse log line");
-                    println!("{log_item}")
+                    self.print_log(&log_item)?;
+
+                    // A new connection only sends the logs which were not seen yet
+                    query.since = Some(log_item.timestamp);
+                    query.tail = None;
                 }
             }
-        } else {
-            let logs = client.get_logs(self.ctx.project_name(), &id).await?;
 
-            for log in logs.into_iter() {
-                println!("{log}");
-            }
+            eprintln!(
+                "{}",
+                "Lost the connection to the logs, reconnecting".yellow()
+            );
+            failed_attempts += 1;
+            tokio::time::sleep(reconnect_delay(failed_attempts)).await;
         }
-
-        Ok(())
     }
 
-    async fn deployments_list(&self, client: &Client, page: u32, limit: u32) -> Result<()> {
+    async fn deployments_list(
+        &self,
+        client: &Client,
+        page: u32,
+        limit: u32,
+        filter: DeploymentsFilter,
+    ) -> Result<()> {
         if limit == 0 {
-            println!();
-            return Ok(());
+            return self.print_result(&Vec::<deployment::Response>::new(), String::new);
         }
 
         let proj_name = self.ctx.project_name();
-        let deployments = client.get_deployments(proj_name, page, limit).await?;
-        let table = get_deployments_table(&deployments, proj_name.as_str(), page);
+        let deployments = client
+            .get_deployments(proj_name, page, limit, &filter)
+            .await?;
 
-        println!("{table}");
-        println!("Run `cargo shuttle logs <id>` to get logs for a given deployment.");
+        self.print_result(&deployments, || {
+            get_deployments_table(&deployments, proj_name.as_str(), page)
+        })?;
+        self.print_message("Run `cargo shuttle logs <id>` to get logs for a given deployment.");
 
         Ok(())
     }
@@ -447,7 +1329,75 @@ impl Shuttle {
             .get_deployment_details(self.ctx.project_name(), &deployment_id)
             .await?;
 
-        println!("{deployment}");
+        self.print_result(&deployment, || {
+            let mut details = deployment.to_string();
+            if let Some(trace_id) = &deployment.trace_id {
+                details.push_str(&format!("\n  trace ID: {trace_id}"));
+            }
+            for transition in &deployment.transitions {
+                details.push_str(&format!("\n  {transition}"));
+            }
+
+            details
+        })?;
+
+        Ok(())
+    }
+
+    async fn deployment_cancel(&self, client: &Client, deployment_id: Uuid) -> Result<()> {
+        let deployment = client
+            .cancel_deployment(self.ctx.project_name(), &deployment_id)
+            .await?;
+
+        self.print_message(format!("Cancelling deployment {}", deployment.id));
+        self.print_result(&deployment, || deployment.to_string())?;
+
+        Ok(())
+    }
+
+    async fn deployment_approve(&self, client: &Client, deployment_id: Uuid) -> Result<()> {
+        let deployment = client
+            .approve_deployment(self.ctx.project_name(), &deployment_id)
+            .await?;
+
+        self.print_message(format!("Approved deployment {}", deployment.id));
+        self.print_result(&deployment, || deployment.to_string())?;
+        self.print_message(format!(
+            "Run `cargo shuttle logs {}` to see how it starts.",
+            deployment.id
+        ));
+
+        Ok(())
+    }
+
+    async fn deployment_require_approval(
+        &self,
+        client: &Client,
+        required: Option<bool>,
+    ) -> Result<()> {
+        let approval = match required {
+            Some(required) => {
+                client
+                    .set_approval(self.ctx.project_name(), Approval { required })
+                    .await?
+            }
+            None => client.get_approval(self.ctx.project_name()).await?,
+        };
+
+        self.print_result(&approval, || approval.to_string())
+    }
+
+    async fn deployment_rollback(&self, client: &Client, to: Option<Uuid>) -> Result<()> {
+        let deployment = client
+            .rollback_service(self.ctx.project_name(), to.as_ref())
+            .await?;
+
+        self.print_message(format!("Rolling back to deployment {}", deployment.id));
+        self.print_result(&deployment, || deployment.to_string())?;
+        self.print_message(format!(
+            "Run `cargo shuttle logs {}` to see how it starts.",
+            deployment.id
+        ));
 
         Ok(())
     }
@@ -456,19 +1406,123 @@ impl Shuttle {
         let resources = client
             .get_service_resources(self.ctx.project_name())
             .await?;
-        let table = get_resources_table(&resources, self.ctx.project_name().as_str());
 
-        println!("{table}");
+        self.print_result(&resources, || {
+            get_resources_table(&resources, self.ctx.project_name().as_str())
+        })?;
+
+        Ok(())
+    }
+
+    async fn resource_delete(&self, client: &Client, resource_type: &str) -> Result<()> {
+        let confirmed = self.confirm(format!(
+            "Delete the {resource_type} resource of {}? Its data cannot be recovered",
+            self.ctx.project_name()
+        ))?;
+
+        if !confirmed {
+            return Ok(());
+        }
+
+        let resource = client
+            .delete_service_resource(self.ctx.project_name(), resource_type)
+            .await?;
+
+        self.print_message(format!("Deleted the {} resource", resource_type.bold()));
+
+        if self.output == OutputMode::Json {
+            println!("{}", serde_json::to_string_pretty(&resource)?);
+        }
 
         Ok(())
     }
 
+    async fn storage_usage(&self, client: &Client) -> Result<()> {
+        let usage = client.get_storage_usage(self.ctx.project_name()).await?;
+
+        self.print_result(&usage, || usage.to_string())
+    }
+
+    async fn persist_ls(&self, client: &Client) -> Result<()> {
+        let keys = client.get_persist_keys(self.ctx.project_name()).await?;
+
+        self.print_result(&keys, || persist::get_table(&keys))
+    }
+
+    async fn metrics(&self, client: &Client, args: MetricsArgs) -> Result<()> {
+        let metrics = client
+            .get_metrics(self.ctx.project_name(), Utc::now() - args.window)
+            .await?;
+
+        self.print_result(&metrics, || metrics_summary(&metrics))
+    }
+
+    async fn proxy(&self, client: &Client, args: ProxyArgs) -> Result<()> {
+        let project_name = self.ctx.project_name();
+
+        let target = match &args.resource {
+            Some(resource_type) => {
+                let resources = client.get_service_resources(project_name).await?;
+                if !resources
+                    .iter()
+                    .any(|resource| resource.r#type.to_string() == *resource_type)
+                {
+                    bail!("{project_name} has no {resource_type} resource, see `cargo shuttle resource list`");
+                }
+
+                format!("the {resource_type} resource of {project_name}")
+            }
+            None => {
+                let service = client.get_service(project_name).await?;
+                if service.deployment.is_none() {
+                    bail!("{project_name} is not running, deploy it with `cargo shuttle deploy`");
+                }
+
+                format!("the private port of {project_name}")
+            }
+        };
+
+        let listener = tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, args.port))
+            .await
+            .with_context(|| format!("failed to listen on port {}", args.port))?;
+        let address = listener.local_addr()?;
+
+        self.print_message(format!(
+            "Forwarding {} to {target}. Press Ctrl-C to stop.",
+            address.to_string().bold()
+        ));
+        if self.output == OutputMode::Json {
+            println!("{}", serde_json::json!({ "address": address }));
+        }
+
+        loop {
+            let connection = tokio::select! {
+                accepted = listener.accept() => accepted.context("failed to accept a connection")?.0,
+                _ = tokio::signal::ctrl_c() => return Ok(()),
+            };
+
+            let client = client.clone();
+            let project_name = project_name.clone();
+            let resource_type = args.resource.clone();
+            tokio::spawn(async move {
+                match client
+                    .get_tunnel_ws(&project_name, resource_type.as_deref())
+                    .await
+                {
+                    Ok(tunnel) => proxy::forward(connection, tunnel).await,
+                    Err(error) => eprintln!("Failed to open a tunnel: {error:#}"),
+                }
+            });
+        }
+    }
+
     async fn spin_local_runtime(
         run_args: &RunArgs,
         service: &BuiltService,
         provisioner_server: &JoinHandle<Result<(), tonic::transport::Error>>,
         i: u16,
         provisioner_port: u16,
+        label_width: Option<usize>,
     ) -> Result<
         Option<(
             Child,
@@ -560,6 +1614,10 @@ impl Shuttle {
             None,
             run_args.port - (1 + i),
             runtime_path,
+            None,
+            &Default::default(),
+            Stdio::inherit(),
+            Stdio::inherit(),
         )
         .await
         .map_err(|err| {
@@ -567,6 +1625,11 @@ impl Shuttle {
             err
         })?;
 
+        // Local runs are not in an environment of the project
+        let args = ShuttleToml::from_path(&working_directory.join("Shuttle.toml"))?
+            .run
+            .args;
+
         let service_name = service.service_name()?;
         let load_request = tonic::Request::new(LoadRequest {
             path: executable_path
@@ -576,6 +1639,7 @@ impl Shuttle {
             service_name: service_name.to_string(),
             resources: Default::default(),
             secrets,
+            args,
         });
 
         trace!("loading service");
@@ -612,10 +1676,21 @@ impl Shuttle {
             .await?
             .into_inner();
 
+        // The logs of several services are told apart by their names
+        let label = label_width.map(|width| {
+            let color = LOG_LABEL_COLORS[i as usize % LOG_LABEL_COLORS.len()];
+            format!("{:<width$} | ", service_name.as_str())
+                .with(color)
+                .to_string()
+        });
+
         tokio::spawn(async move {
             while let Ok(Some(log)) = stream.message().await {
                 let log: shuttle_common::LogItem = log.try_into().expect("to convert log");
-                println!("{log}");
+                match &label {
+                    Some(label) => println!("{label}{log}"),
+                    None => println!("{log}"),
+                }
             }
         });
 
@@ -710,9 +1785,8 @@ impl Shuttle {
         Ok(())
     }
 
-    async fn pre_local_run(&self, run_args: &RunArgs) -> Result<Vec<BuiltService>> {
-        trace!("starting a local run for a service: {run_args:?}");
-
+    /// Print the output of cargo sent to the returned channel while building
+    fn build_message_printer() -> crossbeam_channel::Sender<Message> {
         let (tx, rx): (crossbeam_channel::Sender<Message>, _) = crossbeam_channel::bounded(0);
         tokio::task::spawn_blocking(move || {
             while let Ok(message) = rx.recv() {
@@ -728,6 +1802,13 @@ impl Shuttle {
             }
         });
 
+        tx
+    }
+
+    async fn pre_local_run(&self, run_args: &RunArgs) -> Result<Vec<BuiltService>> {
+        trace!("starting a local run for a service: {run_args:?}");
+
+        let tx = Shuttle::build_message_printer();
         let working_directory = self.ctx.working_directory();
 
         trace!("building project");
@@ -737,13 +1818,53 @@ impl Shuttle {
             working_directory.display()
         );
 
+        // cargo runs in its own process group, so it has to be stopped for us when the user
+        // interrupts the build
+        let cancellation_token = CancellationToken::new();
+        let interrupt = tokio::spawn({
+            let cancellation_token = cancellation_token.clone();
+            async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    cancellation_token.cancel();
+                }
+            }
+        });
+
         // Compile all the alpha or shuttle-next services in the workspace.
-        build_workspace(working_directory, run_args.release, tx).await
+        let result = build_workspace(
+            working_directory,
+            run_args.release,
+            tx,
+            &BuildOptions {
+                package: run_args.package.clone(),
+                locked: run_args.locked,
+                offline: run_args.offline,
+                ..(&run_args.feature_args).into()
+            },
+            cancellation_token,
+        )
+        .await;
+
+        interrupt.abort();
+
+        result.map_err(|error| match error {
+            // The compiler errors were already printed while building
+            BuildError::Compile { status, .. } => {
+                Failure::Build(format!("Build failed. `cargo build` exited with {status}")).into()
+            }
+            error => error.into(),
+        })
     }
 
+    /// Start the provisioner for the resources of a local run, which uses the databases the
+    /// Shuttle.toml in `working_directory` points at instead of starting them in Docker
     async fn setup_local_provisioner(
+        working_directory: &Path,
     ) -> Result<(JoinHandle<Result<(), tonic::transport::Error>>, u16)> {
-        let provisioner = LocalProvisioner::new()?;
+        let overrides = ShuttleToml::from_path(&working_directory.join("Shuttle.toml"))?
+            .local
+            .resources;
+        let provisioner = LocalProvisioner::new(overrides)?;
         let provisioner_port =
             portpicker::pick_unused_port().expect("unable to find available port");
         let provisioner_server = provisioner.start(SocketAddr::new(
@@ -757,7 +1878,9 @@ impl Shuttle {
     #[cfg(target_family = "unix")]
     async fn local_run(&self, run_args: RunArgs) -> Result<()> {
         let services = Shuttle::pre_local_run(self, &run_args).await?;
-        let (provisioner_server, provisioner_port) = Shuttle::setup_local_provisioner().await?;
+        let label_width = log_label_width(&services);
+        let (provisioner_server, provisioner_port) =
+            Shuttle::setup_local_provisioner(self.ctx.working_directory()).await?;
         let mut sigterm_notif =
             tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
                 .expect("Can not get the SIGTERM signal receptor");
@@ -775,7 +1898,7 @@ impl Shuttle {
             // We must cover the case of starting multiple workspace services and receiving a signal in parallel.
             // This must stop all the existing runtimes and creating new ones.
             signal_received = tokio::select! {
-                res = Shuttle::spin_local_runtime(&run_args, service, &provisioner_server, i as u16, provisioner_port) => {
+                res = Shuttle::spin_local_runtime(&run_args, service, &provisioner_server, i as u16, provisioner_port, label_width) => {
                     Shuttle::add_runtime_info(res.unwrap(), &mut runtimes, &provisioner_server).await?;
                     false
                 },
@@ -866,45 +1989,379 @@ impl Shuttle {
         Ok(())
     }
 
-    #[cfg(target_family = "windows")]
-    async fn local_run(&self, run_args: RunArgs) -> Result<()> {
-        let services = Shuttle::pre_local_run(&self, &run_args).await?;
-        let (provisioner_server, provisioner_port) = Shuttle::setup_local_provisioner().await?;
+    #[cfg(target_family = "windows")]
+    async fn local_run(&self, run_args: RunArgs) -> Result<()> {
+        let services = Shuttle::pre_local_run(&self, &run_args).await?;
+        let label_width = log_label_width(&services);
+        let (provisioner_server, provisioner_port) =
+            Shuttle::setup_local_provisioner(self.ctx.working_directory()).await?;
+
+        // Start all the services.
+        let mut runtimes: Vec<(
+            Child,
+            RuntimeClient<ClaimService<InjectPropagation<Channel>>>,
+        )> = Vec::new();
+        for (i, service) in services.iter().enumerate() {
+            Shuttle::add_runtime_info(
+                Shuttle::spin_local_runtime(
+                    &run_args,
+                    service,
+                    &provisioner_server,
+                    i as u16,
+                    provisioner_port,
+                    label_width,
+                )
+                .await?,
+                &mut runtimes,
+                &provisioner_server,
+            )
+            .await?;
+        }
+
+        for (mut rt, _) in runtimes {
+            println!(
+                "a service future completed with exit status: {:?}",
+                rt.wait().await?.code()
+            );
+        }
+
+        println!(
+            "Run `cargo shuttle project start` to create a project environment on Shuttle.\n\
+             Run `cargo shuttle deploy` to deploy your Shuttle service."
+        );
+
+        Ok(())
+    }
+
+    /// Run the services like [Shuttle::local_run], but rebuild and restart the ones affected by
+    /// every change to the project until the user stops it. The local provisioner keeps running
+    /// in between, so the services get the same resources again.
+    async fn local_run_watch(&self, run_args: RunArgs) -> Result<()> {
+        let (provisioner_server, provisioner_port) =
+            Shuttle::setup_local_provisioner(self.ctx.working_directory()).await?;
+
+        // The builder reports its builds on a blocking channel
+        let (events_tx, events_rx) = crossbeam_channel::unbounded();
+        let (forward_tx, mut events) = tokio::sync::mpsc::unbounded_channel();
+        tokio::task::spawn_blocking(move || {
+            while let Ok(event) = events_rx.recv() {
+                if forward_tx.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let cancellation_token = CancellationToken::new();
+        let watch = tokio::spawn({
+            let working_directory = self.ctx.working_directory().to_path_buf();
+            let release = run_args.release;
+            let options = BuildOptions {
+                package: run_args.package.clone(),
+                locked: run_args.locked,
+                offline: run_args.offline,
+                ..(&run_args.feature_args).into()
+            };
+            let cancellation_token = cancellation_token.clone();
+
+            async move {
+                build_workspace_watch(
+                    &working_directory,
+                    release,
+                    Shuttle::build_message_printer(),
+                    events_tx,
+                    &options,
+                    cancellation_token,
+                )
+                .await
+            }
+        });
+
+        println!(
+            "{} {}",
+            "    Watching".bold().green(),
+            self.ctx.working_directory().display()
+        );
+
+        // Every service keeps its ports when it is restarted
+        let mut indices: HashMap<String, u16> = HashMap::new();
+        let mut runtimes: HashMap<
+            String,
+            (
+                Child,
+                RuntimeClient<ClaimService<InjectPropagation<Channel>>>,
+            ),
+        > = HashMap::new();
+        let mut build_started = std::time::Instant::now();
+        let mut label_width: Option<usize> = None;
+
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => event,
+                _ = tokio::signal::ctrl_c() => {
+                    println!("cargo-shuttle received SIGINT. Killing all the runtimes...");
+                    None
+                }
+            };
+
+            // The watch ends on its own when it fails to start
+            let Some(event) = event else {
+                break;
+            };
+
+            match event {
+                BuildEvent::Started { packages } => {
+                    build_started = std::time::Instant::now();
+                    println!("{} {}", "    Building".bold().green(), packages.join(", "));
+                }
+                BuildEvent::Failed(error) => {
+                    match error {
+                        // The compiler errors were already printed while building
+                        BuildError::Compile { status, .. } => {
+                            println!(
+                                "{} `cargo build` exited with {status}",
+                                "Build failed.".red()
+                            )
+                        }
+                        error => println!("{} {error}", "Build failed:".red()),
+                    }
+                    println!("The running services are kept until the next change.\n");
+                }
+                BuildEvent::Finished(services) => {
+                    // The first build is of every service, so it is known whether there are several
+                    if let Some(width) = log_label_width(&services) {
+                        label_width = Some(label_width.unwrap_or_default().max(width));
+                    }
+
+                    let names: Vec<_> = services
+                        .iter()
+                        .map(|service| service.package_name.as_str())
+                        .collect();
+                    println!(
+                        "{} {} in {:.2}s",
+                        "     Rebuilt".bold().green(),
+                        names.join(", "),
+                        build_started.elapsed().as_secs_f64()
+                    );
+
+                    for service in services {
+                        if let Some((mut runtime, mut runtime_client)) =
+                            runtimes.remove(&service.package_name)
+                        {
+                            Shuttle::stop_runtime(&mut runtime, &mut runtime_client)
+                                .await
+                                .unwrap_or_else(|err| {
+                                    trace!(status = ?err, "stopping the runtime errored out");
+                                });
+                            // The ports of the service are only free again once its runtime is gone
+                            let _ = runtime.kill().await;
+                        }
+
+                        let next_index = indices.len() as u16;
+                        let index = *indices
+                            .entry(service.package_name.clone())
+                            .or_insert(next_index);
+
+                        match Shuttle::spin_local_runtime(
+                            &run_args,
+                            &service,
+                            &provisioner_server,
+                            index,
+                            provisioner_port,
+                            label_width,
+                        )
+                        .await?
+                        {
+                            Some(runtime) => {
+                                runtimes.insert(service.package_name, runtime);
+                            }
+                            None => println!(
+                                "{} is not running until the next change.\n",
+                                service.package_name
+                            ),
+                        }
+                    }
+                }
+            }
+        }
+
+        cancellation_token.cancel();
+        let result = watch.await?;
+
+        provisioner_server.abort();
+        for (mut runtime, mut runtime_client) in runtimes.into_values() {
+            Shuttle::stop_runtime(&mut runtime, &mut runtime_client)
+                .await
+                .unwrap_or_else(|err| {
+                    trace!(status = ?err, "stopping the runtime errored out");
+                });
+        }
+
+        Ok(result?)
+    }
+
+    /// Print how the deployer would build the service, by checking the project the same way
+    async fn deploy_dry_run(&self, args: DeployArgs) -> Result<()> {
+        // Loading the workspace reports nothing worth showing
+        let (tx, _rx) = crossbeam_channel::unbounded();
+
+        let plan = plan_workspace(
+            self.ctx.working_directory(),
+            true,
+            tx,
+            &deploy_build_options(&args),
+        )
+        .await?;
+
+        self.print_result(&plan, || plan.to_string().trim_end().to_string())?;
+
+        Ok(())
+    }
+
+    /// List the files the project would be uploaded with, without deploying it
+    async fn deploy_show_archive(&self, args: DeployArgs) -> Result<()> {
+        let artifact = match &args.artifact {
+            Some(path) => Some((path.as_path(), self.describe_artifact(&args, path).await?)),
+            None => None,
+        };
+        let data = self.make_archive(
+            artifact.as_ref().map(|(path, metadata)| (*path, metadata)),
+            args.compression,
+        )?;
+
+        let entries = preview::archive_entries(&data)?;
+
+        self.print_result(&entries, || {
+            let mut output = String::new();
+            for entry in &entries {
+                let _ = writeln!(output, "{:>10}  {}", HumanBytes(entry.size), entry.path);
+            }
+            let _ = write!(
+                output,
+                "{} files, {} compressed",
+                entries.len(),
+                HumanBytes(data.len() as u64)
+            );
+
+            output
+        })?;
+
+        Ok(())
+    }
+
+    /// Upload the archive `data` in chunks while showing the progress, resuming where an earlier
+    /// upload of the same archive stopped. Returns the hash to deploy it with.
+    async fn upload_archive(&self, client: &Client, data: &[u8]) -> Result<String> {
+        let hash = format!("{:x}", Sha256::digest(data));
+        let project_name = self.ctx.project_name();
+        let size = data.len() as u64;
+
+        let mut received = client.get_upload(project_name, &hash).await?.received;
+        if received > 0 {
+            debug!(received, "resuming an earlier upload");
+        }
+
+        let progress_bar = create_upload_progress_bar(size);
+        let mut failures = 0;
+
+        while received < size {
+            progress_bar.set_position(received);
+
+            let end = (received + UPLOAD_CHUNK_SIZE).min(size);
+            let chunk = data[received as usize..end as usize].to_vec();
+
+            match client
+                .upload_chunk(project_name, &hash, received, chunk)
+                .await
+            {
+                Ok(upload) => {
+                    received = upload.received;
+                    failures = 0;
+                }
+                Err(error) if failures < MAX_UPLOAD_RETRIES => {
+                    failures += 1;
+                    debug!(%error, failures, "failed to upload a chunk, resuming the upload");
+                    tokio::time::sleep(std::time::Duration::from_secs(failures)).await;
+
+                    // Part of the chunk may have arrived before the upload failed
+                    if let Ok(upload) = client.get_upload(project_name, &hash).await {
+                        received = upload.received;
+                    }
+                }
+                Err(error) => {
+                    progress_bar.abandon();
+                    return Err(error.context(
+                        "failed to upload the project, deploy again to resume the upload",
+                    ));
+                }
+            }
+        }
+
+        progress_bar.finish_and_clear();
+
+        Ok(hash)
+    }
+
+    /// Show what deploying would change, and ask whether to go on with it
+    async fn deploy_preview(
+        &self,
+        client: &Client,
+        args: &DeployArgs,
+        source_hash: &str,
+    ) -> Result<bool> {
+        // Loading the workspace reports nothing worth showing
+        let (tx, _rx) = crossbeam_channel::unbounded();
+        let plan = plan_workspace(
+            self.ctx.working_directory(),
+            true,
+            tx,
+            &deploy_build_options(args),
+        )
+        .await?;
+
+        let mut services = Vec::new();
+        let mut declared_resources = Vec::new();
+        for service in plan.steps.iter().flat_map(|step| &step.services) {
+            services.push(service.package_name.clone());
 
-        // Start all the services.
-        let mut runtimes: Vec<(
-            Child,
-            RuntimeClient<ClaimService<InjectPropagation<Channel>>>,
-        )> = Vec::new();
-        for (i, service) in services.iter().enumerate() {
-            Shuttle::add_runtime_info(
-                Shuttle::spin_local_runtime(
-                    &run_args,
-                    service,
-                    &provisioner_server,
-                    i as u16,
-                    provisioner_port,
-                )
-                .await?,
-                &mut runtimes,
-                &provisioner_server,
-            )
-            .await?;
+            if let Some(directory) = service.manifest_path.parent() {
+                declared_resources.extend(preview::declared_resources(directory)?);
+            }
         }
 
-        for (mut rt, _) in runtimes {
-            println!(
-                "a service future completed with exit status: {:?}",
-                rt.wait().await?.code()
-            );
-        }
+        // There is nothing live to compare with before the first deployment
+        let (running, live_secrets, live_resources) =
+            match client.get_service(self.ctx.project_name()).await {
+                Ok(service) => (
+                    service.deployment,
+                    client.get_secrets(self.ctx.project_name()).await?,
+                    client
+                        .get_service_resources(self.ctx.project_name())
+                        .await?,
+                ),
+                Err(error) => {
+                    debug!(%error, "failed to get the service, assuming it was never deployed");
+                    (None, Vec::new(), Vec::new())
+                }
+            };
 
-        println!(
-            "Run `cargo shuttle project start` to create a project environment on Shuttle.\n\
-             Run `cargo shuttle deploy` to deploy your Shuttle service."
+        let local_secrets =
+            preview::local_secrets(&self.ctx.working_directory().join("Secrets.toml"))?;
+
+        let preview = preview::Preview::new(
+            services,
+            source_hash.to_string(),
+            running.as_ref(),
+            &local_secrets,
+            &live_secrets,
+            &declared_resources,
+            &live_resources,
         );
+        self.print_result(&preview, || preview.to_string().trim_end().to_string())?;
 
-        Ok(())
+        self.confirm(format!(
+            "Deploy these changes to {}?",
+            self.ctx.project_name()
+        ))
     }
 
     async fn deploy(&self, client: &Client, args: DeployArgs) -> Result<CommandOutcome> {
@@ -912,43 +2369,146 @@ impl Shuttle {
             self.is_dirty()?;
         }
 
-        let data = self.make_archive()?;
+        let artifact = match &args.artifact {
+            Some(path) => Some((path.as_path(), self.describe_artifact(&args, path).await?)),
+            None => None,
+        };
+        let data = self.make_archive(
+            artifact.as_ref().map(|(path, metadata)| (*path, metadata)),
+            args.compression,
+        )?;
+        let source_hash = preview::source_hash(&data)?;
+
+        if args.preview && !self.deploy_preview(client, &args, &source_hash).await? {
+            return Ok(CommandOutcome::Ok);
+        }
+
+        // The progress of the deployment is shown when someone is watching, and its logs otherwise
+        let mut progress = (!args.verbose && self.output == OutputMode::Table && stdout().is_tty())
+            .then(DeployProgress::default);
+
+        let upload_started = std::time::Instant::now();
+        let upload = self.upload_archive(client, &data).await?;
+        if let Some(progress) = &progress {
+            progress.done(Phase::Upload, upload_started.elapsed());
+        }
+        let git = GitMetadata::discover(self.ctx.working_directory());
 
         let deployment = client
-            .deploy(data, self.ctx.project_name(), args.no_test)
+            .deploy(
+                &upload,
+                self.ctx.project_name(),
+                &args,
+                git.as_ref(),
+                &source_hash,
+            )
             .await?;
 
+        let mut query = LogsQuery::default();
+        // Whether the deployment got past building, to tell build failures from crashes
+        let mut built = false;
+        // When the service started running, after which it is waited on to become healthy
+        let mut running_since: Option<std::time::Instant> = None;
         let mut stream = client
-            .get_logs_ws(self.ctx.project_name(), &deployment.id)
+            .get_logs_ws(self.ctx.project_name(), &deployment.id, &query)
             .await?;
 
         loop {
-            let message = stream.next().await;
+            let message = match running_since {
+                Some(since) => {
+                    match tokio::time::timeout(
+                        HEALTHY_TIMEOUT.saturating_sub(since.elapsed()),
+                        stream.next(),
+                    )
+                    .await
+                    {
+                        Ok(message) => message,
+                        // Deployers from before health checks never report services as healthy
+                        Err(_) => break,
+                    }
+                }
+                None => stream.next().await,
+            };
+
             if let Some(Ok(msg)) = message {
                 if let tokio_tungstenite::tungstenite::Message::Text(line) = msg {
                     let log_item: shuttle_common::LogItem =
                         serde_json::from_str(&line).expect("to parse log line");
+                    query.since = Some(log_item.timestamp);
+
+                    let phase = log_item.phase();
+                    match (&mut progress, phase) {
+                        (Some(progress), Some(phase)) => progress.enter(phase),
+                        (Some(progress), None) => progress.log(&log_item),
+                        (None, _) => self.print_message(&log_item),
+                    }
 
-                    match log_item.state.clone() {
+                    if phase == Some(Phase::Healthy) {
+                        break;
+                    }
+
+                    match log_item.state {
                         shuttle_common::deployment::State::Queued
-                        | shuttle_common::deployment::State::Building
-                        | shuttle_common::deployment::State::Built
+                        | shuttle_common::deployment::State::Building => {}
+                        shuttle_common::deployment::State::Built
                         | shuttle_common::deployment::State::Loading => {
-                            println!("{log_item}");
+                            built = true;
+                        }
+                        shuttle_common::deployment::State::Running => {
+                            built = true;
+                            running_since.get_or_insert_with(std::time::Instant::now);
                         }
                         shuttle_common::deployment::State::Crashed => {
-                            println!();
-                            println!("{}", "Deployment crashed".red());
-                            println!();
-                            println!("Run the following for more details");
-                            println!();
-                            print!("cargo shuttle logs {}", &deployment.id);
-                            println!();
-
-                            return Ok(CommandOutcome::DeploymentFailure);
+                            if let Some(progress) = progress.take() {
+                                progress.fail();
+                            }
+
+                            self.print_message("");
+                            self.print_message("Deployment crashed".red());
+                            self.print_message("");
+                            self.print_message("Run the following for more details");
+                            self.print_message("");
+
+                            let deployment = client
+                                .get_deployment_details(self.ctx.project_name(), &deployment.id)
+                                .await?;
+                            self.print_result(&deployment, || {
+                                format!("cargo shuttle logs {}", &deployment.id)
+                            })?;
+
+                            return Ok(if built {
+                                CommandOutcome::DeploymentFailure
+                            } else {
+                                CommandOutcome::BuildFailure
+                            });
+                        }
+                        shuttle_common::deployment::State::Cancelled => {
+                            if let Some(progress) = progress.take() {
+                                progress.fail();
+                            }
+
+                            self.print_message("");
+                            self.print_message("Deployment was cancelled".yellow());
+
+                            return Ok(CommandOutcome::BuildFailure);
                         }
-                        shuttle_common::deployment::State::Running
-                        | shuttle_common::deployment::State::Completed
+                        shuttle_common::deployment::State::PendingApproval => {
+                            if let Some(progress) = progress.take() {
+                                progress.finish();
+                            }
+
+                            self.print_message("");
+                            self.print_message("Deployment is pending approval".yellow());
+                            self.print_message("");
+                            self.print_message("Run the following to let it run");
+                            self.print_message("");
+                            self.print_result(&deployment, || {
+                                format!("cargo shuttle deployment approve {}", &deployment.id)
+                            })?;
+
+                            return Ok(CommandOutcome::Ok);
+                        }
+                        shuttle_common::deployment::State::Completed
                         | shuttle_common::deployment::State::Stopped
                         | shuttle_common::deployment::State::Unknown => {
                             break;
@@ -956,16 +2516,22 @@ impl Shuttle {
                     };
                 }
             } else {
-                println!("Reconnecting websockets logging");
+                if progress.is_none() {
+                    self.print_message("Reconnecting websockets logging");
+                }
                 // A wait time short enough for not much state to have changed, long enough that
                 // the terminal isn't completely spammed
                 tokio::time::sleep(std::time::Duration::from_millis(100)).await;
                 stream = client
-                    .get_logs_ws(self.ctx.project_name(), &deployment.id)
+                    .get_logs_ws(self.ctx.project_name(), &deployment.id, &query)
                     .await?;
             }
         }
 
+        if let Some(progress) = progress {
+            progress.finish();
+        }
+
         // Temporary fix.
         // TODO: Make get_service_summary endpoint wait for a bit and see if it entered Running/Crashed state.
         tokio::time::sleep(std::time::Duration::from_millis(500)).await;
@@ -983,44 +2549,86 @@ impl Shuttle {
                 .await?;
             let resources = get_resources_table(&resources, self.ctx.project_name().as_str());
 
-            println!("{resources}{service}");
+            self.print_result(&deployment, || format!("{resources}{service}"))?;
 
             Ok(CommandOutcome::Ok)
         } else {
-            println!("{}", "Deployment has not entered the running state".red());
-            println!();
+            self.print_message("Deployment has not entered the running state".red());
+            self.print_message("");
 
             match deployment.state {
                 shuttle_common::deployment::State::Stopped => {
-                    println!("State: Stopped - Deployment was running, but has been stopped by the user.")
+                    self.print_message("State: Stopped - Deployment was running, but has been stopped by the user.")
                 }
                 shuttle_common::deployment::State::Completed => {
-                    println!("State: Completed - Deployment was running, but stopped running all by itself.")
+                    self.print_message("State: Completed - Deployment was running, but stopped running all by itself.")
                 }
                 shuttle_common::deployment::State::Unknown => {
-                    println!("State: Unknown - Deployment was in an unknown state. We never expect this state and entering this state should be considered a bug.")
+                    self.print_message("State: Unknown - Deployment was in an unknown state. We never expect this state and entering this state should be considered a bug.")
                 }
                 shuttle_common::deployment::State::Crashed => {
-                    println!(
-                        "{}",
-                        "State: Crashed - Deployment crashed after startup.".red()
-                    );
+                    self.print_message("State: Crashed - Deployment crashed after startup.".red());
+                }
+                shuttle_common::deployment::State::Cancelled => {
+                    self.print_message("State: Cancelled - Deployment was cancelled by the user before it was built.")
                 }
-                _ => println!(
+                _ => self.print_message(
                     "Deployment encountered an unexpected error - Please create a ticket to report this."
                 ),
             }
 
-            println!();
-            println!("Run the following for more details");
-            println!();
-            println!("cargo shuttle logs {}", &deployment.id);
-            println!();
+            self.print_message("");
+            self.print_message("Run the following for more details");
+            self.print_message("");
+            self.print_result(&deployment, || {
+                format!("cargo shuttle logs {}\n", &deployment.id)
+            })?;
 
             Ok(CommandOutcome::DeploymentFailure)
         }
     }
 
+    /// Make sure `artifact` can be of the service which would be deployed, and describe it for the
+    /// deployer
+    async fn describe_artifact(
+        &self,
+        args: &DeployArgs,
+        artifact: &Path,
+    ) -> Result<ArtifactMetadata> {
+        if !artifact.is_file() {
+            bail!("The artifact `{}` does not exist", artifact.display());
+        }
+
+        // Loading the workspace reports nothing worth showing
+        let (tx, _rx) = crossbeam_channel::unbounded();
+        let plan = plan_workspace(
+            self.ctx.working_directory(),
+            true,
+            tx,
+            &BuildOptions {
+                single_service: true,
+                ..deploy_build_options(args)
+            },
+        )
+        .await?;
+        let metadata = ArtifactMetadata::new(&plan, args.target.clone()).await?;
+
+        let is_wasm_module = artifact
+            .extension()
+            .map_or(false, |extension| extension == "wasm");
+        match (metadata.is_wasm, is_wasm_module) {
+            (true, false) => bail!(
+                "`{}` is a shuttle-next service, so its artifact has to be a `.wasm` module",
+                metadata.package_name
+            ),
+            (false, true) => bail!(
+                "`{}` is an alpha service, so its artifact has to be an executable",
+                metadata.package_name
+            ),
+            _ => Ok(metadata),
+        }
+    }
+
     async fn project_create(&self, client: &Client, idle_minutes: u64) -> Result<()> {
         let config = project::Config { idle_minutes };
 
@@ -1036,7 +2644,7 @@ impl Shuttle {
             client,
         )
         .await?;
-        println!("Run `cargo shuttle deploy` to deploy your Shuttle service.");
+        self.print_message("Run `cargo shuttle deploy` to deploy your Shuttle service.");
 
         Ok(())
     }
@@ -1048,16 +2656,31 @@ impl Shuttle {
         Ok(())
     }
 
+    async fn project_restart(&self, client: &Client) -> Result<()> {
+        self.wait_with_spinner(
+            &[
+                project::State::Ready,
+                project::State::Errored {
+                    message: Default::default(),
+                },
+            ],
+            client.restart_project(self.ctx.project_name()),
+            self.ctx.project_name(),
+            client,
+        )
+        .await?;
+
+        Ok(())
+    }
+
     async fn projects_list(&self, client: &Client, page: u32, limit: u32) -> Result<()> {
         if limit == 0 {
-            println!();
-            return Ok(());
+            return self.print_result(&Vec::<project::Response>::new(), String::new);
         }
 
         let projects = client.get_projects_list(page, limit).await?;
-        let projects_table = project::get_table(&projects, page);
 
-        println!("{projects_table}");
+        self.print_result(&projects, || project::get_table(&projects, page))?;
 
         Ok(())
     }
@@ -1079,7 +2702,7 @@ impl Shuttle {
             .await?;
         } else {
             let project = client.get_project(self.ctx.project_name()).await?;
-            println!("{project}");
+            self.print_result(&project, || project.to_string())?;
         }
 
         Ok(())
@@ -1098,7 +2721,9 @@ impl Shuttle {
             client,
         )
         .await?;
-        println!("Run `cargo shuttle project start` to recreate project environment on Shuttle.");
+        self.print_message(
+            "Run `cargo shuttle project start` to recreate project environment on Shuttle.",
+        );
 
         Ok(())
     }
@@ -1125,23 +2750,29 @@ impl Shuttle {
             project = client.get_project(project_name).await?;
         }
         progress_bar.finish_and_clear();
-        println!("{project}");
+        self.print_result(&project, || project.to_string())?;
         Ok(())
     }
 
-    fn make_archive(&self) -> Result<Vec<u8>> {
-        let encoder = GzEncoder::new(Vec::new(), Compression::fast());
-        let mut tar = Builder::new(encoder);
+    /// Pack the project to deploy it, along with the `artifact` to run instead of building it
+    fn make_archive(
+        &self,
+        artifact: Option<(&Path, &ArtifactMetadata)>,
+        compression: ArchiveCompression,
+    ) -> Result<Vec<u8>> {
+        let mut tar = Builder::new(Vec::new());
 
         let working_directory = self.ctx.working_directory();
         let base_directory = working_directory
             .parent()
             .context("get parent directory of crate")?;
 
-        // Make sure the target folder is excluded at all times
+        // Make sure the target folder and the git repository are excluded at all times
         let overrides = OverrideBuilder::new(working_directory)
             .add("!target/")
             .context("add `!target/` override")?
+            .add("!.git/")
+            .context("add `!.git/` override")?
             .build()
             .context("build an override")?;
 
@@ -1151,6 +2782,9 @@ impl Shuttle {
 
         for dir_entry in WalkBuilder::new(working_directory)
             .hidden(false)
+            // Projects which are not in a git repository have their .gitignore respected too
+            .require_git(false)
+            .add_custom_ignore_filename(SHUTTLE_IGNORE_FILENAME)
             .overrides(overrides)
             .build()
         {
@@ -1185,14 +2819,62 @@ impl Shuttle {
             entries.insert(secrets_path, Path::new("shuttle").join("Secrets.toml"));
         }
 
+        // Path dependencies from outside the project are packed with it
+        let vendored = vendor::vendor_external_packages(working_directory)?;
+        let project_directory = working_directory
+            .strip_prefix(base_directory)
+            .context("strip the base of the project directory")?;
+
+        for (k, v) in vendored.files {
+            entries.insert(k, project_directory.join(v));
+        }
+
+        let manifests: HashMap<PathBuf, String> = vendored
+            .manifests
+            .into_iter()
+            .map(|(path, manifest)| (project_directory.join(path), manifest))
+            .collect();
+
         // Append all the entries to the archive.
         for (k, v) in entries {
-            debug!("Packing {k:?}");
-            tar.append_path_with_name(k, v)?;
+            if let Some(manifest) = manifests.get(&v) {
+                debug!("Packing rewritten {k:?}");
+                let mut header = tar::Header::new_gnu();
+                header.set_size(manifest.len() as u64);
+                header.set_mode(0o644);
+                tar.append_data(&mut header, v, manifest.as_bytes())?;
+            } else {
+                debug!("Packing {k:?}");
+                tar.append_path_with_name(k, v)?;
+            }
         }
 
-        let encoder = tar.into_inner().context("get encoder from tar archive")?;
-        let bytes = encoder.finish().context("finish up encoder")?;
+        if let Some((path, metadata)) = artifact {
+            debug!("Packing the artifact {path:?}");
+            tar.append_path_with_name(path, project_directory.join(ARTIFACT_PATH))?;
+
+            let metadata = serde_json::to_vec(metadata)?;
+            let mut header = tar::Header::new_gnu();
+            header.set_size(metadata.len() as u64);
+            header.set_mode(0o644);
+            tar.append_data(
+                &mut header,
+                project_directory.join(ARTIFACT_METADATA_PATH),
+                metadata.as_slice(),
+            )?;
+        }
+
+        let tar = tar.into_inner().context("get data from tar archive")?;
+        let bytes = match compression {
+            ArchiveCompression::Zstd => {
+                zstd::encode_all(tar.as_slice(), 0).context("compress the archive")?
+            }
+            ArchiveCompression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+                encoder.write_all(&tar).context("compress the archive")?;
+                encoder.finish().context("finish up encoder")?
+            }
+        };
 
         Ok(bytes)
     }
@@ -1294,6 +2976,116 @@ fn check_version(runtime_path: &Path) -> Result<()> {
     }
 }
 
+/// The options the deployer builds the service of `args` with
+fn deploy_build_options(args: &DeployArgs) -> BuildOptions {
+    BuildOptions {
+        package: args.package.clone(),
+        locked: args.locked,
+        offline: args.offline,
+        ..(&args.feature_args).into()
+    }
+}
+
+/// How wide the labels in front of the logs of `services` have to be to line up, when there are
+/// several of them to tell apart
+fn log_label_width(services: &[BuiltService]) -> Option<usize> {
+    if services.len() < 2 {
+        return None;
+    }
+
+    services
+        .iter()
+        .filter_map(|service| service.service_name().ok())
+        .map(|name| name.as_str().len())
+        .max()
+}
+
+/// How long to wait before connecting again after `failed_attempts` attempts failed, doubling
+/// every time up to half a minute
+fn reconnect_delay(failed_attempts: u32) -> std::time::Duration {
+    std::time::Duration::from_millis(500 * 2u64.pow(failed_attempts.min(6)))
+        .min(MAX_RECONNECT_DELAY)
+}
+
+/// Ask for an API key to be pasted from the shuttle website
+/// Summarize the metrics of a deployment, with the trend of its memory to spot leaks
+fn metrics_summary(metrics: &metrics::Response) -> String {
+    let mut summary = format!(
+        "Metrics of deployment {} since {}\n\n",
+        metrics.deployment_id,
+        metrics.since.format("%Y-%m-%dT%H:%M:%SZ")
+    );
+
+    let (Some(cpu_average), Some(cpu_peak), Some(memory_latest), Some(memory_peak)) = (
+        metrics.cpu_average(),
+        metrics.cpu_peak(),
+        metrics.memory_latest(),
+        metrics.memory_peak(),
+    ) else {
+        summary.push_str("No samples were taken yet, they are taken every 15 seconds");
+        return summary;
+    };
+
+    let memory: Vec<u64> = metrics.samples.iter().map(|sample| sample.memory).collect();
+    let _ = writeln!(
+        summary,
+        "{:<10}{cpu_average:.1}% average, {cpu_peak:.1}% peak",
+        "CPU".bold()
+    );
+    let _ = writeln!(
+        summary,
+        "{:<10}{} now, {} peak  {}",
+        "Memory".bold(),
+        HumanBytes(memory_latest),
+        HumanBytes(memory_peak),
+        sparkline(&memory, 40).cyan()
+    );
+    let _ = writeln!(summary, "{:<10}{}", "Requests".bold(), metrics.requests());
+    let _ = write!(summary, "{:<10}{}", "Restarts".bold(), metrics.restarts);
+
+    summary
+}
+
+/// Draw `values` as a line of at most `width` bars, averaging the values which share a bar
+fn sparkline(values: &[u64], width: usize) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    if values.is_empty() || width == 0 {
+        return String::new();
+    }
+
+    let chunk = (values.len() + width - 1) / width;
+    let points: Vec<f64> = values
+        .chunks(chunk)
+        .map(|chunk| chunk.iter().sum::<u64>() as f64 / chunk.len() as f64)
+        .collect();
+    let min = points.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = points.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    points
+        .into_iter()
+        .map(|point| {
+            if max == min {
+                BARS[0]
+            } else {
+                BARS[((point - min) / (max - min) * (BARS.len() - 1) as f64).round() as usize]
+            }
+        })
+        .collect()
+}
+
+fn prompt_api_key() -> Result<ApiKey> {
+    let _ = webbrowser::open(SHUTTLE_LOGIN_URL);
+    println!("If your browser did not automatically open, go to {SHUTTLE_LOGIN_URL}");
+
+    let api_key = Password::with_theme(&ColorfulTheme::default())
+        .with_prompt("API key")
+        .validate_with(|input: &String| ApiKey::parse(input).map(|_| {}))
+        .interact()?;
+
+    ApiKey::parse(&api_key)
+}
+
 fn create_spinner() -> ProgressBar {
     let pb = indicatif::ProgressBar::new_spinner();
     pb.enable_steady_tick(std::time::Duration::from_millis(350));
@@ -1318,11 +3110,44 @@ fn create_spinner() -> ProgressBar {
     pb
 }
 
+fn create_upload_progress_bar(size: u64) -> ProgressBar {
+    let pb = indicatif::ProgressBar::new(size);
+    pb.set_style(
+        indicatif::ProgressStyle::with_template(
+            "Uploading [{bar:40.orange}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+        )
+        .unwrap()
+        .progress_chars("=> "),
+    );
+
+    pb
+}
+
 pub enum CommandOutcome {
     Ok,
+    /// The project could not be built by shuttle
+    BuildFailure,
+    /// The project was built, but did not start running
     DeploymentFailure,
 }
 
+impl CommandOutcome {
+    /// The exit code for this outcome, which shells can chain commands on with `&&`
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CommandOutcome::Ok => 0,
+            CommandOutcome::BuildFailure => EXIT_BUILD_FAILURE,
+            CommandOutcome::DeploymentFailure => EXIT_DEPLOYMENT_FAILURE,
+        }
+    }
+}
+
+/// Whether this runs in CI, which CI services like GitHub Actions tell with the `CI` environment
+/// variable
+fn is_ci() -> bool {
+    std::env::var("CI").map_or(false, |ci| !matches!(ci.as_str(), "" | "0" | "false"))
+}
+
 #[cfg(test)]
 mod tests {
     use flate2::read::GzDecoder;
@@ -1330,10 +3155,11 @@ mod tests {
     use tar::Archive;
     use tempfile::TempDir;
 
-    use crate::args::ProjectArgs;
-    use crate::Shuttle;
+    use crate::args::{ArchiveCompression, ProjectArgs};
+    use crate::{sparkline, Shuttle};
     use std::fs::{self, canonicalize};
-    use std::path::PathBuf;
+    use std::io::Read;
+    use std::path::{Path, PathBuf};
     use std::str::FromStr;
 
     pub fn path_from_workspace_root(path: &str) -> PathBuf {
@@ -1348,7 +3174,9 @@ mod tests {
         let mut shuttle = Shuttle::new().unwrap();
         shuttle.load_project(&mut project_args).unwrap();
 
-        let archive = shuttle.make_archive().unwrap();
+        let archive = shuttle
+            .make_archive(None, ArchiveCompression::Gzip)
+            .unwrap();
 
         // Make sure the Secrets.toml file is not initially present
         let tar = GzDecoder::new(&archive[..]);
@@ -1371,6 +3199,38 @@ mod tests {
             .collect()
     }
 
+    fn get_archive_file(mut project_args: ProjectArgs, path: &str) -> String {
+        let mut shuttle = Shuttle::new().unwrap();
+        shuttle.load_project(&mut project_args).unwrap();
+
+        let archive = shuttle
+            .make_archive(None, ArchiveCompression::Gzip)
+            .unwrap();
+
+        let tar = GzDecoder::new(&archive[..]);
+        let mut archive = Archive::new(tar);
+
+        let mut entry = archive
+            .entries()
+            .unwrap()
+            .map(Result::unwrap)
+            .find(|entry| {
+                entry
+                    .path()
+                    .unwrap()
+                    .components()
+                    .skip(1)
+                    .collect::<PathBuf>()
+                    == Path::new(path)
+            })
+            .expect("file to be in the archive");
+
+        let mut content = String::new();
+        entry.read_to_string(&mut content).unwrap();
+
+        content
+    }
+
     #[test]
     fn load_project_returns_proper_working_directory_in_project_args() {
         let mut project_args = ProjectArgs {
@@ -1461,6 +3321,131 @@ version = "0.1.0"
         );
     }
 
+    #[test]
+    fn make_archive_respect_shuttleignore() {
+        let tmp_dir = TempDir::new().unwrap();
+        let working_directory = tmp_dir.path();
+
+        fs::write(working_directory.join(".gitignore"), "*.log").unwrap();
+        fs::write(working_directory.join(".shuttleignore"), "assets/private/").unwrap();
+        fs::write(working_directory.join("debug.log"), "some logs").unwrap();
+        fs::create_dir_all(working_directory.join(".git")).unwrap();
+        fs::write(
+            working_directory.join(".git").join("HEAD"),
+            "ref: refs/heads/main",
+        )
+        .unwrap();
+        fs::create_dir_all(working_directory.join("assets").join("private")).unwrap();
+        fs::write(
+            working_directory
+                .join("assets")
+                .join("private")
+                .join("key.pem"),
+            "a key",
+        )
+        .unwrap();
+        fs::write(working_directory.join("assets").join("logo.svg"), "<svg/>").unwrap();
+        fs::write(
+            working_directory.join("Cargo.toml"),
+            r#"
+[package]
+name = "ignored"
+version = "0.1.0"
+"#,
+        )
+        .unwrap();
+        fs::create_dir_all(working_directory.join("src")).unwrap();
+        fs::write(
+            working_directory.join("src").join("main.rs"),
+            "fn main() {}",
+        )
+        .unwrap();
+
+        let project_args = ProjectArgs {
+            working_directory: working_directory.to_path_buf(),
+            name: Some(ProjectName::from_str("ignored").unwrap()),
+        };
+
+        let mut entries = get_archive_entries(project_args);
+        entries.sort();
+
+        assert_eq!(
+            entries,
+            vec![
+                ".gitignore",
+                ".shuttleignore",
+                "Cargo.lock",
+                "Cargo.toml",
+                "assets/logo.svg",
+                "src/main.rs"
+            ]
+        );
+    }
+
+    #[test]
+    fn make_archive_vendors_external_path_dependencies() {
+        let tmp_dir = TempDir::new().unwrap();
+        let lib_directory = tmp_dir.path().join("lib");
+        let working_directory = tmp_dir.path().join("project");
+
+        fs::create_dir_all(lib_directory.join("src")).unwrap();
+        fs::write(
+            lib_directory.join("Cargo.toml"),
+            r#"
+[package]
+name = "lib"
+version = "0.1.0"
+"#,
+        )
+        .unwrap();
+        fs::write(lib_directory.join("src").join("lib.rs"), "").unwrap();
+
+        fs::create_dir_all(working_directory.join("src")).unwrap();
+        fs::write(
+            working_directory.join("Cargo.toml"),
+            r#"
+[package]
+name = "vendored"
+version = "0.1.0"
+
+[dependencies]
+lib = { path = "../lib" }
+"#,
+        )
+        .unwrap();
+        fs::write(
+            working_directory.join("src").join("main.rs"),
+            "fn main() {}",
+        )
+        .unwrap();
+
+        let project_args = || ProjectArgs {
+            working_directory: working_directory.clone(),
+            name: Some(ProjectName::from_str("vendored").unwrap()),
+        };
+
+        let mut entries = get_archive_entries(project_args());
+        entries.sort();
+
+        assert_eq!(
+            entries,
+            vec![
+                "Cargo.lock",
+                "Cargo.toml",
+                "shuttle-vendor/lib-0.1.0/Cargo.toml",
+                "shuttle-vendor/lib-0.1.0/src/lib.rs",
+                "src/main.rs",
+            ]
+        );
+
+        // The archived manifest points to the vendored package, while the original is untouched
+        let manifest = get_archive_file(project_args(), "Cargo.toml");
+        assert!(manifest.contains(r#"path = "shuttle-vendor/lib-0.1.0""#));
+        assert!(fs::read_to_string(working_directory.join("Cargo.toml"))
+            .unwrap()
+            .contains(r#"path = "../lib""#));
+    }
+
     #[test]
     fn make_archive_ignore_target_folder() {
         let tmp_dir = TempDir::new().unwrap();
@@ -1494,4 +3479,15 @@ version = "0.1.0"
 
         assert_eq!(entries, vec!["Cargo.lock", "Cargo.toml", "src/main.rs"]);
     }
+
+    #[test]
+    fn sparkline_of_memory() {
+        assert_eq!(sparkline(&[], 40), "");
+        assert_eq!(sparkline(&[5, 5, 5], 40), "▁▁▁");
+        assert_eq!(sparkline(&[0, 10, 20, 30, 40, 50, 60, 70], 40), "▁▂▃▄▅▆▇█");
+
+        // Values sharing a bar are averaged
+        assert_eq!(sparkline(&[0, 0, 10, 10, 20, 20], 3), "▁▅█");
+        assert_eq!(sparkline(&[0; 100], 40).chars().count(), 34);
+    }
 }
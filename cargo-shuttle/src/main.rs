@@ -1,18 +1,24 @@
-use anyhow::Result;
-use cargo_shuttle::{Args, CommandOutcome, Shuttle};
+use cargo_shuttle::{exit_code, Args, CommandOutcome, Shuttle};
 use clap::Parser;
 
 #[tokio::main(flavor = "multi_thread")]
-async fn main() -> Result<()> {
+async fn main() {
     tracing_subscriber::fmt::init();
 
-    let result = Shuttle::new()?.run(Args::parse()).await;
+    let result = match Shuttle::new() {
+        Ok(shuttle) => shuttle.run(Args::parse()).await,
+        Err(error) => Err(error),
+    };
 
-    if matches!(result, Ok(CommandOutcome::DeploymentFailure)) {
-        // Deployment failure results in a shell error exit code being returned (this allows
-        // chaining of commands with `&&` for example to fail at the first deployment failure).
-        std::process::exit(1); // TODO: use `std::process::ExitCode::FAILURE` once stable.
+    match result {
+        Ok(CommandOutcome::Ok) => {}
+        // Failures result in a shell error exit code being returned (this allows chaining of
+        // commands with `&&` for example to fail at the first deployment failure), which tells
+        // scripts like CI pipelines what failed
+        Ok(outcome) => std::process::exit(outcome.exit_code()),
+        Err(error) => {
+            eprintln!("Error: {error:?}");
+            std::process::exit(exit_code(&error));
+        }
     }
-
-    result.map(|_| ())
 }
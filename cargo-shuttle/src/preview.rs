@@ -0,0 +1,390 @@
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use crossterm::style::Stylize;
+use flate2::read::GzDecoder;
+use ignore::WalkBuilder;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use shuttle_common::database::{self, AwsRdsEngine, DedicatedEngine, SharedEngine};
+use shuttle_common::models::{deployment, secret};
+use shuttle_common::resource;
+use tar::Archive;
+
+/// How something would change when deploying
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    /// It is not live yet, and will be added
+    New,
+    /// It is live, and will be replaced
+    Updated,
+    /// It is live, and stays as it is
+    Unchanged,
+    /// It is live but no longer used, and is kept
+    Unused,
+}
+
+/// A secret or resource of the service, and how it would change
+#[derive(Debug, Eq, PartialEq, Serialize)]
+pub struct Item {
+    pub name: String,
+    pub change: Change,
+}
+
+/// What deploying would change compared to what is running, shown by `cargo shuttle deploy --preview`
+#[derive(Serialize)]
+pub struct Preview {
+    /// The packages of the services which would be built
+    pub services: Vec<String>,
+    /// Whether the deployed files differ from the ones of the running deployment
+    pub code: Change,
+    pub local_hash: String,
+    /// The hash of the running deployment, which is not known for deployments made before hashes
+    /// were kept
+    pub running_hash: Option<String>,
+    pub secrets: Vec<Item>,
+    pub resources: Vec<Item>,
+}
+
+impl Preview {
+    /// Compare what would be deployed with the `running` deployment, and the secrets and
+    /// resources which are live
+    pub fn new(
+        services: Vec<String>,
+        local_hash: String,
+        running: Option<&deployment::Response>,
+        local_secrets: &BTreeSet<String>,
+        live_secrets: &[secret::Response],
+        declared_resources: &[resource::Type],
+        live_resources: &[resource::Response],
+    ) -> Self {
+        let running_hash = running.and_then(|deployment| deployment.source_hash.clone());
+        let code = match (running, &running_hash) {
+            (None, _) => Change::New,
+            (Some(_), Some(hash)) if *hash == local_hash => Change::Unchanged,
+            (Some(_), _) => Change::Updated,
+        };
+
+        let live_secrets: BTreeSet<_> = live_secrets.iter().map(|secret| &secret.key).collect();
+        let mut secrets: Vec<_> = local_secrets
+            .iter()
+            .map(|key| Item {
+                name: key.clone(),
+                // Values are not sent back, so a secret from Secrets.toml is always set again
+                change: if live_secrets.contains(key) {
+                    Change::Updated
+                } else {
+                    Change::New
+                },
+            })
+            .collect();
+        secrets.extend(
+            live_secrets
+                .into_iter()
+                .filter(|key| !local_secrets.contains(*key))
+                .map(|key| Item {
+                    name: key.clone(),
+                    change: Change::Unchanged,
+                }),
+        );
+
+        let mut resources: Vec<_> = declared_resources
+            .iter()
+            .map(|r#type| Item {
+                name: r#type.to_string(),
+                change: if live_resources.iter().any(|live| live.r#type == *r#type) {
+                    Change::Unchanged
+                } else {
+                    Change::New
+                },
+            })
+            .collect();
+        resources.extend(
+            live_resources
+                .iter()
+                .filter(|live| !declared_resources.contains(&live.r#type))
+                .map(|live| Item {
+                    name: live.r#type.to_string(),
+                    change: Change::Unused,
+                }),
+        );
+
+        Self {
+            services,
+            code,
+            local_hash,
+            running_hash,
+            secrets,
+            resources,
+        }
+    }
+}
+
+impl Display for Preview {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {}", "Services:".bold(), self.services.join(", "))?;
+
+        let short = |hash: &str| hash.get(..12).unwrap_or(hash).to_string();
+        let code = match (self.code, &self.running_hash) {
+            (Change::New, _) => "nothing is running yet".to_string(),
+            (Change::Unchanged, _) => "unchanged since the running deployment".to_string(),
+            (_, Some(running)) => format!(
+                "changed since the running deployment ({} → {})",
+                short(running),
+                short(&self.local_hash)
+            ),
+            (_, None) => {
+                "the running deployment is too old to compare with, so it is replaced".to_string()
+            }
+        };
+        writeln!(f, "{} {code}", "Code:".bold())?;
+
+        writeln!(f, "{}", "Secrets:".bold())?;
+        if self.secrets.is_empty() {
+            writeln!(f, "  none")?;
+        }
+        for secret in &self.secrets {
+            let line = match secret.change {
+                Change::New => format!("+ {}", secret.name).green(),
+                Change::Updated => format!("~ {} (set from Secrets.toml)", secret.name).yellow(),
+                _ => format!("  {}", secret.name).dim(),
+            };
+            writeln!(f, "  {line}")?;
+        }
+
+        writeln!(f, "{}", "Resources:".bold())?;
+        if self.resources.is_empty() {
+            writeln!(f, "  none")?;
+        }
+        for resource in &self.resources {
+            let line = match resource.change {
+                Change::New => format!("+ {} (will be provisioned)", resource.name).green(),
+                Change::Unused => format!(
+                    "- {} (no longer used, and kept until it is removed with `cargo shuttle resource delete`)",
+                    resource.name
+                )
+                .red(),
+                _ => format!("  {}", resource.name).dim(),
+            };
+            writeln!(f, "  {line}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A file in the archive made for a deployment
+#[derive(Serialize)]
+pub struct ArchiveEntry {
+    /// The path in the project
+    pub path: String,
+    pub size: u64,
+}
+
+/// List the files in the archive `data` made for a deployment, by their paths in the project
+pub fn archive_entries(data: &[u8]) -> Result<Vec<ArchiveEntry>> {
+    let mut archive = Archive::new(decompress(data)?);
+    let mut entries = Vec::new();
+
+    for entry in archive.entries().context("read the archive")? {
+        let entry = entry.context("read an archive entry")?;
+
+        // The first component is the directory of the project
+        let path: PathBuf = entry.path()?.components().skip(1).collect();
+        entries.push(ArchiveEntry {
+            path: path.display().to_string(),
+            size: entry.size(),
+        });
+    }
+
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(entries)
+}
+
+/// Hash the files in the archive `data` made for a deployment by their paths and contents, so that
+/// the hash only changes when they do, and not when they are compressed differently
+pub fn source_hash(data: &[u8]) -> Result<String> {
+    let mut archive = Archive::new(decompress(data)?);
+    let mut files = Vec::new();
+
+    for entry in archive.entries().context("read the archive")? {
+        let mut entry = entry.context("read an archive entry")?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+
+        files.push((path, contents));
+    }
+
+    // Entries are added in no particular order
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for (path, contents) in files {
+        hasher.update(path.as_bytes());
+        hasher.update((contents.len() as u64).to_le_bytes());
+        hasher.update(contents);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn decompress(data: &[u8]) -> Result<Box<dyn Read + '_>> {
+    if data.starts_with(&zstd::zstd_safe::MAGICNUMBER.to_le_bytes()) {
+        Ok(Box::new(zstd::Decoder::new(data)?))
+    } else {
+        Ok(Box::new(GzDecoder::new(data)))
+    }
+}
+
+/// Read the keys of the secrets in the Secrets.toml at `path`, if there is one
+pub fn local_secrets(path: &Path) -> Result<BTreeSet<String>> {
+    if !path.exists() {
+        return Ok(BTreeSet::new());
+    }
+
+    let secrets: toml::value::Table = toml::from_str(&fs::read_to_string(path)?)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+
+    Ok(secrets.into_iter().map(|(key, _)| key).collect())
+}
+
+/// Find the resources which the sources of the service in `service_directory` declare with the
+/// attributes of the resource crates
+pub fn declared_resources(service_directory: &Path) -> Result<Vec<resource::Type>> {
+    let mut resources = Vec::new();
+
+    for entry in WalkBuilder::new(service_directory.join("src")).build() {
+        let entry = entry.context("get directory entry")?;
+
+        if entry.path().extension().and_then(|ext| ext.to_str()) == Some("rs") {
+            for r#type in resources_in(&fs::read_to_string(entry.path())?) {
+                if !resources.contains(&r#type) {
+                    resources.push(r#type);
+                }
+            }
+        }
+    }
+
+    Ok(resources)
+}
+
+/// The resources the attributes in `source`, like `#[shuttle_shared_db::Postgres]`, provision
+fn resources_in(source: &str) -> Vec<resource::Type> {
+    source
+        .match_indices("#[shuttle_")
+        .filter_map(|(start, _)| {
+            let path: String = source[start + 2..]
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == ':')
+                .collect();
+
+            attribute_resource(&path)
+        })
+        .collect()
+}
+
+fn attribute_resource(path: &str) -> Option<resource::Type> {
+    let r#type = match path {
+        "shuttle_shared_db::Postgres" => {
+            resource::Type::Database(database::Type::Shared(SharedEngine::Postgres))
+        }
+        "shuttle_shared_db::MongoDb" => {
+            resource::Type::Database(database::Type::Shared(SharedEngine::MongoDb))
+        }
+        "shuttle_shared_db::Redis" => {
+            resource::Type::Database(database::Type::Shared(SharedEngine::Redis))
+        }
+        "shuttle_qdrant::Qdrant" => {
+            resource::Type::Database(database::Type::Shared(SharedEngine::Qdrant))
+        }
+        "shuttle_dedicated_db::MongoDb" => {
+            resource::Type::Database(database::Type::Dedicated(DedicatedEngine::MongoDb))
+        }
+        "shuttle_aws_rds::Postgres" => {
+            resource::Type::Database(database::Type::AwsRds(AwsRdsEngine::Postgres))
+        }
+        "shuttle_aws_rds::MySql" => {
+            resource::Type::Database(database::Type::AwsRds(AwsRdsEngine::MySql))
+        }
+        "shuttle_aws_rds::MariaDB" => {
+            resource::Type::Database(database::Type::AwsRds(AwsRdsEngine::MariaDB))
+        }
+        "shuttle_secrets::Secrets" => resource::Type::Secrets,
+        "shuttle_static_folder::StaticFolder" => resource::Type::StaticFolder,
+        "shuttle_persist::Persist" => resource::Type::Persist,
+        "shuttle_volume::Volume" => resource::Type::Volume,
+        "shuttle_runtime::RunArgs" => resource::Type::RunArgs,
+        _ => return None,
+    };
+
+    Some(r#type)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::Utc;
+    use shuttle_common::database::{self, SharedEngine};
+    use shuttle_common::models::secret;
+    use shuttle_common::resource;
+
+    use super::{resources_in, Change, Item, Preview};
+
+    #[test]
+    fn resources() {
+        let source = r#"
+#[shuttle_runtime::main]
+async fn axum(
+    #[shuttle_shared_db::Postgres(local_uri = "postgres://localhost")] pool: PgPool,
+    #[shuttle_secrets::Secrets] secrets: SecretStore,
+    #[shuttle_unknown::Thing] thing: Thing,
+) -> shuttle_axum::ShuttleAxum {
+"#;
+
+        assert_eq!(
+            resources_in(source),
+            vec![
+                resource::Type::Database(database::Type::Shared(SharedEngine::Postgres)),
+                resource::Type::Secrets,
+            ]
+        );
+    }
+
+    #[test]
+    fn secrets() {
+        let local = BTreeSet::from(["NEW".to_string(), "SET".to_string()]);
+        let live = ["SET", "KEPT"].map(|key| secret::Response {
+            key: key.to_string(),
+            last_update: Utc::now(),
+        });
+
+        let preview = Preview::new(vec![], String::new(), None, &local, &live, &[], &[]);
+
+        assert_eq!(preview.code, Change::New);
+        assert_eq!(
+            preview.secrets,
+            vec![
+                Item {
+                    name: "NEW".to_string(),
+                    change: Change::New
+                },
+                Item {
+                    name: "SET".to_string(),
+                    change: Change::Updated
+                },
+                Item {
+                    name: "KEPT".to_string(),
+                    change: Change::Unchanged
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crossterm::style::Stylize;
+use indicatif::ProgressBar;
+use shuttle_common::deployment::Phase;
+use shuttle_common::LogItem;
+
+use crate::create_spinner;
+
+/// How many of the latest logs of a phase are kept, to show them when it fails
+const KEPT_LOGS: usize = 30;
+
+/// Shows the phases of a deployment as it goes through them, one line each, instead of all of its
+/// logs
+#[derive(Default)]
+pub struct DeployProgress {
+    current: Option<(Phase, Instant)>,
+    spinner: Option<ProgressBar>,
+    /// The latest logs of the current phase
+    logs: VecDeque<String>,
+}
+
+impl DeployProgress {
+    /// Show that the deployment entered `phase`, which marks the phase before it as done. Phases
+    /// which were already passed are ignored, since logs can be sent again when reconnecting.
+    pub fn enter(&mut self, phase: Phase) {
+        if matches!(self.current, Some((current, _)) if current >= phase) {
+            return;
+        }
+
+        if let Some((current, started)) = self.current.take() {
+            self.done(current, started.elapsed());
+        }
+        self.logs.clear();
+
+        if phase == Phase::Healthy {
+            self.println(format!("{} {}", "✓".green(), done_label(phase)));
+        } else {
+            self.spinner
+                .get_or_insert_with(create_spinner)
+                .set_message(format!("{}...", active_label(phase)));
+        }
+
+        self.current = Some((phase, Instant::now()));
+    }
+
+    /// Show that `phase` is done, after taking `elapsed`
+    pub fn done(&self, phase: Phase, elapsed: Duration) {
+        self.println(format!(
+            "{} {} {}",
+            "✓".green(),
+            done_label(phase),
+            format!("({})", format_elapsed(elapsed)).dim()
+        ));
+    }
+
+    /// Keep `log` of the current phase, to show it if the phase fails
+    pub fn log(&mut self, log: &LogItem) {
+        if self.logs.len() == KEPT_LOGS {
+            self.logs.pop_front();
+        }
+        self.logs.push_back(log.to_string());
+    }
+
+    /// Stop showing the progress once the deployment is done
+    pub fn finish(mut self) {
+        if let Some((phase, started)) = self.current.take() {
+            if phase != Phase::Healthy {
+                self.done(phase, started.elapsed());
+            }
+        }
+
+        if let Some(spinner) = self.spinner.take() {
+            spinner.finish_and_clear();
+        }
+    }
+
+    /// Show that the current phase failed, along with its latest logs
+    pub fn fail(mut self) {
+        if let Some((phase, started)) = self.current.take() {
+            self.println(format!(
+                "{} {} {}",
+                "✗".red(),
+                active_label(phase),
+                format!("(failed after {})", format_elapsed(started.elapsed())).dim()
+            ));
+        }
+
+        if let Some(spinner) = self.spinner.take() {
+            spinner.finish_and_clear();
+        }
+
+        if !self.logs.is_empty() {
+            println!();
+            for log in &self.logs {
+                println!("{log}");
+            }
+        }
+    }
+
+    /// Print a line above the spinner, if there is one yet
+    fn println(&self, line: String) {
+        match &self.spinner {
+            Some(spinner) => spinner.println(line),
+            None => println!("{line}"),
+        }
+    }
+}
+
+fn active_label(phase: Phase) -> &'static str {
+    match phase {
+        Phase::Upload => "Uploading",
+        Phase::Queued => "Waiting for a build slot",
+        Phase::Building => "Building",
+        Phase::Provisioning => "Provisioning resources",
+        Phase::Starting => "Starting",
+        Phase::Healthy => "Checking that the service is healthy",
+    }
+}
+
+fn done_label(phase: Phase) -> &'static str {
+    match phase {
+        Phase::Upload => "Uploaded",
+        Phase::Queued => "Got a build slot",
+        Phase::Building => "Built",
+        Phase::Provisioning => "Provisioned resources",
+        Phase::Starting => "Started",
+        Phase::Healthy => "Healthy",
+    }
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+
+    if seconds < 60 {
+        format!("{:.1}s", elapsed.as_secs_f64())
+    } else {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use shuttle_common::deployment::Phase;
+
+    use super::{format_elapsed, DeployProgress};
+
+    #[test]
+    fn elapsed() {
+        assert_eq!(format_elapsed(Duration::from_millis(1500)), "1.5s");
+        assert_eq!(format_elapsed(Duration::from_secs(135)), "2m 15s");
+    }
+
+    #[test]
+    fn phases_only_move_forward() {
+        let mut progress = DeployProgress::default();
+
+        progress.enter(Phase::Building);
+        progress.enter(Phase::Queued);
+        assert!(matches!(progress.current, Some((Phase::Building, _))));
+
+        progress.enter(Phase::Starting);
+        assert!(matches!(progress.current, Some((Phase::Starting, _))));
+
+        progress.finish();
+    }
+}
@@ -0,0 +1,62 @@
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::debug;
+
+/// How much is read from a local connection before it is sent through the tunnel
+const BUFFER_SIZE: usize = 16 * 1024;
+
+/// Pass the bytes between the local `connection` and the binary messages of the `tunnel`, until
+/// either of them is closed
+pub async fn forward(connection: TcpStream, tunnel: WebSocketStream<MaybeTlsStream<TcpStream>>) {
+    let (mut reader, mut writer) = connection.into_split();
+    let (mut sink, mut messages) = tunnel.split();
+
+    let incoming = async {
+        while let Some(message) = messages.next().await {
+            match message {
+                Ok(Message::Binary(data)) => {
+                    if writer.write_all(&data).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(Message::Close(frame)) => {
+                    if let Some(frame) = frame.filter(|frame| !frame.reason.is_empty()) {
+                        debug!(reason = %frame.reason, "tunnel closed by shuttle");
+                    }
+                    break;
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    debug!(%error, "tunnel failed");
+                    break;
+                }
+            }
+        }
+
+        let _ = writer.shutdown().await;
+    };
+
+    let outgoing = async {
+        let mut buf = vec![0; BUFFER_SIZE];
+
+        while let Ok(read @ 1..) = reader.read(&mut buf).await {
+            if sink
+                .send(Message::Binary(buf[..read].to_vec()))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+
+        let _ = sink.close().await;
+    };
+
+    tokio::select! {
+        _ = incoming => {},
+        _ = outgoing => {},
+    }
+}
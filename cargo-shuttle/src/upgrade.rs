@@ -0,0 +1,256 @@
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::read::GzDecoder;
+use semver::Version;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tar::Archive;
+
+/// Where the releases of cargo-shuttle are published, newest first
+const RELEASES_URL: &str = "https://api.github.com/repos/shuttle-hq/shuttle/releases";
+
+/// A release of cargo-shuttle, with the archives of its binaries and their checksums
+#[derive(Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub draft: bool,
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+impl Release {
+    pub fn version(&self) -> Result<Version> {
+        Version::parse(self.tag_name.trim_start_matches('v'))
+            .with_context(|| format!("the release {} does not have a version", self.tag_name))
+    }
+
+    fn asset(&self, name: &str) -> Result<&Asset> {
+        self.assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .ok_or_else(|| anyhow!("the release {} has no {name}", self.tag_name))
+    }
+}
+
+/// Get the latest release, which can be a prerelease when `preview` is set
+pub async fn latest_release(preview: bool) -> Result<Release> {
+    let client = http_client()?;
+
+    if preview {
+        let releases: Vec<Release> = client
+            .get(format!("{RELEASES_URL}?per_page=10"))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context("failed to get the releases of cargo-shuttle")?
+            .json()
+            .await?;
+
+        releases
+            .into_iter()
+            .find(|release| !release.draft)
+            .ok_or_else(|| anyhow!("cargo-shuttle has no releases"))
+    } else {
+        // This is never a prerelease
+        Ok(client
+            .get(format!("{RELEASES_URL}/latest"))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context("failed to get the latest release of cargo-shuttle")?
+            .json()
+            .await?)
+    }
+}
+
+/// Download the binary of `release` for this platform, check it against the checksum published
+/// with it, and put it in place of the running executable, whose path is returned
+pub async fn install(release: &Release) -> Result<PathBuf> {
+    let Some(target) = target() else {
+        bail!("there are no release binaries for this platform, run `cargo install cargo-shuttle` instead");
+    };
+
+    let archive_name = format!("cargo-shuttle-{}-{target}.tar.gz", release.tag_name);
+    let archive = download(release.asset(&archive_name)?).await?;
+    let checksum = download(release.asset(&format!("{archive_name}.sha256"))?).await?;
+    verify(&archive, &String::from_utf8_lossy(&checksum))
+        .with_context(|| format!("failed to verify {archive_name}"))?;
+
+    let binary = binary_from_archive(&archive)?;
+    let exe = env::current_exe().context("failed to find the running executable")?;
+    replace_binary(&exe, &binary)
+        .with_context(|| format!("failed to replace {}", exe.display()))?;
+
+    Ok(exe)
+}
+
+/// The target the release binaries which run on this platform are built for
+fn target() -> Option<&'static str> {
+    if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+        // The static build, which runs whatever the libc is
+        Some("x86_64-unknown-linux-musl")
+    } else if cfg!(all(target_os = "linux", target_arch = "aarch64")) {
+        Some("aarch64-unknown-linux-musl")
+    } else if cfg!(target_os = "macos") {
+        // Apple silicon runs it through Rosetta
+        Some("x86_64-apple-darwin")
+    } else if cfg!(all(target_os = "windows", target_arch = "x86_64")) {
+        Some("x86_64-pc-windows-msvc")
+    } else {
+        None
+    }
+}
+
+fn http_client() -> Result<reqwest::Client> {
+    // The GitHub API refuses requests without a user agent
+    Ok(reqwest::Client::builder()
+        .user_agent(concat!("cargo-shuttle/", env!("CARGO_PKG_VERSION")))
+        .build()?)
+}
+
+async fn download(asset: &Asset) -> Result<Vec<u8>> {
+    let data = http_client()?
+        .get(&asset.browser_download_url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .with_context(|| format!("failed to download {}", asset.name))?
+        .bytes()
+        .await
+        .with_context(|| format!("failed to download {}", asset.name))?;
+
+    Ok(data.to_vec())
+}
+
+/// Check that `data` has the SHA-256 hash in `checksum`, which is in the format of `sha256sum`
+fn verify(data: &[u8], checksum: &str) -> Result<()> {
+    let expected = checksum
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| anyhow!("the checksum is empty"))?;
+    let actual = format!("{:x}", Sha256::digest(data));
+
+    if !actual.eq_ignore_ascii_case(expected) {
+        bail!("the download has the hash {actual} instead of {expected}");
+    }
+
+    Ok(())
+}
+
+/// Take the cargo-shuttle executable out of a release archive
+fn binary_from_archive(data: &[u8]) -> Result<Vec<u8>> {
+    let name = format!("cargo-shuttle{}", env::consts::EXE_SUFFIX);
+    let mut archive = Archive::new(GzDecoder::new(data));
+
+    for entry in archive.entries().context("read the release archive")? {
+        let mut entry = entry.context("read an entry of the release archive")?;
+
+        if entry.path()?.file_name() == Some(name.as_ref()) {
+            let mut binary = Vec::new();
+            entry.read_to_end(&mut binary)?;
+
+            return Ok(binary);
+        }
+    }
+
+    bail!("the release archive has no {name}")
+}
+
+/// Put `binary` in place of the executable at `exe`, which may be running
+fn replace_binary(exe: &Path, binary: &[u8]) -> Result<()> {
+    let new = exe.with_extension("new");
+    fs::write(&new, binary)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::set_permissions(&new, fs::Permissions::from_mode(0o755))?;
+    }
+
+    // A running executable cannot be overwritten on Windows, but it can be moved out of the way
+    #[cfg(windows)]
+    {
+        let old = exe.with_extension("old");
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old)?;
+    }
+
+    // Renaming replaces the file in one step, so that a failed upgrade does not leave half a binary
+    fs::rename(&new, exe)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use sha2::{Digest, Sha256};
+    use tar::{Builder, Header};
+    use tempfile::Builder as TempBuilder;
+
+    use super::{binary_from_archive, replace_binary, verify};
+
+    #[test]
+    fn checksum() {
+        let data = b"cargo-shuttle";
+        let hash = format!("{:x}", Sha256::digest(data));
+
+        assert!(verify(data, &format!("{hash}  cargo-shuttle-v0.18.0.tar.gz\n")).is_ok());
+        assert!(verify(data, &hash.to_uppercase()).is_ok());
+        assert!(verify(b"something else", &hash).is_err());
+        assert!(verify(data, "").is_err());
+    }
+
+    #[test]
+    fn binary_in_archive() {
+        let name = format!("cargo-shuttle{}", env::consts::EXE_SUFFIX);
+        let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+
+        for (path, contents) in [
+            (
+                "cargo-shuttle-v0.18.0/README.md".to_string(),
+                &b"readme"[..],
+            ),
+            (format!("cargo-shuttle-v0.18.0/{name}"), &b"binary"[..]),
+        ] {
+            let mut header = Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_cksum();
+            builder.append_data(&mut header, path, contents).unwrap();
+        }
+
+        let archive = builder.into_inner().unwrap().finish().unwrap();
+
+        assert_eq!(binary_from_archive(&archive).unwrap(), b"binary");
+        assert!(binary_from_archive(&[]).is_err());
+    }
+
+    #[test]
+    fn replace() {
+        let dir = TempBuilder::new()
+            .prefix("cargo-shuttle-upgrade")
+            .tempdir()
+            .unwrap();
+        let exe = dir.path().join("cargo-shuttle");
+        std::fs::write(&exe, "old").unwrap();
+
+        replace_binary(&exe, b"new").unwrap();
+
+        assert_eq!(std::fs::read(&exe).unwrap(), b"new");
+        assert!(!exe.with_extension("new").exists());
+    }
+}
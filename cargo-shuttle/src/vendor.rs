@@ -0,0 +1,475 @@
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use cargo_metadata::MetadataCommand;
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use toml_edit::{Array, Document, InlineTable, Item, TableLike, Value};
+use tracing::trace;
+
+/// The folder of the archive which holds the packages from outside of the workspace
+const VENDOR_DIR: &str = "shuttle-vendor";
+
+/// The tables of a manifest which hold dependencies
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// What has to change in the archive of a workspace so that it can be built without anything
+/// outside of it.
+///
+/// Path dependencies and patches can point outside of the workspace, where the deployer cannot see
+/// them. Those packages are copied into the archive, and the manifests pointing to them are
+/// rewritten to point to the copies.
+#[derive(Debug, Default)]
+pub struct Vendored {
+    /// The files of the copied packages, with where they go relative to the workspace root
+    pub files: Vec<(PathBuf, PathBuf)>,
+    /// The rewritten manifests, by their path relative to the workspace root
+    pub manifests: HashMap<PathBuf, String>,
+}
+
+/// Find the path dependencies of the workspace at `workspace_root` which are outside of it, and
+/// get them ready to be archived with the workspace
+pub fn vendor_external_packages(workspace_root: &Path) -> Result<Vendored> {
+    let workspace_root = dunce::canonicalize(workspace_root)?;
+    let metadata = MetadataCommand::new()
+        .manifest_path(workspace_root.join("Cargo.toml"))
+        .exec()
+        .context("failed to get cargo metadata")?;
+
+    // Where the packages from outside the workspace go in the archive, by their directory
+    let mut external = HashMap::new();
+    let mut manifests = vec![workspace_root.join("Cargo.toml")];
+
+    for package in &metadata.packages {
+        // Only path dependencies have no source
+        if package.source.is_some() {
+            continue;
+        }
+
+        let manifest_path = dunce::canonicalize(&package.manifest_path)?;
+        let dir = manifest_path
+            .parent()
+            .context("manifest should be in a directory")?
+            .to_path_buf();
+
+        if dir.starts_with(&workspace_root) {
+            manifests.push(manifest_path);
+        } else {
+            trace!(package = %package.name, ?dir, "vendoring package from outside the workspace");
+            external.insert(
+                dir,
+                Path::new(VENDOR_DIR).join(format!("{}-{}", package.name, package.version)),
+            );
+        }
+    }
+
+    let mut vendored = Vendored::default();
+    if external.is_empty() {
+        return Ok(vendored);
+    }
+
+    manifests.sort();
+    manifests.dedup();
+
+    for manifest_path in manifests {
+        let dir = manifest_path
+            .parent()
+            .context("manifest should be in a directory")?;
+        let archive_dir = dir.strip_prefix(&workspace_root)?;
+
+        let mut document = read_manifest(&manifest_path)?;
+        if rewrite_paths(&mut document, dir, archive_dir, &external) {
+            vendored
+                .manifests
+                .insert(archive_dir.join("Cargo.toml"), document.to_string());
+        }
+    }
+
+    for (dir, archive_dir) in &external {
+        let mut document = read_manifest(&dir.join("Cargo.toml"))?;
+
+        // The package will not be in its workspace anymore, so it cannot inherit from it
+        let package_workspace_root: PathBuf = MetadataCommand::new()
+            .manifest_path(dir.join("Cargo.toml"))
+            .no_deps()
+            .exec()
+            .context("failed to get cargo metadata of a path dependency")?
+            .workspace_root
+            .into();
+        let package_workspace_root = dunce::canonicalize(package_workspace_root)?;
+        if &package_workspace_root != dir {
+            let root = read_manifest(&package_workspace_root.join("Cargo.toml"))?;
+            inherit_from_workspace(&mut document, &root, &package_workspace_root);
+        }
+
+        rewrite_paths(&mut document, dir, archive_dir, &external);
+        vendored
+            .manifests
+            .insert(archive_dir.join("Cargo.toml"), document.to_string());
+
+        let overrides = OverrideBuilder::new(dir)
+            .add("!target/")
+            .context("add `!target/` override")?
+            .build()
+            .context("build an override")?;
+
+        for entry in WalkBuilder::new(dir)
+            .hidden(false)
+            .overrides(overrides)
+            .build()
+        {
+            let entry = entry.context("get directory entry")?;
+            if entry
+                .file_type()
+                .map_or(true, |file_type| file_type.is_dir())
+            {
+                continue;
+            }
+
+            let path = entry.path().strip_prefix(dir)?;
+            vendored
+                .files
+                .push((entry.path().to_path_buf(), archive_dir.join(path)));
+        }
+    }
+
+    Ok(vendored)
+}
+
+fn read_manifest(path: &Path) -> Result<Document> {
+    read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?
+        .parse()
+        .with_context(|| format!("failed to parse {}", path.display()))
+}
+
+/// Get all the dependency tables of a manifest, including those of targets, the workspace and
+/// patches
+fn dependency_tables(document: &mut Document) -> Vec<&mut dyn TableLike> {
+    let mut tables = Vec::new();
+
+    for (key, item) in document.as_table_mut().iter_mut() {
+        match key.get() {
+            key if DEPENDENCY_TABLES.contains(&key) => tables.extend(item.as_table_like_mut()),
+            "target" => {
+                let targets = item.as_table_like_mut().into_iter();
+                for (_, target) in targets.flat_map(|targets| targets.iter_mut()) {
+                    for (key, item) in target
+                        .as_table_like_mut()
+                        .into_iter()
+                        .flat_map(|t| t.iter_mut())
+                    {
+                        if DEPENDENCY_TABLES.contains(&key.get()) {
+                            tables.extend(item.as_table_like_mut());
+                        }
+                    }
+                }
+            }
+            "workspace" => {
+                if let Some(dependencies) = item
+                    .as_table_like_mut()
+                    .and_then(|workspace| workspace.get_mut("dependencies"))
+                {
+                    tables.extend(dependencies.as_table_like_mut());
+                }
+            }
+            // Patches are tables of dependencies by the registry they patch
+            "patch" => {
+                for (_, registry) in item
+                    .as_table_like_mut()
+                    .into_iter()
+                    .flat_map(|t| t.iter_mut())
+                {
+                    tables.extend(registry.as_table_like_mut());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    tables
+}
+
+/// Point the path dependencies of a manifest in `manifest_dir` to where the `external` packages
+/// are in the archive, given that the manifest will be in `archive_dir`. Returns whether anything
+/// was changed.
+fn rewrite_paths(
+    document: &mut Document,
+    manifest_dir: &Path,
+    archive_dir: &Path,
+    external: &HashMap<PathBuf, PathBuf>,
+) -> bool {
+    let mut changed = false;
+
+    for table in dependency_tables(document) {
+        for (_, dependency) in table.iter_mut() {
+            let Some(dependency) = dependency.as_table_like_mut() else {
+                continue;
+            };
+
+            let Some(path) = dependency.get("path").and_then(Item::as_str) else {
+                continue;
+            };
+
+            let Ok(path) = dunce::canonicalize(manifest_dir.join(path)) else {
+                continue;
+            };
+
+            if let Some(vendored) = external.get(&path) {
+                dependency.insert(
+                    "path",
+                    toml_edit::value(relative_path(archive_dir, vendored)),
+                );
+                changed = true;
+            }
+        }
+    }
+
+    changed
+}
+
+/// Replace the keys of a package which are inherited from its workspace with the values of the
+/// workspace, like `cargo package` does. `root` is the manifest of the workspace in `root_dir`.
+fn inherit_from_workspace(document: &mut Document, root: &Document, root_dir: &Path) {
+    let workspace = root.get("workspace");
+
+    if let Some(package) = document
+        .get_mut("package")
+        .and_then(Item::as_table_like_mut)
+    {
+        package.remove("workspace");
+
+        let inherited = workspace
+            .and_then(|workspace| workspace.get("package"))
+            .and_then(Item::as_table_like);
+
+        for (key, item) in package.iter_mut() {
+            if !is_inherited(item) {
+                continue;
+            }
+
+            if let Some(value) = inherited.and_then(|inherited| inherited.get(key.get())) {
+                *item = value.clone();
+            }
+        }
+    }
+
+    let dependencies = workspace
+        .and_then(|workspace| workspace.get("dependencies"))
+        .and_then(Item::as_table_like);
+
+    for table in dependency_tables(document) {
+        for (name, item) in table.iter_mut() {
+            if !is_inherited(item) {
+                continue;
+            }
+
+            if let Some(inherited) =
+                dependencies.and_then(|dependencies| dependencies.get(name.get()))
+            {
+                *item = merge_dependency(inherited, item, root_dir);
+            }
+        }
+    }
+}
+
+/// Whether an item is `{ workspace = true }`
+fn is_inherited(item: &Item) -> bool {
+    item.as_table_like()
+        .and_then(|table| table.get("workspace"))
+        .and_then(Item::as_bool)
+        == Some(true)
+}
+
+/// Combine a dependency of the workspace with the keys a package adds to it when inheriting it
+fn merge_dependency(inherited: &Item, member: &Item, root_dir: &Path) -> Item {
+    let mut merged = InlineTable::new();
+
+    if let Some(version) = inherited.as_str() {
+        merged.insert("version", Value::from(version));
+    } else if let Some(inherited) = inherited.as_table_like() {
+        for (key, item) in inherited.iter() {
+            if let Some(value) = item.as_value() {
+                merged.insert(key, value.clone());
+            }
+        }
+    }
+
+    // The paths of the workspace are relative to its root, not to the package
+    if let Some(path) = merged.get("path").and_then(Value::as_str) {
+        let path = root_dir.join(path).display().to_string();
+        merged.insert("path", Value::from(path));
+    }
+
+    if let Some(member) = member.as_table_like() {
+        for (key, item) in member.iter() {
+            match (key, item.as_value()) {
+                ("workspace", _) | (_, None) => {}
+                // Features add to the ones of the workspace
+                ("features", Some(features)) => {
+                    let mut all = merged
+                        .get("features")
+                        .and_then(Value::as_array)
+                        .cloned()
+                        .unwrap_or_else(Array::new);
+                    if let Some(features) = features.as_array() {
+                        for feature in features.iter() {
+                            all.push(feature.clone());
+                        }
+                    }
+                    merged.insert("features", Value::Array(all));
+                }
+                (key, Some(value)) => {
+                    merged.insert(key, value.clone());
+                }
+            }
+        }
+    }
+
+    Item::Value(Value::InlineTable(merged))
+}
+
+/// The path from `from` to `to`, which are both relative to the workspace root. Always uses `/`,
+/// since the archive is built on the deployer.
+fn relative_path(from: &Path, to: &Path) -> String {
+    from.components()
+        .map(|_| "..".to_string())
+        .chain(
+            to.components()
+                .map(|component| component.as_os_str().to_string_lossy().to_string()),
+        )
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use tempfile::TempDir;
+    use toml_edit::Document;
+
+    use super::{inherit_from_workspace, relative_path, rewrite_paths};
+
+    #[test]
+    fn relative() {
+        assert_eq!(
+            relative_path(Path::new(""), Path::new("shuttle-vendor/lib-0.1.0")),
+            "shuttle-vendor/lib-0.1.0"
+        );
+        assert_eq!(
+            relative_path(Path::new("service"), Path::new("shuttle-vendor/lib-0.1.0")),
+            "../shuttle-vendor/lib-0.1.0"
+        );
+    }
+
+    #[test]
+    fn rewrite() {
+        let tmp_dir = TempDir::new().unwrap();
+        let root = tmp_dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("project/service")).unwrap();
+        fs::create_dir_all(root.join("lib")).unwrap();
+
+        let mut document: Document = r#"
+[package]
+name = "service"
+
+[dependencies]
+lib = { path = "../../lib", features = ["a"] }
+serde = "1"
+
+[target.'cfg(unix)'.dev-dependencies]
+lib = { path = "../../lib" }
+
+[patch.crates-io]
+lib = { path = "../../lib" }
+"#
+        .parse()
+        .unwrap();
+
+        let external =
+            HashMap::from([(root.join("lib"), PathBuf::from("shuttle-vendor/lib-0.1.0"))]);
+
+        assert!(rewrite_paths(
+            &mut document,
+            &root.join("project/service"),
+            Path::new("service"),
+            &external
+        ));
+
+        let vendored = Some("../shuttle-vendor/lib-0.1.0");
+        assert_eq!(document["dependencies"]["lib"]["path"].as_str(), vendored);
+        assert_eq!(
+            document["dependencies"]["lib"]["features"][0].as_str(),
+            Some("a")
+        );
+        assert_eq!(
+            document["target"]["cfg(unix)"]["dev-dependencies"]["lib"]["path"].as_str(),
+            vendored
+        );
+        assert_eq!(
+            document["patch"]["crates-io"]["lib"]["path"].as_str(),
+            vendored
+        );
+    }
+
+    #[test]
+    fn inherit() {
+        let root: Document = r#"
+[workspace]
+members = ["lib"]
+
+[workspace.package]
+version = "1.2.3"
+edition = "2021"
+
+[workspace.dependencies]
+serde = { version = "1", features = ["derive"] }
+tokio = "1"
+"#
+        .parse()
+        .unwrap();
+
+        let mut document: Document = r#"
+[package]
+name = "lib"
+version.workspace = true
+edition = { workspace = true }
+
+[dependencies]
+serde = { workspace = true, features = ["rc"], optional = true }
+tokio.workspace = true
+"#
+        .parse()
+        .unwrap();
+
+        inherit_from_workspace(&mut document, &root, Path::new("/workspace"));
+
+        let package = &document["package"];
+        assert_eq!(package["version"].as_str(), Some("1.2.3"));
+        assert_eq!(package["edition"].as_str(), Some("2021"));
+
+        let serde = &document["dependencies"]["serde"];
+        assert_eq!(serde["version"].as_str(), Some("1"));
+        assert_eq!(serde["optional"].as_bool(), Some(true));
+        assert_eq!(
+            serde["features"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|feature| feature.as_str())
+                .collect::<Vec<_>>(),
+            vec!["derive", "rc"]
+        );
+        assert!(serde.get("workspace").is_none());
+
+        assert_eq!(
+            document["dependencies"]["tokio"]["version"].as_str(),
+            Some("1")
+        );
+    }
+}
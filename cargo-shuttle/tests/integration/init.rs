@@ -31,6 +31,102 @@ async fn non_interactive_basic_init() {
 
     assert!(cargo_toml.contains("name = \"my-project\""));
     assert!(cargo_toml.contains("shuttle-runtime = "));
+
+    let shuttle_toml = read_to_string(temp_dir_path.join("Shuttle.toml")).unwrap();
+    assert_eq!(shuttle_toml, "name = \"my-project\"\n");
+}
+
+#[tokio::test]
+async fn non_interactive_init_from_repository() {
+    let template_dir = Builder::new().prefix("template-repo").tempdir().unwrap();
+    let template_path = template_dir.path();
+    std::fs::write(
+        template_path.join("Cargo.toml"),
+        "[package]\nname = \"{{project-name}}\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        template_path.join("Shuttle.toml"),
+        "name = \"template\"\nassets = [\"static/**\"]\n",
+    )
+    .unwrap();
+
+    let repo = git2::Repository::init(template_path).unwrap();
+    let mut index = repo.index().unwrap();
+    index
+        .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+        .unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = git2::Signature::now("template", "template@example.com").unwrap();
+    repo.commit(Some("HEAD"), &signature, &signature, "template", &tree, &[])
+        .unwrap();
+
+    let temp_dir = Builder::new().prefix("from-init").tempdir().unwrap();
+    let temp_dir_path = temp_dir.path().join("my-project");
+
+    let args = Args::parse_from([
+        "cargo-shuttle",
+        "--api-url",
+        "http://shuttle.invalid:80",
+        "init",
+        "--api-key",
+        "dh9z58jttoes3qvt",
+        "--name",
+        "my-project",
+        "--from",
+        template_path.to_str().unwrap(),
+        temp_dir_path.to_str().unwrap(),
+    ]);
+    Shuttle::new().unwrap().run(args).await.unwrap();
+
+    let cargo_toml = read_to_string(temp_dir_path.join("Cargo.toml")).unwrap();
+    assert_eq!(cargo_toml, "[package]\nname = \"my-project\"\n");
+
+    let shuttle_toml = read_to_string(temp_dir_path.join("Shuttle.toml")).unwrap();
+    assert_eq!(
+        shuttle_toml,
+        "name = \"my-project\"\nassets = [\"static/**\"]\n"
+    );
+
+    // The project gets its own history
+    let repo = git2::Repository::open(&temp_dir_path).unwrap();
+    assert!(repo.head().is_err());
+}
+
+#[tokio::test]
+async fn non_interactive_init_with_resources() {
+    let temp_dir = Builder::new().prefix("resources-init").tempdir().unwrap();
+    let temp_dir_path = temp_dir.path().to_owned();
+
+    let args = Args::parse_from([
+        "cargo-shuttle",
+        "--api-url",
+        "http://shuttle.invalid:80",
+        "init",
+        "--api-key",
+        "dh9z58jttoes3qvt",
+        "--name",
+        "my-project",
+        "--template",
+        "axum",
+        "--resources",
+        "postgres,secrets",
+        temp_dir_path.to_str().unwrap(),
+    ]);
+    Shuttle::new().unwrap().run(args).await.unwrap();
+
+    let cargo_toml = read_to_string(temp_dir_path.join("Cargo.toml")).unwrap();
+    assert!(cargo_toml.contains("shuttle-shared-db = { version = "));
+    assert!(cargo_toml.contains("shuttle-secrets = "));
+
+    let main_file = read_to_string(temp_dir_path.join("src").join("main.rs")).unwrap();
+    assert!(main_file.contains("#[shuttle_shared_db::Postgres] _pool: sqlx::PgPool,"));
+    assert!(main_file
+        .contains("#[shuttle_secrets::Secrets] _secret_store: shuttle_secrets::SecretStore,"));
+
+    assert!(temp_dir_path.join("Secrets.toml").exists());
+    let gitignore = read_to_string(temp_dir_path.join(".gitignore")).unwrap();
+    assert!(gitignore.lines().any(|line| line == "Secrets*.toml"));
 }
 
 #[tokio::test]
@@ -85,6 +181,8 @@ fn interactive_rocket_init() -> Result<(), Box<dyn std::error::Error>> {
     )?;
     // Partial input should be enough to match "rocket"
     session.send_line("roc")?;
+    session.exp_string("Which resources does your service need?")?;
+    session.send_line("")?;
     session.exp_string("Do you want to create the project environment on Shuttle?")?;
     session.send("y")?;
     session.flush()?;
@@ -121,6 +219,8 @@ fn interactive_rocket_init_dont_prompt_framework() -> Result<(), Box<dyn std::er
     session.exp_string("Where should we create this project?")?;
     session.exp_string("Directory")?;
     session.send_line(temp_dir_path.to_str().unwrap())?;
+    session.exp_string("Which resources does your service need?")?;
+    session.send_line("")?;
     session.exp_string("Do you want to create the project environment on Shuttle?")?;
     session.send("y")?;
     session.flush()?;
@@ -157,6 +257,8 @@ fn interactive_rocket_init_dont_prompt_name() -> Result<(), Box<dyn std::error::
     )?;
     // Partial input should be enough to match "rocket"
     session.send_line("roc")?;
+    session.exp_string("Which resources does your service need?")?;
+    session.send_line("")?;
     session.exp_string("Do you want to create the project environment on Shuttle?")?;
     session.send("y")?;
     session.flush()?;
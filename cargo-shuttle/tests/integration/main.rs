@@ -15,6 +15,10 @@ async fn cargo_shuttle_command(
         .unwrap()
         .run(Args {
             api_url: Some("http://shuttle.invalid:80".to_string()),
+            environment: None,
+            profile: None,
+            output: Default::default(),
+            yes: false,
             project_args: ProjectArgs {
                 working_directory,
                 name: None,
@@ -20,10 +20,19 @@ async fn cargo_shuttle_run(working_directory: &str, external: bool) -> String {
         port,
         external,
         release: false,
+        watch: false,
+        package: None,
+        locked: false,
+        offline: false,
+        feature_args: Default::default(),
     };
 
     let runner = Shuttle::new().unwrap().run(Args {
         api_url: Some("http://shuttle.invalid:80".to_string()),
+        environment: None,
+        profile: None,
+        output: Default::default(),
+        yes: false,
         project_args: ProjectArgs {
             working_directory: working_directory.clone(),
             name: None,
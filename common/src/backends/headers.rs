@@ -33,6 +33,10 @@ impl Header for XShuttleAdminSecret {
     }
 }
 
+/// The version of the API which sent a response, so that clients can tell whether they are
+/// compatible with it
+pub static X_SHUTTLE_VERSION: HeaderName = HeaderName::from_static("x-shuttle-version");
+
 pub static X_SHUTTLE_ACCOUNT_NAME: HeaderName = HeaderName::from_static("x-shuttle-account-name");
 
 /// Typed header for sending account names around
@@ -3,4 +3,6 @@ pub mod cache;
 mod future;
 pub mod headers;
 pub mod metrics;
+pub mod pages;
+pub mod proxy;
 pub mod tracing;
@@ -0,0 +1,91 @@
+use http::header::ACCEPT;
+use http::HeaderMap;
+
+/// Whether the request came from a browser, which is shown a page instead of the error which is
+/// returned to other clients
+pub fn wants_html(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|accept| accept.to_str().ok())
+        .any(|accept| accept.contains("text/html"))
+}
+
+/// A page in the style of shuttle, shown instead of a service which cannot answer
+pub fn page(title: &str, message: &str) -> String {
+    let title = escape(title);
+    let message = escape(message);
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+body {{ margin: 0; min-height: 100vh; display: flex; align-items: center; justify-content: center; background: #1b1b1b; color: #e5e5e5; font-family: system-ui, sans-serif; }}
+main {{ max-width: 32rem; padding: 2rem; text-align: center; }}
+h1 {{ color: #ff8a3f; font-size: 1.75rem; }}
+footer {{ margin-top: 2rem; color: #7a7a7a; font-size: 0.875rem; }}
+</style>
+</head>
+<body>
+<main>
+<h1>{title}</h1>
+<p>{message}</p>
+<footer>Served by shuttle</footer>
+</main>
+</body>
+</html>
+"#
+    )
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header::ACCEPT;
+    use http::HeaderMap;
+
+    use super::{page, wants_html};
+
+    #[test]
+    fn browsers_want_html() {
+        let mut headers = HeaderMap::new();
+        assert!(!wants_html(&headers));
+
+        headers.insert(ACCEPT, "application/json".parse().unwrap());
+        assert!(!wants_html(&headers));
+
+        headers.insert(
+            ACCEPT,
+            "text/html,application/xhtml+xml,*/*;q=0.8".parse().unwrap(),
+        );
+        assert!(wants_html(&headers));
+    }
+
+    #[test]
+    fn page_is_escaped() {
+        let page = page("<matrix>", "neo & \"trinity\"");
+
+        assert!(page.contains("<title>&lt;matrix&gt;</title>"));
+        assert!(page.contains("<p>neo &amp; &quot;trinity&quot;</p>"));
+    }
+}
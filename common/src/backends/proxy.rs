@@ -0,0 +1,85 @@
+use http::header::{CONTENT_TYPE, HOST};
+use http::{HeaderValue, Request, Version};
+
+/// Get a request which came in over any version of HTTP ready to be proxied to a backend, and
+/// return whether it has to be sent over HTTP/2 without TLS (h2c).
+///
+/// Requests which came in over HTTP/2 have their host in their URI instead of in a `Host` header,
+/// so it is copied into one for the backends to find it. gRPC calls stay on HTTP/2, since their
+/// status comes in trailers, which HTTP/1.1 can't carry. Every other request is sent over
+/// HTTP/1.1, which every backend speaks, while WebSocket upgrades keep working over it.
+pub fn prepare<B>(req: &mut Request<B>) -> bool {
+    if !req.headers().contains_key(HOST) {
+        let host = req
+            .uri()
+            .authority()
+            .and_then(|authority| HeaderValue::from_str(authority.as_str()).ok());
+
+        if let Some(host) = host {
+            req.headers_mut().insert(HOST, host);
+        }
+    }
+
+    let h2c = req.version() == Version::HTTP_2 && is_grpc(req);
+    if !h2c {
+        *req.version_mut() = Version::HTTP_11;
+    }
+
+    h2c
+}
+
+fn is_grpc<B>(req: &Request<B>) -> bool {
+    req.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map_or(false, |content_type| {
+            content_type.starts_with("application/grpc")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header::{CONTENT_TYPE, HOST};
+    use http::{Request, Version};
+
+    use super::prepare;
+
+    #[test]
+    fn http2_requests() {
+        let mut req = Request::builder()
+            .version(Version::HTTP_2)
+            .uri("https://my-app.shuttleapp.rs/chat")
+            .body(())
+            .unwrap();
+
+        assert!(!prepare(&mut req));
+        assert_eq!(req.version(), Version::HTTP_11);
+        assert_eq!(req.headers()[HOST], "my-app.shuttleapp.rs");
+    }
+
+    #[test]
+    fn grpc_calls() {
+        let mut req = Request::builder()
+            .version(Version::HTTP_2)
+            .uri("https://my-app.shuttleapp.rs/helloworld.Greeter/SayHello")
+            .header(CONTENT_TYPE, "application/grpc+proto")
+            .body(())
+            .unwrap();
+
+        assert!(prepare(&mut req));
+        assert_eq!(req.version(), Version::HTTP_2);
+        assert_eq!(req.headers()[HOST], "my-app.shuttleapp.rs");
+
+        // gRPC needs HTTP/2 from the start
+        let mut req = Request::builder()
+            .version(Version::HTTP_11)
+            .uri("/helloworld.Greeter/SayHello")
+            .header(HOST, "my-app.shuttleapp.rs")
+            .header(CONTENT_TYPE, "application/grpc")
+            .body(())
+            .unwrap();
+
+        assert!(!prepare(&mut req));
+        assert_eq!(req.headers()[HOST], "my-app.shuttleapp.rs");
+    }
+}
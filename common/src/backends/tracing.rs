@@ -9,6 +9,7 @@ use opentelemetry::{
     global,
     runtime::Tokio,
     sdk::{propagation::TraceContextPropagator, trace, Resource},
+    trace::TraceContextExt,
     KeyValue,
 };
 use opentelemetry_http::HeaderExtractor;
@@ -54,6 +55,17 @@ where
         .init();
 }
 
+/// The id of the trace `span` is part of, when traces are being exported
+pub fn trace_id(span: &Span) -> Option<String> {
+    let context = span.context();
+    let span = context.span();
+    let span_context = span.span_context();
+
+    span_context
+        .is_valid()
+        .then(|| span_context.trace_id().to_string())
+}
+
 /// Layer to extract tracing from headers and set the context on the current span
 #[derive(Clone)]
 pub struct ExtractPropagationLayer;
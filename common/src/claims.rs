@@ -37,6 +37,9 @@ pub enum Scope {
     /// Push a new deployment
     DeploymentPush,
 
+    /// Approve deployments to run, and choose whether they need to be approved
+    DeploymentApprove,
+
     /// Read the logs of a deployment
     Logs,
 
@@ -64,6 +67,27 @@ pub enum Scope {
     /// Add or update secrets of a project
     SecretWrite,
 
+    /// List the environment variables of a project
+    Env,
+
+    /// Add, update or remove environment variables of a project
+    EnvWrite,
+
+    /// List the webhooks of a project
+    Webhook,
+
+    /// Register or remove webhooks of a project
+    WebhookWrite,
+
+    /// List the custom domains of a project
+    Domain,
+
+    /// Add a custom domain to a project
+    DomainWrite,
+
+    /// Open a tunnel to the private port of a service or to its databases
+    Tunnel,
+
     /// Get list of users
     User,
 
@@ -96,6 +120,7 @@ impl ScopeBuilder {
         Self(vec![
             Scope::Deployment,
             Scope::DeploymentPush,
+            Scope::DeploymentApprove,
             Scope::Logs,
             Scope::Service,
             Scope::ServiceCreate,
@@ -105,6 +130,13 @@ impl ScopeBuilder {
             Scope::ResourcesWrite,
             Scope::Secret,
             Scope::SecretWrite,
+            Scope::Env,
+            Scope::EnvWrite,
+            Scope::Webhook,
+            Scope::WebhookWrite,
+            Scope::Domain,
+            Scope::DomainWrite,
+            Scope::Tunnel,
         ])
     }
 
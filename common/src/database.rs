@@ -12,6 +12,7 @@ use utoipa::ToSchema;
 pub enum Type {
     AwsRds(AwsRdsEngine),
     Shared(SharedEngine),
+    Dedicated(DedicatedEngine),
 }
 
 #[derive(Clone, Debug, Deserialize, Display, Serialize, Eq, PartialEq)]
@@ -31,6 +32,16 @@ pub enum AwsRdsEngine {
 pub enum SharedEngine {
     Postgres,
     MongoDb,
+    Redis,
+    Qdrant,
+}
+
+#[derive(Clone, Debug, Deserialize, Display, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub enum DedicatedEngine {
+    MongoDb,
 }
 
 impl Display for Type {
@@ -38,6 +49,7 @@ impl Display for Type {
         match self {
             Type::AwsRds(rds_type) => write!(f, "aws_rds::{rds_type}"),
             Type::Shared(shared_type) => write!(f, "shared::{shared_type}"),
+            Type::Dedicated(dedicated_type) => write!(f, "dedicated::{dedicated_type}"),
         }
     }
 }
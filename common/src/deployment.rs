@@ -1,9 +1,9 @@
 use serde::{Deserialize, Serialize};
-use strum::Display;
+use strum::{Display, EnumString};
 #[cfg(feature = "openapi")]
 use utoipa::ToSchema;
 
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Display, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Display, EnumString, Serialize)]
 #[serde(rename_all = "lowercase")]
 #[strum(serialize_all = "lowercase")]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
@@ -12,14 +12,50 @@ pub enum State {
     Queued,
     Building,
     Built,
+    /// Built, and held until it is approved to run
+    #[serde(rename = "pending-approval")]
+    #[strum(serialize = "pending-approval")]
+    PendingApproval,
     Loading,
     Running,
     Completed,
     Stopped,
     Crashed,
+    Cancelled,
     Unknown,
 }
 
+/// The steps of a deployment until it serves requests, which are shown as its progress
+#[derive(
+    Clone, Copy, Debug, Deserialize, Display, EnumString, Eq, Ord, PartialEq, PartialOrd, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Phase {
+    /// The archive of the project is uploaded, before there is a deployment
+    Upload,
+    Queued,
+    Building,
+    /// The resources of the service, like databases, are provisioned while it is loaded
+    Provisioning,
+    Starting,
+    /// The service started, and accepts connections if it serves any
+    Healthy,
+}
+
+impl Phase {
+    /// The phase a deployment enters along with `state`, if it is one of its phases
+    pub fn of_state(state: &State) -> Option<Self> {
+        match state {
+            State::Queued => Some(Self::Queued),
+            State::Building => Some(Self::Building),
+            State::Loading => Some(Self::Provisioning),
+            State::Running => Some(Self::Starting),
+            _ => None,
+        }
+    }
+}
+
 /// This which environment is this deployment taking place
 #[derive(Clone, Copy)]
 pub enum Environment {
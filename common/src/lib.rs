@@ -13,6 +13,8 @@ pub mod models;
 pub mod project;
 pub mod resource;
 #[cfg(feature = "service")]
+pub mod shuttle_toml;
+#[cfg(feature = "service")]
 pub mod storage_manager;
 #[cfg(feature = "tracing")]
 pub mod tracing;
@@ -1,18 +1,24 @@
 #[cfg(feature = "display")]
 use std::fmt::Write;
+use std::str::FromStr;
 
 use chrono::{DateTime, Utc};
 #[cfg(feature = "display")]
 use crossterm::style::{StyledContent, Stylize};
 use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
 #[cfg(feature = "openapi")]
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-use crate::deployment::State;
+use crate::deployment::{Phase, State};
 
 pub const STATE_MESSAGE: &str = "NEW STATE";
 
+/// The field of a log which marks that its deployment entered a [Phase] no [State] marks, like
+/// `info!(phase = %Phase::Healthy, "...")`
+pub const PHASE_FIELD: &str = "phase";
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 #[cfg_attr(feature = "openapi", schema(as = shuttle_common::log::Item))]
@@ -29,6 +35,23 @@ pub struct Item {
     pub line: Option<u32>,
     pub target: String,
     pub fields: Vec<u8>,
+    /// Logs from before their source was kept are tracing logs
+    #[serde(default)]
+    #[cfg_attr(feature = "openapi", schema(value_type = shuttle_common::log::Source))]
+    pub source: Source,
+}
+
+impl Item {
+    /// The phase of its deployment this log marks the start of, if any
+    pub fn phase(&self) -> Option<Phase> {
+        match serde_json::from_slice(&self.fields).ok()? {
+            serde_json::Value::String(message) if message == STATE_MESSAGE => {
+                Phase::of_state(&self.state)
+            }
+            serde_json::Value::Object(map) => map.get(PHASE_FIELD)?.as_str()?.parse().ok(),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(feature = "display")]
@@ -61,8 +84,16 @@ impl std::fmt::Display for Item {
                     format!("{{{}}} ", extra.join(" "))
                 };
 
-                if !self.target.is_empty() {
-                    let target = format!("{}:", self.target).dim();
+                // Printed lines have no target, so where they were printed stands in for it
+                let target = match self.source {
+                    Source::Tracing => self.target.as_str(),
+                    Source::Stdout => "stdout",
+                    Source::Stderr => "stderr",
+                    Source::Access => "access",
+                };
+
+                if !target.is_empty() {
+                    let target = format!("{target}:").dim();
                     write!(output, "{target} ")?;
                 }
 
@@ -85,7 +116,8 @@ impl std::fmt::Display for Item {
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+/// How severe a log is, ordered from the least to the most severe
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, Ord, PartialOrd)]
 #[serde(rename_all = "lowercase")]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 #[cfg_attr(feature = "openapi", schema(as = shuttle_common::log::Level))]
@@ -110,6 +142,55 @@ impl Level {
     }
 }
 
+impl Level {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Trace => "trace",
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "trace" => Ok(Level::Trace),
+            "debug" => Ok(Level::Debug),
+            "info" => Ok(Level::Info),
+            "warn" | "warning" => Ok(Level::Warn),
+            "error" => Ok(Level::Error),
+            _ => Err(format!(
+                "`{s}` is not a log level, which are trace, debug, info, warn and error"
+            )),
+        }
+    }
+}
+
+/// Where a log of a deployment came from
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Display, EnumString, Eq, PartialEq, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::log::Source))]
+pub enum Source {
+    /// Logged with `tracing`, by the service or by shuttle
+    #[default]
+    Tracing,
+    /// A line the service printed to stdout
+    Stdout,
+    /// A line the service printed to stderr
+    Stderr,
+    /// A request to the service which went through the gateway
+    Access,
+}
+
 impl From<&tracing::Level> for Level {
     fn from(level: &tracing::Level) -> Self {
         match *level {
@@ -121,3 +202,42 @@ impl From<&tracing::Level> for Level {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{Item, Level, STATE_MESSAGE};
+    use crate::deployment::{Phase, State};
+
+    fn item(state: State, fields: serde_json::Value) -> Item {
+        Item {
+            id: Uuid::new_v4(),
+            timestamp: Default::default(),
+            state,
+            level: Level::Info,
+            file: None,
+            line: None,
+            target: String::new(),
+            fields: serde_json::to_vec(&fields).unwrap(),
+            source: Default::default(),
+        }
+    }
+
+    #[test]
+    fn phases() {
+        let entered = |state| item(state, serde_json::json!(STATE_MESSAGE)).phase();
+        assert_eq!(entered(State::Building), Some(Phase::Building));
+        assert_eq!(entered(State::Loading), Some(Phase::Provisioning));
+        assert_eq!(entered(State::Crashed), None);
+
+        let healthy = item(
+            State::Running,
+            serde_json::json!({ "message": "service is healthy", "phase": "healthy" }),
+        );
+        assert_eq!(healthy.phase(), Some(Phase::Healthy));
+
+        let log = item(State::Running, serde_json::json!({ "message": "hello" }));
+        assert_eq!(log.phase(), None);
+    }
+}
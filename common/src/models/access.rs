@@ -0,0 +1,194 @@
+use std::fmt::{Display, Formatter};
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+/// A range of addresses, like `10.0.0.0/8`, or a single address
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cidr {
+    address: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Whether `ip` is in the range
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.address, ip) {
+            (IpAddr::V4(address), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(address) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(address), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(address) & mask == u128::from(ip) & mask
+            }
+            // Clients which reach the gateway over IPv4 on an IPv6 socket
+            (IpAddr::V4(_), IpAddr::V6(ip)) => ip
+                .to_ipv4_mapped()
+                .map_or(false, |ip| self.contains(IpAddr::V4(ip))),
+            (IpAddr::V6(_), IpAddr::V4(_)) => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix) = s.split_once('/').unwrap_or((s, ""));
+        let address: IpAddr = address
+            .parse()
+            .map_err(|_| format!("'{s}' is not an address or a range of addresses"))?;
+        let max = if address.is_ipv4() { 32 } else { 128 };
+
+        let prefix = if prefix.is_empty() {
+            max
+        } else {
+            prefix
+                .parse()
+                .ok()
+                .filter(|prefix| *prefix <= max)
+                .ok_or_else(|| format!("'{s}' needs a prefix length of at most {max}"))?
+        };
+
+        Ok(Self { address, prefix })
+    }
+}
+
+impl Display for Cidr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix)
+    }
+}
+
+impl Serialize for Cidr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cidr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// What a client has to send for the gateway to let its requests through to the service
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::access::Guard))]
+pub enum Guard {
+    /// HTTP basic auth with this username and password
+    Basic { username: String, password: String },
+    /// An `Authorization: Bearer` header with this token
+    Bearer { token: String },
+}
+
+/// The rules for the clients of a service, which replace the ones it had
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::access::Request))]
+pub struct Request {
+    /// Only the clients in these ranges are let through, or every client when this is empty
+    #[serde(default)]
+    #[cfg_attr(feature = "openapi", schema(value_type = Vec<String>))]
+    pub allow: Vec<Cidr>,
+    /// The clients in these ranges are refused, even when they are allowed
+    #[serde(default)]
+    #[cfg_attr(feature = "openapi", schema(value_type = Vec<String>))]
+    pub deny: Vec<Cidr>,
+    pub guard: Option<Guard>,
+}
+
+/// The kind of guard a service has, without its secret
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::access::GuardKind))]
+pub enum GuardKind {
+    Basic { username: String },
+    Bearer,
+}
+
+/// The rules for the clients of a service
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::access::Response))]
+pub struct Response {
+    pub service: String,
+    #[cfg_attr(feature = "openapi", schema(value_type = Vec<String>))]
+    pub allow: Vec<Cidr>,
+    #[cfg_attr(feature = "openapi", schema(value_type = Vec<String>))]
+    pub deny: Vec<Cidr>,
+    pub guard: Option<GuardKind>,
+}
+
+impl Display for Response {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let list = |cidrs: &[Cidr]| {
+            cidrs
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        if self.allow.is_empty() {
+            writeln!(f, "Allowed: every client")?;
+        } else {
+            writeln!(f, "Allowed: {}", list(&self.allow))?;
+        }
+
+        if self.deny.is_empty() {
+            writeln!(f, "Denied: no client")?;
+        } else {
+            writeln!(f, "Denied: {}", list(&self.deny))?;
+        }
+
+        match &self.guard {
+            Some(GuardKind::Basic { username }) => write!(f, "Guard: basic auth as {username}"),
+            Some(GuardKind::Bearer) => write!(f, "Guard: bearer token"),
+            None => write!(f, "Guard: none"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::Cidr;
+
+    #[test]
+    fn cidrs() {
+        let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+
+        let private: Cidr = "10.0.0.0/8".parse().unwrap();
+        assert!(private.contains(ip("10.1.2.3")));
+        assert!(!private.contains(ip("11.0.0.1")));
+        assert!(private.contains(ip("::ffff:10.1.2.3")));
+        assert!(!private.contains(ip("::1")));
+
+        let single: Cidr = "192.168.1.1".parse().unwrap();
+        assert_eq!(single.to_string(), "192.168.1.1/32");
+        assert!(single.contains(ip("192.168.1.1")));
+        assert!(!single.contains(ip("192.168.1.2")));
+
+        let v6: Cidr = "2001:db8::/32".parse().unwrap();
+        assert!(v6.contains(ip("2001:db8::1")));
+        assert!(!v6.contains(ip("2001:db9::1")));
+
+        let everyone: Cidr = "0.0.0.0/0".parse().unwrap();
+        assert!(everyone.contains(ip("8.8.8.8")));
+
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("10.0.0/8".parse::<Cidr>().is_err());
+        assert!("10.0.0.0/x".parse::<Cidr>().is_err());
+    }
+}
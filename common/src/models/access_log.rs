@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+/// A request the gateway proxied to a service, which it ships to the deployer of the project to
+/// be kept with the logs of the service
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::access_log::Entry))]
+pub struct Entry {
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::DateTime))]
+    pub timestamp: DateTime<Utc>,
+    pub service: String,
+    pub method: String,
+    /// The path of the request, without its query which could hold secrets
+    pub path: String,
+    pub status: u16,
+    /// Milliseconds the service took to start responding
+    pub latency_ms: u64,
+    /// Bytes of the body of the response which were sent
+    pub bytes: u64,
+    /// A hash of the address of the client, which tells clients apart without keeping their
+    /// address
+    pub client: String,
+}
+
+impl Entry {
+    /// The message of the log of this request
+    pub fn message(&self) -> String {
+        format!("{} {} {}", self.method, self.path, self.status)
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Which artifacts of the deployments of a project are kept. The artifacts of deployments which are
+/// on their way to running or running are always kept.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::artifacts::Retention))]
+pub struct Retention {
+    /// Keep the executables and assets of this many of the latest deployments of each service, so
+    /// that they can be rolled back to
+    pub keep_deployments: u32,
+    /// Bytes the artifacts and build caches of the project may take up, after which the build
+    /// caches are purged and then the artifacts of the oldest deployments are removed
+    pub max_bytes: Option<u64>,
+}
+
+/// What a sweep of the artifacts of a project removed
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::artifacts::Sweep))]
+pub struct Sweep {
+    /// The deployments whose executables and assets were removed
+    #[cfg_attr(feature = "openapi", schema(value_type = Vec<KnownFormat::Uuid>))]
+    pub deployments: Vec<Uuid>,
+    /// How many uploads were removed since they were abandoned
+    pub uploads: usize,
+    /// The services whose build caches were purged
+    pub build_caches: Vec<String>,
+    /// How many bytes the removed files took up
+    pub bytes_reclaimed: u64,
+}
+
+impl Display for Retention {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Keeping the artifacts of the latest {} deployments of each service",
+            self.keep_deployments
+        )?;
+
+        match self.max_bytes {
+            Some(max_bytes) => write!(
+                f,
+                ", in at most {:.1} MiB",
+                max_bytes as f64 / (1024.0 * 1024.0)
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Display for Sweep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Reclaimed {:.1} MiB by removing the artifacts of {} deployments, {} abandoned uploads and {} build caches",
+            self.bytes_reclaimed as f64 / (1024.0 * 1024.0),
+            self.deployments.len(),
+            self.uploads,
+            self.build_caches.len()
+        )
+    }
+}
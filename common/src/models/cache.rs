@@ -0,0 +1,36 @@
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+/// Which cached responses of a service to drop
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::cache::Purge))]
+pub struct Purge {
+    /// Only drop the responses to the paths starting with this, like `/posts`
+    pub path: Option<String>,
+}
+
+/// The cached responses of a service which were dropped
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::cache::Response))]
+pub struct Response {
+    pub service: String,
+    /// How many responses were dropped
+    pub responses: usize,
+    /// How many bytes the responses took up
+    pub bytes: u64,
+}
+
+impl Display for Response {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Purged {} cached responses of {}, which took up {} bytes",
+            self.responses, self.service, self.bytes
+        )
+    }
+}
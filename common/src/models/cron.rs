@@ -0,0 +1,153 @@
+use chrono::{DateTime, Utc};
+use comfy_table::{
+    modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Attribute, Cell, CellAlignment, Color,
+    ContentArrangement, Table,
+};
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+use strum::Display;
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// A scheduled job of the running deployment of a service
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::cron::Response))]
+pub struct Response {
+    pub name: String,
+    /// The cron expression of the job
+    pub schedule: String,
+    /// The endpoint of the service the job invokes
+    pub path: String,
+    /// When the job runs next
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<KnownFormat::DateTime>))]
+    pub next_run: Option<DateTime<Utc>>,
+    /// The latest time the job ran
+    pub last_run: Option<Run>,
+}
+
+/// A time a scheduled job ran
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::cron::Run))]
+pub struct Run {
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::Uuid))]
+    pub deployment_id: Uuid,
+    pub name: String,
+    /// When the job was due
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::DateTime))]
+    pub started_at: DateTime<Utc>,
+    /// When the job finished, if it did
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<KnownFormat::DateTime>))]
+    pub finished_at: Option<DateTime<Utc>>,
+    pub status: Status,
+    /// Why the run failed, if it did
+    pub error: Option<String>,
+}
+
+/// How the run of a scheduled job went
+#[derive(Clone, Copy, Debug, Deserialize, Display, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::cron::Status))]
+pub enum Status {
+    Running,
+    Succeeded,
+    Failed,
+    /// Not run, because the run before was still going
+    Skipped,
+}
+
+impl Status {
+    fn color(&self) -> Color {
+        match self {
+            Status::Running => Color::Cyan,
+            Status::Succeeded => Color::Green,
+            Status::Failed => Color::Red,
+            Status::Skipped => Color::Yellow,
+        }
+    }
+}
+
+fn format_time(time: &DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+pub fn get_jobs_table(jobs: &Vec<Response>) -> String {
+    if jobs.is_empty() {
+        return format!(
+            "{}\n",
+            "The running deployment of this service has no scheduled jobs. Add them as [[cron]] tables to its Shuttle.toml"
+                .bold()
+        );
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+        .set_header(
+            ["Name", "Schedule", "Path", "Next run", "Last run"].map(|header| {
+                Cell::new(header)
+                    .set_alignment(CellAlignment::Center)
+                    .add_attribute(Attribute::Bold)
+            }),
+        );
+
+    for job in jobs {
+        let last_run = match &job.last_run {
+            Some(run) => Cell::new(format!("{} ({})", format_time(&run.started_at), run.status))
+                .fg(run.status.color()),
+            None => Cell::new("never"),
+        };
+
+        table.add_row(vec![
+            Cell::new(&job.name),
+            Cell::new(&job.schedule),
+            Cell::new(&job.path),
+            Cell::new(job.next_run.as_ref().map(format_time).unwrap_or_default()),
+            last_run,
+        ]);
+    }
+
+    format!("These jobs are scheduled for this service\n{table}\n")
+}
+
+pub fn get_runs_table(runs: &Vec<Run>) -> String {
+    if runs.is_empty() {
+        return format!("{}\n", "No scheduled jobs of this service ran yet".bold());
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+        .set_header(
+            ["Name", "Started", "Finished", "Status", "Error"].map(|header| {
+                Cell::new(header)
+                    .set_alignment(CellAlignment::Center)
+                    .add_attribute(Attribute::Bold)
+            }),
+        );
+
+    for run in runs {
+        table.add_row(vec![
+            Cell::new(&run.name),
+            Cell::new(format_time(&run.started_at)),
+            Cell::new(
+                run.finished_at
+                    .as_ref()
+                    .map(format_time)
+                    .unwrap_or_default(),
+            ),
+            Cell::new(run.status).fg(run.status.color()),
+            Cell::new(run.error.as_deref().unwrap_or_default()),
+        ]);
+    }
+
+    format!("The latest runs of the scheduled jobs of this service\n{table}\n")
+}
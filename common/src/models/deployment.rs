@@ -26,6 +26,123 @@ pub struct Response {
     pub state: State,
     #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::DateTime))]
     pub last_update: DateTime<Utc>,
+    /// The commit the deployment was made from, when it was made from a git repository
+    pub git_commit_id: Option<String>,
+    /// The summary line of the message of that commit
+    pub git_commit_msg: Option<String>,
+    /// The branch that was checked out
+    pub git_branch: Option<String>,
+    /// Whether the deployment included changes which were not committed
+    pub git_dirty: Option<bool>,
+    /// A hash of the files which were deployed, to tell whether they changed since
+    pub source_hash: Option<String>,
+    /// How often the deployment was killed for using more memory than it may
+    #[serde(default)]
+    pub oom_kills: u32,
+    /// The trace the deployment was made in, which support can use to find what happened to it
+    /// across every service which handled it
+    #[serde(default)]
+    pub trace_id: Option<String>,
+    /// What the health checks found out about the deployment, while it is running
+    #[serde(default)]
+    pub health: Option<Health>,
+    /// Every state the deployment was in, oldest first, when getting a single deployment
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transitions: Vec<Transition>,
+}
+
+/// Whether the deployments of a project are held after they are built, until they are approved to
+/// run
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::deployment::Approval))]
+pub struct Approval {
+    pub required: bool,
+}
+
+impl Display for Approval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.required {
+            write!(
+                f,
+                "Deployments are held after they are built, until they are approved with `cargo shuttle deployment approve`"
+            )
+        } else {
+            write!(f, "Deployments run as soon as they are built")
+        }
+    }
+}
+
+/// A time a deployment got into a new state
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::deployment::Transition))]
+pub struct Transition {
+    #[cfg_attr(feature = "openapi", schema(value_type = shuttle_common::deployment::State))]
+    pub state: State,
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::DateTime))]
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Display for Transition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            self.timestamp
+                .format("%Y-%m-%dT%H:%M:%SZ")
+                .to_string()
+                .dim(),
+            self.state
+                .to_string()
+                .with(crossterm::style::Color::from_str(self.state.get_color()).unwrap())
+        )
+    }
+}
+
+/// The results of the health checks of a running deployment
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::deployment::Health))]
+pub struct Health {
+    /// Whether the deployment passed its readiness check
+    pub ready: bool,
+    /// When the deployment was last probed
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::DateTime))]
+    pub last_probe: DateTime<Utc>,
+    /// How many probes in a row failed
+    pub failures: u32,
+    /// Why the last failed probe failed
+    pub last_error: Option<String>,
+}
+
+impl Display for Health {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.last_error, self.failures) {
+            (Some(error), failures) if failures > 0 => write!(
+                f,
+                "{} ({failures} failed probes, last: {error})",
+                "unhealthy".red()
+            ),
+            _ if self.ready => write!(f, "{}", "healthy".green()),
+            _ => write!(f, "{}", "not ready".yellow()),
+        }
+    }
+}
+
+impl Response {
+    /// The commit the deployment was made from in short form, marked when it had uncommitted
+    /// changes
+    pub fn short_commit(&self) -> Option<String> {
+        let commit_id = self.git_commit_id.as_ref()?;
+        let short = commit_id.get(..7).unwrap_or(commit_id);
+
+        if self.git_dirty == Some(true) {
+            Some(format!("{short}*"))
+        } else {
+            Some(short.to_string())
+        }
+    }
 }
 
 impl Display for Response {
@@ -39,7 +156,21 @@ impl Display for Response {
                 .dim(),
             self.id,
             self.state.to_string().cyan()
-        )
+        )?;
+
+        if let Some(health) = &self.health {
+            write!(f, ", health: {health}")?;
+        }
+
+        if self.oom_kills > 0 {
+            write!(
+                f,
+                ", {}",
+                format!("killed {} times for running out of memory", self.oom_kills).red()
+            )?;
+        }
+
+        Ok(())
     }
 }
 
@@ -51,9 +182,9 @@ impl State {
         match self {
             State::Queued | State::Building | State::Built | State::Loading => "cyan",
             State::Running => "green",
-            State::Completed | State::Stopped => "blue",
+            State::Completed | State::Stopped | State::Cancelled => "blue",
             State::Crashed => "red",
-            State::Unknown => "yellow",
+            State::PendingApproval | State::Unknown => "yellow",
         }
     }
 }
@@ -87,6 +218,12 @@ pub fn get_deployments_table(deployments: &Vec<Response>, service_name: &str, pa
                 Cell::new("Last updated")
                     .set_alignment(CellAlignment::Center)
                     .add_attribute(Attribute::Bold),
+                Cell::new("Branch")
+                    .set_alignment(CellAlignment::Center)
+                    .add_attribute(Attribute::Bold),
+                Cell::new("Commit")
+                    .set_alignment(CellAlignment::Center)
+                    .add_attribute(Attribute::Bold),
             ]);
 
         for deploy in deployments.iter() {
@@ -98,6 +235,9 @@ pub fn get_deployments_table(deployments: &Vec<Response>, service_name: &str, pa
                     .set_alignment(CellAlignment::Center),
                 Cell::new(deploy.last_update.format("%Y-%m-%dT%H:%M:%SZ"))
                     .set_alignment(CellAlignment::Center),
+                Cell::new(deploy.git_branch.as_deref().unwrap_or_default()),
+                Cell::new(deploy.short_commit().unwrap_or_default())
+                    .set_alignment(CellAlignment::Center),
             ]);
         }
 
@@ -111,7 +251,7 @@ Most recent {} for {}
             "deployments".bold(),
             service_name,
             table,
-            "More projects might be available on the next page using --page.".bold()
+            "More deployments might be available on the next page using --page.".bold()
         )
     }
 }
@@ -0,0 +1,57 @@
+use chrono::{DateTime, Utc};
+use comfy_table::{
+    modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Attribute, Cell, CellAlignment,
+    ContentArrangement, Table,
+};
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+/// A custom domain of a project, which the gateway terminates TLS for with a certificate of its own
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::domain::Response))]
+pub struct Response {
+    pub fqdn: String,
+    pub project_name: String,
+    /// When the certificate stops being valid. It is renewed in the last 30 days before then.
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::DateTime))]
+    pub certificate_expires_at: DateTime<Utc>,
+}
+
+pub fn get_table(domains: &Vec<Response>) -> String {
+    if domains.is_empty() {
+        format!(
+            "{}\n",
+            "No custom domains are set up for this project".bold()
+        )
+    } else {
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+            .set_header(["Domain", "Certificate expires"].map(|header| {
+                Cell::new(header)
+                    .set_alignment(CellAlignment::Center)
+                    .add_attribute(Attribute::Bold)
+            }));
+
+        for domain in domains {
+            table.add_row(vec![
+                domain.fqdn.clone(),
+                domain
+                    .certificate_expires_at
+                    .format("%Y-%m-%dT%H:%M:%SZ")
+                    .to_string(),
+            ]);
+        }
+
+        format!(
+            r#"These domains serve this project over HTTPS
+{table}
+"#,
+        )
+    }
+}
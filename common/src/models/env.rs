@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use comfy_table::{
+    modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Attribute, Cell, CellAlignment,
+    ContentArrangement, Table,
+};
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+/// An environment variable the runtime of a service is started with
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::env::Response))]
+pub struct Response {
+    pub key: String,
+    pub value: String,
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::DateTime))]
+    pub last_update: DateTime<Utc>,
+}
+
+pub fn get_table(vars: &Vec<Response>) -> String {
+    if vars.is_empty() {
+        format!(
+            "{}\n",
+            "No environment variables are set for this service".bold()
+        )
+    } else {
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+            .set_header(["Key", "Value", "Last updated"].map(|header| {
+                Cell::new(header)
+                    .set_alignment(CellAlignment::Center)
+                    .add_attribute(Attribute::Bold)
+            }));
+
+        for var in vars {
+            table.add_row(vec![
+                var.key.to_string(),
+                var.value.to_string(),
+                var.last_update.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            ]);
+        }
+
+        format!(
+            r#"These environment variables are set for this service, and apply the next time it is started
+{table}
+"#,
+        )
+    }
+}
@@ -45,12 +45,18 @@ pub enum ErrorKind {
     ProjectNotReady,
     ProjectUnavailable,
     CustomDomainNotFound,
+    NodeNotFound,
     InvalidCustomDomain,
     CustomDomainAlreadyExists,
+    CustomDomainMisconfigured,
     InvalidOperation,
     Internal,
     NotReady,
     ServiceUnavailable,
+    TooManyRequests,
+    RequestTooLarge,
+    ProjectTimedOut,
+    RateLimited,
 }
 
 impl From<ErrorKind> for ApiError {
@@ -84,7 +90,8 @@ impl From<ErrorKind> for ApiError {
             4. not be empty.
             5. be shorter than 63 characters.
             6. not contain profanity.
-            7. not be a reserved word."#,
+            7. not be a reserved word.
+            8. not contain `--`, which separates a project from its environments."#,
             ),
             ErrorKind::InvalidOperation => (
                 StatusCode::BAD_REQUEST,
@@ -96,12 +103,33 @@ impl From<ErrorKind> for ApiError {
             ),
             ErrorKind::InvalidCustomDomain => (StatusCode::BAD_REQUEST, "invalid custom domain"),
             ErrorKind::CustomDomainNotFound => (StatusCode::NOT_FOUND, "custom domain not found"),
+            ErrorKind::NodeNotFound => (StatusCode::NOT_FOUND, "node not found"),
             ErrorKind::CustomDomainAlreadyExists => {
                 (StatusCode::BAD_REQUEST, "custom domain already in use")
             }
+            ErrorKind::CustomDomainMisconfigured => (
+                StatusCode::BAD_REQUEST,
+                "custom domain does not resolve to the project, it needs a CNAME record pointing to the domain of the project",
+            ),
             ErrorKind::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized"),
             ErrorKind::Forbidden => (StatusCode::FORBIDDEN, "forbidden"),
             ErrorKind::NotReady => (StatusCode::INTERNAL_SERVER_ERROR, "service not ready"),
+            ErrorKind::RequestTooLarge => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "request body is larger than the project accepts",
+            ),
+            ErrorKind::ProjectTimedOut => (
+                StatusCode::GATEWAY_TIMEOUT,
+                "project took too long to respond",
+            ),
+            ErrorKind::TooManyRequests => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "too many deployments in the last hour",
+            ),
+            ErrorKind::RateLimited => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "too many requests were made to this project, please slow down",
+            ),
         };
         Self {
             message: error_message.to_string(),
@@ -121,6 +149,7 @@ impl From<StatusCode> for ApiError {
                 "we were unable to authorize your request. Is your key still valid?"
             },
             StatusCode::INTERNAL_SERVER_ERROR => "our server was unable to handle your request. A ticket should be created for us to fix this.",
+            StatusCode::TOO_MANY_REQUESTS => "too many requests were made",
             StatusCode::SERVICE_UNAVAILABLE => "we're experiencing a high workload right now, please try again in a little bit",
             StatusCode::BAD_REQUEST => {
                 warn!("responding to a BAD_REQUEST request with an unhelpful message. Use ErrorKind instead");
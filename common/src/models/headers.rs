@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+use crate::shuttle_toml::HttpConfig;
+
+/// The headers the gateway adds to the responses of a service, from the `[http]` table of the
+/// Shuttle.toml of its running deployment
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::headers::Request))]
+pub struct Request {
+    pub project: String,
+    pub service: String,
+    #[serde(default)]
+    #[cfg_attr(feature = "openapi", schema(value_type = Object))]
+    pub http: HttpConfig,
+}
@@ -0,0 +1,111 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// The usage of the active deployment of a service over a window of time
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::metrics::Response))]
+pub struct Response {
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::Uuid))]
+    pub deployment_id: Uuid,
+    /// The start of the window, which ends now
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::DateTime))]
+    pub since: DateTime<Utc>,
+    /// The samples taken in the window, oldest first
+    pub samples: Vec<Sample>,
+    /// How many times the deployment was started again in the window, like when the project woke
+    /// up or the deployer restarted
+    pub restarts: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::metrics::Sample))]
+pub struct Sample {
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::DateTime))]
+    pub timestamp: DateTime<Utc>,
+    /// The CPU used since the sample before, in percent of one core
+    pub cpu: f64,
+    /// The resident memory, in bytes
+    pub memory: u64,
+    /// How many requests were served since the sample before
+    pub requests: u64,
+}
+
+impl Response {
+    /// The average CPU usage over the window, in percent of one core
+    pub fn cpu_average(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+
+        Some(self.samples.iter().map(|sample| sample.cpu).sum::<f64>() / self.samples.len() as f64)
+    }
+
+    /// The highest CPU usage in the window, in percent of one core
+    pub fn cpu_peak(&self) -> Option<f64> {
+        self.samples
+            .iter()
+            .map(|sample| sample.cpu)
+            .reduce(f64::max)
+    }
+
+    /// The memory used when the last sample was taken
+    pub fn memory_latest(&self) -> Option<u64> {
+        self.samples.last().map(|sample| sample.memory)
+    }
+
+    /// The most memory used in the window
+    pub fn memory_peak(&self) -> Option<u64> {
+        self.samples.iter().map(|sample| sample.memory).max()
+    }
+
+    /// How many requests were served in the window
+    pub fn requests(&self) -> u64 {
+        self.samples.iter().map(|sample| sample.requests).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    use super::{Response, Sample};
+
+    #[test]
+    fn summary() {
+        let now = Utc::now();
+        let sample = |minutes, cpu, memory, requests| Sample {
+            timestamp: now - Duration::minutes(minutes),
+            cpu,
+            memory,
+            requests,
+        };
+        let mut response = Response {
+            deployment_id: Uuid::new_v4(),
+            since: now - Duration::hours(1),
+            samples: Vec::new(),
+            restarts: 0,
+        };
+
+        assert_eq!(response.cpu_average(), None);
+        assert_eq!(response.memory_latest(), None);
+        assert_eq!(response.requests(), 0);
+
+        response.samples = vec![
+            sample(3, 10.0, 30, 5),
+            sample(2, 50.0, 50, 0),
+            sample(1, 30.0, 40, 7),
+        ];
+
+        assert_eq!(response.cpu_average(), Some(30.0));
+        assert_eq!(response.cpu_peak(), Some(50.0));
+        assert_eq!(response.memory_latest(), Some(40));
+        assert_eq!(response.memory_peak(), Some(50));
+        assert_eq!(response.requests(), 12);
+    }
+}
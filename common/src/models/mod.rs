@@ -1,11 +1,26 @@
+pub mod access;
+pub mod access_log;
+pub mod artifacts;
+pub mod cache;
+pub mod cron;
 pub mod deployment;
+pub mod domain;
+pub mod env;
 pub mod error;
+pub mod headers;
+pub mod metrics;
+pub mod node;
+pub mod persist;
 pub mod project;
 pub mod resource;
+pub mod route;
 pub mod secret;
 pub mod service;
 pub mod stats;
+pub mod storage;
+pub mod upload;
 pub mod user;
+pub mod webhook;
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
@@ -23,6 +38,11 @@ pub trait ToJson {
 impl ToJson for reqwest::Response {
     async fn to_json<T: DeserializeOwned>(self) -> Result<T> {
         let status_code = self.status();
+        let retry_after = self
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
         let full = self.bytes().await?;
 
         trace!(
@@ -37,7 +57,7 @@ impl ToJson for reqwest::Response {
             serde_json::from_slice(&full).context("failed to parse a successful response")
         } else {
             trace!("parsing response to common error");
-            let res: error::ApiError = match serde_json::from_slice(&full) {
+            let mut res: error::ApiError = match serde_json::from_slice(&full) {
                 Ok(res) => res,
                 _ => {
                     trace!("getting error from status code");
@@ -45,6 +65,10 @@ impl ToJson for reqwest::Response {
                 }
             };
 
+            if let Some(seconds) = retry_after {
+                res.message = format!("{}, please try again in {seconds} seconds", res.message);
+            }
+
             Err(res.into())
         }
     }
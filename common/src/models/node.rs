@@ -0,0 +1,73 @@
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+/// A Docker host the gateway runs the containers of projects on
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::node::Response))]
+pub struct Response {
+    pub name: String,
+    /// The path to the socket of the Docker daemon, or its `tcp://` address
+    pub docker_host: String,
+    /// Whether new projects are kept off the node
+    pub draining: bool,
+    /// How many projects are on the node
+    pub projects: u32,
+}
+
+impl Display for Response {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({}): {} projects",
+            self.name, self.docker_host, self.projects
+        )?;
+
+        if self.draining {
+            write!(f, ", draining")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::node::Create))]
+pub struct Create {
+    pub name: String,
+    /// The path to the socket of the Docker daemon, or its `tcp://` address
+    pub docker_host: String,
+}
+
+/// Where to move a project to
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::node::Migrate))]
+pub struct Migrate {
+    /// The node to move the project to, or none for the one with the fewest projects
+    pub node: Option<String>,
+}
+
+/// A project which is being moved between nodes
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::node::Migration))]
+pub struct Migration {
+    pub project: String,
+    pub from: String,
+    pub to: String,
+}
+
+impl Display for Migration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Moving {} from {} to {}",
+            self.project, self.from, self.to
+        )
+    }
+}
@@ -0,0 +1,65 @@
+use comfy_table::{
+    modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Attribute, Cell, CellAlignment,
+    ContentArrangement, Table,
+};
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+use super::storage::Usage;
+
+/// The keys the persist instance of a service keeps in production
+#[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::persist::Response))]
+pub struct Response {
+    pub keys: Vec<Key>,
+    /// Bytes the values of all the keys take up
+    pub bytes: u64,
+    /// Bytes the values may take up, after which new values are refused
+    pub quota: u64,
+}
+
+/// A key of a persist instance, and the size of its value
+#[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::persist::Key))]
+pub struct Key {
+    pub key: String,
+    pub bytes: u64,
+}
+
+pub fn get_table(response: &Response) -> String {
+    let usage = Usage {
+        bytes: response.bytes,
+        quota: response.quota,
+    };
+
+    if response.keys.is_empty() {
+        return format!(
+            "{}\n{usage}\n",
+            "The persist instance of this service has no keys".bold()
+        );
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+        .set_header(["Key", "Size"].map(|header| {
+            Cell::new(header)
+                .set_alignment(CellAlignment::Center)
+                .add_attribute(Attribute::Bold)
+        }));
+
+    for key in &response.keys {
+        table.add_row(vec![
+            Cell::new(&key.key),
+            Cell::new(format!("{} B", key.bytes)).set_alignment(CellAlignment::Right),
+        ]);
+    }
+
+    format!("These keys are kept by the persist instance of this service\n{table}\n{usage}\n")
+}
@@ -25,6 +25,9 @@ pub struct Response {
     pub name: String,
     #[cfg_attr(feature = "openapi", schema(value_type = shuttle_common::models::project::State))]
     pub state: State,
+    /// The ports which forward raw TCP to the project
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tcp: Vec<TcpEndpoint>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, EnumString)]
@@ -72,7 +75,13 @@ impl Eq for State {}
 
 impl Display for Response {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "project '{}' is {}", self.name, self.state)
+        write!(f, "project '{}' is {}", self.name, self.state)?;
+
+        for endpoint in &self.tcp {
+            write!(f, "\n  {endpoint}")?;
+        }
+
+        Ok(())
     }
 }
 
@@ -185,6 +194,100 @@ pub struct AdminResponse {
     pub account_name: String,
 }
 
+/// What the gateway lets the requests to a project, and their responses, do before it cuts them
+/// off
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::project::ProxyLimits))]
+pub struct ProxyLimits {
+    /// Bytes the body of a request may have, after which it is refused with 413 Payload Too Large
+    pub max_request_body_bytes: u64,
+    /// Seconds the project has to start responding, after which 504 Gateway Timeout is returned
+    pub response_timeout_secs: u64,
+    /// Seconds the body of a request or of a response may go without data, after which the
+    /// connection is closed
+    pub idle_timeout_secs: u64,
+    /// Requests each client may make a second, after which 429 Too Many Requests is returned. A
+    /// rate of 0 does not limit the requests
+    pub requests_per_second: u32,
+    /// Requests each client may make at once before they have to slow down to the rate
+    pub burst: u32,
+}
+
+impl Display for ProxyLimits {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Request bodies of at most {:.1} MiB, {}s to start responding, and bodies idle for at most {}s",
+            self.max_request_body_bytes as f64 / (1024.0 * 1024.0),
+            self.response_timeout_secs,
+            self.idle_timeout_secs
+        )?;
+
+        if self.requests_per_second == 0 {
+            write!(f, ", with no limit on the requests of each client")
+        } else {
+            write!(
+                f,
+                ", with {} requests a second from each client after a burst of {}",
+                self.requests_per_second, self.burst
+            )
+        }
+    }
+}
+
+/// What the gateway serves instead of the service of a project while it is under maintenance
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::project::Maintenance))]
+pub struct Maintenance {
+    /// Status the page is served with
+    #[serde(default = "maintenance_status")]
+    pub status: u16,
+    /// HTML of the page, or none for the maintenance page of shuttle
+    pub page: Option<String>,
+}
+
+/// Function to serve maintenance pages with 503 Service Unavailable as a serde default
+pub const fn maintenance_status() -> u16 {
+    503
+}
+
+/// A port of the gateway which forwards raw TCP, like that of MQTT or of gRPC over TLS, to a port
+/// the services of a project listen on
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::project::TcpEndpoint))]
+pub struct TcpEndpoint {
+    /// Where clients connect to, like `my-project.shuttleapp.rs:30001`
+    pub address: String,
+    /// Port of the gateway
+    pub port: u16,
+    /// Port of the project the connections are forwarded to
+    pub target_port: u16,
+}
+
+/// Ask for a port of the gateway to forward raw TCP to a port of the project
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::project::TcpRequest))]
+pub struct TcpRequest {
+    /// Port the services of the project listen on
+    pub target_port: u16,
+}
+
+impl Display for TcpEndpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "tcp://{} {} port {}",
+            self.address.clone().bold(),
+            "->".dim(),
+            self.target_port
+        )
+    }
+}
+
 pub fn get_table(projects: &Vec<Response>, page: u32) -> String {
     if projects.is_empty() {
         // The page starts at 1 in the CLI.
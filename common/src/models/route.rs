@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// The deployment of the service of an environment which is running, which the gateway routes the
+/// host of the service to
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::route::Request))]
+pub struct Request {
+    pub project: String,
+    pub service: String,
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::Uuid))]
+    pub deployment_id: Uuid,
+}
+
+/// A route of the gateway, from the host a service is served on to the deployment of the service
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::route::Response))]
+pub struct Response {
+    pub hostname: String,
+    pub project: String,
+    pub service: String,
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::Uuid))]
+    pub deployment_id: Uuid,
+}
@@ -59,3 +59,108 @@ URI:           {}
         write!(f, "{deployment}")
     }
 }
+
+/// How many runtimes the running deployment of a service is served by, which take turns answering
+/// its requests
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::service::Replicas))]
+pub struct Replicas {
+    pub replicas: u32,
+    /// How the replicas take turns, or none to keep how they did when scaling
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<shuttle_common::models::service::Routing>))]
+    pub routing: Option<Routing>,
+}
+
+/// Cookie which keeps the clients of a service with sticky sessions on the same replica, unless it
+/// was given another
+pub const STICKY_COOKIE: &str = "shuttle-replica";
+
+/// How the replicas of a service take turns answering its requests
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::service::Routing))]
+pub enum Routing {
+    /// Each replica answers the next request in turn
+    #[default]
+    RoundRobin,
+    /// Each client keeps being answered by the replica which answered it first, which a cookie
+    /// remembers, so that stateful apps like those of websockets can be scaled
+    Sticky { cookie: String },
+    /// The requests with the same value of a header are answered by the same replica, for as long
+    /// as the replicas stay the same. Those without the header take turns
+    Hash { header: String },
+}
+
+impl Display for Routing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RoundRobin => write!(f, "round-robin"),
+            Self::Sticky { cookie } if cookie == STICKY_COOKIE => write!(f, "sticky"),
+            Self::Sticky { cookie } => write!(f, "sticky:{cookie}"),
+            Self::Hash { header } => write!(f, "hash:{header}"),
+        }
+    }
+}
+
+impl FromStr for Routing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (policy, name) = match s.split_once(':') {
+            Some((policy, name)) => (policy, Some(name)),
+            None => (s, None),
+        };
+
+        match (policy, name) {
+            ("round-robin", None) => Ok(Self::RoundRobin),
+            ("sticky", None) => Ok(Self::Sticky {
+                cookie: STICKY_COOKIE.to_string(),
+            }),
+            ("sticky", Some(cookie)) if !cookie.is_empty() => Ok(Self::Sticky {
+                cookie: cookie.to_string(),
+            }),
+            ("hash", Some(header)) if !header.is_empty() => Ok(Self::Hash {
+                header: header.to_ascii_lowercase(),
+            }),
+            _ => Err(format!(
+                "'{s}' is not a routing, expected `round-robin`, `sticky`, `sticky:<cookie>` or `hash:<header>`"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Routing, STICKY_COOKIE};
+
+    #[test]
+    fn routing() {
+        for routing in [
+            Routing::RoundRobin,
+            Routing::Sticky {
+                cookie: STICKY_COOKIE.to_string(),
+            },
+            Routing::Sticky {
+                cookie: "session".to_string(),
+            },
+            Routing::Hash {
+                header: "x-user-id".to_string(),
+            },
+        ] {
+            assert_eq!(routing.to_string().parse::<Routing>().unwrap(), routing);
+        }
+
+        assert_eq!(
+            "hash:X-User-Id".parse::<Routing>().unwrap(),
+            Routing::Hash {
+                header: "x-user-id".to_string()
+            }
+        );
+        assert!("hash".parse::<Routing>().is_err());
+        assert!("round-robin:x".parse::<Routing>().is_err());
+        assert!("random".parse::<Routing>().is_err());
+    }
+}
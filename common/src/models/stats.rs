@@ -8,6 +8,10 @@ use uuid::Uuid;
 #[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::stats::LoadRequest))]
 pub struct LoadRequest {
     pub id: Uuid,
+
+    /// The project the build is for, so that builds can be shared fairly between projects
+    #[serde(default)]
+    pub project: Option<String>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -0,0 +1,32 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+/// How much of its quota the volume of a service takes up
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::storage::Usage))]
+pub struct Usage {
+    /// Bytes the files in the volume take up
+    pub bytes: u64,
+    /// Bytes the volume may take up, after which the running deployment of the service is stopped
+    pub quota: u64,
+}
+
+impl Display for Usage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Using {:.1} MiB of {:.1} MiB ({:.1}%)",
+            self.bytes as f64 / (1024.0 * 1024.0),
+            self.quota as f64 / (1024.0 * 1024.0),
+            if self.quota == 0 {
+                100.0
+            } else {
+                self.bytes as f64 * 100.0 / self.quota as f64
+            }
+        )
+    }
+}
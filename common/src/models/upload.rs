@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+/// How much of an archive was uploaded, so that an interrupted upload can be resumed
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::upload::Response))]
+pub struct Response {
+    /// The number of bytes which were received
+    pub received: u64,
+}
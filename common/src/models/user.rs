@@ -6,3 +6,36 @@ pub struct Response {
     pub key: String,
     pub account_tier: String,
 }
+
+/// A login which was started on a device without a browser, like a terminal, to be approved by
+/// the user in a browser
+#[derive(Deserialize, Serialize)]
+pub struct DeviceAuthorizationResponse {
+    /// The secret the device polls for the API key with
+    pub device_code: String,
+    /// The code the user enters in the browser, like `WDJB-MJHT`
+    pub user_code: String,
+    /// Where the user enters the code
+    pub verification_uri: String,
+    /// Where the user approves the login, with the code already filled in
+    pub verification_uri_complete: String,
+    /// Seconds until the codes expire
+    pub expires_in: u64,
+    /// Seconds to wait between polls
+    pub interval: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DeviceTokenRequest {
+    pub device_code: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DeviceTokenResponse {
+    pub api_key: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DeviceApproveRequest {
+    pub user_code: String,
+}
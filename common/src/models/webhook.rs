@@ -0,0 +1,167 @@
+use chrono::{DateTime, Utc};
+use comfy_table::{
+    modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Attribute, Cell, CellAlignment,
+    ContentArrangement, Table,
+};
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::deployment::State;
+
+/// The header with the signature of a payload, like `t=1685000000,v1=5257a869...`. The signature is
+/// the hex encoded HMAC-SHA256 of `{t}.{body}` with the secret of the webhook, where `t` is the
+/// unix timestamp of when the payload was sent.
+pub const SIGNATURE_HEADER: &str = "Shuttle-Signature";
+
+/// The header with the [Event] of a payload
+pub const EVENT_HEADER: &str = "Shuttle-Event";
+
+/// What happened to a deployment, which webhooks are called for
+#[derive(Clone, Copy, Debug, Deserialize, Display, EnumString, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::webhook::Event))]
+pub enum Event {
+    /// A deployment was queued to be built
+    DeployStarted,
+    /// A deployment started and serves the requests of its service
+    DeploySucceeded,
+    /// A deployment failed to build or to start
+    DeployFailed,
+    /// A running deployment crashed
+    Crashed,
+    /// A deployment which stopped on its own is started again
+    Restarted,
+}
+
+/// A webhook to register
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::webhook::Request))]
+pub struct Request {
+    /// The URL to POST the payloads to
+    pub url: String,
+    /// The events to call it for, or every event when this is empty
+    #[serde(default)]
+    pub events: Vec<Event>,
+}
+
+/// A registered webhook
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::webhook::Response))]
+pub struct Response {
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::Uuid))]
+    pub id: Uuid,
+    pub url: String,
+    pub events: Vec<Event>,
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::DateTime))]
+    pub created_at: DateTime<Utc>,
+}
+
+/// A webhook which was just registered, with the secret its payloads are signed with. The secret is
+/// not shown again.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::webhook::Created))]
+pub struct Created {
+    #[serde(flatten)]
+    pub webhook: Response,
+    pub secret: String,
+}
+
+/// What a webhook is called with
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::webhook::Payload))]
+pub struct Payload {
+    pub event: Event,
+    pub project: String,
+    pub service: String,
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::Uuid))]
+    pub deployment_id: Uuid,
+    #[cfg_attr(feature = "openapi", schema(value_type = shuttle_common::deployment::State))]
+    pub state: State,
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::DateTime))]
+    pub timestamp: DateTime<Utc>,
+    /// What happened, for people. Chat tools like Slack show this field as the message.
+    pub text: String,
+}
+
+impl Payload {
+    pub fn new(
+        event: Event,
+        project: String,
+        service: String,
+        deployment_id: Uuid,
+        state: State,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        let what = match event {
+            Event::DeployStarted => "was queued",
+            Event::DeploySucceeded => "is running",
+            Event::DeployFailed => "failed",
+            Event::Crashed => "crashed",
+            Event::Restarted => "is being restarted",
+        };
+        let text = format!("Deployment {deployment_id} of {service} in {project} {what}");
+
+        Self {
+            event,
+            project,
+            service,
+            deployment_id,
+            state,
+            timestamp,
+            text,
+        }
+    }
+}
+
+pub fn get_table(webhooks: &Vec<Response>) -> String {
+    if webhooks.is_empty() {
+        format!("{}\n", "No webhooks are registered for this project".bold())
+    } else {
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+            .set_header(["ID", "URL", "Events", "Created"].map(|header| {
+                Cell::new(header)
+                    .set_alignment(CellAlignment::Center)
+                    .add_attribute(Attribute::Bold)
+            }));
+
+        for webhook in webhooks {
+            let events = if webhook.events.is_empty() {
+                "all".to_string()
+            } else {
+                webhook
+                    .events
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+
+            table.add_row(vec![
+                webhook.id.to_string(),
+                webhook.url.clone(),
+                events,
+                webhook.created_at.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            ]);
+        }
+
+        format!(
+            r#"These webhooks are called when the deployments of this project change
+{table}
+"#,
+        )
+    }
+}
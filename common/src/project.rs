@@ -7,6 +7,10 @@ use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
+/// The separator between the name of a project and one of its environments in the name of the
+/// service of that environment, which is also the subdomain it is served at
+pub const ENVIRONMENT_SEPARATOR: &str = "--";
+
 /// Project names should conform to valid Host segments (or labels)
 /// as per [IETF RFC 1123](https://datatracker.ietf.org/doc/html/rfc1123).
 /// Initially we'll implement a strict subset of the IETF RFC 1123, concretely:
@@ -69,6 +73,33 @@ impl ProjectName {
     pub fn as_str(&self) -> &str {
         self.0.as_str()
     }
+
+    /// The name of the service of this project in `environment`, or of its main service
+    pub fn service_name(&self, environment: Option<&EnvironmentName>) -> String {
+        match environment {
+            Some(environment) => format!("{self}{ENVIRONMENT_SEPARATOR}{environment}"),
+            None => self.to_string(),
+        }
+    }
+
+    /// Split the name of a service into the project it belongs to and its environment
+    pub fn split_service_name(service_name: &str) -> (&str, Option<&str>) {
+        match service_name.split_once(ENVIRONMENT_SEPARATOR) {
+            Some((project, environment)) => (project, Some(environment)),
+            None => (service_name, None),
+        }
+    }
+
+    /// The first label of the host a service is served on, which is the project for its main
+    /// service and `{environment}--{project}` for the service of an environment
+    pub fn service_host_label(service_name: &str) -> String {
+        match Self::split_service_name(service_name) {
+            (project, Some(environment)) => {
+                format!("{environment}{ENVIRONMENT_SEPARATOR}{project}")
+            }
+            (project, None) => project.to_string(),
+        }
+    }
 }
 
 impl AsRef<String> for ProjectName {
@@ -88,9 +119,46 @@ impl FromStr for ProjectName {
     }
 }
 
+/// An environment of a project, like `staging`, which gets a service of its own with separate
+/// deployments, resources and secrets.
+///
+/// Environment names follow the rules of project names, but may not contain the
+/// [ENVIRONMENT_SEPARATOR].
+#[derive(Clone, Serialize, Debug, Eq, PartialEq)]
+pub struct EnvironmentName(String);
+
+impl<'de> Deserialize<'de> for EnvironmentName {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = String::deserialize(deserializer)?;
+        s.parse().map_err(DeError::custom)
+    }
+}
+
+impl std::fmt::Display for EnvironmentName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for EnvironmentName {
+    type Err = ProjectNameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if ProjectName::is_valid(s) && !s.contains(ENVIRONMENT_SEPARATOR) {
+            Ok(EnvironmentName(s.to_string()))
+        } else {
+            Err(ProjectNameError::InvalidEnvironment(s.to_string()))
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ProjectNameError {
     InvalidName(String),
+    InvalidEnvironment(String),
 }
 
 impl Display for ProjectNameError {
@@ -106,6 +174,11 @@ impl Display for ProjectNameError {
 4. not contain profanity.
 5. not be a reserved word."#,
             ),
+            ProjectNameError::InvalidEnvironment(name) => write!(
+                f,
+                "`{name}` is an invalid environment name. It has to be a valid project name, \
+                 without `{ENVIRONMENT_SEPARATOR}`."
+            ),
         }
     }
 }
@@ -162,4 +235,28 @@ pub mod tests {
             assert!(project_name.is_err(), "{:?} was ok", hostname);
         }
     }
+
+    #[test]
+    fn environments() {
+        let project_name = ProjectName::from_str("my-app").unwrap();
+        let staging = EnvironmentName::from_str("staging").unwrap();
+
+        assert_eq!(project_name.service_name(None), "my-app");
+        assert_eq!(project_name.service_name(Some(&staging)), "my-app--staging");
+        assert_eq!(
+            ProjectName::split_service_name("my-app--staging"),
+            ("my-app", Some("staging"))
+        );
+        assert_eq!(ProjectName::split_service_name("my-app"), ("my-app", None));
+        assert_eq!(
+            ProjectName::service_host_label("my-app--staging"),
+            "staging--my-app"
+        );
+        assert_eq!(ProjectName::service_host_label("my-app"), "my-app");
+
+        assert!(EnvironmentName::from_str("pre-production").is_ok());
+        assert!(EnvironmentName::from_str("pre--production").is_err());
+        assert!(EnvironmentName::from_str("-staging").is_err());
+        assert!(EnvironmentName::from_str("").is_err());
+    }
 }
@@ -35,6 +35,8 @@ pub enum Type {
     Secrets,
     StaticFolder,
     Persist,
+    Volume,
+    RunArgs,
 }
 
 impl Response {
@@ -58,6 +60,8 @@ impl Display for Type {
             Type::Secrets => write!(f, "secrets"),
             Type::StaticFolder => write!(f, "static_folder"),
             Type::Persist => write!(f, "persist"),
+            Type::Volume => write!(f, "volume"),
+            Type::RunArgs => write!(f, "run_args"),
         }
     }
 }
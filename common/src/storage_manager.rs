@@ -1,13 +1,48 @@
-use std::{fs, io, path::PathBuf};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
 
 use uuid::Uuid;
 
+use crate::deployment::Environment;
+
+/// The directory in the storage of a service which is its volume, kept across its deployments
+pub const VOLUME_DIRECTORY: &str = "shuttle-volume";
+
+/// The directory in the storage of a service which keeps the values of its persist instances
+pub const PERSIST_DIRECTORY: &str = "shuttle-persist";
+
+/// Bytes the values of the persist instance of a service may take up in each environment
+pub const PERSIST_QUOTA: u64 = 64 * 1024 * 1024;
+
+/// Path to the values of the persist instance of `service_name` in `environment`, from the path
+/// of the storage of the service
+pub fn persist_path(storage_path: &Path, service_name: &str, environment: Environment) -> PathBuf {
+    let environment = match environment {
+        Environment::Local => "local",
+        Environment::Production => "production",
+    };
+
+    storage_path
+        .join(PERSIST_DIRECTORY)
+        .join(service_name)
+        .join(environment)
+}
+
 pub trait StorageManager: Sync + Send {
     /// Path for a specific service build files
     fn service_build_path(&self, service_name: &str) -> Result<PathBuf, io::Error>;
 
     /// Path to folder for storing service files
     fn service_storage_path(&self, service_name: &str) -> Result<PathBuf, io::Error>;
+
+    /// Path to the volume of a service, which is kept across its deployments
+    fn service_volume_path(&self, service_name: &str) -> Result<PathBuf, io::Error> {
+        Ok(self
+            .service_storage_path(service_name)?
+            .join(VOLUME_DIRECTORY))
+    }
 }
 
 /// Manager to take care of directories for storing project, services and deployment files for deployer
@@ -44,6 +79,53 @@ impl ArtifactsStorageManager {
         Ok(executable_path)
     }
 
+    /// The directory in which the assets of deployments are stored.
+    pub fn assets_path(&self) -> Result<PathBuf, io::Error> {
+        let assets_path = self.artifacts_path.join("shuttle-assets");
+        fs::create_dir_all(&assets_path)?;
+
+        Ok(assets_path)
+    }
+
+    /// Path to the assets shipped with a deployment
+    pub fn deployment_assets_path(&self, deployment_id: &Uuid) -> Result<PathBuf, io::Error> {
+        let assets_path = self.assets_path()?.join(deployment_id.to_string());
+
+        Ok(assets_path)
+    }
+
+    /// The directory in which the settings for running deployments are stored.
+    pub fn run_configs_path(&self) -> Result<PathBuf, io::Error> {
+        let run_configs_path = self.artifacts_path.join("shuttle-run-configs");
+        fs::create_dir_all(&run_configs_path)?;
+
+        Ok(run_configs_path)
+    }
+
+    /// Path to the settings for running a deployment, from the Shuttle.toml it was built with
+    pub fn deployment_run_config_path(&self, deployment_id: &Uuid) -> Result<PathBuf, io::Error> {
+        let run_config_path = self
+            .run_configs_path()?
+            .join(format!("{deployment_id}.json"));
+
+        Ok(run_config_path)
+    }
+
+    /// The directory in which archives are kept while they are uploaded.
+    pub fn uploads_path(&self) -> Result<PathBuf, io::Error> {
+        let uploads_path = self.artifacts_path.join("shuttle-uploads");
+        fs::create_dir_all(&uploads_path)?;
+
+        Ok(uploads_path)
+    }
+
+    /// Path to the part of the archive with the content hash `hash` which was uploaded so far
+    pub fn upload_path(&self, hash: &str) -> Result<PathBuf, io::Error> {
+        let upload_path = self.uploads_path()?.join(hash);
+
+        Ok(upload_path)
+    }
+
     /// Path of the directory to store user files
     pub fn storage_path(&self) -> Result<PathBuf, io::Error> {
         let storage_path = self.artifacts_path.join("shuttle-storage");
@@ -0,0 +1,4 @@
+ALTER TABLE deployments ADD COLUMN git_commit_id TEXT;  -- Commit the deployment was made from.
+ALTER TABLE deployments ADD COLUMN git_commit_msg TEXT; -- Summary of the message of that commit.
+ALTER TABLE deployments ADD COLUMN git_branch TEXT;     -- Branch that was checked out.
+ALTER TABLE deployments ADD COLUMN git_dirty BOOLEAN;   -- Whether uncommitted changes were deployed.
@@ -0,0 +1 @@
+ALTER TABLE deployments ADD COLUMN source_hash TEXT; -- Hash of the deployed files, to tell whether they changed since.
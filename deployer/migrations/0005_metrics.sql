@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS metrics (
+    id TEXT,           -- The deployment that this sample was taken of.
+    timestamp INTEGER, -- Unix epoch timestamp.
+    cpu REAL,          -- CPU used since the sample before, in percent of one core.
+    memory INTEGER,    -- Resident memory, in bytes.
+    requests INTEGER,  -- Requests served since the sample before.
+    PRIMARY KEY (id, timestamp),
+    FOREIGN KEY(id) REFERENCES deployments(id)
+);
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS routes (
+    service_id TEXT PRIMARY KEY, -- The service whose requests are routed.
+    deployment_id TEXT,          -- The deployment its requests are sent to.
+    FOREIGN KEY(service_id) REFERENCES services(id),
+    FOREIGN KEY(deployment_id) REFERENCES deployments(id)
+);
+
+-- Keep sending requests to the latest running deployment of every service
+INSERT INTO routes (service_id, deployment_id)
+    SELECT service_id, id FROM (
+        SELECT service_id, id, MAX(last_update)
+        FROM deployments
+        WHERE state = 'Running'
+        GROUP BY service_id
+    );
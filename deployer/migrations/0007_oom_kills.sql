@@ -0,0 +1 @@
+ALTER TABLE deployments ADD COLUMN oom_kills INTEGER NOT NULL DEFAULT 0; -- How often the deployment was killed for running out of memory.
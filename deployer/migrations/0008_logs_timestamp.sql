@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS logs_timestamp ON logs (timestamp); -- To prune the oldest logs.
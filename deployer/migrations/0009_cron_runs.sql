@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS cron_runs (
+    id TEXT,            -- The deployment that this job was run by.
+    name TEXT,          -- The name of the job in the Shuttle.toml.
+    started_at INTEGER, -- Unix epoch of when the job was due.
+    finished_at INTEGER,-- Unix epoch of when the job finished, if it did.
+    status TEXT,        -- Enum indicating how the run went.
+    error TEXT,         -- Why the run failed, if it did.
+    PRIMARY KEY (id, name, started_at),
+    FOREIGN KEY(id) REFERENCES deployments(id)
+);
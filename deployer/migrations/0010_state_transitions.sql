@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS state_transitions (
+    id TEXT,           -- The deployment that changed its state.
+    state TEXT,        -- Enum indicating the state the deployment changed to.
+    timestamp INTEGER, -- Unix epoch of when the state changed.
+    PRIMARY KEY (id, timestamp, state),
+    FOREIGN KEY(id) REFERENCES deployments(id)
+);
+
+-- Keep the transitions which are still in the logs, and at least the current state of every deployment
+INSERT OR IGNORE INTO state_transitions (id, state, timestamp)
+    SELECT id, state, timestamp FROM logs WHERE fields = '"NEW STATE"';
+INSERT OR IGNORE INTO state_transitions (id, state, timestamp)
+    SELECT id, state, last_update FROM deployments
+    WHERE NOT EXISTS (SELECT 1 FROM state_transitions AS t WHERE t.id = deployments.id);
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS env_vars (
+    service_id TEXT,      -- Identifier of the service this variable belongs to.
+    key TEXT,             -- Name of the environment variable.
+    value TEXT,           -- The value it is set to in the runtime process.
+    last_update INTEGER,  -- Unix epoch of the last update of the variable.
+    PRIMARY KEY (service_id, key),
+    FOREIGN KEY(service_id) REFERENCES services(id)
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS artifact_retention (
+    id INTEGER PRIMARY KEY CHECK (id = 0), -- There is one retention for the project of this deployer.
+    keep_deployments INTEGER,              -- How many of the latest deployments of each service keep their artifacts.
+    max_bytes INTEGER,                     -- Bytes the artifacts and build caches may take up, if limited.
+    last_update INTEGER                    -- Unix epoch of when an admin chose this retention.
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS replicas (
+    service_id TEXT PRIMARY KEY, -- The service which was scaled.
+    count INTEGER,               -- How many runtimes its running deployment is served by.
+    last_update INTEGER,         -- Unix epoch of when it was last scaled.
+    FOREIGN KEY(service_id) REFERENCES services(id)
+);
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS webhooks (
+    id TEXT PRIMARY KEY, -- Identifier of the webhook.
+    url TEXT,            -- The URL the payloads are posted to.
+    secret TEXT,         -- The key the payloads are signed with.
+    events TEXT,         -- JSON array of the events it is called for, or an empty one for every event.
+    created_at INTEGER   -- Unix epoch of when it was registered.
+);
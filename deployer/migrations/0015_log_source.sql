@@ -0,0 +1 @@
+ALTER TABLE logs ADD COLUMN source TEXT NOT NULL DEFAULT 'Tracing'; -- Whether the log was traced, or printed by the service to stdout or stderr.
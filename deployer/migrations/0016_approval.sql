@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS approval (
+    id INTEGER PRIMARY KEY CHECK (id = 0), -- There is one approval gate for the project of this deployer.
+    required BOOLEAN,                      -- Whether its deployments are held after they are built, until they are approved.
+    last_update INTEGER                    -- Unix epoch of when it was last chosen.
+);
@@ -0,0 +1 @@
+ALTER TABLE deployments ADD COLUMN trace_id TEXT; -- The trace the deployment was made in, to find its spans across the services which handled it.
@@ -0,0 +1 @@
+ALTER TABLE replicas ADD COLUMN routing JSON; -- How its replicas take turns, when it is not round-robin.
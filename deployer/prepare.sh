@@ -13,7 +13,9 @@ if [[ $PROD != "true" ]]; then
     shuttle-runtime = { path = "/usr/src/shuttle/runtime" }
 
     shuttle-aws-rds = { path = "/usr/src/shuttle/resources/aws-rds" }
+    shuttle-dedicated-db = { path = "/usr/src/shuttle/resources/dedicated-db" }
     shuttle-persist = { path = "/usr/src/shuttle/resources/persist" }
+    shuttle-qdrant = { path = "/usr/src/shuttle/resources/qdrant" }
     shuttle-shared-db = { path = "/usr/src/shuttle/resources/shared-db" }
     shuttle-secrets = { path = "/usr/src/shuttle/resources/secrets" }
     shuttle-static-folder = { path = "/usr/src/shuttle/resources/static-folder" }
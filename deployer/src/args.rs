@@ -4,6 +4,7 @@ use clap::Parser;
 use fqdn::FQDN;
 use hyper::Uri;
 use shuttle_common::project::ProjectName;
+use shuttle_service::builder::AuditPolicy;
 use tonic::transport::Endpoint;
 
 /// Program to handle the deploys for a single project
@@ -54,4 +55,100 @@ pub struct Args {
     /// Add an auth layer to deployer for local development
     #[arg(long)]
     pub local: bool,
+
+    /// Wrapper to compile deployments with, like `sccache`, to share compilation artifacts between deployments
+    #[clap(long, env = "SHUTTLE_RUSTC_WRAPPER")]
+    pub rustc_wrapper: Option<PathBuf>,
+
+    /// Seconds after which a build is aborted
+    #[clap(long, default_value = "1200")]
+    pub build_timeout: u64,
+
+    /// Folder to build deployments in, with a subfolder per service. Defaults to the target folder of each service
+    #[clap(long, env = "SHUTTLE_BUILD_TARGET_DIR")]
+    pub build_target_dir: Option<PathBuf>,
+
+    /// Install the Rust toolchain pinned by a service's rust-toolchain.toml, and the wasm32-wasi target, when they are missing
+    #[clap(long)]
+    pub install_toolchain: bool,
+
+    /// Whether to check deployments for dependencies with known vulnerabilities (`off`, `warn` or `deny`)
+    #[clap(long, env = "SHUTTLE_AUDIT_POLICY", default_value = "off")]
+    pub audit_policy: AuditPolicy,
+
+    /// Target triple to build deployments for, like `x86_64-unknown-linux-musl` for static executables
+    #[clap(long, env = "SHUTTLE_BUILD_TARGET")]
+    pub build_target: Option<String>,
+
+    /// Image to build deployments in, with the Rust toolchain, so that builds run in throwaway containers without network instead of on this machine
+    #[clap(long, env = "SHUTTLE_BUILD_IMAGE")]
+    pub build_image: Option<String>,
+
+    /// Container engine to run the builds in the build image with
+    #[clap(long, env = "SHUTTLE_BUILD_ENGINE", default_value = "docker")]
+    pub build_engine: PathBuf,
+
+    /// Memory a build in the build image may use, in MiB
+    #[clap(long, env = "SHUTTLE_BUILD_MEMORY_LIMIT")]
+    pub build_memory_limit: Option<u64>,
+
+    /// How many cores a build in the build image may use, like `1.5`
+    #[clap(long, env = "SHUTTLE_BUILD_CPU_LIMIT")]
+    pub build_cpu_limit: Option<f64>,
+
+    /// Disk space a build in the build image may use in its container, in MiB. Needs a storage driver with quotas
+    #[clap(long, env = "SHUTTLE_BUILD_DISK_LIMIT")]
+    pub build_disk_limit: Option<u64>,
+
+    /// Folder to keep the executables of builds in, so that deploying an unchanged project again skips compilation
+    #[clap(long, env = "SHUTTLE_ARTIFACT_STORE")]
+    pub artifact_store: Option<PathBuf>,
+
+    /// How many deployments can be built at the same time
+    #[clap(long, default_value = "4")]
+    pub build_concurrency: usize,
+
+    /// How many builds to keep in the artifact store
+    #[clap(long, default_value = "50")]
+    pub artifact_store_keep: usize,
+
+    /// How many of the latest deployments of each service keep their executables and assets, so that they can be rolled back to
+    #[clap(long, env = "SHUTTLE_ARTIFACT_KEEP_DEPLOYMENTS", default_value = "10")]
+    pub artifact_keep_deployments: u32,
+
+    /// Size in MiB the artifacts and build caches of the project may take up, after which the build caches and then the artifacts of the oldest deployments are removed
+    #[clap(long, env = "SHUTTLE_ARTIFACT_QUOTA")]
+    pub artifact_quota: Option<u64>,
+
+    /// Days to keep the logs of deployments for
+    #[clap(long, env = "SHUTTLE_LOG_RETENTION_DAYS", default_value = "30")]
+    pub log_retention_days: u32,
+
+    /// Size in MiB the logs of deployments may take up, after which the oldest ones are removed
+    #[clap(long, env = "SHUTTLE_LOG_QUOTA", default_value = "512")]
+    pub log_quota: u64,
+
+    /// Memory a deployment may use, in MiB, before it is killed. Deployments are not limited by default
+    #[clap(long, env = "SHUTTLE_MEMORY_LIMIT")]
+    pub memory_limit: Option<u64>,
+
+    /// How many cores a deployment may use, like `0.5`, before it is throttled. Deployments are not limited by default
+    #[clap(long, env = "SHUTTLE_CPU_LIMIT")]
+    pub cpu_limit: Option<f64>,
+
+    /// Cgroup (v2) to put the cgroups which enforce the limits of deployments in
+    #[clap(long, default_value = "/sys/fs/cgroup/shuttle")]
+    pub cgroup_root: PathBuf,
+
+    /// Seconds a deployment which is stopped or replaced gets to finish the work it has in flight
+    #[clap(long, env = "SHUTTLE_DRAIN_TIMEOUT", default_value = "30")]
+    pub drain_timeout: u64,
+
+    /// The most runtimes the running deployment of a service may be scaled to
+    #[clap(long, env = "SHUTTLE_MAX_REPLICAS", default_value = "10")]
+    pub max_replicas: u32,
+
+    /// Size in MiB the volume of each service may take up, after which its running deployment is stopped
+    #[clap(long, env = "SHUTTLE_VOLUME_QUOTA", default_value = "1024")]
+    pub volume_quota: u64,
 }
@@ -0,0 +1,70 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use shuttle_service::builder::CancellationToken;
+use uuid::Uuid;
+
+/// The cancellation tokens of the deployments which are queued or building
+#[derive(Clone, Default)]
+pub struct Cancellations(Arc<Mutex<HashMap<Uuid, CancellationToken>>>);
+
+impl Cancellations {
+    /// Get the token which is cancelled when deployment `id` is, starting to track it if needed
+    pub fn track(&self, id: Uuid) -> CancellationToken {
+        self.0.lock().unwrap().entry(id).or_default().clone()
+    }
+
+    /// Cancel deployment `id`, if it is still queued or building
+    pub fn cancel(&self, id: &Uuid) -> bool {
+        match self.0.lock().unwrap().get(id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop tracking deployment `id` once it is done building, getting whether it was cancelled.
+    /// Once this returned, the deployment cannot be cancelled anymore.
+    pub fn finish(&self, id: &Uuid) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .remove(id)
+            .map_or(false, |token| token.is_cancelled())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::Cancellations;
+
+    #[test]
+    fn cancel() {
+        let cancellations = Cancellations::default();
+        let id = Uuid::new_v4();
+
+        // Nothing to cancel for deployments which are not tracked
+        assert!(!cancellations.cancel(&id));
+
+        let token = cancellations.track(id);
+        assert!(!token.is_cancelled());
+        assert!(cancellations.cancel(&id));
+        assert!(token.is_cancelled());
+        assert!(cancellations.finish(&id));
+
+        // Nor once they are done
+        assert!(!cancellations.cancel(&id));
+        assert!(!cancellations.finish(&id));
+
+        let other = Uuid::new_v4();
+        cancellations.track(other);
+        assert!(!cancellations.finish(&other));
+        assert!(!cancellations.cancel(&other));
+    }
+}
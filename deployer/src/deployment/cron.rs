@@ -0,0 +1,246 @@
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use chrono::Utc;
+use hyper::{Body, Client, Request};
+use shuttle_common::shuttle_toml::CronJob;
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::persistence::{CronRun, CronStatus, DeploymentUpdater};
+
+/// Keep running the scheduled `jobs` of deployment `id`, by invoking their endpoints on the service
+/// at `address`. This never returns, so the jobs run for as long as this is polled.
+///
+/// A job which is due while its previous run is still going is skipped, so that runs never overlap.
+pub async fn schedule(
+    id: Uuid,
+    address: SocketAddr,
+    jobs: &[CronJob],
+    deployment_updater: &impl DeploymentUpdater,
+) -> Infallible {
+    futures::future::join_all(
+        jobs.iter()
+            .map(|job| schedule_job(id, address, job, deployment_updater)),
+    )
+    .await;
+
+    futures::future::pending().await
+}
+
+async fn schedule_job(
+    id: Uuid,
+    address: SocketAddr,
+    job: &CronJob,
+    deployment_updater: &impl DeploymentUpdater,
+) {
+    let schedule = match job.schedule() {
+        Ok(schedule) => schedule,
+        Err(error) => {
+            warn!(%error, job = %job.name, "not scheduling job with an invalid schedule");
+            return;
+        }
+    };
+    let running = Arc::new(AtomicBool::new(false));
+    let mut last = Utc::now();
+
+    // The schedule is followed from the last time the job was due, in case the clock of the
+    // timer is a little ahead of the wall clock
+    loop {
+        let Some(due) = schedule.after(&last).next() else {
+            return;
+        };
+
+        sleep((due - Utc::now()).to_std().unwrap_or_default()).await;
+        last = due;
+
+        let mut run = CronRun {
+            id,
+            name: job.name.clone(),
+            started_at: due,
+            finished_at: None,
+            status: CronStatus::Running,
+            error: None,
+        };
+
+        if running.swap(true, Ordering::SeqCst) {
+            warn!(job = %job.name, "skipping job since its previous run is still going");
+
+            run.status = CronStatus::Skipped;
+            record(deployment_updater, &run).await;
+            continue;
+        }
+
+        info!(job = %job.name, "running job");
+        record(deployment_updater, &run).await;
+
+        let running = running.clone();
+        let deployment_updater = deployment_updater.clone();
+        let path = job.path.clone();
+
+        tokio::spawn(async move {
+            match invoke(address, &path, &run.name).await {
+                Ok(()) => {
+                    info!(job = %run.name, "job succeeded");
+                    run.status = CronStatus::Succeeded;
+                }
+                Err(error) => {
+                    warn!(%error, job = %run.name, "job failed");
+                    run.status = CronStatus::Failed;
+                    run.error = Some(error);
+                }
+            }
+
+            run.finished_at = Some(Utc::now());
+            record(&deployment_updater, &run).await;
+            running.store(false, Ordering::SeqCst);
+        });
+    }
+}
+
+/// Send the request which runs job `name` to the endpoint at `path` of the service at `address`
+async fn invoke(address: SocketAddr, path: &str, name: &str) -> Result<(), String> {
+    let request = Request::post(format!("http://{address}{path}"))
+        .header("x-shuttle-cron-job", name)
+        .body(Body::empty())
+        .map_err(|error| error.to_string())?;
+    let response = Client::new()
+        .request(request)
+        .await
+        .map_err(|error| error.to_string())?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("{path} answered with {}", response.status()))
+    }
+}
+
+async fn record(deployment_updater: &impl DeploymentUpdater, run: &CronRun) {
+    if let Err(error) = deployment_updater.record_cron_run(run).await {
+        error!(
+            error = &error as &dyn std::error::Error,
+            job = %run.name,
+            "failed to record run of job"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        net::{Ipv4Addr, SocketAddr},
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Body, Response, Server,
+    };
+    use portpicker::pick_unused_port;
+    use shuttle_common::shuttle_toml::CronJob;
+    use tokio::time::timeout;
+    use uuid::Uuid;
+
+    use super::schedule;
+    use crate::persistence::{CronRun, CronStatus, DeploymentUpdater};
+
+    #[derive(Clone, Default)]
+    struct RecordingUpdater(Arc<Mutex<Vec<CronRun>>>);
+
+    #[async_trait]
+    impl DeploymentUpdater for RecordingUpdater {
+        type Err = std::io::Error;
+
+        async fn set_address(&self, _id: &Uuid, _address: &SocketAddr) -> Result<(), Self::Err> {
+            Ok(())
+        }
+
+        async fn set_is_next(&self, _id: &Uuid, _is_next: bool) -> Result<(), Self::Err> {
+            Ok(())
+        }
+
+        async fn record_oom_kill(&self, _id: &Uuid) -> Result<(), Self::Err> {
+            Ok(())
+        }
+
+        async fn record_cron_run(&self, run: &CronRun) -> Result<(), Self::Err> {
+            self.0.lock().unwrap().push(run.clone());
+
+            Ok(())
+        }
+
+        async fn switch_route(
+            &self,
+            _service_id: &Uuid,
+            _id: &Uuid,
+        ) -> Result<Option<Uuid>, Self::Err> {
+            Ok(None)
+        }
+    }
+
+    /// Serve a job which takes `duration` to run
+    fn serve(duration: Duration) -> SocketAddr {
+        let address = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), pick_unused_port().unwrap());
+        let make_service = make_service_fn(move |_| async move {
+            Ok::<_, Infallible>(service_fn(move |_| async move {
+                tokio::time::sleep(duration).await;
+
+                Ok::<_, Infallible>(Response::new(Body::empty()))
+            }))
+        });
+
+        tokio::spawn(Server::bind(&address).serve(make_service));
+
+        address
+    }
+
+    #[tokio::test]
+    async fn runs_do_not_overlap() {
+        let address = serve(Duration::from_millis(1500));
+        let updater = RecordingUpdater::default();
+        let jobs = [CronJob {
+            name: "slow".to_string(),
+            schedule: "* * * * * *".to_string(),
+            path: "/slow".to_string(),
+        }];
+
+        let _ = timeout(
+            Duration::from_millis(3500),
+            schedule(Uuid::new_v4(), address, &jobs, &updater),
+        )
+        .await;
+
+        let runs = updater.0.lock().unwrap().clone();
+        let statuses: Vec<_> = runs.iter().map(|run| run.status).collect();
+
+        assert!(statuses.contains(&CronStatus::Succeeded), "{statuses:?}");
+        assert!(statuses.contains(&CronStatus::Skipped), "{statuses:?}");
+
+        // No run started while the one before it was still going
+        let started: Vec<_> = runs
+            .iter()
+            .filter(|run| run.status == CronStatus::Running)
+            .map(|run| run.started_at)
+            .collect();
+        for run in runs
+            .iter()
+            .filter(|run| run.status == CronStatus::Succeeded)
+        {
+            let finished_at = run.finished_at.unwrap();
+            assert!(!started
+                .iter()
+                .any(|started_at| *started_at > run.started_at && *started_at < finished_at));
+        }
+    }
+}
@@ -28,7 +28,7 @@ use tracing::{field::Visit, span, warn, Metadata, Subscriber};
 use tracing_subscriber::Layer;
 use uuid::Uuid;
 
-use crate::persistence::{self, DeploymentState, LogLevel, State};
+use crate::persistence::{self, DeploymentState, LogLevel, LogSource, State};
 
 /// Records logs for the deployment progress
 pub trait LogRecorder: Clone + Send + 'static {
@@ -63,6 +63,9 @@ pub struct Log {
     pub fields: serde_json::Value,
 
     pub r#type: LogType,
+
+    /// Whether the log was traced or printed by the service
+    pub source: LogSource,
 }
 
 impl From<Log> for persistence::Log {
@@ -83,6 +86,7 @@ impl From<Log> for persistence::Log {
             line: log.line,
             target: log.target,
             fields,
+            source: log.source,
         }
     }
 }
@@ -98,6 +102,7 @@ impl From<Log> for shuttle_common::LogItem {
             line: log.line,
             target: log.target,
             fields: serde_json::to_vec(&log.fields).unwrap(),
+            source: log.source.into(),
         }
     }
 }
@@ -128,6 +133,7 @@ impl TryFrom<runtime::LogItem> for Log {
             target: log.target,
             fields: serde_json::from_slice(&log.fields)?,
             r#type: LogType::Event,
+            source: LogSource::Tracing,
         })
     }
 }
@@ -202,6 +208,7 @@ where
                         .unwrap_or_else(|| metadata.target().to_string()),
                     fields: serde_json::Value::Object(visitor.fields),
                     r#type: LogType::Event,
+                    source: LogSource::Tracing,
                 });
                 break;
             }
@@ -245,6 +252,7 @@ where
             target: metadata.target().to_string(),
             fields: Default::default(),
             r#type: LogType::State,
+            source: LogSource::Tracing,
         });
 
         extensions.insert::<ScopeDetails>(details);
@@ -311,7 +319,7 @@ mod tests {
     };
 
     use crate::{
-        persistence::{DeploymentUpdater, Resource, ResourceManager},
+        persistence::{CronRun, DeploymentUpdater, Resource, ResourceManager},
         RuntimeManager,
     };
     use async_trait::async_trait;
@@ -334,7 +342,7 @@ mod tests {
             deploy_layer::LogType, gateway_client::BuildQueueClient, ActiveDeploymentsGetter,
             Built, DeploymentManager, Queued,
         },
-        persistence::{Secret, SecretGetter, SecretRecorder, State},
+        persistence::{EnvVar, EnvVarGetter, Secret, SecretGetter, SecretRecorder, State},
     };
 
     use super::{DeployLayer, Log, LogRecorder};
@@ -470,7 +478,14 @@ mod tests {
         let path = tmp_dir.into_path();
         let (tx, _rx) = crossbeam_channel::unbounded();
 
-        RuntimeManager::new(path, format!("http://{}", provisioner_addr), None, tx)
+        RuntimeManager::new(
+            path,
+            format!("http://{}", provisioner_addr),
+            None,
+            tx,
+            Default::default(),
+            Duration::from_secs(1),
+        )
     }
 
     #[async_trait::async_trait]
@@ -507,6 +522,22 @@ mod tests {
         async fn set_is_next(&self, _id: &Uuid, _is_next: bool) -> Result<(), Self::Err> {
             Ok(())
         }
+
+        async fn record_oom_kill(&self, _id: &Uuid) -> Result<(), Self::Err> {
+            Ok(())
+        }
+
+        async fn record_cron_run(&self, _run: &CronRun) -> Result<(), Self::Err> {
+            Ok(())
+        }
+
+        async fn switch_route(
+            &self,
+            _service_id: &Uuid,
+            _id: &Uuid,
+        ) -> Result<Option<Uuid>, Self::Err> {
+            Ok(None)
+        }
     }
 
     #[derive(Clone)]
@@ -556,6 +587,18 @@ mod tests {
         }
     }
 
+    #[derive(Clone)]
+    struct StubEnvVarGetter;
+
+    #[async_trait::async_trait]
+    impl EnvVarGetter for StubEnvVarGetter {
+        type Err = std::io::Error;
+
+        async fn get_env_vars(&self, _service_id: &Uuid) -> Result<Vec<EnvVar>, Self::Err> {
+            Ok(Default::default())
+        }
+    }
+
     #[derive(Clone)]
     struct StubResourceManager;
 
@@ -663,6 +706,148 @@ mod tests {
         );
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn deployment_cancelled() {
+        let deployment_manager = get_deployment_manager().await;
+
+        let queued = get_queue("sleep-async");
+        let id = queued.id;
+        deployment_manager.queue_push(queued).await;
+
+        let building = test_states(
+            &id,
+            vec![
+                StateLog {
+                    id,
+                    state: State::Queued,
+                },
+                StateLog {
+                    id,
+                    state: State::Building,
+                },
+            ],
+        );
+
+        select! {
+            _ = sleep(Duration::from_secs(60)) => {
+                let states = RECORDER.lock().unwrap().get_deployment_states(&id);
+                panic!("states should go into 'Building' for a valid service: {:#?}", states);
+            },
+            _ = building => {}
+        };
+
+        assert!(deployment_manager.cancel(&id));
+
+        let test = test_states(
+            &id,
+            vec![
+                StateLog {
+                    id,
+                    state: State::Queued,
+                },
+                StateLog {
+                    id,
+                    state: State::Building,
+                },
+                StateLog {
+                    id,
+                    state: State::Cancelled,
+                },
+            ],
+        );
+
+        select! {
+            _ = sleep(Duration::from_secs(60)) => {
+                let states = RECORDER.lock().unwrap().get_deployment_states(&id);
+                panic!("states should go into 'Cancelled' for a cancelled build: {:#?}", states);
+            },
+            _ = test => {}
+        };
+
+        // The deployment was never stored to run
+        assert!(!deployment_manager
+            .storage_manager()
+            .deployment_executable_path(&id)
+            .unwrap()
+            .exists());
+        assert!(!deployment_manager.cancel(&id));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn deployment_pending_approval() {
+        let deployment_manager = get_deployment_manager().await;
+
+        let mut queued = get_queue("sleep-async");
+        queued.requires_approval = true;
+        let id = queued.id;
+        deployment_manager.queue_push(queued).await;
+
+        let test = test_states(
+            &id,
+            vec![
+                StateLog {
+                    id,
+                    state: State::Queued,
+                },
+                StateLog {
+                    id,
+                    state: State::Building,
+                },
+                StateLog {
+                    id,
+                    state: State::PendingApproval,
+                },
+            ],
+        );
+
+        select! {
+            _ = sleep(Duration::from_secs(460)) => {
+                let states = RECORDER.lock().unwrap().get_deployment_states(&id);
+                panic!("states should go into 'PendingApproval' when approval is required: {:#?}", states);
+            },
+            _ = test => {}
+        };
+
+        // The deployment is kept to run once it is approved
+        assert!(deployment_manager
+            .storage_manager()
+            .deployment_executable_path(&id)
+            .unwrap()
+            .exists());
+
+        deployment_manager.reject(&id).await;
+
+        let test = test_states(
+            &id,
+            vec![
+                StateLog {
+                    id,
+                    state: State::Queued,
+                },
+                StateLog {
+                    id,
+                    state: State::Building,
+                },
+                StateLog {
+                    id,
+                    state: State::PendingApproval,
+                },
+                StateLog {
+                    id,
+                    state: State::Cancelled,
+                },
+            ],
+        );
+
+        select! {
+            _ = sleep(Duration::from_secs(60)) => {
+                let states = RECORDER.lock().unwrap().get_deployment_states(&id);
+                panic!("states should go into 'Cancelled' for a rejected deployment: {:#?}", states);
+            },
+            _ = test => {}
+        };
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn deployment_self_stop() {
         let deployment_manager = get_deployment_manager().await;
@@ -855,6 +1040,9 @@ mod tests {
                 service_id: Uuid::new_v4(),
                 data: Bytes::from("violets are red").to_vec(),
                 will_run_tests: false,
+                prebuilt: false,
+                requires_approval: false,
+                build_options: Default::default(),
                 tracing_context: Default::default(),
                 claim: None,
             })
@@ -879,6 +1067,7 @@ mod tests {
             .active_deployment_getter(StubActiveDeploymentGetter)
             .artifacts_path(PathBuf::from("/tmp"))
             .secret_getter(StubSecretGetter)
+            .env_var_getter(StubEnvVarGetter)
             .resource_manager(StubResourceManager)
             .runtime(get_runtime_manager())
             .deployment_updater(StubDeploymentUpdater)
@@ -914,6 +1103,9 @@ mod tests {
             service_id: Uuid::new_v4(),
             data: bytes,
             will_run_tests: false,
+            prebuilt: false,
+            requires_approval: false,
+            build_options: Default::default(),
             tracing_context: Default::default(),
             claim: None,
         }
@@ -2,7 +2,8 @@ use hyper::{body, client::HttpConnector, Body, Client, Method, Request, Uri};
 use opentelemetry::global;
 use opentelemetry_http::HeaderInjector;
 use serde::{de::DeserializeOwned, Serialize};
-use shuttle_common::models::stats;
+use shuttle_common::models::{headers, route, stats};
+use shuttle_common::shuttle_toml::HttpConfig;
 use thiserror::Error;
 use tracing::{trace, Span};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
@@ -33,16 +34,57 @@ pub trait BuildQueueClient: Clone + Send + Sync + 'static {
 pub struct GatewayClient {
     client: Client<HttpConnector>,
     base: Uri,
+    /// The project of this deployer, which the gateway shares build slots between
+    project: String,
 }
 
 impl GatewayClient {
-    pub fn new(uri: Uri) -> Self {
+    pub fn new(uri: Uri, project: String) -> Self {
         Self {
             client: Client::new(),
             base: uri,
+            project,
         }
     }
 
+    /// Route the host of `service`, of an environment of the project, to its running deployment
+    pub async fn set_route(
+        &self,
+        service: &str,
+        deployment_id: Uuid,
+    ) -> Result<route::Response, Error> {
+        let body = route::Request {
+            project: self.project.clone(),
+            service: service.to_string(),
+            deployment_id,
+        };
+
+        self.post("routes", Some(body)).await
+    }
+
+    /// Remove the route of `service` to a deployment which stopped running
+    pub async fn delete_route(&self, service: &str, deployment_id: Uuid) -> Result<(), Error> {
+        let body = route::Request {
+            project: self.project.clone(),
+            service: service.to_string(),
+            deployment_id,
+        };
+
+        self.delete("routes", Some(body)).await
+    }
+
+    /// Have the gateway add the headers of `http` to the responses of `service`, or none when it
+    /// is empty
+    pub async fn set_headers(&self, service: &str, http: HttpConfig) -> Result<(), Error> {
+        let body = headers::Request {
+            project: self.project.clone(),
+            service: service.to_string(),
+            http,
+        };
+
+        self.post("headers", Some(body)).await
+    }
+
     /// Make a post request to a gateway endpoint
     pub async fn post<B: Serialize, T: DeserializeOwned>(
         &self,
@@ -102,14 +144,20 @@ impl GatewayClient {
 #[async_trait::async_trait]
 impl BuildQueueClient for GatewayClient {
     async fn get_slot(&self, id: Uuid) -> Result<bool, Error> {
-        let body = stats::LoadRequest { id };
+        let body = stats::LoadRequest {
+            id,
+            project: Some(self.project.clone()),
+        };
         let load: stats::LoadResponse = self.post("stats/load", Some(body)).await?;
 
         Ok(load.has_capacity)
     }
 
     async fn release_slot(&self, id: Uuid) -> Result<(), Error> {
-        let body = stats::LoadRequest { id };
+        let body = stats::LoadRequest {
+            id,
+            project: Some(self.project.clone()),
+        };
         let _load: stats::LoadResponse = self.delete("stats/load", Some(body)).await?;
 
         Ok(())
@@ -0,0 +1,320 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::Utc;
+use hyper::{Body, Client, Request};
+use shuttle_common::{deployment::Phase, models::deployment::Health, shuttle_toml::HealthConfig};
+use tokio::{
+    net::TcpStream,
+    time::{sleep, timeout, Instant},
+};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// How long to wait between attempts to reach a started service which is not ready yet
+const READINESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long a single probe may take before it counts as failed
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The latest health of every running deployment
+#[derive(Clone, Default)]
+pub struct HealthStatuses(Arc<Mutex<HashMap<Uuid, Health>>>);
+
+impl HealthStatuses {
+    pub fn get(&self, id: &Uuid) -> Option<Health> {
+        self.0.lock().unwrap().get(id).cloned()
+    }
+
+    fn record(&self, id: Uuid, health: Health) {
+        self.0.lock().unwrap().insert(id, health);
+    }
+
+    pub fn remove(&self, id: &Uuid) {
+        self.0.lock().unwrap().remove(id);
+    }
+}
+
+/// Wait for the service of deployment `id` at `address` to pass its readiness check, or get why the
+/// last probe failed once it had too long.
+///
+/// Services which do not declare a health check path are ready once they accept connections. Those
+/// which serve no requests never do, so they are taken to be ready once the check gives up on them.
+pub async fn ready(
+    id: Uuid,
+    address: SocketAddr,
+    config: &HealthConfig,
+    statuses: &HealthStatuses,
+) -> Result<(), String> {
+    let path = config.path.as_deref();
+    let deadline = Instant::now() + config.readiness_timeout();
+
+    statuses.record(
+        id,
+        Health {
+            ready: false,
+            last_probe: Utc::now(),
+            failures: 0,
+            last_error: None,
+        },
+    );
+
+    loop {
+        let result = probe(address, path).await;
+
+        match (result, path) {
+            (Ok(()), _) => {
+                info!(phase = %Phase::Healthy, "service is ready");
+                statuses.record(
+                    id,
+                    Health {
+                        ready: true,
+                        last_probe: Utc::now(),
+                        failures: 0,
+                        last_error: None,
+                    },
+                );
+                return Ok(());
+            }
+            (Err(_), None) if Instant::now() >= deadline => {
+                info!(
+                    phase = %Phase::Healthy,
+                    "service started, but does not accept connections, which is expected if it serves no requests"
+                );
+                statuses.record(
+                    id,
+                    Health {
+                        ready: true,
+                        last_probe: Utc::now(),
+                        failures: 0,
+                        last_error: None,
+                    },
+                );
+                return Ok(());
+            }
+            (Err(error), Some(_)) if Instant::now() >= deadline => {
+                return Err(error);
+            }
+            (Err(_), _) => sleep(READINESS_INTERVAL).await,
+        }
+    }
+}
+
+/// Keep probing the ready service of deployment `id` at `address` for as long as it runs. This only
+/// returns, with why the last probe failed, when it failed too many probes in a row, and never for
+/// services without a health check path.
+pub async fn live(
+    id: Uuid,
+    address: SocketAddr,
+    config: &HealthConfig,
+    statuses: &HealthStatuses,
+) -> String {
+    let Some(path) = config.path.as_deref() else {
+        return futures::future::pending().await;
+    };
+
+    let mut failures = 0;
+
+    loop {
+        sleep(config.interval()).await;
+
+        match probe(address, Some(path)).await {
+            Ok(()) => {
+                if failures > 0 {
+                    info!("service passed its liveness probe again");
+                }
+
+                failures = 0;
+                statuses.record(
+                    id,
+                    Health {
+                        ready: true,
+                        last_probe: Utc::now(),
+                        failures,
+                        last_error: None,
+                    },
+                );
+            }
+            Err(error) => {
+                failures += 1;
+                warn!(%error, failures, "service failed its liveness probe");
+                statuses.record(
+                    id,
+                    Health {
+                        ready: true,
+                        last_probe: Utc::now(),
+                        failures,
+                        last_error: Some(error.clone()),
+                    },
+                );
+
+                if failures >= config.failure_threshold {
+                    return error;
+                }
+            }
+        }
+    }
+}
+
+/// Probe the service at `address` once, with a GET of `path` when there is one, or else by
+/// connecting to it
+async fn probe(address: SocketAddr, path: Option<&str>) -> Result<(), String> {
+    let probe = async {
+        let Some(path) = path else {
+            return TcpStream::connect(address)
+                .await
+                .map(|_| ())
+                .map_err(|error| error.to_string());
+        };
+
+        let request = Request::get(format!("http://{address}{path}"))
+            .body(Body::empty())
+            .map_err(|error| error.to_string())?;
+        let response = Client::new()
+            .request(request)
+            .await
+            .map_err(|error| error.to_string())?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("{path} answered with {}", response.status()))
+        }
+    };
+
+    timeout(PROBE_TIMEOUT, probe)
+        .await
+        .unwrap_or_else(|_| Err(format!("no answer within {}s", PROBE_TIMEOUT.as_secs())))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        net::{Ipv4Addr, SocketAddr},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Body, Response, Server, StatusCode,
+    };
+    use portpicker::pick_unused_port;
+    use shuttle_common::shuttle_toml::HealthConfig;
+    use tokio::time::timeout;
+    use uuid::Uuid;
+
+    use super::{live, ready, HealthStatuses};
+
+    /// Serve `/health`, which answers with a success for as long as `healthy` is set
+    fn serve(healthy: Arc<AtomicBool>) -> SocketAddr {
+        let address = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), pick_unused_port().unwrap());
+        let make_service = make_service_fn(move |_| {
+            let healthy = healthy.clone();
+
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let status =
+                        if request.uri().path() == "/health" && healthy.load(Ordering::SeqCst) {
+                            StatusCode::OK
+                        } else {
+                            StatusCode::SERVICE_UNAVAILABLE
+                        };
+
+                    async move {
+                        Ok::<_, Infallible>(
+                            Response::builder()
+                                .status(status)
+                                .body(Body::empty())
+                                .unwrap(),
+                        )
+                    }
+                }))
+            }
+        });
+
+        tokio::spawn(Server::bind(&address).serve(make_service));
+
+        address
+    }
+
+    #[tokio::test]
+    async fn restart_after_failed_probes() {
+        let healthy = Arc::new(AtomicBool::new(true));
+        let address = serve(healthy.clone());
+        let statuses = HealthStatuses::default();
+        let id = Uuid::new_v4();
+        let config = HealthConfig {
+            path: Some("/health".to_string()),
+            interval: 1,
+            failure_threshold: 2,
+            readiness_timeout: 5,
+        };
+
+        ready(id, address, &config, &statuses).await.unwrap();
+
+        let watcher = {
+            let statuses = statuses.clone();
+            tokio::spawn(async move { live(id, address, &config, &statuses).await })
+        };
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        let health = statuses.get(&id).unwrap();
+        assert!(health.ready);
+        assert_eq!(health.failures, 0);
+
+        healthy.store(false, Ordering::SeqCst);
+
+        let error = timeout(Duration::from_secs(5), watcher)
+            .await
+            .expect("the service to be given up on")
+            .unwrap();
+        assert_eq!(error, "/health answered with 503 Service Unavailable");
+        assert_eq!(statuses.get(&id).unwrap().failures, 2);
+    }
+
+    #[tokio::test]
+    async fn never_ready() {
+        let address = serve(Arc::new(AtomicBool::new(false)));
+        let config = HealthConfig {
+            path: Some("/health".to_string()),
+            readiness_timeout: 1,
+            ..Default::default()
+        };
+
+        let result = timeout(
+            Duration::from_secs(5),
+            ready(Uuid::new_v4(), address, &config, &Default::default()),
+        )
+        .await
+        .expect("the readiness check to give up");
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn no_path_is_never_unhealthy() {
+        let address = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), pick_unused_port().unwrap());
+        let config = HealthConfig {
+            readiness_timeout: 1,
+            ..Default::default()
+        };
+        let statuses = HealthStatuses::default();
+        let id = Uuid::new_v4();
+
+        ready(id, address, &config, &statuses).await.unwrap();
+        assert!(timeout(
+            Duration::from_secs(2),
+            live(id, address, &config, &statuses)
+        )
+        .await
+        .is_err());
+    }
+}
@@ -1,47 +1,76 @@
+mod cancel;
+mod cron;
 pub mod deploy_layer;
 pub mod gateway_client;
+mod health;
 mod queue;
+mod replicas;
+mod restart;
 mod run;
 
-use std::{path::PathBuf, sync::Arc};
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
 pub use queue::Queued;
-pub use run::{ActiveDeploymentsGetter, Built};
-use shuttle_common::storage_manager::ArtifactsStorageManager;
+pub use replicas::Replicas;
+pub use run::{ActiveDeploymentsGetter, Built, RunConfig};
+use shuttle_common::{
+    models::{deployment::Health, service::Routing},
+    storage_manager::ArtifactsStorageManager,
+};
+use shuttle_service::builder::{
+    ArtifactStore, AuditPolicy, BuildOptions, BuildQueue, BuildSandbox, RetentionPolicy,
+};
 use tracing::{instrument, Span};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
 use crate::{
-    persistence::{DeploymentUpdater, ResourceManager, SecretGetter, SecretRecorder, State},
+    persistence::{
+        DeploymentUpdater, EnvVarGetter, ResourceManager, SecretGetter, SecretRecorder, State,
+    },
     RuntimeManager,
 };
 use tokio::sync::{mpsc, Mutex};
 use uuid::Uuid;
 
-use self::{deploy_layer::LogRecorder, gateway_client::BuildQueueClient};
+use self::{
+    cancel::Cancellations, deploy_layer::LogRecorder, gateway_client::BuildQueueClient,
+    health::HealthStatuses,
+};
 
 const QUEUE_BUFFER_SIZE: usize = 100;
 const RUN_BUFFER_SIZE: usize = 100;
+const DEFAULT_BUILD_CONCURRENCY: usize = 4;
+const DEFAULT_MAX_REPLICAS: u32 = 10;
 
-pub struct DeploymentManagerBuilder<LR, SR, ADG, DU, SG, RM, QC> {
+pub struct DeploymentManagerBuilder<LR, SR, ADG, DU, SG, EG, RM, QC> {
     build_log_recorder: Option<LR>,
     secret_recorder: Option<SR>,
     active_deployment_getter: Option<ADG>,
     artifacts_path: Option<PathBuf>,
+    /// The build settings chosen by the operator, which apply to every deployment
+    build_options: BuildOptions,
+    build_target_dir: Option<PathBuf>,
+    /// Which builds to keep in the artifact store of the `build_options`
+    artifact_retention: RetentionPolicy,
+    build_concurrency: usize,
     runtime_manager: Option<Arc<Mutex<RuntimeManager>>>,
+    replicas: Replicas,
+    max_replicas: u32,
     deployment_updater: Option<DU>,
     secret_getter: Option<SG>,
+    env_var_getter: Option<EG>,
     resource_manager: Option<RM>,
     queue_client: Option<QC>,
 }
 
-impl<LR, SR, ADG, DU, SG, RM, QC> DeploymentManagerBuilder<LR, SR, ADG, DU, SG, RM, QC>
+impl<LR, SR, ADG, DU, SG, EG, RM, QC> DeploymentManagerBuilder<LR, SR, ADG, DU, SG, EG, RM, QC>
 where
     LR: LogRecorder,
     SR: SecretRecorder,
     ADG: ActiveDeploymentsGetter,
     DU: DeploymentUpdater,
     SG: SecretGetter,
+    EG: EnvVarGetter,
     RM: ResourceManager,
     QC: BuildQueueClient,
 {
@@ -69,6 +98,76 @@ where
         self
     }
 
+    /// Compile deployments through this wrapper, like `sccache`
+    pub fn rustc_wrapper(mut self, rustc_wrapper: Option<PathBuf>) -> Self {
+        self.build_options.rustc_wrapper = rustc_wrapper;
+
+        self
+    }
+
+    /// Abort builds which take longer than this
+    pub fn build_timeout(mut self, build_timeout: Duration) -> Self {
+        self.build_options.timeout = Some(build_timeout);
+
+        self
+    }
+
+    /// Build in this directory instead of the target directory of each service
+    pub fn build_target_dir(mut self, build_target_dir: Option<PathBuf>) -> Self {
+        self.build_target_dir = build_target_dir;
+
+        self
+    }
+
+    /// Install the toolchain pinned by a service, and the wasm target, with rustup when they are missing
+    pub fn install_toolchain(mut self, install_toolchain: bool) -> Self {
+        self.build_options.install_toolchain = install_toolchain;
+
+        self
+    }
+
+    /// Build deployments for this target triple instead of the host
+    pub fn build_target(mut self, build_target: Option<String>) -> Self {
+        self.build_options.target = build_target;
+
+        self
+    }
+
+    /// Build at most this many deployments at the same time
+    pub fn build_concurrency(mut self, build_concurrency: usize) -> Self {
+        self.build_concurrency = build_concurrency;
+
+        self
+    }
+
+    /// Reuse the executables of earlier builds of unchanged projects from this folder
+    pub fn artifact_store(mut self, artifact_store: Option<PathBuf>) -> Self {
+        self.build_options.artifact_store = artifact_store.map(ArtifactStore::new);
+
+        self
+    }
+
+    /// Which builds to keep in the artifact store
+    pub fn artifact_retention(mut self, artifact_retention: RetentionPolicy) -> Self {
+        self.artifact_retention = artifact_retention;
+
+        self
+    }
+
+    /// Check the dependencies of deployments for known vulnerabilities
+    pub fn audit_policy(mut self, audit_policy: AuditPolicy) -> Self {
+        self.build_options.audit_policy = audit_policy;
+
+        self
+    }
+
+    /// Build deployments in throwaway containers instead of on this machine
+    pub fn build_sandbox(mut self, build_sandbox: Option<BuildSandbox>) -> Self {
+        self.build_options.sandbox = build_sandbox;
+
+        self
+    }
+
     pub fn queue_client(mut self, queue_client: QC) -> Self {
         self.queue_client = Some(queue_client);
 
@@ -81,6 +180,13 @@ where
         self
     }
 
+    /// Start the runtimes of services with the environment variables set for them
+    pub fn env_var_getter(mut self, env_var_getter: EG) -> Self {
+        self.env_var_getter = Some(env_var_getter);
+
+        self
+    }
+
     pub fn resource_manager(mut self, resource_manager: RM) -> Self {
         self.resource_manager = Some(resource_manager);
 
@@ -99,6 +205,21 @@ where
         self
     }
 
+    /// Run the deployments of services in as many runtimes as they are scaled to in `replicas`,
+    /// which the proxy spreads their requests over
+    pub fn replicas(mut self, replicas: Replicas) -> Self {
+        self.replicas = replicas;
+
+        self
+    }
+
+    /// Let services be scaled to at most this many runtimes
+    pub fn max_replicas(mut self, max_replicas: u32) -> Self {
+        self.max_replicas = max_replicas;
+
+        self
+    }
+
     /// Creates two Tokio tasks, one for building queued services, the other for
     /// executing/deploying built services. Two multi-producer, single consumer
     /// channels are also created which are for moving on-going service
@@ -118,11 +239,16 @@ where
             .deployment_updater
             .expect("a deployment updater to be set");
         let secret_getter = self.secret_getter.expect("a secret getter to be set");
+        let env_var_getter = self
+            .env_var_getter
+            .expect("an environment variable getter to be set");
         let resource_manager = self.resource_manager.expect("a resource manager to be set");
 
         let (queue_send, queue_recv) = mpsc::channel(QUEUE_BUFFER_SIZE);
         let (run_send, run_recv) = mpsc::channel(RUN_BUFFER_SIZE);
         let storage_manager = ArtifactsStorageManager::new(artifacts_path);
+        let health_statuses = HealthStatuses::default();
+        let cancellations = Cancellations::default();
 
         let run_send_clone = run_send.clone();
 
@@ -134,15 +260,24 @@ where
             secret_recorder,
             storage_manager.clone(),
             queue_client,
+            self.build_options,
+            self.build_target_dir.clone(),
+            self.artifact_retention,
+            BuildQueue::new(self.build_concurrency),
+            cancellations.clone(),
         ));
         tokio::spawn(run::task(
             run_recv,
+            run_send.clone(),
             runtime_manager.clone(),
             deployment_updater,
             active_deployment_getter,
             secret_getter,
+            env_var_getter,
             resource_manager,
             storage_manager.clone(),
+            health_statuses.clone(),
+            self.replicas.clone(),
         ));
 
         DeploymentManager {
@@ -150,6 +285,11 @@ where
             run_send,
             runtime_manager,
             storage_manager,
+            health_statuses,
+            cancellations,
+            replicas: self.replicas,
+            max_replicas: self.max_replicas,
+            build_target_dir: self.build_target_dir,
         }
     }
 }
@@ -160,6 +300,11 @@ pub struct DeploymentManager {
     run_send: RunSender,
     runtime_manager: Arc<Mutex<RuntimeManager>>,
     storage_manager: ArtifactsStorageManager,
+    health_statuses: HealthStatuses,
+    cancellations: Cancellations,
+    replicas: Replicas,
+    max_replicas: u32,
+    build_target_dir: Option<PathBuf>,
 }
 
 /// ```no-test
@@ -179,16 +324,30 @@ pub struct DeploymentManager {
 impl DeploymentManager {
     /// Create a new deployment manager. Manages one or more 'pipelines' for
     /// processing service building, loading, and deployment.
-    pub fn builder<LR, SR, ADG, DU, SG, RM, QC>(
-    ) -> DeploymentManagerBuilder<LR, SR, ADG, DU, SG, RM, QC> {
+    pub fn builder<LR, SR, ADG, DU, SG, EG, RM, QC>(
+    ) -> DeploymentManagerBuilder<LR, SR, ADG, DU, SG, EG, RM, QC> {
         DeploymentManagerBuilder {
             build_log_recorder: None,
             secret_recorder: None,
             active_deployment_getter: None,
             artifacts_path: None,
+            build_options: BuildOptions {
+                // Services talk to the deployer through the shuttle crates, so they have to be of
+                // the same version
+                supported_version: env!("CARGO_PKG_VERSION").parse().ok(),
+                // The environment of the deployer holds secrets user code must not see
+                scrub_env: true,
+                ..Default::default()
+            },
+            build_target_dir: None,
+            artifact_retention: Default::default(),
+            build_concurrency: DEFAULT_BUILD_CONCURRENCY,
             runtime_manager: None,
+            replicas: Default::default(),
+            max_replicas: DEFAULT_MAX_REPLICAS,
             deployment_updater: None,
             secret_getter: None,
+            env_var_getter: None,
             resource_manager: None,
             queue_client: None,
         }
@@ -201,6 +360,8 @@ impl DeploymentManager {
             propagator.inject_context(&cx, &mut queued.tracing_context);
         });
 
+        // Tracked from here, so that it can be cancelled while it waits in the channel as well
+        self.cancellations.track(queued.id);
         self.queue_send.send(queued).await.unwrap();
     }
 
@@ -213,9 +374,60 @@ impl DeploymentManager {
         self.runtime_manager.lock().await.kill(&id).await;
     }
 
+    /// Cancel a deployment which is queued or building, getting whether it still was. Its build is
+    /// aborted and it ends in the [State::Cancelled] state.
+    pub fn cancel(&self, id: &Uuid) -> bool {
+        self.cancellations.cancel(id)
+    }
+
+    /// Reject a deployment which is pending approval, so that it ends in the [State::Cancelled]
+    /// state without running
+    pub async fn reject(&self, id: &Uuid) {
+        queue::build_cancelled(id, &self.storage_manager).await;
+    }
+
+    /// How many runtimes the deployments of service `service_id` run in
+    pub fn replicas(&self, service_id: &Uuid) -> u32 {
+        self.replicas.count(service_id)
+    }
+
+    /// Run the deployments of service `service_id` in `replicas` runtimes, starting or stopping
+    /// replicas of its running deployment right away
+    pub fn scale(&self, service_id: Uuid, replicas: u32) {
+        self.replicas.scale(service_id, replicas);
+    }
+
+    /// How the replicas of service `service_id` take turns
+    pub fn routing(&self, service_id: &Uuid) -> Routing {
+        self.replicas.routing(service_id)
+    }
+
+    /// Let the replicas of service `service_id` take turns by `routing` from the next request on
+    pub fn route(&self, service_id: Uuid, routing: Routing) {
+        self.replicas.route(service_id, routing);
+    }
+
+    /// The most runtimes a service may be scaled to
+    pub fn max_replicas(&self) -> u32 {
+        self.max_replicas
+    }
+
     pub fn storage_manager(&self) -> ArtifactsStorageManager {
         self.storage_manager.clone()
     }
+
+    /// The latest results of the health checks of a running deployment
+    pub fn health(&self, id: &Uuid) -> Option<Health> {
+        self.health_statuses.get(id)
+    }
+
+    /// The directory `service_name` is built in, when the operator chose one directory for all
+    /// builds instead of the target directory of each service
+    pub fn build_target_dir(&self, service_name: &str) -> Option<PathBuf> {
+        self.build_target_dir
+            .as_ref()
+            .map(|dir| dir.join(service_name))
+    }
 }
 
 type QueueSender = mpsc::Sender<queue::Queued>;
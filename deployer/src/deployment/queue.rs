@@ -1,8 +1,11 @@
+use super::cancel::Cancellations;
 use super::deploy_layer::{Log, LogRecorder, LogType};
 use super::gateway_client::BuildQueueClient;
-use super::{Built, QueueReceiver, RunSender, State};
+use super::{Built, QueueReceiver, RunConfig, RunSender, State};
 use crate::error::{Error, Result, TestError};
-use crate::persistence::{DeploymentUpdater, LogLevel, SecretRecorder};
+use crate::gc::remove_deployment;
+use crate::monitoring;
+use crate::persistence::{DeploymentUpdater, LogLevel, LogSource, SecretRecorder};
 use shuttle_common::storage_manager::{ArtifactsStorageManager, StorageManager};
 
 use cargo_metadata::Message;
@@ -11,7 +14,12 @@ use crossbeam_channel::Sender;
 use opentelemetry::global;
 use serde_json::json;
 use shuttle_common::claims::Claim;
-use shuttle_service::builder::{build_workspace, BuiltService};
+use shuttle_common::project::ProjectName;
+use shuttle_common::shuttle_toml::ShuttleToml;
+use shuttle_service::builder::{
+    build_workspace, load_prebuilt, ArtifactStore, BuildOptions, BuildPriority, BuildQueue,
+    BuildSlot, BuiltService, CancellationToken, QueuePosition, QueuedBuild, RetentionPolicy,
+};
 use tokio::time::{sleep, timeout};
 use tracing::{debug, debug_span, error, info, instrument, trace, warn, Instrument, Span};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
@@ -23,12 +31,15 @@ use std::fs::remove_file;
 use std::io::{BufRead, BufReader, Read};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use flate2::read::GzDecoder;
 use tar::Archive;
 use tokio::fs;
 
+/// How many of the slowest crates of a build to show in its summary
+const SLOWEST_CRATES_SHOWN: usize = 5;
+
 pub async fn task(
     mut recv: QueueReceiver,
     run_send: RunSender,
@@ -37,12 +48,31 @@ pub async fn task(
     secret_recorder: impl SecretRecorder,
     storage_manager: ArtifactsStorageManager,
     queue_client: impl BuildQueueClient,
+    build_options: BuildOptions,
+    build_target_dir: Option<PathBuf>,
+    artifact_retention: RetentionPolicy,
+    build_queue: BuildQueue,
+    cancellations: Cancellations,
 ) {
     info!("Queue task started");
 
-    while let Some(queued) = recv.recv().await {
+    while let Some(mut queued) = recv.recv().await {
         let id = queued.id;
 
+        queued.build_options.rustc_wrapper = build_options.rustc_wrapper.clone();
+        queued.build_options.timeout = build_options.timeout;
+        queued.build_options.install_toolchain = build_options.install_toolchain;
+        queued.build_options.audit_policy = build_options.audit_policy;
+        queued.build_options.target = build_options.target.clone();
+        queued.build_options.artifact_store = build_options.artifact_store.clone();
+        queued.build_options.supported_version = build_options.supported_version.clone();
+        queued.build_options.scrub_env = build_options.scrub_env;
+        queued.build_options.sandbox = build_options.sandbox.clone();
+        // Every service gets its own directory, so that they never see each other's artifacts
+        queued.build_options.target_dir = build_target_dir
+            .as_ref()
+            .map(|dir| dir.join(&queued.service_name));
+
         info!("Queued deployment at the front of the queue: {id}");
 
         let deployment_updater = deployment_updater.clone();
@@ -51,6 +81,12 @@ pub async fn task(
         let secret_recorder = secret_recorder.clone();
         let storage_manager = storage_manager.clone();
         let queue_client = queue_client.clone();
+        let artifact_store = build_options.artifact_store.clone();
+        let artifact_retention = artifact_retention.clone();
+        let cancellations = cancellations.clone();
+        // Prebuilt artifacts are not built, so they do not need a build slot
+        let queued_build = (!queued.prebuilt)
+            .then(|| build_queue.enqueue(queued.service_name.clone(), BuildPriority::Normal));
 
         tokio::spawn(async move {
             let parent_cx = global::get_text_map_propagator(|propagator| {
@@ -59,38 +95,84 @@ pub async fn task(
             let span = debug_span!("builder");
             span.set_parent(parent_cx);
 
+            let tracked = &cancellations;
             async move {
-                match timeout(
-                    Duration::from_secs(60 * 3), // Timeout after 3 minutes if the build queue hangs or it takes too long for a slot to become available
-                    wait_for_queue(queue_client.clone(), id),
-                )
-                .await
-                {
-                    Ok(_) => {}
-                    Err(err) => return build_failed(&id, err),
-                }
+                let cancellation = tracked.track(id);
+                let _build_slot = match queued_build {
+                    Some(queued_build) => {
+                        let build_slot = tokio::select! {
+                            build_slot = wait_for_build_slot(queued_build, id) => build_slot,
+                            _ = cancellation.cancelled() => {
+                                return build_cancelled(&id, &storage_manager).await;
+                            }
+                        };
+
+                        let waited = tokio::select! {
+                            waited = timeout(
+                                Duration::from_secs(60 * 3), // Timeout after 3 minutes if the build queue hangs or it takes too long for a slot to become available
+                                wait_for_queue(queue_client.clone(), id),
+                            ) => waited,
+                            _ = cancellation.cancelled() => {
+                                remove_from_queue(queue_client, id).await;
+                                return build_cancelled(&id, &storage_manager).await;
+                            }
+                        };
+
+                        if let Err(err) = waited {
+                            return build_failed(&id, err);
+                        }
+
+                        Some(build_slot)
+                    }
+                    None => None,
+                };
+                let prebuilt = queued.prebuilt;
+                let requires_approval = queued.requires_approval;
+                let started = Instant::now();
 
-                match queued
+                let result = queued
                     .handle(
-                        storage_manager,
+                        storage_manager.clone(),
                         deployment_updater,
                         log_recorder,
                         secret_recorder,
+                        cancellation,
                     )
-                    .await
-                {
+                    .await;
+
+                if !prebuilt {
+                    remove_from_queue(queue_client, id).await;
+                }
+
+                // A deployment cancelled after its build finished is still cancelled, as long as
+                // it did not move on to run
+                if tracked.finish(&id) {
+                    return build_cancelled(&id, &storage_manager).await;
+                }
+
+                if !prebuilt {
+                    monitoring::observe_build(started.elapsed(), result.is_ok());
+                }
+
+                match result {
                     Ok(built) => {
-                        remove_from_queue(queue_client, id).await;
-                        promote_to_run(built, run_send_cloned).await
-                    }
-                    Err(err) => {
-                        remove_from_queue(queue_client, id).await;
-                        build_failed(&id, err)
+                        if let Some(artifact_store) = artifact_store {
+                            collect_artifacts(&artifact_store, &artifact_retention).await;
+                        }
+                        if requires_approval {
+                            hold_for_approval(&built.id)
+                        } else {
+                            promote_to_run(built, run_send_cloned).await
+                        }
                     }
+                    Err(err) => build_failed(&id, err),
                 }
             }
             .instrument(span)
-            .await
+            .await;
+
+            // However it ended, the deployment cannot be cancelled anymore
+            cancellations.finish(&id);
         });
     }
 }
@@ -103,6 +185,69 @@ fn build_failed(_id: &Uuid, error: impl std::error::Error + 'static) {
     );
 }
 
+/// Keep a deployment which was built from running, until a user approves it
+#[instrument(skip(id), fields(id = %id, state = %State::PendingApproval))]
+fn hold_for_approval(id: &Uuid) {
+    let line =
+        format!("Waiting for approval, run `cargo shuttle deployment approve {id}` to run it");
+    info!(build_line = line.as_str(), "deployment is pending approval");
+}
+
+/// Remove what the build of a cancelled deployment left behind, since it will never run
+#[instrument(skip(id, storage_manager), fields(id = %id, state = %State::Cancelled))]
+pub(super) async fn build_cancelled(id: &Uuid, storage_manager: &ArtifactsStorageManager) {
+    info!(
+        build_line = "Deployment cancelled",
+        "deployment was cancelled"
+    );
+
+    if let Err(error) = remove_deployment(storage_manager, id).await {
+        warn!(
+            error = &error as &dyn std::error::Error,
+            "failed to remove the artifacts of a cancelled deployment"
+        );
+    }
+}
+
+/// Remove the builds the retention policy does not keep from the artifact store
+#[instrument(skip(artifact_store))]
+async fn collect_artifacts(artifact_store: &ArtifactStore, artifact_retention: &RetentionPolicy) {
+    match artifact_store.collect_garbage(artifact_retention).await {
+        Ok(removed) if !removed.is_empty() => {
+            debug!(
+                count = removed.len(),
+                "removed old builds from the artifact store"
+            )
+        }
+        Ok(_) => {}
+        Err(error) => warn!(
+            error = &error as &dyn std::error::Error,
+            "failed to clean up the artifact store"
+        ),
+    }
+}
+
+/// Wait for the deployer to have room for another build, telling the user how many builds are
+/// ahead of theirs
+#[instrument(skip(queued_build), fields(state = %State::Queued))]
+async fn wait_for_build_slot(queued_build: QueuedBuild, id: Uuid) -> BuildSlot {
+    let mut position = queued_build.position();
+    let ready = queued_build.ready();
+    tokio::pin!(ready);
+
+    loop {
+        if let QueuePosition::Waiting { ahead } = *position.borrow_and_update() {
+            let line = format!("Queued ({ahead} ahead of you)");
+            info!(build_line = line.as_str(), "Waiting for a build slot");
+        }
+
+        tokio::select! {
+            slot = &mut ready => return slot,
+            Ok(()) = position.changed() => {}
+        }
+    }
+}
+
 #[instrument(skip(queue_client), fields(state = %State::Queued))]
 async fn wait_for_queue(queue_client: impl BuildQueueClient, id: Uuid) -> Result<()> {
     trace!("getting a build slot");
@@ -150,24 +295,30 @@ pub struct Queued {
     pub service_id: Uuid,
     pub data: Vec<u8>,
     pub will_run_tests: bool,
+    /// Whether `data` has an artifact which was built beforehand, to run instead of building it
+    pub prebuilt: bool,
+    /// Whether the deployment is held once it is built, until a user approves it to run
+    pub requires_approval: bool,
+    pub build_options: BuildOptions,
     pub tracing_context: HashMap<String, String>,
     pub claim: Option<Claim>,
 }
 
 impl Queued {
-    #[instrument(skip(self, storage_manager, deployment_updater, log_recorder, secret_recorder), fields(id = %self.id, state = %State::Building))]
+    #[instrument(skip(self, storage_manager, deployment_updater, log_recorder, secret_recorder, cancellation_token), fields(id = %self.id, state = %State::Building))]
     async fn handle(
         self,
         storage_manager: ArtifactsStorageManager,
         deployment_updater: impl DeploymentUpdater,
         log_recorder: impl LogRecorder,
         secret_recorder: impl SecretRecorder,
+        cancellation_token: CancellationToken,
     ) -> Result<Built> {
         info!("Extracting received data");
 
         let project_path = storage_manager.service_build_path(&self.service_name)?;
 
-        extract_tar_gz_data(self.data.as_slice(), &project_path).await?;
+        extract_archive(self.data.as_slice(), &project_path).await?;
 
         info!("Building deployment");
 
@@ -189,6 +340,7 @@ impl Queued {
                         target: String::new(),
                         fields: json!({ "build_line": line }),
                         r#type: LogType::Event,
+                        source: LogSource::Tracing,
                     },
                     message => Log {
                         id,
@@ -200,6 +352,7 @@ impl Queued {
                         target: String::new(),
                         fields: serde_json::to_value(message).unwrap(),
                         r#type: LogType::Event,
+                        source: LogSource::Tracing,
                     },
                 };
                 log_recorder.record(log);
@@ -208,8 +361,80 @@ impl Queued {
 
         let project_path = project_path.canonicalize()?;
 
-        // Currently returns the first found shuttle service in a given workspace.
-        let runtime = build_deployment(&project_path, tx.clone()).await?;
+        let runtime = if self.prebuilt {
+            info!(
+                build_line = "Using the prebuilt artifact",
+                "Skipping the build"
+            );
+
+            load_prebuilt(&project_path, &self.build_options)
+                .await
+                .map_err(|e| Error::Build(Box::new(e)))?
+        } else {
+            // Currently returns the first found shuttle service in a given workspace.
+            build_deployment(
+                &project_path,
+                tx.clone(),
+                &self.build_options,
+                cancellation_token,
+            )
+            .await?
+        };
+
+        if let Some(stats) = runtime.compiler_cache_stats {
+            let line = format!(
+                "Compiler cache: {} hits, {} misses",
+                stats.hits, stats.misses
+            );
+            info!(build_line = line.as_str(), "Compiler cache statistics");
+        }
+
+        if let Some(toolchain) = &runtime.toolchain {
+            let line = format!("Built with {toolchain}");
+            info!(build_line = line.as_str(), "Toolchain used for the build");
+        }
+
+        if let Some(optimization) = &runtime.wasm_optimization {
+            let line = format!(
+                "Optimized the wasm module from {} KiB to {} KiB",
+                optimization.before / 1024,
+                optimization.after / 1024
+            );
+            info!(build_line = line.as_str(), "Wasm module optimized");
+        }
+
+        let line = if self.prebuilt {
+            format!(
+                "Received {} ({:.1} MiB)",
+                runtime.package_name,
+                runtime.binary_size as f64 / (1024.0 * 1024.0)
+            )
+        } else {
+            format!(
+                "Built {} ({:.1} MiB) in {:.1}s",
+                runtime.package_name,
+                runtime.binary_size as f64 / (1024.0 * 1024.0),
+                runtime.build_duration.as_secs_f64()
+            )
+        };
+        info!(
+            build_line = line.as_str(),
+            binary_size = runtime.binary_size,
+            build_duration_ms = runtime.build_duration.as_millis() as u64,
+            "Build metrics"
+        );
+
+        let mut crate_timings = runtime.crate_timings.clone();
+        crate_timings.sort_by(|a, b| b.duration.cmp(&a.duration));
+        for timing in crate_timings.iter().take(SLOWEST_CRATES_SHOWN) {
+            let line = format!(
+                "  {:>6.1}s {} ({})",
+                timing.duration.as_secs_f64(),
+                timing.package,
+                timing.target
+            );
+            info!(build_line = line.as_str(), "Slow crate");
+        }
 
         // Get the Secrets.toml from the shuttle service in the workspace.
         let secrets = get_secrets(&runtime.working_directory).await?;
@@ -232,6 +457,27 @@ impl Queued {
 
         store_executable(&storage_manager, runtime.executable_path.clone(), &self.id).await?;
 
+        if !runtime.assets.is_empty() {
+            let line = format!("Storing {} assets", runtime.assets.len());
+            info!(build_line = line.as_str(), "Storing assets");
+
+            store_assets(
+                &storage_manager,
+                &runtime.working_directory,
+                &runtime.assets,
+                &self.id,
+            )
+            .await?;
+        }
+
+        store_run_config(
+            &storage_manager,
+            &runtime.working_directory,
+            &self.service_name,
+            &self.id,
+        )
+        .await?;
+
         let is_next = runtime.is_wasm;
 
         deployment_updater
@@ -259,6 +505,9 @@ impl fmt::Debug for Queued {
             .field("service_name", &self.service_name)
             .field("service_id", &self.service_id)
             .field("will_run_tests", &self.will_run_tests)
+            .field("prebuilt", &self.prebuilt)
+            .field("requires_approval", &self.requires_approval)
+            .field("build_options", &self.build_options)
             .finish_non_exhaustive()
     }
 }
@@ -298,10 +547,16 @@ async fn set_secrets(
     Ok(())
 }
 
-/// Equivalent to the command: `tar -xzf --strip-components 1`
+/// Equivalent to the command: `tar -xf --strip-components 1`, for archives compressed with zstd
+/// or, by older clients, with gzip
 #[instrument(skip(data, dest))]
-async fn extract_tar_gz_data(data: impl Read, dest: impl AsRef<Path>) -> Result<()> {
-    let tar = GzDecoder::new(data);
+async fn extract_archive(data: &[u8], dest: impl AsRef<Path>) -> Result<()> {
+    let tar: Box<dyn Read + Send + '_> =
+        if data.starts_with(&zstd::zstd_safe::MAGICNUMBER.to_le_bytes()) {
+            Box::new(zstd::Decoder::new(data)?)
+        } else {
+            Box::new(GzDecoder::new(data))
+        };
     let mut archive = Archive::new(tar);
     archive.set_overwrite(true);
 
@@ -331,14 +586,16 @@ async fn extract_tar_gz_data(data: impl Read, dest: impl AsRef<Path>) -> Result<
     Ok(())
 }
 
-#[instrument(skip(project_path, tx))]
+#[instrument(skip(project_path, tx, cancellation_token))]
 async fn build_deployment(
     project_path: &Path,
     tx: crossbeam_channel::Sender<Message>,
+    build_options: &BuildOptions,
+    cancellation_token: CancellationToken,
 ) -> Result<BuiltService> {
-    let runtimes = build_workspace(project_path, true, tx)
+    let runtimes = build_workspace(project_path, true, tx, build_options, cancellation_token)
         .await
-        .map_err(|e| Error::Build(e.into()))?;
+        .map_err(|e| Error::Build(Box::new(e)))?;
 
     Ok(runtimes[0].clone())
 }
@@ -410,16 +667,75 @@ async fn store_executable(
     Ok(())
 }
 
+/// Copy the assets of a service, which are relative to its `working_directory`, to where the
+/// deployment will be started from, so that they can be found at the same relative paths.
+#[instrument(skip(storage_manager, working_directory, assets, id))]
+async fn store_assets(
+    storage_manager: &ArtifactsStorageManager,
+    working_directory: &Path,
+    assets: &[PathBuf],
+    id: &Uuid,
+) -> Result<()> {
+    let assets_path = storage_manager.deployment_assets_path(id)?;
+
+    for asset in assets {
+        let destination = assets_path.join(asset);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        fs::copy(working_directory.join(asset), destination).await?;
+    }
+
+    Ok(())
+}
+
+/// Keep the settings for running service `service_name` from the Shuttle.toml in its
+/// `working_directory`, since the build files are gone by the time the deployment is started again.
+#[instrument(skip(storage_manager, working_directory, id))]
+async fn store_run_config(
+    storage_manager: &ArtifactsStorageManager,
+    working_directory: &Path,
+    service_name: &str,
+    id: &Uuid,
+) -> Result<()> {
+    let shuttle_toml = ShuttleToml::from_path(&working_directory.join("Shuttle.toml"))
+        .map_err(|e| Error::Build(Box::new(e)))?;
+    let (_, environment) = ProjectName::split_service_name(service_name);
+    let run_config = RunConfig {
+        health: shuttle_toml.health,
+        restart: shuttle_toml.restart,
+        cron: shuttle_toml.cron,
+        args: shuttle_toml.run.args(environment).to_vec(),
+        http: shuttle_toml.http,
+    };
+    let run_config = serde_json::to_vec(&run_config).map_err(|e| Error::Build(Box::new(e)))?;
+
+    fs::write(storage_manager.deployment_run_config_path(id)?, run_config).await?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
-    use std::{collections::BTreeMap, fs::File, io::Write, path::Path};
-
-    use shuttle_common::storage_manager::ArtifactsStorageManager;
+    use std::{
+        collections::BTreeMap,
+        fs::File,
+        io::{Read, Write},
+        path::Path,
+        path::PathBuf,
+    };
+
+    use flate2::read::GzDecoder;
+    use shuttle_common::{
+        shuttle_toml::{CronJob, HealthConfig, HttpConfig, RestartPolicy},
+        storage_manager::ArtifactsStorageManager,
+    };
     use tempfile::Builder;
     use tokio::fs;
     use uuid::Uuid;
 
-    use crate::error::TestError;
+    use crate::{deployment::RunConfig, error::TestError};
 
     #[tokio::test]
     async fn extract_tar_gz_data() {
@@ -478,7 +794,7 @@ ff0e55bda1ff01000000000000000000e0079c01ff12a55500280000",
         )
         .unwrap();
 
-        super::extract_tar_gz_data(test_data.as_slice(), &p)
+        super::extract_archive(test_data.as_slice(), &p)
             .await
             .unwrap();
         assert!(fs::read_to_string(p.join("world.txt"))
@@ -519,9 +835,25 @@ ff0e55bda1ff01000000000000000000e0079c01ff12a55500280000",
         );
 
         // Can we extract again without error?
-        super::extract_tar_gz_data(test_data.as_slice(), &p)
+        super::extract_archive(test_data.as_slice(), &p)
             .await
             .unwrap();
+
+        // Is the same archive extracted when it is compressed with zstd instead?
+        let mut tar = Vec::new();
+        GzDecoder::new(test_data.as_slice())
+            .read_to_end(&mut tar)
+            .unwrap();
+        let zstd_data = zstd::encode_all(tar.as_slice(), 0).unwrap();
+
+        fs::remove_file(p.join("world.txt")).await.unwrap();
+        super::extract_archive(zstd_data.as_slice(), &p)
+            .await
+            .unwrap();
+        assert!(fs::read_to_string(p.join("world.txt"))
+            .await
+            .unwrap()
+            .starts_with("abc"));
     }
 
     #[tokio::test(flavor = "multi_thread")]
@@ -575,6 +907,99 @@ ff0e55bda1ff01000000000000000000e0079c01ff12a55500280000",
         );
     }
 
+    #[tokio::test]
+    async fn store_assets() {
+        let artifacts_dir = Builder::new().prefix("assets-store").tempdir().unwrap();
+        let artifacts_p = artifacts_dir.path();
+        let storage_manager = ArtifactsStorageManager::new(artifacts_p.to_path_buf());
+
+        let build_p = storage_manager.builds_path().unwrap();
+        fs::create_dir_all(build_p.join("static")).await.unwrap();
+        fs::write(build_p.join("static/index.html"), "hello")
+            .await
+            .unwrap();
+
+        let id = Uuid::new_v4();
+
+        super::store_assets(
+            &storage_manager,
+            &build_p,
+            &[PathBuf::from("static/index.html")],
+            &id,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(
+                artifacts_p
+                    .join("shuttle-assets")
+                    .join(id.to_string())
+                    .join("static/index.html")
+            )
+            .await
+            .unwrap(),
+            "hello"
+        );
+    }
+
+    #[tokio::test]
+    async fn store_run_config() {
+        let artifacts_dir = Builder::new().prefix("run-config-store").tempdir().unwrap();
+        let artifacts_p = artifacts_dir.path();
+        let storage_manager = ArtifactsStorageManager::new(artifacts_p.to_path_buf());
+
+        let build_p = storage_manager.builds_path().unwrap();
+        fs::write(
+            build_p.join("Shuttle.toml"),
+            "restart = \"never\"\n\n[health]\npath = \"/health\"\nfailure_threshold = 5\n\n[[cron]]\nname = \"tick\"\nschedule = \"* * * * *\"\npath = \"/tick\"\n\n[run]\nargs = [\"--verbose\"]\n\n[run.environments]\nstaging = [\"--quiet\"]\n\n[http]\nnosniff = true",
+        )
+        .await
+        .unwrap();
+
+        let id = Uuid::new_v4();
+
+        super::store_run_config(&storage_manager, &build_p, "matrix", &id)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            RunConfig::load(&storage_manager, &id),
+            RunConfig {
+                health: HealthConfig {
+                    path: Some("/health".to_string()),
+                    failure_threshold: 5,
+                    ..Default::default()
+                },
+                restart: RestartPolicy::Never,
+                cron: vec![CronJob {
+                    name: "tick".to_string(),
+                    schedule: "* * * * *".to_string(),
+                    path: "/tick".to_string(),
+                }],
+                args: vec!["--verbose".to_string()],
+                http: HttpConfig {
+                    nosniff: true,
+                    ..Default::default()
+                },
+            }
+        );
+        assert_eq!(
+            RunConfig::load(&storage_manager, &Uuid::new_v4()),
+            RunConfig::default()
+        );
+
+        // Services in an environment of the project get the arguments of the environment
+        let staging_id = Uuid::new_v4();
+        super::store_run_config(&storage_manager, &build_p, "matrix--staging", &staging_id)
+            .await
+            .unwrap();
+        assert_eq!(
+            RunConfig::load(&storage_manager, &staging_id).args,
+            ["--quiet"]
+        );
+    }
+
     #[tokio::test]
     async fn get_secrets() {
         let temp = Builder::new().prefix("secrets").tempdir().unwrap();
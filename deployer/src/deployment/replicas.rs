@@ -0,0 +1,699 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    convert::Infallible,
+    hash::{Hash, Hasher},
+    iter,
+    net::{Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use hyper::header::{HeaderMap, HeaderValue, COOKIE};
+use portpicker::pick_unused_port;
+use shuttle_common::{
+    models::service::Routing, shuttle_toml::HealthConfig, storage_manager::ArtifactsStorageManager,
+};
+use shuttle_proto::runtime::{StartRequest, SubscribeStopRequest, SubscribeStopResponse};
+use tokio::{
+    sync::{mpsc, watch},
+    time::sleep,
+};
+use tonic::Streaming;
+use tracing::{error, info, instrument, warn};
+use uuid::Uuid;
+
+use super::{
+    health::{self, HealthStatuses},
+    run,
+};
+use crate::{
+    error::{Error, Result},
+    persistence::{EnvVarGetter, ResourceManager, SecretGetter},
+    RuntimeManager,
+};
+
+/// How long to wait before trying again to start a replica which failed to start
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How many requests in a row a runtime may fail to answer before it is taken out of the rotation
+const EJECT_AFTER_FAILURES: u32 = 3;
+
+/// How long a runtime which failed too many requests is left out of the rotation, before it is
+/// tried again
+const EJECTION: Duration = Duration::from_secs(30);
+
+/// How many runtimes each service is scaled to, and the replicas which are up to share the requests
+/// of each running deployment
+#[derive(Clone, Default)]
+pub struct Replicas {
+    /// The replica counts of the services, by service id
+    counts: Arc<Mutex<HashMap<Uuid, watch::Sender<u32>>>>,
+    /// How the replicas of the services take turns, by service id
+    routings: Arc<Mutex<HashMap<Uuid, Routing>>>,
+    /// The replicas which are ready to serve requests, by the address of the runtime the deployment
+    /// started with
+    pools: Arc<Mutex<HashMap<SocketAddr, Pool>>>,
+    /// The runtimes which failed the latest requests they were sent, by their address
+    failures: Arc<Mutex<HashMap<SocketAddr, Failures>>>,
+}
+
+/// The requests a runtime failed to answer in a row
+struct Failures {
+    count: u32,
+    /// Until when it is left out of the rotation, once it failed too many
+    ejected_until: Option<Instant>,
+}
+
+/// The replicas of a deployment, which take turns with its own runtime
+struct Pool {
+    service_id: Uuid,
+    addresses: Vec<SocketAddr>,
+    /// How many requests were sent to the deployment
+    next: usize,
+}
+
+impl Pool {
+    /// The next of the deployment listening at `address` and its replicas in turn
+    fn turn(&mut self, address: SocketAddr) -> SocketAddr {
+        let turn = self.next % (self.addresses.len() + 1);
+        self.next = self.next.wrapping_add(1);
+
+        match turn {
+            0 => address,
+            turn => self.addresses[turn - 1],
+        }
+    }
+
+    /// The next of the `candidates` in turn, which are some of the deployment listening at
+    /// `address` and its replicas
+    fn turn_among(&mut self, address: SocketAddr, candidates: &[SocketAddr]) -> SocketAddr {
+        for _ in 0..=self.addresses.len() {
+            let member = self.turn(address);
+            if candidates.contains(&member) {
+                return member;
+            }
+        }
+
+        candidates[0]
+    }
+}
+
+impl Replicas {
+    /// How many runtimes the deployments of service `service_id` run in
+    pub fn count(&self, service_id: &Uuid) -> u32 {
+        self.counts
+            .lock()
+            .unwrap()
+            .get(service_id)
+            .map_or(1, |count| *count.borrow())
+    }
+
+    /// Run the deployments of service `service_id` in `count` runtimes, which starts or stops the
+    /// replicas of its running deployment
+    pub fn scale(&self, service_id: Uuid, count: u32) {
+        self.counts
+            .lock()
+            .unwrap()
+            .entry(service_id)
+            .or_insert_with(|| watch::channel(1).0)
+            .send_replace(count);
+    }
+
+    /// How the replicas of service `service_id` take turns
+    pub fn routing(&self, service_id: &Uuid) -> Routing {
+        self.routings
+            .lock()
+            .unwrap()
+            .get(service_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Let the replicas of service `service_id` take turns by `routing` from the next request on
+    pub fn route(&self, service_id: Uuid, routing: Routing) {
+        self.routings.lock().unwrap().insert(service_id, routing);
+    }
+
+    fn subscribe(&self, service_id: Uuid) -> watch::Receiver<u32> {
+        self.counts
+            .lock()
+            .unwrap()
+            .entry(service_id)
+            .or_insert_with(|| watch::channel(1).0)
+            .subscribe()
+    }
+
+    /// The address to send a request with `headers` for the deployment listening at `address` to,
+    /// picked from it and its replicas by the routing of its service. Clients of sticky sessions
+    /// which are not on a replica yet also get the `Set-Cookie` value which keeps them on it.
+    ///
+    /// The runtimes which were `tried` already are not picked again, and those which failed too
+    /// many requests only while every other was tried. There is nothing to pick once every one of
+    /// them was tried.
+    pub fn pick(
+        &self,
+        address: SocketAddr,
+        headers: &HeaderMap,
+        tried: &[SocketAddr],
+    ) -> Option<(SocketAddr, Option<HeaderValue>)> {
+        let mut pools = self.pools.lock().unwrap();
+        let Some(pool) = pools.get_mut(&address) else {
+            return (!tried.contains(&address)).then_some((address, None));
+        };
+
+        let untried: Vec<_> = iter::once(address)
+            .chain(pool.addresses.iter().copied())
+            .filter(|member| !tried.contains(member))
+            .collect();
+        if untried.is_empty() {
+            return None;
+        }
+
+        let now = Instant::now();
+        let failures = self.failures.lock().unwrap();
+        let healthy: Vec<_> = untried
+            .iter()
+            .copied()
+            .filter(|member| {
+                failures
+                    .get(member)
+                    .and_then(|failures| failures.ejected_until)
+                    .map_or(true, |until| until <= now)
+            })
+            .collect();
+        drop(failures);
+
+        let candidates = if healthy.is_empty() { untried } else { healthy };
+
+        let picked = match self.routing(&pool.service_id) {
+            Routing::RoundRobin => (pool.turn_among(address, &candidates), None),
+            Routing::Sticky { cookie } => {
+                let pinned = cookie_value(headers, &cookie).and_then(|value| {
+                    candidates
+                        .iter()
+                        .copied()
+                        .find(|member| replica_key(member) == value)
+                });
+                if let Some(member) = pinned {
+                    return Some((member, None));
+                }
+
+                let member = pool.turn_among(address, &candidates);
+                let set_cookie = HeaderValue::from_str(&format!(
+                    "{cookie}={}; Path=/; HttpOnly; SameSite=Lax",
+                    replica_key(&member)
+                ))
+                .ok();
+
+                (member, set_cookie)
+            }
+            Routing::Hash { header } => match headers.get(header.as_str()) {
+                // Rendezvous hashing, so that only the values of the replicas which come or go
+                // move
+                Some(value) => {
+                    let member = candidates
+                        .iter()
+                        .copied()
+                        .max_by_key(|member| weight(value.as_bytes(), member))
+                        .unwrap_or(address);
+
+                    (member, None)
+                }
+                None => (pool.turn_among(address, &candidates), None),
+            },
+        };
+
+        Some(picked)
+    }
+
+    /// The runtime listening at `member` answered a request
+    pub fn report_success(&self, member: SocketAddr) {
+        let mut failures = self.failures.lock().unwrap();
+        if !failures.is_empty() {
+            failures.remove(&member);
+        }
+    }
+
+    /// The runtime listening at `member` could not be reached or broke off a request, which takes
+    /// it out of the rotation for a while once it happened too many times in a row
+    pub fn report_failure(&self, member: SocketAddr) {
+        let mut failures = self.failures.lock().unwrap();
+        let failures = failures.entry(member).or_insert(Failures {
+            count: 0,
+            ejected_until: None,
+        });
+
+        failures.count += 1;
+        if failures.count >= EJECT_AFTER_FAILURES {
+            if failures.ejected_until.is_none() {
+                warn!(%member, failures = failures.count, "taking runtime out of the rotation");
+            }
+            failures.ejected_until = Some(Instant::now() + EJECTION);
+        }
+    }
+
+    fn add(&self, service_id: Uuid, address: SocketAddr, replica: SocketAddr) {
+        self.pools
+            .lock()
+            .unwrap()
+            .entry(address)
+            .or_insert_with(|| Pool {
+                service_id,
+                addresses: Vec::new(),
+                next: 0,
+            })
+            .addresses
+            .push(replica);
+    }
+
+    fn remove(&self, address: SocketAddr, replica: SocketAddr) {
+        if let Some(pool) = self.pools.lock().unwrap().get_mut(&address) {
+            pool.addresses.retain(|other| other != &replica);
+        }
+        self.failures.lock().unwrap().remove(&replica);
+    }
+
+    /// Stop sending requests to the replicas of the deployment listening at `address`
+    pub fn clear(&self, address: SocketAddr) {
+        let pool = self.pools.lock().unwrap().remove(&address);
+
+        // The ports are picked again by the runtimes which are started next
+        let mut failures = self.failures.lock().unwrap();
+        failures.remove(&address);
+        for replica in pool.into_iter().flat_map(|pool| pool.addresses) {
+            failures.remove(&replica);
+        }
+    }
+}
+
+/// What a cookie of sticky sessions holds for the runtime listening at `address`, which does not
+/// tell clients the address
+fn replica_key(address: &SocketAddr) -> String {
+    let mut hasher = DefaultHasher::new();
+    address.hash(&mut hasher);
+
+    format!("{:016x}", hasher.finish())
+}
+
+/// How much the runtime listening at `address` wants the requests with a header of `value`
+fn weight(value: &[u8], address: &SocketAddr) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    address.hash(&mut hasher);
+
+    hasher.finish()
+}
+
+/// The value of the cookie called `name` in `headers`
+fn cookie_value<'h>(headers: &'h HeaderMap, name: &str) -> Option<&'h str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find_map(|(key, value)| (key == name).then_some(value))
+}
+
+/// Starts replicas of a running deployment, the same way its own runtime was started
+#[derive(Clone)]
+pub struct ReplicaStarter<SG, EG, RM> {
+    pub id: Uuid,
+    pub service_name: String,
+    pub service_id: Uuid,
+    pub is_next: bool,
+    pub storage_manager: ArtifactsStorageManager,
+    pub secret_getter: SG,
+    pub env_var_getter: EG,
+    pub resource_manager: RM,
+    pub runtime_manager: Arc<tokio::sync::Mutex<RuntimeManager>>,
+    pub health: HealthConfig,
+    pub args: Vec<String>,
+}
+
+impl<SG, EG, RM> ReplicaStarter<SG, EG, RM>
+where
+    SG: SecretGetter,
+    EG: EnvVarGetter,
+    RM: ResourceManager,
+{
+    /// Start a replica which is ready to serve requests, getting its id, its address and the stream
+    /// which tells when it stops
+    #[instrument(skip(self), fields(id = %self.id))]
+    async fn start(&self) -> Result<(Uuid, SocketAddr, Streaming<SubscribeStopResponse>)> {
+        let executable_path = self.storage_manager.deployment_executable_path(&self.id)?;
+        let port = pick_unused_port().ok_or_else(|| {
+            Error::PrepareRun("could not find a free port to start a replica on".to_string())
+        })?;
+        let address = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port);
+
+        let alpha_runtime_path = (!self.is_next).then(|| executable_path.clone());
+        let assets_path = self.storage_manager.deployment_assets_path(&self.id)?;
+        let assets_path = assets_path.exists().then_some(assets_path);
+        let env = run::runtime_env(&self.env_var_getter, &self.service_id).await?;
+
+        let (replica_id, mut runtime_client) = self
+            .runtime_manager
+            .lock()
+            .await
+            .get_replica_client(self.id, alpha_runtime_path, assets_path, env)
+            .await
+            .map_err(Error::Runtime)?;
+
+        let started = async {
+            // Without a claim, the resources provisioned for the deployment are reused
+            run::load(
+                self.service_name.clone(),
+                self.service_id,
+                executable_path,
+                self.secret_getter.clone(),
+                self.resource_manager.clone(),
+                runtime_client.clone(),
+                self.args.clone(),
+                None,
+            )
+            .await?;
+
+            let stream = runtime_client
+                .subscribe_stop(tonic::Request::new(SubscribeStopRequest {}))
+                .await
+                .map_err(|status| Error::Start(status.to_string()))?
+                .into_inner();
+
+            runtime_client
+                .start(tonic::Request::new(StartRequest {
+                    ip: address.to_string(),
+                }))
+                .await
+                .map_err(|status| Error::Start(status.to_string()))?;
+
+            // The health of a replica is not reported, only that of the deployment
+            health::ready(
+                replica_id,
+                address,
+                &self.health,
+                &HealthStatuses::default(),
+            )
+            .await
+            .map_err(|error| {
+                Error::Unhealthy(format!(
+                    "not ready within {}s: {error}",
+                    self.health.readiness_timeout
+                ))
+            })?;
+
+            Ok::<_, Error>(stream)
+        }
+        .await;
+
+        match started {
+            Ok(stream) => Ok((replica_id, address, stream)),
+            Err(error) => {
+                self.runtime_manager
+                    .lock()
+                    .await
+                    .kill_replica(&self.id, &replica_id)
+                    .await;
+
+                Err(error)
+            }
+        }
+    }
+}
+
+/// Keep as many replicas of the deployment listening at `address` running as its service is scaled
+/// to, sending them their share of its requests once they are ready. Replicas which stop are started
+/// again. This never returns, and the replicas are left running when it is dropped.
+pub async fn keep_scaled<SG, EG, RM>(
+    address: SocketAddr,
+    replicas: Replicas,
+    starter: ReplicaStarter<SG, EG, RM>,
+) -> Infallible
+where
+    SG: SecretGetter,
+    EG: EnvVarGetter,
+    RM: ResourceManager,
+{
+    let mut count = replicas.subscribe(starter.service_id);
+    let (stopped_send, mut stopped_recv) = mpsc::unbounded_channel();
+    let mut running: Vec<(Uuid, SocketAddr)> = Vec::new();
+
+    loop {
+        // The runtime of the deployment itself counts as one
+        let wanted = count.borrow_and_update().saturating_sub(1) as usize;
+
+        while running.len() < wanted {
+            match starter.start().await {
+                Ok((replica_id, replica, mut stream)) => {
+                    info!(%replica_id, %replica, "replica is serving requests");
+                    replicas.add(starter.service_id, address, replica);
+                    running.push((replica_id, replica));
+
+                    let stopped_send = stopped_send.clone();
+                    let health = starter.health.clone();
+                    tokio::spawn(async move {
+                        let statuses = HealthStatuses::default();
+
+                        // A replica which keeps failing its liveness probes is replaced like one
+                        // which stopped
+                        tokio::select! {
+                            _ = stream.message() => {}
+                            error = health::live(replica_id, replica, &health, &statuses) => {
+                                warn!(%replica_id, %error, "replica failed its liveness probes");
+                            }
+                        }
+                        let _ = stopped_send.send(replica_id);
+                    });
+                }
+                Err(error) => {
+                    error!(
+                        error = &error as &dyn std::error::Error,
+                        "failed to start a replica"
+                    );
+                    break;
+                }
+            }
+        }
+
+        while running.len() > wanted {
+            let Some((replica_id, replica)) = running.pop() else {
+                break;
+            };
+
+            // No requests are sent to it while it finishes those it is serving
+            replicas.remove(address, replica);
+            starter
+                .runtime_manager
+                .lock()
+                .await
+                .kill_replica(&starter.id, &replica_id)
+                .await;
+            info!(%replica_id, "stopped replica");
+        }
+
+        tokio::select! {
+            changed = count.changed() => {
+                if changed.is_err() {
+                    return futures::future::pending().await;
+                }
+            }
+            Some(replica_id) = stopped_recv.recv() => {
+                // Replicas which were scaled away are not running anymore
+                if let Some(index) = running.iter().position(|(other, _)| other == &replica_id) {
+                    let (_, replica) = running.remove(index);
+                    replicas.remove(address, replica);
+                    starter
+                        .runtime_manager
+                        .lock()
+                        .await
+                        .kill_replica(&starter.id, &replica_id)
+                        .await;
+                    warn!(%replica_id, "replica stopped, starting another");
+                }
+            }
+            _ = sleep(RETRY_INTERVAL), if running.len() < wanted => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr};
+
+    use hyper::header::{HeaderMap, HeaderValue, COOKIE};
+    use shuttle_common::models::service::Routing;
+    use uuid::Uuid;
+
+    use super::Replicas;
+
+    /// A deployment listening on port 8000, with replicas listening on the ports after it
+    fn scaled(replicas: &Replicas, service_id: Uuid, count: u16) -> Vec<SocketAddr> {
+        let address = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 8000);
+        let mut members = vec![address];
+
+        for port in 8001..8000 + count {
+            let replica = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port);
+            replicas.add(service_id, address, replica);
+            members.push(replica);
+        }
+
+        members
+    }
+
+    #[test]
+    fn round_robin() {
+        let replicas = Replicas::default();
+        let address = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 8000);
+        let headers = HeaderMap::new();
+        let next = || replicas.pick(address, &headers, &[]).unwrap().0;
+
+        // Deployments without replicas get all their requests
+        assert_eq!(next(), address);
+        assert_eq!(next(), address);
+
+        let members = scaled(&replicas, Uuid::new_v4(), 3);
+
+        let turns: Vec<_> = (0..6).map(|_| next()).collect();
+        assert_eq!(turns, [members.clone(), members.clone()].concat());
+
+        replicas.remove(address, members[1]);
+        assert!((0..4).all(|_| next() != members[1]));
+
+        replicas.clear(address);
+        assert_eq!(next(), address);
+    }
+
+    #[test]
+    fn sticky() {
+        let replicas = Replicas::default();
+        let service_id = Uuid::new_v4();
+        let members = scaled(&replicas, service_id, 3);
+        replicas.route(
+            service_id,
+            Routing::Sticky {
+                cookie: "session".to_string(),
+            },
+        );
+
+        // New clients take turns, and are told which replica they are on
+        let (first, _) = replicas.pick(members[0], &HeaderMap::new(), &[]).unwrap();
+        let (second, set_cookie) = replicas.pick(members[0], &HeaderMap::new(), &[]).unwrap();
+        let set_cookie = set_cookie.unwrap();
+        assert_ne!(first, second);
+
+        let cookie = set_cookie.to_str().unwrap().split(';').next().unwrap();
+        assert!(cookie.starts_with("session="));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            COOKIE,
+            HeaderValue::from_str(&format!("theme=dark; {cookie}")).unwrap(),
+        );
+        for _ in 0..4 {
+            assert_eq!(
+                replicas.pick(members[0], &headers, &[]),
+                Some((second, None))
+            );
+        }
+
+        // Clients of a replica which went away are moved to another
+        replicas.remove(members[0], second);
+        let (moved, set_cookie) = replicas.pick(members[0], &headers, &[]).unwrap();
+        assert_ne!(moved, second);
+        assert!(set_cookie.is_some());
+    }
+
+    #[test]
+    fn consistent_hash() {
+        let replicas = Replicas::default();
+        let service_id = Uuid::new_v4();
+        let members = scaled(&replicas, service_id, 4);
+        replicas.route(
+            service_id,
+            Routing::Hash {
+                header: "x-user-id".to_string(),
+            },
+        );
+
+        let pick = |user: usize| {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-user-id", HeaderValue::from(user));
+            replicas.pick(members[0], &headers, &[]).unwrap().0
+        };
+
+        let before: Vec<_> = (0..100).map(pick).collect();
+        assert_eq!(before, (0..100).map(pick).collect::<Vec<_>>());
+        assert!(members.iter().all(|member| before.contains(member)));
+
+        // Only the users of the replica which went away move
+        replicas.remove(members[0], members[3]);
+        let after: Vec<_> = (0..100).map(pick).collect();
+        for (before, after) in before.iter().zip(&after) {
+            if before != &members[3] {
+                assert_eq!(before, after);
+            } else {
+                assert_ne!(after, &members[3]);
+            }
+        }
+
+        // Requests without the header take turns
+        let turns: Vec<_> = (0..3)
+            .map(|_| replicas.pick(members[0], &HeaderMap::new(), &[]).unwrap().0)
+            .collect();
+        assert_eq!(turns, members[..3]);
+    }
+
+    #[test]
+    fn failover() {
+        let replicas = Replicas::default();
+        let members = scaled(&replicas, Uuid::new_v4(), 3);
+        let address = members[0];
+        let headers = HeaderMap::new();
+
+        // Requests which failed on some runtimes are sent to the others
+        let (first, _) = replicas.pick(address, &headers, &[]).unwrap();
+        let (second, _) = replicas.pick(address, &headers, &[first]).unwrap();
+        let (third, _) = replicas.pick(address, &headers, &[first, second]).unwrap();
+        assert_ne!(first, second);
+        assert!(![first, second].contains(&third));
+        assert_eq!(replicas.pick(address, &headers, &members), None);
+
+        // A runtime which failed a few requests in a row keeps its turns
+        replicas.report_failure(members[1]);
+        replicas.report_failure(members[1]);
+        replicas.report_success(members[1]);
+        replicas.report_failure(members[1]);
+        replicas.report_failure(members[1]);
+        assert!((0..6).any(|_| replicas.pick(address, &headers, &[]).unwrap().0 == members[1]));
+
+        // Until it failed too many, which leaves it out of the rotation
+        replicas.report_failure(members[1]);
+        assert!((0..6).all(|_| replicas.pick(address, &headers, &[]).unwrap().0 != members[1]));
+
+        // Unless it is the only one left to try
+        assert_eq!(
+            replicas.pick(address, &headers, &[members[0], members[2]]),
+            Some((members[1], None))
+        );
+
+        // Deployments without replicas have nothing to fail over to
+        let lone = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 9000);
+        assert_eq!(replicas.pick(lone, &headers, &[]), Some((lone, None)));
+        assert_eq!(replicas.pick(lone, &headers, &[lone]), None);
+    }
+
+    #[test]
+    fn scale() {
+        let replicas = Replicas::default();
+        let service_id = Uuid::new_v4();
+
+        assert_eq!(replicas.count(&service_id), 1);
+
+        let count = replicas.subscribe(service_id);
+        replicas.scale(service_id, 3);
+
+        assert_eq!(replicas.count(&service_id), 3);
+        assert!(count.has_changed().unwrap());
+        assert_eq!(*count.borrow(), 3);
+    }
+}
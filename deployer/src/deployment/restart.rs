@@ -0,0 +1,102 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use shuttle_common::shuttle_toml::RestartPolicy;
+use shuttle_proto::runtime::StopReason;
+use uuid::Uuid;
+
+/// How long to wait before the first restart of a deployment, which doubles with every restart after
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+
+/// How many restarts in a row a deployment gets within [CRASH_LOOP_WINDOW] before it is taken to be
+/// crash looping
+const CRASH_LOOP_LIMIT: usize = 5;
+
+/// How long the stops of a deployment are remembered to tell whether it is crash looping
+const CRASH_LOOP_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// Whether `policy` starts a deployment which stopped for `reason` again. Deployments which were
+/// stopped on request are never started again.
+pub fn applies(policy: RestartPolicy, reason: Option<StopReason>) -> bool {
+    match (policy, reason) {
+        (RestartPolicy::Never, _) | (_, Some(StopReason::Request)) => false,
+        (RestartPolicy::Always, _) => true,
+        (RestartPolicy::OnFailure, reason) => reason != Some(StopReason::End),
+    }
+}
+
+/// The recent stops of the deployments which are restarted
+#[derive(Clone, Default)]
+pub struct Restarts(Arc<Mutex<HashMap<Uuid, Vec<Instant>>>>);
+
+impl Restarts {
+    /// Record that deployment `id` stopped, and get how long to wait before it is started again. A
+    /// deployment which stopped too often lately is crash looping and gets no restart.
+    pub fn backoff(&self, id: &Uuid) -> Option<Duration> {
+        self.backoff_at(id, Instant::now())
+    }
+
+    fn backoff_at(&self, id: &Uuid, now: Instant) -> Option<Duration> {
+        let mut restarts = self.0.lock().unwrap();
+        let stops = restarts.entry(*id).or_default();
+
+        stops.retain(|stop| now.duration_since(*stop) < CRASH_LOOP_WINDOW);
+        stops.push(now);
+
+        if stops.len() > CRASH_LOOP_LIMIT {
+            restarts.remove(id);
+
+            return None;
+        }
+
+        Some(BACKOFF_BASE * 2u32.pow(stops.len() as u32 - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use shuttle_common::shuttle_toml::RestartPolicy;
+    use shuttle_proto::runtime::StopReason;
+    use uuid::Uuid;
+
+    use super::{applies, Restarts, CRASH_LOOP_WINDOW};
+
+    #[test]
+    fn policies() {
+        assert!(applies(RestartPolicy::OnFailure, Some(StopReason::Crash)));
+        assert!(applies(RestartPolicy::OnFailure, None));
+        assert!(!applies(RestartPolicy::OnFailure, Some(StopReason::End)));
+        assert!(applies(RestartPolicy::Always, Some(StopReason::End)));
+        assert!(!applies(RestartPolicy::Always, Some(StopReason::Request)));
+        assert!(!applies(RestartPolicy::Never, Some(StopReason::Crash)));
+    }
+
+    #[test]
+    fn backoff_until_crash_loop() {
+        let restarts = Restarts::default();
+        let id = Uuid::new_v4();
+        let start = Instant::now();
+        let backoff = |seconds| restarts.backoff_at(&id, start + Duration::from_secs(seconds));
+
+        assert_eq!(backoff(0), Some(Duration::from_secs(1)));
+        assert_eq!(backoff(10), Some(Duration::from_secs(2)));
+        assert_eq!(backoff(20), Some(Duration::from_secs(4)));
+
+        // Stops long ago do not count anymore
+        let later = CRASH_LOOP_WINDOW.as_secs() + 25;
+        assert_eq!(backoff(later), Some(Duration::from_secs(1)));
+        assert_eq!(backoff(later + 10), Some(Duration::from_secs(2)));
+        assert_eq!(backoff(later + 20), Some(Duration::from_secs(4)));
+        assert_eq!(backoff(later + 30), Some(Duration::from_secs(8)));
+        assert_eq!(backoff(later + 40), Some(Duration::from_secs(16)));
+        assert_eq!(backoff(later + 50), None);
+
+        // The count starts over once a crash looping deployment is parked
+        assert_eq!(backoff(later + 60), Some(Duration::from_secs(1)));
+    }
+}
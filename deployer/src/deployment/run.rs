@@ -8,9 +8,11 @@ use std::{
 use async_trait::async_trait;
 use opentelemetry::global;
 use portpicker::pick_unused_port;
+use serde::{Deserialize, Serialize};
 use shuttle_common::{
     claims::{Claim, ClaimService, InjectPropagation},
     resource,
+    shuttle_toml::{CronJob, HealthConfig, HttpConfig, RestartPolicy},
     storage_manager::ArtifactsStorageManager,
 };
 
@@ -18,32 +20,46 @@ use shuttle_proto::runtime::{
     runtime_client::RuntimeClient, LoadRequest, StartRequest, StopReason, SubscribeStopRequest,
     SubscribeStopResponse,
 };
-use tokio::sync::Mutex;
+use tokio::{sync::Mutex, time::sleep};
 use tonic::{transport::Channel, Code};
 use tracing::{debug, debug_span, error, info, instrument, trace, warn, Instrument};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 use uuid::Uuid;
 
-use super::{RunReceiver, State};
+use super::{
+    cron,
+    health::{self, HealthStatuses},
+    replicas::{self, ReplicaStarter, Replicas},
+    restart::{self, Restarts},
+    RunReceiver, RunSender, State,
+};
 use crate::{
     error::{Error, Result},
-    persistence::{DeploymentUpdater, Resource, ResourceManager, SecretGetter},
+    persistence::{DeploymentUpdater, EnvVarGetter, Resource, ResourceManager, SecretGetter},
     RuntimeManager,
 };
 
 /// Run a task which takes runnable deploys from a channel and starts them up on our runtime
 /// A deploy is killed when it receives a signal from the kill channel
+#[allow(clippy::too_many_arguments)]
 pub async fn task(
     mut recv: RunReceiver,
+    run_send: RunSender,
     runtime_manager: Arc<Mutex<RuntimeManager>>,
     deployment_updater: impl DeploymentUpdater,
     active_deployment_getter: impl ActiveDeploymentsGetter,
     secret_getter: impl SecretGetter,
+    env_var_getter: impl EnvVarGetter,
     resource_manager: impl ResourceManager,
     storage_manager: ArtifactsStorageManager,
+    health_statuses: HealthStatuses,
+    replicas: Replicas,
 ) {
     info!("Run task started");
 
+    let restarts = Restarts::default();
+    let limits = runtime_manager.lock().await.limits();
+
     while let Some(built) = recv.recv().await {
         let id = built.id;
 
@@ -51,8 +67,20 @@ pub async fn task(
 
         let deployment_updater = deployment_updater.clone();
         let secret_getter = secret_getter.clone();
+        let env_var_getter = env_var_getter.clone();
         let resource_manager = resource_manager.clone();
         let storage_manager = storage_manager.clone();
+        let health_statuses = health_statuses.clone();
+        let replicas = replicas.clone();
+
+        let run_config = RunConfig::load(&storage_manager, &id);
+        let restart_policy = run_config.restart;
+        let restarter = Restarter::new(
+            &built,
+            run_send.clone(),
+            restarts.clone(),
+            runtime_manager.clone(),
+        );
 
         let old_deployments_killer = kill_old_deployments(
             built.service_id,
@@ -60,10 +88,29 @@ pub async fn task(
             active_deployment_getter.clone(),
             runtime_manager.clone(),
         );
+        let cleanup_restarter = restarter.clone();
+        let cleanup_updater = deployment_updater.clone();
+        let limits = limits.clone();
         let cleanup = move |response: Option<SubscribeStopResponse>| {
             debug!(response = ?response,  "stop client response: ");
 
-            if let Some(response) = response {
+            let reason = response
+                .as_ref()
+                .map(|response| StopReason::from_i32(response.reason).unwrap_or_default());
+
+            // The kernel kills a runtime which runs out of memory without it getting to say why
+            if matches!(reason, None | Some(StopReason::Crash)) && limits.out_of_memory(&id) {
+                tokio::spawn(async move {
+                    if let Err(error) = cleanup_updater.record_oom_kill(&id).await {
+                        error!(
+                            error = &error as &dyn std::error::Error,
+                            "failed to record that the deployment ran out of memory"
+                        );
+                    }
+                });
+
+                crashed_cleanup(&id, Error::OutOfMemory(limits.memory.unwrap_or_default()));
+            } else if let Some(response) = response {
                 match StopReason::from_i32(response.reason).unwrap_or_default() {
                     StopReason::Request => stopped_cleanup(&id),
                     StopReason::End => completed_cleanup(&id),
@@ -80,6 +127,10 @@ pub async fn task(
                     )),
                 )
             }
+
+            if restart::applies(restart_policy, reason) {
+                tokio::spawn(cleanup_restarter.restart());
+            }
         };
         let runtime_manager = runtime_manager.clone();
 
@@ -95,9 +146,14 @@ pub async fn task(
                     .handle(
                         storage_manager,
                         secret_getter,
+                        env_var_getter,
                         resource_manager,
                         runtime_manager,
                         deployment_updater,
+                        run_config,
+                        health_statuses,
+                        replicas,
+                        restarter,
                         old_deployments_killer,
                         cleanup,
                     )
@@ -114,6 +170,8 @@ pub async fn task(
     }
 }
 
+/// Shut down the other running deployments of a service, which get to finish the requests they were
+/// still serving
 #[instrument(skip(active_deployment_getter, runtime_manager))]
 async fn kill_old_deployments(
     service_id: Uuid,
@@ -121,16 +179,24 @@ async fn kill_old_deployments(
     active_deployment_getter: impl ActiveDeploymentsGetter,
     runtime_manager: Arc<Mutex<RuntimeManager>>,
 ) -> Result<()> {
-    let mut guard = runtime_manager.lock().await;
-
-    for old_id in active_deployment_getter
+    let old_ids: Vec<_> = active_deployment_getter
         .clone()
         .get_active_deployments(&service_id)
         .await
         .map_err(|e| Error::OldCleanup(Box::new(e)))?
         .into_iter()
         .filter(|old_id| old_id != &deployment_id)
-    {
+        .collect();
+
+    if old_ids.is_empty() {
+        return Ok(());
+    }
+
+    trace!(count = old_ids.len(), "draining old deployments");
+
+    let mut guard = runtime_manager.lock().await;
+
+    for old_id in old_ids {
         trace!(%old_id, "stopping old deployment");
 
         if !guard.kill(&old_id).await {
@@ -167,6 +233,86 @@ fn start_crashed_cleanup(_id: &Uuid, error: impl std::error::Error + 'static) {
     );
 }
 
+/// The settings from the Shuttle.toml of a deployment which are about running it
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct RunConfig {
+    pub health: HealthConfig,
+    pub restart: RestartPolicy,
+    pub cron: Vec<CronJob>,
+    /// The arguments from the Shuttle.toml for the environment of the service
+    pub args: Vec<String>,
+    /// The headers the gateway adds to the responses of the service
+    pub http: HttpConfig,
+}
+
+impl RunConfig {
+    /// The settings deployment `id` was built with, or the defaults if it was built before they
+    /// were kept
+    pub fn load(storage_manager: &ArtifactsStorageManager, id: &Uuid) -> Self {
+        storage_manager
+            .deployment_run_config_path(id)
+            .and_then(std::fs::read)
+            .ok()
+            .and_then(|run_config| serde_json::from_slice(&run_config).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Starts a deployment again after it stopped
+#[derive(Clone)]
+pub struct Restarter {
+    built: Built,
+    run_send: RunSender,
+    restarts: Restarts,
+    runtime_manager: Arc<Mutex<RuntimeManager>>,
+}
+
+impl Restarter {
+    pub fn new(
+        built: &Built,
+        run_send: RunSender,
+        restarts: Restarts,
+        runtime_manager: Arc<Mutex<RuntimeManager>>,
+    ) -> Self {
+        Self {
+            // A restarted service gets its resources from the cache, like a project which wakes up
+            built: Built {
+                tracing_context: Default::default(),
+                claim: None,
+                ..built.clone()
+            },
+            run_send,
+            restarts,
+            runtime_manager,
+        }
+    }
+
+    /// Start the deployment again once its backoff is over. A deployment which is crash looping is
+    /// parked as crashed instead, with the last lines its runtime wrote to stderr.
+    #[instrument(skip(self), fields(id = %self.built.id))]
+    async fn restart(self) {
+        let id = self.built.id;
+
+        let Some(backoff) = self.restarts.backoff(&id) else {
+            let mut runtime_manager = self.runtime_manager.lock().await;
+            let stderr = runtime_manager.stderr_tail(&id);
+            runtime_manager.kill(&id).await;
+
+            crashed_cleanup(&id, Error::CrashLoop(stderr.join("\n")));
+
+            return;
+        };
+
+        info!("starting the deployment again in {}s", backoff.as_secs());
+        sleep(backoff).await;
+
+        if self.run_send.send(self.built).await.is_err() {
+            error!("failed to start the deployment again");
+        }
+    }
+}
+
 #[async_trait]
 pub trait ActiveDeploymentsGetter: Clone + Send + Sync + 'static {
     type Err: std::error::Error + Send;
@@ -188,16 +334,21 @@ pub struct Built {
 }
 
 impl Built {
-    #[instrument(skip(self, storage_manager, secret_getter, resource_manager, runtime_manager, deployment_updater, kill_old_deployments, cleanup), fields(id = %self.id, state = %State::Loading))]
+    #[instrument(skip(self, storage_manager, secret_getter, env_var_getter, resource_manager, runtime_manager, deployment_updater, run_config, health_statuses, replicas, restarter, kill_old_deployments, cleanup), fields(id = %self.id, state = %State::Loading))]
     #[allow(clippy::too_many_arguments)]
     async fn handle(
         self,
         storage_manager: ArtifactsStorageManager,
         secret_getter: impl SecretGetter,
+        env_var_getter: impl EnvVarGetter,
         resource_manager: impl ResourceManager,
         runtime_manager: Arc<Mutex<RuntimeManager>>,
         deployment_updater: impl DeploymentUpdater,
-        kill_old_deployments: impl futures::Future<Output = Result<()>>,
+        run_config: RunConfig,
+        health_statuses: HealthStatuses,
+        replicas: Replicas,
+        restarter: Restarter,
+        kill_old_deployments: impl futures::Future<Output = Result<()>> + Send + 'static,
         cleanup: impl FnOnce(Option<SubscribeStopResponse>) + Send + 'static,
     ) -> Result<()> {
         // For alpha this is the path to the users project with an embedded runtime.
@@ -223,15 +374,34 @@ impl Built {
             Some(executable_path.clone())
         };
 
+        // Start the service next to its assets, if it has any
+        let assets_path = storage_manager.deployment_assets_path(&self.id)?;
+        let assets_path = assets_path.exists().then_some(assets_path);
+
+        let env = runtime_env(&env_var_getter, &self.service_id).await?;
+
+        let replica_starter = ReplicaStarter {
+            id: self.id,
+            service_name: self.service_name.clone(),
+            service_id: self.service_id,
+            is_next: self.is_next,
+            storage_manager,
+            secret_getter: secret_getter.clone(),
+            env_var_getter,
+            resource_manager: resource_manager.clone(),
+            runtime_manager: runtime_manager.clone(),
+            health: run_config.health.clone(),
+            args: run_config.args.clone(),
+        };
+
         let runtime_client = runtime_manager
             .lock()
             .await
-            .get_runtime_client(self.id, alpha_runtime_path.clone())
+            .get_runtime_client(self.id, alpha_runtime_path.clone(), assets_path, env)
             .await
             .map_err(Error::Runtime)?;
 
-        kill_old_deployments.await?;
-
+        // The old deployments keep serving until this one is ready
         // Execute loaded service
         load(
             self.service_name.clone(),
@@ -240,6 +410,7 @@ impl Built {
             secret_getter,
             resource_manager,
             runtime_client.clone(),
+            run_config.args.clone(),
             self.claim,
         )
         .await?;
@@ -250,6 +421,13 @@ impl Built {
             runtime_client,
             address,
             deployment_updater,
+            run_config,
+            health_statuses,
+            runtime_manager,
+            replicas,
+            replica_starter,
+            kill_old_deployments,
+            restarter,
             cleanup,
         ));
 
@@ -257,13 +435,29 @@ impl Built {
     }
 }
 
-async fn load(
+/// The environment variables the runtimes of the service `service_id` are started with
+pub(super) async fn runtime_env(
+    env_var_getter: &impl EnvVarGetter,
+    service_id: &Uuid,
+) -> Result<HashMap<String, String>> {
+    Ok(env_var_getter
+        .get_env_vars(service_id)
+        .await
+        .map_err(|e| Error::EnvVarsGet(Box::new(e)))?
+        .into_iter()
+        .map(|var| (var.key, var.value))
+        .collect())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn load(
     service_name: String,
     service_id: Uuid,
     executable_path: PathBuf,
     secret_getter: impl SecretGetter,
     resource_manager: impl ResourceManager,
     mut runtime_client: RuntimeClient<ClaimService<InjectPropagation<Channel>>>,
+    args: Vec<String>,
     claim: Option<Claim>,
 ) -> Result<()> {
     info!(
@@ -305,6 +499,7 @@ async fn load(
         service_name: service_name.clone(),
         resources,
         secrets,
+        args,
     });
 
     if let Some(claim) = claim {
@@ -350,15 +545,27 @@ async fn load(
     }
 }
 
-#[instrument(skip(runtime_client, deployment_updater, cleanup), fields(state = %State::Running))]
+#[instrument(skip(runtime_client, deployment_updater, run_config, health_statuses, runtime_manager, replicas, replica_starter, kill_old_deployments, restarter, cleanup), fields(state = %State::Running))]
+#[allow(clippy::too_many_arguments)]
 async fn run(
     id: Uuid,
     service_name: String,
     mut runtime_client: RuntimeClient<ClaimService<InjectPropagation<Channel>>>,
     address: SocketAddr,
     deployment_updater: impl DeploymentUpdater,
+    run_config: RunConfig,
+    health_statuses: HealthStatuses,
+    runtime_manager: Arc<Mutex<RuntimeManager>>,
+    replicas: Replicas,
+    replica_starter: ReplicaStarter<impl SecretGetter, impl EnvVarGetter, impl ResourceManager>,
+    kill_old_deployments: impl futures::Future<Output = Result<()>>,
+    restarter: Restarter,
     cleanup: impl FnOnce(Option<SubscribeStopResponse>) + Send + 'static,
 ) {
+    let RunConfig {
+        health, cron: jobs, ..
+    } = run_config;
+
     deployment_updater
         .set_address(&id, &address)
         .await
@@ -382,10 +589,110 @@ async fn run(
         Ok(response) => {
             info!(response = ?response.into_inner(),  "start client response: ");
 
-            // Wait for stop reason
-            let reason = stream.message().await.expect("message from tonic stream");
+            // Wait for the service to be ready, unless it stops first
+            let ready = tokio::select! {
+                reason = stream.message() => {
+                    health_statuses.remove(&id);
+                    cleanup(reason.expect("message from tonic stream"));
+
+                    return;
+                }
+                ready = health::ready(id, address, &health, &health_statuses) => ready,
+            };
+
+            if let Err(error) = ready {
+                health_statuses.remove(&id);
+                runtime_manager.lock().await.kill(&id).await;
+
+                crashed_cleanup(
+                    &id,
+                    Error::Unhealthy(format!(
+                        "not ready within {}s: {error}",
+                        health.readiness_timeout
+                    )),
+                );
+
+                return;
+            }
+
+            let service_id = restarter.built.service_id;
+            let previous = match deployment_updater.switch_route(&service_id, &id).await {
+                Ok(previous) => previous.filter(|previous| previous != &id),
+                Err(error) => {
+                    health_statuses.remove(&id);
+                    runtime_manager.lock().await.kill(&id).await;
+
+                    crashed_cleanup(&id, Error::Route(Box::new(error)));
+
+                    return;
+                }
+            };
+
+            info!("sending requests to the service");
+
+            // The replicas take their share of the requests as soon as they are ready
+            let scaling = replicas::keep_scaled(address, replicas.clone(), replica_starter);
+            tokio::pin!(scaling);
+
+            // Send requests back to the deployment which served them before if this one stops
+            // before that one is stopped
+            tokio::select! {
+                reason = stream.message() => {
+                    if let Some(previous) = previous {
+                        match deployment_updater.switch_route(&service_id, &previous).await {
+                            Ok(_) => warn!(%previous, "rolled requests back to the previous deployment"),
+                            Err(error) => error!(
+                                error = &error as &dyn std::error::Error,
+                                %previous,
+                                "failed to roll requests back to the previous deployment"
+                            ),
+                        }
+                    }
+
+                    health_statuses.remove(&id);
+                    stop_replicas(id, address, &replicas, &runtime_manager).await;
+                    cleanup(reason.expect("message from tonic stream"));
+
+                    return;
+                }
+                never = &mut scaling => match never {},
+                result = kill_old_deployments => {
+                    if let Err(error) = result {
+                        error!(
+                            error = &error as &dyn std::error::Error,
+                            "failed to stop the old deployments"
+                        );
+                    }
+                }
+            }
+
+            // Wait for stop reason, unless the service turns out to be unhealthy first, while its
+            // jobs run on schedule and its replicas are kept up
+            tokio::select! {
+                reason = stream.message() => {
+                    health_statuses.remove(&id);
+                    stop_replicas(id, address, &replicas, &runtime_manager).await;
+
+                    cleanup(reason.expect("message from tonic stream"));
+                }
+                error = health::live(id, address, &health, &health_statuses) => {
+                    health_statuses.remove(&id);
+                    replicas.clear(address);
+                    runtime_manager.lock().await.kill(&id).await;
+
+                    crashed_cleanup(
+                        &id,
+                        Error::Unhealthy(format!(
+                            "failed {} liveness probes in a row: {error}",
+                            health.failure_threshold
+                        )),
+                    );
 
-            cleanup(reason);
+                    restarter.restart().await;
+                }
+                never = cron::schedule(id, address, &jobs, &deployment_updater) => match never {},
+                never = &mut scaling => match never {},
+            }
         }
         Err(ref status) if status.code() == Code::InvalidArgument => {
             cleanup(Some(SubscribeStopResponse {
@@ -404,6 +711,18 @@ async fn run(
     }
 }
 
+/// Stop sending requests to the replicas of deployment `id`, which listens at `address`, and shut
+/// them down, since they are of no use without it
+async fn stop_replicas(
+    id: Uuid,
+    address: SocketAddr,
+    replicas: &Replicas,
+    runtime_manager: &Mutex<RuntimeManager>,
+) {
+    replicas.clear(address);
+    runtime_manager.lock().await.kill_replicas(&id).await;
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
@@ -426,18 +745,21 @@ mod tests {
     };
     use tempfile::Builder;
     use tokio::{
-        sync::{oneshot, Mutex},
+        sync::{mpsc, oneshot, Mutex},
         time::sleep,
     };
     use tonic::transport::Server;
     use uuid::Uuid;
 
     use crate::{
-        persistence::{DeploymentUpdater, Resource, ResourceManager, Secret, SecretGetter},
+        persistence::{
+            CronRun, DeploymentUpdater, EnvVar, EnvVarGetter, Resource, ResourceManager, Secret,
+            SecretGetter,
+        },
         RuntimeManager,
     };
 
-    use super::Built;
+    use super::{Built, Restarter};
 
     const RESOURCES_PATH: &str = "tests/resources";
 
@@ -494,7 +816,14 @@ mod tests {
             }
         });
 
-        RuntimeManager::new(path, format!("http://{}", provisioner_addr), None, tx)
+        RuntimeManager::new(
+            path,
+            format!("http://{}", provisioner_addr),
+            None,
+            tx,
+            Default::default(),
+            Duration::from_secs(1),
+        )
     }
 
     #[derive(Clone)]
@@ -509,6 +838,18 @@ mod tests {
         }
     }
 
+    #[derive(Clone)]
+    struct StubEnvVarGetter;
+
+    #[async_trait]
+    impl EnvVarGetter for StubEnvVarGetter {
+        type Err = std::io::Error;
+
+        async fn get_env_vars(&self, _service_id: &Uuid) -> Result<Vec<EnvVar>, Self::Err> {
+            Ok(Default::default())
+        }
+    }
+
     #[derive(Clone)]
     struct StubResourceManager;
 
@@ -538,6 +879,22 @@ mod tests {
         async fn set_is_next(&self, _id: &Uuid, _is_next: bool) -> Result<(), Self::Err> {
             Ok(())
         }
+
+        async fn record_oom_kill(&self, _id: &Uuid) -> Result<(), Self::Err> {
+            Ok(())
+        }
+
+        async fn record_cron_run(&self, _run: &CronRun) -> Result<(), Self::Err> {
+            Ok(())
+        }
+
+        async fn switch_route(
+            &self,
+            _service_id: &Uuid,
+            _id: &Uuid,
+        ) -> Result<Option<Uuid>, Self::Err> {
+            Ok(None)
+        }
     }
 
     // This test uses the kill signal to make sure a service does stop when asked to
@@ -559,13 +916,25 @@ mod tests {
             }
         };
 
+        let restarter = Restarter::new(
+            &built,
+            mpsc::channel(1).0,
+            Default::default(),
+            runtime_manager.clone(),
+        );
+
         built
             .handle(
                 storage_manager,
                 StubSecretGetter,
+                StubEnvVarGetter,
                 StubResourceManager,
                 runtime_manager.clone(),
                 StubDeploymentUpdater,
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                restarter,
                 kill_old_deployments(),
                 handle_cleanup,
             )
@@ -602,13 +971,25 @@ mod tests {
             }
         };
 
+        let restarter = Restarter::new(
+            &built,
+            mpsc::channel(1).0,
+            Default::default(),
+            runtime_manager.clone(),
+        );
+
         built
             .handle(
                 storage_manager,
                 StubSecretGetter,
+                StubEnvVarGetter,
                 StubResourceManager,
                 runtime_manager.clone(),
                 StubDeploymentUpdater,
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                restarter,
                 kill_old_deployments(),
                 handle_cleanup,
             )
@@ -644,13 +1025,25 @@ mod tests {
             }
         };
 
+        let restarter = Restarter::new(
+            &built,
+            mpsc::channel(1).0,
+            Default::default(),
+            runtime_manager.clone(),
+        );
+
         built
             .handle(
                 storage_manager,
                 StubSecretGetter,
+                StubEnvVarGetter,
                 StubResourceManager,
                 runtime_manager.clone(),
                 StubDeploymentUpdater,
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                restarter,
                 kill_old_deployments(),
                 handle_cleanup,
             )
@@ -675,13 +1068,25 @@ mod tests {
 
         let handle_cleanup = |_result| panic!("service should never be started");
 
+        let restarter = Restarter::new(
+            &built,
+            mpsc::channel(1).0,
+            Default::default(),
+            runtime_manager.clone(),
+        );
+
         built
             .handle(
                 storage_manager,
                 StubSecretGetter,
+                StubEnvVarGetter,
                 StubResourceManager,
                 runtime_manager.clone(),
                 StubDeploymentUpdater,
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                restarter,
                 kill_old_deployments(),
                 handle_cleanup,
             )
@@ -24,6 +24,8 @@ pub enum Error {
     SecretsSet(#[source] Box<dyn StdError + Send>),
     #[error("Failed to get secrets: {0}")]
     SecretsGet(#[source] Box<dyn StdError + Send>),
+    #[error("Failed to get environment variables: {0}")]
+    EnvVarsGet(#[source] Box<dyn StdError + Send>),
     #[error("Failed to cleanup old deployments: {0}")]
     OldCleanup(#[source] Box<dyn StdError + Send>),
     #[error("Gateway client error: {0}")]
@@ -32,6 +34,14 @@ pub enum Error {
     Runtime(#[source] anyhow::Error),
     #[error("Failed to call start on runtime: {0}")]
     Start(String),
+    #[error("Service is unhealthy: {0}")]
+    Unhealthy(String),
+    #[error("Service is crash looping, the last lines it wrote to stderr were:\n{0}")]
+    CrashLoop(String),
+    #[error("Service was killed for using more than its memory limit of {0} MiB")]
+    OutOfMemory(u64),
+    #[error("Failed to route requests to deployment: {0}")]
+    Route(#[source] Box<dyn StdError + Send>),
 }
 
 #[derive(Error, Debug)]
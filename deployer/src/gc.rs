@@ -0,0 +1,239 @@
+use std::{io, sync::Arc, time::Duration};
+
+use shuttle_common::{
+    models::artifacts::{Retention, Sweep},
+    storage_manager::{ArtifactsStorageManager, StorageManager},
+};
+use shuttle_service::builder::{dir_size, purge_crate};
+use tokio::{fs, sync::Mutex, time::MissedTickBehavior};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+    deployment::DeploymentManager,
+    handlers::upload,
+    persistence::{Deployment, DeploymentFilter, Persistence, PersistenceError, State},
+};
+
+/// How often the artifacts of the project are swept
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The states of deployments which still need their artifacts, however old they are
+const IN_USE: [State; 6] = [
+    State::Queued,
+    State::Building,
+    State::Built,
+    State::PendingApproval,
+    State::Loading,
+    State::Running,
+];
+
+/// Removes what the retention of the project does not keep: the executables and assets of old
+/// deployments, abandoned uploads, and the build caches when everything takes up too much space
+#[derive(Clone)]
+pub struct ArtifactCollector {
+    persistence: Persistence,
+    deployment_manager: DeploymentManager,
+    /// The retention of the deployer, for when an admin did not choose one for the project
+    default_retention: Retention,
+    /// Held while sweeping, so that sweeps do not remove files from under each other
+    sweeping: Arc<Mutex<()>>,
+}
+
+impl ArtifactCollector {
+    pub fn new(
+        persistence: Persistence,
+        deployment_manager: DeploymentManager,
+        default_retention: Retention,
+    ) -> Self {
+        Self {
+            persistence,
+            deployment_manager,
+            default_retention,
+            sweeping: Default::default(),
+        }
+    }
+
+    /// The retention the artifacts of the project are swept by
+    pub async fn retention(&self) -> Result<Retention, PersistenceError> {
+        Ok(self
+            .persistence
+            .get_artifact_retention()
+            .await?
+            .unwrap_or(self.default_retention))
+    }
+
+    /// Remove the artifacts the retention does not keep, getting what was removed
+    pub async fn sweep(&self) -> anyhow::Result<Sweep> {
+        let _sweeping = self.sweeping.lock().await;
+        let retention = self.retention().await?;
+        let storage_manager = self.deployment_manager.storage_manager();
+        let mut sweep = Sweep::default();
+
+        if let Ok(uploads_path) = storage_manager.uploads_path() {
+            let (count, bytes) = upload::remove_expired(&uploads_path).await;
+            sweep.uploads = count;
+            sweep.bytes_reclaimed += bytes;
+        }
+
+        // The deployments which keep their artifacts, with how much space they take up
+        let mut kept: Vec<(Deployment, u64)> = Vec::new();
+        let services = self.persistence.get_all_services().await?;
+
+        for service in &services {
+            let deployments = self
+                .persistence
+                .get_deployments(&service.id, &Default::default(), 0, u32::MAX)
+                .await?;
+
+            for (index, deployment) in deployments.into_iter().enumerate() {
+                let size = deployment_size(&storage_manager, &deployment.id).await?;
+
+                // Like the deployments which failed to build, or were swept before
+                if size == 0 {
+                    continue;
+                }
+
+                if index >= retention.keep_deployments as usize
+                    && !IN_USE.contains(&deployment.state)
+                {
+                    remove_deployment(&storage_manager, &deployment.id).await?;
+                    sweep.bytes_reclaimed += size;
+                    sweep.deployments.push(deployment.id);
+                } else {
+                    kept.push((deployment, size));
+                }
+            }
+        }
+
+        let Some(max_bytes) = retention.max_bytes else {
+            return Ok(sweep);
+        };
+
+        let mut caches = Vec::new();
+        for service in &services {
+            let project_path = storage_manager.service_build_path(&service.name)?;
+            let target_dir = self.deployment_manager.build_target_dir(&service.name);
+            let mut size = dir_size(&project_path).await;
+            if let Some(target_dir) = &target_dir {
+                size += dir_size(target_dir).await;
+            }
+
+            caches.push((service, project_path, target_dir, size));
+        }
+
+        let mut size: u64 = kept.iter().map(|(_, size)| size).sum::<u64>()
+            + caches.iter().map(|(.., size)| size).sum::<u64>();
+
+        // The build caches go first, since the next build can make them again
+        for (service, project_path, target_dir, _) in &caches {
+            if size <= max_bytes {
+                return Ok(sweep);
+            }
+
+            // Removing the target directory from under a build would fail it
+            let building = DeploymentFilter {
+                state: Some(State::Building),
+                ..Default::default()
+            };
+            if !self
+                .persistence
+                .get_deployments(&service.id, &building, 0, 1)
+                .await?
+                .is_empty()
+            {
+                continue;
+            }
+
+            let summary = purge_crate(project_path, target_dir.as_deref()).await?;
+            if summary.bytes_freed > 0 {
+                size = size.saturating_sub(summary.bytes_freed);
+                sweep.bytes_reclaimed += summary.bytes_freed;
+                sweep.build_caches.push(service.name.clone());
+            }
+        }
+
+        // Then the artifacts of the oldest deployments, until the rest fits
+        kept.sort_by_key(|(deployment, _)| deployment.last_update);
+        for (deployment, deployment_size) in kept {
+            if size <= max_bytes {
+                break;
+            }
+
+            if IN_USE.contains(&deployment.state) {
+                continue;
+            }
+
+            remove_deployment(&storage_manager, &deployment.id).await?;
+            size = size.saturating_sub(deployment_size);
+            sweep.bytes_reclaimed += deployment_size;
+            sweep.deployments.push(deployment.id);
+        }
+
+        Ok(sweep)
+    }
+}
+
+/// Keep sweeping the artifacts of the project
+pub async fn sweep(collector: ArtifactCollector) {
+    let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+
+        match collector.sweep().await {
+            Ok(sweep) if sweep.bytes_reclaimed > 0 => info!(
+                deployments = sweep.deployments.len(),
+                uploads = sweep.uploads,
+                build_caches = sweep.build_caches.len(),
+                bytes_reclaimed = sweep.bytes_reclaimed,
+                "swept artifacts"
+            ),
+            Ok(_) => {}
+            Err(error) => error!(
+                error = error.as_ref() as &dyn std::error::Error,
+                "failed to sweep artifacts"
+            ),
+        }
+    }
+}
+
+/// How much space the executable, assets and run config of deployment `id` take up
+async fn deployment_size(storage_manager: &ArtifactsStorageManager, id: &Uuid) -> io::Result<u64> {
+    let mut size = dir_size(&storage_manager.deployment_assets_path(id)?).await;
+
+    for path in [
+        storage_manager.deployment_executable_path(id)?,
+        storage_manager.deployment_run_config_path(id)?,
+    ] {
+        match fs::metadata(path).await {
+            Ok(metadata) => size += metadata.len(),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(size)
+}
+
+/// Remove the executable, assets and run config of deployment `id`, as far as they are stored
+pub async fn remove_deployment(
+    storage_manager: &ArtifactsStorageManager,
+    id: &Uuid,
+) -> io::Result<()> {
+    for path in [
+        storage_manager.deployment_executable_path(id)?,
+        storage_manager.deployment_run_config_path(id)?,
+    ] {
+        match fs::remove_file(path).await {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+            _ => {}
+        }
+    }
+
+    match fs::remove_dir_all(storage_manager.deployment_assets_path(id)?).await {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
@@ -1,44 +1,67 @@
 mod error;
 
+use anyhow::Context;
 use axum::extract::ws::{self, WebSocket};
 use axum::extract::{Extension, Path, Query};
 use axum::handler::Handler;
 use axum::headers::HeaderMapExt;
 use axum::middleware::{self, from_extractor};
-use axum::routing::{get, post, Router};
+use axum::routing::{delete, get, post, Router};
 use axum::{extract::BodyStream, Json};
 use bytes::BufMut;
-use chrono::{TimeZone, Utc};
+use chrono::{DateTime, TimeZone, Utc};
 use fqdn::FQDN;
 use futures::StreamExt;
+use hyper::header::{HeaderName, CONTENT_TYPE};
 use hyper::Uri;
+use prometheus::TEXT_FORMAT;
+use regex::Regex;
 use serde::Deserialize;
+use serde_json::json;
 use shuttle_common::backends::auth::{
     AdminSecretLayer, AuthPublicKey, JwtAuthenticationLayer, ScopedLayer,
 };
 use shuttle_common::backends::headers::XShuttleAccountName;
 use shuttle_common::backends::metrics::{Metrics, TraceLayer};
-use shuttle_common::claims::{Claim, Scope};
-use shuttle_common::models::secret;
+use shuttle_common::backends::tracing::trace_id;
+use shuttle_common::claims::{Claim, ClaimLayer, InjectPropagationLayer, Scope};
+use shuttle_common::deployment::Environment;
+use shuttle_common::models::{
+    access_log, artifacts, env, persist, secret, service::Routing, storage, webhook,
+};
 use shuttle_common::project::ProjectName;
-use shuttle_common::storage_manager::StorageManager;
+use shuttle_common::storage_manager::{persist_path, StorageManager, PERSIST_QUOTA};
 use shuttle_common::{request_span, LogItem};
-use shuttle_service::builder::clean_crate;
-use tracing::{debug, error, field, instrument, trace, warn};
+use shuttle_proto::provisioner::{provisioner_client::ProvisionerClient, DatabaseRequest};
+use shuttle_service::builder::{purge_crate, BuildOptions};
+use tonic::transport::Endpoint;
+use tower::ServiceBuilder;
+use tracing::{debug, error, field, instrument, trace, warn, Span};
 use utoipa::{IntoParams, OpenApi};
 
 use utoipa_swagger_ui::SwaggerUi;
 use uuid::Uuid;
 
-use crate::deployment::{DeploymentManager, Queued};
-use crate::persistence::{Deployment, Log, Persistence, ResourceManager, SecretGetter, State};
+use crate::deployment::deploy_layer::{self, LogRecorder, LogType};
+use crate::deployment::{Built, DeploymentManager, Queued, RunConfig};
+use crate::gc::ArtifactCollector;
+use crate::monitoring;
+use crate::persistence::{
+    Deployment, DeploymentFilter, EnvVarGetter, Log, LogFilter, LogLevel, LogSource, Persistence,
+    ResourceManager, ResourceType, SecretGetter, SecretRecorder, State, Webhook,
+};
+use crate::proxy::service_fqdn;
+use crate::volumes::VolumeKeeper;
+use crate::webhooks;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 pub use {self::error::Error, self::error::Result, self::local::set_jwt_bearer};
 
 mod local;
 mod project;
+mod tunnel;
+pub(crate) mod upload;
 
 #[derive(OpenApi)]
 #[openapi(
@@ -46,15 +69,43 @@ mod project;
         get_services,
         get_service,
         create_service,
+        get_upload,
+        upload_chunk,
         stop_service,
+        rollback_service,
+        get_replicas,
+        scale_service,
         get_service_resources,
+        delete_service_resource,
         get_deployments,
         get_deployment,
         delete_deployment,
+        cancel_deployment,
+        approve_deployment,
+        get_approval,
+        set_approval,
         get_logs_subscribe,
         get_logs,
+        get_tunnel,
+        get_metrics,
+        get_cron_jobs,
+        get_cron_runs,
+        get_storage_usage,
+        get_persist_keys,
         get_secrets,
-        clean_project
+        set_secrets,
+        delete_secret,
+        get_env_vars,
+        set_env_vars,
+        delete_env_var,
+        get_webhooks,
+        create_webhook,
+        delete_webhook,
+        clean_project,
+        get_artifact_retention,
+        set_artifact_retention,
+        delete_artifact_retention,
+        sweep_artifacts
     ),
     components(schemas(
         shuttle_common::models::service::Summary,
@@ -64,16 +115,55 @@ mod project;
         shuttle_common::database::AwsRdsEngine,
         shuttle_common::database::SharedEngine,
         shuttle_common::models::service::Response,
+        shuttle_common::models::service::Replicas,
+        shuttle_common::models::service::Routing,
         shuttle_common::models::secret::Response,
+        shuttle_common::models::env::Response,
+        shuttle_common::models::webhook::Event,
+        shuttle_common::models::webhook::Request,
+        shuttle_common::models::webhook::Response,
+        shuttle_common::models::webhook::Created,
+        shuttle_common::models::webhook::Payload,
+        shuttle_common::models::upload::Response,
         shuttle_common::models::deployment::Response,
+        shuttle_common::models::deployment::Health,
+        shuttle_common::models::deployment::Transition,
+        shuttle_common::models::deployment::Approval,
+        shuttle_common::models::metrics::Response,
+        shuttle_common::models::metrics::Sample,
+        shuttle_common::models::cron::Response,
+        shuttle_common::models::cron::Run,
+        shuttle_common::models::cron::Status,
+        shuttle_common::models::storage::Usage,
+        shuttle_common::models::persist::Response,
+        shuttle_common::models::persist::Key,
+        shuttle_common::models::artifacts::Retention,
+        shuttle_common::models::artifacts::Sweep,
         shuttle_common::log::Item,
         shuttle_common::models::secret::Response,
         shuttle_common::log::Level,
+        shuttle_common::log::Source,
         shuttle_common::deployment::State
     ))
 )]
 pub struct ApiDoc;
 
+/// The prefix of the routes of the current version of the API. They are also served without it,
+/// for the clients which came before the API was versioned.
+pub const API_VERSION: &str = "/v1";
+
+/// The OpenAPI document of the current version of the API, with its routes under their prefix
+pub fn openapi() -> utoipa::openapi::OpenApi {
+    let mut openapi = ApiDoc::openapi();
+    openapi.info.version = API_VERSION.trim_start_matches('/').to_string();
+    openapi.paths.paths = std::mem::take(&mut openapi.paths.paths)
+        .into_iter()
+        .map(|(path, item)| (format!("{API_VERSION}{path}"), item))
+        .collect();
+
+    openapi
+}
+
 #[derive(Debug, Clone, Copy, Deserialize, IntoParams)]
 pub struct PaginationDetails {
     /// Page to fetch, starting from 0.
@@ -82,9 +172,161 @@ pub struct PaginationDetails {
     pub limit: Option<u32>,
 }
 
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+pub struct LogsQuery {
+    /// Only get the logs after this time, like the time of the last log a client saw before it
+    /// reconnected.
+    pub since: Option<DateTime<Utc>>,
+    /// Only get the logs up to this time.
+    pub until: Option<DateTime<Utc>>,
+    /// Only get this many of the most recent logs.
+    pub tail: Option<usize>,
+    /// Only get the logs at this level or a more severe one.
+    pub level: Option<shuttle_common::log::Level>,
+    /// Only get the logs of this tracing target, or of the modules in it.
+    pub target: Option<String>,
+    /// Only get the logs from this source, like the lines the service printed to stdout.
+    pub source: Option<shuttle_common::log::Source>,
+    /// Only get the logs with a message matching this regular expression.
+    #[serde(default, deserialize_with = "deserialize_regex")]
+    #[param(value_type = Option<String>)]
+    pub grep: Option<Regex>,
+}
+
+impl LogsQuery {
+    /// The part of this query the log store can filter on
+    fn filter(&self) -> LogFilter {
+        LogFilter {
+            since: self.since,
+            until: self.until,
+            level: self.level.clone().map(Into::into),
+            target: self.target.clone(),
+            source: self.source.map(Into::into),
+        }
+    }
+
+    /// Only keep the logs this query asks for, out of those the store got for its filter
+    fn apply(&self, logs: Vec<Log>) -> Vec<LogItem> {
+        let mut items: Vec<LogItem> = logs
+            .into_iter()
+            .filter_map(Into::into)
+            .filter(|item| self.matches(item))
+            .collect();
+
+        if let Some(tail) = self.tail {
+            items.drain(..items.len().saturating_sub(tail));
+        }
+
+        items
+    }
+
+    /// Whether the log `item` passes the filters of this query, leaving out `since` and `tail`
+    /// which depend on the logs before it
+    fn matches(&self, item: &LogItem) -> bool {
+        if self.until.map_or(false, |until| item.timestamp > until) {
+            return false;
+        }
+
+        if self
+            .level
+            .as_ref()
+            .map_or(false, |level| item.level < *level)
+        {
+            return false;
+        }
+
+        if let Some(target) = &self.target {
+            let in_target = item
+                .target
+                .strip_prefix(target.as_str())
+                .map_or(false, |rest| rest.is_empty() || rest.starts_with("::"));
+
+            if !in_target {
+                return false;
+            }
+        }
+
+        if self.source.map_or(false, |source| item.source != source) {
+            return false;
+        }
+
+        if let Some(grep) = &self.grep {
+            return grep.is_match(&log_message(item));
+        }
+
+        true
+    }
+}
+
+fn deserialize_regex<'de, D>(deserializer: D) -> std::result::Result<Option<Regex>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let Some(pattern) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+
+    Regex::new(&pattern)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// The message of the log `item`, or all its fields when it has no message
+fn log_message(item: &LogItem) -> String {
+    match serde_json::from_slice(&item.fields) {
+        Ok(serde_json::Value::Object(map)) => match map.get("message") {
+            Some(serde_json::Value::String(message)) => message.clone(),
+            _ => serde_json::Value::Object(map).to_string(),
+        },
+        Ok(serde_json::Value::String(message)) => message,
+        Ok(other) => other.to_string(),
+        Err(_) => String::from_utf8_lossy(&item.fields).into_owned(),
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+pub struct DeploymentsQuery {
+    /// Only get the deployments in this state.
+    pub state: Option<shuttle_common::deployment::State>,
+    /// Only get the deployments last updated after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only get the deployments last updated up to this time.
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl From<DeploymentsQuery> for DeploymentFilter {
+    fn from(query: DeploymentsQuery) -> Self {
+        Self {
+            state: query.state.map(Into::into),
+            since: query.since,
+            until: query.until,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+pub struct ServiceQuery {
+    /// The service to use, like the one of an environment of the project. Defaults to the one
+    /// named after the project.
+    pub service: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, IntoParams)]
+pub struct UploadQuery {
+    /// Where the chunk starts in the archive.
+    pub offset: u64,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, IntoParams)]
+pub struct RollbackQuery {
+    /// The deployment to run again. Defaults to the one which was stopped most recently.
+    pub to: Option<Uuid>,
+}
+
 #[derive(Clone)]
 pub struct RouterBuilder {
     router: Router,
+    persistence: Persistence,
     project_name: ProjectName,
     auth_uri: Uri,
 }
@@ -93,7 +335,10 @@ impl RouterBuilder {
     pub fn new(
         persistence: Persistence,
         deployment_manager: DeploymentManager,
+        artifact_collector: ArtifactCollector,
+        volume_keeper: VolumeKeeper,
         proxy_fqdn: FQDN,
+        provisioner_address: Endpoint,
         project_name: ProjectName,
         auth_uri: Uri,
     ) -> Self {
@@ -104,6 +349,7 @@ impl RouterBuilder {
                 "/projects/:project_name/api-docs/openapi.json",
                 ApiDoc::openapi(),
             ))
+            .route("/projects/:project_name/docs", get(get_openapi))
             .route(
                 "/projects/:project_name/services",
                 get(get_services.layer(ScopedLayer::new(vec![Scope::Service]))),
@@ -114,10 +360,30 @@ impl RouterBuilder {
                     .post(create_service.layer(ScopedLayer::new(vec![Scope::ServiceCreate])))
                     .delete(stop_service.layer(ScopedLayer::new(vec![Scope::ServiceCreate]))),
             )
+            .route(
+                "/projects/:project_name/uploads/:hash",
+                get(get_upload.layer(ScopedLayer::new(vec![Scope::ServiceCreate])))
+                    .patch(upload_chunk.layer(ScopedLayer::new(vec![Scope::ServiceCreate]))),
+            )
+            .route(
+                "/projects/:project_name/services/:service_name/rollback",
+                post(rollback_service.layer(ScopedLayer::new(vec![Scope::DeploymentPush]))),
+            )
+            .route(
+                "/projects/:project_name/services/:service_name/replicas",
+                get(get_replicas.layer(ScopedLayer::new(vec![Scope::Service])))
+                    .put(scale_service.layer(ScopedLayer::new(vec![Scope::ServiceCreate]))),
+            )
             .route(
                 "/projects/:project_name/services/:service_name/resources",
                 get(get_service_resources).layer(ScopedLayer::new(vec![Scope::Resources])),
             )
+            .route(
+                "/projects/:project_name/services/:service_name/resources/:resource_type",
+                delete(
+                    delete_service_resource.layer(ScopedLayer::new(vec![Scope::ResourcesWrite])),
+                ),
+            )
             .route(
                 "/projects/:project_name/deployments",
                 get(get_deployments).layer(ScopedLayer::new(vec![Scope::Service])),
@@ -127,6 +393,19 @@ impl RouterBuilder {
                 get(get_deployment.layer(ScopedLayer::new(vec![Scope::Deployment])))
                     .delete(delete_deployment.layer(ScopedLayer::new(vec![Scope::DeploymentPush]))),
             )
+            .route(
+                "/projects/:project_name/deployments/:deployment_id/cancel",
+                post(cancel_deployment.layer(ScopedLayer::new(vec![Scope::DeploymentPush]))),
+            )
+            .route(
+                "/projects/:project_name/deployments/:deployment_id/approve",
+                post(approve_deployment.layer(ScopedLayer::new(vec![Scope::DeploymentApprove]))),
+            )
+            .route(
+                "/projects/:project_name/approval",
+                get(get_approval.layer(ScopedLayer::new(vec![Scope::Deployment])))
+                    .put(set_approval.layer(ScopedLayer::new(vec![Scope::DeploymentApprove]))),
+            )
             .route(
                 "/projects/:project_name/ws/deployments/:deployment_id/logs",
                 get(get_logs_subscribe.layer(ScopedLayer::new(vec![Scope::Logs]))),
@@ -135,23 +414,85 @@ impl RouterBuilder {
                 "/projects/:project_name/deployments/:deployment_id/logs",
                 get(get_logs.layer(ScopedLayer::new(vec![Scope::Logs]))),
             )
+            .route(
+                "/projects/:project_name/ws/services/:service_name/tunnel",
+                get(get_tunnel.layer(ScopedLayer::new(vec![Scope::Tunnel]))),
+            )
+            .route(
+                "/projects/:project_name/services/:service_name/metrics",
+                get(get_metrics.layer(ScopedLayer::new(vec![Scope::Service]))),
+            )
+            .route(
+                "/projects/:project_name/services/:service_name/cron",
+                get(get_cron_jobs.layer(ScopedLayer::new(vec![Scope::Service]))),
+            )
+            .route(
+                "/projects/:project_name/services/:service_name/cron/runs",
+                get(get_cron_runs.layer(ScopedLayer::new(vec![Scope::Service]))),
+            )
+            .route(
+                "/projects/:project_name/services/:service_name/storage",
+                get(get_storage_usage.layer(ScopedLayer::new(vec![Scope::Service]))),
+            )
+            .route(
+                "/projects/:project_name/services/:service_name/persist",
+                get(get_persist_keys.layer(ScopedLayer::new(vec![Scope::Service]))),
+            )
             .route(
                 "/projects/:project_name/secrets/:service_name",
-                get(get_secrets.layer(ScopedLayer::new(vec![Scope::Secret]))),
+                get(get_secrets.layer(ScopedLayer::new(vec![Scope::Secret])))
+                    .put(set_secrets.layer(ScopedLayer::new(vec![Scope::SecretWrite]))),
+            )
+            .route(
+                "/projects/:project_name/secrets/:service_name/:key",
+                delete(delete_secret.layer(ScopedLayer::new(vec![Scope::SecretWrite]))),
+            )
+            .route(
+                "/projects/:project_name/env/:service_name",
+                get(get_env_vars.layer(ScopedLayer::new(vec![Scope::Env])))
+                    .put(set_env_vars.layer(ScopedLayer::new(vec![Scope::EnvWrite]))),
+            )
+            .route(
+                "/projects/:project_name/env/:service_name/:key",
+                delete(delete_env_var.layer(ScopedLayer::new(vec![Scope::EnvWrite]))),
+            )
+            .route(
+                "/projects/:project_name/webhooks",
+                get(get_webhooks.layer(ScopedLayer::new(vec![Scope::Webhook])))
+                    .post(create_webhook.layer(ScopedLayer::new(vec![Scope::WebhookWrite]))),
+            )
+            .route(
+                "/projects/:project_name/webhooks/:webhook_id",
+                delete(delete_webhook.layer(ScopedLayer::new(vec![Scope::WebhookWrite]))),
             )
             .route(
                 "/projects/:project_name/clean",
                 post(clean_project.layer(ScopedLayer::new(vec![Scope::DeploymentPush]))),
             )
-            .layer(Extension(persistence))
+            .route(
+                "/projects/:project_name/artifacts/retention",
+                get(get_artifact_retention)
+                    .put(set_artifact_retention)
+                    .delete(delete_artifact_retention)
+                    .layer(ScopedLayer::new(vec![Scope::Admin])),
+            )
+            .route(
+                "/projects/:project_name/artifacts/sweep",
+                post(sweep_artifacts.layer(ScopedLayer::new(vec![Scope::Admin]))),
+            )
+            .layer(Extension(persistence.clone()))
             .layer(Extension(deployment_manager))
+            .layer(Extension(artifact_collector))
+            .layer(Extension(volume_keeper))
             .layer(Extension(proxy_fqdn))
+            .layer(Extension(provisioner_address))
             .layer(JwtAuthenticationLayer::new(AuthPublicKey::new(
                 auth_uri.clone(),
             )));
 
         Self {
             router,
+            persistence,
             project_name,
             auth_uri,
         }
@@ -175,8 +516,18 @@ impl RouterBuilder {
     }
 
     pub fn into_router(self) -> Router {
-        self.router
+        let api = self
+            .router
             .route("/projects/:project_name/status", get(get_status))
+            // Posted by the gateway, which is not behind the auth of the project either
+            .route(
+                "/projects/:project_name/access-logs",
+                post(post_access_logs).layer(Extension(self.persistence.clone())),
+            );
+
+        Router::new()
+            .nest(API_VERSION, api.clone())
+            .merge(api)
             .route_layer(from_extractor::<Metrics>())
             .layer(
                 TraceLayer::new(|request| {
@@ -198,6 +549,11 @@ impl RouterBuilder {
             )
             .route_layer(from_extractor::<project::ProjectNameGuard>())
             .layer(Extension(self.project_name))
+            // Scraped by the operators of the deployer, which is not behind the auth of its project
+            .route(
+                "/metrics",
+                get(get_prometheus_metrics).layer(Extension(self.persistence)),
+            )
     }
 }
 
@@ -253,7 +609,7 @@ pub async fn get_service(
             .map(Into::into);
 
         let response = shuttle_common::models::service::Summary {
-            uri: format!("https://{proxy_fqdn}"),
+            uri: format!("https://{}", service_fqdn(&proxy_fqdn, &service.name)),
             name: service.name,
             deployment,
         };
@@ -296,6 +652,97 @@ pub async fn get_service_resources(
     }
 }
 
+#[instrument(skip_all, fields(%project_name, %service_name, %resource_type))]
+#[utoipa::path(
+    delete,
+    path = "/projects/{project_name}/services/{service_name}/resources/{resource_type}",
+    responses(
+        (status = 200, description = "Deletes a resource of a specific service, deprovisioning it if it is a database.", body = shuttle_common::resource::Response),
+        (status = 500, description = "Database or provisioner error.", body = String),
+        (status = 404, description = "Record could not be found.", body = String),
+        (status = 409, description = "The service is running.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the service."),
+        ("service_name" = String, Path, description = "Name of the service."),
+        ("resource_type" = String, Path, description = "Type of the resource, like `database::shared::postgres`.")
+    )
+)]
+pub async fn delete_service_resource(
+    Extension(persistence): Extension<Persistence>,
+    Extension(provisioner_address): Extension<Endpoint>,
+    Extension(claim): Extension<Claim>,
+    Path((project_name, service_name, resource_type)): Path<(String, String, String)>,
+) -> Result<Json<shuttle_common::resource::Response>> {
+    let Some(service) = persistence.get_service_by_name(&service_name).await? else {
+        return Err(Error::NotFound("service not found".to_string()));
+    };
+
+    let not_found = || Error::NotFound(format!("the service has no {resource_type} resource"));
+    let r#type: ResourceType = resource_type.parse().map_err(|_| not_found())?;
+    if !persistence
+        .get_resources(&service.id)
+        .await?
+        .iter()
+        .any(|resource| resource.r#type == r#type)
+    {
+        return Err(not_found());
+    }
+
+    // A running deployment would lose its database from under it
+    if persistence
+        .get_active_deployment(&service.id)
+        .await?
+        .is_some()
+    {
+        return Err(Error::Conflict(
+            "stop the service before deleting its resources".to_string(),
+        ));
+    }
+
+    // Secrets and static folders live with the deployments, so only databases have to be deprovisioned
+    if let ResourceType::Database(db_type) = r#type {
+        delete_database(provisioner_address, claim, &service_name, db_type.into()).await?;
+    }
+
+    let resource = persistence
+        .delete_resource(&service.id, r#type)
+        .await?
+        .ok_or_else(not_found)?;
+
+    Ok(Json(resource.into()))
+}
+
+/// Ask the provisioner to delete the database of type `db_type` it made for `service_name`
+async fn delete_database(
+    provisioner_address: Endpoint,
+    claim: Claim,
+    service_name: &str,
+    db_type: shuttle_common::database::Type,
+) -> anyhow::Result<()> {
+    let channel = provisioner_address
+        .connect()
+        .await
+        .context("failed to connect to provisioner")?;
+    let channel = ServiceBuilder::new()
+        .layer(ClaimLayer)
+        .layer(InjectPropagationLayer)
+        .service(channel);
+
+    let mut request = tonic::Request::new(DatabaseRequest {
+        project_name: service_name.to_string(),
+        db_type: Some(db_type.into()),
+    });
+    request.extensions_mut().insert(claim);
+
+    ProvisionerClient::new(channel)
+        .delete_database(request)
+        .await
+        .context("failed to delete the database")?;
+
+    Ok(())
+}
+
 #[instrument(skip_all, fields(%project_name, %service_name))]
 #[utoipa::path(
     post,
@@ -328,24 +775,69 @@ pub async fn create_service(
         last_update: Utc::now(),
         address: None,
         is_next: false,
+        git_commit_id: params.get("git-commit-id").cloned(),
+        git_commit_msg: params.get("git-commit-msg").cloned(),
+        git_branch: params.get("git-branch").cloned(),
+        git_dirty: params.get("git-dirty").map(|dirty| dirty == "true"),
+        source_hash: params.get("source-hash").cloned(),
+        oom_kills: 0,
+        trace_id: trace_id(&Span::current()),
     };
 
-    let mut data = Vec::new();
-    while let Some(buf) = stream.next().await {
-        let buf = buf?;
-        debug!("Received {} bytes", buf.len());
-        data.put(buf);
-    }
+    let data = match params.get("upload") {
+        // The archive was uploaded beforehand, in chunks which can be resumed
+        Some(hash) => {
+            let path = upload::path(&deployment_manager.storage_manager(), hash)?;
+            upload::take(&path, hash).await?
+        }
+        None => {
+            let mut data = Vec::new();
+            while let Some(buf) = stream.next().await {
+                let buf = buf?;
+                debug!("Received {} bytes", buf.len());
+                data.put(buf);
+            }
+
+            data
+        }
+    };
     debug!("Received a total of {} bytes", data.len());
 
     persistence.insert_deployment(deployment.clone()).await?;
 
+    // A prebuilt artifact cannot be tested, since that needs building the service
+    let prebuilt = params.contains_key("artifact");
+    let requires_approval = persistence.get_approval().await?.required;
+
     let queued = Queued {
         id,
         service_name: service.name,
         service_id: service.id,
         data,
-        will_run_tests: !params.contains_key("no-test"),
+        will_run_tests: !params.contains_key("no-test") && !prebuilt,
+        prebuilt,
+        requires_approval,
+        build_options: BuildOptions {
+            features: params
+                .get("features")
+                .map(|features| {
+                    features
+                        .split(',')
+                        .filter(|feature| !feature.is_empty())
+                        .map(ToString::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            all_features: params.contains_key("all-features"),
+            no_default_features: params.contains_key("no-default-features"),
+            use_cache: true,
+            package: params.get("package").cloned(),
+            locked: params.contains_key("locked"),
+            offline: params.contains_key("offline"),
+            // A deployment runs a single service
+            single_service: true,
+            ..Default::default()
+        },
         tracing_context: Default::default(),
         claim: Some(claim),
     };
@@ -355,6 +847,65 @@ pub async fn create_service(
     Ok(Json(deployment.into()))
 }
 
+#[instrument(skip_all, fields(%project_name, %hash))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/uploads/{hash}",
+    responses(
+        (status = 200, description = "Gets how much of an archive was uploaded, to resume uploading it.", body = shuttle_common::models::upload::Response),
+        (status = 400, description = "The hash is not a SHA-256 hash.", body = String),
+        (status = 500, description = "Error reading the upload.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project to deploy the archive to."),
+        ("hash" = String, Path, description = "The SHA-256 hash of the archive.")
+    )
+)]
+pub async fn get_upload(
+    Extension(deployment_manager): Extension<DeploymentManager>,
+    Path((project_name, hash)): Path<(String, String)>,
+) -> Result<Json<shuttle_common::models::upload::Response>> {
+    let storage_manager = deployment_manager.storage_manager();
+
+    // Uploads are started or resumed here, which is a good time to give up on old ones
+    if let Ok(uploads_path) = storage_manager.uploads_path() {
+        upload::remove_expired(&uploads_path).await;
+    }
+
+    let path = upload::path(&storage_manager, &hash)?;
+    let received = upload::received(&path).await?;
+
+    Ok(Json(shuttle_common::models::upload::Response { received }))
+}
+
+#[instrument(skip_all, fields(%project_name, %hash, %offset))]
+#[utoipa::path(
+    patch,
+    path = "/projects/{project_name}/uploads/{hash}",
+    responses(
+        (status = 200, description = "Adds a chunk to the upload of an archive, which is deployed with the `upload` parameter once it is complete.", body = shuttle_common::models::upload::Response),
+        (status = 400, description = "The hash is not a SHA-256 hash.", body = String),
+        (status = 409, description = "The chunk does not start where the upload ends.", body = String),
+        (status = 500, description = "Error writing the upload.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project to deploy the archive to."),
+        ("hash" = String, Path, description = "The SHA-256 hash of the archive."),
+        UploadQuery
+    )
+)]
+pub async fn upload_chunk(
+    Extension(deployment_manager): Extension<DeploymentManager>,
+    Path((project_name, hash)): Path<(String, String)>,
+    Query(UploadQuery { offset }): Query<UploadQuery>,
+    stream: BodyStream,
+) -> Result<Json<shuttle_common::models::upload::Response>> {
+    let path = upload::path(&deployment_manager.storage_manager(), &hash)?;
+    let received = upload::append(&path, offset, stream).await?;
+
+    Ok(Json(shuttle_common::models::upload::Response { received }))
+}
+
 #[instrument(skip_all, fields(%project_name, %service_name))]
 #[utoipa::path(
     delete,
@@ -385,9 +936,9 @@ pub async fn stop_service(
         }
 
         let response = shuttle_common::models::service::Summary {
+            uri: format!("https://{}", service_fqdn(&proxy_fqdn, &service.name)),
             name: service.name,
             deployment: running_deployment.map(Into::into),
-            uri: format!("https://{proxy_fqdn}"),
         };
 
         Ok(Json(response))
@@ -396,95 +947,429 @@ pub async fn stop_service(
     }
 }
 
-#[instrument(skip(persistence))]
+#[instrument(skip_all, fields(%project_name, %service_name))]
 #[utoipa::path(
-    get,
-    path = "/projects/{project_name}/deployments",
+    post,
+    path = "/projects/{project_name}/services/{service_name}/rollback",
     responses(
-        (status = 200, description = "Gets deployments information associated to a specific project.", body = shuttle_common::models::deployment::Response),
+        (status = 200, description = "Runs a previous deployment of a service again, without building it.", body = shuttle_common::models::deployment::Response),
         (status = 500, description = "Database error.", body = String),
         (status = 404, description = "Record could not be found.", body = String),
+        (status = 409, description = "The deployment is already running.", body = String),
     ),
     params(
-        ("project_name" = String, Path, description = "Name of the project that owns the deployments."),
-        PaginationDetails
+        ("project_name" = String, Path, description = "Name of the project that owns the service."),
+        ("service_name" = String, Path, description = "Name of the service."),
+        RollbackQuery
     )
 )]
-pub async fn get_deployments(
+pub async fn rollback_service(
     Extension(persistence): Extension<Persistence>,
-    Path(project_name): Path<String>,
-    Query(PaginationDetails { page, limit }): Query<PaginationDetails>,
-) -> Result<Json<Vec<shuttle_common::models::deployment::Response>>> {
-    if let Some(service) = persistence.get_service_by_name(&project_name).await? {
-        let limit = limit.unwrap_or(u32::MAX);
-        let page = page.unwrap_or(0);
-        let deployments = persistence
-            .get_deployments(&service.id, page * limit, limit)
+    Extension(deployment_manager): Extension<DeploymentManager>,
+    Extension(claim): Extension<Claim>,
+    Path((project_name, service_name)): Path<(String, String)>,
+    Query(RollbackQuery { to }): Query<RollbackQuery>,
+) -> Result<Json<shuttle_common::models::deployment::Response>> {
+    let Some(service) = persistence.get_service_by_name(&service_name).await? else {
+        return Err(Error::NotFound("service not found".to_string()));
+    };
+
+    // Only deployments whose executable is still around can be run without building them again
+    let storage_manager = deployment_manager.storage_manager();
+    let is_built = |deployment: &Deployment| {
+        storage_manager
+            .deployment_executable_path(&deployment.id)
+            .map_or(false, |path| path.exists())
+    };
+
+    let mut deployment = match to {
+        Some(id) => {
+            let deployment = persistence
+                .get_deployment(&id)
+                .await?
+                .filter(|deployment| deployment.service_id == service.id)
+                .ok_or_else(|| Error::NotFound("deployment not found".to_string()))?;
+
+            if deployment.state == State::Running {
+                return Err(Error::Conflict(format!(
+                    "deployment {id} is already running"
+                )));
+            }
+
+            if !is_built(&deployment) {
+                return Err(Error::NotFound(format!(
+                    "the build of deployment {id} is not available anymore"
+                )));
+            }
+
+            deployment
+        }
+        None => persistence
+            .get_previous_deployments(&service.id)
             .await?
             .into_iter()
-            .map(Into::into)
-            .collect();
+            .find(is_built)
+            .ok_or_else(|| Error::NotFound("no previous deployment to roll back to".to_string()))?,
+    };
 
-        Ok(Json(deployments))
-    } else {
-        Err(Error::NotFound("service not found".to_string()))
-    }
+    let built = Built {
+        id: deployment.id,
+        service_name: service.name,
+        service_id: service.id,
+        tracing_context: Default::default(),
+        is_next: deployment.is_next,
+        claim: Some(claim),
+    };
+
+    // The running deployment is stopped once this one is loaded
+    deployment_manager.run_push(built).await;
+    deployment.state = State::Built;
+
+    Ok(Json(deployment.into()))
 }
 
-#[instrument(skip_all, fields(%project_name, %deployment_id))]
-#[instrument(skip(persistence))]
+#[instrument(skip_all, fields(%project_name, %service_name))]
 #[utoipa::path(
     get,
-    path = "/projects/{project_name}/deployments/{deployment_id}",
+    path = "/projects/{project_name}/services/{service_name}/replicas",
     responses(
-        (status = 200, description = "Gets a specific deployment information.", body = shuttle_common::models::deployment::Response),
-        (status = 500, description = "Database or streaming error.", body = String),
+        (status = 200, description = "Gets how many runtimes the running deployment of a specific service is served by.", body = shuttle_common::models::service::Replicas),
+        (status = 500, description = "Database error.", body = String),
         (status = 404, description = "Record could not be found.", body = String),
     ),
     params(
-        ("project_name" = String, Path, description = "Name of the project that owns the deployment."),
-        ("deployment_id" = String, Path, description = "The deployment id in uuid format.")
+        ("project_name" = String, Path, description = "Name of the project that owns the service."),
+        ("service_name" = String, Path, description = "Name of the service.")
     )
 )]
-pub async fn get_deployment(
+pub async fn get_replicas(
     Extension(persistence): Extension<Persistence>,
-    Path((project_name, deployment_id)): Path<(String, Uuid)>,
-) -> Result<Json<shuttle_common::models::deployment::Response>> {
-    if let Some(deployment) = persistence.get_deployment(&deployment_id).await? {
-        Ok(Json(deployment.into()))
-    } else {
-        Err(Error::NotFound("deployment not found".to_string()))
-    }
+    Extension(deployment_manager): Extension<DeploymentManager>,
+    Path((project_name, service_name)): Path<(String, String)>,
+) -> Result<Json<shuttle_common::models::service::Replicas>> {
+    let Some(service) = persistence.get_service_by_name(&service_name).await? else {
+        return Err(Error::NotFound("service not found".to_string()));
+    };
+
+    Ok(Json(shuttle_common::models::service::Replicas {
+        replicas: deployment_manager.replicas(&service.id),
+        routing: Some(deployment_manager.routing(&service.id)),
+    }))
 }
 
-#[instrument(skip_all, fields(%project_name, %deployment_id))]
+#[instrument(skip_all, fields(%project_name, %service_name))]
 #[utoipa::path(
-    delete,
-    path = "/projects/{project_name}/deployments/{deployment_id}",
+    put,
+    path = "/projects/{project_name}/services/{service_name}/replicas",
+    request_body = shuttle_common::models::service::Replicas,
     responses(
-        (status = 200, description = "Deletes a specific deployment.", body = shuttle_common::models::deployment::Response),
-        (status = 500, description = "Database or streaming error.", body = String),
+        (status = 200, description = "Scales a specific service to run in this many runtimes, which take turns serving its requests. The replicas of its running deployment are started or stopped right away.", body = shuttle_common::models::service::Replicas),
+        (status = 500, description = "Database error.", body = String),
         (status = 404, description = "Record could not be found.", body = String),
+        (status = 400, description = "The service cannot be scaled to this many replicas.", body = String),
     ),
     params(
-        ("project_name" = String, Path, description = "Name of the project that owns the deployment."),
-        ("deployment_id" = String, Path, description = "The deployment id in uuid format.")
+        ("project_name" = String, Path, description = "Name of the project that owns the service."),
+        ("service_name" = String, Path, description = "Name of the service.")
     )
 )]
-pub async fn delete_deployment(
-    Extension(deployment_manager): Extension<DeploymentManager>,
+pub async fn scale_service(
     Extension(persistence): Extension<Persistence>,
-    Path((project_name, deployment_id)): Path<(String, Uuid)>,
-) -> Result<Json<shuttle_common::models::deployment::Response>> {
-    if let Some(deployment) = persistence.get_deployment(&deployment_id).await? {
-        deployment_manager.kill(deployment.id).await;
+    Extension(deployment_manager): Extension<DeploymentManager>,
+    Path((project_name, service_name)): Path<(String, String)>,
+    Json(replicas): Json<shuttle_common::models::service::Replicas>,
+) -> Result<Json<shuttle_common::models::service::Replicas>> {
+    let max_replicas = deployment_manager.max_replicas();
+    if !(1..=max_replicas).contains(&replicas.replicas) {
+        return Err(Error::BadRequest(format!(
+            "a service can run in 1 to {max_replicas} replicas"
+        )));
+    }
 
-        Ok(Json(deployment.into()))
-    } else {
+    match &replicas.routing {
+        Some(Routing::Sticky { cookie })
+            if cookie.is_empty()
+                || !cookie
+                    .bytes()
+                    .all(|byte| byte.is_ascii_alphanumeric() || b"-_.".contains(&byte)) =>
+        {
+            return Err(Error::BadRequest(format!(
+                "'{cookie}' cannot be the name of a cookie"
+            )));
+        }
+        Some(Routing::Hash { header }) if HeaderName::try_from(header.as_str()).is_err() => {
+            return Err(Error::BadRequest(format!(
+                "'{header}' cannot be the name of a header"
+            )));
+        }
+        _ => {}
+    }
+
+    let Some(service) = persistence.get_service_by_name(&service_name).await? else {
+        return Err(Error::NotFound("service not found".to_string()));
+    };
+
+    let routing = replicas
+        .routing
+        .unwrap_or_else(|| deployment_manager.routing(&service.id));
+
+    persistence
+        .set_replicas(&service.id, replicas.replicas, &routing)
+        .await?;
+    deployment_manager.scale(service.id, replicas.replicas);
+    deployment_manager.route(service.id, routing.clone());
+
+    Ok(Json(shuttle_common::models::service::Replicas {
+        replicas: replicas.replicas,
+        routing: Some(routing),
+    }))
+}
+
+#[instrument(skip(persistence))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/deployments",
+    responses(
+        (status = 200, description = "Gets deployments information associated to a specific project.", body = shuttle_common::models::deployment::Response),
+        (status = 500, description = "Database error.", body = String),
+        (status = 404, description = "Record could not be found.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the deployments."),
+        PaginationDetails,
+        ServiceQuery,
+        DeploymentsQuery
+    )
+)]
+pub async fn get_deployments(
+    Extension(persistence): Extension<Persistence>,
+    Path(project_name): Path<String>,
+    Query(PaginationDetails { page, limit }): Query<PaginationDetails>,
+    Query(ServiceQuery { service }): Query<ServiceQuery>,
+    Query(query): Query<DeploymentsQuery>,
+) -> Result<Json<Vec<shuttle_common::models::deployment::Response>>> {
+    let service_name = service.unwrap_or(project_name);
+
+    if let Some(service) = persistence.get_service_by_name(&service_name).await? {
+        let limit = limit.unwrap_or(u32::MAX);
+        let page = page.unwrap_or(0);
+        let deployments = persistence
+            .get_deployments(
+                &service.id,
+                &query.into(),
+                page.saturating_mul(limit),
+                limit,
+            )
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        Ok(Json(deployments))
+    } else {
+        Err(Error::NotFound("service not found".to_string()))
+    }
+}
+
+#[instrument(skip_all, fields(%project_name, %deployment_id))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/deployments/{deployment_id}",
+    responses(
+        (status = 200, description = "Gets a specific deployment information.", body = shuttle_common::models::deployment::Response),
+        (status = 500, description = "Database or streaming error.", body = String),
+        (status = 404, description = "Record could not be found.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the deployment."),
+        ("deployment_id" = String, Path, description = "The deployment id in uuid format.")
+    )
+)]
+pub async fn get_deployment(
+    Extension(persistence): Extension<Persistence>,
+    Extension(deployment_manager): Extension<DeploymentManager>,
+    Path((project_name, deployment_id)): Path<(String, Uuid)>,
+) -> Result<Json<shuttle_common::models::deployment::Response>> {
+    if let Some(deployment) = persistence.get_deployment(&deployment_id).await? {
+        let mut response: shuttle_common::models::deployment::Response = deployment.into();
+        response.health = deployment_manager.health(&deployment_id);
+        response.transitions = persistence
+            .get_state_transitions(&deployment_id)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        Ok(Json(response))
+    } else {
+        Err(Error::NotFound("deployment not found".to_string()))
+    }
+}
+
+#[instrument(skip_all, fields(%project_name, %deployment_id))]
+#[utoipa::path(
+    delete,
+    path = "/projects/{project_name}/deployments/{deployment_id}",
+    responses(
+        (status = 200, description = "Deletes a specific deployment.", body = shuttle_common::models::deployment::Response),
+        (status = 500, description = "Database or streaming error.", body = String),
+        (status = 404, description = "Record could not be found.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the deployment."),
+        ("deployment_id" = String, Path, description = "The deployment id in uuid format.")
+    )
+)]
+pub async fn delete_deployment(
+    Extension(deployment_manager): Extension<DeploymentManager>,
+    Extension(persistence): Extension<Persistence>,
+    Path((project_name, deployment_id)): Path<(String, Uuid)>,
+) -> Result<Json<shuttle_common::models::deployment::Response>> {
+    if let Some(deployment) = persistence.get_deployment(&deployment_id).await? {
+        deployment_manager.kill(deployment.id).await;
+
+        Ok(Json(deployment.into()))
+    } else {
         Err(Error::NotFound("deployment not found".to_string()))
     }
 }
 
+#[instrument(skip_all, fields(%project_name, %deployment_id))]
+#[utoipa::path(
+    post,
+    path = "/projects/{project_name}/deployments/{deployment_id}/cancel",
+    responses(
+        (status = 200, description = "Cancels a deployment which is queued, building or pending approval.", body = shuttle_common::models::deployment::Response),
+        (status = 500, description = "Database error.", body = String),
+        (status = 404, description = "Record could not be found.", body = String),
+        (status = 409, description = "The deployment is not queued, building or pending approval anymore.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the deployment."),
+        ("deployment_id" = String, Path, description = "The deployment id in uuid format.")
+    )
+)]
+pub async fn cancel_deployment(
+    Extension(deployment_manager): Extension<DeploymentManager>,
+    Extension(persistence): Extension<Persistence>,
+    Path((project_name, deployment_id)): Path<(String, Uuid)>,
+) -> Result<Json<shuttle_common::models::deployment::Response>> {
+    let Some(deployment) = persistence.get_deployment(&deployment_id).await? else {
+        return Err(Error::NotFound("deployment not found".to_string()));
+    };
+
+    // A deployment which is pending approval is rejected
+    if deployment.state == State::PendingApproval {
+        deployment_manager.reject(&deployment.id).await;
+
+        return Ok(Json(deployment.into()));
+    }
+
+    if !matches!(deployment.state, State::Queued | State::Building)
+        || !deployment_manager.cancel(&deployment.id)
+    {
+        return Err(Error::Conflict(
+            "deployment can only be cancelled while it is queued, building or pending approval"
+                .to_string(),
+        ));
+    }
+
+    Ok(Json(deployment.into()))
+}
+
+#[instrument(skip_all, fields(%project_name, %deployment_id))]
+#[utoipa::path(
+    post,
+    path = "/projects/{project_name}/deployments/{deployment_id}/approve",
+    responses(
+        (status = 200, description = "Approves a deployment which is pending approval to run.", body = shuttle_common::models::deployment::Response),
+        (status = 500, description = "Database error.", body = String),
+        (status = 404, description = "Record could not be found.", body = String),
+        (status = 409, description = "The deployment is not pending approval.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the deployment."),
+        ("deployment_id" = String, Path, description = "The deployment id in uuid format.")
+    )
+)]
+pub async fn approve_deployment(
+    Extension(deployment_manager): Extension<DeploymentManager>,
+    Extension(persistence): Extension<Persistence>,
+    Extension(claim): Extension<Claim>,
+    Path((project_name, deployment_id)): Path<(String, Uuid)>,
+) -> Result<Json<shuttle_common::models::deployment::Response>> {
+    let Some(mut deployment) = persistence.get_deployment(&deployment_id).await? else {
+        return Err(Error::NotFound("deployment not found".to_string()));
+    };
+
+    if deployment.state != State::PendingApproval {
+        return Err(Error::Conflict(format!(
+            "deployment {deployment_id} is not pending approval"
+        )));
+    }
+
+    let Some(service) = persistence.get_service(&deployment.service_id).await? else {
+        return Err(Error::NotFound("service not found".to_string()));
+    };
+
+    let built = Built {
+        id: deployment.id,
+        service_name: service.name,
+        service_id: service.id,
+        tracing_context: Default::default(),
+        is_next: deployment.is_next,
+        claim: Some(claim),
+    };
+
+    // The running deployment is stopped once this one is loaded
+    deployment_manager.run_push(built).await;
+    deployment.state = State::Built;
+
+    Ok(Json(deployment.into()))
+}
+
+#[instrument(skip_all, fields(%project_name))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/approval",
+    responses(
+        (status = 200, description = "Gets whether the deployments of a project are held until they are approved to run.", body = shuttle_common::models::deployment::Approval),
+        (status = 500, description = "Database error.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project."),
+    )
+)]
+pub async fn get_approval(
+    Extension(persistence): Extension<Persistence>,
+    Path(project_name): Path<String>,
+) -> Result<Json<shuttle_common::models::deployment::Approval>> {
+    Ok(Json(persistence.get_approval().await?))
+}
+
+#[instrument(skip_all, fields(%project_name))]
+#[utoipa::path(
+    put,
+    path = "/projects/{project_name}/approval",
+    request_body = shuttle_common::models::deployment::Approval,
+    responses(
+        (status = 200, description = "Chooses whether the deployments of a project are held until they are approved to run. Deployments which are already pending approval stay so.", body = shuttle_common::models::deployment::Approval),
+        (status = 500, description = "Database error.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project."),
+    )
+)]
+pub async fn set_approval(
+    Extension(persistence): Extension<Persistence>,
+    Path(project_name): Path<String>,
+    Json(approval): Json<shuttle_common::models::deployment::Approval>,
+) -> Result<Json<shuttle_common::models::deployment::Approval>> {
+    persistence.set_approval(&approval).await?;
+
+    Ok(Json(approval))
+}
+
 #[instrument(skip_all, fields(%project_name, %deployment_id))]
 #[utoipa::path(
     get,
@@ -496,21 +1381,22 @@ pub async fn delete_deployment(
     ),
     params(
         ("project_name" = String, Path, description = "Name of the project that owns the deployment."),
-        ("deployment_id" = String, Path, description = "The deployment id in uuid format.")
+        ("deployment_id" = String, Path, description = "The deployment id in uuid format."),
+        LogsQuery
     )
 )]
 pub async fn get_logs(
     Extension(persistence): Extension<Persistence>,
     Path((project_name, deployment_id)): Path<(String, Uuid)>,
+    Query(query): Query<LogsQuery>,
 ) -> Result<Json<Vec<LogItem>>> {
     if let Some(deployment) = persistence.get_deployment(&deployment_id).await? {
         Ok(Json(
-            persistence
-                .get_deployment_logs(&deployment.id)
-                .await?
-                .into_iter()
-                .filter_map(Into::into)
-                .collect(),
+            query.apply(
+                persistence
+                    .get_filtered_logs(&deployment.id, &query.filter())
+                    .await?,
+            ),
         ))
     } else {
         Err(Error::NotFound("deployment not found".to_string()))
@@ -525,20 +1411,27 @@ pub async fn get_logs(
     ),
     params(
         ("project_name" = String, Path, description = "Name of the project that owns the deployment."),
-        ("deployment_id" = String, Path, description = "The deployment id in uuid format.")
+        ("deployment_id" = String, Path, description = "The deployment id in uuid format."),
+        LogsQuery
     )
 )]
 pub async fn get_logs_subscribe(
     Extension(persistence): Extension<Persistence>,
     Path((_project_name, deployment_id)): Path<(String, Uuid)>,
+    Query(query): Query<LogsQuery>,
     ws_upgrade: ws::WebSocketUpgrade,
 ) -> axum::response::Response {
-    ws_upgrade.on_upgrade(move |s| logs_websocket_handler(s, persistence, deployment_id))
+    ws_upgrade.on_upgrade(move |s| logs_websocket_handler(s, persistence, deployment_id, query))
 }
 
-async fn logs_websocket_handler(mut s: WebSocket, persistence: Persistence, id: Uuid) {
+async fn logs_websocket_handler(
+    mut s: WebSocket,
+    persistence: Persistence,
+    id: Uuid,
+    query: LogsQuery,
+) {
     let mut log_recv = persistence.get_log_subscriber();
-    let backlog = match persistence.get_deployment_logs(&id).await {
+    let backlog = match persistence.get_filtered_logs(&id, &query.filter()).await {
         Ok(backlog) => backlog,
         Err(error) => {
             error!(
@@ -555,18 +1448,20 @@ async fn logs_websocket_handler(mut s: WebSocket, persistence: Persistence, id:
     };
 
     // Unwrap is safe because it only returns None for out of range numbers or invalid nanosecond
-    let mut last_timestamp = Utc.timestamp_opt(0, 0).unwrap();
+    let mut last_timestamp = query
+        .since
+        .unwrap_or_else(|| Utc.timestamp_opt(0, 0).unwrap());
+    if let Some(log) = backlog.last() {
+        last_timestamp = last_timestamp.max(log.timestamp);
+    }
 
-    for log in backlog.into_iter() {
-        last_timestamp = log.timestamp;
-        if let Some(log_item) = Option::<LogItem>::from(log) {
-            let msg = serde_json::to_string(&log_item).expect("to convert log item to json");
-            let sent = s.send(ws::Message::Text(msg)).await;
+    for log_item in query.apply(backlog) {
+        let msg = serde_json::to_string(&log_item).expect("to convert log item to json");
+        let sent = s.send(ws::Message::Text(msg)).await;
 
-            // Client disconnected?
-            if sent.is_err() {
-                return;
-            }
+        // Client disconnected?
+        if sent.is_err() {
+            return;
         }
     }
 
@@ -574,7 +1469,9 @@ async fn logs_websocket_handler(mut s: WebSocket, persistence: Persistence, id:
         trace!(?log, "received log from broadcast channel");
 
         if log.id == id && log.timestamp > last_timestamp {
-            if let Some(log_item) = Option::<LogItem>::from(Log::from(log)) {
+            if let Some(log_item) =
+                Option::<LogItem>::from(Log::from(log)).filter(|log_item| query.matches(log_item))
+            {
                 let msg = serde_json::to_string(&log_item).expect("to convert log item to json");
                 let sent = s.send(ws::Message::Text(msg)).await;
 
@@ -589,63 +1486,976 @@ async fn logs_websocket_handler(mut s: WebSocket, persistence: Persistence, id:
     let _ = s.close().await;
 }
 
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+pub struct TunnelQuery {
+    /// Type of the database resource to reach instead of the service, like `database::shared::postgres`.
+    pub resource: Option<String>,
+}
+
 #[instrument(skip_all, fields(%project_name, %service_name))]
 #[utoipa::path(
     get,
-    path = "/projects/{project_name}/secrets/{service_name}",
+    path = "/projects/{project_name}/ws/services/{service_name}/tunnel",
     responses(
-        (status = 200, description = "Gets the secrets a specific service.", body = [shuttle_common::models::secret::Response]),
-        (status = 500, description = "Database or streaming error.", body = String),
-        (status = 404, description = "Record could not be found.", body = String),
+        (status = 101, description = "Opens a tunnel to the private port of the running service, or to one of its databases, carried in binary messages."),
+        (status = 500, description = "Database error.", body = String),
+        (status = 404, description = "The service is not running, or has no such resource.", body = String),
+        (status = 400, description = "The resource is not a database.", body = String),
     ),
     params(
         ("project_name" = String, Path, description = "Name of the project that owns the service."),
-        ("service_name" = String, Path, description = "Name of the service.")
+        ("service_name" = String, Path, description = "Name of the service."),
+        TunnelQuery
     )
 )]
-pub async fn get_secrets(
+pub async fn get_tunnel(
     Extension(persistence): Extension<Persistence>,
     Path((project_name, service_name)): Path<(String, String)>,
-) -> Result<Json<Vec<secret::Response>>> {
-    if let Some(service) = persistence.get_service_by_name(&service_name).await? {
-        let keys = persistence
-            .get_secrets(&service.id)
+    Query(query): Query<TunnelQuery>,
+    ws_upgrade: ws::WebSocketUpgrade,
+) -> Result<axum::response::Response> {
+    let Some(service) = persistence.get_service_by_name(&service_name).await? else {
+        return Err(Error::NotFound("service not found".to_string()));
+    };
+
+    let address = match query.resource {
+        None => persistence
+            .get_active_deployment(&service.id)
             .await?
-            .into_iter()
-            .map(Into::into)
-            .collect();
+            .and_then(|deployment| deployment.address)
+            .ok_or_else(|| Error::NotFound("the service is not running".to_string()))?
+            .to_string(),
+        Some(resource_type) => {
+            let not_found =
+                || Error::NotFound(format!("the service has no {resource_type} resource"));
+            let r#type: ResourceType = resource_type.parse().map_err(|_| not_found())?;
 
-        Ok(Json(keys))
-    } else {
-        Err(Error::NotFound("service not found".to_string()))
-    }
+            if !matches!(r#type, ResourceType::Database(_)) {
+                return Err(Error::BadRequest(
+                    "only databases can be reached through a tunnel".to_string(),
+                ));
+            }
+
+            let resource = persistence
+                .get_resources(&service.id)
+                .await?
+                .into_iter()
+                .find(|resource| resource.r#type == r#type)
+                .ok_or_else(not_found)?;
+
+            tunnel::database_address(&resource.data)?
+        }
+    };
+
+    Ok(ws_upgrade.on_upgrade(move |socket| tunnel::pipe(socket, address)))
 }
 
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+pub struct MetricsQuery {
+    /// Only get the samples taken after this time, which is an hour ago by default.
+    pub since: Option<DateTime<Utc>>,
+}
+
+#[instrument(skip_all, fields(%project_name, %service_name))]
 #[utoipa::path(
-    post,
-    path = "/projects/{project_name}/clean",
+    get,
+    path = "/projects/{project_name}/services/{service_name}/metrics",
     responses(
-        (status = 200, description = "Clean a specific project build artifacts.", body = [String]),
-        (status = 500, description = "Clean project error.", body = String),
+        (status = 200, description = "Gets the CPU, memory, requests and restarts of the running deployment of a service.", body = shuttle_common::models::metrics::Response),
+        (status = 500, description = "Database error.", body = String),
+        (status = 404, description = "The service is not running.", body = String),
     ),
     params(
         ("project_name" = String, Path, description = "Name of the project that owns the service."),
+        ("service_name" = String, Path, description = "Name of the service."),
+        MetricsQuery
     )
 )]
-pub async fn clean_project(
-    Extension(deployment_manager): Extension<DeploymentManager>,
-    Path(project_name): Path<String>,
-) -> Result<Json<Vec<String>>> {
-    let project_path = deployment_manager
-        .storage_manager()
-        .service_build_path(&project_name)
-        .map_err(anyhow::Error::new)?;
+pub async fn get_metrics(
+    Extension(persistence): Extension<Persistence>,
+    Path((project_name, service_name)): Path<(String, String)>,
+    Query(query): Query<MetricsQuery>,
+) -> Result<Json<shuttle_common::models::metrics::Response>> {
+    let Some(service) = persistence.get_service_by_name(&service_name).await? else {
+        return Err(Error::NotFound("service not found".to_string()));
+    };
 
-    let lines = clean_crate(&project_path, true)?;
+    let Some(deployment) = persistence.get_active_deployment(&service.id).await? else {
+        return Err(Error::NotFound("the service is not running".to_string()));
+    };
 
-    Ok(Json(lines))
+    let since = query
+        .since
+        .unwrap_or_else(|| Utc::now() - chrono::Duration::hours(1));
+    let samples = persistence
+        .get_samples(&deployment.id, since)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    let restarts = persistence.get_restart_count(&deployment.id, since).await?;
+
+    Ok(Json(shuttle_common::models::metrics::Response {
+        deployment_id: deployment.id,
+        since,
+        samples,
+        restarts,
+    }))
 }
 
-async fn get_status() -> String {
-    "Ok".to_string()
+#[instrument(skip_all, fields(%project_name, %service_name))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/services/{service_name}/cron",
+    responses(
+        (status = 200, description = "Gets the scheduled jobs of the running deployment of a service.", body = [shuttle_common::models::cron::Response]),
+        (status = 500, description = "Database error.", body = String),
+        (status = 404, description = "The service is not running.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the service."),
+        ("service_name" = String, Path, description = "Name of the service.")
+    )
+)]
+pub async fn get_cron_jobs(
+    Extension(persistence): Extension<Persistence>,
+    Extension(deployment_manager): Extension<DeploymentManager>,
+    Path((project_name, service_name)): Path<(String, String)>,
+) -> Result<Json<Vec<shuttle_common::models::cron::Response>>> {
+    let Some(service) = persistence.get_service_by_name(&service_name).await? else {
+        return Err(Error::NotFound("service not found".to_string()));
+    };
+
+    let Some(deployment) = persistence.get_active_deployment(&service.id).await? else {
+        return Err(Error::NotFound("the service is not running".to_string()));
+    };
+
+    let runs = persistence
+        .get_cron_runs(&service.id, None, CRON_RUNS_LIMIT)
+        .await?;
+    let jobs = RunConfig::load(&deployment_manager.storage_manager(), &deployment.id)
+        .cron
+        .into_iter()
+        .map(|job| shuttle_common::models::cron::Response {
+            next_run: job
+                .schedule()
+                .ok()
+                .and_then(|schedule| schedule.upcoming(Utc).next()),
+            last_run: runs
+                .iter()
+                .find(|run| run.name == job.name)
+                .cloned()
+                .map(Into::into),
+            name: job.name,
+            schedule: job.schedule,
+            path: job.path,
+        })
+        .collect();
+
+    Ok(Json(jobs))
+}
+
+/// How many runs of scheduled jobs are listed by default
+const CRON_RUNS_LIMIT: u32 = 50;
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+pub struct CronRunsQuery {
+    /// Only get the runs of the job with this name.
+    pub name: Option<String>,
+    /// Only get this many of the latest runs, 50 by default.
+    pub limit: Option<u32>,
+}
+
+#[instrument(skip_all, fields(%project_name, %service_name))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/services/{service_name}/cron/runs",
+    responses(
+        (status = 200, description = "Gets the latest runs of the scheduled jobs of a service, newest first.", body = [shuttle_common::models::cron::Run]),
+        (status = 500, description = "Database error.", body = String),
+        (status = 404, description = "Record could not be found.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the service."),
+        ("service_name" = String, Path, description = "Name of the service."),
+        CronRunsQuery
+    )
+)]
+pub async fn get_cron_runs(
+    Extension(persistence): Extension<Persistence>,
+    Path((project_name, service_name)): Path<(String, String)>,
+    Query(query): Query<CronRunsQuery>,
+) -> Result<Json<Vec<shuttle_common::models::cron::Run>>> {
+    let Some(service) = persistence.get_service_by_name(&service_name).await? else {
+        return Err(Error::NotFound("service not found".to_string()));
+    };
+
+    let runs = persistence
+        .get_cron_runs(
+            &service.id,
+            query.name.as_deref(),
+            query.limit.unwrap_or(CRON_RUNS_LIMIT),
+        )
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(Json(runs))
+}
+
+#[instrument(skip_all, fields(%project_name, %service_name))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/services/{service_name}/storage",
+    responses(
+        (status = 200, description = "Gets how much of its quota the volume of a service takes up.", body = shuttle_common::models::storage::Usage),
+        (status = 500, description = "Database or storage error.", body = String),
+        (status = 404, description = "Service not found.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the service."),
+        ("service_name" = String, Path, description = "Name of the service.")
+    )
+)]
+pub async fn get_storage_usage(
+    Extension(persistence): Extension<Persistence>,
+    Extension(volume_keeper): Extension<VolumeKeeper>,
+    Path((project_name, service_name)): Path<(String, String)>,
+) -> Result<Json<storage::Usage>> {
+    if persistence
+        .get_service_by_name(&service_name)
+        .await?
+        .is_none()
+    {
+        return Err(Error::NotFound("service not found".to_string()));
+    }
+
+    let usage = volume_keeper
+        .usage(&service_name)
+        .await
+        .context("failed to measure the volume")?;
+
+    Ok(Json(usage))
+}
+
+#[instrument(skip_all, fields(%project_name, %service_name))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/services/{service_name}/persist",
+    responses(
+        (status = 200, description = "Gets the keys the persist instance of a service keeps in production.", body = shuttle_common::models::persist::Response),
+        (status = 500, description = "Database or storage error.", body = String),
+        (status = 404, description = "Service not found.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the service."),
+        ("service_name" = String, Path, description = "Name of the service.")
+    )
+)]
+pub async fn get_persist_keys(
+    Extension(persistence): Extension<Persistence>,
+    Extension(deployment_manager): Extension<DeploymentManager>,
+    Path((project_name, service_name)): Path<(String, String)>,
+) -> Result<Json<persist::Response>> {
+    if persistence
+        .get_service_by_name(&service_name)
+        .await?
+        .is_none()
+    {
+        return Err(Error::NotFound("service not found".to_string()));
+    }
+
+    let storage_path = deployment_manager
+        .storage_manager()
+        .service_storage_path(&service_name)
+        .context("failed to get the storage of the service")?;
+    let path = persist_path(&storage_path, &service_name, Environment::Production);
+
+    let mut keys = Vec::new();
+    // A service which never stored a value has no directory yet
+    if let Ok(mut dir) = tokio::fs::read_dir(&path).await {
+        while let Some(entry) = dir
+            .next_entry()
+            .await
+            .context("failed to read the persist directory")?
+        {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("bin") {
+                continue;
+            }
+
+            if let Some(key) = path.file_stem().and_then(|s| s.to_str()) {
+                let bytes = entry
+                    .metadata()
+                    .await
+                    .context("failed to read the size of a value")?
+                    .len();
+                keys.push(persist::Key {
+                    key: key.to_string(),
+                    bytes,
+                });
+            }
+        }
+    }
+    keys.sort_by(|a, b| a.key.cmp(&b.key));
+
+    Ok(Json(persist::Response {
+        bytes: keys.iter().map(|key| key.bytes).sum(),
+        keys,
+        quota: PERSIST_QUOTA,
+    }))
+}
+
+#[instrument(skip_all, fields(%project_name, %service_name))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/secrets/{service_name}",
+    responses(
+        (status = 200, description = "Gets the secrets a specific service.", body = [shuttle_common::models::secret::Response]),
+        (status = 500, description = "Database or streaming error.", body = String),
+        (status = 404, description = "Record could not be found.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the service."),
+        ("service_name" = String, Path, description = "Name of the service.")
+    )
+)]
+pub async fn get_secrets(
+    Extension(persistence): Extension<Persistence>,
+    Path((project_name, service_name)): Path<(String, String)>,
+) -> Result<Json<Vec<secret::Response>>> {
+    if let Some(service) = persistence.get_service_by_name(&service_name).await? {
+        let keys = persistence
+            .get_secrets(&service.id)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        Ok(Json(keys))
+    } else {
+        Err(Error::NotFound("service not found".to_string()))
+    }
+}
+
+#[instrument(skip_all, fields(%project_name, %service_name))]
+#[utoipa::path(
+    put,
+    path = "/projects/{project_name}/secrets/{service_name}",
+    request_body = BTreeMap<String, String>,
+    responses(
+        (status = 200, description = "Sets secrets of a specific service, which it gets the next time it is started.", body = [shuttle_common::models::secret::Response]),
+        (status = 500, description = "Database error.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the service."),
+        ("service_name" = String, Path, description = "Name of the service.")
+    )
+)]
+pub async fn set_secrets(
+    Extension(persistence): Extension<Persistence>,
+    Path((project_name, service_name)): Path<(String, String)>,
+    Json(secrets): Json<BTreeMap<String, String>>,
+) -> Result<Json<Vec<secret::Response>>> {
+    // Secrets can be set before the first deployment of a service
+    let service = persistence.get_or_create_service(&service_name).await?;
+
+    for (key, value) in secrets {
+        persistence.insert_secret(&service.id, &key, &value).await?;
+    }
+
+    let keys = persistence
+        .get_secrets(&service.id)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(Json(keys))
+}
+
+#[instrument(skip_all, fields(%project_name, %service_name, %key))]
+#[utoipa::path(
+    delete,
+    path = "/projects/{project_name}/secrets/{service_name}/{key}",
+    responses(
+        (status = 200, description = "Removes a secret of a specific service.", body = shuttle_common::models::secret::Response),
+        (status = 500, description = "Database error.", body = String),
+        (status = 404, description = "Record could not be found.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the service."),
+        ("service_name" = String, Path, description = "Name of the service."),
+        ("key" = String, Path, description = "Key of the secret.")
+    )
+)]
+pub async fn delete_secret(
+    Extension(persistence): Extension<Persistence>,
+    Path((project_name, service_name, key)): Path<(String, String, String)>,
+) -> Result<Json<secret::Response>> {
+    let Some(service) = persistence.get_service_by_name(&service_name).await? else {
+        return Err(Error::NotFound("service not found".to_string()));
+    };
+
+    if let Some(secret) = persistence.delete_secret(&service.id, &key).await? {
+        Ok(Json(secret.into()))
+    } else {
+        Err(Error::NotFound(format!("no secret with the key {key}")))
+    }
+}
+
+#[instrument(skip_all, fields(%project_name, %service_name))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/env/{service_name}",
+    responses(
+        (status = 200, description = "Gets the environment variables of a specific service.", body = [shuttle_common::models::env::Response]),
+        (status = 500, description = "Database error.", body = String),
+        (status = 404, description = "Record could not be found.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the service."),
+        ("service_name" = String, Path, description = "Name of the service.")
+    )
+)]
+pub async fn get_env_vars(
+    Extension(persistence): Extension<Persistence>,
+    Path((project_name, service_name)): Path<(String, String)>,
+) -> Result<Json<Vec<env::Response>>> {
+    let Some(service) = persistence.get_service_by_name(&service_name).await? else {
+        return Err(Error::NotFound("service not found".to_string()));
+    };
+
+    let vars = persistence
+        .get_env_vars(&service.id)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(Json(vars))
+}
+
+#[instrument(skip_all, fields(%project_name, %service_name))]
+#[utoipa::path(
+    put,
+    path = "/projects/{project_name}/env/{service_name}",
+    request_body = BTreeMap<String, String>,
+    responses(
+        (status = 200, description = "Sets environment variables of a specific service, which its runtime gets the next time it is started.", body = [shuttle_common::models::env::Response]),
+        (status = 500, description = "Database error.", body = String),
+        (status = 400, description = "A key is not a valid environment variable name.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the service."),
+        ("service_name" = String, Path, description = "Name of the service.")
+    )
+)]
+pub async fn set_env_vars(
+    Extension(persistence): Extension<Persistence>,
+    Path((project_name, service_name)): Path<(String, String)>,
+    Json(vars): Json<BTreeMap<String, String>>,
+) -> Result<Json<Vec<env::Response>>> {
+    if let Some(key) = vars.keys().find(|key| !is_env_key(key)) {
+        return Err(Error::BadRequest(format!(
+            "{key} is not a valid environment variable name. Names are made of letters, digits and underscores, do not start with a digit and do not start with SHUTTLE_"
+        )));
+    }
+
+    // Like secrets, these can be set before the first deployment of a service
+    let service = persistence.get_or_create_service(&service_name).await?;
+
+    for (key, value) in vars {
+        persistence.set_env_var(&service.id, &key, &value).await?;
+    }
+
+    let vars = persistence
+        .get_env_vars(&service.id)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(Json(vars))
+}
+
+#[instrument(skip_all, fields(%project_name, %service_name, %key))]
+#[utoipa::path(
+    delete,
+    path = "/projects/{project_name}/env/{service_name}/{key}",
+    responses(
+        (status = 200, description = "Removes an environment variable of a specific service.", body = shuttle_common::models::env::Response),
+        (status = 500, description = "Database error.", body = String),
+        (status = 404, description = "Record could not be found.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the service."),
+        ("service_name" = String, Path, description = "Name of the service."),
+        ("key" = String, Path, description = "Name of the environment variable.")
+    )
+)]
+pub async fn delete_env_var(
+    Extension(persistence): Extension<Persistence>,
+    Path((project_name, service_name, key)): Path<(String, String, String)>,
+) -> Result<Json<env::Response>> {
+    let Some(service) = persistence.get_service_by_name(&service_name).await? else {
+        return Err(Error::NotFound("service not found".to_string()));
+    };
+
+    if let Some(var) = persistence.delete_env_var(&service.id, &key).await? {
+        Ok(Json(var.into()))
+    } else {
+        Err(Error::NotFound(format!(
+            "no environment variable named {key}"
+        )))
+    }
+}
+
+/// Whether `key` can be the name of an environment variable set by users. The `SHUTTLE_` prefix is
+/// kept for the variables shuttle sets itself.
+fn is_env_key(key: &str) -> bool {
+    let mut chars = key.chars();
+
+    chars
+        .next()
+        .map_or(false, |first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !key.starts_with("SHUTTLE_")
+}
+
+#[instrument(skip_all, fields(%project_name))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/webhooks",
+    responses(
+        (status = 200, description = "Gets the webhooks which are called when the deployments of a project change.", body = [shuttle_common::models::webhook::Response]),
+        (status = 500, description = "Database error.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project."),
+    )
+)]
+pub async fn get_webhooks(
+    Extension(persistence): Extension<Persistence>,
+    Path(project_name): Path<String>,
+) -> Result<Json<Vec<webhook::Response>>> {
+    let webhooks = persistence
+        .get_webhooks()
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(Json(webhooks))
+}
+
+#[instrument(skip_all, fields(%project_name))]
+#[utoipa::path(
+    post,
+    path = "/projects/{project_name}/webhooks",
+    request_body = shuttle_common::models::webhook::Request,
+    responses(
+        (status = 200, description = "Registers a webhook for the deployments of a project, with the secret its payloads are signed with.", body = shuttle_common::models::webhook::Created),
+        (status = 500, description = "Database error.", body = String),
+        (status = 400, description = "The URL is not an http or https URL.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project."),
+    )
+)]
+pub async fn create_webhook(
+    Extension(persistence): Extension<Persistence>,
+    Path(project_name): Path<String>,
+    Json(request): Json<webhook::Request>,
+) -> Result<Json<webhook::Created>> {
+    webhooks::check_url(&request.url)
+        .await
+        .map_err(Error::BadRequest)?;
+
+    let webhook = Webhook {
+        id: Uuid::new_v4(),
+        url: request.url,
+        secret: webhooks::generate_secret(),
+        events: sqlx::types::Json(request.events),
+        created_at: Utc::now(),
+    };
+    persistence.insert_webhook(&webhook).await?;
+
+    Ok(Json(webhook::Created {
+        secret: webhook.secret.clone(),
+        webhook: webhook.into(),
+    }))
+}
+
+#[instrument(skip_all, fields(%project_name, %webhook_id))]
+#[utoipa::path(
+    delete,
+    path = "/projects/{project_name}/webhooks/{webhook_id}",
+    responses(
+        (status = 200, description = "Removes a webhook of a project, which is not called anymore.", body = shuttle_common::models::webhook::Response),
+        (status = 500, description = "Database error.", body = String),
+        (status = 404, description = "Record could not be found.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project."),
+        ("webhook_id" = String, Path, description = "Id of the webhook.")
+    )
+)]
+pub async fn delete_webhook(
+    Extension(persistence): Extension<Persistence>,
+    Path((project_name, webhook_id)): Path<(String, Uuid)>,
+) -> Result<Json<webhook::Response>> {
+    if let Some(webhook) = persistence.delete_webhook(&webhook_id).await? {
+        Ok(Json(webhook.into()))
+    } else {
+        Err(Error::NotFound(format!(
+            "no webhook with the id {webhook_id}"
+        )))
+    }
+}
+
+#[instrument(skip_all, fields(%project_name))]
+#[utoipa::path(
+    post,
+    path = "/projects/{project_name}/clean",
+    responses(
+        (status = 200, description = "Purges the build cache of a specific project, with its target directory and incremental compilation state.", body = [String]),
+        (status = 500, description = "Clean project error.", body = String),
+        (status = 409, description = "A deployment of the project is being built.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the service."),
+    )
+)]
+pub async fn clean_project(
+    Extension(persistence): Extension<Persistence>,
+    Extension(deployment_manager): Extension<DeploymentManager>,
+    Path(project_name): Path<String>,
+) -> Result<Json<Vec<String>>> {
+    // Removing the target directory from under a build would fail it
+    if let Some(service) = persistence.get_service_by_name(&project_name).await? {
+        let building = DeploymentFilter {
+            state: Some(State::Building),
+            ..Default::default()
+        };
+        if !persistence
+            .get_deployments(&service.id, &building, 0, 1)
+            .await?
+            .is_empty()
+        {
+            return Err(Error::Conflict(
+                "a deployment is being built, clean the build cache once it is done".to_string(),
+            ));
+        }
+    }
+
+    let project_path = deployment_manager
+        .storage_manager()
+        .service_build_path(&project_name)
+        .map_err(anyhow::Error::new)?;
+    let target_dir = deployment_manager.build_target_dir(&project_name);
+
+    let summary = purge_crate(&project_path, target_dir.as_deref())
+        .await
+        .map_err(anyhow::Error::new)?;
+
+    let mut lines: Vec<String> = summary
+        .removed_paths
+        .iter()
+        .map(|path| format!("Removed {}", path.display()))
+        .collect();
+    lines.push(format!(
+        "Removed {} paths, freeing {:.1} MiB",
+        summary.removed_paths.len(),
+        summary.bytes_freed as f64 / (1024.0 * 1024.0)
+    ));
+
+    Ok(Json(lines))
+}
+
+async fn get_openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(openapi())
+}
+
+async fn get_status() -> String {
+    "Ok".to_string()
+}
+
+/// Keep the requests the gateway proxied to the services as logs of their running deployments
+async fn post_access_logs(
+    Extension(persistence): Extension<Persistence>,
+    Json(entries): Json<Vec<access_log::Entry>>,
+) -> Result<()> {
+    let mut deployments = HashMap::new();
+
+    for entry in entries {
+        if !deployments.contains_key(&entry.service) {
+            let id = match persistence.get_service_by_name(&entry.service).await? {
+                Some(service) => persistence
+                    .get_active_deployment(&service.id)
+                    .await?
+                    .map(|deployment| deployment.id),
+                None => None,
+            };
+
+            deployments.insert(entry.service.clone(), id);
+        }
+
+        // The requests to services which are no longer running have no deployment to go with
+        if let Some(id) = deployments[&entry.service] {
+            persistence.record(from_access_log(id, entry));
+        }
+    }
+
+    Ok(())
+}
+
+/// The log of a request the gateway proxied to the deployment `id`
+fn from_access_log(id: Uuid, entry: access_log::Entry) -> deploy_layer::Log {
+    deploy_layer::Log {
+        id,
+        state: State::Running,
+        level: if entry.status >= 500 {
+            LogLevel::Error
+        } else {
+            LogLevel::Info
+        },
+        timestamp: entry.timestamp,
+        file: None,
+        line: None,
+        target: String::new(),
+        fields: json!({
+            "message": entry.message(),
+            "latency_ms": entry.latency_ms,
+            "bytes": entry.bytes,
+            "client": entry.client,
+        }),
+        r#type: LogType::Event,
+        source: LogSource::Access,
+    }
+}
+
+async fn get_prometheus_metrics(
+    Extension(persistence): Extension<Persistence>,
+) -> Result<([(HeaderName, &'static str); 1], String)> {
+    let metrics = monitoring::render(&persistence).await?;
+
+    Ok(([(CONTENT_TYPE, TEXT_FORMAT)], metrics))
+}
+
+#[instrument(skip_all, fields(%project_name))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/artifacts/retention",
+    responses(
+        (status = 200, description = "Gets which artifacts of the deployments of a project are kept.", body = shuttle_common::models::artifacts::Retention),
+        (status = 500, description = "Database error.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project."),
+    )
+)]
+pub async fn get_artifact_retention(
+    Extension(artifact_collector): Extension<ArtifactCollector>,
+    Path(project_name): Path<String>,
+) -> Result<Json<artifacts::Retention>> {
+    Ok(Json(artifact_collector.retention().await?))
+}
+
+#[instrument(skip_all, fields(%project_name))]
+#[utoipa::path(
+    put,
+    path = "/projects/{project_name}/artifacts/retention",
+    request_body = shuttle_common::models::artifacts::Retention,
+    responses(
+        (status = 200, description = "Keeps the artifacts of a project by this retention instead of the one of the deployer.", body = shuttle_common::models::artifacts::Retention),
+        (status = 500, description = "Database error.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project."),
+    )
+)]
+pub async fn set_artifact_retention(
+    Extension(persistence): Extension<Persistence>,
+    Path(project_name): Path<String>,
+    Json(retention): Json<artifacts::Retention>,
+) -> Result<Json<artifacts::Retention>> {
+    persistence.set_artifact_retention(&retention).await?;
+
+    Ok(Json(retention))
+}
+
+#[instrument(skip_all, fields(%project_name))]
+#[utoipa::path(
+    delete,
+    path = "/projects/{project_name}/artifacts/retention",
+    responses(
+        (status = 200, description = "Goes back to keeping the artifacts of a project by the retention of the deployer, which it returns.", body = shuttle_common::models::artifacts::Retention),
+        (status = 500, description = "Database error.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project."),
+    )
+)]
+pub async fn delete_artifact_retention(
+    Extension(persistence): Extension<Persistence>,
+    Extension(artifact_collector): Extension<ArtifactCollector>,
+    Path(project_name): Path<String>,
+) -> Result<Json<artifacts::Retention>> {
+    persistence.delete_artifact_retention().await?;
+
+    Ok(Json(artifact_collector.retention().await?))
+}
+
+#[instrument(skip_all, fields(%project_name))]
+#[utoipa::path(
+    post,
+    path = "/projects/{project_name}/artifacts/sweep",
+    responses(
+        (status = 200, description = "Removes the artifacts of a project its retention does not keep right away, and reports the space it reclaimed.", body = shuttle_common::models::artifacts::Sweep),
+        (status = 500, description = "Sweep error.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project."),
+    )
+)]
+pub async fn sweep_artifacts(
+    Extension(artifact_collector): Extension<ArtifactCollector>,
+    Path(project_name): Path<String>,
+) -> Result<Json<artifacts::Sweep>> {
+    Ok(Json(artifact_collector.sweep().await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use regex::Regex;
+    use serde_json::json;
+    use shuttle_common::{
+        deployment::State,
+        log::{Level, Source},
+        LogItem,
+    };
+    use uuid::Uuid;
+
+    use shuttle_common::models::access_log;
+
+    use super::{from_access_log, is_env_key, openapi, LogsQuery};
+    use crate::persistence::{LogLevel, LogSource};
+
+    fn item(level: Level, target: &str, message: &str) -> LogItem {
+        LogItem {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            state: State::Running,
+            level,
+            file: None,
+            line: None,
+            target: target.to_string(),
+            fields: serde_json::to_vec(&json!({ "message": message })).unwrap(),
+            source: Source::Tracing,
+        }
+    }
+
+    #[test]
+    fn logs_query_matches() {
+        let query = LogsQuery {
+            since: None,
+            until: None,
+            tail: None,
+            level: Some(Level::Warn),
+            target: Some("app::db".to_string()),
+            source: None,
+            grep: Some(Regex::new("^connection (lost|refused)").unwrap()),
+        };
+
+        assert!(query.matches(&item(Level::Error, "app::db", "connection lost")));
+        assert!(query.matches(&item(Level::Warn, "app::db::pool", "connection refused")));
+        assert!(!query.matches(&item(Level::Info, "app::db", "connection lost")));
+        assert!(!query.matches(&item(Level::Error, "app::dbx", "connection lost")));
+        assert!(!query.matches(&item(Level::Error, "app", "connection lost")));
+        assert!(!query.matches(&item(Level::Error, "app::db", "GET / 200")));
+
+        let query = LogsQuery {
+            until: Some(Utc::now() - Duration::minutes(1)),
+            level: None,
+            target: None,
+            grep: None,
+            ..query
+        };
+
+        assert!(!query.matches(&item(Level::Info, "app", "too late")));
+
+        let query = LogsQuery {
+            until: None,
+            source: Some(Source::Stdout),
+            ..query
+        };
+        let printed = LogItem {
+            source: Source::Stdout,
+            ..item(Level::Info, "", "listening on 8000")
+        };
+
+        assert!(query.matches(&printed));
+        assert!(!query.matches(&item(Level::Info, "app", "listening on 8000")));
+    }
+
+    #[test]
+    fn access_logs() {
+        let id = Uuid::new_v4();
+        let entry = access_log::Entry {
+            timestamp: Utc::now(),
+            service: "matrix".to_string(),
+            method: "GET".to_string(),
+            path: "/neo".to_string(),
+            status: 503,
+            latency_ms: 12,
+            bytes: 512,
+            client: "0123456789abcdef".to_string(),
+        };
+
+        let log = from_access_log(id, entry.clone());
+
+        assert_eq!(log.id, id);
+        assert_eq!(log.timestamp, entry.timestamp);
+        assert_eq!(log.level, LogLevel::Error);
+        assert_eq!(log.source, LogSource::Access);
+        assert_eq!(
+            log.fields,
+            json!({
+                "message": "GET /neo 503",
+                "latency_ms": 12,
+                "bytes": 512,
+                "client": "0123456789abcdef",
+            })
+        );
+    }
+
+    #[test]
+    fn env_keys() {
+        for key in ["LEVEL", "_private", "api_key_2"] {
+            assert!(is_env_key(key), "{key}");
+        }
+
+        for key in ["", "2FA", "MY-KEY", "A=B", "SHUTTLE_PORT"] {
+            assert!(!is_env_key(key), "{key}");
+        }
+    }
+
+    #[test]
+    fn openapi_is_versioned() {
+        let openapi = openapi();
+
+        assert_eq!(openapi.info.version, "v1");
+        assert!(openapi
+            .paths
+            .paths
+            .contains_key("/v1/projects/{project_name}/services/{service_name}"));
+        assert!(openapi
+            .paths
+            .paths
+            .keys()
+            .all(|path| path.starts_with("/v1/projects/{project_name}/")));
+    }
 }
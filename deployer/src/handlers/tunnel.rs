@@ -0,0 +1,114 @@
+use anyhow::Context;
+use axum::extract::ws::{self, CloseFrame, WebSocket};
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{debug, warn};
+
+use super::Result;
+
+/// How much is read from the tunnelled connection before it is sent on
+const BUFFER_SIZE: usize = 16 * 1024;
+
+/// The part of a database resource's data a tunnel needs to reach it
+#[derive(Deserialize)]
+struct DatabaseAddress {
+    address_private: String,
+    port: String,
+}
+
+/// The `host:port` of the database with the resource data `data`, as reached from inside the
+/// network of the deployer
+pub fn database_address(data: &serde_json::Value) -> Result<String> {
+    let address: DatabaseAddress = serde_json::from_value(data.clone())
+        .context("failed to read the address of the database")?;
+
+    Ok(format!("{}:{}", address.address_private, address.port))
+}
+
+/// Connect to `address` and pass the bytes between it and the binary messages of `socket`, until
+/// either of them is closed
+pub async fn pipe(mut socket: WebSocket, address: String) {
+    let stream = match TcpStream::connect(&address).await {
+        Ok(stream) => stream,
+        Err(error) => {
+            warn!(%error, address, "failed to connect a tunnel");
+
+            let _ = socket
+                .send(ws::Message::Close(Some(CloseFrame {
+                    code: ws::close_code::ERROR,
+                    reason: "failed to connect to the tunnelled address".into(),
+                })))
+                .await;
+            return;
+        }
+    };
+
+    debug!(address, "tunnel connected");
+
+    let (mut reader, mut writer) = stream.into_split();
+    let (mut sink, mut messages) = socket.split();
+
+    let incoming = async {
+        while let Some(Ok(message)) = messages.next().await {
+            match message {
+                ws::Message::Binary(data) => {
+                    if writer.write_all(&data).await.is_err() {
+                        break;
+                    }
+                }
+                ws::Message::Close(_) => break,
+                _ => {}
+            }
+        }
+
+        let _ = writer.shutdown().await;
+    };
+
+    let outgoing = async {
+        let mut buf = vec![0; BUFFER_SIZE];
+
+        while let Ok(read @ 1..) = reader.read(&mut buf).await {
+            if sink
+                .send(ws::Message::Binary(buf[..read].to_vec()))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+
+        let _ = sink.close().await;
+    };
+
+    tokio::select! {
+        _ = incoming => {},
+        _ = outgoing => {},
+    }
+
+    debug!(address, "tunnel closed");
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::database_address;
+
+    #[test]
+    fn address_of_database() {
+        let data = json!({
+            "engine": "postgres",
+            "role_name": "user-test",
+            "role_password": "password",
+            "database_name": "db-test",
+            "port": "5432",
+            "address_private": "postgres.internal",
+            "address_public": "db.shuttle.rs",
+        });
+
+        assert_eq!(database_address(&data).unwrap(), "postgres.internal:5432");
+        assert!(database_address(&json!({ "path": "/opt/shuttle" })).is_err());
+    }
+}
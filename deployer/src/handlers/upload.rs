@@ -0,0 +1,176 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::extract::BodyStream;
+use futures::StreamExt;
+use sha2::{Digest, Sha256};
+use shuttle_common::storage_manager::ArtifactsStorageManager;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, warn};
+
+use super::{Error, Result};
+
+/// Uploads which were not finished for this long are given up on
+const UPLOAD_EXPIRATION: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// Where the part of the archive with the SHA-256 hash `hash` which was uploaded so far is kept
+pub fn path(storage_manager: &ArtifactsStorageManager, hash: &str) -> Result<PathBuf> {
+    // The hash is used as a file name, so it may not be anything else
+    if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(Error::BadRequest(format!(
+            "`{hash}` is not a SHA-256 hash of an archive"
+        )));
+    }
+
+    storage_manager
+        .upload_path(&hash.to_ascii_lowercase())
+        .context("failed to get the uploads directory")
+        .map_err(Error::from)
+}
+
+/// How many bytes of the upload at `path` were received
+pub async fn received(path: &Path) -> Result<u64> {
+    match fs::metadata(path).await {
+        Ok(metadata) => Ok(metadata.len()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(error) => Err(anyhow::Error::from(error)
+            .context("failed to read the upload")
+            .into()),
+    }
+}
+
+/// Add the chunk in `stream`, which starts at `offset` in the archive, to the upload at `path`
+pub async fn append(path: &Path, offset: u64, mut stream: BodyStream) -> Result<u64> {
+    let received = received(path).await?;
+    if offset != received {
+        return Err(Error::Conflict(format!(
+            "{received} bytes of the archive were received, so the next chunk starts there and not at {offset}"
+        )));
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .context("failed to open the upload")?;
+
+    // Keep what arrived of an interrupted chunk, so that the upload resumes after it
+    while let Some(buf) = stream.next().await {
+        let buf = buf?;
+        file.write_all(&buf)
+            .await
+            .context("failed to write to the upload")?;
+    }
+    file.flush()
+        .await
+        .context("failed to write to the upload")?;
+
+    self::received(path).await
+}
+
+/// Take the finished upload at `path`, making sure it has the SHA-256 hash `hash`
+pub async fn take(path: &Path, hash: &str) -> Result<Vec<u8>> {
+    let data = match fs::read(path).await {
+        Ok(data) => data,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            return Err(Error::NotFound(format!(
+                "no archive with the hash {hash} was uploaded"
+            )))
+        }
+        Err(error) => {
+            return Err(anyhow::Error::from(error)
+                .context("failed to read the upload")
+                .into())
+        }
+    };
+
+    fs::remove_file(path)
+        .await
+        .context("failed to remove the upload")?;
+
+    let actual = format!("{:x}", Sha256::digest(&data));
+    if !actual.eq_ignore_ascii_case(hash) {
+        return Err(Error::BadRequest(format!(
+            "the uploaded archive has the hash {actual} instead of {hash}, so it has to be uploaded again"
+        )));
+    }
+
+    Ok(data)
+}
+
+/// Remove the uploads in `uploads_path` which were not touched for [UPLOAD_EXPIRATION], getting
+/// how many were removed and how much space they took up
+pub async fn remove_expired(uploads_path: &Path) -> (usize, u64) {
+    let mut removed = (0, 0);
+    let Ok(mut entries) = fs::read_dir(uploads_path).await else {
+        return removed;
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let Ok(metadata) = entry.metadata().await else {
+            continue;
+        };
+        let expired = metadata
+            .modified()
+            .map(|modified| modified.elapsed().unwrap_or_default() > UPLOAD_EXPIRATION)
+            .unwrap_or_default();
+
+        if expired {
+            debug!(path = ?entry.path(), "removing an expired upload");
+            match fs::remove_file(entry.path()).await {
+                Ok(()) => {
+                    removed.0 += 1;
+                    removed.1 += metadata.len();
+                }
+                Err(error) => warn!(%error, "failed to remove an expired upload"),
+            }
+        }
+    }
+
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::{Digest, Sha256};
+    use shuttle_common::storage_manager::ArtifactsStorageManager;
+    use tempfile::Builder;
+    use tokio::fs;
+
+    use super::{path, received, take};
+    use crate::handlers::Error;
+
+    #[tokio::test]
+    async fn upload() {
+        let dir = Builder::new()
+            .prefix("shuttle-upload-test")
+            .tempdir()
+            .unwrap();
+        let storage_manager = ArtifactsStorageManager::new(dir.path().to_path_buf());
+
+        assert!(matches!(
+            path(&storage_manager, "../../etc/passwd"),
+            Err(Error::BadRequest(_))
+        ));
+
+        let data = b"an archive";
+        let hash = format!("{:x}", Sha256::digest(data));
+        let path = path(&storage_manager, &hash).unwrap();
+        assert_eq!(received(&path).await.unwrap(), 0);
+
+        fs::write(&path, &data[..3]).await.unwrap();
+        assert_eq!(received(&path).await.unwrap(), 3);
+        assert!(matches!(
+            take(&path, &hash).await,
+            Err(Error::BadRequest(_))
+        ));
+        assert!(matches!(take(&path, &hash).await, Err(Error::NotFound(_))));
+
+        fs::write(&path, data).await.unwrap();
+        assert_eq!(take(&path, &hash).await.unwrap(), data);
+        assert_eq!(received(&path).await.unwrap(), 0);
+    }
+}
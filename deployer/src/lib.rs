@@ -1,16 +1,21 @@
-use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+use std::{convert::Infallible, net::SocketAddr, sync::Arc, time::Duration};
 
 pub use args::Args;
 pub use deployment::deploy_layer::DeployLayer;
+pub use deployment::Replicas;
 use deployment::{Built, DeploymentManager};
 use fqdn::FQDN;
 use hyper::{
     server::conn::AddrStream,
     service::{make_service_fn, service_fn},
 };
+pub use limits::Limits;
+pub use metrics::RequestCounter;
 pub use persistence::Persistence;
 use proxy::AddressGetter;
 pub use runtime_manager::RuntimeManager;
+use shuttle_common::models::artifacts::Retention;
+use shuttle_service::builder::{BuildSandbox, RetentionPolicy};
 use tokio::sync::Mutex;
 use tracing::{error, info};
 
@@ -19,33 +24,117 @@ use crate::deployment::gateway_client::GatewayClient;
 mod args;
 mod deployment;
 mod error;
+mod gc;
 pub mod handlers;
+mod limits;
+mod log_retention;
+mod metrics;
+mod monitoring;
 mod persistence;
 mod proxy;
+mod routes;
 mod runtime_manager;
+mod volumes;
+mod webhooks;
 
 pub async fn start(
     persistence: Persistence,
     runtime_manager: Arc<Mutex<RuntimeManager>>,
+    replicas: Replicas,
+    requests: RequestCounter,
     args: Args,
 ) {
+    tokio::spawn(metrics::sample(
+        persistence.clone(),
+        runtime_manager.clone(),
+        requests,
+    ));
+    tokio::spawn(log_retention::prune(
+        persistence.clone(),
+        log_retention::LogRetention {
+            days: args.log_retention_days,
+            quota: args.log_quota,
+        },
+    ));
+    tokio::spawn(webhooks::dispatch(
+        persistence.clone(),
+        args.project.clone(),
+    ));
+
+    let gateway_client = GatewayClient::new(args.gateway_uri, args.project.to_string());
+
+    let build_sandbox = args.build_image.map(|image| BuildSandbox {
+        engine: args.build_engine,
+        image,
+        cargo_home: args.artifacts_path.join("cargo-home"),
+        memory: args.build_memory_limit,
+        millicpus: args
+            .build_cpu_limit
+            .map(|cpu| (cpu * 1000.0).round() as u64),
+        disk: args.build_disk_limit,
+    });
+
+    // The deployments which are started again are scaled like they were before
+    for (service_id, count, routing) in persistence.get_all_replicas().await.unwrap() {
+        replicas.scale(service_id, count);
+        replicas.route(service_id, routing);
+    }
+
     let deployment_manager = DeploymentManager::builder()
         .build_log_recorder(persistence.clone())
         .secret_recorder(persistence.clone())
         .active_deployment_getter(persistence.clone())
         .artifacts_path(args.artifacts_path)
+        .rustc_wrapper(args.rustc_wrapper)
+        .build_timeout(Duration::from_secs(args.build_timeout))
+        .build_target_dir(args.build_target_dir)
+        .install_toolchain(args.install_toolchain)
+        .audit_policy(args.audit_policy)
+        .build_sandbox(build_sandbox)
+        .build_target(args.build_target)
+        .build_concurrency(args.build_concurrency)
+        .artifact_store(args.artifact_store)
+        .artifact_retention(RetentionPolicy {
+            max_entries: Some(args.artifact_store_keep),
+            max_age: None,
+        })
         .runtime(runtime_manager)
+        .replicas(replicas)
+        .max_replicas(args.max_replicas)
         .deployment_updater(persistence.clone())
         .secret_getter(persistence.clone())
+        .env_var_getter(persistence.clone())
         .resource_manager(persistence.clone())
-        .queue_client(GatewayClient::new(args.gateway_uri))
+        .queue_client(gateway_client.clone())
         .build();
 
-    persistence.cleanup_invalid_states().await.unwrap();
+    let storage_manager = deployment_manager.storage_manager();
+    tokio::spawn(routes::update(
+        persistence.clone(),
+        gateway_client,
+        storage_manager.clone(),
+    ));
+    let recovered_deployments = persistence
+        .recover_deployments(|id| {
+            storage_manager
+                .deployment_executable_path(id)
+                .map_or(false, |path| path.exists())
+        })
+        .await
+        .unwrap();
 
+    // The deployments which were running go first, so that the recovered ones which are newer
+    // take over from them
     let runnable_deployments = persistence.get_all_runnable_deployments().await.unwrap();
-    info!(count = %runnable_deployments.len(), "enqueuing runnable deployments");
-    for existing_deployment in runnable_deployments {
+    info!(
+        count = %runnable_deployments.len(),
+        recovered = %recovered_deployments.len(),
+        "enqueuing runnable deployments"
+    );
+    for existing_deployment in runnable_deployments
+        .into_iter()
+        .chain(recovered_deployments)
+    {
         let built = Built {
             id: existing_deployment.id,
             service_name: existing_deployment.service_name,
@@ -57,10 +146,30 @@ pub async fn start(
         deployment_manager.run_push(built).await;
     }
 
+    let artifact_collector = gc::ArtifactCollector::new(
+        persistence.clone(),
+        deployment_manager.clone(),
+        Retention {
+            keep_deployments: args.artifact_keep_deployments,
+            max_bytes: args.artifact_quota.map(|quota| quota * 1024 * 1024),
+        },
+    );
+    tokio::spawn(gc::sweep(artifact_collector.clone()));
+
+    let volume_keeper = volumes::VolumeKeeper::new(
+        persistence.clone(),
+        deployment_manager.clone(),
+        args.volume_quota * 1024 * 1024,
+    );
+    tokio::spawn(volumes::enforce(volume_keeper.clone()));
+
     let mut builder = handlers::RouterBuilder::new(
         persistence,
         deployment_manager,
+        artifact_collector,
+        volume_keeper,
         args.proxy_fqdn,
+        args.provisioner_address,
         args.project,
         args.auth_uri,
     );
@@ -86,15 +195,26 @@ pub async fn start_proxy(
     proxy_address: SocketAddr,
     fqdn: FQDN,
     address_getter: impl AddressGetter,
+    replicas: Replicas,
+    requests: RequestCounter,
 ) {
     let make_service = make_service_fn(move |socket: &AddrStream| {
         let remote_address = socket.remote_addr();
         let address_getter = address_getter.clone();
+        let replicas = replicas.clone();
+        let requests = requests.clone();
         let fqdn = fqdn.clone();
 
         async move {
             Ok::<_, Infallible>(service_fn(move |req| {
-                proxy::handle(remote_address, fqdn.clone(), req, address_getter.clone())
+                proxy::handle(
+                    remote_address,
+                    fqdn.clone(),
+                    req,
+                    address_getter.clone(),
+                    replicas.clone(),
+                    requests.clone(),
+                )
             }))
         }
     });
@@ -0,0 +1,145 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use uuid::Uuid;
+
+/// Length of the period runtimes get their share of CPU time in, in microseconds
+const CPU_PERIOD: u64 = 100_000;
+
+/// The memory and CPU the runtimes of deployments may use, enforced with a cgroup (v2) for every
+/// deployment
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    /// Memory a deployment may use, in MiB, after which it is killed
+    pub memory: Option<u64>,
+    /// How many cores a deployment may use, after which it is throttled
+    pub cpu: Option<f64>,
+    /// The cgroup to make the cgroups of deployments in
+    pub cgroup_root: PathBuf,
+}
+
+impl Limits {
+    pub fn is_empty(&self) -> bool {
+        self.memory.is_none() && self.cpu.is_none()
+    }
+
+    fn cgroup_path(&self, id: &Uuid) -> PathBuf {
+        self.cgroup_root.join(id.to_string())
+    }
+
+    /// Put the runtime with process id `pid` of deployment `id` into a fresh cgroup which holds it
+    /// to the limits
+    pub fn apply(&self, id: &Uuid, pid: u32) -> io::Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.cgroup_root)?;
+        fs::write(
+            self.cgroup_root.join("cgroup.subtree_control"),
+            "+cpu +memory",
+        )?;
+
+        // A cgroup left behind by a runtime which crashed still counts its kills
+        let path = self.cgroup_path(id);
+        let _ = fs::remove_dir(&path);
+        fs::create_dir_all(&path)?;
+
+        if let Some(memory) = self.memory {
+            fs::write(path.join("memory.max"), (memory * 1024 * 1024).to_string())?;
+
+            // Swapping would let a deployment use more memory than it has
+            if let Err(error) = fs::write(path.join("memory.swap.max"), "0") {
+                if error.kind() != io::ErrorKind::NotFound {
+                    return Err(error);
+                }
+            }
+        }
+
+        if let Some(cpu) = self.cpu {
+            fs::write(path.join("cpu.max"), cpu_max(cpu))?;
+        }
+
+        fs::write(path.join("cgroup.procs"), pid.to_string())
+    }
+
+    /// Whether the kernel killed the runtime of deployment `id` for using more memory than it may
+    pub fn out_of_memory(&self, id: &Uuid) -> bool {
+        self.memory.is_some() && oom_kills(&self.cgroup_path(id).join("memory.events")) > 0
+    }
+
+    /// Remove the cgroup of deployment `id` once its runtime is gone
+    pub fn remove(&self, id: &Uuid) {
+        if !self.is_empty() {
+            let _ = fs::remove_dir(self.cgroup_path(id));
+        }
+    }
+}
+
+/// The quota of a cgroup which may use `cpu` cores, in the format of `cpu.max`
+fn cpu_max(cpu: f64) -> String {
+    let quota = (cpu * CPU_PERIOD as f64).round() as u64;
+
+    format!("{} {CPU_PERIOD}", quota.max(1000))
+}
+
+/// How many processes of a cgroup were killed for running out of memory, from its `memory.events`
+fn oom_kills(events_path: &Path) -> u64 {
+    fs::read_to_string(events_path)
+        .unwrap_or_default()
+        .lines()
+        .find_map(|line| line.strip_prefix("oom_kill "))
+        .and_then(|count| count.trim().parse().ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::Builder;
+    use uuid::Uuid;
+
+    use super::{cpu_max, oom_kills, Limits};
+
+    #[test]
+    fn cpu_quota() {
+        assert_eq!(cpu_max(1.0), "100000 100000");
+        assert_eq!(cpu_max(0.5), "50000 100000");
+        assert_eq!(cpu_max(2.25), "225000 100000");
+        assert_eq!(cpu_max(0.001), "1000 100000");
+    }
+
+    #[test]
+    fn out_of_memory() {
+        let root = Builder::new().prefix("shuttle-cgroup").tempdir().unwrap();
+        let id = Uuid::new_v4();
+        let limits = Limits {
+            memory: Some(256),
+            cpu: None,
+            cgroup_root: root.path().to_path_buf(),
+        };
+        let events = root.path().join(id.to_string()).join("memory.events");
+
+        assert!(!limits.out_of_memory(&id));
+
+        fs::create_dir_all(events.parent().unwrap()).unwrap();
+        fs::write(
+            &events,
+            "low 0\nhigh 0\nmax 12\noom 1\noom_kill 0\noom_group_kill 0\n",
+        )
+        .unwrap();
+        assert_eq!(oom_kills(&events), 0);
+        assert!(!limits.out_of_memory(&id));
+
+        fs::write(
+            &events,
+            "low 0\nhigh 0\nmax 40\noom 2\noom_kill 1\noom_group_kill 0\n",
+        )
+        .unwrap();
+        assert_eq!(oom_kills(&events), 1);
+        assert!(limits.out_of_memory(&id));
+    }
+}
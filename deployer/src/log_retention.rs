@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, trace};
+
+use crate::persistence::Persistence;
+
+/// How often old logs are pruned
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How long the logs of the deployments of a project are kept, and how much space they may take up
+#[derive(Clone, Debug)]
+pub struct LogRetention {
+    /// Days after which logs are removed
+    pub days: u32,
+    /// Size in MiB the logs may take up before the oldest ones are removed
+    pub quota: u64,
+}
+
+/// Keep removing the logs which are past the retention, or which no longer fit in the quota
+pub async fn prune(persistence: Persistence, retention: LogRetention) {
+    let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+
+        let before = Utc::now() - chrono::Duration::days(retention.days.into());
+
+        match persistence
+            .prune_logs(before, retention.quota * 1024 * 1024)
+            .await
+        {
+            Ok(count) => trace!(count, "pruned logs"),
+            Err(error) => error!(
+                error = &error as &dyn std::error::Error,
+                "failed to prune logs"
+            ),
+        }
+    }
+}
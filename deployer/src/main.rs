@@ -1,8 +1,11 @@
-use std::process::exit;
+use std::{process::exit, time::Duration};
 
 use clap::Parser;
 use shuttle_common::backends::tracing::setup_tracing;
-use shuttle_deployer::{start, start_proxy, Args, DeployLayer, Persistence, RuntimeManager};
+use shuttle_deployer::{
+    start, start_proxy, Args, DeployLayer, Limits, Persistence, Replicas, RequestCounter,
+    RuntimeManager,
+};
 use tokio::select;
 use tracing::{error, trace};
 use tracing_subscriber::prelude::*;
@@ -26,13 +29,22 @@ async fn main() {
         args.provisioner_address.uri().to_string(),
         Some(args.auth_uri.to_string()),
         persistence.get_log_sender(),
+        Limits {
+            memory: args.memory_limit,
+            cpu: args.cpu_limit,
+            cgroup_root: args.cgroup_root.clone(),
+        },
+        Duration::from_secs(args.drain_timeout),
     );
 
+    let replicas = Replicas::default();
+    let requests = RequestCounter::default();
+
     select! {
-        _ = start_proxy(args.proxy_address, args.proxy_fqdn.clone(), persistence.clone()) => {
+        _ = start_proxy(args.proxy_address, args.proxy_fqdn.clone(), persistence.clone(), replicas.clone(), requests.clone()) => {
             error!("Proxy stopped.")
         },
-        _ = start(persistence, runtime_manager, args) => {
+        _ = start(persistence, runtime_manager, replicas, requests, args) => {
             error!("Deployment service stopped.")
         },
     }
@@ -0,0 +1,130 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::Utc;
+use sysinfo::{Pid, PidExt, ProcessExt, System, SystemExt};
+use tokio::time::MissedTickBehavior;
+use tracing::{error, trace};
+
+use crate::persistence::{Persistence, Sample};
+use crate::RuntimeManager;
+
+/// How often the usage of the running deployments is sampled
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How many days samples are kept, which is the longest window metrics can be asked for
+const RETENTION_DAYS: i64 = 7;
+
+/// Counts the requests the proxy served for each service, between samples
+#[derive(Clone, Default)]
+pub struct RequestCounter(Arc<Mutex<HashMap<String, u64>>>);
+
+impl RequestCounter {
+    pub fn record(&self, service_name: &str) {
+        *self
+            .0
+            .lock()
+            .unwrap()
+            .entry(service_name.to_string())
+            .or_default() += 1;
+    }
+
+    /// Get the requests served for a service since the last time they were taken
+    pub fn take(&self, service_name: &str) -> u64 {
+        self.0
+            .lock()
+            .unwrap()
+            .remove(service_name)
+            .unwrap_or_default()
+    }
+}
+
+/// Keep sampling the CPU and memory of the runtimes of the running deployments, along with the
+/// requests they served, and drop the samples which are too old to be asked for
+pub async fn sample(
+    persistence: Persistence,
+    runtime_manager: Arc<tokio::sync::Mutex<RuntimeManager>>,
+    requests: RequestCounter,
+) {
+    let mut system = System::new();
+    let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+
+        let pids = runtime_manager.lock().await.pids();
+        let deployments = match persistence.get_all_runnable_deployments().await {
+            Ok(deployments) => deployments,
+            Err(error) => {
+                error!(
+                    error = &error as &dyn std::error::Error,
+                    "failed to get the running deployments to sample"
+                );
+                continue;
+            }
+        };
+
+        for deployment in deployments {
+            let Some(pid) = pids.get(&deployment.id).copied().map(Pid::from_u32) else {
+                continue;
+            };
+
+            if !system.refresh_process(pid) {
+                trace!(id = %deployment.id, "runtime of deployment is gone");
+                continue;
+            }
+
+            let Some(process) = system.process(pid) else {
+                continue;
+            };
+
+            let sample = Sample {
+                id: deployment.id,
+                timestamp: Utc::now(),
+                cpu: process.cpu_usage().into(),
+                memory: process.memory() as i64,
+                requests: requests.take(&deployment.service_name) as i64,
+            };
+
+            if let Err(error) = persistence.insert_sample(&sample).await {
+                error!(
+                    error = &error as &dyn std::error::Error,
+                    "failed to insert metrics sample"
+                );
+            }
+        }
+
+        if let Err(error) = persistence
+            .delete_samples_before(Utc::now() - chrono::Duration::days(RETENTION_DAYS))
+            .await
+        {
+            error!(
+                error = &error as &dyn std::error::Error,
+                "failed to delete old metrics samples"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RequestCounter;
+
+    #[test]
+    fn count_requests() {
+        let counter = RequestCounter::default();
+
+        counter.record("my-app");
+        counter.record("my-app");
+        counter.record("my-app--staging");
+
+        assert_eq!(counter.take("my-app"), 2);
+        assert_eq!(counter.take("my-app"), 0);
+        assert_eq!(counter.clone().take("my-app--staging"), 1);
+        assert_eq!(counter.take("other"), 0);
+    }
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use chrono::{TimeZone, Utc};
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_histogram_vec, register_int_gauge, register_int_gauge_vec, Encoder, HistogramVec,
+    IntGauge, IntGaugeVec, TextEncoder,
+};
+
+use crate::persistence::{Persistence, PersistenceError, State};
+
+/// How long the builds of deployments took, by whether they were `built` or `failed`
+static BUILD_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "shuttle_deployer_build_duration_seconds",
+        "How long the builds of deployments took",
+        &["outcome"],
+        // Builds take from a few seconds when they are cached to many minutes when they are not
+        vec![5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1200.0, 1800.0]
+    )
+    .expect("to register the build duration metric")
+});
+
+static QUEUE_DEPTH: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "shuttle_deployer_queue_depth",
+        "How many deployments are queued to be built"
+    )
+    .expect("to register the queue depth metric")
+});
+
+static ACTIVE_DEPLOYMENTS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "shuttle_deployer_active_deployments",
+        "How many deployments are running"
+    )
+    .expect("to register the active deployments metric")
+});
+
+static RESTARTS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "shuttle_deployer_deployment_restarts",
+        "How many times each running deployment was started again",
+        &["deployment_id", "service"]
+    )
+    .expect("to register the restarts metric")
+});
+
+static LOG_STORE_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "shuttle_deployer_log_store_bytes",
+        "How many bytes the stored logs of deployments take up"
+    )
+    .expect("to register the log store size metric")
+});
+
+/// Record how long a build took, and whether it built
+pub fn observe_build(duration: Duration, built: bool) {
+    BUILD_DURATION
+        .with_label_values(&[if built { "built" } else { "failed" }])
+        .observe(duration.as_secs_f64());
+}
+
+/// Measure what the deployer is doing, and render it with the builds which were recorded in the
+/// Prometheus text format
+pub async fn render(persistence: &Persistence) -> Result<String, PersistenceError> {
+    QUEUE_DEPTH.set(persistence.get_state_count(State::Queued).await?.into());
+
+    let running = persistence.get_all_runnable_deployments().await?;
+    ACTIVE_DEPLOYMENTS.set(running.len() as i64);
+
+    // Deployments which stopped are dropped, so that their labels do not pile up
+    RESTARTS.reset();
+    let epoch = Utc.timestamp_opt(0, 0).unwrap();
+    for deployment in running {
+        let restarts = persistence.get_restart_count(&deployment.id, epoch).await?;
+        RESTARTS
+            .with_label_values(&[&deployment.id.to_string(), &deployment.service_name])
+            .set(restarts.into());
+    }
+
+    LOG_STORE_BYTES.set(persistence.get_log_size().await? as i64);
+
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .expect("to encode the metrics");
+
+    Ok(String::from_utf8(buffer).expect("metrics to be UTF-8"))
+}
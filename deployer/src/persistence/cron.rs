@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use strum::Display;
+use uuid::Uuid;
+
+/// A time a deployment ran one of its scheduled jobs
+#[derive(sqlx::FromRow, Clone, Debug, PartialEq)]
+pub struct CronRun {
+    pub id: Uuid,
+    pub name: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub status: CronStatus,
+    pub error: Option<String>,
+}
+
+/// How the run of a scheduled job went
+#[derive(sqlx::Type, Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum CronStatus {
+    /// The job is still running
+    Running,
+
+    /// The endpoint of the job answered with a success status
+    Succeeded,
+
+    /// The endpoint of the job could not be reached or answered with an error
+    Failed,
+
+    /// The job was not run because its previous run was still going
+    Skipped,
+}
+
+impl From<CronStatus> for shuttle_common::models::cron::Status {
+    fn from(status: CronStatus) -> Self {
+        match status {
+            CronStatus::Running => Self::Running,
+            CronStatus::Succeeded => Self::Succeeded,
+            CronStatus::Failed => Self::Failed,
+            CronStatus::Skipped => Self::Skipped,
+        }
+    }
+}
+
+impl From<CronRun> for shuttle_common::models::cron::Run {
+    fn from(run: CronRun) -> Self {
+        Self {
+            deployment_id: run.id,
+            name: run.name,
+            started_at: run.started_at,
+            finished_at: run.finished_at,
+            status: run.status.into(),
+            error: run.error,
+        }
+    }
+}
@@ -7,7 +7,7 @@ use tracing::error;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-use super::state::State;
+use super::{cron::CronRun, state::State};
 
 #[derive(Clone, Debug, Eq, PartialEq, ToSchema)]
 pub struct Deployment {
@@ -17,6 +17,16 @@ pub struct Deployment {
     pub last_update: DateTime<Utc>,
     pub address: Option<SocketAddr>,
     pub is_next: bool,
+    pub git_commit_id: Option<String>,
+    pub git_commit_msg: Option<String>,
+    pub git_branch: Option<String>,
+    pub git_dirty: Option<bool>,
+    /// A hash of the files which were deployed, to tell whether they changed since
+    pub source_hash: Option<String>,
+    /// How often the deployment was killed for using more memory than it may
+    pub oom_kills: u32,
+    /// The trace the deployment was made in, when traces are being exported
+    pub trace_id: Option<String>,
 }
 
 impl FromRow<'_, SqliteRow> for Deployment {
@@ -40,6 +50,13 @@ impl FromRow<'_, SqliteRow> for Deployment {
             last_update: row.try_get("last_update")?,
             address,
             is_next: row.try_get("is_next")?,
+            git_commit_id: row.try_get("git_commit_id")?,
+            git_commit_msg: row.try_get("git_commit_msg")?,
+            git_branch: row.try_get("git_branch")?,
+            git_dirty: row.try_get("git_dirty")?,
+            source_hash: row.try_get("source_hash")?,
+            oom_kills: row.try_get("oom_kills")?,
+            trace_id: row.try_get("trace_id")?,
         })
     }
 }
@@ -51,6 +68,15 @@ impl From<Deployment> for shuttle_common::models::deployment::Response {
             service_id: deployment.service_id,
             state: deployment.state.into(),
             last_update: deployment.last_update,
+            git_commit_id: deployment.git_commit_id,
+            git_commit_msg: deployment.git_commit_msg,
+            git_branch: deployment.git_branch,
+            git_dirty: deployment.git_dirty,
+            source_hash: deployment.source_hash,
+            oom_kills: deployment.oom_kills,
+            trace_id: deployment.trace_id,
+            health: None,
+            transitions: Vec::new(),
         }
     }
 }
@@ -65,6 +91,26 @@ pub trait DeploymentUpdater: Clone + Send + Sync + 'static {
 
     /// Set if a deployment is build on shuttle-next
     async fn set_is_next(&self, id: &Uuid, is_next: bool) -> Result<(), Self::Err>;
+
+    /// Count that a deployment was killed for using more memory than it may
+    async fn record_oom_kill(&self, id: &Uuid) -> Result<(), Self::Err>;
+
+    /// Record how a run of a scheduled job of a deployment went so far
+    async fn record_cron_run(&self, run: &CronRun) -> Result<(), Self::Err>;
+
+    /// Send the requests to a service to one of its deployments from now on, getting the deployment
+    /// they were sent to before
+    async fn switch_route(&self, service_id: &Uuid, id: &Uuid) -> Result<Option<Uuid>, Self::Err>;
+}
+
+/// Which deployments of a service to list
+#[derive(Clone, Debug, Default)]
+pub struct DeploymentFilter {
+    pub state: Option<State>,
+    /// Only the deployments last updated after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only the deployments last updated up to this time
+    pub until: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -74,6 +120,23 @@ pub struct DeploymentState {
     pub last_update: DateTime<Utc>,
 }
 
+/// A time a deployment got into a new state
+#[derive(sqlx::FromRow, Clone, Debug, PartialEq, Eq)]
+pub struct StateTransition {
+    pub id: Uuid,
+    pub state: State,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl From<StateTransition> for shuttle_common::models::deployment::Transition {
+    fn from(transition: StateTransition) -> Self {
+        Self {
+            state: transition.state.into(),
+            timestamp: transition.timestamp,
+        }
+    }
+}
+
 #[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
 pub struct DeploymentRunnable {
     pub id: Uuid,
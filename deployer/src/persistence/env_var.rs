@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+#[async_trait::async_trait]
+/// Get the environment variables to start the runtime of a service with
+pub trait EnvVarGetter: Clone + Send + Sync + 'static {
+    type Err: std::error::Error + Send + Sync;
+
+    async fn get_env_vars(&self, service_id: &Uuid) -> Result<Vec<EnvVar>, Self::Err>;
+}
+
+#[derive(sqlx::FromRow, Debug, Eq, PartialEq)]
+pub struct EnvVar {
+    pub service_id: Uuid,
+    pub key: String,
+    pub value: String,
+    pub last_update: DateTime<Utc>,
+}
+
+impl From<EnvVar> for shuttle_common::models::env::Response {
+    fn from(env_var: EnvVar) -> Self {
+        Self {
+            key: env_var.key,
+            value: env_var.value,
+            last_update: env_var.last_update,
+        }
+    }
+}
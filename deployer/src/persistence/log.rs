@@ -15,6 +15,7 @@ pub struct Log {
     pub line: Option<u32>,
     pub target: String,
     pub fields: serde_json::Value,
+    pub source: Source,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, sqlx::Type)]
@@ -26,6 +27,46 @@ pub enum Level {
     Error,
 }
 
+impl Level {
+    /// This level and the levels which are more severe than it
+    pub fn and_above(&self) -> impl Iterator<Item = Level> {
+        let level = self.clone();
+
+        [
+            Level::Trace,
+            Level::Debug,
+            Level::Info,
+            Level::Warn,
+            Level::Error,
+        ]
+        .into_iter()
+        .skip_while(move |other| *other != level)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, sqlx::Type)]
+pub enum Source {
+    Tracing,
+    Stdout,
+    Stderr,
+    Access,
+}
+
+/// Which logs of a deployment to get
+#[derive(Clone, Debug, Default)]
+pub struct LogFilter {
+    /// Only the logs after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only the logs up to this time
+    pub until: Option<DateTime<Utc>>,
+    /// Only the logs at this level or a more severe one
+    pub level: Option<Level>,
+    /// Only the logs of this tracing target, or of the modules in it
+    pub target: Option<String>,
+    /// Only the logs from this source
+    pub source: Option<Source>,
+}
+
 impl From<Log> for Option<shuttle_common::LogItem> {
     fn from(log: Log) -> Self {
         if log.state == State::Building {
@@ -45,6 +86,7 @@ impl From<Log> for Option<shuttle_common::LogItem> {
                     line: log.line,
                     target: log.target,
                     fields: serde_json::to_vec(&json!({ "message": msg })).unwrap(),
+                    source: log.source.into(),
                 };
 
                 return Some(item);
@@ -66,6 +108,7 @@ impl From<Log> for shuttle_common::LogItem {
             line: log.line,
             target: log.target,
             fields: serde_json::to_vec(&log.fields).unwrap(),
+            source: log.source.into(),
         }
     }
 }
@@ -94,6 +137,28 @@ impl From<shuttle_common::log::Level> for Level {
     }
 }
 
+impl From<Source> for shuttle_common::log::Source {
+    fn from(source: Source) -> Self {
+        match source {
+            Source::Tracing => Self::Tracing,
+            Source::Stdout => Self::Stdout,
+            Source::Stderr => Self::Stderr,
+            Source::Access => Self::Access,
+        }
+    }
+}
+
+impl From<shuttle_common::log::Source> for Source {
+    fn from(source: shuttle_common::log::Source) -> Self {
+        match source {
+            shuttle_common::log::Source::Tracing => Self::Tracing,
+            shuttle_common::log::Source::Stdout => Self::Stdout,
+            shuttle_common::log::Source::Stderr => Self::Stderr,
+            shuttle_common::log::Source::Access => Self::Access,
+        }
+    }
+}
+
 fn extract_message(fields: &Value) -> Option<String> {
     if let Value::Object(ref map) = fields {
         if let Some(message) = map.get("build_line") {
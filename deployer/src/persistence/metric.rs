@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// A sample of the usage of a running deployment
+#[derive(sqlx::FromRow, Clone, Debug, PartialEq)]
+pub struct Sample {
+    pub id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub cpu: f64,
+    pub memory: i64,
+    pub requests: i64,
+}
+
+impl From<Sample> for shuttle_common::models::metrics::Sample {
+    fn from(sample: Sample) -> Self {
+        Self {
+            timestamp: sample.timestamp,
+            cpu: sample.cpu,
+            memory: sample.memory as u64,
+            requests: sample.requests as u64,
+        }
+    }
+}
@@ -1,11 +1,15 @@
+mod cron;
 pub mod deployment;
+mod env_var;
 mod error;
 pub mod log;
+mod metric;
 mod resource;
 mod secret;
 pub mod service;
 mod state;
 mod user;
+mod webhook;
 
 use crate::deployment::deploy_layer::{self, LogRecorder, LogType};
 use crate::deployment::ActiveDeploymentsGetter;
@@ -13,37 +17,56 @@ use crate::proxy::AddressGetter;
 use error::{Error, Result};
 use sqlx::QueryBuilder;
 
+use std::collections::HashSet;
 use std::net::SocketAddr;
 use std::path::Path;
 use std::str::FromStr;
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde_json::json;
+use shuttle_common::models::{artifacts, deployment::Approval, service::Routing};
 use shuttle_common::STATE_MESSAGE;
 use sqlx::migrate::{MigrateDatabase, Migrator};
 use sqlx::sqlite::{Sqlite, SqliteConnectOptions, SqliteJournalMode, SqlitePool};
+use sqlx::types::Json;
+use sqlx::{Executor, Transaction};
 use tokio::sync::broadcast::{self, Receiver, Sender};
+use tokio::sync::oneshot;
 use tokio::task::JoinHandle;
 use tracing::{error, info, instrument, trace};
 use uuid::Uuid;
 
+pub use self::cron::{CronRun, CronStatus};
 use self::deployment::DeploymentRunnable;
-pub use self::deployment::{Deployment, DeploymentState, DeploymentUpdater};
+pub use self::deployment::{
+    Deployment, DeploymentFilter, DeploymentState, DeploymentUpdater, StateTransition,
+};
+pub use self::env_var::{EnvVar, EnvVarGetter};
 pub use self::error::Error as PersistenceError;
-pub use self::log::{Level as LogLevel, Log};
+pub use self::log::{Level as LogLevel, Log, LogFilter, Source as LogSource};
+pub use self::metric::Sample;
 pub use self::resource::{Resource, ResourceManager, Type as ResourceType};
 pub use self::secret::{Secret, SecretGetter, SecretRecorder};
 pub use self::service::Service;
 pub use self::state::State;
 pub use self::user::User;
+pub use self::webhook::Webhook;
 
 pub static MIGRATIONS: Migrator = sqlx::migrate!("./migrations");
 
+/// The size of a log in the log store, which is what counts towards the log quota
+const LOG_SIZE: &str = "LENGTH(fields) + LENGTH(target) + IFNULL(LENGTH(file), 0)";
+
+/// How many state changes the subscribers to them can fall behind by before they miss some
+const STATE_BUFFER_SIZE: usize = 256;
+
 #[derive(Clone)]
 pub struct Persistence {
     pool: SqlitePool,
     log_send: crossbeam_channel::Sender<deploy_layer::Log>,
+    flush_send: crossbeam_channel::Sender<oneshot::Sender<()>>,
     stream_log_send: Sender<deploy_layer::Log>,
+    state_send: Sender<deploy_layer::Log>,
 }
 
 impl Persistence {
@@ -89,16 +112,34 @@ impl Persistence {
 
         let (log_send, log_recv): (crossbeam_channel::Sender<deploy_layer::Log>, _) =
             crossbeam_channel::bounded(0);
+        let (flush_send, flush_recv) = crossbeam_channel::unbounded::<oneshot::Sender<()>>();
 
         let (stream_log_send, _) = broadcast::channel(1);
         let stream_log_send_clone = stream_log_send.clone();
+        let (state_send, _) = broadcast::channel(STATE_BUFFER_SIZE);
+        let state_send_clone = state_send.clone();
 
         let pool_cloned = pool.clone();
 
         // The logs are received on a non-async thread.
         // This moves them to an async thread
         let handle = tokio::spawn(async move {
-            while let Ok(log) = log_recv.recv() {
+            loop {
+                // A log is only handed over once the previous one is written, so a flush is
+                // answered after every log recorded before it
+                let log = crossbeam_channel::select! {
+                    recv(log_recv) -> log => log,
+                    recv(flush_recv) -> flushed => {
+                        if let Ok(flushed) = flushed {
+                            let _ = flushed.send(());
+                        }
+                        continue;
+                    }
+                };
+                let Ok(log) = log else {
+                    break;
+                };
+
                 trace!(?log, "persistence received got log");
                 match log.r#type {
                     LogType::Event => {
@@ -123,6 +164,7 @@ impl Persistence {
                                 line: log.line,
                                 target: String::new(),
                                 fields: json!(STATE_MESSAGE),
+                                source: LogSource::Tracing,
                             },
                         )
                         .await
@@ -140,6 +182,9 @@ impl Persistence {
                                     "failed to update deployment state"
                                 )
                             });
+
+                        // Nobody listening is fine
+                        let _ = state_send_clone.send(log.clone());
                     }
                 };
 
@@ -162,7 +207,9 @@ impl Persistence {
         let persistence = Self {
             pool,
             log_send,
+            flush_send,
             stream_log_send,
+            state_send,
         };
 
         (persistence, handle)
@@ -170,9 +217,10 @@ impl Persistence {
 
     pub async fn insert_deployment(&self, deployment: impl Into<Deployment>) -> Result<()> {
         let deployment = deployment.into();
+        let mut transaction = self.pool.begin().await?;
 
         sqlx::query(
-            "INSERT INTO deployments (id, service_id, state, last_update, address, is_next) VALUES (?, ?, ?, ?, ?, ?)",
+            "INSERT INTO deployments (id, service_id, state, last_update, address, is_next, git_commit_id, git_commit_msg, git_branch, git_dirty, source_hash, trace_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(deployment.id)
         .bind(deployment.service_id)
@@ -180,10 +228,25 @@ impl Persistence {
         .bind(deployment.last_update)
         .bind(deployment.address.map(|socket| socket.to_string()))
         .bind(deployment.is_next)
-        .execute(&self.pool)
-        .await
-        .map(|_| ())
-        .map_err(Error::from)
+        .bind(deployment.git_commit_id)
+        .bind(deployment.git_commit_msg)
+        .bind(deployment.git_branch)
+        .bind(deployment.git_dirty)
+        .bind(deployment.source_hash)
+        .bind(deployment.trace_id)
+        .execute(&mut transaction)
+        .await?;
+        insert_state_transition(
+            &mut transaction,
+            &DeploymentState {
+                id: deployment.id,
+                state: deployment.state,
+                last_update: deployment.last_update,
+            },
+        )
+        .await?;
+
+        transaction.commit().await.map_err(Error::from)
     }
 
     pub async fn get_deployment(&self, id: &Uuid) -> Result<Option<Deployment>> {
@@ -193,13 +256,26 @@ impl Persistence {
     pub async fn get_deployments(
         &self,
         service_id: &Uuid,
+        filter: &DeploymentFilter,
         offset: u32,
         limit: u32,
     ) -> Result<Vec<Deployment>> {
         let mut query = QueryBuilder::new("SELECT * FROM deployments WHERE service_id = ");
+        query.push_bind(service_id);
+
+        if let Some(state) = filter.state {
+            query.push(" AND state = ").push_bind(state);
+        }
+
+        if let Some(since) = filter.since {
+            query.push(" AND last_update > ").push_bind(since);
+        }
+
+        if let Some(until) = filter.until {
+            query.push(" AND last_update <= ").push_bind(until);
+        }
 
         query
-            .push_bind(service_id)
             .push(" ORDER BY last_update DESC LIMIT ")
             .push_bind(limit);
 
@@ -223,18 +299,303 @@ impl Persistence {
             .map_err(Error::from)
     }
 
-    // Clean up all invalid states inside persistence
-    pub async fn cleanup_invalid_states(&self) -> Result<()> {
-        sqlx::query("UPDATE deployments SET state = ? WHERE state IN(?, ?, ?, ?)")
-            .bind(State::Stopped)
-            .bind(State::Queued)
-            .bind(State::Built)
-            .bind(State::Building)
-            .bind(State::Loading)
+    /// Get the deployments of a service which ran without crashing and were stopped since, most
+    /// recently stopped first. These are the ones it can be rolled back to.
+    pub async fn get_previous_deployments(&self, service_id: &Uuid) -> Result<Vec<Deployment>> {
+        sqlx::query_as(
+            "SELECT * FROM deployments WHERE service_id = ? AND state IN(?, ?) ORDER BY last_update DESC",
+        )
+        .bind(service_id)
+        .bind(State::Stopped)
+        .bind(State::Completed)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::from)
+    }
+
+    /// Remove a secret from a service, returning it if there was one with this key
+    pub async fn delete_secret(&self, service_id: &Uuid, key: &str) -> Result<Option<Secret>> {
+        sqlx::query_as("DELETE FROM secrets WHERE service_id = ? AND key = ? RETURNING *")
+            .bind(service_id)
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Set an environment variable of a service, replacing its value if it was set already
+    pub async fn set_env_var(&self, service_id: &Uuid, key: &str, value: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO env_vars (service_id, key, value, last_update) VALUES (?, ?, ?, ?)",
+        )
+        .bind(service_id)
+        .bind(key)
+        .bind(value)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+        .map_err(Error::from)
+    }
+
+    /// Remove an environment variable from a service, returning it if it was set
+    pub async fn delete_env_var(&self, service_id: &Uuid, key: &str) -> Result<Option<EnvVar>> {
+        sqlx::query_as("DELETE FROM env_vars WHERE service_id = ? AND key = ? RETURNING *")
+            .bind(service_id)
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Get the retention of artifacts an admin chose for this project, if they chose one
+    pub async fn get_artifact_retention(&self) -> Result<Option<artifacts::Retention>> {
+        let retention: Option<(u32, Option<i64>)> =
+            sqlx::query_as("SELECT keep_deployments, max_bytes FROM artifact_retention")
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(
+            retention.map(|(keep_deployments, max_bytes)| artifacts::Retention {
+                keep_deployments,
+                max_bytes: max_bytes.map(|max_bytes| max_bytes as u64),
+            }),
+        )
+    }
+
+    /// Keep the artifacts of this project by `retention` instead of the retention of the deployer
+    pub async fn set_artifact_retention(&self, retention: &artifacts::Retention) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO artifact_retention (id, keep_deployments, max_bytes, last_update) VALUES (0, ?, ?, ?)",
+        )
+        .bind(retention.keep_deployments)
+        .bind(retention.max_bytes.map(|max_bytes| max_bytes as i64))
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+        .map_err(Error::from)
+    }
+
+    /// Go back to keeping the artifacts of this project by the retention of the deployer
+    pub async fn delete_artifact_retention(&self) -> Result<()> {
+        sqlx::query("DELETE FROM artifact_retention")
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(Error::from)
+    }
+
+    /// Get whether the deployments of this project have to be approved before they run
+    pub async fn get_approval(&self) -> Result<Approval> {
+        let required: Option<bool> = sqlx::query_scalar("SELECT required FROM approval")
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(Approval {
+            required: required.unwrap_or_default(),
+        })
+    }
+
+    /// Choose whether the deployments of this project have to be approved before they run
+    pub async fn set_approval(&self, approval: &Approval) -> Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO approval (id, required, last_update) VALUES (0, ?, ?)")
+            .bind(approval.required)
+            .bind(Utc::now())
             .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(Error::from)
+    }
+
+    /// Get how many replicas every service which was scaled runs, and how they take turns, by the
+    /// id of the service
+    pub async fn get_all_replicas(&self) -> Result<Vec<(Uuid, u32, Routing)>> {
+        let replicas: Vec<(Uuid, u32, Option<Json<Routing>>)> =
+            sqlx::query_as("SELECT service_id, count, routing FROM replicas")
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(replicas
+            .into_iter()
+            .map(|(service_id, count, routing)| {
+                (service_id, count, routing.map(|r| r.0).unwrap_or_default())
+            })
+            .collect())
+    }
+
+    /// Serve the running deployment of a service by `count` runtimes taking turns by `routing`
+    /// from now on
+    pub async fn set_replicas(
+        &self,
+        service_id: &Uuid,
+        count: u32,
+        routing: &Routing,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO replicas (service_id, count, routing, last_update) VALUES (?, ?, ?, ?)",
+        )
+        .bind(service_id)
+        .bind(count)
+        .bind(Json(routing))
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+        .map_err(Error::from)
+    }
+
+    /// Register a webhook for this project
+    pub async fn insert_webhook(&self, webhook: &Webhook) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO webhooks (id, url, secret, events, created_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(webhook.id)
+        .bind(&webhook.url)
+        .bind(&webhook.secret)
+        .bind(&webhook.events)
+        .bind(webhook.created_at)
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+        .map_err(Error::from)
+    }
+
+    /// Get the webhooks of this project, in the order they were registered
+    pub async fn get_webhooks(&self) -> Result<Vec<Webhook>> {
+        sqlx::query_as("SELECT * FROM webhooks ORDER BY created_at")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Remove a webhook, returning it if it was registered
+    pub async fn delete_webhook(&self, id: &Uuid) -> Result<Option<Webhook>> {
+        sqlx::query_as("DELETE FROM webhooks WHERE id = ? RETURNING *")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Error::from)
+    }
+
+    pub async fn delete_resource(
+        &self,
+        service_id: &Uuid,
+        r#type: ResourceType,
+    ) -> Result<Option<Resource>> {
+        sqlx::query_as("DELETE FROM resources WHERE service_id = ? AND type = ? RETURNING *")
+            .bind(service_id)
+            .bind(r#type)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Settle the deployments which were on their way to running when the deployer stopped, and
+    /// get those to start again, oldest first.
+    ///
+    /// Deployments which were queued or building are failed, since their archives are gone with
+    /// the deployer. Those which were built or loading are started again when `is_built` finds
+    /// their executable, unless a newer deployment of their service runs or is started again, in
+    /// which case they are stopped. The reason is kept in the logs of every deployment.
+    pub async fn recover_deployments(
+        &self,
+        is_built: impl Fn(&Uuid) -> bool,
+    ) -> Result<Vec<DeploymentRunnable>> {
+        let deployments: Vec<(Uuid, Uuid, State)> = sqlx::query_as(
+            r#"SELECT id, service_id, state FROM deployments
+                WHERE state IN (?, ?, ?, ?, ?)
+                ORDER BY last_update DESC, id"#,
+        )
+        .bind(State::Queued)
+        .bind(State::Building)
+        .bind(State::Built)
+        .bind(State::Loading)
+        .bind(State::Running)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut claimed_services = HashSet::new();
+        let now = Utc::now();
+
+        for (id, service_id, state) in deployments {
+            let (state, reason) = match state {
+                State::Running => {
+                    claimed_services.insert(service_id);
+                    continue;
+                }
+                State::Queued | State::Building => (
+                    State::Crashed,
+                    "the deployer restarted before this deployment was built, so it has to be deployed again",
+                ),
+                _ if !is_built(&id) => (
+                    State::Crashed,
+                    "the deployer restarted and lost the build of this deployment, so it has to be deployed again",
+                ),
+                _ if claimed_services.contains(&service_id) => (
+                    State::Stopped,
+                    "the deployer restarted while this deployment was starting, and a newer deployment of this service runs instead",
+                ),
+                _ => {
+                    claimed_services.insert(service_id);
+
+                    (
+                        State::Built,
+                        "the deployer restarted while this deployment was starting, so it is started again",
+                    )
+                }
+            };
+
+            info!(%id, %state, reason, "recovering deployment");
+
+            let mut transaction = self.pool.begin().await?;
+            set_state(
+                &mut transaction,
+                &DeploymentState {
+                    id,
+                    state,
+                    last_update: now,
+                },
+            )
             .await?;
+            insert_log(
+                &mut transaction,
+                Log {
+                    id,
+                    timestamp: now,
+                    state,
+                    level: LogLevel::Warn,
+                    file: None,
+                    line: None,
+                    target: module_path!().to_string(),
+                    fields: json!({ "message": reason }),
+                    source: LogSource::Tracing,
+                },
+            )
+            .await?;
+            transaction.commit().await?;
+        }
+
+        sqlx::query_as(
+            r#"SELECT d.id, service_id, s.name AS service_name, d.is_next
+                FROM deployments AS d
+                JOIN services AS s ON s.id = d.service_id
+                WHERE state = ?
+                ORDER BY last_update, d.id"#,
+        )
+        .bind(State::Built)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::from)
+    }
 
-        Ok(())
+    /// Get every state deployment `id` was in, in the order it got into them
+    pub async fn get_state_transitions(&self, id: &Uuid) -> Result<Vec<StateTransition>> {
+        sqlx::query_as("SELECT * FROM state_transitions WHERE id = ? ORDER BY timestamp")
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(Error::from)
     }
 
     pub async fn get_or_create_service(&self, name: &str) -> Result<Service> {
@@ -256,6 +617,14 @@ impl Persistence {
         }
     }
 
+    pub async fn get_service(&self, id: &Uuid) -> Result<Option<Service>> {
+        sqlx::query_as("SELECT * FROM services WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Error::from)
+    }
+
     pub async fn get_service_by_name(&self, name: &str) -> Result<Option<Service>> {
         sqlx::query_as("SELECT * FROM services WHERE name = ?")
             .bind(name)
@@ -280,6 +649,16 @@ impl Persistence {
             .map_err(Error::from)
     }
 
+    /// Count the deployments which are in `state`
+    pub async fn get_state_count(&self, state: State) -> Result<u32> {
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM deployments WHERE state = ?")
+            .bind(state)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count as u32)
+    }
+
     pub async fn get_all_runnable_deployments(&self) -> Result<Vec<DeploymentRunnable>> {
         sqlx::query_as(
             r#"SELECT d.id, service_id, s.name AS service_name, d.is_next
@@ -294,9 +673,176 @@ impl Persistence {
         .map_err(Error::from)
     }
 
-    pub(crate) async fn get_deployment_logs(&self, id: &Uuid) -> Result<Vec<Log>> {
-        // TODO: stress this a bit
-        get_deployment_logs(&self.pool, id).await
+    pub async fn insert_sample(&self, sample: &Sample) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO metrics (id, timestamp, cpu, memory, requests) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(sample.id)
+        .bind(sample.timestamp)
+        .bind(sample.cpu)
+        .bind(sample.memory)
+        .bind(sample.requests)
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+        .map_err(Error::from)
+    }
+
+    /// Get the samples taken of a deployment after `since`, oldest first
+    pub async fn get_samples(&self, id: &Uuid, since: DateTime<Utc>) -> Result<Vec<Sample>> {
+        sqlx::query_as("SELECT * FROM metrics WHERE id = ? AND timestamp > ? ORDER BY timestamp")
+            .bind(id)
+            .bind(since)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Remove the samples which were taken before `before`, of any deployment
+    pub async fn delete_samples_before(&self, before: DateTime<Utc>) -> Result<()> {
+        sqlx::query("DELETE FROM metrics WHERE timestamp <= ?")
+            .bind(before)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(Error::from)
+    }
+
+    /// Get the latest runs of the scheduled jobs of a service, or of its job called `name`, newest
+    /// first
+    pub async fn get_cron_runs(
+        &self,
+        service_id: &Uuid,
+        name: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<CronRun>> {
+        let mut query = QueryBuilder::new(
+            "SELECT c.* FROM cron_runs AS c JOIN deployments AS d ON d.id = c.id WHERE d.service_id = ",
+        );
+        query.push_bind(service_id);
+
+        if let Some(name) = name {
+            query.push(" AND c.name = ").push_bind(name.to_string());
+        }
+
+        query
+            .push(" ORDER BY c.started_at DESC LIMIT ")
+            .push_bind(limit)
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Count how many times a deployment was started again after `since`, which leaves out the
+    /// first time it was started
+    pub async fn get_restart_count(&self, id: &Uuid, since: DateTime<Utc>) -> Result<u32> {
+        let starts: Vec<(DateTime<Utc>,)> = sqlx::query_as(
+            "SELECT timestamp FROM logs WHERE id = ? AND state = ? AND fields = ? ORDER BY timestamp",
+        )
+        .bind(id)
+        .bind(State::Running)
+        .bind(json!(STATE_MESSAGE))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(starts
+            .into_iter()
+            .skip(1)
+            .filter(|(timestamp,)| *timestamp > since)
+            .count() as u32)
+    }
+
+    /// Get the logs of a deployment which pass `filter`, oldest first
+    pub(crate) async fn get_filtered_logs(
+        &self,
+        id: &Uuid,
+        filter: &LogFilter,
+    ) -> Result<Vec<Log>> {
+        let mut query = QueryBuilder::new("SELECT * FROM logs WHERE id = ");
+        query.push_bind(id);
+
+        if let Some(since) = filter.since {
+            query.push(" AND timestamp > ").push_bind(since);
+        }
+
+        if let Some(until) = filter.until {
+            query.push(" AND timestamp <= ").push_bind(until);
+        }
+
+        if let Some(level) = &filter.level {
+            query.push(" AND level IN (");
+
+            let mut levels = query.separated(", ");
+            for level in level.and_above() {
+                levels.push_bind(level);
+            }
+            levels.push_unseparated(")");
+        }
+
+        if let Some(target) = &filter.target {
+            let modules = format!("{target}::");
+
+            query
+                .push(" AND (target = ")
+                .push_bind(target.clone())
+                .push(" OR substr(target, 1, length(")
+                .push_bind(modules.clone())
+                .push(")) = ")
+                .push_bind(modules)
+                .push(")");
+        }
+
+        if let Some(source) = filter.source {
+            query.push(" AND source = ").push_bind(source);
+        }
+
+        query
+            .push(" ORDER BY timestamp")
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Get how many bytes the stored logs take up, measured the same way as for the log quota
+    pub async fn get_log_size(&self) -> Result<u64> {
+        let (size,): (i64,) =
+            sqlx::query_as(&format!("SELECT IFNULL(SUM({LOG_SIZE}), 0) FROM logs"))
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(size as u64)
+    }
+
+    /// Remove the logs from before `before`, and then the oldest logs until the rest take up at
+    /// most `max_size` bytes. Gets how many logs were removed.
+    pub async fn prune_logs(&self, before: DateTime<Utc>, max_size: u64) -> Result<u64> {
+        let expired = sqlx::query("DELETE FROM logs WHERE timestamp <= ?")
+            .bind(before)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        // Sum up the sizes of the logs from the newest one, to find the newest log which no
+        // longer fits
+        let over_quota = sqlx::query(&format!(
+            r#"DELETE FROM logs WHERE timestamp <= (
+                SELECT timestamp FROM (
+                    SELECT timestamp, SUM({LOG_SIZE}) OVER (ORDER BY timestamp DESC) AS size
+                    FROM logs
+                )
+                WHERE size > ?
+                ORDER BY timestamp DESC
+                LIMIT 1
+            )"#
+        ))
+        .bind(max_size as i64)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(expired + over_quota)
     }
 
     /// Get a broadcast channel for listening to logs that are being stored into persistence
@@ -304,24 +850,63 @@ impl Persistence {
         self.stream_log_send.subscribe()
     }
 
+    /// Get a broadcast channel for listening to the deployments getting into new states, once those
+    /// are stored
+    pub fn get_state_subscriber(&self) -> Receiver<deploy_layer::Log> {
+        self.state_send.subscribe()
+    }
+
     /// Returns a sender for sending logs to persistence storage
     pub fn get_log_sender(&self) -> crossbeam_channel::Sender<deploy_layer::Log> {
         self.log_send.clone()
     }
+
+    /// Wait until every log recorded so far has been written
+    pub async fn flush_logs(&self) {
+        let (flushed_send, flushed_recv) = oneshot::channel();
+
+        if self.flush_send.send(flushed_send).is_ok() {
+            let _ = flushed_recv.await;
+        }
+    }
 }
 
 async fn update_deployment(pool: &SqlitePool, state: impl Into<DeploymentState>) -> Result<()> {
-    let state = state.into();
+    let mut transaction = pool.begin().await?;
 
+    set_state(&mut transaction, &state.into()).await?;
+
+    transaction.commit().await.map_err(Error::from)
+}
+
+/// Move a deployment to a new state, keeping the transition
+async fn set_state(
+    transaction: &mut Transaction<'_, Sqlite>,
+    state: &DeploymentState,
+) -> Result<()> {
     sqlx::query("UPDATE deployments SET state = ?, last_update = ? WHERE id = ?")
         .bind(state.state)
         .bind(state.last_update)
         .bind(state.id)
-        .execute(pool)
-        .await
-        .map(|_| ())
-        .map_err(Error::from)
-}
+        .execute(&mut *transaction)
+        .await?;
+
+    insert_state_transition(transaction, state).await
+}
+
+async fn insert_state_transition(
+    transaction: &mut Transaction<'_, Sqlite>,
+    state: &DeploymentState,
+) -> Result<()> {
+    sqlx::query("INSERT OR IGNORE INTO state_transitions (id, state, timestamp) VALUES (?, ?, ?)")
+        .bind(state.id)
+        .bind(state.state)
+        .bind(state.last_update)
+        .execute(transaction)
+        .await
+        .map(|_| ())
+        .map_err(Error::from)
+}
 
 async fn get_deployment(pool: &SqlitePool, id: &Uuid) -> Result<Option<Deployment>> {
     sqlx::query_as("SELECT * FROM deployments WHERE id = ?")
@@ -331,10 +916,13 @@ async fn get_deployment(pool: &SqlitePool, id: &Uuid) -> Result<Option<Deploymen
         .map_err(Error::from)
 }
 
-async fn insert_log(pool: &SqlitePool, log: impl Into<Log>) -> Result<()> {
+async fn insert_log<'c>(
+    executor: impl Executor<'c, Database = Sqlite>,
+    log: impl Into<Log>,
+) -> Result<()> {
     let log = log.into();
 
-    sqlx::query("INSERT INTO logs (id, timestamp, state, level, file, line, target, fields) VALUES (?, ?, ?, ?, ?, ?, ?, ?)")
+    sqlx::query("INSERT INTO logs (id, timestamp, state, level, file, line, target, fields, source) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")
         .bind(log.id)
         .bind(log.timestamp)
         .bind(log.state)
@@ -343,20 +931,13 @@ async fn insert_log(pool: &SqlitePool, log: impl Into<Log>) -> Result<()> {
         .bind(log.line)
         .bind(log.target)
         .bind(log.fields)
-        .execute(pool)
+        .bind(log.source)
+        .execute(executor)
         .await
         .map(|_| ())
         .map_err(Error::from)
 }
 
-async fn get_deployment_logs(pool: &SqlitePool, id: &Uuid) -> Result<Vec<Log>> {
-    sqlx::query_as("SELECT * FROM logs WHERE id = ? ORDER BY timestamp")
-        .bind(id)
-        .fetch_all(pool)
-        .await
-        .map_err(Error::from)
-}
-
 impl LogRecorder for Persistence {
     fn record(&self, log: deploy_layer::Log) {
         self.log_send
@@ -424,6 +1005,19 @@ impl SecretGetter for Persistence {
     }
 }
 
+#[async_trait::async_trait]
+impl EnvVarGetter for Persistence {
+    type Err = Error;
+
+    async fn get_env_vars(&self, service_id: &Uuid) -> Result<Vec<EnvVar>> {
+        sqlx::query_as("SELECT * FROM env_vars WHERE service_id = ? ORDER BY key")
+            .bind(service_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(Error::from)
+    }
+}
+
 #[async_trait::async_trait]
 impl AddressGetter for Persistence {
     #[instrument(skip(self))]
@@ -433,10 +1027,10 @@ impl AddressGetter for Persistence {
     ) -> crate::handlers::Result<Option<std::net::SocketAddr>> {
         let address_str = sqlx::query_as::<_, (String,)>(
             r#"SELECT d.address
-                FROM deployments AS d
-                JOIN services AS s ON d.service_id = s.id
-                WHERE s.name = ? AND d.state = ?
-                ORDER BY d.last_update"#,
+                FROM routes AS r
+                JOIN deployments AS d ON r.deployment_id = d.id
+                JOIN services AS s ON r.service_id = s.id
+                WHERE s.name = ? AND d.state = ?"#,
         )
         .bind(service_name)
         .bind(State::Running)
@@ -457,6 +1051,26 @@ impl AddressGetter for Persistence {
             Ok(None)
         }
     }
+
+    async fn get_state_for_service(
+        &self,
+        service_name: &str,
+    ) -> crate::handlers::Result<Option<State>> {
+        sqlx::query_as::<_, (State,)>(
+            r#"SELECT d.state
+                FROM deployments AS d
+                JOIN services AS s ON d.service_id = s.id
+                WHERE s.name = ?
+                ORDER BY d.last_update DESC
+                LIMIT 1"#,
+        )
+        .bind(service_name)
+        .fetch_optional(&self.pool)
+        .await
+        .map(|state| state.map(|(state,)| state))
+        .map_err(Error::from)
+        .map_err(crate::handlers::Error::Persistence)
+    }
 }
 
 #[async_trait::async_trait]
@@ -482,6 +1096,55 @@ impl DeploymentUpdater for Persistence {
             .map(|_| ())
             .map_err(Error::from)
     }
+
+    async fn record_oom_kill(&self, id: &Uuid) -> Result<()> {
+        sqlx::query("UPDATE deployments SET oom_kills = oom_kills + 1 WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(Error::from)
+    }
+
+    async fn record_cron_run(&self, run: &CronRun) -> Result<()> {
+        sqlx::query(
+            r#"INSERT INTO cron_runs (id, name, started_at, finished_at, status, error) VALUES (?, ?, ?, ?, ?, ?)
+                ON CONFLICT (id, name, started_at) DO UPDATE SET finished_at = excluded.finished_at, status = excluded.status, error = excluded.error"#,
+        )
+        .bind(run.id)
+        .bind(&run.name)
+        .bind(run.started_at)
+        .bind(run.finished_at)
+        .bind(run.status)
+        .bind(&run.error)
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+        .map_err(Error::from)
+    }
+
+    async fn switch_route(&self, service_id: &Uuid, id: &Uuid) -> Result<Option<Uuid>> {
+        let mut transaction = self.pool.begin().await?;
+
+        let previous =
+            sqlx::query_as::<_, (Uuid,)>("SELECT deployment_id FROM routes WHERE service_id = ?")
+                .bind(service_id)
+                .fetch_optional(&mut transaction)
+                .await?;
+
+        sqlx::query(
+            r#"INSERT INTO routes (service_id, deployment_id) VALUES (?, ?)
+                ON CONFLICT (service_id) DO UPDATE SET deployment_id = excluded.deployment_id"#,
+        )
+        .bind(service_id)
+        .bind(id)
+        .execute(&mut transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(previous.map(|(previous,)| previous))
+    }
 }
 
 #[async_trait::async_trait]
@@ -515,6 +1178,8 @@ mod tests {
     use chrono::{Duration, TimeZone, Utc};
     use rand::Rng;
     use serde_json::json;
+    use shuttle_common::models::webhook::Event;
+    use sqlx::types::Json;
 
     use super::*;
     use crate::persistence::{
@@ -536,6 +1201,13 @@ mod tests {
             last_update: Utc.with_ymd_and_hms(2022, 4, 25, 4, 43, 33).unwrap(),
             address: None,
             is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
         };
         let address = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 12345);
 
@@ -555,11 +1227,14 @@ mod tests {
 
         p.set_address(&id, &address).await.unwrap();
         p.set_is_next(&id, true).await.unwrap();
+        p.record_oom_kill(&id).await.unwrap();
+        p.record_oom_kill(&id).await.unwrap();
 
         let update = p.get_deployment(&id).await.unwrap().unwrap();
         assert_eq!(update.state, State::Built);
         assert_eq!(update.address, Some(address));
         assert!(update.is_next);
+        assert_eq!(update.oom_kills, 2);
         assert_ne!(
             update.last_update,
             Utc.with_ymd_and_hms(2022, 4, 25, 4, 43, 33).unwrap()
@@ -579,6 +1254,13 @@ mod tests {
                 last_update: Utc::now(),
                 address: None,
                 is_next: false,
+                git_commit_id: None,
+                git_commit_msg: None,
+                git_branch: None,
+                git_dirty: None,
+                source_hash: None,
+                oom_kills: 0,
+                trace_id: None,
             })
             .collect();
 
@@ -589,14 +1271,54 @@ mod tests {
         // Reverse to match last_updated desc order
         deployments.reverse();
         assert_eq!(
-            p.get_deployments(&service_id, 0, 5).await.unwrap(),
+            p.get_deployments(&service_id, &Default::default(), 0, 5)
+                .await
+                .unwrap(),
             deployments[0..5]
         );
         assert_eq!(
-            p.get_deployments(&service_id, 5, 5).await.unwrap(),
+            p.get_deployments(&service_id, &Default::default(), 5, 5)
+                .await
+                .unwrap(),
             deployments[5..10]
         );
-        assert_eq!(p.get_deployments(&service_id, 20, 5).await.unwrap(), vec![]);
+        assert_eq!(
+            p.get_deployments(&service_id, &Default::default(), 20, 5)
+                .await
+                .unwrap(),
+            vec![]
+        );
+
+        let crashed = Deployment {
+            id: Uuid::new_v4(),
+            state: State::Crashed,
+            last_update: Utc::now(),
+            ..deployments[0].clone()
+        };
+        p.insert_deployment(crashed.clone()).await.unwrap();
+
+        let filter = DeploymentFilter {
+            state: Some(State::Crashed),
+            ..Default::default()
+        };
+        assert_eq!(
+            p.get_deployments(&service_id, &filter, 0, u32::MAX)
+                .await
+                .unwrap(),
+            vec![crashed.clone()]
+        );
+
+        let filter = DeploymentFilter {
+            since: Some(deployments[2].last_update),
+            until: Some(deployments[1].last_update),
+            ..Default::default()
+        };
+        assert_eq!(
+            p.get_deployments(&service_id, &filter, 0, u32::MAX)
+                .await
+                .unwrap(),
+            deployments[1..2]
+        );
     }
 
     #[tokio::test(flavor = "multi_thread")]
@@ -613,6 +1335,13 @@ mod tests {
             last_update: Utc.with_ymd_and_hms(2022, 4, 25, 7, 29, 35).unwrap(),
             address: None,
             is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
         };
         let deployment_stopped = Deployment {
             id: Uuid::new_v4(),
@@ -621,6 +1350,13 @@ mod tests {
             last_update: Utc.with_ymd_and_hms(2022, 4, 25, 7, 49, 35).unwrap(),
             address: None,
             is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
         };
         let deployment_other = Deployment {
             id: Uuid::new_v4(),
@@ -629,6 +1365,13 @@ mod tests {
             last_update: Utc.with_ymd_and_hms(2022, 4, 25, 7, 39, 39).unwrap(),
             address: None,
             is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
         };
         let deployment_running = Deployment {
             id: Uuid::new_v4(),
@@ -637,6 +1380,13 @@ mod tests {
             last_update: Utc.with_ymd_and_hms(2022, 4, 25, 7, 48, 29).unwrap(),
             address: Some(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 9876)),
             is_next: true,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
         };
 
         for deployment in [
@@ -652,6 +1402,14 @@ mod tests {
             p.get_active_deployment(&xyz_id).await.unwrap().unwrap(),
             deployment_running
         );
+        assert_eq!(
+            p.get_previous_deployments(&xyz_id).await.unwrap(),
+            vec![deployment_stopped]
+        );
+        assert_eq!(
+            p.get_previous_deployments(&service_id).await.unwrap(),
+            vec![]
+        );
     }
 
     #[tokio::test(flavor = "multi_thread")]
@@ -668,6 +1426,13 @@ mod tests {
             last_update: Utc.with_ymd_and_hms(2023, 4, 17, 1, 1, 2).unwrap(),
             address: None,
             is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
         };
         let deployment_crashed = Deployment {
             id: Uuid::new_v4(),
@@ -676,6 +1441,13 @@ mod tests {
             last_update: Utc.with_ymd_and_hms(2023, 4, 17, 1, 1, 2).unwrap(), // second
             address: None,
             is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
         };
         let deployment_stopped = Deployment {
             id: Uuid::new_v4(),
@@ -684,6 +1456,13 @@ mod tests {
             last_update: Utc.with_ymd_and_hms(2023, 4, 17, 1, 1, 1).unwrap(), // first
             address: None,
             is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
         };
         let deployment_running = Deployment {
             id: Uuid::new_v4(),
@@ -692,6 +1471,13 @@ mod tests {
             last_update: Utc.with_ymd_and_hms(2023, 4, 17, 1, 1, 3).unwrap(), // third
             address: Some(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 9876)),
             is_next: true,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
         };
 
         for deployment in [
@@ -703,23 +1489,26 @@ mod tests {
             p.insert_deployment(deployment.clone()).await.unwrap();
         }
 
-        let actual = p.get_deployments(&service_id, 0, u32::MAX).await.unwrap();
+        let actual = p
+            .get_deployments(&service_id, &Default::default(), 0, u32::MAX)
+            .await
+            .unwrap();
         let expected = vec![deployment_running, deployment_crashed, deployment_stopped];
 
         assert_eq!(actual, expected, "deployments should be sorted by time");
     }
 
-    // Test that we are correctly cleaning up any stale / unexpected states for a deployment
-    // The reason this does not clean up two (or more) running states for a single deployment is because
-    // it should theoretically be impossible for a service to have two deployments in the running state.
-    // And even if a service were to have this, then the start ups of these deployments (more specifically
-    // the last deployment that is starting up) will stop all the deployments correctly.
+    // Test that the deployments which were on their way to running when the deployer stopped are
+    // settled. This does not stop two (or more) running deployments of a single service, because
+    // the start up of the last one stops the others anyway.
     #[tokio::test(flavor = "multi_thread")]
-    async fn cleanup_invalid_states() {
+    async fn recover_deployments() {
         let (p, _) = Persistence::new_in_memory().await;
 
-        let service_id = add_service(&p.pool).await.unwrap();
-        let time = Utc::now();
+        let service_id = add_service_named(&p.pool, "service").await.unwrap();
+        let other_service_id = add_service_named(&p.pool, "other-service").await.unwrap();
+        // The deployments last changed before the deployer restarted
+        let time = Utc::now() - Duration::minutes(1);
 
         let deployment_crashed = Deployment {
             id: Uuid::new_v4(),
@@ -728,6 +1517,13 @@ mod tests {
             last_update: time,
             address: None,
             is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
         };
         let deployment_stopped = Deployment {
             id: Uuid::new_v4(),
@@ -736,6 +1532,13 @@ mod tests {
             last_update: time.checked_add_signed(Duration::seconds(1)).unwrap(),
             address: None,
             is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
         };
         let deployment_running = Deployment {
             id: Uuid::new_v4(),
@@ -744,6 +1547,13 @@ mod tests {
             last_update: time.checked_add_signed(Duration::seconds(2)).unwrap(),
             address: Some(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 9876)),
             is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
         };
         let deployment_queued = Deployment {
             id: Uuid::new_v4(),
@@ -752,6 +1562,13 @@ mod tests {
             last_update: time.checked_add_signed(Duration::seconds(3)).unwrap(),
             address: None,
             is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
         };
         let deployment_building = Deployment {
             id: Uuid::new_v4(),
@@ -760,6 +1577,13 @@ mod tests {
             last_update: time.checked_add_signed(Duration::seconds(4)).unwrap(),
             address: None,
             is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
         };
         let deployment_built = Deployment {
             id: Uuid::new_v4(),
@@ -768,6 +1592,13 @@ mod tests {
             last_update: time.checked_add_signed(Duration::seconds(5)).unwrap(),
             address: None,
             is_next: true,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
         };
         let deployment_loading = Deployment {
             id: Uuid::new_v4(),
@@ -776,9 +1607,67 @@ mod tests {
             last_update: time.checked_add_signed(Duration::seconds(6)).unwrap(),
             address: None,
             is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
+        };
+
+        // A build which is gone cannot be started again, and an older deployment which was starting
+        // up should not take over from the one running
+        let deployment_lost = Deployment {
+            id: Uuid::new_v4(),
+            service_id: other_service_id,
+            state: State::Built,
+            last_update: time.checked_add_signed(Duration::seconds(7)).unwrap(),
+            address: None,
+            is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
+        };
+        let deployment_other_loading = Deployment {
+            id: Uuid::new_v4(),
+            service_id: other_service_id,
+            state: State::Loading,
+            last_update: time,
+            address: None,
+            is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
+        };
+        let deployment_other_running = Deployment {
+            id: Uuid::new_v4(),
+            service_id: other_service_id,
+            state: State::Running,
+            last_update: time.checked_add_signed(Duration::seconds(1)).unwrap(),
+            address: Some(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 9877)),
+            is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
         };
 
         for deployment in [
+            &deployment_lost,
+            &deployment_other_loading,
+            &deployment_other_running,
             &deployment_crashed,
             &deployment_stopped,
             &deployment_running,
@@ -790,28 +1679,146 @@ mod tests {
             p.insert_deployment(deployment.clone()).await.unwrap();
         }
 
-        p.cleanup_invalid_states().await.unwrap();
+        let is_built = |id: &Uuid| {
+            [
+                deployment_built.id,
+                deployment_loading.id,
+                deployment_other_loading.id,
+            ]
+            .contains(id)
+        };
+        let resumed = p.recover_deployments(is_built).await.unwrap();
+        assert_eq!(
+            resumed,
+            vec![DeploymentRunnable {
+                id: deployment_loading.id,
+                service_name: "service".to_string(),
+                service_id,
+                is_next: false,
+            }]
+        );
 
-        let actual: Vec<_> = p
-            .get_deployments(&service_id, 0, u32::MAX)
+        let mut actual: Vec<_> = p
+            .get_deployments(&service_id, &Default::default(), 0, u32::MAX)
             .await
             .unwrap()
             .into_iter()
             .map(|deployment| (deployment.id, deployment.state))
             .collect();
-        let expected = vec![
-            (deployment_loading.id, State::Stopped),
+        let mut expected = vec![
+            (deployment_loading.id, State::Built),
             (deployment_built.id, State::Stopped),
-            (deployment_building.id, State::Stopped),
-            (deployment_queued.id, State::Stopped),
+            (deployment_building.id, State::Crashed),
+            (deployment_queued.id, State::Crashed),
             (deployment_running.id, State::Running),
             (deployment_stopped.id, State::Stopped),
             (deployment_crashed.id, State::Crashed),
         ];
+        // Settling a deployment updates it, so the order of the deployments changes
+        actual.sort_by_key(|(id, _)| *id);
+        expected.sort_by_key(|(id, _)| *id);
+        assert_eq!(actual, expected);
+
+        let mut actual: Vec<_> = p
+            .get_deployments(&other_service_id, &Default::default(), 0, u32::MAX)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|deployment| (deployment.id, deployment.state))
+            .collect();
+        let mut expected = vec![
+            (deployment_lost.id, State::Crashed),
+            (deployment_other_running.id, State::Running),
+            (deployment_other_loading.id, State::Stopped),
+        ];
+        actual.sort_by_key(|(id, _)| *id);
+        expected.sort_by_key(|(id, _)| *id);
+        assert_eq!(actual, expected);
+
+        // Why a deployment was settled is in its logs, and the change in its transitions
+        let logs = p
+            .get_filtered_logs(&deployment_lost.id, &Default::default())
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].state, State::Crashed);
+        assert_eq!(
+            p.get_state_transitions(&deployment_lost.id)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|transition| transition.state)
+                .collect::<Vec<_>>(),
+            vec![State::Built, State::Crashed]
+        );
+
+        // Running the recovery again changes nothing
+        assert_eq!(p.recover_deployments(is_built).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn state_transitions() {
+        let (p, _) = Persistence::new_in_memory().await;
+        let id = Uuid::new_v4();
+        let start = Utc::now();
+
+        p.insert_deployment(Deployment {
+            id,
+            service_id: add_service(&p.pool).await.unwrap(),
+            state: State::Running,
+            last_update: start - Duration::seconds(1),
+            address: None,
+            is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
+        })
+        .await
+        .unwrap();
+
+        for (offset, state) in [State::Queued, State::Building, State::Built, State::Loading]
+            .into_iter()
+            .enumerate()
+        {
+            p.record(deploy_layer::Log {
+                id,
+                timestamp: start + Duration::seconds(offset as i64),
+                state,
+                level: Level::Info,
+                file: None,
+                line: None,
+                target: String::new(),
+                fields: json!(STATE_MESSAGE),
+                r#type: LogType::State,
+                source: LogSource::Tracing,
+            });
+        }
+
+        // Wait for the states to be written
+        p.flush_logs().await;
+
+        let transitions: Vec<_> = p
+            .get_state_transitions(&id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|transition| transition.state)
+            .collect();
 
+        // The first transition is the state the deployment was added with
         assert_eq!(
-            actual, expected,
-            "invalid states should be moved to the stopped state"
+            transitions,
+            vec![
+                State::Running,
+                State::Queued,
+                State::Building,
+                State::Built,
+                State::Loading
+            ]
         );
     }
     #[tokio::test(flavor = "multi_thread")]
@@ -835,6 +1842,13 @@ mod tests {
                 last_update: Utc.with_ymd_and_hms(2022, 4, 25, 4, 29, 33).unwrap(),
                 address: None,
                 is_next: false,
+                git_commit_id: None,
+                git_commit_msg: None,
+                git_branch: None,
+                git_dirty: None,
+                source_hash: None,
+                oom_kills: 0,
+                trace_id: None,
             },
             Deployment {
                 id: id_1,
@@ -843,6 +1857,13 @@ mod tests {
                 last_update: Utc.with_ymd_and_hms(2022, 4, 25, 4, 29, 44).unwrap(),
                 address: None,
                 is_next: false,
+                git_commit_id: None,
+                git_commit_msg: None,
+                git_branch: None,
+                git_dirty: None,
+                source_hash: None,
+                oom_kills: 0,
+                trace_id: None,
             },
             Deployment {
                 id: id_2,
@@ -851,6 +1872,13 @@ mod tests {
                 last_update: Utc.with_ymd_and_hms(2022, 4, 25, 4, 33, 48).unwrap(),
                 address: None,
                 is_next: true,
+                git_commit_id: None,
+                git_commit_msg: None,
+                git_branch: None,
+                git_dirty: None,
+                source_hash: None,
+                oom_kills: 0,
+                trace_id: None,
             },
             Deployment {
                 id: Uuid::new_v4(),
@@ -859,6 +1887,13 @@ mod tests {
                 last_update: Utc.with_ymd_and_hms(2022, 4, 25, 4, 38, 52).unwrap(),
                 address: None,
                 is_next: true,
+                git_commit_id: None,
+                git_commit_msg: None,
+                git_branch: None,
+                git_dirty: None,
+                source_hash: None,
+                oom_kills: 0,
+                trace_id: None,
             },
             Deployment {
                 id: id_3,
@@ -867,6 +1902,13 @@ mod tests {
                 last_update: Utc.with_ymd_and_hms(2022, 4, 25, 4, 42, 32).unwrap(),
                 address: None,
                 is_next: false,
+                git_commit_id: None,
+                git_commit_msg: None,
+                git_branch: None,
+                git_dirty: None,
+                source_hash: None,
+                oom_kills: 0,
+                trace_id: None,
             },
         ] {
             p.insert_deployment(deployment).await.unwrap();
@@ -896,6 +1938,10 @@ mod tests {
                 },
             ]
         );
+
+        assert_eq!(p.get_state_count(State::Running).await.unwrap(), 3);
+        assert_eq!(p.get_state_count(State::Built).await.unwrap(), 1);
+        assert_eq!(p.get_state_count(State::Queued).await.unwrap(), 0);
     }
 
     #[tokio::test(flavor = "multi_thread")]
@@ -912,11 +1958,15 @@ mod tests {
             line: Some(12),
             target: "tests::log_insert".to_string(),
             fields: json!({"message": "job queued"}),
+            source: LogSource::Tracing,
         };
 
         insert_log(&p.pool, log.clone()).await.unwrap();
 
-        let logs = p.get_deployment_logs(&deployment_id).await.unwrap();
+        let logs = p
+            .get_filtered_logs(&deployment_id, &Default::default())
+            .await
+            .unwrap();
         assert!(!logs.is_empty(), "there should be one log");
 
         assert_eq!(logs.first().unwrap(), &log);
@@ -937,6 +1987,7 @@ mod tests {
             line: Some(5),
             target: "tests::logs_for_deployment".to_string(),
             fields: json!({"message": "job queued"}),
+            source: LogSource::Tracing,
         };
         let log_b = Log {
             id: deployment_b,
@@ -947,6 +1998,7 @@ mod tests {
             line: Some(5),
             target: "tests::logs_for_deployment".to_string(),
             fields: json!({"message": "job queued"}),
+            source: LogSource::Tracing,
         };
         let log_a2 = Log {
             id: deployment_a,
@@ -957,21 +2009,203 @@ mod tests {
             line: None,
             target: String::new(),
             fields: json!({"message": "unused Result"}),
+            source: LogSource::Tracing,
         };
 
         for log in [log_a1.clone(), log_b, log_a2.clone()] {
             insert_log(&p.pool, log).await.unwrap();
         }
 
-        let logs = p.get_deployment_logs(&deployment_a).await.unwrap();
+        let logs = p
+            .get_filtered_logs(&deployment_a, &Default::default())
+            .await
+            .unwrap();
         assert!(!logs.is_empty(), "there should be two logs");
 
         assert_eq!(logs, vec![log_a1, log_a2]);
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn filtered_logs() {
+        let (p, _) = Persistence::new_in_memory().await;
+        let id = add_deployment(&p.pool).await.unwrap();
+        let start = Utc::now();
+        let log = |seconds, level, target: &str| Log {
+            id,
+            timestamp: start + Duration::seconds(seconds),
+            state: State::Running,
+            level,
+            file: None,
+            line: None,
+            target: target.to_string(),
+            fields: json!({"message": "tick"}),
+            source: LogSource::Tracing,
+        };
+
+        let app = log(1, Level::Info, "app");
+        let db_error = log(2, Level::Error, "app::db");
+        let dbx_warn = log(3, Level::Warn, "app::dbx");
+        let db_debug = log(4, Level::Debug, "app::db::pool");
+        let printed = Log {
+            source: LogSource::Stdout,
+            ..log(5, Level::Info, "")
+        };
+
+        for log in [
+            app.clone(),
+            db_error.clone(),
+            dbx_warn.clone(),
+            db_debug.clone(),
+            printed.clone(),
+        ] {
+            insert_log(&p.pool, log).await.unwrap();
+        }
+
+        let filter = |filter: LogFilter| {
+            let p = p.clone();
+            async move { p.get_filtered_logs(&id, &filter).await.unwrap() }
+        };
+
+        assert_eq!(
+            filter(LogFilter {
+                level: Some(Level::Warn),
+                ..Default::default()
+            })
+            .await,
+            vec![db_error.clone(), dbx_warn.clone()]
+        );
+        assert_eq!(
+            filter(LogFilter {
+                target: Some("app::db".to_string()),
+                ..Default::default()
+            })
+            .await,
+            vec![db_error.clone(), db_debug]
+        );
+        assert_eq!(
+            filter(LogFilter {
+                since: Some(app.timestamp),
+                until: Some(dbx_warn.timestamp),
+                ..Default::default()
+            })
+            .await,
+            vec![db_error, dbx_warn]
+        );
+        assert_eq!(
+            filter(LogFilter {
+                source: Some(LogSource::Stdout),
+                ..Default::default()
+            })
+            .await,
+            vec![printed]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn prune_logs() {
+        let (p, _) = Persistence::new_in_memory().await;
+        let id = add_deployment(&p.pool).await.unwrap();
+        let start = Utc::now() - Duration::days(2);
+        let fields = json!({"message": "tick"});
+        let size = (fields.to_string().len() + "app".len()) as u64;
+        let logs: Vec<_> = (0..4)
+            .map(|day| Log {
+                id,
+                timestamp: start + Duration::hours(day * 12),
+                state: State::Running,
+                level: Level::Info,
+                file: None,
+                line: None,
+                target: "app".to_string(),
+                fields: fields.clone(),
+                source: LogSource::Tracing,
+            })
+            .collect();
+
+        assert_eq!(p.get_log_size().await.unwrap(), 0);
+
+        for log in logs.iter().cloned() {
+            insert_log(&p.pool, log).await.unwrap();
+        }
+
+        assert_eq!(p.get_log_size().await.unwrap(), 4 * size);
+
+        // The first log is too old, and then only the two newest fit
+        let removed = p
+            .prune_logs(start + Duration::hours(6), 2 * size)
+            .await
+            .unwrap();
+        assert_eq!(removed, 2);
+        assert_eq!(
+            p.get_filtered_logs(&id, &Default::default()).await.unwrap(),
+            logs[2..].to_vec()
+        );
+
+        assert_eq!(p.prune_logs(start, 2 * size).await.unwrap(), 0);
+        assert_eq!(p.get_log_size().await.unwrap(), 2 * size);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cron_runs() {
+        let (p, _) = Persistence::new_in_memory().await;
+        let id = add_deployment(&p.pool).await.unwrap();
+        let service_id = get_deployment(&p.pool, &id)
+            .await
+            .unwrap()
+            .unwrap()
+            .service_id;
+        let start = Utc.with_ymd_and_hms(2023, 5, 1, 3, 0, 0).unwrap();
+
+        let mut cleanup = CronRun {
+            id,
+            name: "cleanup".to_string(),
+            started_at: start,
+            finished_at: None,
+            status: CronStatus::Running,
+            error: None,
+        };
+        let report = CronRun {
+            id,
+            name: "report".to_string(),
+            started_at: start + Duration::minutes(1),
+            finished_at: None,
+            status: CronStatus::Skipped,
+            error: None,
+        };
+
+        p.record_cron_run(&cleanup).await.unwrap();
+        p.record_cron_run(&report).await.unwrap();
+
+        // Finishing a run updates it instead of adding another one
+        cleanup.finished_at = Some(start + Duration::seconds(5));
+        cleanup.status = CronStatus::Failed;
+        cleanup.error = Some("/jobs/cleanup answered with 500".to_string());
+        p.record_cron_run(&cleanup).await.unwrap();
+
+        assert_eq!(
+            p.get_cron_runs(&service_id, None, 10).await.unwrap(),
+            vec![report.clone(), cleanup.clone()]
+        );
+        assert_eq!(
+            p.get_cron_runs(&service_id, Some("cleanup"), 10)
+                .await
+                .unwrap(),
+            vec![cleanup]
+        );
+        assert_eq!(
+            p.get_cron_runs(&service_id, None, 1).await.unwrap(),
+            vec![report]
+        );
+        assert!(p
+            .get_cron_runs(&Uuid::new_v4(), None, 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn log_recorder_event() {
-        let (p, handle) = Persistence::new_in_memory().await;
+        let (p, _) = Persistence::new_in_memory().await;
         let deployment_id = add_deployment(&p.pool).await.unwrap();
 
         let event = deploy_layer::Log {
@@ -984,15 +2218,18 @@ mod tests {
             target: "tests::log_recorder_event".to_string(),
             fields: json!({"message": "job queued"}),
             r#type: deploy_layer::LogType::Event,
+            source: LogSource::Tracing,
         };
 
         p.record(event);
 
-        // Drop channel and wait for it to finish
-        drop(p.log_send);
-        assert!(handle.await.is_ok());
+        // Wait for the log to be written
+        p.flush_logs().await;
 
-        let logs = get_deployment_logs(&p.pool, &deployment_id).await.unwrap();
+        let logs = p
+            .get_filtered_logs(&deployment_id, &Default::default())
+            .await
+            .unwrap();
 
         assert!(!logs.is_empty(), "there should be one log");
 
@@ -1007,7 +2244,7 @@ mod tests {
 
     #[tokio::test(flavor = "multi_thread")]
     async fn log_recorder_state() {
-        let (p, handle) = Persistence::new_in_memory().await;
+        let (p, _) = Persistence::new_in_memory().await;
 
         let id = Uuid::new_v4();
         let service_id = add_service(&p.pool).await.unwrap();
@@ -1019,6 +2256,13 @@ mod tests {
             last_update: Utc.with_ymd_and_hms(2022, 4, 29, 2, 39, 39).unwrap(),
             address: None,
             is_next: false,
+            git_commit_id: None,
+            git_commit_msg: None,
+            git_branch: None,
+            git_dirty: None,
+            source_hash: None,
+            oom_kills: 0,
+            trace_id: None,
         })
         .await
         .unwrap();
@@ -1032,15 +2276,15 @@ mod tests {
             target: String::new(),
             fields: serde_json::Value::Null,
             r#type: deploy_layer::LogType::State,
+            source: LogSource::Tracing,
         };
 
         p.record(state);
 
-        // Drop channel and wait for it to finish
-        drop(p.log_send);
-        assert!(handle.await.is_ok());
+        // Wait for the log to be written
+        p.flush_logs().await;
 
-        let logs = get_deployment_logs(&p.pool, &id).await.unwrap();
+        let logs = p.get_filtered_logs(&id, &Default::default()).await.unwrap();
 
         assert!(!logs.is_empty(), "state change should be logged");
 
@@ -1059,10 +2303,108 @@ mod tests {
                 last_update: Utc.with_ymd_and_hms(2022, 4, 29, 2, 39, 59).unwrap(),
                 address: None,
                 is_next: false,
+                git_commit_id: None,
+                git_commit_msg: None,
+                git_branch: None,
+                git_dirty: None,
+                source_hash: None,
+                oom_kills: 0,
+                trace_id: None,
             }
         );
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn metrics() {
+        let (p, _) = Persistence::new_in_memory().await;
+        let id = add_deployment(&p.pool).await.unwrap();
+        let now = Utc::now();
+
+        let samples: Vec<_> = (1..=3)
+            .rev()
+            .map(|minutes| Sample {
+                id,
+                timestamp: now - Duration::minutes(minutes * 10),
+                cpu: 12.5,
+                memory: 64 * 1024 * 1024,
+                requests: minutes,
+            })
+            .collect();
+
+        for sample in &samples {
+            p.insert_sample(sample).await.unwrap();
+        }
+
+        assert_eq!(
+            p.get_samples(&id, now - Duration::hours(1)).await.unwrap(),
+            samples
+        );
+        assert_eq!(
+            p.get_samples(&id, now - Duration::minutes(15))
+                .await
+                .unwrap(),
+            samples[2..]
+        );
+
+        p.delete_samples_before(now - Duration::minutes(15))
+            .await
+            .unwrap();
+        assert_eq!(
+            p.get_samples(&id, now - Duration::hours(1)).await.unwrap(),
+            samples[2..]
+        );
+
+        for hours in [3, 2, 1] {
+            insert_log(
+                &p.pool,
+                Log {
+                    id,
+                    timestamp: now - Duration::hours(hours),
+                    state: State::Running,
+                    level: Level::Info,
+                    file: None,
+                    line: None,
+                    target: String::new(),
+                    fields: json!(STATE_MESSAGE),
+                    source: LogSource::Tracing,
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        // Logs of a running deployment are not starts
+        insert_log(
+            &p.pool,
+            Log {
+                id,
+                timestamp: now - Duration::minutes(30),
+                state: State::Running,
+                level: Level::Info,
+                file: None,
+                line: None,
+                target: "tests::metrics".to_string(),
+                fields: json!({"message": "serving"}),
+                source: LogSource::Tracing,
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            p.get_restart_count(&id, now - Duration::days(1))
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            p.get_restart_count(&id, now - Duration::minutes(90))
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn deployment_resources() {
         let (p, _) = Persistence::new_in_memory().await;
@@ -1109,7 +2451,24 @@ mod tests {
 
         let resources = p.get_resources(&service_id).await.unwrap();
 
-        assert_eq!(resources, vec![resource2, resource4]);
+        assert_eq!(resources, vec![resource2.clone(), resource4]);
+
+        let deleted = p
+            .delete_resource(&service_id, resource3.r#type)
+            .await
+            .unwrap();
+        assert_eq!(deleted, None, "resources of other services are not deleted");
+
+        let deleted = p
+            .delete_resource(&service_id, resource2.r#type)
+            .await
+            .unwrap();
+        assert_eq!(deleted, Some(resource2));
+        assert_eq!(p.get_resources(&service_id).await.unwrap().len(), 1);
+        assert_eq!(
+            p.get_resources(&service_id2).await.unwrap(),
+            vec![resource3]
+        );
     }
 
     #[tokio::test(flavor = "multi_thread")]
@@ -1159,6 +2518,180 @@ mod tests {
         ];
 
         assert_eq!(actual, expected);
+
+        let deleted = p.delete_secret(&service_id, "key3").await.unwrap().unwrap();
+        assert_eq!(deleted.value, "value3");
+        assert_eq!(p.delete_secret(&service_id, "key3").await.unwrap(), None);
+        assert_eq!(p.delete_secret(&service_id, "key2").await.unwrap(), None);
+        assert_eq!(p.get_secrets(&service_id).await.unwrap().len(), 1);
+        assert_eq!(p.get_secrets(&service_id2).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn env_vars() {
+        let (p, _) = Persistence::new_in_memory().await;
+
+        let service_id = add_service(&p.pool).await.unwrap();
+        let service_id2 = add_service(&p.pool).await.unwrap();
+
+        p.set_env_var(&service_id, "LEVEL", "debug").await.unwrap();
+        p.set_env_var(&service_id2, "LEVEL", "info").await.unwrap();
+        p.set_env_var(&service_id, "GREETING", "hello")
+            .await
+            .unwrap();
+        p.set_env_var(&service_id, "LEVEL", "trace").await.unwrap();
+
+        let actual: Vec<_> = p
+            .get_env_vars(&service_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|mut i| {
+                // Reset dates for test
+                i.last_update = Default::default();
+                i
+            })
+            .collect();
+        let expected = vec![
+            EnvVar {
+                service_id,
+                key: "GREETING".to_string(),
+                value: "hello".to_string(),
+                last_update: Default::default(),
+            },
+            EnvVar {
+                service_id,
+                key: "LEVEL".to_string(),
+                value: "trace".to_string(),
+                last_update: Default::default(),
+            },
+        ];
+
+        assert_eq!(actual, expected);
+
+        let deleted = p
+            .delete_env_var(&service_id, "GREETING")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(deleted.value, "hello");
+        assert_eq!(
+            p.delete_env_var(&service_id, "GREETING").await.unwrap(),
+            None
+        );
+        assert_eq!(p.get_env_vars(&service_id).await.unwrap().len(), 1);
+        assert_eq!(p.get_env_vars(&service_id2).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn approval() {
+        let (p, _) = Persistence::new_in_memory().await;
+
+        assert_eq!(
+            p.get_approval().await.unwrap(),
+            Approval { required: false }
+        );
+
+        p.set_approval(&Approval { required: true }).await.unwrap();
+        assert_eq!(p.get_approval().await.unwrap(), Approval { required: true });
+
+        p.set_approval(&Approval { required: false }).await.unwrap();
+        assert_eq!(
+            p.get_approval().await.unwrap(),
+            Approval { required: false }
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn artifact_retention() {
+        let (p, _) = Persistence::new_in_memory().await;
+
+        assert_eq!(p.get_artifact_retention().await.unwrap(), None);
+
+        let retention = artifacts::Retention {
+            keep_deployments: 3,
+            max_bytes: Some(10 * 1024 * 1024 * 1024),
+        };
+        p.set_artifact_retention(&retention).await.unwrap();
+        assert_eq!(p.get_artifact_retention().await.unwrap(), Some(retention));
+
+        // Choosing another one replaces it
+        let retention = artifacts::Retention {
+            keep_deployments: 1,
+            max_bytes: None,
+        };
+        p.set_artifact_retention(&retention).await.unwrap();
+        assert_eq!(p.get_artifact_retention().await.unwrap(), Some(retention));
+
+        p.delete_artifact_retention().await.unwrap();
+        assert_eq!(p.get_artifact_retention().await.unwrap(), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn replicas() {
+        let (p, _) = Persistence::new_in_memory().await;
+        let service_id = add_service(&p.pool).await.unwrap();
+        let service_id2 = add_service(&p.pool).await.unwrap();
+
+        assert!(p.get_all_replicas().await.unwrap().is_empty());
+
+        let sticky = Routing::Sticky {
+            cookie: "session".to_string(),
+        };
+        p.set_replicas(&service_id, 3, &Routing::RoundRobin)
+            .await
+            .unwrap();
+        p.set_replicas(&service_id2, 2, &sticky).await.unwrap();
+
+        // Scaling again replaces the count
+        p.set_replicas(&service_id, 1, &Routing::RoundRobin)
+            .await
+            .unwrap();
+
+        let mut replicas = p.get_all_replicas().await.unwrap();
+        replicas.sort_by_key(|(_, count, _)| *count);
+        assert_eq!(
+            replicas,
+            vec![
+                (service_id, 1, Routing::RoundRobin),
+                (service_id2, 2, sticky)
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn webhooks() {
+        let (p, _) = Persistence::new_in_memory().await;
+
+        let all = Webhook {
+            id: Uuid::new_v4(),
+            url: "https://hooks.example.com/all".to_string(),
+            secret: "secret".to_string(),
+            events: Json(Vec::new()),
+            created_at: Utc.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap(),
+        };
+        let crashes = Webhook {
+            id: Uuid::new_v4(),
+            url: "https://hooks.example.com/crashes".to_string(),
+            secret: "other secret".to_string(),
+            events: Json(vec![Event::Crashed, Event::DeployFailed]),
+            created_at: Utc.with_ymd_and_hms(2023, 5, 2, 12, 0, 0).unwrap(),
+        };
+
+        p.insert_webhook(&crashes).await.unwrap();
+        p.insert_webhook(&all).await.unwrap();
+        assert_eq!(
+            p.get_webhooks().await.unwrap(),
+            vec![all.clone(), crashes.clone()]
+        );
+
+        assert!(all.wants(Event::Restarted));
+        assert!(crashes.wants(Event::Crashed));
+        assert!(!crashes.wants(Event::DeploySucceeded));
+
+        assert_eq!(p.delete_webhook(&all.id).await.unwrap(), Some(all.clone()));
+        assert_eq!(p.delete_webhook(&all.id).await.unwrap(), None);
+        assert_eq!(p.get_webhooks().await.unwrap(), vec![crashes]);
     }
 
     #[tokio::test(flavor = "multi_thread")]
@@ -1190,24 +2723,34 @@ mod tests {
         let (p, _) = Persistence::new_in_memory().await;
         let service_id = add_service_named(&p.pool, "service-name").await.unwrap();
         let service_other_id = add_service_named(&p.pool, "other-name").await.unwrap();
+        let blue_id = Uuid::new_v4();
+        let green_id = Uuid::new_v4();
+        let stopped_id = Uuid::new_v4();
+        let other_id = Uuid::new_v4();
 
         sqlx::query(
-            "INSERT INTO deployments (id, service_id, state, last_update, address) VALUES (?, ?, ?, ?, ?), (?, ?, ?, ?, ?), (?, ?, ?, ?, ?)",
+            "INSERT INTO deployments (id, service_id, state, last_update, address) VALUES (?, ?, ?, ?, ?), (?, ?, ?, ?, ?), (?, ?, ?, ?, ?), (?, ?, ?, ?, ?)",
         )
-        // This running item should match
-        .bind(Uuid::new_v4())
+        // The running deployment which serves the service now
+        .bind(blue_id)
         .bind(service_id)
         .bind(State::Running)
         .bind(Utc::now())
         .bind("10.0.0.5:12356")
+        // The running deployment which is switched to
+        .bind(green_id)
+        .bind(service_id)
+        .bind(State::Running)
+        .bind(Utc::now())
+        .bind("10.0.0.5:12357")
         // A stopped item should not match
-        .bind(Uuid::new_v4())
+        .bind(stopped_id)
         .bind(service_id)
         .bind(State::Stopped)
         .bind(Utc::now())
         .bind("10.0.0.5:9876")
         // Another service should not match
-        .bind(Uuid::new_v4())
+        .bind(other_id)
         .bind(service_other_id)
         .bind(State::Running)
         .bind(Utc::now())
@@ -1216,6 +2759,13 @@ mod tests {
         .await
         .unwrap();
 
+        assert_eq!(
+            p.get_address_for_service("service-name").await.unwrap(),
+            None
+        );
+
+        assert_eq!(p.switch_route(&service_id, &blue_id).await.unwrap(), None);
+        p.switch_route(&service_other_id, &other_id).await.unwrap();
         assert_eq!(
             SocketAddr::from(([10, 0, 0, 5], 12356)),
             p.get_address_for_service("service-name")
@@ -1223,6 +2773,54 @@ mod tests {
                 .unwrap()
                 .unwrap(),
         );
+
+        assert_eq!(
+            p.switch_route(&service_id, &green_id).await.unwrap(),
+            Some(blue_id)
+        );
+        assert_eq!(
+            SocketAddr::from(([10, 0, 0, 5], 12357)),
+            p.get_address_for_service("service-name")
+                .await
+                .unwrap()
+                .unwrap(),
+        );
+
+        // Requests are never sent to a deployment which stopped
+        p.switch_route(&service_id, &stopped_id).await.unwrap();
+        assert_eq!(
+            p.get_address_for_service("service-name").await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn state_getter() {
+        let (p, _) = Persistence::new_in_memory().await;
+        let service_id = add_service_named(&p.pool, "service-name").await.unwrap();
+
+        assert_eq!(p.get_state_for_service("service-name").await.unwrap(), None);
+
+        sqlx::query(
+            "INSERT INTO deployments (id, service_id, state, last_update) VALUES (?, ?, ?, ?), (?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(service_id)
+        .bind(State::Crashed)
+        .bind(Utc::now() - Duration::minutes(5))
+        .bind(Uuid::new_v4())
+        .bind(service_id)
+        .bind(State::Building)
+        .bind(Utc::now())
+        .execute(&p.pool)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            p.get_state_for_service("service-name").await.unwrap(),
+            Some(State::Building)
+        );
+        assert_eq!(p.get_state_for_service("other-name").await.unwrap(), None);
     }
 
     #[tokio::test(flavor = "multi_thread")]
@@ -1240,6 +2838,13 @@ mod tests {
                 last_update: Utc.with_ymd_and_hms(2022, 4, 25, 4, 29, 33).unwrap(),
                 address: None,
                 is_next: false,
+                git_commit_id: None,
+                git_commit_msg: None,
+                git_branch: None,
+                git_dirty: None,
+                source_hash: None,
+                oom_kills: 0,
+                trace_id: None,
             },
             Deployment {
                 id: Uuid::new_v4(),
@@ -1248,6 +2853,13 @@ mod tests {
                 last_update: Utc.with_ymd_and_hms(2022, 4, 25, 4, 29, 44).unwrap(),
                 address: None,
                 is_next: false,
+                git_commit_id: None,
+                git_commit_msg: None,
+                git_branch: None,
+                git_dirty: None,
+                source_hash: None,
+                oom_kills: 0,
+                trace_id: None,
             },
             Deployment {
                 id: id_1,
@@ -1256,6 +2868,13 @@ mod tests {
                 last_update: Utc.with_ymd_and_hms(2022, 4, 25, 4, 33, 48).unwrap(),
                 address: None,
                 is_next: false,
+                git_commit_id: None,
+                git_commit_msg: None,
+                git_branch: None,
+                git_dirty: None,
+                source_hash: None,
+                oom_kills: 0,
+                trace_id: None,
             },
             Deployment {
                 id: Uuid::new_v4(),
@@ -1264,6 +2883,13 @@ mod tests {
                 last_update: Utc.with_ymd_and_hms(2022, 4, 25, 4, 38, 52).unwrap(),
                 address: None,
                 is_next: false,
+                git_commit_id: None,
+                git_commit_msg: None,
+                git_branch: None,
+                git_dirty: None,
+                source_hash: None,
+                oom_kills: 0,
+                trace_id: None,
             },
             Deployment {
                 id: id_2,
@@ -1272,6 +2898,13 @@ mod tests {
                 last_update: Utc.with_ymd_and_hms(2022, 4, 25, 4, 42, 32).unwrap(),
                 address: None,
                 is_next: true,
+                git_commit_id: None,
+                git_commit_msg: None,
+                git_branch: None,
+                git_dirty: None,
+                source_hash: None,
+                oom_kills: 0,
+                trace_id: None,
             },
         ] {
             p.insert_deployment(deployment).await.unwrap();
@@ -6,6 +6,7 @@ use strum::{Display, EnumString};
 pub enum Type {
     AwsRds(AwsRdsType),
     Shared(SharedType),
+    Dedicated(DedicatedType),
 }
 
 #[derive(Clone, Copy, Debug, Display, EnumString, Eq, PartialEq)]
@@ -21,6 +22,14 @@ pub enum AwsRdsType {
 pub enum SharedType {
     Postgres,
     MongoDb,
+    Redis,
+    Qdrant,
+}
+
+#[derive(Clone, Copy, Debug, Display, EnumString, Eq, PartialEq)]
+#[strum(serialize_all = "lowercase")]
+pub enum DedicatedType {
+    MongoDb,
 }
 
 impl From<Type> for shuttle_common::database::Type {
@@ -28,6 +37,7 @@ impl From<Type> for shuttle_common::database::Type {
         match r#type {
             Type::AwsRds(rds_type) => Self::AwsRds(rds_type.into()),
             Type::Shared(shared_type) => Self::Shared(shared_type.into()),
+            Type::Dedicated(dedicated_type) => Self::Dedicated(dedicated_type.into()),
         }
     }
 }
@@ -47,6 +57,16 @@ impl From<SharedType> for shuttle_common::database::SharedEngine {
         match shared_type {
             SharedType::Postgres => Self::Postgres,
             SharedType::MongoDb => Self::MongoDb,
+            SharedType::Redis => Self::Redis,
+            SharedType::Qdrant => Self::Qdrant,
+        }
+    }
+}
+
+impl From<DedicatedType> for shuttle_common::database::DedicatedEngine {
+    fn from(dedicated_type: DedicatedType) -> Self {
+        match dedicated_type {
+            DedicatedType::MongoDb => Self::MongoDb,
         }
     }
 }
@@ -56,6 +76,9 @@ impl From<shuttle_common::database::Type> for Type {
         match r#type {
             shuttle_common::database::Type::AwsRds(rds_type) => Self::AwsRds(rds_type.into()),
             shuttle_common::database::Type::Shared(shared_type) => Self::Shared(shared_type.into()),
+            shuttle_common::database::Type::Dedicated(dedicated_type) => {
+                Self::Dedicated(dedicated_type.into())
+            }
         }
     }
 }
@@ -75,6 +98,16 @@ impl From<shuttle_common::database::SharedEngine> for SharedType {
         match shared_type {
             shuttle_common::database::SharedEngine::Postgres => Self::Postgres,
             shuttle_common::database::SharedEngine::MongoDb => Self::MongoDb,
+            shuttle_common::database::SharedEngine::Redis => Self::Redis,
+            shuttle_common::database::SharedEngine::Qdrant => Self::Qdrant,
+        }
+    }
+}
+
+impl From<shuttle_common::database::DedicatedEngine> for DedicatedType {
+    fn from(dedicated_type: shuttle_common::database::DedicatedEngine) -> Self {
+        match dedicated_type {
+            shuttle_common::database::DedicatedEngine::MongoDb => Self::MongoDb,
         }
     }
 }
@@ -84,6 +117,7 @@ impl Display for Type {
         match self {
             Type::AwsRds(rds_type) => write!(f, "aws_rds::{rds_type}"),
             Type::Shared(shared_type) => write!(f, "shared::{shared_type}"),
+            Type::Dedicated(dedicated_type) => write!(f, "dedicated::{dedicated_type}"),
         }
     }
 }
@@ -100,6 +134,9 @@ impl FromStr for Type {
                 "shared" => Ok(Self::Shared(
                     SharedType::from_str(rest).map_err(|e| e.to_string())?,
                 )),
+                "dedicated" => Ok(Self::Dedicated(
+                    DedicatedType::from_str(rest).map_err(|e| e.to_string())?,
+                )),
                 _ => Err(format!("'{prefix}' is an unknown database type")),
             }
         } else {
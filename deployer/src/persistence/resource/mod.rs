@@ -18,7 +18,7 @@ pub trait ResourceManager: Clone + Send + Sync + 'static {
     async fn get_resources(&self, service_id: &Uuid) -> Result<Vec<Resource>, Self::Err>;
 }
 
-#[derive(sqlx::FromRow, Debug, Eq, PartialEq)]
+#[derive(sqlx::FromRow, Clone, Debug, Eq, PartialEq)]
 pub struct Resource {
     pub service_id: Uuid,
     pub r#type: Type,
@@ -42,6 +42,8 @@ pub enum Type {
     Secrets,
     StaticFolder,
     Persist,
+    Volume,
+    RunArgs,
 }
 
 impl From<Type> for shuttle_common::resource::Type {
@@ -51,6 +53,8 @@ impl From<Type> for shuttle_common::resource::Type {
             Type::Secrets => Self::Secrets,
             Type::StaticFolder => Self::StaticFolder,
             Type::Persist => Self::Persist,
+            Type::Volume => Self::Volume,
+            Type::RunArgs => Self::RunArgs,
         }
     }
 }
@@ -62,6 +66,8 @@ impl From<shuttle_common::resource::Type> for Type {
             shuttle_common::resource::Type::Secrets => Self::Secrets,
             shuttle_common::resource::Type::StaticFolder => Self::StaticFolder,
             shuttle_common::resource::Type::Persist => Self::Persist,
+            shuttle_common::resource::Type::Volume => Self::Volume,
+            shuttle_common::resource::Type::RunArgs => Self::RunArgs,
         }
     }
 }
@@ -73,6 +79,8 @@ impl Display for Type {
             Type::Secrets => write!(f, "secrets"),
             Type::StaticFolder => write!(f, "static_folder"),
             Type::Persist => write!(f, "persist"),
+            Type::Volume => write!(f, "volume"),
+            Type::RunArgs => write!(f, "run_args"),
         }
     }
 }
@@ -91,6 +99,8 @@ impl FromStr for Type {
                 "secrets" => Ok(Self::Secrets),
                 "static_folder" => Ok(Self::StaticFolder),
                 "persist" => Ok(Self::Persist),
+                "volume" => Ok(Self::Volume),
+                "run_args" => Ok(Self::RunArgs),
                 _ => Err(format!("'{s}' is an unknown resource type")),
             }
         }
@@ -136,9 +146,14 @@ mod tests {
             Type::Database(database::Type::AwsRds(database::AwsRdsType::MariaDB)),
             Type::Database(database::Type::Shared(database::SharedType::Postgres)),
             Type::Database(database::Type::Shared(database::SharedType::MongoDb)),
+            Type::Database(database::Type::Shared(database::SharedType::Redis)),
+            Type::Database(database::Type::Shared(database::SharedType::Qdrant)),
+            Type::Database(database::Type::Dedicated(database::DedicatedType::MongoDb)),
             Type::Secrets,
             Type::StaticFolder,
             Type::Persist,
+            Type::Volume,
+            Type::RunArgs,
         ];
 
         for input in inputs {
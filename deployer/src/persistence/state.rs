@@ -13,6 +13,9 @@ pub enum State {
     /// Deployment is built, but has not been started yet
     Built,
 
+    /// Deployment is built, but is held until a user approves it to run
+    PendingApproval,
+
     /// Deployment is being loaded and resources are provisioned
     Loading,
 
@@ -28,6 +31,9 @@ pub enum State {
     /// Something in the deployment process failed
     Crashed,
 
+    /// Deployment was cancelled by the user before it was built
+    Cancelled,
+
     /// We never expect this state and entering this state should be considered a bug
     Unknown,
 }
@@ -44,11 +50,13 @@ impl From<State> for shuttle_common::deployment::State {
             State::Queued => Self::Queued,
             State::Building => Self::Building,
             State::Built => Self::Built,
+            State::PendingApproval => Self::PendingApproval,
             State::Loading => Self::Loading,
             State::Running => Self::Running,
             State::Completed => Self::Completed,
             State::Stopped => Self::Stopped,
             State::Crashed => Self::Crashed,
+            State::Cancelled => Self::Cancelled,
             State::Unknown => Self::Unknown,
         }
     }
@@ -60,11 +68,13 @@ impl From<shuttle_common::deployment::State> for State {
             shuttle_common::deployment::State::Queued => Self::Queued,
             shuttle_common::deployment::State::Building => Self::Building,
             shuttle_common::deployment::State::Built => Self::Built,
+            shuttle_common::deployment::State::PendingApproval => Self::PendingApproval,
             shuttle_common::deployment::State::Loading => Self::Loading,
             shuttle_common::deployment::State::Running => Self::Running,
             shuttle_common::deployment::State::Completed => Self::Completed,
             shuttle_common::deployment::State::Stopped => Self::Stopped,
             shuttle_common::deployment::State::Crashed => Self::Crashed,
+            shuttle_common::deployment::State::Cancelled => Self::Cancelled,
             shuttle_common::deployment::State::Unknown => Self::Unknown,
         }
     }
@@ -0,0 +1,31 @@
+use chrono::{DateTime, Utc};
+use shuttle_common::models::webhook::{self, Event};
+use sqlx::types::Json;
+use uuid::Uuid;
+
+#[derive(sqlx::FromRow, Clone, Debug, Eq, PartialEq)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub url: String,
+    pub secret: String,
+    pub events: Json<Vec<Event>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Webhook {
+    /// Whether this webhook is called for `event`
+    pub fn wants(&self, event: Event) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+impl From<Webhook> for webhook::Response {
+    fn from(webhook: Webhook) -> Self {
+        Self {
+            id: webhook.id,
+            url: webhook.url,
+            events: webhook.events.0,
+            created_at: webhook.created_at,
+        }
+    }
+}
@@ -7,28 +7,41 @@ use async_trait::async_trait;
 use axum::headers::HeaderMapExt;
 use fqdn::FQDN;
 use hyper::{
+    body::HttpBody,
     client::{connect::dns::GaiResolver, HttpConnector},
-    header::{HeaderValue, HOST, SERVER},
-    Body, Client, Request, Response, StatusCode,
+    header::{HeaderValue, CONTENT_TYPE, HOST, SERVER, SET_COOKIE, UPGRADE},
+    Body, Client, Method, Request, Response, StatusCode,
 };
 use hyper_reverse_proxy::{ProxyError, ReverseProxy};
 use once_cell::sync::Lazy;
 use opentelemetry::global;
 use opentelemetry_http::HeaderExtractor;
 use shuttle_common::backends::headers::XShuttleProject;
+use shuttle_common::backends::{pages, proxy};
+use shuttle_common::project::ProjectName;
 use tracing::{error, field, instrument, trace, Span};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
+use crate::{deployment::Replicas, metrics::RequestCounter, persistence::State};
+
 static PROXY_CLIENT: Lazy<ReverseProxy<HttpConnector<GaiResolver>>> =
     Lazy::new(|| ReverseProxy::new(Client::new()));
+/// Proxies the gRPC calls, which have to stay on HTTP/2, to the services over h2c
+static H2C_PROXY_CLIENT: Lazy<ReverseProxy<HttpConnector<GaiResolver>>> =
+    Lazy::new(|| ReverseProxy::new(Client::builder().http2_only(true).build_http()));
 static SERVER_HEADER: Lazy<HeaderValue> = Lazy::new(|| "shuttle.rs".parse().unwrap());
 
-#[instrument(name = "proxy_request", skip(address_getter), fields(http.method = %req.method(), http.uri = %req.uri(), http.status_code = field::Empty, service = field::Empty))]
+/// How many runtimes a request which can be sent again is sent to before it is given up on
+const MAX_ATTEMPTS: usize = 3;
+
+#[instrument(name = "proxy_request", skip(address_getter, replicas, requests), fields(http.method = %req.method(), http.uri = %req.uri(), http.status_code = field::Empty, service = field::Empty))]
 pub async fn handle(
     remote_address: SocketAddr,
     fqdn: FQDN,
-    req: Request<Body>,
+    mut req: Request<Body>,
     address_getter: impl AddressGetter,
+    replicas: Replicas,
+    requests: RequestCounter,
 ) -> Result<Response<Body>, Infallible> {
     let span = Span::current();
     let parent_context = global::get_text_map_propagator(|propagator| {
@@ -36,6 +49,9 @@ pub async fn handle(
     });
     span.set_parent(parent_context);
 
+    let h2c = proxy::prepare(&mut req);
+    let html = pages::wants_html(req.headers());
+
     let host: FQDN = match req.headers().get(HOST) {
         Some(host) => host
             .to_str()
@@ -52,16 +68,7 @@ pub async fn handle(
         }
     };
 
-    if host != fqdn {
-        trace!(?host, "proxy won't serve foreign domain");
-        return Ok(Response::builder()
-            .status(StatusCode::BAD_REQUEST)
-            .body(Body::from("this domain is not served by proxy"))
-            .unwrap());
-    }
-
-    // We only have one service per project, and its name coincides
-    // with that of the project
+    // The service is named after the project, or after the environment of the project it is in
     let service = match req.headers().typed_get::<XShuttleProject>() {
         Some(project) => project.0,
         None => {
@@ -73,11 +80,40 @@ pub async fn handle(
         }
     };
 
+    if host != service_fqdn(&fqdn, &service) {
+        trace!(?host, "proxy won't serve foreign domain");
+        return Ok(Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(Body::from("this domain is not served by proxy"))
+            .unwrap());
+    }
+
     // Record current service for tracing purposes
     span.record("service", &service);
 
-    let proxy_address = match address_getter.get_address_for_service(&service).await {
+    let address = match address_getter.get_address_for_service(&service).await {
         Ok(Some(address)) => address,
+        Ok(None) if html => {
+            let message = match address_getter.get_state_for_service(&service).await {
+                Ok(Some(
+                    state @ (State::Queued | State::Building | State::Built | State::Loading),
+                )) => format!(
+                    "The service is being deployed, its latest deployment is {}. Please try again in a moment.",
+                    state.to_string().to_lowercase()
+                ),
+                Ok(Some(state)) => format!(
+                    "The service is not running, its latest deployment is {}.",
+                    state.to_string().to_lowercase()
+                ),
+                Ok(None) => "The service has not been deployed yet.".to_string(),
+                Err(err) => {
+                    error!(error = %err, service, "proxy failed to find the state of the service");
+                    "The service is not running.".to_string()
+                }
+            };
+
+            return Ok(unavailable(&service, &message));
+        }
         Ok(None) => {
             trace!(?host, service, "service not found on this server");
             let response_body = format!("could not find service: {}", service);
@@ -97,32 +133,135 @@ pub async fn handle(
         }
     };
 
-    match reverse_proxy(remote_address.ip(), &proxy_address.to_string(), req).await {
-        Ok(response) => {
-            Span::current().record("http.status_code", response.status().as_u16());
-            Ok(response)
-        }
-        Err(error) => {
-            match error {
-                ProxyError::InvalidUri(e) => {
-                    error!(error = %e, "error while handling request in reverse proxy: 'invalid uri'");
-                }
-                ProxyError::HyperError(e) => {
-                    error!(error = %e, "error while handling request in reverse proxy: 'hyper error'");
+    requests.record(&service);
+
+    // The replicas of the deployment take turns with it, by the routing of the service. Requests
+    // which can be sent again are, to another of them, when the one they were sent to failed.
+    let retry = retryable(&req).then(|| copy_request(&req));
+    let mut tried = Vec::new();
+    let (mut proxy_address, mut set_cookie) = replicas
+        .pick(address, req.headers(), &tried)
+        .unwrap_or((address, None));
+    let mut req = Some(req);
+
+    loop {
+        let attempt = match (req.take(), &retry) {
+            (Some(req), _) => req,
+            (None, Some(retry)) => copy_request(retry),
+            (None, None) => unreachable!("only retryable requests are sent again"),
+        };
+
+        let error = match reverse_proxy(
+            remote_address.ip(),
+            &proxy_address.to_string(),
+            attempt,
+            h2c,
+        )
+        .await
+        {
+            Ok(mut response) => {
+                replicas.report_success(proxy_address);
+                Span::current().record("http.status_code", response.status().as_u16());
+
+                if let Some(set_cookie) = set_cookie {
+                    response.headers_mut().append(SET_COOKIE, set_cookie);
                 }
-                ProxyError::ForwardHeaderError => {
-                    error!("error while handling request in reverse proxy: 'fwd header error'");
+
+                return Ok(response);
+            }
+            Err(error) => error,
+        };
+
+        match error {
+            ProxyError::InvalidUri(e) => {
+                error!(error = %e, "error while handling request in reverse proxy: 'invalid uri'");
+            }
+            ProxyError::HyperError(e) => {
+                error!(error = %e, %proxy_address, "error while handling request in reverse proxy: 'hyper error'");
+
+                // The runtime could not be reached or broke off, unlike for the other errors,
+                // which are about the request
+                replicas.report_failure(proxy_address);
+                tried.push(proxy_address);
+
+                let next = retry
+                    .as_ref()
+                    .filter(|_| tried.len() < MAX_ATTEMPTS)
+                    .and_then(|retry| replicas.pick(address, retry.headers(), &tried));
+                if let Some(next) = next {
+                    (proxy_address, set_cookie) = next;
+                    continue;
                 }
-                ProxyError::UpgradeError(e) => error!(error = %e,
-                    "error while handling request needing upgrade in reverse proxy"
-                ),
-            };
-            Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(Body::empty())
-                .unwrap())
+            }
+            ProxyError::ForwardHeaderError => {
+                error!("error while handling request in reverse proxy: 'fwd header error'");
+            }
+            ProxyError::UpgradeError(e) => error!(error = %e,
+                "error while handling request needing upgrade in reverse proxy"
+            ),
+        };
+
+        if html {
+            return Ok(unavailable(
+                &service,
+                "The service is not answering, it may have crashed or be restarting.",
+            ));
         }
+
+        return Ok(Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(Body::empty())
+            .unwrap());
+    }
+}
+
+/// Whether a request may be sent again when the runtime it was sent to failed, because it has no
+/// body to send again and sending it twice does the same as sending it once
+fn retryable(req: &Request<Body>) -> bool {
+    matches!(
+        *req.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE | Method::DELETE
+    ) && req.body().is_end_stream()
+        && !req.headers().contains_key(UPGRADE)
+}
+
+/// A copy of the `req` which is [retryable], to send it again
+fn copy_request(req: &Request<Body>) -> Request<Body> {
+    let mut copy = Request::new(Body::empty());
+    *copy.method_mut() = req.method().clone();
+    *copy.uri_mut() = req.uri().clone();
+    *copy.version_mut() = req.version();
+    *copy.headers_mut() = req.headers().clone();
+
+    copy
+}
+
+/// A page telling a browser why the service cannot answer
+fn unavailable(service: &str, message: &str) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::BAD_GATEWAY)
+        .header(CONTENT_TYPE, "text/html; charset=utf-8")
+        .body(pages::page(&format!("{service} is unavailable"), message).into())
+        .unwrap()
+}
+
+/// The domain `service` is served at. The service named after the project is served at the domain
+/// of the project, and the service of an environment at `{environment}--{project}` next to it,
+/// which the gateway routes to this deployer while a deployment of the service is running.
+pub(crate) fn service_fqdn(project_fqdn: &FQDN, service: &str) -> FQDN {
+    let (_project, environment) = ProjectName::split_service_name(service);
+    if environment.is_none() {
+        return project_fqdn.clone();
     }
+
+    let project_fqdn = project_fqdn.to_string();
+    let Some((_, domain)) = project_fqdn.split_once('.') else {
+        return FQDN::default();
+    };
+
+    format!("{}.{domain}", ProjectName::service_host_label(service))
+        .parse()
+        .unwrap_or_default()
 }
 
 #[async_trait]
@@ -131,6 +270,12 @@ pub trait AddressGetter: Clone + Send + Sync + 'static {
         &self,
         service_name: &str,
     ) -> crate::handlers::Result<Option<SocketAddr>>;
+
+    /// The state of the latest deployment of the service, if it has any
+    async fn get_state_for_service(
+        &self,
+        service_name: &str,
+    ) -> crate::handlers::Result<Option<State>>;
 }
 
 #[instrument(skip(req))]
@@ -138,11 +283,70 @@ async fn reverse_proxy(
     remote_ip: IpAddr,
     service_address: &str,
     req: Request<Body>,
+    h2c: bool,
 ) -> Result<Response<Body>, ProxyError> {
     let forward_uri = format!("http://{service_address}");
-    let mut response = PROXY_CLIENT.call(remote_ip, &forward_uri, req).await?;
+    let client = if h2c {
+        &H2C_PROXY_CLIENT
+    } else {
+        &PROXY_CLIENT
+    };
+    let mut response = client.call(remote_ip, &forward_uri, req).await?;
 
     response.headers_mut().insert(SERVER, SERVER_HEADER.clone());
 
     Ok(response)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use fqdn::FQDN;
+    use hyper::{header::UPGRADE, Body, Method, Request};
+
+    use super::{copy_request, retryable, service_fqdn};
+
+    #[test]
+    fn environment_domain() {
+        let project_fqdn = FQDN::from_str("my-app.shuttleapp.rs").unwrap();
+
+        assert_eq!(service_fqdn(&project_fqdn, "my-app"), project_fqdn);
+        assert_eq!(
+            service_fqdn(&project_fqdn, "my-app--staging"),
+            FQDN::from_str("staging--my-app.shuttleapp.rs").unwrap()
+        );
+    }
+
+    #[test]
+    fn retryable_requests() {
+        let request = |method: Method, body: Body| {
+            Request::builder()
+                .method(method)
+                .uri("/chat?room=1")
+                .header("x-user-id", "42")
+                .body(body)
+                .unwrap()
+        };
+
+        let get = request(Method::GET, Body::empty());
+        assert!(retryable(&get));
+
+        let copy = copy_request(&get);
+        assert_eq!(copy.method(), Method::GET);
+        assert_eq!(copy.uri(), "/chat?room=1");
+        assert_eq!(copy.headers(), get.headers());
+
+        assert!(retryable(&request(Method::DELETE, Body::empty())));
+
+        // Those which may not do the same when sent twice, or whose body is gone once sent, are not
+        assert!(!retryable(&request(Method::POST, Body::empty())));
+        assert!(!retryable(&request(Method::GET, Body::from("body"))));
+
+        let mut upgrade = request(Method::GET, Body::empty());
+        upgrade
+            .headers_mut()
+            .insert(UPGRADE, "websocket".parse().unwrap());
+        assert!(!retryable(&upgrade));
+    }
+}
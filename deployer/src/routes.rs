@@ -0,0 +1,97 @@
+use shuttle_common::{project::ProjectName, storage_manager::ArtifactsStorageManager};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, error, warn};
+
+use crate::{
+    deployment::{deploy_layer, gateway_client::GatewayClient, RunConfig},
+    persistence::{Persistence, PersistenceError, State},
+};
+
+/// Keep the routes of the gateway to the services of the environments of the project pointed at
+/// their running deployments, so that the gateway sends the requests to their hosts here, and the
+/// headers it adds to their responses to the ones of the running deployments
+pub async fn update(
+    persistence: Persistence,
+    gateway_client: GatewayClient,
+    storage_manager: ArtifactsStorageManager,
+) {
+    let mut states = persistence.get_state_subscriber();
+
+    loop {
+        let log = match states.recv().await {
+            Ok(log) => log,
+            Err(RecvError::Lagged(missed)) => {
+                warn!(missed, "routes fell behind on the states of deployments");
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+
+        if let Err(error) = route(&persistence, &gateway_client, &storage_manager, log).await {
+            error!(
+                error = &error as &dyn std::error::Error,
+                "failed to get the service of a deployment to route"
+            );
+        }
+    }
+}
+
+/// Route the host of the service of a deployment to it when it starts running, and remove the route
+/// when it stops
+async fn route(
+    persistence: &Persistence,
+    gateway_client: &GatewayClient,
+    storage_manager: &ArtifactsStorageManager,
+    log: deploy_layer::Log,
+) -> Result<(), PersistenceError> {
+    let running = match log.state {
+        State::Running => true,
+        State::Stopped | State::Completed | State::Crashed => false,
+        _ => return Ok(()),
+    };
+
+    let Some(deployment) = persistence.get_deployment(&log.id).await? else {
+        return Ok(());
+    };
+    let Some(service) = persistence.get_service(&deployment.service_id).await? else {
+        return Ok(());
+    };
+
+    // Every service gets the headers of its running deployment, which may have none to clear the
+    // ones of the deployment before
+    if running {
+        let http = RunConfig::load(storage_manager, &log.id).http;
+
+        if let Err(error) = gateway_client.set_headers(&service.name, http).await {
+            error!(
+                error = &error as &dyn std::error::Error,
+                service = %service.name,
+                "failed to update the headers of the service on the gateway"
+            );
+        }
+    }
+
+    // The main service of the project is served on the host of the project
+    if ProjectName::split_service_name(&service.name).1.is_none() {
+        return Ok(());
+    }
+
+    let result = if running {
+        gateway_client
+            .set_route(&service.name, log.id)
+            .await
+            .map(|route| debug!(hostname = %route.hostname, "routed service"))
+    } else {
+        gateway_client.delete_route(&service.name, log.id).await
+    };
+
+    if let Err(error) = result {
+        error!(
+            error = &error as &dyn std::error::Error,
+            service = %service.name,
+            "failed to update the route of the service on the gateway"
+        );
+    }
+
+    Ok(())
+}
@@ -1,19 +1,44 @@
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    process::Stdio,
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::Context;
+use chrono::Utc;
+use futures::future::join_all;
+use serde_json::json;
 use shuttle_common::claims::{ClaimService, InjectPropagation};
 use shuttle_proto::runtime::{
-    self, runtime_client::RuntimeClient, StopRequest, SubscribeLogsRequest,
+    self, runtime_client::RuntimeClient, ShutdownRequest, StopRequest, SubscribeLogsRequest,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process,
+    sync::Mutex,
+    time::timeout,
 };
-use tokio::{process, sync::Mutex};
 use tonic::transport::Channel;
-use tracing::{debug, info, trace};
+use tracing::{debug, info, trace, warn};
 use uuid::Uuid;
 
-use crate::deployment::deploy_layer;
+use crate::{
+    deployment::deploy_layer::{self, LogType},
+    persistence::{LogLevel, LogSource, State},
+    Limits,
+};
 
 const MANIFEST_DIR: &str = env!("CARGO_MANIFEST_DIR");
 
+/// How long a runtime has to stop its deployment, on top of the drain timeout, before its process is
+/// killed
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many of the lines a runtime wrote to stderr last are kept
+const STDERR_LINES: usize = 20;
+
 type Runtimes = Arc<
     std::sync::Mutex<
         HashMap<
@@ -26,15 +51,24 @@ type Runtimes = Arc<
     >,
 >;
 
+type StderrTails = Arc<std::sync::Mutex<HashMap<Uuid, VecDeque<String>>>>;
+
+/// The ids of the runtimes of the replicas of each deployment, which go next to its own runtime
+type ReplicaIds = Arc<std::sync::Mutex<HashMap<Uuid, Vec<Uuid>>>>;
+
 /// Manager that can start up mutliple runtimes. This is needed so that two runtimes can be up when a new deployment is made:
 /// One runtime for the new deployment being loaded; another for the currently active deployment
 #[derive(Clone)]
 pub struct RuntimeManager {
     runtimes: Runtimes,
+    stderr_tails: StderrTails,
+    replicas: ReplicaIds,
     artifacts_path: PathBuf,
     provisioner_address: String,
     auth_uri: Option<String>,
     log_sender: crossbeam_channel::Sender<deploy_layer::Log>,
+    limits: Limits,
+    drain_timeout: Duration,
 }
 
 impl RuntimeManager {
@@ -43,13 +77,19 @@ impl RuntimeManager {
         provisioner_address: String,
         auth_uri: Option<String>,
         log_sender: crossbeam_channel::Sender<deploy_layer::Log>,
+        limits: Limits,
+        drain_timeout: Duration,
     ) -> Arc<Mutex<Self>> {
         Arc::new(Mutex::new(Self {
             runtimes: Default::default(),
+            stderr_tails: Default::default(),
+            replicas: Default::default(),
             artifacts_path,
             provisioner_address,
             auth_uri,
             log_sender,
+            limits,
+            drain_timeout,
         }))
     }
 
@@ -57,6 +97,48 @@ impl RuntimeManager {
         &mut self,
         id: Uuid,
         alpha_runtime_path: Option<PathBuf>,
+        assets_path: Option<PathBuf>,
+        env: HashMap<String, String>,
+    ) -> anyhow::Result<RuntimeClient<ClaimService<InjectPropagation<Channel>>>> {
+        self.start_runtime(id, id, alpha_runtime_path, assets_path, env)
+            .await
+    }
+
+    /// Start another runtime for deployment `id`, getting the id of the replica it runs. It is
+    /// killed along with the deployment, or on its own with [RuntimeManager::kill_replica].
+    pub async fn get_replica_client(
+        &mut self,
+        id: Uuid,
+        alpha_runtime_path: Option<PathBuf>,
+        assets_path: Option<PathBuf>,
+        env: HashMap<String, String>,
+    ) -> anyhow::Result<(
+        Uuid,
+        RuntimeClient<ClaimService<InjectPropagation<Channel>>>,
+    )> {
+        let replica_id = Uuid::new_v4();
+        let runtime_client = self
+            .start_runtime(replica_id, id, alpha_runtime_path, assets_path, env)
+            .await?;
+
+        self.replicas
+            .lock()
+            .unwrap()
+            .entry(id)
+            .or_default()
+            .push(replica_id);
+
+        Ok((replica_id, runtime_client))
+    }
+
+    /// Start the runtime `runtime_id`, whose logs are those of deployment `id`
+    async fn start_runtime(
+        &mut self,
+        runtime_id: Uuid,
+        id: Uuid,
+        alpha_runtime_path: Option<PathBuf>,
+        assets_path: Option<PathBuf>,
+        env: HashMap<String, String>,
     ) -> anyhow::Result<RuntimeClient<ClaimService<InjectPropagation<Channel>>>> {
         trace!("making new client");
 
@@ -107,17 +189,71 @@ impl RuntimeManager {
             }
         };
 
-        let (process, runtime_client) = runtime::start(
+        let (mut process, runtime_client) = runtime::start(
             is_next,
             runtime::StorageManagerType::Artifacts(self.artifacts_path.clone()),
             &self.provisioner_address,
             self.auth_uri.as_ref(),
             port,
             get_runtime_executable,
+            assets_path.as_deref(),
+            &env,
+            Stdio::piped(),
+            Stdio::piped(),
         )
         .await
         .context("failed to start shuttle runtime")?;
 
+        // A runtime which cannot be held to the limits does not get to run at all
+        let limited = process
+            .id()
+            .context("runtime exited right after it started")
+            .and_then(|pid| {
+                self.limits
+                    .apply(&runtime_id, pid)
+                    .context("failed to apply the resource limits to the runtime")
+            });
+        if let Err(error) = limited {
+            let _ = process.start_kill();
+
+            return Err(error);
+        }
+
+        // Keep what the runtime prints as logs of the deployment, apart from what it traces, with
+        // the last lines it wrote to stderr to tell why it crashed
+        if let Some(stdout) = process.stdout.take() {
+            tokio::spawn(record_output(
+                stdout,
+                id,
+                LogSource::Stdout,
+                self.log_sender.clone(),
+                |_| {},
+            ));
+        }
+
+        if let Some(stderr) = process.stderr.take() {
+            let stderr_tails = self.stderr_tails.clone();
+            stderr_tails
+                .lock()
+                .unwrap()
+                .insert(runtime_id, VecDeque::new());
+
+            tokio::spawn(record_output(
+                stderr,
+                id,
+                LogSource::Stderr,
+                self.log_sender.clone(),
+                move |line| {
+                    if let Some(tail) = stderr_tails.lock().unwrap().get_mut(&runtime_id) {
+                        if tail.len() == STDERR_LINES {
+                            tail.pop_front();
+                        }
+                        tail.push_back(line.to_string());
+                    }
+                },
+            ));
+        }
+
         let sender = self.log_sender.clone();
         let mut stream = runtime_client
             .clone()
@@ -139,25 +275,111 @@ impl RuntimeManager {
         self.runtimes
             .lock()
             .unwrap()
-            .insert(id, (process, runtime_client.clone()));
+            .insert(runtime_id, (process, runtime_client.clone()));
 
         Ok(runtime_client)
     }
 
-    /// Send a kill / stop signal for a deployment to its running runtime
+    /// The process ids of the runtimes which are up, by the deployment they run
+    pub fn pids(&self) -> HashMap<Uuid, u32> {
+        self.runtimes
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(id, (process, _))| Some((*id, process.id()?)))
+            .collect()
+    }
+
+    /// The limits the runtimes are held to
+    pub fn limits(&self) -> Limits {
+        self.limits.clone()
+    }
+
+    /// The last lines the runtime of a deployment wrote to stderr
+    pub fn stderr_tail(&self, id: &Uuid) -> Vec<String> {
+        self.stderr_tails
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|tail| tail.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Ask the runtime of a deployment, and those of its replicas, to shut it down, once they had the
+    /// drain timeout to finish their work in flight, and then kill the runtimes
     pub async fn kill(&mut self, id: &Uuid) -> bool {
+        self.kill_replicas(id).await;
+
+        self.shutdown(id).await
+    }
+
+    /// Shut down the replicas of a deployment, leaving its own runtime be
+    pub async fn kill_replicas(&mut self, id: &Uuid) {
+        let replica_ids = self.replicas.lock().unwrap().remove(id).unwrap_or_default();
+
+        join_all(
+            replica_ids
+                .iter()
+                .map(|replica_id| self.shutdown(replica_id)),
+        )
+        .await;
+    }
+
+    /// Shut down the replica `replica_id` of deployment `id`
+    pub async fn kill_replica(&mut self, id: &Uuid, replica_id: &Uuid) -> bool {
+        if let Some(replica_ids) = self.replicas.lock().unwrap().get_mut(id) {
+            replica_ids.retain(|other| other != replica_id);
+        }
+
+        self.shutdown(replica_id).await
+    }
+
+    /// Ask runtime `id` to shut down its deployment, and then kill it
+    async fn shutdown(&self, id: &Uuid) -> bool {
         let value = self.runtimes.lock().unwrap().remove(id);
+        self.stderr_tails.lock().unwrap().remove(id);
 
         if let Some((mut process, mut runtime_client)) = value {
-            trace!(%id, "sending stop signal for deployment");
+            trace!(%id, "sending shutdown signal for deployment");
 
-            let stop_request = tonic::Request::new(StopRequest {});
-            let response = runtime_client.stop(stop_request).await.unwrap();
+            let shutdown_request = tonic::Request::new(ShutdownRequest {
+                drain_timeout: self.drain_timeout.as_secs(),
+            });
 
-            trace!(?response, "stop deployment response");
+            // A runtime which does not answer is killed all the same
+            let result = match timeout(
+                self.drain_timeout + STOP_TIMEOUT,
+                runtime_client.shutdown(shutdown_request),
+            )
+            .await
+            {
+                Ok(Ok(response)) => {
+                    trace!(?response, "shutdown deployment response");
+                    let response = response.into_inner();
 
-            let result = response.into_inner().success;
-            let _ = process.start_kill();
+                    if response.success && !response.drained {
+                        warn!(%id, "deployment did not finish its work in flight in time");
+                    }
+
+                    response.success
+                }
+                // Runtimes of older versions can only be stopped right away
+                Ok(Err(status)) if status.code() == tonic::Code::Unimplemented => {
+                    stop(id, &mut runtime_client).await
+                }
+                Ok(Err(status)) => {
+                    warn!(%id, %status, "failed to shut down deployment");
+
+                    false
+                }
+                Err(_) => {
+                    warn!(%id, "runtime did not shut down deployment in time");
+
+                    false
+                }
+            };
+            let _ = process.kill().await;
+            self.limits.remove(id);
 
             result
         } else {
@@ -167,6 +389,32 @@ impl RuntimeManager {
     }
 }
 
+/// Stop the deployment `id` right away
+async fn stop(
+    id: &Uuid,
+    runtime_client: &mut RuntimeClient<ClaimService<InjectPropagation<Channel>>>,
+) -> bool {
+    let stop_request = tonic::Request::new(StopRequest {});
+
+    match timeout(STOP_TIMEOUT, runtime_client.stop(stop_request)).await {
+        Ok(Ok(response)) => {
+            trace!(?response, "stop deployment response");
+
+            response.into_inner().success
+        }
+        Ok(Err(status)) => {
+            warn!(%id, %status, "failed to stop deployment");
+
+            false
+        }
+        Err(_) => {
+            warn!(%id, "runtime did not stop deployment in time");
+
+            false
+        }
+    }
+}
+
 impl Drop for RuntimeManager {
     fn drop(&mut self) {
         info!("runtime manager shutting down");
@@ -176,3 +424,69 @@ impl Drop for RuntimeManager {
         }
     }
 }
+
+/// Record every line the runtime of deployment `id` writes to `output` as a log from `source`,
+/// handing it to `on_line` first
+async fn record_output(
+    output: impl AsyncRead + Unpin,
+    id: Uuid,
+    source: LogSource,
+    log_sender: crossbeam_channel::Sender<deploy_layer::Log>,
+    mut on_line: impl FnMut(&str),
+) {
+    let mut lines = BufReader::new(output).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        on_line(&line);
+
+        let log = deploy_layer::Log {
+            id,
+            state: State::Running,
+            level: LogLevel::Info,
+            timestamp: Utc::now(),
+            file: None,
+            line: None,
+            target: String::new(),
+            fields: json!({ "message": line }),
+            r#type: LogType::Event,
+            source,
+        };
+
+        if log_sender.send(log).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::record_output;
+    use crate::persistence::LogSource;
+
+    #[tokio::test]
+    async fn output_is_recorded() {
+        let id = Uuid::new_v4();
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let mut seen = Vec::new();
+
+        record_output(
+            &b"listening on 8000\nthread 'main' panicked\n"[..],
+            id,
+            LogSource::Stderr,
+            sender,
+            |line| seen.push(line.to_string()),
+        )
+        .await;
+
+        assert_eq!(seen, ["listening on 8000", "thread 'main' panicked"]);
+
+        let logs: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(logs.len(), 2);
+        assert!(logs
+            .iter()
+            .all(|log| log.id == id && log.source == LogSource::Stderr));
+        assert_eq!(logs[1].fields["message"], "thread 'main' panicked");
+    }
+}
@@ -0,0 +1,120 @@
+use std::{io, time::Duration};
+
+use chrono::Utc;
+use serde_json::json;
+use shuttle_common::{models::storage::Usage, storage_manager::StorageManager};
+use shuttle_service::builder::dir_size;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info};
+
+use crate::{
+    deployment::{
+        deploy_layer::{self, LogRecorder, LogType},
+        DeploymentManager,
+    },
+    persistence::{LogLevel, LogSource, Persistence, PersistenceError, State},
+};
+
+/// How often the volumes of running deployments are measured against the quota
+const ENFORCE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Keeps the volumes of the services of the project within the quota of the deployer
+#[derive(Clone)]
+pub struct VolumeKeeper {
+    persistence: Persistence,
+    deployment_manager: DeploymentManager,
+    /// Bytes the volume of each service may take up
+    quota: u64,
+}
+
+impl VolumeKeeper {
+    pub fn new(
+        persistence: Persistence,
+        deployment_manager: DeploymentManager,
+        quota: u64,
+    ) -> Self {
+        Self {
+            persistence,
+            deployment_manager,
+            quota,
+        }
+    }
+
+    /// How much of the quota the volume of `service_name` takes up
+    pub async fn usage(&self, service_name: &str) -> io::Result<Usage> {
+        let path = self
+            .deployment_manager
+            .storage_manager()
+            .service_volume_path(service_name)?;
+
+        Ok(Usage {
+            bytes: dir_size(&path).await,
+            quota: self.quota,
+        })
+    }
+
+    /// Stop the running deployments whose volumes take up more than the quota
+    async fn enforce(&self) -> Result<(), PersistenceError> {
+        for deployment in self.persistence.get_all_runnable_deployments().await? {
+            let usage = match self.usage(&deployment.service_name).await {
+                Ok(usage) => usage,
+                Err(error) => {
+                    error!(
+                        error = &error as &dyn std::error::Error,
+                        service_name = %deployment.service_name,
+                        "failed to measure volume"
+                    );
+                    continue;
+                }
+            };
+
+            if usage.bytes <= usage.quota {
+                continue;
+            }
+
+            info!(
+                id = %deployment.id,
+                bytes = usage.bytes,
+                quota = usage.quota,
+                "stopping deployment whose volume is over the quota"
+            );
+
+            // Let the owner know why their deployment stopped
+            self.persistence.record(deploy_layer::Log {
+                id: deployment.id,
+                state: State::Running,
+                level: LogLevel::Error,
+                timestamp: Utc::now(),
+                file: None,
+                line: None,
+                target: String::new(),
+                fields: json!({
+                    "message": format!("Stopping the deployment since its volume is over the quota. {usage}")
+                }),
+                r#type: LogType::Event,
+                source: LogSource::Tracing,
+            });
+
+            self.deployment_manager.kill(deployment.id).await;
+        }
+
+        Ok(())
+    }
+}
+
+/// Keep stopping the deployments whose volumes grew over the quota
+pub async fn enforce(volume_keeper: VolumeKeeper) {
+    let mut interval = tokio::time::interval(ENFORCE_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(error) = volume_keeper.enforce().await {
+            error!(
+                error = &error as &dyn std::error::Error,
+                "failed to enforce the volume quota"
+            );
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use std::{net::IpAddr, time::Duration};
+
+use chrono::Utc;
+use reqwest::{header::CONTENT_TYPE, StatusCode};
+use ring::{
+    hmac,
+    rand::{SecureRandom, SystemRandom},
+};
+use shuttle_common::{
+    models::webhook::{Event, Payload, EVENT_HEADER, SIGNATURE_HEADER},
+    project::ProjectName,
+};
+use tokio::{net::lookup_host, sync::broadcast::error::RecvError, time::sleep};
+use tracing::{debug, error, instrument, warn};
+
+use crate::{
+    deployment::deploy_layer,
+    persistence::{Persistence, PersistenceError, State, Webhook},
+};
+
+/// How many times a payload is sent before it is given up on
+const ATTEMPTS: u32 = 5;
+
+/// How long to wait before sending a payload again the first time, which doubles after every attempt
+const INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+
+/// How long a webhook has to answer
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Call the webhooks of the project when its deployments get into states they were registered for
+pub async fn dispatch(persistence: Persistence, project_name: ProjectName) {
+    // Redirects are not followed, since they could lead to addresses webhooks may not call
+    let client = reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("to build the webhook client");
+    let mut states = persistence.get_state_subscriber();
+
+    loop {
+        let log = match states.recv().await {
+            Ok(log) => log,
+            Err(RecvError::Lagged(missed)) => {
+                warn!(missed, "webhooks fell behind on the states of deployments");
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+
+        if let Err(error) = notify(&persistence, &client, &project_name, log).await {
+            error!(
+                error = &error as &dyn std::error::Error,
+                "failed to call the webhooks"
+            );
+        }
+    }
+}
+
+/// Call the webhooks for the state a deployment got into, if that is an event any of them wants
+async fn notify(
+    persistence: &Persistence,
+    client: &reqwest::Client,
+    project_name: &ProjectName,
+    log: deploy_layer::Log,
+) -> Result<(), PersistenceError> {
+    // The transitions after this one are for the next time around
+    let states: Vec<_> = persistence
+        .get_state_transitions(&log.id)
+        .await?
+        .into_iter()
+        .filter(|transition| transition.timestamp <= log.timestamp)
+        .map(|transition| transition.state)
+        .collect();
+
+    let Some(event) = event(&states) else {
+        return Ok(());
+    };
+
+    let webhooks: Vec<_> = persistence
+        .get_webhooks()
+        .await?
+        .into_iter()
+        .filter(|webhook| webhook.wants(event))
+        .collect();
+
+    if webhooks.is_empty() {
+        return Ok(());
+    }
+
+    let Some(deployment) = persistence.get_deployment(&log.id).await? else {
+        return Ok(());
+    };
+    let service = persistence
+        .get_service(&deployment.service_id)
+        .await?
+        .map(|service| service.name)
+        .unwrap_or_default();
+
+    let payload = Payload::new(
+        event,
+        project_name.to_string(),
+        service,
+        log.id,
+        log.state.into(),
+        log.timestamp,
+    );
+    let body = serde_json::to_vec(&payload).expect("to serialize the webhook payload");
+
+    for webhook in webhooks {
+        tokio::spawn(deliver(client.clone(), webhook, event, body.clone()));
+    }
+
+    Ok(())
+}
+
+/// The event of a deployment getting into the last of `states`, which are all the states it got
+/// into so far
+fn event(states: &[State]) -> Option<Event> {
+    let (state, before) = states.split_last()?;
+
+    match state {
+        State::Queued => Some(Event::DeployStarted),
+        // Deployments which are started again by a rollback, or when the deployer starts, are
+        // pushed to run and are built first
+        State::Loading
+            if matches!(
+                before.last(),
+                Some(State::Running | State::Completed | State::Crashed)
+            ) =>
+        {
+            Some(Event::Restarted)
+        }
+        State::Running if !before.contains(&State::Running) => Some(Event::DeploySucceeded),
+        State::Crashed if before.contains(&State::Running) => Some(Event::Crashed),
+        State::Crashed => Some(Event::DeployFailed),
+        _ => None,
+    }
+}
+
+/// Post the `body` of an `event` to the `webhook`, backing off and trying again while it does not
+/// take it
+#[instrument(skip(client, webhook, body), fields(webhook_id = %webhook.id, url = %webhook.url))]
+async fn deliver(client: reqwest::Client, webhook: Webhook, event: Event, body: Vec<u8>) {
+    // Checked again, since what the host resolves to can have changed since it was registered
+    if let Err(reason) = check_url(&webhook.url).await {
+        warn!(reason, "webhook cannot be called");
+        return;
+    }
+
+    let mut backoff = INITIAL_BACKOFF;
+
+    for attempt in 1..=ATTEMPTS {
+        // Signed again for every attempt, so that the timestamp is that of the attempt
+        let signature = sign(&webhook.secret, Utc::now().timestamp(), &body);
+
+        let result = client
+            .post(&webhook.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, event.to_string())
+            .header(SIGNATURE_HEADER, signature)
+            .body(body.clone())
+            .send()
+            .await;
+
+        match result {
+            Ok(response) if response.status().is_success() => {
+                debug!(attempt, "called webhook");
+                return;
+            }
+            Ok(response) if response.status().is_redirection() => {
+                warn!(status = %response.status(), "webhook redirected the payload, which is not followed");
+                return;
+            }
+            // The request will not be taken by trying again, unless it is being rate limited
+            Ok(response)
+                if response.status().is_client_error()
+                    && !matches!(
+                        response.status(),
+                        StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS
+                    ) =>
+            {
+                warn!(status = %response.status(), "webhook refused the payload");
+                return;
+            }
+            Ok(response) => warn!(attempt, status = %response.status(), "webhook failed"),
+            Err(error) => warn!(attempt, %error, "failed to reach webhook"),
+        }
+
+        if attempt < ATTEMPTS {
+            sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+
+    error!("gave up on calling webhook after {ATTEMPTS} attempts");
+}
+
+/// The value of the signature header of a `body` sent at `timestamp` to a webhook with `secret`
+fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let mut context = hmac::Context::with_key(&key);
+    context.update(format!("{timestamp}.").as_bytes());
+    context.update(body);
+
+    format!("t={timestamp},v1={}", hex::encode(context.sign()))
+}
+
+/// Check that `url` can be called by a webhook, which is when it is an http or https URL whose host
+/// only resolves to public addresses. Otherwise webhooks could call services on the network of the
+/// deployer, like the provisioner, auth or the metadata endpoint of the cloud.
+pub async fn check_url(url: &str) -> Result<(), String> {
+    let parsed = match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => parsed,
+        _ => return Err(format!("{url} is not an http or https URL")),
+    };
+    let (Some(host), Some(port)) = (parsed.host_str(), parsed.port_or_known_default()) else {
+        return Err(format!("{url} is not an http or https URL"));
+    };
+
+    let addresses: Vec<_> = lookup_host((host.trim_start_matches('[').trim_end_matches(']'), port))
+        .await
+        .map_err(|error| format!("could not resolve {host}: {error}"))?
+        .map(|address| address.ip())
+        .collect();
+
+    if addresses.is_empty() {
+        return Err(format!("{host} does not resolve to any address"));
+    }
+
+    match addresses.into_iter().find(|address| !is_public(address)) {
+        Some(address) => Err(format!(
+            "{host} resolves to {address}, which is not a public address"
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Whether `address` is reachable from the internet, rather than only from this machine or its
+/// network
+fn is_public(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => {
+            !(address.is_loopback()
+                || address.is_private()
+                || address.is_link_local()
+                || address.is_unspecified()
+                || address.is_broadcast())
+        }
+        IpAddr::V6(address) => match address.to_ipv4_mapped() {
+            Some(address) => is_public(&address.into()),
+            None => {
+                let first = address.segments()[0];
+                // Unique local (fc00::/7) and link-local (fe80::/10) addresses are of the network
+                !(address.is_loopback()
+                    || address.is_unspecified()
+                    || first & 0xfe00 == 0xfc00
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Make a secret to sign the payloads of a new webhook with
+pub fn generate_secret() -> String {
+    let mut secret = [0; 32];
+    SystemRandom::new()
+        .fill(&mut secret)
+        .expect("to generate a webhook secret");
+
+    hex::encode(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::hmac;
+    use shuttle_common::models::webhook::Event;
+
+    use super::{check_url, event, generate_secret, sign};
+    use crate::persistence::State;
+
+    #[test]
+    fn events() {
+        use State::*;
+
+        assert_eq!(event(&[Queued]), Some(Event::DeployStarted));
+        assert_eq!(event(&[Queued, Building]), None);
+        assert_eq!(
+            event(&[Queued, Building, Built, Loading, Running]),
+            Some(Event::DeploySucceeded)
+        );
+        assert_eq!(
+            event(&[Queued, Building, Crashed]),
+            Some(Event::DeployFailed)
+        );
+        assert_eq!(
+            event(&[Queued, Building, Built, Loading, Crashed]),
+            Some(Event::DeployFailed)
+        );
+        assert_eq!(
+            event(&[Queued, Building, Built, Loading, Running, Crashed]),
+            Some(Event::Crashed)
+        );
+
+        // A crashed deployment which is started again
+        let restarted = [Queued, Building, Built, Loading, Running, Crashed, Loading];
+        assert_eq!(event(&restarted), Some(Event::Restarted));
+        assert_eq!(event(&[&restarted[..], &[Running]].concat()), None);
+
+        // A deployment which is started again when the deployer starts
+        assert_eq!(
+            event(&[Queued, Building, Built, Loading, Running, Built, Loading, Running]),
+            None
+        );
+    }
+
+    #[test]
+    fn signature() {
+        let secret = generate_secret();
+        assert_eq!(secret.len(), 64);
+        assert_ne!(secret, generate_secret());
+
+        let body = br#"{"event":"crashed"}"#;
+        let signature = sign(&secret, 1685000000, body);
+        let mac = signature
+            .strip_prefix("t=1685000000,v1=")
+            .expect("the timestamp to come first");
+
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        let signed = [b"1685000000.".as_slice(), body].concat();
+        hmac::verify(&key, &signed, &hex::decode(mac).unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn urls() {
+        assert!(check_url("https://93.184.216.34/hooks").await.is_ok());
+        assert!(check_url("http://[2606:2800:220:1::]:8080/hooks")
+            .await
+            .is_ok());
+
+        for url in [
+            "ftp://93.184.216.34/hooks",
+            "not a url",
+            "http://127.0.0.1:8000/",
+            "http://localhost:8000/",
+            "http://10.0.0.5/",
+            "http://192.168.1.1/",
+            "http://172.16.0.1/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://0.0.0.0/",
+            "http://[::1]/",
+            "http://[fd00::1]/",
+            "http://[fe80::1]/",
+            "http://[::ffff:127.0.0.1]/",
+        ] {
+            assert!(check_url(url).await.is_err(), "{url} should be refused");
+        }
+    }
+}
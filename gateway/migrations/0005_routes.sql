@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS routes (
+  hostname TEXT PRIMARY KEY,
+  project_name TEXT NOT NULL REFERENCES projects (project_name),
+  service_name TEXT NOT NULL,
+  deployment_id TEXT NOT NULL
+);
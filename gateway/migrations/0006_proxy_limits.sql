@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS proxy_limits (
+  project_name TEXT PRIMARY KEY REFERENCES projects (project_name),
+  max_request_body_bytes INTEGER NOT NULL,
+  response_timeout_secs INTEGER NOT NULL,
+  idle_timeout_secs INTEGER NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE proxy_limits ADD COLUMN requests_per_second INTEGER;
+ALTER TABLE proxy_limits ADD COLUMN burst INTEGER;
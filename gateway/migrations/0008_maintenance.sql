@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS maintenance (
+  project_name TEXT PRIMARY KEY REFERENCES projects (project_name),
+  status INTEGER NOT NULL,
+  page TEXT
+);
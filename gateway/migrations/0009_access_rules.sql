@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS access_rules (
+  project_name TEXT NOT NULL REFERENCES projects (project_name),
+  service_name TEXT NOT NULL,
+  rules JSON NOT NULL,
+  PRIMARY KEY (project_name, service_name)
+);
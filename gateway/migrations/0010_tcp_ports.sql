@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS tcp_ports (
+  port INTEGER PRIMARY KEY,
+  project_name TEXT NOT NULL REFERENCES projects (project_name),
+  target_port INTEGER NOT NULL,
+  UNIQUE (project_name, target_port)
+);
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS nodes (
+  node_name TEXT PRIMARY KEY,
+  docker_host TEXT NOT NULL,
+  draining BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+-- The projects made before there were nodes are on the Docker host of the gateway
+ALTER TABLE projects ADD COLUMN node_name TEXT NOT NULL DEFAULT 'default';
@@ -0,0 +1,236 @@
+use std::net::IpAddr;
+
+use axum::headers::authorization::{Basic, Bearer};
+use axum::headers::{Authorization, HeaderMapExt};
+use http::HeaderMap;
+use ring::constant_time::verify_slices_are_equal;
+use ring::digest::{Context as Digest, SHA256};
+use serde::{Deserialize, Serialize};
+use shuttle_common::models::access::{self, Cidr, Guard, GuardKind};
+
+/// The rules for the clients of a service, which the gateway keeps with the secret of their guard
+/// hashed
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AccessRules {
+    allow: Vec<Cidr>,
+    deny: Vec<Cidr>,
+    guard: Option<HashedGuard>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum HashedGuard {
+    Basic {
+        username: String,
+        salt: String,
+        hash: String,
+    },
+    Bearer {
+        salt: String,
+        hash: String,
+    },
+}
+
+/// Why a client was not let through to a service
+#[derive(Debug, Eq, PartialEq)]
+pub enum Denied {
+    /// The address of the client is not allowed
+    Address,
+    /// The client did not send the credentials of the guard, and is challenged for them with this
+    /// `WWW-Authenticate` header
+    Credentials(&'static str),
+}
+
+impl AccessRules {
+    pub fn new(request: access::Request) -> Self {
+        let guard = request.guard.map(|guard| match guard {
+            Guard::Basic { username, password } => {
+                let salt = new_salt();
+                let hash = hash(&salt, &password);
+
+                HashedGuard::Basic {
+                    username,
+                    salt,
+                    hash,
+                }
+            }
+            Guard::Bearer { token } => {
+                let salt = new_salt();
+                let hash = hash(&salt, &token);
+
+                HashedGuard::Bearer { salt, hash }
+            }
+        });
+
+        Self {
+            allow: request.allow,
+            deny: request.deny,
+            guard,
+        }
+    }
+
+    /// Whether the `client` which sent a request with `headers` is let through
+    pub fn check(&self, client: IpAddr, headers: &HeaderMap) -> Result<(), Denied> {
+        if self.deny.iter().any(|cidr| cidr.contains(client))
+            || !(self.allow.is_empty() || self.allow.iter().any(|cidr| cidr.contains(client)))
+        {
+            return Err(Denied::Address);
+        }
+
+        match &self.guard {
+            None => Ok(()),
+            Some(HashedGuard::Basic {
+                username,
+                salt,
+                hash: expected,
+            }) => match headers.typed_get::<Authorization<Basic>>() {
+                Some(Authorization(basic))
+                    if basic.username() == username
+                        && matches(salt, basic.password(), expected) =>
+                {
+                    Ok(())
+                }
+                _ => Err(Denied::Credentials(
+                    r#"Basic realm="shuttle", charset="UTF-8""#,
+                )),
+            },
+            Some(HashedGuard::Bearer {
+                salt,
+                hash: expected,
+            }) => match headers.typed_get::<Authorization<Bearer>>() {
+                Some(Authorization(bearer)) if matches(salt, bearer.token(), expected) => Ok(()),
+                _ => Err(Denied::Credentials(r#"Bearer realm="shuttle""#)),
+            },
+        }
+    }
+
+    /// Whether the credentials of a guard came with the requests, which the service should not
+    /// get
+    pub fn is_guarded(&self) -> bool {
+        self.guard.is_some()
+    }
+
+    pub fn into_response(self, service: String) -> access::Response {
+        let guard = self.guard.map(|guard| match guard {
+            HashedGuard::Basic { username, .. } => GuardKind::Basic { username },
+            HashedGuard::Bearer { .. } => GuardKind::Bearer,
+        });
+
+        access::Response {
+            service,
+            allow: self.allow,
+            deny: self.deny,
+            guard,
+        }
+    }
+}
+
+fn new_salt() -> String {
+    to_hex(&rand::random::<[u8; 16]>())
+}
+
+fn hash(salt: &str, secret: &str) -> String {
+    let mut digest = Digest::new(&SHA256);
+    digest.update(salt.as_bytes());
+    digest.update(secret.as_bytes());
+
+    to_hex(digest.finish().as_ref())
+}
+
+fn matches(salt: &str, secret: &str, expected: &str) -> bool {
+    verify_slices_are_equal(hash(salt, secret).as_bytes(), expected.as_bytes()).is_ok()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::headers::{Authorization, HeaderMapExt};
+    use http::HeaderMap;
+    use shuttle_common::models::access::{self, Guard, GuardKind};
+
+    use super::{AccessRules, Denied};
+
+    #[test]
+    fn addresses() {
+        let rules = AccessRules::new(access::Request {
+            allow: vec!["10.0.0.0/8".parse().unwrap()],
+            deny: vec!["10.0.0.13".parse().unwrap()],
+            guard: None,
+        });
+        let headers = HeaderMap::new();
+
+        assert_eq!(rules.check("10.0.0.1".parse().unwrap(), &headers), Ok(()));
+        assert_eq!(
+            rules.check("10.0.0.13".parse().unwrap(), &headers),
+            Err(Denied::Address)
+        );
+        assert_eq!(
+            rules.check("8.8.8.8".parse().unwrap(), &headers),
+            Err(Denied::Address)
+        );
+
+        let open = AccessRules::new(access::Request::default());
+        assert_eq!(open.check("8.8.8.8".parse().unwrap(), &headers), Ok(()));
+    }
+
+    #[test]
+    fn basic_guard() {
+        let rules = AccessRules::new(access::Request {
+            guard: Some(Guard::Basic {
+                username: "neo".to_string(),
+                password: "there is no spoon".to_string(),
+            }),
+            ..Default::default()
+        });
+        let client = "10.0.0.1".parse().unwrap();
+
+        let mut headers = HeaderMap::new();
+        assert!(matches!(
+            rules.check(client, &headers),
+            Err(Denied::Credentials(challenge)) if challenge.starts_with("Basic")
+        ));
+
+        headers.typed_insert(Authorization::basic("neo", "there is a spoon"));
+        assert!(rules.check(client, &headers).is_err());
+
+        headers.typed_insert(Authorization::basic("smith", "there is no spoon"));
+        assert!(rules.check(client, &headers).is_err());
+
+        headers.typed_insert(Authorization::basic("neo", "there is no spoon"));
+        assert_eq!(rules.check(client, &headers), Ok(()));
+
+        let response = rules.into_response("matrix".to_string());
+        assert_eq!(
+            response.guard,
+            Some(GuardKind::Basic {
+                username: "neo".to_string()
+            })
+        );
+        assert!(!serde_json::to_string(&response)
+            .unwrap()
+            .contains("there is no spoon"));
+    }
+
+    #[test]
+    fn bearer_guard() {
+        let rules = AccessRules::new(access::Request {
+            guard: Some(Guard::Bearer {
+                token: "red-pill".to_string(),
+            }),
+            ..Default::default()
+        });
+        let client = "10.0.0.1".parse().unwrap();
+
+        let mut headers = HeaderMap::new();
+        assert!(rules.check(client, &headers).is_err());
+
+        headers.typed_insert(Authorization::bearer("blue-pill").unwrap());
+        assert!(rules.check(client, &headers).is_err());
+
+        headers.typed_insert(Authorization::bearer("red-pill").unwrap());
+        assert_eq!(rules.check(client, &headers), Ok(()));
+    }
+}
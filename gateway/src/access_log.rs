@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use http::header::CONTENT_TYPE;
+use http::HeaderMap;
+use hyper::body::{Buf, HttpBody};
+use hyper::client::HttpConnector;
+use hyper::{Body, Client, Request};
+use once_cell::sync::Lazy;
+use pin_project::{pin_project, pinned_drop};
+use ring::digest::{Context as Digest, SHA256};
+use shuttle_common::models::access_log::Entry;
+use tokio::sync::mpsc::{self, error::TrySendError, Receiver, Sender};
+use tokio::time::{interval, timeout};
+use tracing::{debug, warn};
+
+use crate::service::GatewayService;
+use crate::ProjectName;
+
+/// How many entries can wait to be shipped, after which new ones are dropped
+const QUEUE_SIZE: usize = 8192;
+
+/// How many entries of a project are shipped at most at once
+const MAX_BATCH: usize = 512;
+
+/// How often the entries which are waiting are shipped
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long the deployer of a project has to take the entries
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+static CLIENT: Lazy<Client<HttpConnector>> = Lazy::new(Client::new);
+
+/// Records the requests proxied to projects, and ships them in batches to the deployers of the
+/// projects to be kept with their logs
+#[derive(Clone)]
+pub struct AccessLogger {
+    sender: Sender<(ProjectName, Entry)>,
+    /// Made up when the gateway starts, so that the hashes of clients cannot be reversed by
+    /// hashing every address
+    salt: [u8; 16],
+}
+
+impl AccessLogger {
+    /// Start shipping the entries which are recorded to the deployers of `gateway`
+    pub fn spawn(gateway: Arc<GatewayService>) -> Self {
+        let (sender, receiver) = mpsc::channel(QUEUE_SIZE);
+        tokio::spawn(ship(gateway, receiver));
+
+        Self {
+            sender,
+            salt: rand::random(),
+        }
+    }
+
+    /// Tell the `client` apart from the other clients without keeping its address
+    pub fn hash_client(&self, client: IpAddr) -> String {
+        let mut digest = Digest::new(&SHA256);
+        digest.update(&self.salt);
+        match client {
+            IpAddr::V4(ip) => digest.update(&ip.octets()),
+            IpAddr::V6(ip) => digest.update(&ip.octets()),
+        }
+
+        digest
+            .finish()
+            .as_ref()
+            .iter()
+            .take(8)
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Record a request to the project, dropping it if the deployers are not keeping up
+    pub fn record(&self, project_name: ProjectName, entry: Entry) {
+        if let Err(TrySendError::Full(_)) = self.sender.try_send((project_name, entry)) {
+            debug!("dropping access log as the queue is full");
+        }
+    }
+}
+
+async fn ship(gateway: Arc<GatewayService>, mut receiver: Receiver<(ProjectName, Entry)>) {
+    let mut batches: HashMap<ProjectName, Vec<Entry>> = HashMap::new();
+    let mut flush = interval(FLUSH_INTERVAL);
+
+    loop {
+        tokio::select! {
+            received = receiver.recv() => {
+                let Some((project_name, entry)) = received else {
+                    return;
+                };
+
+                let batch = batches.entry(project_name.clone()).or_default();
+                batch.push(entry);
+
+                if batch.len() >= MAX_BATCH {
+                    let batch = batches.remove(&project_name).unwrap_or_default();
+                    tokio::spawn(deliver(gateway.clone(), project_name, batch));
+                }
+            }
+            _ = flush.tick() => {
+                for (project_name, batch) in batches.drain() {
+                    tokio::spawn(deliver(gateway.clone(), project_name, batch));
+                }
+            }
+        }
+    }
+}
+
+/// Post the entries to the deployer of the project, if it is still running
+async fn deliver(gateway: Arc<GatewayService>, project_name: ProjectName, entries: Vec<Entry>) {
+    let target_ip = match gateway
+        .find_project(&project_name)
+        .await
+        .and_then(|project| project.target_ip())
+    {
+        Ok(Some(target_ip)) => target_ip,
+        Ok(None) => return,
+        Err(error) => {
+            warn!(%project_name, %error, "failed to find the project to ship access logs to");
+            return;
+        }
+    };
+
+    let request = Request::post(format!(
+        "http://{target_ip}:8001/projects/{project_name}/access-logs"
+    ))
+    .header(CONTENT_TYPE, "application/json")
+    .body(Body::from(
+        serde_json::to_vec(&entries).expect("to serialize the access logs"),
+    ))
+    .expect("to build the access logs request");
+
+    match timeout(DELIVERY_TIMEOUT, CLIENT.request(request)).await {
+        Ok(Ok(response)) if response.status().is_success() => {}
+        Ok(Ok(response)) => {
+            warn!(%project_name, status = %response.status(), "deployer refused the access logs")
+        }
+        Ok(Err(error)) => warn!(%project_name, %error, "failed to ship the access logs"),
+        Err(_elapsed) => warn!(%project_name, "timed out shipping the access logs"),
+    }
+}
+
+/// Counts the bytes of the body of a response, and records its request once it is done
+#[pin_project(PinnedDrop)]
+pub struct LoggedBody<B> {
+    #[pin]
+    inner: B,
+    logger: AccessLogger,
+    /// The request, until it is recorded
+    pending: Option<(ProjectName, Entry)>,
+}
+
+impl<B> LoggedBody<B> {
+    pub fn new(inner: B, logger: AccessLogger, project_name: ProjectName, entry: Entry) -> Self {
+        Self {
+            inner,
+            logger,
+            pending: Some((project_name, entry)),
+        }
+    }
+}
+
+impl<B: HttpBody> HttpBody for LoggedBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        let poll = this.inner.poll_data(cx);
+
+        if let Poll::Ready(Some(Ok(data))) = &poll {
+            if let Some((_, entry)) = this.pending {
+                entry.bytes += data.remaining() as u64;
+            }
+        }
+
+        poll
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        self.project().inner.poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> hyper::body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+// The body is dropped both when it was sent and when the client went away, which is recorded
+// with the bytes sent until then
+#[pinned_drop]
+impl<B> PinnedDrop for LoggedBody<B> {
+    fn drop(self: Pin<&mut Self>) {
+        let this = self.project();
+        if let Some((project_name, entry)) = this.pending.take() {
+            this.logger.record(project_name, entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use hyper::body::Body;
+    use shuttle_common::models::access_log::Entry;
+    use tokio::sync::mpsc;
+
+    use super::{AccessLogger, LoggedBody};
+
+    #[tokio::test]
+    async fn records_bytes_once_body_is_done() {
+        let (sender, mut receiver) = mpsc::channel(1);
+        let logger = AccessLogger {
+            sender,
+            salt: [0; 16],
+        };
+        let entry = Entry {
+            timestamp: Utc::now(),
+            service: "matrix".to_string(),
+            method: "GET".to_string(),
+            path: "/".to_string(),
+            status: 200,
+            latency_ms: 1,
+            bytes: 0,
+            client: logger.hash_client("10.0.0.1".parse().unwrap()),
+        };
+
+        let body = LoggedBody::new(
+            Body::from("wake up, neo"),
+            logger.clone(),
+            "matrix".parse().unwrap(),
+            entry.clone(),
+        );
+        let bytes = hyper::body::to_bytes(body).await.unwrap();
+        assert_eq!(bytes.len(), 12);
+
+        let (project_name, recorded) = receiver.recv().await.unwrap();
+        assert_eq!(project_name.to_string(), "matrix");
+        assert_eq!(recorded, Entry { bytes: 12, ..entry });
+    }
+
+    #[test]
+    fn client_hashes() {
+        let (sender, _receiver) = mpsc::channel(1);
+        let logger = AccessLogger {
+            sender,
+            salt: rand::random(),
+        };
+        let neo = logger.hash_client("10.0.0.1".parse().unwrap());
+
+        assert_eq!(neo.len(), 16);
+        assert_eq!(neo, logger.hash_client("10.0.0.1".parse().unwrap()));
+        assert_ne!(neo, logger.hash_client("10.0.0.2".parse().unwrap()));
+        assert_ne!(neo, logger.hash_client("::1".parse().unwrap()));
+    }
+}
@@ -19,6 +19,10 @@ use tokio::sync::Mutex;
 use tokio::time::sleep;
 use tower::{Layer, Service};
 use tracing::{error, trace, warn};
+use x509_parser::nom::AsBytes;
+use x509_parser::parse_x509_certificate;
+use x509_parser::pem::parse_x509_pem;
+use x509_parser::time::ASN1Time;
 
 use crate::proxy::AsResponderTo;
 use crate::{Error, ProjectName};
@@ -34,6 +38,16 @@ pub struct CustomDomain {
     pub private_key: String,
 }
 
+impl CustomDomain {
+    /// When the certificate of the domain stops being valid, if it can be parsed
+    pub fn not_after(&self) -> Option<ASN1Time> {
+        let (_, pem) = parse_x509_pem(self.certificate.as_bytes()).ok()?;
+        let (_, certificate) = parse_x509_certificate(pem.contents.as_bytes()).ok()?;
+
+        Some(certificate.validity().not_after)
+    }
+}
+
 /// An ACME client implementation that completes Http01 challenges
 /// It is safe to clone this type as it functions as a singleton
 #[derive(Clone, Default)]
@@ -112,7 +112,7 @@ where
 
         let forward_to_auth = match req.uri().path() {
             "/login" | "/logout" => true,
-            other => other.starts_with("/users"),
+            other => other.starts_with("/users") || other.starts_with("/login/device"),
         };
 
         // If /users/reset-api-key is called, invalidate the cached JWT.
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use shuttle_common::claims::EXP_MINUTES;
+use shuttle_common::models::stats;
+use ttl_cache::TtlCache;
+use uuid::Uuid;
+
+/// How long a build is counted as waiting for a slot after it last asked for one. Deployers ask
+/// every second while they wait.
+const WAITING_TTL: Duration = Duration::from_secs(5);
+
+/// How long a build keeps its slot, in case its deployer never gives it back
+const RUNNING_TTL: Duration = Duration::from_secs(60 * EXP_MINUTES as u64);
+
+/// The build slots shared by the deployers of every project. Free slots go to the builds of the
+/// projects with the fewest running builds first, and then to the builds which waited longest, so
+/// that a project which deploys a lot cannot take all of them.
+pub struct BuildQueue {
+    /// The builds which have a slot, with their project
+    running: TtlCache<Uuid, Option<String>>,
+    /// The builds which asked for a slot and did not get one yet
+    waiting: HashMap<Uuid, Waiting>,
+}
+
+struct Waiting {
+    /// The project of the build, which older deployers do not tell
+    project: Option<String>,
+    /// When the build first asked for a slot
+    since: Instant,
+    /// When the build last asked for a slot
+    last_seen: Instant,
+}
+
+impl BuildQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            running: TtlCache::new(capacity),
+            waiting: HashMap::new(),
+        }
+    }
+
+    /// Ask for a slot for build `id` of `project`, getting whether it has one
+    pub fn request(&mut self, id: Uuid, project: Option<String>) -> bool {
+        if self.running.contains_key(&id) {
+            return true;
+        }
+
+        let now = Instant::now();
+        // Builds whose deployers stopped asking were cancelled or went away
+        self.waiting
+            .retain(|_, waiting| now.duration_since(waiting.last_seen) < WAITING_TTL);
+        self.waiting
+            .entry(id)
+            .or_insert(Waiting {
+                project,
+                since: now,
+                last_seen: now,
+            })
+            .last_seen = now;
+
+        let free = self
+            .running
+            .capacity()
+            .saturating_sub(self.running.iter().count());
+
+        // The build gets a slot when there are enough free slots for the builds ahead of it
+        let mut running_by_project: HashMap<&Option<String>, usize> = HashMap::new();
+        for (_, project) in self.running.iter() {
+            *running_by_project.entry(project).or_default() += 1;
+        }
+        let rank = |id: &Uuid, waiting: &Waiting| {
+            (
+                running_by_project
+                    .get(&waiting.project)
+                    .copied()
+                    .unwrap_or_default(),
+                waiting.since,
+                *id,
+            )
+        };
+        let own = rank(&id, &self.waiting[&id]);
+        let ahead = self
+            .waiting
+            .iter()
+            .filter(|(id, waiting)| rank(id, waiting) < own)
+            .count();
+
+        if ahead >= free {
+            return false;
+        }
+
+        let waiting = self.waiting.remove(&id).expect("the build to be waiting");
+        self.running.insert(id, waiting.project, RUNNING_TTL);
+
+        true
+    }
+
+    /// Give back the slot of build `id`, or stop waiting for one
+    pub fn release(&mut self, id: &Uuid) {
+        self.running.remove(id);
+        self.waiting.remove(id);
+    }
+
+    /// Give back every slot
+    pub fn clear(&mut self) {
+        self.running.clear();
+        self.waiting.clear();
+    }
+
+    /// How many builds have a slot, and whether there are free ones
+    pub fn load(&mut self) -> stats::LoadResponse {
+        let active = self.running.iter().count();
+
+        stats::LoadResponse {
+            builds_count: active,
+            has_capacity: active < self.running.capacity(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::BuildQueue;
+
+    fn project(name: &str) -> Option<String> {
+        Some(name.to_string())
+    }
+
+    #[test]
+    fn interleaves_projects() {
+        let mut queue = BuildQueue::new(2);
+
+        // A project which deploys a lot takes the free slots while it is alone
+        let spammy: Vec<_> = (0..4).map(|_| Uuid::new_v4()).collect();
+        assert!(queue.request(spammy[0], project("spammy")));
+        assert!(queue.request(spammy[1], project("spammy")));
+        assert!(!queue.request(spammy[2], project("spammy")));
+        assert!(!queue.request(spammy[3], project("spammy")));
+
+        // Another project which waited less gets the next slot, since it has no running builds
+        let quiet = Uuid::new_v4();
+        assert!(!queue.request(quiet, project("quiet")));
+
+        queue.release(&spammy[0]);
+        assert!(!queue.request(spammy[2], project("spammy")));
+        assert!(queue.request(quiet, project("quiet")));
+
+        // Then the builds of the first project go by when they started waiting
+        queue.release(&spammy[1]);
+        assert!(!queue.request(spammy[3], project("spammy")));
+        assert!(queue.request(spammy[2], project("spammy")));
+        let load = queue.load();
+        assert_eq!(load.builds_count, 2);
+        assert!(!load.has_capacity);
+
+        // A build which has a slot keeps it when it asks again
+        assert!(queue.request(quiet, project("quiet")));
+    }
+
+    #[test]
+    fn released_builds_stop_waiting() {
+        let mut queue = BuildQueue::new(1);
+
+        let running = Uuid::new_v4();
+        let cancelled = Uuid::new_v4();
+        let next = Uuid::new_v4();
+        assert!(queue.request(running, project("a")));
+        assert!(!queue.request(cancelled, project("b")));
+        assert!(!queue.request(next, project("b")));
+
+        queue.release(&cancelled);
+        queue.release(&running);
+        assert!(queue.request(next, project("b")));
+    }
+}
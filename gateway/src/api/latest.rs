@@ -7,48 +7,57 @@ use std::time::Duration;
 use axum::body::Body;
 use axum::extract::{Extension, Path, Query, State};
 use axum::handler::Handler;
-use axum::http::Request;
+use axum::http::{Method, Request};
 use axum::middleware::from_extractor;
-use axum::response::Response;
-use axum::routing::{any, get, post};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{any, delete, get, post};
 use axum::{Json as AxumJson, Router};
+use chrono::{TimeZone, Utc};
 use fqdn::FQDN;
 use futures::Future;
-use http::{StatusCode, Uri};
+use http::header::{HeaderName, CONTENT_TYPE, RETRY_AFTER};
+use http::{HeaderValue, StatusCode, Uri};
 use instant_acme::{AccountCredentials, ChallengeType};
+use prometheus::TEXT_FORMAT;
 use serde::{Deserialize, Serialize};
 use shuttle_common::backends::auth::{AuthPublicKey, JwtAuthenticationLayer, ScopedLayer};
 use shuttle_common::backends::cache::CacheManager;
+use shuttle_common::backends::headers::X_SHUTTLE_VERSION;
 use shuttle_common::backends::metrics::{Metrics, TraceLayer};
-use shuttle_common::claims::{Scope, EXP_MINUTES};
+use shuttle_common::claims::Scope;
 use shuttle_common::models::error::ErrorKind;
-use shuttle_common::models::{project, stats};
+use shuttle_common::models::{access, cache, domain, headers, node, project, route, stats};
 use shuttle_common::request_span;
 use tokio::sync::mpsc::Sender;
-use tokio::sync::{Mutex, MutexGuard};
-use tracing::{field, instrument, trace};
-use ttl_cache::TtlCache;
+use tokio::sync::Mutex;
+use tower_http::set_header::SetResponseHeaderLayer;
+use tracing::{field, instrument, trace, warn};
 use utoipa::IntoParams;
 
 use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
 use utoipa::{Modify, OpenApi};
 use utoipa_swagger_ui::SwaggerUi;
-use uuid::Uuid;
 use x509_parser::nom::AsBytes;
 use x509_parser::parse_x509_certificate;
 use x509_parser::pem::parse_x509_pem;
 use x509_parser::time::ASN1Time;
 
+use crate::access::AccessRules;
 use crate::acme::{AcmeClient, CustomDomain};
 use crate::auth::{ScopedUser, User};
+use crate::cache::ResponseCache;
+use crate::monitoring;
 use crate::project::{ContainerInspectResponseExt, Project, ProjectCreating};
 use crate::service::GatewayService;
 use crate::task::{self, BoxedTask, TaskResult};
+use crate::tcp::TcpForwarder;
 use crate::tls::{GatewayCertResolver, RENEWAL_VALIDITY_THRESHOLD_IN_DAYS};
 use crate::worker::WORKER_QUEUE_SIZE;
 use crate::{Error, ProjectName};
 
 use super::auth_layer::ShuttleAuthLayer;
+use super::build_queue::BuildQueue;
+use super::rate_limit::RateLimiter;
 
 pub const SVC_DEGRADED_THRESHOLD: usize = 128;
 
@@ -63,6 +72,8 @@ pub enum GatewayStatus {
 #[derive(Serialize, Deserialize)]
 pub struct StatusResponse {
     status: GatewayStatus,
+    /// The version of the gateway, so that clients can tell whether they are compatible with it
+    version: String,
 }
 
 #[derive(Debug, Clone, Copy, Deserialize, IntoParams)]
@@ -77,18 +88,21 @@ impl StatusResponse {
     pub fn healthy() -> Self {
         Self {
             status: GatewayStatus::Healthy,
+            version: env!("CARGO_PKG_VERSION").to_string(),
         }
     }
 
     pub fn degraded() -> Self {
         Self {
             status: GatewayStatus::Degraded,
+            version: env!("CARGO_PKG_VERSION").to_string(),
         }
     }
 
     pub fn unhealthy() -> Self {
         Self {
             status: GatewayStatus::Unhealthy,
+            version: env!("CARGO_PKG_VERSION").to_string(),
         }
     }
 }
@@ -113,6 +127,7 @@ async fn get_project(
     let response = project::Response {
         name: scope.to_string(),
         state,
+        tcp: service.tcp_ports(&scope).await?,
     };
 
     Ok(AxumJson(response))
@@ -143,6 +158,7 @@ async fn get_projects_list(
         .map(|project| project::Response {
             name: project.0.to_string(),
             state: project.1.into(),
+            tcp: Vec::new(),
         })
         .collect();
 
@@ -184,6 +200,7 @@ async fn create_project(
     let response = project::Response {
         name: project.to_string(),
         state: state.into(),
+        tcp: Vec::new(),
     };
 
     Ok(AxumJson(response))
@@ -212,6 +229,7 @@ async fn destroy_project(
     let mut response = project::Response {
         name: project.to_string(),
         state: state.into(),
+        tcp: Vec::new(),
     };
 
     if response.state == shuttle_common::models::project::State::Destroyed {
@@ -231,20 +249,399 @@ async fn destroy_project(
     Ok(AxumJson(response))
 }
 
+#[instrument(skip_all, fields(%project))]
+#[utoipa::path(
+    post,
+    path = "/projects/{project_name}/restart",
+    responses(
+        (status = 200, description = "Successfully started restarting a specific project.", body = shuttle_common::models::project::Response),
+        (status = 400, description = "The project has no container to restart."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+    )
+)]
+async fn restart_project(
+    State(RouterState {
+        service, sender, ..
+    }): State<RouterState>,
+    ScopedUser { scope: project, .. }: ScopedUser,
+) -> Result<AxumJson<project::Response>, Error> {
+    // Fail early for projects without a container, like destroyed ones, which need to be started
+    // instead
+    let state = service.find_project(&project).await?.reboot()?;
+
+    service
+        .new_task()
+        .project(project.clone())
+        .and_then(task::reboot())
+        .and_then(task::run_until_done())
+        .send(&sender)
+        .await?;
+
+    let response = project::Response {
+        name: project.to_string(),
+        state: state.into(),
+        tcp: Vec::new(),
+    };
+
+    Ok(AxumJson(response))
+}
+
+/// Bytes the maintenance page of a project may have
+const MAX_MAINTENANCE_PAGE_BYTES: usize = 64 * 1024;
+
+#[instrument(skip_all, fields(%project))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/maintenance",
+    responses(
+        (status = 200, description = "Got what is served instead of the project while it is under maintenance, or null when it is not.", body = Option<shuttle_common::models::project::Maintenance>),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+    )
+)]
+async fn get_maintenance(
+    State(RouterState { service, .. }): State<RouterState>,
+    ScopedUser { scope: project, .. }: ScopedUser,
+) -> Result<AxumJson<Option<project::Maintenance>>, Error> {
+    Ok(AxumJson(service.maintenance(&project).await?))
+}
+
+#[instrument(skip_all, fields(%project))]
+#[utoipa::path(
+    put,
+    path = "/projects/{project_name}/maintenance",
+    request_body = shuttle_common::models::project::Maintenance,
+    responses(
+        (status = 200, description = "Put the project under maintenance.", body = shuttle_common::models::project::Maintenance),
+        (status = 400, description = "The status or the page cannot be served."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+    )
+)]
+async fn put_maintenance(
+    State(RouterState { service, .. }): State<RouterState>,
+    ScopedUser { scope: project, .. }: ScopedUser,
+    AxumJson(maintenance): AxumJson<project::Maintenance>,
+) -> Result<AxumJson<project::Maintenance>, Error> {
+    if !(200..600).contains(&maintenance.status) {
+        return Err(Error::custom(
+            ErrorKind::InvalidOperation,
+            "the status of the maintenance page has to be from 200 to 599",
+        ));
+    }
+
+    if maintenance
+        .page
+        .as_ref()
+        .map_or(false, |page| page.len() > MAX_MAINTENANCE_PAGE_BYTES)
+    {
+        return Err(Error::custom(
+            ErrorKind::InvalidOperation,
+            format!(
+                "the maintenance page can have at most {} KiB",
+                MAX_MAINTENANCE_PAGE_BYTES / 1024
+            ),
+        ));
+    }
+
+    service.set_maintenance(&project, &maintenance).await?;
+
+    Ok(AxumJson(maintenance))
+}
+
+#[instrument(skip_all, fields(%project))]
+#[utoipa::path(
+    delete,
+    path = "/projects/{project_name}/maintenance",
+    responses(
+        (status = 200, description = "Went back to serving the project, and got what was served instead of it.", body = Option<shuttle_common::models::project::Maintenance>),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+    )
+)]
+async fn delete_maintenance(
+    State(RouterState { service, .. }): State<RouterState>,
+    ScopedUser { scope: project, .. }: ScopedUser,
+) -> Result<AxumJson<Option<project::Maintenance>>, Error> {
+    let maintenance = service.maintenance(&project).await?;
+    service.end_maintenance(&project).await?;
+
+    Ok(AxumJson(maintenance))
+}
+
+#[instrument(skip_all, fields(%project, %service_name))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/access/{service_name}",
+    responses(
+        (status = 200, description = "Got the rules for the clients of the service.", body = shuttle_common::models::access::Response),
+        (status = 400, description = "The service is not of the project."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+        ("service_name" = String, Path, description = "The name of the service, which is that of the project or of one of its environments."),
+    )
+)]
+async fn get_access_rules(
+    State(RouterState { service, .. }): State<RouterState>,
+    ScopedUser { scope: project, .. }: ScopedUser,
+    Path((_, service_name)): Path<(ProjectName, String)>,
+) -> Result<AxumJson<access::Response>, Error> {
+    check_service_of(&project, &service_name)?;
+
+    let rules = service
+        .access_rules(&project, &service_name)
+        .await?
+        .unwrap_or_else(|| AccessRules::new(Default::default()));
+
+    Ok(AxumJson(rules.into_response(service_name)))
+}
+
+#[instrument(skip_all, fields(%project, %service_name))]
+#[utoipa::path(
+    put,
+    path = "/projects/{project_name}/access/{service_name}",
+    request_body = shuttle_common::models::access::Request,
+    responses(
+        (status = 200, description = "Replaced the rules for the clients of the service.", body = shuttle_common::models::access::Response),
+        (status = 400, description = "The service is not of the project."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+        ("service_name" = String, Path, description = "The name of the service, which is that of the project or of one of its environments."),
+    )
+)]
+async fn put_access_rules(
+    State(RouterState { service, .. }): State<RouterState>,
+    ScopedUser { scope: project, .. }: ScopedUser,
+    Path((_, service_name)): Path<(ProjectName, String)>,
+    AxumJson(request): AxumJson<access::Request>,
+) -> Result<AxumJson<access::Response>, Error> {
+    check_service_of(&project, &service_name)?;
+
+    let rules = AccessRules::new(request);
+    service
+        .set_access_rules(&project, &service_name, &rules)
+        .await?;
+
+    Ok(AxumJson(rules.into_response(service_name)))
+}
+
+#[instrument(skip_all, fields(%project, %service_name))]
+#[utoipa::path(
+    delete,
+    path = "/projects/{project_name}/access/{service_name}",
+    responses(
+        (status = 200, description = "Let every client through to the service again.", body = shuttle_common::models::access::Response),
+        (status = 400, description = "The service is not of the project."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+        ("service_name" = String, Path, description = "The name of the service, which is that of the project or of one of its environments."),
+    )
+)]
+async fn delete_access_rules(
+    State(RouterState { service, .. }): State<RouterState>,
+    ScopedUser { scope: project, .. }: ScopedUser,
+    Path((_, service_name)): Path<(ProjectName, String)>,
+) -> Result<AxumJson<access::Response>, Error> {
+    check_service_of(&project, &service_name)?;
+
+    service.delete_access_rules(&project, &service_name).await?;
+
+    Ok(AxumJson(
+        AccessRules::new(Default::default()).into_response(service_name),
+    ))
+}
+
+#[instrument(skip_all, fields(%project, %service_name))]
+#[utoipa::path(
+    delete,
+    path = "/projects/{project_name}/cache/{service_name}",
+    responses(
+        (status = 200, description = "Dropped the cached responses of the service.", body = shuttle_common::models::cache::Response),
+        (status = 400, description = "The service is not of the project."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+        ("service_name" = String, Path, description = "The name of the service, which is that of the project or of one of its environments."),
+        ("path" = Option<String>, Query, description = "Only drop the responses to the paths starting with this."),
+    )
+)]
+async fn purge_cache(
+    Extension(response_cache): Extension<Arc<ResponseCache>>,
+    ScopedUser { scope: project, .. }: ScopedUser,
+    Path((_, service_name)): Path<(ProjectName, String)>,
+    Query(purge): Query<cache::Purge>,
+) -> Result<AxumJson<cache::Response>, Error> {
+    check_service_of(&project, &service_name)?;
+
+    let (responses, bytes) = response_cache.purge(&project, &service_name, purge.path.as_deref());
+
+    Ok(AxumJson(cache::Response {
+        service: service_name,
+        responses,
+        bytes,
+    }))
+}
+
+#[instrument(skip_all, fields(%project))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/tcp",
+    responses(
+        (status = 200, description = "Got the ports which forward raw TCP to the project.", body = [shuttle_common::models::project::TcpEndpoint]),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+    )
+)]
+async fn get_tcp_ports(
+    State(RouterState { service, .. }): State<RouterState>,
+    ScopedUser { scope: project, .. }: ScopedUser,
+) -> Result<AxumJson<Vec<project::TcpEndpoint>>, Error> {
+    Ok(AxumJson(service.tcp_ports(&project).await?))
+}
+
+#[instrument(skip_all, fields(%project, target_port = request.target_port))]
+#[utoipa::path(
+    post,
+    path = "/projects/{project_name}/tcp",
+    request_body = shuttle_common::models::project::TcpRequest,
+    responses(
+        (status = 200, description = "Got a port which forwards raw TCP to the port of the project.", body = shuttle_common::models::project::TcpEndpoint),
+        (status = 400, description = "The project has as many ports as it may have."),
+        (status = 503, description = "Every port is taken."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+    )
+)]
+async fn post_tcp_port(
+    State(RouterState { service, .. }): State<RouterState>,
+    Extension(forwarder): Extension<Arc<TcpForwarder>>,
+    ScopedUser { scope: project, .. }: ScopedUser,
+    AxumJson(request): AxumJson<project::TcpRequest>,
+) -> Result<AxumJson<project::TcpEndpoint>, Error> {
+    if request.target_port == 0 {
+        return Err(Error::from_kind(ErrorKind::InvalidOperation));
+    }
+
+    let endpoint = service
+        .allocate_tcp_port(&project, request.target_port, forwarder.ports())
+        .await?;
+
+    if let Err(error) = forwarder.listen(project.clone(), endpoint.port, endpoint.target_port) {
+        service.release_tcp_port(&project, endpoint.port).await?;
+
+        return Err(Error::source(ErrorKind::Internal, error));
+    }
+
+    Ok(AxumJson(endpoint))
+}
+
+#[instrument(skip_all, fields(%project, %port))]
+#[utoipa::path(
+    delete,
+    path = "/projects/{project_name}/tcp/{port}",
+    responses(
+        (status = 200, description = "Stopped forwarding raw TCP on the port, and got what it forwarded to.", body = shuttle_common::models::project::TcpEndpoint),
+        (status = 400, description = "The port is not one of the project."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+        ("port" = u16, Path, description = "The port of the gateway."),
+    )
+)]
+async fn delete_tcp_port(
+    State(RouterState { service, .. }): State<RouterState>,
+    Extension(forwarder): Extension<Arc<TcpForwarder>>,
+    ScopedUser { scope: project, .. }: ScopedUser,
+    Path((_, port)): Path<(ProjectName, u16)>,
+) -> Result<AxumJson<project::TcpEndpoint>, Error> {
+    let endpoint = service
+        .release_tcp_port(&project, port)
+        .await?
+        .ok_or_else(|| Error::from_kind(ErrorKind::InvalidOperation))?;
+
+    forwarder.close(port);
+
+    Ok(AxumJson(endpoint))
+}
+
+/// The service has to be the one of the project, or of one of its environments
+fn check_service_of(project_name: &ProjectName, service_name: &str) -> Result<(), Error> {
+    match shuttle_common::project::ProjectName::split_service_name(service_name) {
+        (project, _) if project == project_name.as_str() => Ok(()),
+        _ => Err(Error::from_kind(ErrorKind::InvalidOperation)),
+    }
+}
+
 #[instrument(skip_all, fields(scope = %scoped_user.scope))]
 async fn route_project(
     State(RouterState {
-        service, sender, ..
+        service,
+        sender,
+        deploy_limiter,
+        ..
     }): State<RouterState>,
     scoped_user: ScopedUser,
     req: Request<Body>,
-) -> Result<Response<Body>, Error> {
+) -> Result<Response, Error> {
+    if is_deploy(&req) && !scoped_user.user.claim.scopes.contains(&Scope::Admin) {
+        if let Some(deploy_limiter) = deploy_limiter {
+            if let Err(retry_after) = deploy_limiter.check(&scoped_user.user.name.to_string()) {
+                warn!(
+                    account.name = %scoped_user.user.name,
+                    ?retry_after,
+                    "refusing deployment over the rate limit"
+                );
+
+                let mut response = Error::from_kind(ErrorKind::TooManyRequests).into_response();
+                response
+                    .headers_mut()
+                    .insert(RETRY_AFTER, HeaderValue::from(retry_after.as_secs().max(1)));
+
+                return Ok(response);
+            }
+        }
+    }
+
     let project_name = scoped_user.scope;
     let project = service.find_or_start_project(&project_name, sender).await?;
 
     service
         .route(&project, &project_name, &scoped_user.user.name, req)
         .await
+        .map(IntoResponse::into_response)
+}
+
+/// Whether `req` deploys a service, which is a `POST` to the service on its deployer
+fn is_deploy(req: &Request<Body>) -> bool {
+    let path = req.uri().path();
+    let path = path.strip_prefix("/v1").unwrap_or(path);
+
+    req.method() == Method::POST
+        && matches!(
+            path.split('/').collect::<Vec<_>>()[..],
+            ["", "projects", _, "services", _]
+        )
 }
 
 #[utoipa::path(
@@ -284,24 +681,18 @@ async fn get_status(State(RouterState { sender, .. }): State<RouterState>) -> Re
     )
 )]
 async fn post_load(
-    State(RouterState { running_builds, .. }): State<RouterState>,
+    State(RouterState { build_queue, .. }): State<RouterState>,
     AxumJson(build): AxumJson<stats::LoadRequest>,
 ) -> Result<AxumJson<stats::LoadResponse>, Error> {
-    let mut running_builds = running_builds.lock().await;
+    let mut build_queue = build_queue.lock().await;
 
-    trace!(id = %build.id, "checking build queue");
-    let mut load = calculate_capacity(&mut running_builds);
+    trace!(id = %build.id, project = ?build.project, "checking build queue");
+    let got_slot = build_queue.request(build.id, build.project);
 
-    if load.has_capacity
-        && running_builds
-            .insert(build.id, (), Duration::from_secs(60 * EXP_MINUTES as u64))
-            .is_none()
-    {
-        // Only increase when an item was not already in the queue
-        load.builds_count += 1;
-    }
-
-    Ok(AxumJson(load))
+    Ok(AxumJson(stats::LoadResponse {
+        has_capacity: got_slot,
+        ..build_queue.load()
+    }))
 }
 
 #[instrument(skip_all)]
@@ -314,16 +705,309 @@ async fn post_load(
     )
 )]
 async fn delete_load(
-    State(RouterState { running_builds, .. }): State<RouterState>,
+    State(RouterState { build_queue, .. }): State<RouterState>,
     AxumJson(build): AxumJson<stats::LoadRequest>,
 ) -> Result<AxumJson<stats::LoadResponse>, Error> {
-    let mut running_builds = running_builds.lock().await;
-    running_builds.remove(&build.id);
+    let mut build_queue = build_queue.lock().await;
+    build_queue.release(&build.id);
 
     trace!(id = %build.id, "removing from build queue");
-    let load = calculate_capacity(&mut running_builds);
 
-    Ok(AxumJson(load))
+    Ok(AxumJson(build_queue.load()))
+}
+
+#[instrument(skip_all, fields(project = %route.project, service = %route.service, deployment_id = %route.deployment_id))]
+#[utoipa::path(
+    post,
+    path = "/routes",
+    responses(
+        (status = 200, description = "Routed the host of the service to the deployment.", body = shuttle_common::models::route::Response),
+        (status = 400, description = "The service is not of an environment of the project."),
+        (status = 500, description = "Server internal error.")
+    )
+)]
+async fn post_route(
+    State(RouterState { service, .. }): State<RouterState>,
+    AxumJson(route): AxumJson<route::Request>,
+) -> Result<AxumJson<route::Response>, Error> {
+    let project_name = environment_project(&route)?;
+    service.find_project(&project_name).await?;
+
+    let hostname = service
+        .set_route(&project_name, &route.service, &route.deployment_id)
+        .await?;
+
+    trace!(%hostname, "routed service");
+
+    Ok(AxumJson(route::Response {
+        hostname: hostname.to_string(),
+        project: route.project,
+        service: route.service,
+        deployment_id: route.deployment_id,
+    }))
+}
+
+#[instrument(skip_all, fields(project = %route.project, service = %route.service, deployment_id = %route.deployment_id))]
+#[utoipa::path(
+    delete,
+    path = "/routes",
+    responses(
+        (status = 200, description = "Removed the route of the service, if it was still to the deployment."),
+        (status = 400, description = "The service is not of an environment of the project."),
+        (status = 500, description = "Server internal error.")
+    )
+)]
+async fn delete_route(
+    State(RouterState { service, .. }): State<RouterState>,
+    AxumJson(route): AxumJson<route::Request>,
+) -> Result<AxumJson<()>, Error> {
+    let project_name = environment_project(&route)?;
+
+    service
+        .delete_route(&project_name, &route.service, &route.deployment_id)
+        .await?;
+
+    Ok(AxumJson(()))
+}
+
+#[instrument(skip_all, fields(project = %request.project, service = %request.service))]
+#[utoipa::path(
+    post,
+    path = "/headers",
+    responses(
+        (status = 200, description = "Set the headers added to the responses of the service."),
+        (status = 400, description = "The service is not of the project."),
+        (status = 500, description = "Server internal error.")
+    )
+)]
+async fn post_headers(
+    State(RouterState { service, .. }): State<RouterState>,
+    AxumJson(request): AxumJson<headers::Request>,
+) -> Result<AxumJson<()>, Error> {
+    let project_name: ProjectName = request
+        .project
+        .parse()
+        .map_err(|_| Error::from_kind(ErrorKind::InvalidProjectName))?;
+
+    check_service_of(&project_name, &request.service)?;
+
+    service.find_project(&project_name).await?;
+    service
+        .set_http_headers(&project_name, &request.service, &request.http)
+        .await?;
+
+    Ok(AxumJson(()))
+}
+
+/// The project of a route, whose service has to be of one of the environments of the project. The
+/// main service of a project is served on the host of the project, which needs no route.
+fn environment_project(route: &route::Request) -> Result<ProjectName, Error> {
+    let project_name: ProjectName = route
+        .project
+        .parse()
+        .map_err(|_| Error::from_kind(ErrorKind::InvalidProjectName))?;
+
+    match shuttle_common::project::ProjectName::split_service_name(&route.service) {
+        (project, Some(_environment)) if project == project_name.as_str() => Ok(project_name),
+        _ => Err(Error::from_kind(ErrorKind::InvalidOperation)),
+    }
+}
+
+#[instrument(skip(service))]
+#[utoipa::path(
+    get,
+    path = "/admin/projects/{project_name}/limits",
+    responses(
+        (status = 200, description = "Got the limits of the requests to the project.", body = shuttle_common::models::project::ProxyLimits),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+    )
+)]
+async fn get_proxy_limits(
+    State(RouterState { service, .. }): State<RouterState>,
+    Path(project_name): Path<ProjectName>,
+) -> Result<AxumJson<project::ProxyLimits>, Error> {
+    service.find_project(&project_name).await?;
+
+    Ok(AxumJson(service.proxy_limits(&project_name).await?))
+}
+
+#[instrument(skip(service))]
+#[utoipa::path(
+    put,
+    path = "/admin/projects/{project_name}/limits",
+    request_body = shuttle_common::models::project::ProxyLimits,
+    responses(
+        (status = 200, description = "Gave the project limits of its own.", body = shuttle_common::models::project::ProxyLimits),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+    )
+)]
+async fn put_proxy_limits(
+    State(RouterState { service, .. }): State<RouterState>,
+    Path(project_name): Path<ProjectName>,
+    AxumJson(limits): AxumJson<project::ProxyLimits>,
+) -> Result<AxumJson<project::ProxyLimits>, Error> {
+    service.find_project(&project_name).await?;
+    service.set_proxy_limits(&project_name, &limits).await?;
+
+    Ok(AxumJson(limits))
+}
+
+#[instrument(skip(service))]
+#[utoipa::path(
+    delete,
+    path = "/admin/projects/{project_name}/limits",
+    responses(
+        (status = 200, description = "Put the project back on the limits of the gateway.", body = shuttle_common::models::project::ProxyLimits),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+    )
+)]
+async fn delete_proxy_limits(
+    State(RouterState { service, .. }): State<RouterState>,
+    Path(project_name): Path<ProjectName>,
+) -> Result<AxumJson<project::ProxyLimits>, Error> {
+    service.find_project(&project_name).await?;
+    service.reset_proxy_limits(&project_name).await?;
+
+    Ok(AxumJson(service.proxy_limits(&project_name).await?))
+}
+
+#[instrument(skip(service))]
+#[utoipa::path(
+    get,
+    path = "/admin/nodes",
+    responses(
+        (status = 200, description = "Got the nodes projects can be placed on.", body = [shuttle_common::models::node::Response]),
+        (status = 500, description = "Server internal error.")
+    )
+)]
+async fn get_nodes(
+    State(RouterState { service, .. }): State<RouterState>,
+) -> Result<AxumJson<Vec<node::Response>>, Error> {
+    Ok(AxumJson(service.nodes().await?))
+}
+
+#[instrument(skip(service))]
+#[utoipa::path(
+    post,
+    path = "/admin/nodes",
+    request_body = shuttle_common::models::node::Create,
+    responses(
+        (status = 200, description = "Added a node projects can be placed on.", body = shuttle_common::models::node::Response),
+        (status = 400, description = "The name of the node is taken or invalid, or its Docker host is invalid."),
+        (status = 500, description = "Server internal error.")
+    )
+)]
+async fn post_node(
+    State(RouterState { service, .. }): State<RouterState>,
+    AxumJson(create): AxumJson<node::Create>,
+) -> Result<AxumJson<node::Response>, Error> {
+    Ok(AxumJson(
+        service.add_node(&create.name, &create.docker_host).await?,
+    ))
+}
+
+#[instrument(skip(service))]
+#[utoipa::path(
+    delete,
+    path = "/admin/nodes/{node_name}",
+    responses(
+        (status = 200, description = "Removed the node.", body = shuttle_common::models::node::Response),
+        (status = 400, description = "The node still has projects."),
+        (status = 404, description = "There is no such node."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("node_name" = String, Path, description = "The name of the node."),
+    )
+)]
+async fn delete_node(
+    State(RouterState { service, .. }): State<RouterState>,
+    Path(node_name): Path<String>,
+) -> Result<AxumJson<node::Response>, Error> {
+    Ok(AxumJson(service.remove_node(&node_name).await?))
+}
+
+#[instrument(skip(service, sender))]
+#[utoipa::path(
+    post,
+    path = "/admin/nodes/{node_name}/drain",
+    responses(
+        (status = 200, description = "Kept new projects off the node, and started moving its projects to the other nodes.", body = [shuttle_common::models::node::Migration]),
+        (status = 404, description = "There is no such node."),
+        (status = 503, description = "There is no other node the projects can be moved to."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("node_name" = String, Path, description = "The name of the node."),
+    )
+)]
+async fn drain_node(
+    State(RouterState {
+        service, sender, ..
+    }): State<RouterState>,
+    Path(node_name): Path<String>,
+) -> Result<AxumJson<Vec<node::Migration>>, Error> {
+    Ok(AxumJson(service.drain_node(&node_name, &sender).await?))
+}
+
+#[instrument(skip(service))]
+#[utoipa::path(
+    delete,
+    path = "/admin/nodes/{node_name}/drain",
+    responses(
+        (status = 200, description = "Let new projects on the node again.", body = shuttle_common::models::node::Response),
+        (status = 404, description = "There is no such node."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("node_name" = String, Path, description = "The name of the node."),
+    )
+)]
+async fn undrain_node(
+    State(RouterState { service, .. }): State<RouterState>,
+    Path(node_name): Path<String>,
+) -> Result<AxumJson<node::Response>, Error> {
+    Ok(AxumJson(
+        service.set_node_draining(&node_name, false).await?,
+    ))
+}
+
+#[instrument(skip(service, sender))]
+#[utoipa::path(
+    post,
+    path = "/admin/projects/{project_name}/migrate",
+    request_body = shuttle_common::models::node::Migrate,
+    responses(
+        (status = 200, description = "Started moving the project to another node.", body = shuttle_common::models::node::Migration),
+        (status = 400, description = "The project is already on the node, or the node is being drained."),
+        (status = 404, description = "There is no such project or node."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+    )
+)]
+async fn migrate_project(
+    State(RouterState {
+        service, sender, ..
+    }): State<RouterState>,
+    Path(project_name): Path<ProjectName>,
+    AxumJson(migrate): AxumJson<node::Migrate>,
+) -> Result<AxumJson<node::Migration>, Error> {
+    Ok(AxumJson(
+        service
+            .migrate_project(&project_name, migrate.node, &sender)
+            .await?,
+    ))
 }
 
 #[instrument(skip_all)]
@@ -336,13 +1020,24 @@ async fn delete_load(
     )
 )]
 async fn get_load_admin(
-    State(RouterState { running_builds, .. }): State<RouterState>,
+    State(RouterState { build_queue, .. }): State<RouterState>,
 ) -> Result<AxumJson<stats::LoadResponse>, Error> {
-    let mut running_builds = running_builds.lock().await;
+    let mut build_queue = build_queue.lock().await;
 
-    let load = calculate_capacity(&mut running_builds);
+    Ok(AxumJson(build_queue.load()))
+}
 
-    Ok(AxumJson(load))
+#[instrument(skip_all)]
+#[utoipa::path(
+    get,
+    path = "/admin/metrics",
+    responses(
+        (status = 200, description = "Got the metrics of the gateway in the Prometheus text format.", body = String),
+        (status = 500, description = "Server internal error.")
+    )
+)]
+async fn get_metrics() -> ([(HeaderName, &'static str); 1], String) {
+    ([(CONTENT_TYPE, TEXT_FORMAT)], monitoring::render())
 }
 
 #[instrument(skip_all)]
@@ -355,25 +1050,12 @@ async fn get_load_admin(
     )
 )]
 async fn delete_load_admin(
-    State(RouterState { running_builds, .. }): State<RouterState>,
+    State(RouterState { build_queue, .. }): State<RouterState>,
 ) -> Result<AxumJson<stats::LoadResponse>, Error> {
-    let mut running_builds = running_builds.lock().await;
-    running_builds.clear();
-
-    let load = calculate_capacity(&mut running_builds);
+    let mut build_queue = build_queue.lock().await;
+    build_queue.clear();
 
-    Ok(AxumJson(load))
-}
-
-fn calculate_capacity(running_builds: &mut MutexGuard<TtlCache<Uuid, ()>>) -> stats::LoadResponse {
-    let active = running_builds.iter().count();
-    let capacity = running_builds.capacity();
-    let has_capacity = active < capacity;
-
-    stats::LoadResponse {
-        builds_count: active,
-        has_capacity,
-    }
+    Ok(AxumJson(build_queue.load()))
 }
 
 #[instrument(skip_all)]
@@ -463,13 +1145,44 @@ async fn request_custom_domain_acme_certificate(
         .parse()
         .map_err(|_err| Error::from(ErrorKind::InvalidCustomDomain))?;
 
+    attach_custom_domain(
+        &service,
+        &sender,
+        &acme_client,
+        &resolver,
+        &project_name,
+        &fqdn,
+        credentials,
+    )
+    .await?;
+
+    Ok(format!(
+        r#""New certificate created for {} project.""#,
+        project_name
+    ))
+}
+
+/// Get a certificate for the custom domain, recreate the project so that it is served on the
+/// domain, and terminate TLS for the domain with the certificate
+async fn attach_custom_domain(
+    service: &Arc<GatewayService>,
+    sender: &Sender<BoxedTask>,
+    acme_client: &AcmeClient,
+    resolver: &GatewayCertResolver,
+    project_name: &ProjectName,
+    fqdn: &FQDN,
+    credentials: AccountCredentials<'_>,
+) -> Result<(), Error> {
+    let project = service.find_project(project_name).await?;
+    let idle_minutes = project
+        .container()
+        .ok_or_else(|| Error::from_kind(ErrorKind::ProjectNotReady))?
+        .idle_minutes();
+
     let (certs, private_key) = service
-        .create_custom_domain_certificate(&fqdn, &acme_client, &project_name, credentials)
+        .create_custom_domain_certificate(fqdn, acme_client, project_name, credentials)
         .await?;
 
-    let project = service.find_project(&project_name).await?;
-    let idle_minutes = project.container().unwrap().idle_minutes();
-
     // Destroy and recreate the project with the new domain.
     service
         .new_task()
@@ -490,7 +1203,7 @@ async fn request_custom_domain_acme_certificate(
                 }
             }
         }))
-        .send(&sender)
+        .send(sender)
         .await?;
 
     let mut buf = Vec::new();
@@ -499,10 +1212,114 @@ async fn request_custom_domain_acme_certificate(
     resolver
         .serve_pem(&fqdn.to_string(), Cursor::new(buf))
         .await?;
-    Ok(format!(
-        r#""New certificate created for {} project.""#,
-        project_name
-    ))
+
+    Ok(())
+}
+
+#[instrument(skip_all, fields(%project_name, %fqdn))]
+#[utoipa::path(
+    post,
+    path = "/projects/{project_name}/domains/{fqdn}",
+    responses(
+        (status = 200, description = "Added the custom domain to the project.", body = shuttle_common::models::domain::Response),
+        (status = 400, description = "The domain is invalid, used by another project or does not resolve to the project."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+        ("fqdn" = String, Path, description = "The custom domain to serve the project on."),
+    )
+)]
+async fn add_custom_domain(
+    State(RouterState {
+        service, sender, ..
+    }): State<RouterState>,
+    Extension(acme_client): Extension<AcmeClient>,
+    Extension(resolver): Extension<Arc<GatewayCertResolver>>,
+    ScopedUser {
+        scope: project_name,
+        ..
+    }: ScopedUser,
+    Path((_, fqdn)): Path<(ProjectName, String)>,
+) -> Result<AxumJson<domain::Response>, Error> {
+    let fqdn: FQDN = fqdn
+        .parse()
+        .map_err(|_err| Error::from(ErrorKind::InvalidCustomDomain))?;
+
+    match service.project_details_for_custom_domain(&fqdn).await {
+        Ok(custom_domain) if custom_domain.project_name == project_name => {
+            return domain_response(custom_domain).map(AxumJson);
+        }
+        Ok(_) => return Err(Error::from_kind(ErrorKind::CustomDomainAlreadyExists)),
+        Err(err) if err.kind() == ErrorKind::CustomDomainNotFound => {}
+        Err(err) => return Err(err),
+    }
+
+    // A project is served on one domain, which its deployer hands out as its address
+    match service.find_custom_domain_for_project(&project_name).await {
+        Ok(_) => return Err(Error::from_kind(ErrorKind::CustomDomainAlreadyExists)),
+        Err(err) if err.kind() == ErrorKind::CustomDomainNotFound => {}
+        Err(err) => return Err(err),
+    }
+
+    service.verify_custom_domain(&project_name, &fqdn).await?;
+
+    attach_custom_domain(
+        &service,
+        &sender,
+        &acme_client,
+        &resolver,
+        &project_name,
+        &fqdn,
+        service.credentials(),
+    )
+    .await?;
+
+    let custom_domain = service.project_details_for_custom_domain(&fqdn).await?;
+
+    domain_response(custom_domain).map(AxumJson)
+}
+
+#[instrument(skip_all, fields(%project_name))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/domains",
+    responses(
+        (status = 200, description = "Got the custom domains of the project.", body = [shuttle_common::models::domain::Response]),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+    )
+)]
+async fn get_custom_domains(
+    State(RouterState { service, .. }): State<RouterState>,
+    ScopedUser {
+        scope: project_name,
+        ..
+    }: ScopedUser,
+) -> Result<AxumJson<Vec<domain::Response>>, Error> {
+    match service.find_custom_domain_for_project(&project_name).await {
+        Ok(custom_domain) => Ok(AxumJson(vec![domain_response(custom_domain)?])),
+        Err(err) if err.kind() == ErrorKind::CustomDomainNotFound => Ok(AxumJson(Vec::new())),
+        Err(err) => Err(err),
+    }
+}
+
+fn domain_response(custom_domain: CustomDomain) -> Result<domain::Response, Error> {
+    let not_after = custom_domain
+        .not_after()
+        .ok_or_else(|| Error::from_kind(ErrorKind::Internal))?;
+    let certificate_expires_at = Utc
+        .timestamp_opt(not_after.timestamp(), 0)
+        .single()
+        .ok_or_else(|| Error::from_kind(ErrorKind::Internal))?;
+
+    Ok(domain::Response {
+        fqdn: custom_domain.fqdn.to_string(),
+        project_name: custom_domain.project_name.to_string(),
+        certificate_expires_at,
+    })
 }
 
 #[instrument(skip_all, fields(%project_name, %fqdn))]
@@ -644,26 +1461,70 @@ impl Modify for SecurityAddon {
         request_custom_domain_acme_certificate,
         renew_custom_domain_acme_certificate,
         renew_gateway_acme_certificate,
+        add_custom_domain,
+        get_custom_domains,
         get_status,
         get_projects_list,
         get_project,
         destroy_project,
         create_project,
+        restart_project,
+        get_maintenance,
+        put_maintenance,
+        delete_maintenance,
+        get_access_rules,
+        put_access_rules,
+        delete_access_rules,
+        get_tcp_ports,
+        post_tcp_port,
+        delete_tcp_port,
+        purge_cache,
         post_load,
         delete_load,
+        post_route,
+        delete_route,
+        post_headers,
         get_projects,
         revive_projects,
         destroy_projects,
         get_load_admin,
-        delete_load_admin
+        delete_load_admin,
+        get_metrics,
+        get_proxy_limits,
+        put_proxy_limits,
+        delete_proxy_limits,
+        get_nodes,
+        post_node,
+        delete_node,
+        drain_node,
+        undrain_node,
+        migrate_project
     ),
     modifiers(&SecurityAddon),
     components(schemas(
         shuttle_common::models::project::Response,
         shuttle_common::models::stats::LoadResponse,
         shuttle_common::models::project::AdminResponse,
+        shuttle_common::models::project::ProxyLimits,
+        shuttle_common::models::project::Maintenance,
+        shuttle_common::models::project::TcpEndpoint,
+        shuttle_common::models::project::TcpRequest,
+        shuttle_common::models::access::Request,
+        shuttle_common::models::access::Response,
+        shuttle_common::models::access::Guard,
+        shuttle_common::models::access::GuardKind,
         shuttle_common::models::stats::LoadResponse,
-        shuttle_common::models::project::State
+        shuttle_common::models::project::State,
+        shuttle_common::models::domain::Response,
+        shuttle_common::models::route::Request,
+        shuttle_common::models::route::Response,
+        shuttle_common::models::headers::Request,
+        shuttle_common::models::cache::Purge,
+        shuttle_common::models::cache::Response,
+        shuttle_common::models::node::Response,
+        shuttle_common::models::node::Create,
+        shuttle_common::models::node::Migrate,
+        shuttle_common::models::node::Migration
     ))
 )]
 pub struct ApiDoc;
@@ -672,7 +1533,9 @@ pub struct ApiDoc;
 pub(crate) struct RouterState {
     pub service: Arc<GatewayService>,
     pub sender: Sender<BoxedTask>,
-    pub running_builds: Arc<Mutex<TtlCache<Uuid, ()>>>,
+    pub build_queue: Arc<Mutex<BuildQueue>>,
+    /// Limits how often each account may deploy, when the gateway was started with a limit
+    pub deploy_limiter: Option<Arc<RateLimiter>>,
 }
 
 pub struct ApiBuilder {
@@ -680,6 +1543,7 @@ pub struct ApiBuilder {
     service: Option<Arc<GatewayService>>,
     sender: Option<Sender<BoxedTask>>,
     bind: Option<SocketAddr>,
+    deploys_per_hour: Option<usize>,
 }
 
 impl Default for ApiBuilder {
@@ -695,6 +1559,7 @@ impl ApiBuilder {
             service: None,
             sender: None,
             bind: None,
+            deploys_per_hour: None,
         }
     }
 
@@ -719,6 +1584,14 @@ impl ApiBuilder {
                         .layer(ScopedLayer::new(vec![Scope::CustomDomainCertificateRenew])),
                 ),
             )
+            .route(
+                "/projects/:project_name/domains",
+                get(get_custom_domains.layer(ScopedLayer::new(vec![Scope::Domain]))),
+            )
+            .route(
+                "/projects/:project_name/domains/:fqdn",
+                post(add_custom_domain.layer(ScopedLayer::new(vec![Scope::DomainWrite]))),
+            )
             .route(
                 "/admin/acme/gateway/renew",
                 post(
@@ -731,6 +1604,35 @@ impl ApiBuilder {
         self
     }
 
+    /// Let projects ask for the ports of the `forwarder` to forward raw TCP to them
+    pub fn with_tcp_forwarder(mut self, forwarder: Arc<TcpForwarder>) -> Self {
+        self.router = self
+            .router
+            .route(
+                "/projects/:project_name/tcp",
+                get(get_tcp_ports.layer(ScopedLayer::new(vec![Scope::Project])))
+                    .post(post_tcp_port.layer(ScopedLayer::new(vec![Scope::ProjectCreate]))),
+            )
+            .route(
+                "/projects/:project_name/tcp/:port",
+                delete(delete_tcp_port.layer(ScopedLayer::new(vec![Scope::ProjectCreate]))),
+            )
+            .layer(Extension(forwarder));
+        self
+    }
+
+    /// Let projects drop the responses `cache` keeps for their services
+    pub fn with_response_cache(mut self, cache: Arc<ResponseCache>) -> Self {
+        self.router = self
+            .router
+            .route(
+                "/projects/:project_name/cache/:service_name",
+                delete(purge_cache.layer(ScopedLayer::new(vec![Scope::ProjectCreate]))),
+            )
+            .layer(Extension(cache));
+        self
+    }
+
     pub fn with_service(mut self, service: Arc<GatewayService>) -> Self {
         self.service = Some(service);
         self
@@ -746,6 +1648,12 @@ impl ApiBuilder {
         self
     }
 
+    /// Refuse the deployments of an account over this many in an hour, with a `429`
+    pub fn with_deploy_rate_limit(mut self, deploys_per_hour: usize) -> Self {
+        self.deploys_per_hour = Some(deploys_per_hour);
+        self
+    }
+
     pub fn with_default_traces(mut self) -> Self {
         self.router = self.router.route_layer(from_extractor::<Metrics>()).layer(
             TraceLayer::new(|request| {
@@ -765,9 +1673,23 @@ impl ApiBuilder {
     pub fn with_default_routes(mut self) -> Self {
         let admin_routes = Router::new()
             .route("/projects", get(get_projects))
+            .route(
+                "/projects/:project_name/limits",
+                get(get_proxy_limits)
+                    .put(put_proxy_limits)
+                    .delete(delete_proxy_limits),
+            )
+            .route("/projects/:project_name/migrate", post(migrate_project))
+            .route("/nodes", get(get_nodes).post(post_node))
+            .route("/nodes/:node_name", delete(delete_node))
+            .route(
+                "/nodes/:node_name/drain",
+                post(drain_node).delete(undrain_node),
+            )
             .route("/revive", post(revive_projects))
             .route("/destroy", post(destroy_projects))
             .route("/stats/load", get(get_load_admin).delete(delete_load_admin))
+            .route("/metrics", get(get_metrics))
             // TODO: The `/swagger-ui` responds with a 303 See Other response which is followed in
             // browsers but leads to 404 Not Found. This must be investigated.
             .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
@@ -786,8 +1708,30 @@ impl ApiBuilder {
                     .delete(destroy_project.layer(ScopedLayer::new(vec![Scope::ProjectCreate])))
                     .post(create_project.layer(ScopedLayer::new(vec![Scope::ProjectCreate]))),
             )
+            .route(
+                "/projects/:project_name/restart",
+                post(restart_project.layer(ScopedLayer::new(vec![Scope::ProjectCreate]))),
+            )
+            .route(
+                "/projects/:project_name/maintenance",
+                get(get_maintenance.layer(ScopedLayer::new(vec![Scope::Project])))
+                    .put(put_maintenance.layer(ScopedLayer::new(vec![Scope::ProjectCreate])))
+                    .delete(delete_maintenance.layer(ScopedLayer::new(vec![Scope::ProjectCreate]))),
+            )
+            .route(
+                "/projects/:project_name/access/:service_name",
+                get(get_access_rules.layer(ScopedLayer::new(vec![Scope::Project])))
+                    .put(put_access_rules.layer(ScopedLayer::new(vec![Scope::ProjectCreate])))
+                    .delete(
+                        delete_access_rules.layer(ScopedLayer::new(vec![Scope::ProjectCreate])),
+                    ),
+            )
             .route("/projects/:project_name/*any", any(route_project))
+            // The versioned routes of the deployers of projects
+            .route("/v1/projects/:project_name/*any", any(route_project))
             .route("/stats/load", post(post_load).delete(delete_load))
+            .route("/routes", post(post_route).delete(delete_route))
+            .route("/headers", post(post_headers))
             .nest("/admin", admin_routes);
 
         self
@@ -819,13 +1763,26 @@ impl ApiBuilder {
             concurrent_builds = 1;
         }
 
-        let running_builds = Arc::new(Mutex::new(TtlCache::new(concurrent_builds)));
+        let build_queue = Arc::new(Mutex::new(BuildQueue::new(concurrent_builds)));
+        let deploy_limiter = self.deploys_per_hour.map(|deploys_per_hour| {
+            Arc::new(RateLimiter::new(
+                deploys_per_hour,
+                Duration::from_secs(60 * 60),
+            ))
+        });
 
-        self.router.with_state(RouterState {
-            service,
-            sender,
-            running_builds,
-        })
+        self.router
+            // Let clients tell whether they are compatible with this gateway from any response
+            .layer(SetResponseHeaderLayer::overriding(
+                X_SHUTTLE_VERSION.clone(),
+                HeaderValue::from_static(env!("CARGO_PKG_VERSION")),
+            ))
+            .with_state(RouterState {
+                service,
+                sender,
+                build_queue,
+                deploy_limiter,
+            })
     }
 
     pub fn serve(self) -> impl Future<Output = Result<(), hyper::Error>> {
@@ -954,6 +1911,23 @@ pub mod tests {
             .await
             .unwrap();
 
+        let restart_project = |project: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(format!("/projects/{project}/restart"))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // The project is still being created, so it has no container to restart yet
+        router
+            .call(restart_project("reloaded").with_header(&authorization))
+            .map_ok(|resp| {
+                assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+            })
+            .await
+            .unwrap();
+
         let trinity_key = world.create_user("trinity");
 
         let authorization = Authorization::bearer(&trinity_key).unwrap();
@@ -972,6 +1946,14 @@ pub mod tests {
             .await
             .unwrap();
 
+        router
+            .call(restart_project("reloaded").with_header(&authorization))
+            .map_ok(|resp| {
+                assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+            })
+            .await
+            .unwrap();
+
         let get_load = || {
             Request::builder()
                 .method("GET")
@@ -1034,6 +2016,33 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    fn deploys() {
+        let request = |method: &str, uri: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        assert!(is_deploy(&request("POST", "/projects/matrix/services/neo")));
+        assert!(is_deploy(&request(
+            "POST",
+            "/v1/projects/matrix/services/neo"
+        )));
+        assert!(!is_deploy(&request("GET", "/projects/matrix/services/neo")));
+        assert!(!is_deploy(&request(
+            "DELETE",
+            "/projects/matrix/services/neo"
+        )));
+        assert!(!is_deploy(&request(
+            "POST",
+            "/projects/matrix/services/neo/secrets"
+        )));
+        assert!(!is_deploy(&request("POST", "/projects/matrix")));
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn status() {
         let world = World::new().await;
@@ -1,3 +1,5 @@
 mod auth_layer;
+mod build_queue;
+mod rate_limit;
 
 pub mod latest;
@@ -0,0 +1,92 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Limits how many times each account may do something within a sliding window
+pub struct RateLimiter {
+    limit: usize,
+    window: Duration,
+    /// When each account did it within the window, oldest first
+    hits: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: usize, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            hits: Default::default(),
+        }
+    }
+
+    /// Count a hit by `account`, or get how long it has to wait when it is over the limit
+    pub fn check(&self, account: &str) -> Result<(), Duration> {
+        self.check_at(account, Instant::now())
+    }
+
+    fn check_at(&self, account: &str, now: Instant) -> Result<(), Duration> {
+        let mut hits = self.hits.lock().unwrap();
+
+        // Forget the hits which left the window, and the accounts which have none left
+        hits.retain(|_, times| {
+            while times
+                .front()
+                .map_or(false, |time| now.duration_since(*time) >= self.window)
+            {
+                times.pop_front();
+            }
+
+            !times.is_empty()
+        });
+
+        let times = hits.entry(account.to_string()).or_default();
+        if times.len() >= self.limit {
+            let oldest = times.front().copied().unwrap_or(now);
+
+            return Err(self.window.saturating_sub(now.duration_since(oldest)));
+        }
+
+        times.push_back(now);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::RateLimiter;
+
+    #[test]
+    fn limits_each_account() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert_eq!(limiter.check_at("neo", start), Ok(()));
+        assert_eq!(
+            limiter.check_at("neo", start + Duration::from_secs(10)),
+            Ok(())
+        );
+        assert_eq!(
+            limiter.check_at("neo", start + Duration::from_secs(20)),
+            Err(Duration::from_secs(40))
+        );
+
+        // Other accounts have their own limit
+        assert_eq!(
+            limiter.check_at("trinity", start + Duration::from_secs(20)),
+            Ok(())
+        );
+
+        // Refused hits do not count, and the oldest hit leaves the window
+        assert_eq!(
+            limiter.check_at("neo", start + Duration::from_secs(60)),
+            Ok(())
+        );
+        assert_eq!(
+            limiter.check_at("neo", start + Duration::from_secs(61)),
+            Err(Duration::from_secs(9))
+        );
+    }
+}
@@ -1,8 +1,13 @@
-use std::{net::SocketAddr, path::PathBuf};
+use std::{
+    net::{IpAddr, SocketAddr},
+    ops::RangeInclusive,
+    path::PathBuf,
+};
 
 use clap::{Parser, Subcommand, ValueEnum};
 use fqdn::FQDN;
 use http::Uri;
+use shuttle_common::models::project::ProxyLimits;
 
 #[derive(Parser, Debug)]
 pub struct Args {
@@ -39,10 +44,40 @@ pub struct StartArgs {
     /// Allows to disable the use of TLS in the user proxy service (DANGEROUS)
     #[arg(long, default_value = "enable")]
     pub use_tls: UseTls,
+    /// How many deployments each account may make in an hour, after which they are refused until
+    /// the oldest is an hour old
+    #[arg(long, default_value = "30")]
+    pub deploys_per_hour: usize,
+    /// Addresses of clients, like uptime monitors, whose requests to projects are never rate
+    /// limited
+    #[arg(long, value_delimiter = ',')]
+    pub rate_limit_allowlist: Vec<IpAddr>,
+    /// Ports, like `30000-30999`, to give to projects which forward raw TCP to them on the address
+    /// of the user proxy. Projects cannot forward raw TCP without them
+    #[arg(long, value_parser = parse_port_range)]
+    pub tcp_ports: Option<RangeInclusive<u16>>,
+    /// Bytes of the responses of each project to keep for the paths its services ask to have
+    /// cached. Nothing is cached when it is 0
+    #[arg(long, default_value_t = 16 * 1024 * 1024)]
+    pub response_cache_budget: u64,
     #[command(flatten)]
     pub context: ContextArgs,
 }
 
+fn parse_port_range(ports: &str) -> Result<RangeInclusive<u16>, String> {
+    let (start, end) = ports
+        .split_once('-')
+        .ok_or_else(|| "expected a range of ports like `30000-30999`".to_string())?;
+    let start: u16 = start.parse().map_err(|error| format!("{error}"))?;
+    let end: u16 = end.parse().map_err(|error| format!("{error}"))?;
+
+    if start == 0 || start > end {
+        return Err("expected the first port to be above 0 and not above the last".to_string());
+    }
+
+    Ok(start..=end)
+}
+
 #[derive(clap::Args, Debug, Clone)]
 pub struct ContextArgs {
     /// Default image to deploy user runtimes into
@@ -68,4 +103,38 @@ pub struct ContextArgs {
     /// The path to the docker daemon socket
     #[arg(long, default_value = "/var/run/docker.sock")]
     pub docker_host: String,
+    #[command(flatten)]
+    pub proxy_limits: ProxyLimitsArgs,
+}
+
+/// The limits of the requests to projects which were not given limits of their own
+#[derive(clap::Args, Debug, Clone)]
+pub struct ProxyLimitsArgs {
+    /// Size in MiB the body of a request to a project may have
+    #[arg(long, default_value = "32")]
+    pub max_request_body_mib: u64,
+    /// Seconds a project has to start responding to a request
+    #[arg(long, default_value = "120")]
+    pub response_timeout: u64,
+    /// Seconds the body of a request or of a response may go without data
+    #[arg(long, default_value = "60")]
+    pub idle_timeout: u64,
+    /// Requests each client may make to a project a second, or 0 to not limit them
+    #[arg(long, default_value = "100")]
+    pub requests_per_second: u32,
+    /// Requests each client may make to a project at once
+    #[arg(long, default_value = "200")]
+    pub burst: u32,
+}
+
+impl From<&ProxyLimitsArgs> for ProxyLimits {
+    fn from(args: &ProxyLimitsArgs) -> Self {
+        Self {
+            max_request_body_bytes: args.max_request_body_mib * 1024 * 1024,
+            response_timeout_secs: args.response_timeout,
+            idle_timeout_secs: args.idle_timeout,
+            requests_per_second: args.requests_per_second,
+            burst: args.burst,
+        }
+    }
 }
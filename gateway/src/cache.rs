@@ -0,0 +1,525 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::http::header::{AGE, AUTHORIZATION, CACHE_CONTROL, SET_COOKIE, VARY};
+use axum::http::response::Parts;
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri};
+use hyper::body::{Body, Bytes};
+use hyper::Response;
+
+use crate::ProjectName;
+
+/// The largest share of the budget of a project a single response can take up
+const MAX_RESPONSE_SHARE: u64 = 4;
+
+/// Keeps the responses to the paths services ask to have cached, for as long as their
+/// `Cache-Control` lets shared caches keep them, so that the services are not called for them
+/// again during a spike of traffic.
+///
+/// The responses of each project can take up a budget of bytes, after which the ones which were
+/// used least recently are dropped.
+pub struct ResponseCache {
+    budget: u64,
+    projects: Mutex<HashMap<ProjectName, Responses>>,
+}
+
+#[derive(Default)]
+struct Responses {
+    responses: HashMap<Key, Cached>,
+    bytes: u64,
+}
+
+/// What a response is cached by, besides the headers it varies by
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Key {
+    service: String,
+    host: String,
+    path: String,
+    query: Option<String>,
+}
+
+struct Cached {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    /// The headers of the request the response is for which it varies by, with their values
+    vary: Vec<(HeaderName, Option<HeaderValue>)>,
+    stored: Instant,
+    expires: Instant,
+    used: Instant,
+}
+
+impl Key {
+    pub fn new(service: &str, host: &str, uri: &Uri) -> Self {
+        Self {
+            service: service.to_string(),
+            host: host.to_string(),
+            path: uri.path().to_string(),
+            query: uri.query().map(ToString::to_string),
+        }
+    }
+}
+
+impl Cached {
+    fn size(&self) -> u64 {
+        let headers: usize = self
+            .headers
+            .iter()
+            .map(|(name, value)| name.as_str().len() + value.len())
+            .sum();
+
+        (self.body.len() + headers) as u64
+    }
+
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| headers.get(name) == value.as_ref())
+    }
+}
+
+impl ResponseCache {
+    /// Keep up to `budget` bytes of responses for each project, or none when it is 0
+    pub fn new(budget: u64) -> Self {
+        Self {
+            budget,
+            projects: Default::default(),
+        }
+    }
+
+    /// The largest response which can be cached, whose body has to be buffered to keep it
+    pub fn max_response(&self) -> u64 {
+        self.budget / MAX_RESPONSE_SHARE
+    }
+
+    /// The cached response to a request with `headers` to the service of the project, if there is
+    /// one which is still fresh
+    pub fn get(
+        &self,
+        project: &ProjectName,
+        key: &Key,
+        headers: &HeaderMap,
+    ) -> Option<Response<Body>> {
+        self.get_at(project, key, headers, Instant::now())
+    }
+
+    fn get_at(
+        &self,
+        project: &ProjectName,
+        key: &Key,
+        headers: &HeaderMap,
+        now: Instant,
+    ) -> Option<Response<Body>> {
+        let mut projects = self.projects.lock().unwrap();
+        let responses = projects.get_mut(project)?;
+
+        let cached = responses.responses.get_mut(key)?;
+        if cached.expires <= now {
+            let size = cached.size();
+            responses.responses.remove(key);
+            responses.bytes -= size;
+
+            return None;
+        }
+        if !cached.matches(headers) {
+            return None;
+        }
+        cached.used = now;
+
+        let mut response = Response::new(Body::from(cached.body.clone()));
+        *response.status_mut() = cached.status;
+        *response.headers_mut() = cached.headers.clone();
+        response.headers_mut().insert(
+            AGE,
+            HeaderValue::from(now.duration_since(cached.stored).as_secs()),
+        );
+
+        Some(response)
+    }
+
+    /// Keep the response with `parts` and `body` to a request with `headers` for `ttl`, dropping
+    /// the responses of the project which were used least recently to make room for it
+    pub fn insert(
+        &self,
+        project: &ProjectName,
+        key: Key,
+        headers: &HeaderMap,
+        parts: &Parts,
+        body: Bytes,
+        ttl: Duration,
+    ) {
+        self.insert_at(project, key, headers, parts, body, ttl, Instant::now())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn insert_at(
+        &self,
+        project: &ProjectName,
+        key: Key,
+        headers: &HeaderMap,
+        parts: &Parts,
+        body: Bytes,
+        ttl: Duration,
+        now: Instant,
+    ) {
+        let vary = parts
+            .headers
+            .get_all(VARY)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+            .map(|name| {
+                let value = headers.get(&name).cloned();
+                (name, value)
+            })
+            .collect();
+
+        let cached = Cached {
+            status: parts.status,
+            headers: parts.headers.clone(),
+            body,
+            vary,
+            stored: now,
+            expires: now + ttl,
+            used: now,
+        };
+        let size = cached.size();
+        if self.budget == 0 || size > self.max_response() {
+            return;
+        }
+
+        let mut projects = self.projects.lock().unwrap();
+        let responses = projects.entry(project.clone()).or_default();
+
+        if let Some(replaced) = responses.responses.remove(&key) {
+            responses.bytes -= replaced.size();
+        }
+
+        if responses.bytes + size > self.budget {
+            let mut dropped = 0;
+            responses.responses.retain(|_, cached| {
+                let keep = cached.expires > now;
+                if !keep {
+                    dropped += cached.size();
+                }
+                keep
+            });
+            responses.bytes -= dropped;
+        }
+
+        while responses.bytes + size > self.budget {
+            let Some(oldest) = responses
+                .responses
+                .iter()
+                .min_by_key(|(_, cached)| cached.used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            let dropped = responses.responses.remove(&oldest).unwrap();
+            responses.bytes -= dropped.size();
+        }
+
+        responses.bytes += size;
+        responses.responses.insert(key, cached);
+    }
+
+    /// Drop the cached responses of the service of the project, or only the ones to the paths
+    /// starting with `path`, returning how many were dropped and how many bytes they took up
+    pub fn purge(&self, project: &ProjectName, service: &str, path: Option<&str>) -> (usize, u64) {
+        let mut projects = self.projects.lock().unwrap();
+        let Some(responses) = projects.get_mut(project) else {
+            return (0, 0);
+        };
+
+        let mut purged = (0, 0);
+        responses.responses.retain(|key, cached| {
+            let keep =
+                key.service != service || path.map_or(false, |path| !key.path.starts_with(path));
+            if !keep {
+                purged.0 += 1;
+                purged.1 += cached.size();
+            }
+            keep
+        });
+        responses.bytes -= purged.1;
+
+        purged
+    }
+}
+
+/// Whether the response to a request with `method` and `headers` can come from the cache. The
+/// responses to requests with credentials are for those credentials only.
+pub fn is_cacheable_request(method: &Method, headers: &HeaderMap) -> bool {
+    method == Method::GET
+        && !headers.contains_key(AUTHORIZATION)
+        && !directives(headers).any(|(name, _)| name == "no-cache" || name == "no-store")
+}
+
+/// How long a shared cache can keep a response with `status` and `headers`, if it can at all
+pub fn ttl(status: StatusCode, headers: &HeaderMap) -> Option<Duration> {
+    if !matches!(status.as_u16(), 200 | 203 | 301 | 404 | 410) || headers.contains_key(SET_COOKIE) {
+        return None;
+    }
+
+    // A response which varies by everything can never be reused
+    if headers
+        .get_all(VARY)
+        .iter()
+        .any(|value| value.to_str().map_or(true, |value| value.contains('*')))
+    {
+        return None;
+    }
+
+    let mut max_age = None;
+    let mut shared_max_age = None;
+    for (name, value) in directives(headers) {
+        match name.as_str() {
+            "private" | "no-store" | "no-cache" => return None,
+            "max-age" => max_age = value.and_then(|value| value.parse().ok()),
+            "s-maxage" => shared_max_age = value.and_then(|value| value.parse().ok()),
+            _ => {}
+        }
+    }
+
+    shared_max_age
+        .or(max_age)
+        .filter(|seconds| *seconds > 0)
+        .map(Duration::from_secs)
+}
+
+/// The directives of the `Cache-Control` headers, with their lowercased names and their values
+fn directives(headers: &HeaderMap) -> impl Iterator<Item = (String, Option<String>)> + '_ {
+    headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|directive| {
+            let (name, value) = directive
+                .split_once('=')
+                .map_or((directive, None), |(name, value)| {
+                    (name, Some(value.trim().trim_matches('"').to_string()))
+                });
+
+            (name.trim().to_ascii_lowercase(), value)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use axum::http::header::{
+        ACCEPT_LANGUAGE, AGE, AUTHORIZATION, CACHE_CONTROL, SET_COOKIE, VARY,
+    };
+    use axum::http::{HeaderMap, HeaderValue, Method, Response, StatusCode};
+    use hyper::body::Bytes;
+
+    use super::{is_cacheable_request, ttl, Key, ResponseCache};
+    use crate::ProjectName;
+
+    fn headers(pairs: &[(axum::http::HeaderName, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.clone(), HeaderValue::from_static(value)))
+            .collect()
+    }
+
+    fn key(path: &str) -> Key {
+        Key::new("matrix", "matrix.shuttleapp.rs", &path.parse().unwrap())
+    }
+
+    fn parts(vary: Option<&'static str>) -> axum::http::response::Parts {
+        let mut response = Response::new(());
+        if let Some(vary) = vary {
+            response
+                .headers_mut()
+                .insert(VARY, HeaderValue::from_static(vary));
+        }
+
+        response.into_parts().0
+    }
+
+    #[test]
+    fn cache_control() {
+        let ok = |pairs| ttl(StatusCode::OK, &headers(pairs));
+
+        assert_eq!(
+            ok(&[(CACHE_CONTROL, "public, max-age=60")]),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            ok(&[(CACHE_CONTROL, "max-age=60, s-maxage=600")]),
+            Some(Duration::from_secs(600))
+        );
+        assert_eq!(ok(&[]), None);
+        assert_eq!(ok(&[(CACHE_CONTROL, "max-age=0")]), None);
+        assert_eq!(ok(&[(CACHE_CONTROL, "private, max-age=60")]), None);
+        assert_eq!(ok(&[(CACHE_CONTROL, "no-store")]), None);
+        assert_eq!(
+            ok(&[(CACHE_CONTROL, "max-age=60"), (SET_COOKIE, "session=1")]),
+            None
+        );
+        assert_eq!(ok(&[(CACHE_CONTROL, "max-age=60"), (VARY, "*")]), None);
+        assert_eq!(
+            ttl(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &headers(&[(CACHE_CONTROL, "max-age=60")])
+            ),
+            None
+        );
+
+        assert!(is_cacheable_request(&Method::GET, &HeaderMap::new()));
+        assert!(!is_cacheable_request(&Method::POST, &HeaderMap::new()));
+        assert!(!is_cacheable_request(
+            &Method::GET,
+            &headers(&[(AUTHORIZATION, "Bearer token")])
+        ));
+        assert!(!is_cacheable_request(
+            &Method::GET,
+            &headers(&[(CACHE_CONTROL, "no-cache")])
+        ));
+    }
+
+    #[test]
+    fn fresh_responses() {
+        let cache = ResponseCache::new(1024);
+        let project: ProjectName = "matrix".parse().unwrap();
+        let now = Instant::now();
+
+        cache.insert_at(
+            &project,
+            key("/posts"),
+            &HeaderMap::new(),
+            &parts(None),
+            Bytes::from_static(b"posts"),
+            Duration::from_secs(60),
+            now,
+        );
+
+        let hit = cache
+            .get_at(
+                &project,
+                &key("/posts"),
+                &HeaderMap::new(),
+                now + Duration::from_secs(30),
+            )
+            .unwrap();
+        assert_eq!(hit.headers()[AGE], "30");
+        assert!(cache
+            .get_at(&project, &key("/posts?page=2"), &HeaderMap::new(), now)
+            .is_none());
+
+        // Stale responses are dropped
+        assert!(cache
+            .get_at(
+                &project,
+                &key("/posts"),
+                &HeaderMap::new(),
+                now + Duration::from_secs(60)
+            )
+            .is_none());
+        assert_eq!(cache.purge(&project, "matrix", None), (0, 0));
+    }
+
+    #[test]
+    fn vary() {
+        let cache = ResponseCache::new(1024);
+        let project: ProjectName = "matrix".parse().unwrap();
+        let now = Instant::now();
+        let english = headers(&[(ACCEPT_LANGUAGE, "en")]);
+
+        cache.insert_at(
+            &project,
+            key("/"),
+            &english,
+            &parts(Some("Accept-Language")),
+            Bytes::from_static(b"hello"),
+            Duration::from_secs(60),
+            now,
+        );
+
+        assert!(cache.get_at(&project, &key("/"), &english, now).is_some());
+        assert!(cache
+            .get_at(
+                &project,
+                &key("/"),
+                &headers(&[(ACCEPT_LANGUAGE, "fr")]),
+                now
+            )
+            .is_none());
+        assert!(cache
+            .get_at(&project, &key("/"), &HeaderMap::new(), now)
+            .is_none());
+    }
+
+    #[test]
+    fn budget() {
+        let cache = ResponseCache::new(400);
+        let project: ProjectName = "matrix".parse().unwrap();
+        let now = Instant::now();
+        let body = Bytes::from(vec![0; 100]);
+
+        for (index, path) in ["/1", "/2", "/3", "/4"].into_iter().enumerate() {
+            cache.insert_at(
+                &project,
+                key(path),
+                &HeaderMap::new(),
+                &parts(None),
+                body.clone(),
+                Duration::from_secs(60),
+                now + Duration::from_secs(index as u64),
+            );
+        }
+
+        // The first response was used since, so the second one makes room for the fifth
+        cache.get_at(
+            &project,
+            &key("/1"),
+            &HeaderMap::new(),
+            now + Duration::from_secs(10),
+        );
+        cache.insert_at(
+            &project,
+            key("/5"),
+            &HeaderMap::new(),
+            &parts(None),
+            body.clone(),
+            Duration::from_secs(60),
+            now + Duration::from_secs(11),
+        );
+
+        let cached = |path| {
+            cache
+                .get_at(
+                    &project,
+                    &key(path),
+                    &HeaderMap::new(),
+                    now + Duration::from_secs(12),
+                )
+                .is_some()
+        };
+        assert!(cached("/1"));
+        assert!(!cached("/2"));
+        assert!(cached("/5"));
+
+        // Responses which would take up too much of the budget are not kept
+        cache.insert_at(
+            &project,
+            key("/large"),
+            &HeaderMap::new(),
+            &parts(None),
+            Bytes::from(vec![0; 200]),
+            Duration::from_secs(60),
+            now,
+        );
+        assert!(!cached("/large"));
+
+        assert_eq!(cache.purge(&project, "matrix", Some("/1")), (1, 100));
+        assert_eq!(cache.purge(&project, "matrix--staging", None), (0, 0));
+        assert_eq!(cache.purge(&project, "matrix", None), (3, 300));
+    }
+}
@@ -0,0 +1,236 @@
+use axum::http::header::{
+    ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+    ACCESS_CONTROL_REQUEST_METHOD, CONTENT_SECURITY_POLICY, ORIGIN, STRICT_TRANSPORT_SECURITY,
+    VARY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use shuttle_common::shuttle_toml::{CorsConfig, HttpConfig, DEFAULT_CORS_METHODS};
+
+/// The answer to the CORS preflight request of a browser, which the gateway gives instead of the
+/// service when the service has CORS settings
+pub fn preflight(http: &HttpConfig, method: &Method, headers: &HeaderMap) -> Option<Response> {
+    let cors = http.cors.as_ref()?;
+
+    if method != Method::OPTIONS || !headers.contains_key(ACCESS_CONTROL_REQUEST_METHOD) {
+        return None;
+    }
+    let origin = headers.get(ORIGIN)?;
+
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    let response_headers = response.headers_mut();
+
+    // An origin which is not allowed gets no CORS headers, so that the browser refuses the call
+    allow_origin(cors, origin, response_headers);
+    if response_headers.contains_key(ACCESS_CONTROL_ALLOW_ORIGIN) {
+        let methods = if cors.methods.is_empty() {
+            DEFAULT_CORS_METHODS.join(", ")
+        } else {
+            cors.methods.join(", ")
+        };
+        insert(response_headers, ACCESS_CONTROL_ALLOW_METHODS, &methods);
+
+        if !cors.headers.is_empty() {
+            insert(
+                response_headers,
+                ACCESS_CONTROL_ALLOW_HEADERS,
+                &cors.headers.join(", "),
+            );
+        }
+
+        if let Some(max_age) = cors.max_age {
+            response_headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from(max_age));
+        }
+    }
+
+    Some(response)
+}
+
+/// Add the headers of `http` to the `headers` of a response of the service to a request from
+/// `origin`. The security headers the service set itself are left as they are.
+pub fn apply(http: &HttpConfig, origin: Option<&HeaderValue>, headers: &mut HeaderMap) {
+    if let (Some(cors), Some(origin)) = (&http.cors, origin) {
+        allow_origin(cors, origin, headers);
+
+        if headers.contains_key(ACCESS_CONTROL_ALLOW_ORIGIN) && !cors.expose_headers.is_empty() {
+            insert(
+                headers,
+                ACCESS_CONTROL_EXPOSE_HEADERS,
+                &cors.expose_headers.join(", "),
+            );
+        }
+    }
+
+    if let Some(hsts) = http.hsts {
+        insert_missing(
+            headers,
+            STRICT_TRANSPORT_SECURITY,
+            &format!("max-age={hsts}"),
+        );
+    }
+
+    if let Some(frame_options) = http.frame_options {
+        insert_missing(headers, X_FRAME_OPTIONS, frame_options.as_str());
+    }
+
+    if http.nosniff {
+        insert_missing(headers, X_CONTENT_TYPE_OPTIONS, "nosniff");
+    }
+
+    if let Some(policy) = &http.content_security_policy {
+        insert_missing(headers, CONTENT_SECURITY_POLICY, policy);
+    }
+}
+
+/// Let scripts of `origin` read the response, if they are allowed to
+fn allow_origin(cors: &CorsConfig, origin: &HeaderValue, headers: &mut HeaderMap) {
+    let Ok(origin_str) = origin.to_str() else {
+        return;
+    };
+
+    if !cors.allows(origin_str) {
+        return;
+    }
+
+    if cors.allows_any() && !cors.credentials {
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+    } else {
+        // The answer depends on the origin, so caches have to keep one for each
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+        headers.append(VARY, HeaderValue::from_static("Origin"));
+    }
+
+    if cors.credentials {
+        headers.insert(
+            ACCESS_CONTROL_ALLOW_CREDENTIALS,
+            HeaderValue::from_static("true"),
+        );
+    }
+}
+
+fn insert(headers: &mut HeaderMap, name: HeaderName, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(name, value);
+    }
+}
+
+fn insert_missing(headers: &mut HeaderMap, name: HeaderName, value: &str) {
+    if !headers.contains_key(&name) {
+        insert(headers, name, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::header::{
+        ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_METHODS,
+        ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN,
+        STRICT_TRANSPORT_SECURITY, VARY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+    };
+    use axum::http::{HeaderMap, HeaderValue, Method, StatusCode};
+    use shuttle_common::shuttle_toml::{CorsConfig, FrameOptions, HttpConfig};
+
+    use super::{apply, preflight};
+
+    fn cors(origins: &[&str], credentials: bool) -> HttpConfig {
+        HttpConfig {
+            cors: Some(CorsConfig {
+                origins: origins.iter().map(ToString::to_string).collect(),
+                credentials,
+                max_age: Some(600),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn preflight_headers(origin: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ORIGIN, HeaderValue::from_static(origin));
+        headers.insert(
+            ACCESS_CONTROL_REQUEST_METHOD,
+            HeaderValue::from_static("PUT"),
+        );
+
+        headers
+    }
+
+    #[test]
+    fn preflights() {
+        let http = cors(&["https://example.com"], true);
+
+        let response = preflight(
+            &http,
+            &Method::OPTIONS,
+            &preflight_headers("https://example.com"),
+        )
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let headers = response.headers();
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "https://example.com");
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_METHODS], "GET, HEAD, POST");
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(headers[ACCESS_CONTROL_MAX_AGE], "600");
+        assert_eq!(headers[VARY], "Origin");
+
+        // Other sites get no CORS headers, so that their scripts are refused by the browser
+        let response = preflight(
+            &http,
+            &Method::OPTIONS,
+            &preflight_headers("https://evil.example"),
+        )
+        .unwrap();
+        assert!(!response.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        // Other requests, and services without CORS settings, go to the service
+        assert!(preflight(
+            &http,
+            &Method::PUT,
+            &preflight_headers("https://example.com")
+        )
+        .is_none());
+        assert!(preflight(&http, &Method::OPTIONS, &HeaderMap::new()).is_none());
+        assert!(preflight(
+            &HttpConfig::default(),
+            &Method::OPTIONS,
+            &preflight_headers("https://example.com")
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn any_origin() {
+        let http = cors(&["*"], false);
+        let origin = HeaderValue::from_static("https://example.com");
+
+        let mut headers = HeaderMap::new();
+        apply(&http, Some(&origin), &mut headers);
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(!headers.contains_key(VARY));
+
+        // Requests which are not from scripts of other sites have no origin
+        let mut headers = HeaderMap::new();
+        apply(&http, None, &mut headers);
+        assert!(headers.is_empty());
+    }
+
+    #[test]
+    fn security_headers() {
+        let http = HttpConfig {
+            hsts: Some(31536000),
+            frame_options: Some(FrameOptions::Deny),
+            nosniff: true,
+            ..Default::default()
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert(X_FRAME_OPTIONS, HeaderValue::from_static("SAMEORIGIN"));
+        apply(&http, None, &mut headers);
+
+        assert_eq!(headers[STRICT_TRANSPORT_SECURITY], "max-age=31536000");
+        assert_eq!(headers[X_CONTENT_TYPE_OPTIONS], "nosniff");
+        // The service knows better than the defaults of its project
+        assert_eq!(headers[X_FRAME_OPTIONS], "SAMEORIGIN");
+    }
+}
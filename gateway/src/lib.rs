@@ -16,17 +16,26 @@ use futures::prelude::*;
 use serde::{Deserialize, Deserializer, Serialize};
 use service::ContainerSettings;
 use shuttle_common::models::error::{ApiError, ErrorKind};
+use shuttle_common::project::ENVIRONMENT_SEPARATOR;
 use tokio::sync::mpsc::error::SendError;
 use tracing::error;
 
+pub mod access;
+pub mod access_log;
 pub mod acme;
 pub mod api;
 pub mod args;
 pub mod auth;
+pub mod cache;
+pub mod headers;
+pub mod limits;
+pub mod monitoring;
 pub mod project;
 pub mod proxy;
 pub mod service;
 pub mod task;
+pub mod tcp;
+pub mod throttle;
 pub mod tls;
 pub mod worker;
 
@@ -141,7 +150,9 @@ impl ProjectName {
             || name.ends_with('-')
             || name.starts_with('-')
             || name.is_empty()
-            || is_invalid_length)
+            || is_invalid_length
+            // Reserved for the subdomains of the environments of projects
+            || name.contains(ENVIRONMENT_SEPARATOR))
     }
 }
 
@@ -351,7 +362,7 @@ pub mod tests {
 
     use crate::acme::AcmeClient;
     use crate::api::latest::ApiBuilder;
-    use crate::args::{ContextArgs, StartArgs, UseTls};
+    use crate::args::{ContextArgs, ProxyLimitsArgs, StartArgs, UseTls};
     use crate::proxy::UserServiceBuilder;
     use crate::service::{ContainerSettings, GatewayService, MIGRATIONS};
     use crate::worker::Worker;
@@ -603,6 +614,10 @@ pub mod tests {
                 user,
                 bouncer,
                 use_tls: UseTls::Disable,
+                deploys_per_hour: 30,
+                rate_limit_allowlist: Vec::new(),
+                tcp_ports: None,
+                response_cache_budget: 0,
                 context: ContextArgs {
                     docker_host,
                     image,
@@ -611,6 +626,13 @@ pub mod tests {
                     auth_uri: auth_uri.clone(),
                     network_name,
                     proxy_fqdn: FQDN::from_str("test.shuttleapp.rs").unwrap(),
+                    proxy_limits: ProxyLimitsArgs {
+                        max_request_body_mib: 32,
+                        response_timeout: 120,
+                        idle_timeout: 60,
+                        requests_per_second: 100,
+                        burst: 200,
+                    },
                 },
             };
 
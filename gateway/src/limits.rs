@@ -0,0 +1,172 @@
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::Future;
+use http::HeaderMap;
+use hyper::body::{Body, Bytes, HttpBody, SizeHint};
+use pin_project::pin_project;
+use tokio::time::{sleep, Instant, Sleep};
+
+/// Why a body was cut off
+#[derive(Debug, strum::Display)]
+pub enum LimitError {
+    /// The body has more bytes than it may
+    TooLarge,
+    /// The body went without data for longer than it may
+    Idle,
+    /// The body failed on its own
+    Body(hyper::Error),
+}
+
+impl std::error::Error for LimitError {}
+
+/// A body which is cut off when it gets larger than it may, or goes without data for too long, so
+/// that clients and services which never finish their bodies can't hold on to the gateway
+#[pin_project]
+pub struct LimitedBody {
+    #[pin]
+    body: Body,
+    /// How many more bytes the body may have, when its size is limited
+    remaining: Option<u64>,
+    idle_timeout: Duration,
+    #[pin]
+    idle: Sleep,
+    /// Set when the body is cut off for being too large, for the proxy to tell why it failed
+    too_large: Arc<AtomicBool>,
+    done: bool,
+}
+
+impl LimitedBody {
+    pub fn new(body: Body, max_size: Option<u64>, idle_timeout: Duration) -> Self {
+        Self {
+            body,
+            remaining: max_size,
+            idle_timeout,
+            idle: sleep(idle_timeout),
+            too_large: Default::default(),
+            done: false,
+        }
+    }
+
+    /// A flag which is set when the body is cut off for being too large
+    pub fn too_large(&self) -> Arc<AtomicBool> {
+        self.too_large.clone()
+    }
+
+    /// Turn this into a [Body] which streams the data, without the trailers, for the bodies of
+    /// requests which can only be proxied as a [Body]
+    pub fn into_stream_body(self) -> Body {
+        let mut body = Box::pin(self);
+        let stream = futures::stream::poll_fn(move |cx| body.as_mut().poll_data(cx));
+
+        Body::wrap_stream(stream)
+    }
+}
+
+impl HttpBody for LimitedBody {
+    type Data = Bytes;
+    type Error = LimitError;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let mut this = self.project();
+
+        if *this.done {
+            return Poll::Ready(None);
+        }
+
+        match this.body.poll_data(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                if let Some(remaining) = this.remaining {
+                    match remaining.checked_sub(chunk.len() as u64) {
+                        Some(left) => *remaining = left,
+                        None => {
+                            *this.done = true;
+                            this.too_large.store(true, Ordering::Relaxed);
+                            return Poll::Ready(Some(Err(LimitError::TooLarge)));
+                        }
+                    }
+                }
+
+                this.idle
+                    .as_mut()
+                    .reset(Instant::now() + *this.idle_timeout);
+
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(Some(Err(error))) => {
+                *this.done = true;
+                Poll::Ready(Some(Err(LimitError::Body(error))))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => match this.idle.poll(cx) {
+                Poll::Ready(()) => {
+                    *this.done = true;
+                    Poll::Ready(Some(Err(LimitError::Idle)))
+                }
+                Poll::Pending => Poll::Pending,
+            },
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.project();
+
+        if *this.done {
+            return Poll::Ready(Ok(None));
+        }
+
+        this.body.poll_trailers(cx).map_err(LimitError::Body)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.done || self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    use hyper::body::{to_bytes, Body, Bytes};
+
+    use super::{LimitError, LimitedBody};
+
+    #[tokio::test]
+    async fn too_large() {
+        let body = LimitedBody::new(Body::from("hello"), Some(5), Duration::from_secs(1));
+        assert_eq!(to_bytes(body).await.unwrap(), "hello");
+
+        let body = LimitedBody::new(Body::from("hello!"), Some(5), Duration::from_secs(1));
+        let too_large = body.too_large();
+        assert!(matches!(to_bytes(body).await, Err(LimitError::TooLarge)));
+        assert!(too_large.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn idle() {
+        let (mut sender, body) = Body::channel();
+        let body = LimitedBody::new(body, None, Duration::from_millis(100));
+
+        tokio::spawn(async move {
+            sender.send_data(Bytes::from("hello")).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            let _ = sender.send_data(Bytes::from("too late")).await;
+        });
+
+        assert!(matches!(to_bytes(body).await, Err(LimitError::Idle)));
+    }
+}
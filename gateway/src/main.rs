@@ -6,9 +6,11 @@ use shuttle_gateway::acme::{AcmeClient, CustomDomain};
 use shuttle_gateway::api::latest::{ApiBuilder, SVC_DEGRADED_THRESHOLD};
 use shuttle_gateway::args::StartArgs;
 use shuttle_gateway::args::{Args, Commands, UseTls};
+use shuttle_gateway::cache::ResponseCache;
 use shuttle_gateway::proxy::UserServiceBuilder;
 use shuttle_gateway::service::{GatewayService, MIGRATIONS};
 use shuttle_gateway::task;
+use shuttle_gateway::tcp::TcpForwarder;
 use shuttle_gateway::tls::make_tls_acceptor;
 use shuttle_gateway::worker::{Worker, WORKER_QUEUE_SIZE};
 use sqlx::migrate::MigrateDatabase;
@@ -138,17 +140,38 @@ async fn start(db: SqlitePool, fs: PathBuf, args: StartArgs) -> io::Result<()> {
 
     let acme_client = AcmeClient::new();
 
+    let response_cache = Arc::new(ResponseCache::new(args.response_cache_budget));
+
     let mut api_builder = ApiBuilder::new()
         .with_service(Arc::clone(&gateway))
         .with_sender(sender.clone())
-        .binding_to(args.control);
+        .binding_to(args.control)
+        .with_deploy_rate_limit(args.deploys_per_hour)
+        .with_response_cache(Arc::clone(&response_cache));
+
+    if let Some(tcp_ports) = args.tcp_ports {
+        let forwarder = TcpForwarder::new(
+            Arc::clone(&gateway),
+            sender.clone(),
+            args.user.ip(),
+            tcp_ports,
+        );
+        forwarder
+            .listen_all()
+            .await
+            .expect("to listen on the ports which forward raw TCP");
+
+        api_builder = api_builder.with_tcp_forwarder(forwarder);
+    }
 
     let mut user_builder = UserServiceBuilder::new()
         .with_service(Arc::clone(&gateway))
         .with_task_sender(sender)
         .with_public(args.context.proxy_fqdn.clone())
         .with_user_proxy_binding_to(args.user)
-        .with_bouncer(args.bouncer);
+        .with_bouncer(args.bouncer)
+        .with_rate_limit_allowlist(args.rate_limit_allowlist)
+        .with_response_cache(response_cache);
 
     if let UseTls::Enable = args.use_tls {
         let (resolver, tls_acceptor) = make_tls_acceptor();
@@ -175,6 +198,27 @@ async fn start(db: SqlitePool, fs: PathBuf, args: StartArgs) -> io::Result<()> {
                 .unwrap();
         }
 
+        // Every 12 hours renew the certificates of custom domains which are about to expire.
+        tokio::spawn({
+            let gateway = Arc::clone(&gateway);
+            let acme_client = acme_client.clone();
+            let resolver = resolver.clone();
+            async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(12 * 60 * 60));
+
+                loop {
+                    interval.tick().await;
+
+                    if let Err(error) = gateway
+                        .renew_custom_domain_certificates(&acme_client, &resolver)
+                        .await
+                    {
+                        error!(%error, "failed to renew the certificates of custom domains");
+                    }
+                }
+            }
+        });
+
         tokio::spawn(async move {
             // Make sure we have a certificate for ourselves.
             let certs = gateway
@@ -0,0 +1,45 @@
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_int_counter_vec, register_int_gauge, Encoder, IntCounterVec, IntGauge, TextEncoder,
+};
+
+use crate::ProjectName;
+
+static RATE_LIMITED_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "shuttle_gateway_rate_limited_requests_total",
+        "How many requests to each project were refused for being over its rate limit",
+        &["project"]
+    )
+    .expect("to register the rate limited requests metric")
+});
+
+static THROTTLED_CLIENTS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "shuttle_gateway_throttled_clients",
+        "How many clients of projects the rate limits are being kept for"
+    )
+    .expect("to register the throttled clients metric")
+});
+
+/// Record that a request to the project was refused by its rate limit
+pub fn inc_rate_limited(project_name: &ProjectName) {
+    RATE_LIMITED_REQUESTS
+        .with_label_values(&[&project_name.to_string()])
+        .inc();
+}
+
+/// Record how many clients the rate limits are being kept for
+pub fn set_throttled_clients(clients: usize) {
+    THROTTLED_CLIENTS.set(clients as i64);
+}
+
+/// Render the metrics which were recorded in the Prometheus text format
+pub fn render() -> String {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .expect("to encode the metrics");
+
+    String::from_utf8(buffer).expect("metrics to be UTF-8")
+}
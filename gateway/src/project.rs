@@ -1530,11 +1530,14 @@ pub mod exec {
             .await
             .expect("could not list projects")
         {
+            let Ok(gateway_ctx) = gateway.context_of(&project_name).await else {
+                continue;
+            };
+
             match gateway.find_project(&project_name).await.unwrap() {
                 Project::Errored(ProjectError { ctx: Some(ctx), .. }) => {
                     if let Some(container) = ctx.container() {
-                        if let Ok(container) = gateway
-                            .context()
+                        if let Ok(container) = gateway_ctx
                             .docker()
                             .inspect_container(safe_unwrap!(container.id), None)
                             .await
@@ -1587,8 +1590,7 @@ pub mod exec {
                 }
                 // Currently nothing should enter the stopped state
                 Project::Stopped(ProjectStopped { container }) => {
-                    if let Ok(container) = gateway
-                        .context()
+                    if let Ok(container) = gateway_ctx
                         .docker()
                         .inspect_container(safe_unwrap!(container.id), None)
                         .await
@@ -1,19 +1,25 @@
 use std::convert::Infallible;
 use std::future::Future;
 use std::io;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::pin::Pin;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
-use axum::headers::{HeaderMapExt, Host};
-use axum::response::{IntoResponse, Response};
+use axum::headers::{ContentLength, HeaderMapExt, Host};
+use axum::http::header::{AUTHORIZATION, ORIGIN, RETRY_AFTER, WWW_AUTHENTICATE};
+use axum::http::response::Parts;
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
 use axum_server::accept::DefaultAcceptor;
 use axum_server::tls_rustls::RustlsAcceptor;
+use chrono::Utc;
 use fqdn::{fqdn, FQDN};
 use futures::future::{ready, Ready};
 use futures::prelude::*;
-use hyper::body::{Body, HttpBody};
+use hyper::body::{Body, Bytes, HttpBody};
 use hyper::client::connect::dns::GaiResolver;
 use hyper::client::HttpConnector;
 use hyper::server::conn::AddrStream;
@@ -23,20 +29,36 @@ use once_cell::sync::Lazy;
 use opentelemetry::global;
 use opentelemetry_http::HeaderInjector;
 use shuttle_common::backends::headers::XShuttleProject;
+use shuttle_common::backends::{pages, proxy};
+use shuttle_common::models::access_log::Entry;
+use shuttle_common::models::project::Maintenance;
+use shuttle_common::project::ENVIRONMENT_SEPARATOR;
 use tokio::sync::mpsc::Sender;
+use tokio::time::timeout;
 use tower::{Service, ServiceBuilder};
 use tower_sanitize_path::SanitizePath;
-use tracing::{debug_span, error, field, trace};
+use tracing::{debug, debug_span, error, field, trace};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
+use crate::access::Denied;
+use crate::access_log::{AccessLogger, LoggedBody};
 use crate::acme::{AcmeClient, ChallengeResponderLayer, CustomDomain};
+use crate::cache::{self, ResponseCache};
+use crate::headers;
+use crate::limits::LimitedBody;
+use crate::monitoring;
 use crate::service::GatewayService;
 use crate::task::BoxedTask;
-use crate::{Error, ErrorKind};
+use crate::throttle::Throttle;
+use crate::{Error, ErrorKind, ProjectName};
 
 static PROXY_CLIENT: Lazy<ReverseProxy<HttpConnector<GaiResolver>>> =
     Lazy::new(|| ReverseProxy::new(Client::new()));
 
+/// Proxies the gRPC calls, which have to stay on HTTP/2, to the deployers over h2c
+static H2C_PROXY_CLIENT: Lazy<ReverseProxy<HttpConnector<GaiResolver>>> =
+    Lazy::new(|| ReverseProxy::new(Client::builder().http2_only(true).build_http()));
+
 pub trait AsResponderTo<R> {
     fn as_responder_to(&self, req: R) -> Self;
 
@@ -75,6 +97,9 @@ pub struct UserProxy {
     task_sender: Sender<BoxedTask>,
     remote_addr: SocketAddr,
     public: FQDN,
+    throttle: Arc<Throttle>,
+    access_logger: AccessLogger,
+    cache: Arc<ResponseCache>,
 }
 
 impl<'r> AsResponderTo<&'r AddrStream> for UserProxy {
@@ -90,10 +115,7 @@ where
     S: AsResponderTo<R> + Clone,
 {
     fn as_responder_to(&self, req: R) -> Self {
-        let responder = self.clone();
-        responder.inner().as_responder_to(req);
-
-        responder
+        SanitizePath::sanitize_paths(self.inner().as_responder_to(req))
     }
 }
 
@@ -106,63 +128,328 @@ impl UserProxy {
         let span = debug_span!("proxy", http.method = %req.method(), http.host = ?req.headers().get("Host"), http.uri = %req.uri(), http.status_code = field::Empty, project = field::Empty);
         trace!(?req, "serving proxy request");
 
+        let started = Instant::now();
+        let timestamp = Utc::now();
+        let method = req.method().to_string();
+        let path = req.uri().path().to_owned();
+        let html = pages::wants_html(req.headers());
+
+        let h2c = proxy::prepare(&mut req);
+
         let fqdn = req
             .headers()
             .typed_get::<Host>()
             .map(|host| fqdn!(host.hostname()))
             .ok_or_else(|| Error::from_kind(ErrorKind::ProjectNotFound))?;
 
-        let project_name =
+        // The services of the environments of a project are at `{environment}--{project}`, which
+        // the deployer of the project routes to while they have a running deployment
+        let (project_name, service_name): (ProjectName, String) =
             if fqdn.is_subdomain_of(&self.public) && fqdn.depth() - self.public.depth() == 1 {
-                fqdn.labels()
-                    .next()
-                    .unwrap()
-                    .to_owned()
-                    .parse()
-                    .map_err(|_| Error::from_kind(ErrorKind::ProjectNotFound))?
+                let label = fqdn.labels().next().unwrap();
+
+                if label.contains(ENVIRONMENT_SEPARATOR) {
+                    self.gateway.find_route(&fqdn).await?
+                } else {
+                    let project_name = label
+                        .parse()
+                        .map_err(|_| Error::from_kind(ErrorKind::ProjectNotFound))?;
+
+                    (project_name, label.to_owned())
+                }
             } else if let Ok(CustomDomain { project_name, .. }) =
                 self.gateway.project_details_for_custom_domain(&fqdn).await
             {
-                project_name
+                let service_name = project_name.to_string();
+
+                (project_name, service_name)
             } else {
                 return Err(Error::from_kind(ErrorKind::ProjectNotFound));
             };
 
         req.headers_mut()
-            .typed_insert(XShuttleProject(project_name.to_string()));
+            .typed_insert(XShuttleProject(service_name.clone()));
 
-        let project = self
+        if let Some(rules) = self
             .gateway
-            .find_or_start_project(&project_name, task_sender)
-            .await?;
-
-        // Record current project for tracing purposes
-        span.record("project", &project_name.to_string());
+            .access_rules(&project_name, &service_name)
+            .await?
+        {
+            match rules.check(self.remote_addr.ip(), req.headers()) {
+                // The credentials of the guard are for the gateway, not for the service
+                Ok(()) if rules.is_guarded() => {
+                    req.headers_mut().remove(AUTHORIZATION);
+                }
+                Ok(()) => {}
+                Err(Denied::Address) => return Err(Error::from_kind(ErrorKind::Forbidden)),
+                Err(Denied::Credentials(challenge)) => {
+                    let mut response = Error::from_kind(ErrorKind::Unauthorized).into_response();
+                    response
+                        .headers_mut()
+                        .insert(WWW_AUTHENTICATE, HeaderValue::from_static(challenge));
+
+                    return Ok(response);
+                }
+            }
+        }
 
-        let target_ip = project
-            .target_ip()?
-            .ok_or_else(|| Error::from_kind(ErrorKind::ProjectNotReady))?;
+        if let Some(maintenance) = self.gateway.maintenance(&project_name).await? {
+            return Ok(maintenance_response(&project_name, maintenance));
+        }
 
-        let target_url = format!("http://{}:{}", target_ip, 8000);
+        let limits = self.gateway.proxy_limits(&project_name).await?;
+        let idle_timeout = Duration::from_secs(limits.idle_timeout_secs);
+
+        // Refuse the clients which are over the rate of the project before starting it for them,
+        // so that one project being flooded does not slow down its neighbours
+        if let Err(retry_after) = self.throttle.check(
+            &project_name,
+            self.remote_addr.ip(),
+            limits.requests_per_second,
+            limits.burst,
+        ) {
+            debug!(
+                %project_name,
+                client = %self.remote_addr.ip(),
+                ?retry_after,
+                "refusing request over the rate limit"
+            );
+            monitoring::inc_rate_limited(&project_name);
+
+            let mut response = Error::from_kind(ErrorKind::RateLimited).into_response();
+            response.headers_mut().insert(
+                RETRY_AFTER,
+                HeaderValue::from(retry_after.as_secs_f64().ceil() as u64),
+            );
+
+            return Ok(response);
+        }
+        monitoring::set_throttled_clients(self.throttle.clients());
 
-        let cx = span.context();
+        // Refuse the bodies which say they are too large before starting the project for them
+        if req
+            .headers()
+            .typed_get::<ContentLength>()
+            .map_or(false, |length| length.0 > limits.max_request_body_bytes)
+        {
+            return Err(Error::from_kind(ErrorKind::RequestTooLarge));
+        }
 
-        global::get_text_map_propagator(|propagator| {
-            propagator.inject_context(&cx, &mut HeaderInjector(req.headers_mut()))
-        });
+        // The browsers asking whether scripts of other sites can call the service are answered
+        // here, so that the service does not need to handle CORS itself
+        let http = self
+            .gateway
+            .http_headers(&project_name, &service_name)
+            .await?;
+        if let Some(response) = http
+            .as_ref()
+            .and_then(|http| headers::preflight(http, req.method(), req.headers()))
+        {
+            return Ok(response);
+        }
+        let origin = req.headers().get(ORIGIN).cloned();
+
+        // The responses to the paths the service asked to have cached are served from the cache,
+        // without even starting the project for them
+        let cache_key = http
+            .as_ref()
+            .and_then(|http| http.cache.as_ref())
+            .filter(|cache| {
+                cache.covers(req.uri().path())
+                    && cache::is_cacheable_request(req.method(), req.headers())
+            })
+            .map(|_| {
+                let key = cache::Key::new(&service_name, &fqdn.to_string(), req.uri());
+                (key, req.headers().clone())
+            });
+        let cached = cache_key
+            .as_ref()
+            .and_then(|(key, headers)| self.cache.get(&project_name, key, headers));
+
+        let (mut parts, body) = match cached {
+            Some(cached) => cached.into_parts(),
+            None => {
+                let project = self
+                    .gateway
+                    .find_or_start_project(&project_name, task_sender)
+                    .await?;
+
+                // Record current project for tracing purposes
+                span.record("project", &project_name.to_string());
+
+                let Some(target_ip) = project.target_ip()? else {
+                    return unavailable(
+                        html,
+                        &project_name,
+                        &format!(
+                            "The project is {}, please try again in a moment.",
+                            project.state()
+                        ),
+                        ErrorKind::ProjectNotReady,
+                    );
+                };
+
+                let target_url = format!("http://{}:{}", target_ip, 8000);
+
+                let cx = span.context();
+
+                global::get_text_map_propagator(|propagator| {
+                    propagator.inject_context(&cx, &mut HeaderInjector(req.headers_mut()))
+                });
+
+                let client = if h2c {
+                    &H2C_PROXY_CLIENT
+                } else {
+                    &PROXY_CLIENT
+                };
+
+                let (parts, body) = req.into_parts();
+                let body =
+                    LimitedBody::new(body, Some(limits.max_request_body_bytes), idle_timeout);
+                let too_large = body.too_large();
+                let req = Request::from_parts(parts, body.into_stream_body());
+
+                let proxy = match timeout(
+                    Duration::from_secs(limits.response_timeout_secs),
+                    client.call(self.remote_addr.ip(), &target_url, req),
+                )
+                .await
+                {
+                    Ok(Ok(proxy)) => proxy,
+                    Ok(Err(_)) if too_large.load(Ordering::Relaxed) => {
+                        return Err(Error::from_kind(ErrorKind::RequestTooLarge))
+                    }
+                    Ok(Err(_)) => {
+                        return unavailable(
+                            html,
+                            &project_name,
+                            "The project is not answering, it may be restarting or down.",
+                            ErrorKind::ProjectUnavailable,
+                        )
+                    }
+                    Err(_elapsed) => return Err(Error::from_kind(ErrorKind::ProjectTimedOut)),
+                };
+
+                let (parts, body) = proxy.into_parts();
+
+                match cache_key {
+                    Some((key, headers)) => {
+                        self.cache_response(&project_name, key, &headers, parts, body, idle_timeout)
+                            .await
+                    }
+                    None => (parts, body),
+                }
+            }
+        };
 
-        let proxy = PROXY_CLIENT
-            .call(self.remote_addr.ip(), &target_url, req)
-            .await
-            .map_err(|_| Error::from_kind(ErrorKind::ProjectUnavailable))?;
+        if let Some(http) = &http {
+            headers::apply(http, origin.as_ref(), &mut parts.headers);
+        }
 
-        let (parts, body) = proxy.into_parts();
-        let body = <Body as HttpBody>::map_err(body, axum::Error::new).boxed_unsync();
+        let entry = Entry {
+            timestamp,
+            service: service_name,
+            method,
+            path,
+            status: parts.status.as_u16(),
+            latency_ms: started.elapsed().as_millis() as u64,
+            bytes: 0,
+            client: self.client_hash(),
+        };
+        let body = LoggedBody::new(
+            LimitedBody::new(body, None, idle_timeout),
+            self.access_logger.clone(),
+            project_name,
+            entry,
+        )
+        .map_err(axum::Error::new)
+        .boxed_unsync();
 
         span.record("http.status_code", parts.status.as_u16());
 
         Ok(Response::from_parts(parts, body))
     }
+
+    /// Tells the client of this connection apart in the access logs, without its address
+    fn client_hash(&self) -> String {
+        self.access_logger.hash_client(self.remote_addr.ip())
+    }
+
+    /// Keep the response of the service for as long as it can be cached, when it says it is small
+    /// enough to be buffered
+    async fn cache_response(
+        &self,
+        project_name: &ProjectName,
+        key: cache::Key,
+        headers: &HeaderMap,
+        parts: Parts,
+        body: Body,
+        idle_timeout: Duration,
+    ) -> (Parts, Body) {
+        let Some(ttl) = cache::ttl(parts.status, &parts.headers) else {
+            return (parts, body);
+        };
+        if !parts
+            .headers
+            .typed_get::<ContentLength>()
+            .map_or(false, |length| length.0 <= self.cache.max_response())
+        {
+            return (parts, body);
+        }
+
+        match timeout(idle_timeout, hyper::body::to_bytes(body)).await {
+            Ok(Ok(bytes)) => {
+                self.cache
+                    .insert(project_name, key, headers, &parts, bytes.clone(), ttl);
+
+                (parts, Body::from(bytes))
+            }
+            Ok(Err(error)) => {
+                debug!(%error, "failed to read the response to cache");
+                (
+                    parts,
+                    Body::wrap_stream(stream::once(ready(Err::<Bytes, _>(error)))),
+                )
+            }
+            Err(elapsed) => {
+                debug!(%project_name, "the response to cache did not arrive in time");
+                (
+                    parts,
+                    Body::wrap_stream(stream::once(ready(Err::<Bytes, _>(elapsed)))),
+                )
+            }
+        }
+    }
+}
+
+/// The page served instead of a project which is under maintenance
+fn maintenance_response(project_name: &ProjectName, maintenance: Maintenance) -> Response {
+    let status =
+        StatusCode::from_u16(maintenance.status).unwrap_or(StatusCode::SERVICE_UNAVAILABLE);
+    let page = maintenance.page.unwrap_or_else(|| {
+        pages::page(
+            &format!("{project_name} is under maintenance"),
+            "It will be back soon, please try again later.",
+        )
+    });
+
+    (status, Html(page)).into_response()
+}
+
+/// A page telling a browser why the project cannot answer, while other clients get the `error`
+fn unavailable(
+    html: bool,
+    project_name: &ProjectName,
+    reason: &str,
+    error: ErrorKind,
+) -> Result<Response, Error> {
+    if !html {
+        return Err(Error::from_kind(error));
+    }
+
+    let page = pages::page(&format!("{project_name} is unavailable"), reason);
+
+    Ok((StatusCode::BAD_GATEWAY, Html(page)).into_response())
 }
 
 impl Service<Request<Body>> for UserProxy {
@@ -249,6 +536,8 @@ pub struct UserServiceBuilder {
     bouncer_binds_to: Option<SocketAddr>,
     user_binds_to: Option<SocketAddr>,
     public: Option<FQDN>,
+    rate_limit_allowlist: Vec<IpAddr>,
+    cache: Option<Arc<ResponseCache>>,
 }
 
 impl Default for UserServiceBuilder {
@@ -267,6 +556,8 @@ impl UserServiceBuilder {
             tls_acceptor: None,
             bouncer_binds_to: None,
             user_binds_to: None,
+            rate_limit_allowlist: Vec::new(),
+            cache: None,
         }
     }
 
@@ -305,6 +596,18 @@ impl UserServiceBuilder {
         self
     }
 
+    /// Never rate limit the requests of these clients
+    pub fn with_rate_limit_allowlist(mut self, allowlist: Vec<IpAddr>) -> Self {
+        self.rate_limit_allowlist = allowlist;
+        self
+    }
+
+    /// Keep the responses to the paths services ask to have cached in `cache`
+    pub fn with_response_cache(mut self, cache: Arc<ResponseCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
     pub fn serve(self) -> impl Future<Output = Result<(), io::Error>> {
         let service = self.service.expect("a GatewayService is required");
         let task_sender = self.task_sender.expect("a task sender is required");
@@ -318,6 +621,11 @@ impl UserServiceBuilder {
             task_sender,
             remote_addr: "127.0.0.1:80".parse().unwrap(),
             public: public.clone(),
+            throttle: Arc::new(Throttle::new(self.rate_limit_allowlist)),
+            access_logger: AccessLogger::spawn(service.clone()),
+            cache: self
+                .cache
+                .unwrap_or_else(|| Arc::new(ResponseCache::new(0))),
         })
         .into_make_service();
 
@@ -377,3 +685,166 @@ impl UserServiceBuilder {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+    use std::net::{IpAddr, SocketAddr};
+    use std::pin::Pin;
+    use std::sync::Arc;
+
+    use axum::http::StatusCode;
+    use hyper::server::accept::Accept;
+    use hyper::server::conn::AddrIncoming;
+    use hyper::{Body, Request};
+    use shuttle_common::models::access;
+    use shuttle_common::models::project::ProxyLimits;
+    use tokio::net::{TcpListener, TcpSocket};
+    use tokio::sync::mpsc::channel;
+    use tower::Service;
+    use tower_sanitize_path::SanitizePath;
+
+    use super::{AsResponderTo, ResponderMakeService, UserProxy};
+    use crate::access::AccessRules;
+    use crate::access_log::AccessLogger;
+    use crate::cache::ResponseCache;
+    use crate::service::GatewayService;
+    use crate::tests::World;
+    use crate::throttle::Throttle;
+    use crate::{AccountName, ProjectName};
+
+    type UserProxyMakeService = ResponderMakeService<SanitizePath<UserProxy>>;
+
+    /// The user proxy of a gateway, as it is served, in front of a project which is not ready yet
+    async fn user_proxy(world: &World) -> (UserProxyMakeService, Arc<GatewayService>) {
+        let gateway = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+        let (task_sender, _) = channel(1);
+
+        gateway
+            .create_project(
+                "matrix".parse().unwrap(),
+                "neo".parse::<AccountName>().unwrap(),
+                false,
+                0,
+            )
+            .await
+            .unwrap();
+
+        let make_service = SanitizePath::sanitize_paths(UserProxy {
+            gateway: gateway.clone(),
+            task_sender,
+            remote_addr: "127.0.0.1:80".parse().unwrap(),
+            public: world.fqdn(),
+            throttle: Arc::new(Throttle::new(Vec::new())),
+            access_logger: AccessLogger::spawn(gateway.clone()),
+            cache: Arc::new(ResponseCache::new(0)),
+        })
+        .into_make_service();
+
+        (make_service, gateway)
+    }
+
+    /// Connect to the user proxy from `peer`, and get what serves the requests of the connection
+    async fn connect(
+        make_service: &mut UserProxyMakeService,
+        peer: [u8; 4],
+    ) -> SanitizePath<UserProxy> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut incoming = AddrIncoming::from_listener(listener).unwrap();
+
+        let socket = TcpSocket::new_v4().unwrap();
+        socket.bind(SocketAddr::new(IpAddr::from(peer), 0)).unwrap();
+        let _client = socket.connect(address).await.unwrap();
+
+        let stream = poll_fn(|cx| Pin::new(&mut incoming).poll_accept(cx))
+            .await
+            .unwrap()
+            .unwrap();
+
+        make_service.call(&stream).await.unwrap()
+    }
+
+    async fn get(responder: &mut SanitizePath<UserProxy>) -> StatusCode {
+        let request = Request::get("/")
+            .header("Host", "matrix.test.shuttleapp.rs")
+            .body(Body::empty())
+            .unwrap();
+
+        responder.call(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn rate_limits_each_client() {
+        let world = World::new().await;
+        let (mut make_service, gateway) = user_proxy(&world).await;
+        let matrix: ProjectName = "matrix".parse().unwrap();
+
+        // A single request a second, so that the second request of a client is refused
+        gateway
+            .set_proxy_limits(
+                &matrix,
+                &ProxyLimits {
+                    max_request_body_bytes: 1024,
+                    response_timeout_secs: 10,
+                    idle_timeout_secs: 10,
+                    requests_per_second: 1,
+                    burst: 1,
+                },
+            )
+            .await
+            .unwrap();
+
+        let mut neo = connect(&mut make_service, [127, 0, 0, 2]).await;
+        let mut trinity = connect(&mut make_service, [127, 0, 0, 3]).await;
+        assert_eq!(neo.inner().remote_addr.ip(), IpAddr::from([127, 0, 0, 2]));
+
+        assert_eq!(get(&mut neo).await, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(get(&mut neo).await, StatusCode::TOO_MANY_REQUESTS);
+
+        // Another client has a bucket of its own
+        assert_eq!(get(&mut trinity).await, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn access_logs_tell_clients_apart() {
+        let world = World::new().await;
+        let (mut make_service, _) = user_proxy(&world).await;
+
+        let neo = connect(&mut make_service, [127, 0, 0, 2]).await;
+        let trinity = connect(&mut make_service, [127, 0, 0, 3]).await;
+        let neo_again = connect(&mut make_service, [127, 0, 0, 2]).await;
+
+        assert_ne!(neo.inner().client_hash(), trinity.inner().client_hash());
+        assert_eq!(neo.inner().client_hash(), neo_again.inner().client_hash());
+    }
+
+    #[tokio::test]
+    async fn access_rules_check_the_client() {
+        let world = World::new().await;
+        let (mut make_service, gateway) = user_proxy(&world).await;
+
+        gateway
+            .set_access_rules(
+                &"matrix".parse().unwrap(),
+                "matrix",
+                &AccessRules::new(access::Request {
+                    allow: vec!["127.0.0.0/30".parse().unwrap()],
+                    deny: vec!["127.0.0.3".parse().unwrap()],
+                    guard: None,
+                }),
+            )
+            .await
+            .unwrap();
+
+        // Let through to the project, which is not ready yet
+        let mut neo = connect(&mut make_service, [127, 0, 0, 2]).await;
+        assert_eq!(get(&mut neo).await, StatusCode::SERVICE_UNAVAILABLE);
+
+        let mut trinity = connect(&mut make_service, [127, 0, 0, 3]).await;
+        assert_eq!(get(&mut trinity).await, StatusCode::FORBIDDEN);
+
+        let mut morpheus = connect(&mut make_service, [127, 0, 0, 9]).await;
+        assert_eq!(get(&mut morpheus).await, StatusCode::FORBIDDEN);
+    }
+}
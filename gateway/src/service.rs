@@ -1,15 +1,22 @@
+use std::collections::{HashMap, HashSet};
 use std::io::Cursor;
-use std::net::Ipv4Addr;
-use std::ops::Sub;
+use std::net::{IpAddr, Ipv4Addr};
+use std::ops::{RangeInclusive, Sub};
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 use axum::body::Body;
 use axum::headers::HeaderMapExt;
 use axum::http::Request;
 use axum::response::Response;
+use bollard::container::{
+    Config, CreateContainerOptions, DownloadFromContainerOptions, UploadToContainerOptions,
+};
+use bollard::errors::Error as DockerError;
+use bollard::service::{HostConfig, Mount, MountTypeEnum};
 use bollard::{Docker, API_DEFAULT_VERSION};
 use fqdn::{Fqdn, FQDN};
+use futures::TryStreamExt;
 use hyper::client::connect::dns::GaiResolver;
 use hyper::client::HttpConnector;
 use hyper::Client;
@@ -19,23 +26,28 @@ use once_cell::sync::Lazy;
 use opentelemetry::global;
 use opentelemetry_http::HeaderInjector;
 use shuttle_common::backends::headers::{XShuttleAccountName, XShuttleAdminSecret};
+use shuttle_common::models::node;
+use shuttle_common::models::project::{Maintenance, ProxyLimits, TcpEndpoint};
+use shuttle_common::shuttle_toml::HttpConfig;
 use sqlx::error::DatabaseError;
 use sqlx::migrate::Migrator;
 use sqlx::sqlite::SqlitePool;
 use sqlx::types::Json as SqlxJson;
 use sqlx::{query, Error as SqlxError, QueryBuilder, Row};
 use tokio::sync::mpsc::Sender;
-use tracing::{debug, trace, warn, Span};
+use tracing::{debug, error, info, trace, warn, Span};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
 use x509_parser::nom::AsBytes;
 use x509_parser::parse_x509_certificate;
 use x509_parser::prelude::parse_x509_pem;
 use x509_parser::time::ASN1Time;
 
+use crate::access::AccessRules;
 use crate::acme::{AccountWrapper, AcmeClient, CustomDomain};
 use crate::args::ContextArgs;
 use crate::project::{Project, ProjectCreating};
-use crate::task::{self, BoxedTask, TaskBuilder};
+use crate::task::{self, BoxedTask, TaskBuilder, TaskResult};
 use crate::tls::{ChainAndPrivateKey, GatewayCertResolver, RENEWAL_VALIDITY_THRESHOLD_IN_DAYS};
 use crate::worker::TaskRouter;
 use crate::{AccountName, DockerContext, Error, ErrorKind, ProjectDetails, ProjectName};
@@ -44,6 +56,12 @@ pub static MIGRATIONS: Migrator = sqlx::migrate!("./migrations");
 static PROXY_CLIENT: Lazy<ReverseProxy<HttpConnector<GaiResolver>>> =
     Lazy::new(|| ReverseProxy::new(Client::new()));
 
+/// How many ports forwarding raw TCP each project may have
+pub const MAX_TCP_PORTS_PER_PROJECT: usize = 4;
+
+/// The node on the Docker host of the gateway itself
+pub const DEFAULT_NODE: &str = "default";
+
 impl From<SqlxError> for Error {
     fn from(err: SqlxError) -> Self {
         debug!("internal SQLx error: {err}");
@@ -167,11 +185,17 @@ impl ContainerSettings {
 pub struct GatewayContextProvider {
     docker: Docker,
     settings: ContainerSettings,
+    /// The Docker daemons of the nodes other than the default one
+    nodes: RwLock<HashMap<String, Docker>>,
 }
 
 impl GatewayContextProvider {
     pub fn new(docker: Docker, settings: ContainerSettings) -> Self {
-        Self { docker, settings }
+        Self {
+            docker,
+            settings,
+            nodes: Default::default(),
+        }
     }
 
     pub fn context(&self) -> GatewayContext {
@@ -180,6 +204,121 @@ impl GatewayContextProvider {
             settings: self.settings.clone(),
         }
     }
+
+    /// The context of the projects on `node`, if the gateway knows of it
+    pub fn context_on(&self, node: &str) -> Option<GatewayContext> {
+        if node == DEFAULT_NODE {
+            return Some(self.context());
+        }
+
+        self.nodes
+            .read()
+            .unwrap()
+            .get(node)
+            .map(|docker| GatewayContext {
+                docker: docker.clone(),
+                settings: self.settings.clone(),
+            })
+    }
+
+    pub fn add_node(&self, node: String, docker: Docker) {
+        self.nodes.write().unwrap().insert(node, docker);
+    }
+
+    pub fn remove_node(&self, node: &str) {
+        self.nodes.write().unwrap().remove(node);
+    }
+}
+
+/// Connect to the Docker daemon at `docker_host`, which is either the path to its socket or a
+/// `tcp://` address
+fn connect_docker(docker_host: &str) -> Result<Docker, DockerError> {
+    if let Some(address) = docker_host.strip_prefix("tcp://") {
+        Docker::connect_with_http(&format!("http://{address}"), 60, API_DEFAULT_VERSION)
+    } else {
+        Docker::connect_with_unix(docker_host, 60, API_DEFAULT_VERSION)
+    }
+}
+
+/// Copy the volume of `project_name` from the node of `from` to the node of `to`. Docker only
+/// reads and writes the files of volumes through containers, so a container which is never
+/// started is made on each node to reach it.
+async fn copy_volume(
+    from: &GatewayContext,
+    to: &GatewayContext,
+    project_name: &ProjectName,
+) -> Result<(), Error> {
+    let prefix = &from.settings.prefix;
+    let volume = format!("{prefix}{project_name}_vol");
+    let helper = format!("{prefix}{project_name}_migration");
+    let config = Config {
+        image: Some(from.settings.image.clone()),
+        host_config: Some(HostConfig {
+            mounts: Some(vec![Mount {
+                target: Some("/opt/shuttle".to_string()),
+                source: Some(volume.clone()),
+                typ: Some(MountTypeEnum::VOLUME),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let options = CreateContainerOptions {
+        name: helper.as_str(),
+        platform: None,
+    };
+    let docker_error = |error: DockerError| Error::source(ErrorKind::Internal, error);
+
+    from.docker
+        .create_container(Some(options.clone()), config.clone())
+        .await
+        .map_err(docker_error)?;
+    let archive = from
+        .docker
+        .download_from_container(
+            &helper,
+            Some(DownloadFromContainerOptions {
+                path: "/opt/shuttle",
+            }),
+        )
+        .try_fold(Vec::new(), |mut archive, chunk| async move {
+            archive.extend_from_slice(&chunk);
+            Ok(archive)
+        })
+        .await;
+    from.docker
+        .remove_container(&helper, None)
+        .await
+        .map_err(docker_error)?;
+    let archive = archive.map_err(docker_error)?;
+
+    to.docker
+        .create_container(Some(options), config)
+        .await
+        .map_err(docker_error)?;
+    let uploaded = to
+        .docker
+        .upload_to_container(
+            &helper,
+            Some(UploadToContainerOptions {
+                path: "/opt",
+                ..Default::default()
+            }),
+            archive.into(),
+        )
+        .await;
+    to.docker
+        .remove_container(&helper, None)
+        .await
+        .map_err(docker_error)?;
+    uploaded.map_err(docker_error)?;
+
+    // The project is only on the new node from now on
+    from.docker
+        .remove_volume(&volume, None)
+        .await
+        .map_err(docker_error)
 }
 
 pub struct GatewayService {
@@ -187,6 +326,8 @@ pub struct GatewayService {
     db: SqlitePool,
     task_router: TaskRouter<BoxedTask>,
     state_location: PathBuf,
+    /// The limits of the projects which were not given limits of their own
+    default_proxy_limits: ProxyLimits,
 }
 
 impl GatewayService {
@@ -195,12 +336,33 @@ impl GatewayService {
     /// * `args` - The [`Args`] with which the service was
     /// started. Will be passed as [`Context`] to workers and state.
     pub async fn init(args: ContextArgs, db: SqlitePool, state_location: PathBuf) -> Self {
-        let docker = Docker::connect_with_unix(&args.docker_host, 60, API_DEFAULT_VERSION).unwrap();
+        let docker = connect_docker(&args.docker_host).unwrap();
 
         let container_settings = ContainerSettings::builder().from_args(&args).await;
 
         let provider = GatewayContextProvider::new(docker, container_settings);
 
+        query("INSERT INTO nodes (node_name, docker_host) VALUES (?1, ?2) ON CONFLICT (node_name) DO UPDATE SET docker_host = excluded.docker_host")
+            .bind(DEFAULT_NODE)
+            .bind(&args.docker_host)
+            .execute(&db)
+            .await
+            .expect("to register the default node");
+
+        let nodes = query("SELECT node_name, docker_host FROM nodes WHERE node_name != ?1")
+            .bind(DEFAULT_NODE)
+            .fetch_all(&db)
+            .await
+            .expect("to list the nodes");
+        for row in nodes {
+            let node: String = row.get("node_name");
+            let docker_host: String = row.get("docker_host");
+            match connect_docker(&docker_host) {
+                Ok(docker) => provider.add_node(node, docker),
+                Err(error) => error!(%node, docker_host, %error, "failed to connect to a node"),
+            }
+        }
+
         let task_router = TaskRouter::new();
 
         Self {
@@ -208,6 +370,7 @@ impl GatewayService {
             db,
             task_router,
             state_location,
+            default_proxy_limits: (&args.proxy_limits).into(),
         }
     }
 
@@ -453,12 +616,14 @@ impl GatewayService {
         let project = SqlxJson(Project::Creating(
             ProjectCreating::new_with_random_initial_key(project_name.clone(), idle_minutes),
         ));
+        let node = self.place_project().await?;
 
-        query("INSERT INTO projects (project_name, account_name, initial_key, project_state, created_at) VALUES (?1, ?2, ?3, ?4, CURRENT_TIMESTAMP)")
+        query("INSERT INTO projects (project_name, account_name, initial_key, project_state, node_name, created_at) VALUES (?1, ?2, ?3, ?4, ?5, CURRENT_TIMESTAMP)")
             .bind(&project_name)
             .bind(&account_name)
             .bind(project.initial_key().unwrap())
             .bind(&project)
+            .bind(&node)
             .execute(&self.db)
             .await
             .map_err(|err| {
@@ -551,6 +716,374 @@ impl GatewayService {
         Ok(custom_domain)
     }
 
+    /// The limits of the requests to the project, which are those of the gateway unless the
+    /// project was given its own
+    pub async fn proxy_limits(&self, project_name: &ProjectName) -> Result<ProxyLimits, Error> {
+        let limits = query("SELECT max_request_body_bytes, response_timeout_secs, idle_timeout_secs, requests_per_second, burst FROM proxy_limits WHERE project_name = ?1")
+            .bind(project_name)
+            .fetch_optional(&self.db)
+            .await?
+            .map(|row| ProxyLimits {
+                max_request_body_bytes: row.get::<i64, _>("max_request_body_bytes") as u64,
+                response_timeout_secs: row.get::<i64, _>("response_timeout_secs") as u64,
+                idle_timeout_secs: row.get::<i64, _>("idle_timeout_secs") as u64,
+                // Projects which were given limits before there were rate limits get the rate of
+                // the gateway
+                requests_per_second: row
+                    .get::<Option<i64>, _>("requests_per_second")
+                    .map_or(self.default_proxy_limits.requests_per_second, |rate| {
+                        rate as u32
+                    }),
+                burst: row
+                    .get::<Option<i64>, _>("burst")
+                    .map_or(self.default_proxy_limits.burst, |burst| burst as u32),
+            })
+            .unwrap_or(self.default_proxy_limits);
+
+        Ok(limits)
+    }
+
+    pub async fn set_proxy_limits(
+        &self,
+        project_name: &ProjectName,
+        limits: &ProxyLimits,
+    ) -> Result<(), Error> {
+        query("INSERT OR REPLACE INTO proxy_limits (project_name, max_request_body_bytes, response_timeout_secs, idle_timeout_secs, requests_per_second, burst) VALUES (?1, ?2, ?3, ?4, ?5, ?6)")
+            .bind(project_name)
+            .bind(limits.max_request_body_bytes as i64)
+            .bind(limits.response_timeout_secs as i64)
+            .bind(limits.idle_timeout_secs as i64)
+            .bind(limits.requests_per_second as i64)
+            .bind(limits.burst as i64)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Go back to the limits of the gateway for the project
+    pub async fn reset_proxy_limits(&self, project_name: &ProjectName) -> Result<(), Error> {
+        query("DELETE FROM proxy_limits WHERE project_name = ?1")
+            .bind(project_name)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// What is served instead of the project, if it is under maintenance
+    pub async fn maintenance(
+        &self,
+        project_name: &ProjectName,
+    ) -> Result<Option<Maintenance>, Error> {
+        let maintenance = query("SELECT status, page FROM maintenance WHERE project_name = ?1")
+            .bind(project_name)
+            .fetch_optional(&self.db)
+            .await?
+            .map(|row| Maintenance {
+                status: row.get::<i64, _>("status") as u16,
+                page: row.get("page"),
+            });
+
+        Ok(maintenance)
+    }
+
+    pub async fn set_maintenance(
+        &self,
+        project_name: &ProjectName,
+        maintenance: &Maintenance,
+    ) -> Result<(), Error> {
+        query(
+            "INSERT OR REPLACE INTO maintenance (project_name, status, page) VALUES (?1, ?2, ?3)",
+        )
+        .bind(project_name)
+        .bind(maintenance.status as i64)
+        .bind(&maintenance.page)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Go back to serving the project
+    pub async fn end_maintenance(&self, project_name: &ProjectName) -> Result<(), Error> {
+        query("DELETE FROM maintenance WHERE project_name = ?1")
+            .bind(project_name)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// The rules for the clients of the service of the project, if it has any
+    pub async fn access_rules(
+        &self,
+        project_name: &ProjectName,
+        service_name: &str,
+    ) -> Result<Option<AccessRules>, Error> {
+        let rules =
+            query("SELECT rules FROM access_rules WHERE project_name = ?1 AND service_name = ?2")
+                .bind(project_name)
+                .bind(service_name)
+                .fetch_optional(&self.db)
+                .await?
+                .map(|row| row.get::<SqlxJson<AccessRules>, _>("rules").0);
+
+        Ok(rules)
+    }
+
+    pub async fn set_access_rules(
+        &self,
+        project_name: &ProjectName,
+        service_name: &str,
+        rules: &AccessRules,
+    ) -> Result<(), Error> {
+        query("INSERT OR REPLACE INTO access_rules (project_name, service_name, rules) VALUES (?1, ?2, ?3)")
+            .bind(project_name)
+            .bind(service_name)
+            .bind(SqlxJson(rules))
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Let every client through to the service of the project again
+    pub async fn delete_access_rules(
+        &self,
+        project_name: &ProjectName,
+        service_name: &str,
+    ) -> Result<(), Error> {
+        query("DELETE FROM access_rules WHERE project_name = ?1 AND service_name = ?2")
+            .bind(project_name)
+            .bind(service_name)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// The headers to add to the responses of the service of the project, if it has any
+    pub async fn http_headers(
+        &self,
+        project_name: &ProjectName,
+        service_name: &str,
+    ) -> Result<Option<HttpConfig>, Error> {
+        let http =
+            query("SELECT http FROM http_headers WHERE project_name = ?1 AND service_name = ?2")
+                .bind(project_name)
+                .bind(service_name)
+                .fetch_optional(&self.db)
+                .await?
+                .map(|row| row.get::<SqlxJson<HttpConfig>, _>("http").0);
+
+        Ok(http)
+    }
+
+    /// Add the headers of `http` to the responses of the service of the project from now on, or
+    /// stop adding any when it is empty
+    pub async fn set_http_headers(
+        &self,
+        project_name: &ProjectName,
+        service_name: &str,
+        http: &HttpConfig,
+    ) -> Result<(), Error> {
+        if *http == HttpConfig::default() {
+            query("DELETE FROM http_headers WHERE project_name = ?1 AND service_name = ?2")
+                .bind(project_name)
+                .bind(service_name)
+                .execute(&self.db)
+                .await?;
+        } else {
+            query("INSERT OR REPLACE INTO http_headers (project_name, service_name, http) VALUES (?1, ?2, ?3)")
+                .bind(project_name)
+                .bind(service_name)
+                .bind(SqlxJson(http))
+                .execute(&self.db)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// The ports of the gateway which forward raw TCP to the project
+    pub async fn tcp_ports(&self, project_name: &ProjectName) -> Result<Vec<TcpEndpoint>, Error> {
+        let endpoints =
+            query("SELECT port, target_port FROM tcp_ports WHERE project_name = ?1 ORDER BY port")
+                .bind(project_name)
+                .fetch_all(&self.db)
+                .await?
+                .into_iter()
+                .map(|row| self.tcp_endpoint(project_name, row.get("port"), row.get("target_port")))
+                .collect();
+
+        Ok(endpoints)
+    }
+
+    /// Every port which forwards raw TCP, with the project and the port of the project it
+    /// forwards to
+    pub async fn iter_tcp_ports(&self) -> Result<Vec<(u16, ProjectName, u16)>, Error> {
+        let ports = query("SELECT port, project_name, target_port FROM tcp_ports")
+            .fetch_all(&self.db)
+            .await?
+            .into_iter()
+            .map(|row| {
+                (
+                    row.get("port"),
+                    row.get("project_name"),
+                    row.get("target_port"),
+                )
+            })
+            .collect();
+
+        Ok(ports)
+    }
+
+    /// Give the project the first free port of `ports` to forward raw TCP to its `target_port`,
+    /// or the port it was given for it already
+    pub async fn allocate_tcp_port(
+        &self,
+        project_name: &ProjectName,
+        target_port: u16,
+        ports: &RangeInclusive<u16>,
+    ) -> Result<TcpEndpoint, Error> {
+        let mut transaction = self.db.begin().await?;
+
+        let taken: Vec<(u16, String, u16)> =
+            query("SELECT port, project_name, target_port FROM tcp_ports")
+                .fetch_all(&mut transaction)
+                .await?
+                .into_iter()
+                .map(|row| {
+                    (
+                        row.get("port"),
+                        row.get("project_name"),
+                        row.get("target_port"),
+                    )
+                })
+                .collect();
+
+        let project_ports: Vec<_> = taken
+            .iter()
+            .filter(|(_, name, _)| name == project_name.as_str())
+            .collect();
+
+        if let Some((port, _, _)) = project_ports
+            .iter()
+            .find(|(_, _, target)| *target == target_port)
+        {
+            return Ok(self.tcp_endpoint(project_name, *port, target_port));
+        }
+
+        if project_ports.len() >= MAX_TCP_PORTS_PER_PROJECT {
+            return Err(Error::custom(
+                ErrorKind::InvalidOperation,
+                format!("projects may forward raw TCP on {MAX_TCP_PORTS_PER_PROJECT} ports"),
+            ));
+        }
+
+        let port = ports
+            .clone()
+            .find(|port| !taken.iter().any(|(taken, _, _)| taken == port))
+            .ok_or_else(|| {
+                Error::custom(
+                    ErrorKind::ServiceUnavailable,
+                    "every port for raw TCP is taken",
+                )
+            })?;
+
+        query("INSERT INTO tcp_ports (port, project_name, target_port) VALUES (?1, ?2, ?3)")
+            .bind(port)
+            .bind(project_name)
+            .bind(target_port)
+            .execute(&mut transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(self.tcp_endpoint(project_name, port, target_port))
+    }
+
+    /// Stop forwarding the port to the project, returning what it forwarded to when it did
+    pub async fn release_tcp_port(
+        &self,
+        project_name: &ProjectName,
+        port: u16,
+    ) -> Result<Option<TcpEndpoint>, Error> {
+        let target_port = query(
+            "DELETE FROM tcp_ports WHERE project_name = ?1 AND port = ?2 RETURNING target_port",
+        )
+        .bind(project_name)
+        .bind(port)
+        .fetch_optional(&self.db)
+        .await?
+        .map(|row| row.get("target_port"));
+
+        Ok(target_port.map(|target_port| self.tcp_endpoint(project_name, port, target_port)))
+    }
+
+    fn tcp_endpoint(&self, project_name: &ProjectName, port: u16, target_port: u16) -> TcpEndpoint {
+        TcpEndpoint {
+            address: format!("{project_name}.{}:{port}", self.context().settings.fqdn),
+            port,
+            target_port,
+        }
+    }
+
+    /// Route the host of the service of an environment of the project to its running deployment,
+    /// returning the host
+    pub async fn set_route(
+        &self,
+        project_name: &ProjectName,
+        service_name: &str,
+        deployment_id: &Uuid,
+    ) -> Result<FQDN, Error> {
+        let hostname: FQDN = format!(
+            "{}.{}",
+            shuttle_common::project::ProjectName::service_host_label(service_name),
+            self.context().settings.fqdn
+        )
+        .parse()
+        .map_err(|_| Error::from_kind(ErrorKind::InvalidOperation))?;
+
+        query("INSERT OR REPLACE INTO routes (hostname, project_name, service_name, deployment_id) VALUES (?1, ?2, ?3, ?4)")
+            .bind(hostname.to_string())
+            .bind(project_name)
+            .bind(service_name)
+            .bind(deployment_id.to_string())
+            .execute(&self.db)
+            .await?;
+
+        Ok(hostname)
+    }
+
+    /// Remove the route of a service, unless it was taken over by another deployment since
+    pub async fn delete_route(
+        &self,
+        project_name: &ProjectName,
+        service_name: &str,
+        deployment_id: &Uuid,
+    ) -> Result<(), Error> {
+        query("DELETE FROM routes WHERE project_name = ?1 AND service_name = ?2 AND deployment_id = ?3")
+            .bind(project_name)
+            .bind(service_name)
+            .bind(deployment_id.to_string())
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Find the project and service `hostname` is routed to
+    pub async fn find_route(&self, hostname: &Fqdn) -> Result<(ProjectName, String), Error> {
+        query("SELECT project_name, service_name FROM routes WHERE hostname = ?1")
+            .bind(hostname.to_string())
+            .fetch_optional(&self.db)
+            .await?
+            .map(|row| (row.get("project_name"), row.get("service_name")))
+            .ok_or_else(|| Error::from_kind(ErrorKind::ProjectNotFound))
+    }
+
     pub async fn iter_projects_detailed(
         &self,
     ) -> Result<impl Iterator<Item = ProjectDetails>, Error> {
@@ -593,6 +1126,76 @@ impl GatewayService {
         }
     }
 
+    /// Check that the custom domain resolves to the same addresses as the domain the project is
+    /// served on, so that the ACME challenge for it, and its requests afterwards, come to us
+    pub async fn verify_custom_domain(
+        &self,
+        project_name: &ProjectName,
+        fqdn: &Fqdn,
+    ) -> Result<(), Error> {
+        let public = format!("{project_name}.{}", self.context().settings.fqdn);
+
+        if resolves_to(&fqdn.to_string(), &public).await {
+            Ok(())
+        } else {
+            Err(Error::from_kind(ErrorKind::CustomDomainMisconfigured))
+        }
+    }
+
+    /// Renew the certificates of the custom domains which expire in the next 30 days, and start
+    /// serving the new ones
+    pub async fn renew_custom_domain_certificates(
+        &self,
+        acme: &AcmeClient,
+        resolver: &GatewayCertResolver,
+    ) -> Result<(), Error> {
+        for custom_domain in self.iter_custom_domains().await? {
+            let fqdn = custom_domain.fqdn.to_string();
+
+            // Certificates which can't be parsed are replaced as well
+            let due = custom_domain.not_after().map_or(true, |not_after| {
+                not_after.sub(ASN1Time::now()).map_or(true, |left| {
+                    left.whole_days() <= RENEWAL_VALIDITY_THRESHOLD_IN_DAYS
+                })
+            });
+            if !due {
+                continue;
+            }
+
+            let (certs, private_key) = match acme
+                .create_certificate(&fqdn, ChallengeType::Http01, self.credentials())
+                .await
+            {
+                Ok(pair) => pair,
+                Err(error) => {
+                    error!(
+                        error = &error as &dyn std::error::Error,
+                        %fqdn,
+                        "failed to renew the certificate of a custom domain"
+                    );
+                    continue;
+                }
+            };
+
+            self.create_custom_domain(
+                &custom_domain.project_name,
+                &custom_domain.fqdn,
+                &certs,
+                &private_key,
+            )
+            .await?;
+
+            let mut buf = Vec::new();
+            buf.extend(certs.as_bytes());
+            buf.extend(private_key.as_bytes());
+            resolver.serve_pem(&fqdn, Cursor::new(buf)).await?;
+
+            info!(%fqdn, "renewed the certificate of a custom domain");
+        }
+
+        Ok(())
+    }
+
     async fn create_certificate<'a>(
         &self,
         acme: &AcmeClient,
@@ -684,30 +1287,326 @@ impl GatewayService {
         self.provider.context()
     }
 
-    /// Create a builder for a new [ProjectTask]
-    pub fn new_task(self: &Arc<Self>) -> TaskBuilder {
-        TaskBuilder::new(self.clone())
+    /// The context of the node `project_name` is on
+    pub async fn context_of(&self, project_name: &ProjectName) -> Result<GatewayContext, Error> {
+        let node = self.node_of(project_name).await?;
+
+        self.provider.context_on(&node).ok_or_else(|| {
+            Error::custom(
+                ErrorKind::NodeNotFound,
+                format!("the gateway is not connected to node {node}"),
+            )
+        })
     }
 
-    /// Find a project by name. And start the project if it is idle, waiting for it to start up
-    pub async fn find_or_start_project(
-        self: &Arc<Self>,
-        project_name: &ProjectName,
-        task_sender: Sender<BoxedTask>,
-    ) -> Result<Project, Error> {
-        let mut project = self.find_project(project_name).await?;
+    /// The nodes projects can be placed on, with how many projects are on each
+    pub async fn nodes(&self) -> Result<Vec<node::Response>, Error> {
+        let nodes = query(
+            r#"
+        SELECT nodes.node_name, docker_host, draining, COUNT(project_name) AS projects
+        FROM nodes
+        LEFT JOIN projects ON projects.node_name = nodes.node_name
+        GROUP BY nodes.node_name
+        ORDER BY nodes.node_name
+        "#,
+        )
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(|row| node::Response {
+            name: row.get("node_name"),
+            docker_host: row.get("docker_host"),
+            draining: row.get("draining"),
+            projects: row.get("projects"),
+        })
+        .collect();
 
-        // Start the project if it is idle
-        if project.is_stopped() {
-            trace!(%project_name, "starting up idle project");
+        Ok(nodes)
+    }
 
-            let handle = self
-                .new_task()
-                .project(project_name.clone())
-                .and_then(task::start())
-                .and_then(task::run_until_done())
-                .and_then(task::check_health())
-                .send(&task_sender)
+    pub async fn node(&self, node: &str) -> Result<node::Response, Error> {
+        self.nodes()
+            .await?
+            .into_iter()
+            .find(|response| response.name == node)
+            .ok_or_else(|| Error::from_kind(ErrorKind::NodeNotFound))
+    }
+
+    /// Let projects be placed on the Docker daemon at `docker_host`
+    pub async fn add_node(&self, node: &str, docker_host: &str) -> Result<node::Response, Error> {
+        if node.is_empty() || !node.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(Error::custom(
+                ErrorKind::InvalidOperation,
+                "the name of a node may only have letters, digits and dashes",
+            ));
+        }
+
+        let docker = connect_docker(docker_host)
+            .map_err(|error| Error::source(ErrorKind::InvalidOperation, error))?;
+
+        query("INSERT INTO nodes (node_name, docker_host) VALUES (?1, ?2)")
+            .bind(node)
+            .bind(docker_host)
+            .execute(&self.db)
+            .await
+            .map_err(|err| {
+                if let Some(db_err_code) = err.as_database_error().and_then(DatabaseError::code) {
+                    if db_err_code == "1555" {
+                        // SQLITE_CONSTRAINT_PRIMARYKEY
+                        return Error::custom(
+                            ErrorKind::InvalidOperation,
+                            "a node with this name already exists",
+                        );
+                    }
+                }
+                err.into()
+            })?;
+
+        self.provider.add_node(node.to_string(), docker);
+
+        self.node(node).await
+    }
+
+    /// Forget about a node which has no projects left on it
+    pub async fn remove_node(&self, node: &str) -> Result<node::Response, Error> {
+        if node == DEFAULT_NODE {
+            return Err(Error::custom(
+                ErrorKind::InvalidOperation,
+                "the default node can not be removed",
+            ));
+        }
+
+        let removed = self.node(node).await?;
+        if removed.projects > 0 {
+            return Err(Error::custom(
+                ErrorKind::InvalidOperation,
+                "the node still has projects, drain it first",
+            ));
+        }
+
+        // Projects placed on the node in the meantime keep it around
+        let deleted = query(
+            "DELETE FROM nodes WHERE node_name = ?1 AND NOT EXISTS (SELECT 1 FROM projects WHERE node_name = ?1)",
+        )
+        .bind(node)
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+
+        if deleted == 0 {
+            return Err(Error::custom(
+                ErrorKind::InvalidOperation,
+                "the node still has projects, drain it first",
+            ));
+        }
+
+        self.provider.remove_node(node);
+
+        Ok(removed)
+    }
+
+    /// Keep new projects off `node`, or let them on it again
+    pub async fn set_node_draining(
+        &self,
+        node: &str,
+        draining: bool,
+    ) -> Result<node::Response, Error> {
+        let updated = query("UPDATE nodes SET draining = ?1 WHERE node_name = ?2")
+            .bind(draining)
+            .bind(node)
+            .execute(&self.db)
+            .await?
+            .rows_affected();
+
+        if updated == 0 {
+            return Err(Error::from_kind(ErrorKind::NodeNotFound));
+        }
+
+        self.node(node).await
+    }
+
+    /// Keep new projects off `node`, and move the projects on it to the other nodes
+    pub async fn drain_node(
+        self: &Arc<Self>,
+        node: &str,
+        sender: &Sender<BoxedTask>,
+    ) -> Result<Vec<node::Migration>, Error> {
+        // Fail before draining the node when its projects have nowhere to go
+        self.place_project_except(Some(node)).await?;
+        self.set_node_draining(node, true).await?;
+
+        let projects: Vec<ProjectName> =
+            query("SELECT project_name FROM projects WHERE node_name = ?1")
+                .bind(node)
+                .fetch_all(&self.db)
+                .await?
+                .into_iter()
+                .map(|row| row.get("project_name"))
+                .collect();
+
+        let mut migrations = Vec::with_capacity(projects.len());
+        for project_name in projects {
+            migrations.push(self.migrate_project(&project_name, None, sender).await?);
+        }
+
+        Ok(migrations)
+    }
+
+    /// Move `project_name` to the node `to`, or to the one with the fewest projects. The project
+    /// is destroyed on its node, its volume is copied over, and it is created again on the new
+    /// node if it had a container. Its routes follow it as soon as it is ready again.
+    pub async fn migrate_project(
+        self: &Arc<Self>,
+        project_name: &ProjectName,
+        to: Option<String>,
+        sender: &Sender<BoxedTask>,
+    ) -> Result<node::Migration, Error> {
+        let from = self.node_of(project_name).await?;
+        let to = match to {
+            Some(to) => {
+                if self.node(&to).await?.draining {
+                    return Err(Error::custom(
+                        ErrorKind::InvalidOperation,
+                        "the node is being drained",
+                    ));
+                }
+                to
+            }
+            None => self.place_project_except(Some(&from)).await?,
+        };
+
+        if to == from {
+            return Err(Error::custom(
+                ErrorKind::InvalidOperation,
+                "the project is already on this node",
+            ));
+        }
+
+        if self.provider.context_on(&to).is_none() {
+            return Err(Error::custom(
+                ErrorKind::NodeNotFound,
+                format!("the gateway is not connected to node {to}"),
+            ));
+        }
+
+        let container = self.find_project(project_name).await?.container();
+        let service = self.clone();
+        let node = to.clone();
+
+        self.new_task()
+            .project(project_name.clone())
+            .and_then(task::destroy())
+            .and_then(task::run_until_done())
+            .and_then(task::run(move |ctx| {
+                let service = service.clone();
+                let node = node.clone();
+                let container = container.clone();
+                async move {
+                    let Some(target) = service.provider.context_on(&node) else {
+                        return TaskResult::Err(Error::from_kind(ErrorKind::NodeNotFound));
+                    };
+
+                    if let Err(err) = copy_volume(&ctx.gateway, &target, &ctx.project_name).await {
+                        return TaskResult::Err(err);
+                    }
+
+                    if let Err(err) = service.set_node_of(&ctx.project_name, &node).await {
+                        return TaskResult::Err(err);
+                    }
+
+                    match container {
+                        Some(container) => match ProjectCreating::from_container(container, 0) {
+                            Ok(creating) => TaskResult::Done(Project::Creating(creating)),
+                            Err(err) => TaskResult::Done(Project::Errored(err)),
+                        },
+                        None => TaskResult::Done(ctx.state),
+                    }
+                }
+            }))
+            .and_then(task::run_until_done())
+            .send(sender)
+            .await?;
+
+        Ok(node::Migration {
+            project: project_name.to_string(),
+            from,
+            to,
+        })
+    }
+
+    pub async fn node_of(&self, project_name: &ProjectName) -> Result<String, Error> {
+        query("SELECT node_name FROM projects WHERE project_name = ?1")
+            .bind(project_name)
+            .fetch_optional(&self.db)
+            .await?
+            .map(|row| row.get("node_name"))
+            .ok_or_else(|| Error::from_kind(ErrorKind::ProjectNotFound))
+    }
+
+    async fn set_node_of(&self, project_name: &ProjectName, node: &str) -> Result<(), Error> {
+        query("UPDATE projects SET node_name = ?1 WHERE project_name = ?2")
+            .bind(node)
+            .bind(project_name)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// The node a new project goes on
+    async fn place_project(&self) -> Result<String, Error> {
+        self.place_project_except(None).await
+    }
+
+    /// The node with the fewest projects of those which are not being drained, other than `except`
+    async fn place_project_except(&self, except: Option<&str>) -> Result<String, Error> {
+        query(
+            r#"
+        SELECT nodes.node_name
+        FROM nodes
+        LEFT JOIN projects ON projects.node_name = nodes.node_name
+        WHERE NOT draining AND nodes.node_name IS NOT ?1
+        GROUP BY nodes.node_name
+        ORDER BY COUNT(project_name), nodes.node_name
+        LIMIT 1
+        "#,
+        )
+        .bind(except)
+        .fetch_optional(&self.db)
+        .await?
+        .map(|row| row.get("node_name"))
+        .ok_or_else(|| {
+            Error::custom(
+                ErrorKind::ServiceUnavailable,
+                "there is no node to place the project on which is not being drained",
+            )
+        })
+    }
+
+    /// Create a builder for a new [ProjectTask]
+    pub fn new_task(self: &Arc<Self>) -> TaskBuilder {
+        TaskBuilder::new(self.clone())
+    }
+
+    /// Find a project by name. And start the project if it is idle, waiting for it to start up
+    pub async fn find_or_start_project(
+        self: &Arc<Self>,
+        project_name: &ProjectName,
+        task_sender: Sender<BoxedTask>,
+    ) -> Result<Project, Error> {
+        let mut project = self.find_project(project_name).await?;
+
+        // Start the project if it is idle
+        if project.is_stopped() {
+            trace!(%project_name, "starting up idle project");
+
+            let handle = self
+                .new_task()
+                .project(project_name.clone())
+                .and_then(task::start())
+                .and_then(task::run_until_done())
+                .and_then(task::check_health())
+                .send(&task_sender)
                 .await?;
 
             // Wait for project to come up and set new state
@@ -736,6 +1635,19 @@ impl GatewayService {
     }
 }
 
+/// Whether `domain` resolves to any of the addresses `target` resolves to
+async fn resolves_to(domain: &str, target: &str) -> bool {
+    let (Ok(domain), Ok(target)) = tokio::join!(
+        tokio::net::lookup_host(format!("{domain}:443")),
+        tokio::net::lookup_host(format!("{target}:443"))
+    ) else {
+        return false;
+    };
+
+    let target: HashSet<IpAddr> = target.map(|addr| addr.ip()).collect();
+    domain.map(|addr| addr.ip()).any(|ip| target.contains(&ip))
+}
+
 #[derive(Clone)]
 pub struct GatewayContext {
     docker: Docker,
@@ -1077,4 +1989,333 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn service_set_find_delete_route() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let svc = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let account: AccountName = "neo".parse().unwrap();
+        let project_name: ProjectName = "matrix".parse().unwrap();
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+
+        svc.create_project(project_name.clone(), account, false, 0)
+            .await
+            .unwrap();
+
+        let hostname = svc
+            .set_route(&project_name, "matrix--staging", &first)
+            .await
+            .unwrap();
+        assert_eq!(
+            hostname,
+            format!("staging--matrix.{}", world.args().proxy_fqdn)
+                .parse::<FQDN>()
+                .unwrap()
+        );
+        assert_eq!(
+            svc.find_route(&hostname).await.unwrap(),
+            (project_name.clone(), "matrix--staging".to_string())
+        );
+
+        // The next deployment takes the route over before the first one stops
+        svc.set_route(&project_name, "matrix--staging", &second)
+            .await
+            .unwrap();
+        svc.delete_route(&project_name, "matrix--staging", &first)
+            .await
+            .unwrap();
+        assert!(svc.find_route(&hostname).await.is_ok());
+
+        svc.delete_route(&project_name, "matrix--staging", &second)
+            .await
+            .unwrap();
+        assert_err_kind!(svc.find_route(&hostname).await, ErrorKind::ProjectNotFound);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn service_set_delete_access_rules() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let svc = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let project_name: ProjectName = "matrix".parse().unwrap();
+        svc.create_project(project_name.clone(), "neo".parse().unwrap(), false, 0)
+            .await
+            .unwrap();
+
+        assert!(svc
+            .access_rules(&project_name, "matrix--staging")
+            .await
+            .unwrap()
+            .is_none());
+
+        let rules = AccessRules::new(shuttle_common::models::access::Request {
+            allow: vec!["10.0.0.0/8".parse().unwrap()],
+            ..Default::default()
+        });
+        svc.set_access_rules(&project_name, "matrix--staging", &rules)
+            .await
+            .unwrap();
+
+        let stored = svc
+            .access_rules(&project_name, "matrix--staging")
+            .await
+            .unwrap()
+            .unwrap();
+        let headers = http::HeaderMap::new();
+        assert!(stored.check("10.0.0.1".parse().unwrap(), &headers).is_ok());
+        assert!(stored.check("8.8.8.8".parse().unwrap(), &headers).is_err());
+
+        // The rules of an environment do not apply to the project
+        assert!(svc
+            .access_rules(&project_name, "matrix")
+            .await
+            .unwrap()
+            .is_none());
+
+        svc.delete_access_rules(&project_name, "matrix--staging")
+            .await
+            .unwrap();
+        assert!(svc
+            .access_rules(&project_name, "matrix--staging")
+            .await
+            .unwrap()
+            .is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn service_place_drain_nodes() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let svc = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let neo: AccountName = "neo".parse().unwrap();
+        let matrix: ProjectName = "matrix".parse().unwrap();
+        let reloaded: ProjectName = "reloaded".parse().unwrap();
+        let revolutions: ProjectName = "revolutions".parse().unwrap();
+
+        svc.create_project(matrix.clone(), neo.clone(), false, 0)
+            .await?;
+        svc.add_node("second", "/var/run/second.sock").await?;
+        assert_err_kind!(
+            svc.add_node("second", "/var/run/second.sock").await,
+            ErrorKind::InvalidOperation
+        );
+
+        // New projects go on the node with the fewest projects
+        svc.create_project(reloaded.clone(), neo.clone(), false, 0)
+            .await?;
+        assert_eq!(svc.node_of(&matrix).await?, DEFAULT_NODE);
+        assert_eq!(svc.node_of(&reloaded).await?, "second");
+
+        // But not on the nodes being drained
+        svc.set_node_draining("second", true).await?;
+        svc.create_project(revolutions.clone(), neo.clone(), false, 0)
+            .await?;
+        assert_eq!(svc.node_of(&revolutions).await?, DEFAULT_NODE);
+
+        let nodes: Vec<_> = svc
+            .nodes()
+            .await?
+            .into_iter()
+            .map(|node| (node.name, node.draining, node.projects))
+            .collect();
+        assert_eq!(
+            nodes,
+            vec![
+                (DEFAULT_NODE.to_string(), false, 2),
+                ("second".to_string(), true, 1)
+            ]
+        );
+
+        // Nodes are only removed once their projects are gone
+        assert_err_kind!(svc.remove_node("second").await, ErrorKind::InvalidOperation);
+        assert_err_kind!(
+            svc.remove_node(DEFAULT_NODE).await,
+            ErrorKind::InvalidOperation
+        );
+        assert_err_kind!(
+            svc.set_node_draining("third", true).await,
+            ErrorKind::NodeNotFound
+        );
+
+        // The projects of the last node which is not drained have nowhere to go
+        let (sender, _receiver) = tokio::sync::mpsc::channel(256);
+        assert_err_kind!(
+            svc.drain_node(DEFAULT_NODE, &sender).await,
+            ErrorKind::ServiceUnavailable
+        );
+        assert!(!svc.node(DEFAULT_NODE).await?.draining);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn service_set_clear_http_headers() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let svc = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let project_name: ProjectName = "matrix".parse().unwrap();
+        svc.create_project(project_name.clone(), "neo".parse().unwrap(), false, 0)
+            .await
+            .unwrap();
+
+        assert!(svc
+            .http_headers(&project_name, "matrix")
+            .await
+            .unwrap()
+            .is_none());
+
+        let http = HttpConfig {
+            hsts: Some(3600),
+            nosniff: true,
+            ..Default::default()
+        };
+        svc.set_http_headers(&project_name, "matrix", &http)
+            .await
+            .unwrap();
+        assert_eq!(
+            svc.http_headers(&project_name, "matrix").await.unwrap(),
+            Some(http)
+        );
+
+        // The headers of the project do not apply to its environments
+        assert!(svc
+            .http_headers(&project_name, "matrix--staging")
+            .await
+            .unwrap()
+            .is_none());
+
+        // A deployment without any headers clears the ones of the deployment before
+        svc.set_http_headers(&project_name, "matrix", &HttpConfig::default())
+            .await
+            .unwrap();
+        assert!(svc
+            .http_headers(&project_name, "matrix")
+            .await
+            .unwrap()
+            .is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn service_allocate_release_tcp_ports() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let svc = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let matrix: ProjectName = "matrix".parse().unwrap();
+        let zion: ProjectName = "zion".parse().unwrap();
+        for project_name in [&matrix, &zion] {
+            svc.create_project(project_name.clone(), "neo".parse().unwrap(), false, 0)
+                .await
+                .unwrap();
+        }
+
+        let ports = 30000..=30001;
+
+        let mqtt = svc.allocate_tcp_port(&matrix, 1883, &ports).await.unwrap();
+        assert_eq!(mqtt.port, 30000);
+        assert_eq!(mqtt.target_port, 1883);
+        assert_eq!(mqtt.address, "matrix.test.shuttleapp.rs:30000");
+
+        // Asking for the same port of the project again gives the same port of the gateway
+        assert_eq!(
+            svc.allocate_tcp_port(&matrix, 1883, &ports).await.unwrap(),
+            mqtt
+        );
+
+        let game = svc.allocate_tcp_port(&zion, 7777, &ports).await.unwrap();
+        assert_eq!(game.port, 30001);
+
+        assert_err_kind!(
+            svc.allocate_tcp_port(&matrix, 8883, &ports).await,
+            ErrorKind::ServiceUnavailable
+        );
+
+        assert_eq!(svc.tcp_ports(&matrix).await.unwrap(), vec![mqtt.clone()]);
+        assert_eq!(svc.iter_tcp_ports().await.unwrap().len(), 2);
+
+        // Projects only release their own ports
+        assert!(svc.release_tcp_port(&zion, 30000).await.unwrap().is_none());
+        assert_eq!(
+            svc.release_tcp_port(&matrix, 30000).await.unwrap(),
+            Some(mqtt)
+        );
+
+        let mqtts = svc.allocate_tcp_port(&matrix, 8883, &ports).await.unwrap();
+        assert_eq!(mqtts.port, 30000);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn service_set_end_maintenance() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let svc = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let project_name: ProjectName = "matrix".parse().unwrap();
+        svc.create_project(project_name.clone(), "neo".parse().unwrap(), false, 0)
+            .await
+            .unwrap();
+
+        assert_eq!(svc.maintenance(&project_name).await.unwrap(), None);
+
+        let maintenance = Maintenance {
+            status: 503,
+            page: Some("<h1>Back soon</h1>".to_string()),
+        };
+        svc.set_maintenance(&project_name, &maintenance)
+            .await
+            .unwrap();
+        assert_eq!(
+            svc.maintenance(&project_name).await.unwrap(),
+            Some(maintenance)
+        );
+
+        svc.end_maintenance(&project_name).await.unwrap();
+        assert_eq!(svc.maintenance(&project_name).await.unwrap(), None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn service_set_reset_proxy_limits() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let svc = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let project_name: ProjectName = "matrix".parse().unwrap();
+        svc.create_project(project_name.clone(), "neo".parse().unwrap(), false, 0)
+            .await
+            .unwrap();
+
+        let defaults = ProxyLimits::from(&world.args().proxy_limits);
+        assert_eq!(svc.proxy_limits(&project_name).await.unwrap(), defaults);
+
+        let limits = ProxyLimits {
+            max_request_body_bytes: 1024,
+            response_timeout_secs: 5,
+            idle_timeout_secs: 1,
+            requests_per_second: 10,
+            burst: 20,
+        };
+        svc.set_proxy_limits(&project_name, &limits).await.unwrap();
+        assert_eq!(svc.proxy_limits(&project_name).await.unwrap(), limits);
+
+        svc.reset_proxy_limits(&project_name).await.unwrap();
+        assert_eq!(svc.proxy_limits(&project_name).await.unwrap(), defaults);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn custom_domain_resolves_to_project() {
+        assert!(resolves_to("localhost", "127.0.0.1").await);
+        assert!(!resolves_to("127.0.0.2", "127.0.0.1").await);
+        assert!(!resolves_to("does-not-exist.invalid", "127.0.0.1").await);
+    }
 }
@@ -135,6 +135,15 @@ pub fn start() -> impl Task<ProjectContext, Output = Project, Error = Error> {
     })
 }
 
+pub fn reboot() -> impl Task<ProjectContext, Output = Project, Error = Error> {
+    run(|ctx| async move {
+        match ctx.state.reboot() {
+            Ok(state) => TaskResult::Done(state),
+            Err(err) => TaskResult::Err(err),
+        }
+    })
+}
+
 pub fn check_health() -> impl Task<ProjectContext, Output = Project, Error = Error> {
     run(|ctx| async move {
         match ctx.state.refresh(&ctx.gateway).await {
@@ -441,7 +450,10 @@ where
             return TaskResult::Done(());
         }
 
-        let ctx = self.service.context();
+        let ctx = match self.service.context_of(&self.project_name).await {
+            Ok(ctx) => ctx,
+            Err(err) => return TaskResult::Err(err),
+        };
 
         let project = match self.service.find_project(&self.project_name).await {
             Ok(project) => project,
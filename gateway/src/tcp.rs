@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::copy_bidirectional;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::Sender;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
+use tracing::{debug, error, info, warn};
+
+use crate::service::GatewayService;
+use crate::task::BoxedTask;
+use crate::{Error, ProjectName};
+
+/// How long a project has to take a connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait after failing to accept a connection, since errors like running out of file
+/// descriptors last for a while and would otherwise be retried in a tight loop
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Forwards the raw TCP of the ports given to projects, which are not HTTP the user proxy could
+/// route by host, like MQTT, game servers or gRPC over TLS ending in the service.
+///
+/// The bytes are passed through as they are, so the access rules, rate limits and access logs of
+/// the user proxy do not apply to them.
+pub struct TcpForwarder {
+    gateway: Arc<GatewayService>,
+    task_sender: Sender<BoxedTask>,
+    ip: IpAddr,
+    ports: RangeInclusive<u16>,
+    listeners: Mutex<HashMap<u16, JoinHandle<()>>>,
+}
+
+impl TcpForwarder {
+    /// Forward the `ports` given to projects on the `ip` of the user proxy
+    pub fn new(
+        gateway: Arc<GatewayService>,
+        task_sender: Sender<BoxedTask>,
+        ip: IpAddr,
+        ports: RangeInclusive<u16>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            gateway,
+            task_sender,
+            ip,
+            ports,
+            listeners: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// The ports which may be given to projects
+    pub fn ports(&self) -> &RangeInclusive<u16> {
+        &self.ports
+    }
+
+    /// Listen on every port the projects were given, like when the gateway starts
+    pub async fn listen_all(self: &Arc<Self>) -> Result<(), Error> {
+        for (port, project_name, target_port) in self.gateway.iter_tcp_ports().await? {
+            if let Err(error) = self.listen(project_name.clone(), port, target_port) {
+                error!(%error, %project_name, port, "failed to listen for raw TCP");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Forward the connections to `port` to the `target_port` of the project
+    pub fn listen(
+        self: &Arc<Self>,
+        project_name: ProjectName,
+        port: u16,
+        target_port: u16,
+    ) -> io::Result<()> {
+        let mut listeners = self.listeners.lock().unwrap();
+        if listeners.contains_key(&port) {
+            return Ok(());
+        }
+
+        let listener = std::net::TcpListener::bind(SocketAddr::new(self.ip, port))?;
+        listener.set_nonblocking(true)?;
+        let listener = TcpListener::from_std(listener)?;
+
+        info!(%project_name, port, target_port, "forwarding raw TCP");
+
+        let forwarder = Arc::clone(self);
+        let handle = tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, client)) => {
+                        tokio::spawn(Arc::clone(&forwarder).forward(
+                            project_name.clone(),
+                            target_port,
+                            stream,
+                            client,
+                        ));
+                    }
+                    Err(error) => {
+                        warn!(%error, port, "failed to accept raw TCP");
+                        sleep(ACCEPT_BACKOFF).await;
+                    }
+                }
+            }
+        });
+        listeners.insert(port, handle);
+
+        Ok(())
+    }
+
+    /// Stop listening on the `port`, leaving the connections which were forwarded already
+    pub fn close(&self, port: u16) {
+        if let Some(handle) = self.listeners.lock().unwrap().remove(&port) {
+            handle.abort();
+        }
+    }
+
+    async fn forward(
+        self: Arc<Self>,
+        project_name: ProjectName,
+        target_port: u16,
+        mut stream: TcpStream,
+        client: SocketAddr,
+    ) {
+        // There is no page to tell a raw client about the maintenance, so it is hung up on
+        match self.gateway.maintenance(&project_name).await {
+            Ok(None) => {}
+            Ok(Some(_)) => {
+                debug!(%project_name, "not forwarding raw TCP to project under maintenance");
+                return;
+            }
+            Err(error) => {
+                error!(%error, %project_name, "failed to get the maintenance of the project");
+                return;
+            }
+        }
+
+        let target_ip = match self
+            .gateway
+            .find_or_start_project(&project_name, self.task_sender.clone())
+            .await
+            .and_then(|project| project.target_ip())
+        {
+            Ok(Some(target_ip)) => target_ip,
+            Ok(None) => {
+                debug!(%project_name, "not forwarding raw TCP to project which is not ready");
+                return;
+            }
+            Err(error) => {
+                warn!(%error, %project_name, "failed to find the project to forward raw TCP to");
+                return;
+            }
+        };
+
+        let mut target = match timeout(
+            CONNECT_TIMEOUT,
+            TcpStream::connect(SocketAddr::new(target_ip, target_port)),
+        )
+        .await
+        {
+            Ok(Ok(target)) => target,
+            Ok(Err(error)) => {
+                debug!(%error, %project_name, target_port, "project refused raw TCP");
+                return;
+            }
+            Err(_elapsed) => {
+                debug!(%project_name, target_port, "project did not take raw TCP in time");
+                return;
+            }
+        };
+
+        match copy_bidirectional(&mut stream, &mut target).await {
+            Ok((sent, received)) => {
+                debug!(%project_name, %client, sent, received, "forwarded raw TCP")
+            }
+            Err(error) => debug!(%error, %project_name, %client, "raw TCP was cut"),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::ProjectName;
+
+/// How often the buckets which filled up again are forgotten
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Limits how many requests each client may make to each project with a token bucket, which lets
+/// clients make a burst of requests before they have to slow down to the rate of the project
+pub struct Throttle {
+    /// The clients which are never limited, like the health checks of uptime monitors
+    allowlist: Vec<IpAddr>,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    buckets: HashMap<(ProjectName, IpAddr), Bucket>,
+    pruned: Option<Instant>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// When the bucket fills up again if no more requests are made, after which it can be
+    /// forgotten
+    full_at: Instant,
+}
+
+impl Throttle {
+    pub fn new(allowlist: Vec<IpAddr>) -> Self {
+        Self {
+            allowlist,
+            state: Default::default(),
+        }
+    }
+
+    /// Take a token for a request by `client` to `project`, or get how long it has to wait for one.
+    /// Projects with a rate of 0 are not limited.
+    pub fn check(
+        &self,
+        project: &ProjectName,
+        client: IpAddr,
+        requests_per_second: u32,
+        burst: u32,
+    ) -> Result<(), Duration> {
+        self.check_at(project, client, requests_per_second, burst, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        project: &ProjectName,
+        client: IpAddr,
+        requests_per_second: u32,
+        burst: u32,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        if requests_per_second == 0 || self.allowlist.contains(&client) {
+            return Ok(());
+        }
+
+        let rate = f64::from(requests_per_second);
+        let capacity = f64::from(burst.max(1));
+
+        let mut state = self.state.lock().unwrap();
+
+        if state
+            .pruned
+            .map_or(true, |pruned| now.duration_since(pruned) >= PRUNE_INTERVAL)
+        {
+            state.buckets.retain(|_, bucket| bucket.full_at > now);
+            state.pruned = Some(now);
+        }
+
+        let bucket = state
+            .buckets
+            .entry((project.clone(), client))
+            .or_insert(Bucket {
+                tokens: capacity,
+                updated: now,
+                full_at: now,
+            });
+
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.updated = now;
+
+        let result = if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        };
+
+        bucket.full_at = now + Duration::from_secs_f64((capacity - bucket.tokens) / rate);
+
+        result
+    }
+
+    /// How many clients are being tracked
+    pub fn clients(&self) -> usize {
+        self.state.lock().unwrap().buckets.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use std::time::{Duration, Instant};
+
+    use super::Throttle;
+
+    #[test]
+    fn limits_each_client_of_each_project() {
+        let throttle = Throttle::new(Vec::new());
+        let matrix = "matrix".parse().unwrap();
+        let zion = "zion".parse().unwrap();
+        let neo: IpAddr = "10.0.0.1".parse().unwrap();
+        let trinity: IpAddr = "10.0.0.2".parse().unwrap();
+        let start = Instant::now();
+
+        // A burst of 2, refilled at 1 request a second
+        assert_eq!(throttle.check_at(&matrix, neo, 1, 2, start), Ok(()));
+        assert_eq!(throttle.check_at(&matrix, neo, 1, 2, start), Ok(()));
+        assert_eq!(
+            throttle.check_at(&matrix, neo, 1, 2, start),
+            Err(Duration::from_secs(1))
+        );
+        assert_eq!(
+            throttle.check_at(&matrix, neo, 1, 2, start + Duration::from_millis(500)),
+            Err(Duration::from_millis(500))
+        );
+        assert_eq!(
+            throttle.check_at(&matrix, neo, 1, 2, start + Duration::from_secs(1)),
+            Ok(())
+        );
+
+        // Other clients, and other projects, have their own buckets
+        assert_eq!(throttle.check_at(&matrix, trinity, 1, 2, start), Ok(()));
+        assert_eq!(throttle.check_at(&zion, neo, 1, 2, start), Ok(()));
+
+        // Projects without a rate are not limited
+        for _ in 0..10 {
+            assert_eq!(throttle.check_at(&zion, trinity, 0, 0, start), Ok(()));
+        }
+    }
+
+    #[test]
+    fn allowlist() {
+        let neo: IpAddr = "10.0.0.1".parse().unwrap();
+        let throttle = Throttle::new(vec![neo]);
+        let matrix = "matrix".parse().unwrap();
+        let start = Instant::now();
+
+        for _ in 0..10 {
+            assert_eq!(throttle.check_at(&matrix, neo, 1, 1, start), Ok(()));
+        }
+        assert_eq!(throttle.clients(), 0);
+    }
+
+    #[test]
+    fn forgets_full_buckets() {
+        let throttle = Throttle::new(Vec::new());
+        let matrix = "matrix".parse().unwrap();
+        let neo: IpAddr = "10.0.0.1".parse().unwrap();
+        let trinity: IpAddr = "10.0.0.2".parse().unwrap();
+        let start = Instant::now();
+
+        assert_eq!(throttle.check_at(&matrix, neo, 1, 10, start), Ok(()));
+        assert_eq!(throttle.clients(), 1);
+
+        // The bucket of neo filled up after a second, and is forgotten at the next prune
+        let later = start + Duration::from_secs(60);
+        assert_eq!(throttle.check_at(&matrix, trinity, 1, 10, later), Ok(()));
+        assert_eq!(throttle.clients(), 1);
+    }
+}
@@ -160,7 +160,8 @@ pub fn make_tls_acceptor() -> (Arc<GatewayCertResolver>, RustlsAcceptor<DefaultA
         .with_safe_defaults()
         .with_no_client_auth()
         .with_cert_resolver(Arc::clone(&resolver) as Arc<dyn ResolvesServerCert>);
-    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    // WebSocket clients offer only HTTP/1.1, which they upgrade from
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
 
     let rustls_config = RustlsConfig::from_config(Arc::new(server_config));
 
@@ -3,7 +3,7 @@
 pub struct DatabaseRequest {
     #[prost(string, tag = "1")]
     pub project_name: ::prost::alloc::string::String,
-    #[prost(oneof = "database_request::DbType", tags = "10, 11")]
+    #[prost(oneof = "database_request::DbType", tags = "10, 11, 12")]
     pub db_type: ::core::option::Option<database_request::DbType>,
 }
 /// Nested message and enum types in `DatabaseRequest`.
@@ -15,12 +15,14 @@ pub mod database_request {
         Shared(super::Shared),
         #[prost(message, tag = "11")]
         AwsRds(super::AwsRds),
+        #[prost(message, tag = "12")]
+        Dedicated(super::Dedicated),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Shared {
-    #[prost(oneof = "shared::Engine", tags = "1, 50")]
+    #[prost(oneof = "shared::Engine", tags = "1, 50, 51, 52")]
     pub engine: ::core::option::Option<shared::Engine>,
 }
 /// Nested message and enum types in `Shared`.
@@ -32,10 +34,22 @@ pub mod shared {
         Postgres(::prost::alloc::string::String),
         #[prost(string, tag = "50")]
         Mongodb(::prost::alloc::string::String),
+        #[prost(string, tag = "51")]
+        Redis(::prost::alloc::string::String),
+        #[prost(message, tag = "52")]
+        Qdrant(super::QdrantConfig),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QdrantConfig {
+    #[prost(uint64, tag = "1")]
+    pub vector_size: u64,
+    #[prost(string, tag = "2")]
+    pub distance: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct AwsRds {
     #[prost(oneof = "aws_rds::Engine", tags = "1, 2, 3")]
     pub engine: ::core::option::Option<aws_rds::Engine>,
@@ -58,6 +72,24 @@ pub mod aws_rds {
 pub struct RdsConfig {}
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Dedicated {
+    #[prost(oneof = "dedicated::Engine", tags = "1")]
+    pub engine: ::core::option::Option<dedicated::Engine>,
+}
+/// Nested message and enum types in `Dedicated`.
+pub mod dedicated {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Engine {
+        #[prost(message, tag = "1")]
+        Mongodb(super::DedicatedConfig),
+    }
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DedicatedConfig {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct DatabaseResponse {
     #[prost(string, tag = "1")]
     pub username: ::prost::alloc::string::String,
@@ -16,6 +16,9 @@ pub struct LoadRequest {
         ::prost::alloc::string::String,
         ::prost::alloc::string::String,
     >,
+    /// Arguments from the Shuttle.toml to pass to the service
+    #[prost(string, repeated, tag = "30")]
+    pub args: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -56,6 +59,23 @@ pub struct StopResponse {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ShutdownRequest {
+    /// How long to wait for the work in flight to finish before stopping the service anyway, in seconds
+    #[prost(uint64, tag = "1")]
+    pub drain_timeout: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ShutdownResponse {
+    /// Was the shutdown successful
+    #[prost(bool, tag = "1")]
+    pub success: bool,
+    /// Did the work in flight finish before the drain timeout
+    #[prost(bool, tag = "2")]
+    pub drained: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SubscribeStopRequest {}
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -276,6 +296,27 @@ pub mod runtime_client {
             let path = http::uri::PathAndQuery::from_static("/runtime.Runtime/Stop");
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// Ask a started service to stop taking new work, and stop it once its work in flight is done or
+        /// the drain timeout passed
+        pub async fn shutdown(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ShutdownRequest>,
+        ) -> Result<tonic::Response<super::ShutdownResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/runtime.Runtime/Shutdown",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         /// Channel to notify a service has been stopped
         pub async fn subscribe_stop(
             &mut self,
@@ -346,6 +387,12 @@ pub mod runtime_server {
             &self,
             request: tonic::Request<super::StopRequest>,
         ) -> Result<tonic::Response<super::StopResponse>, tonic::Status>;
+        /// Ask a started service to stop taking new work, and stop it once its work in flight is done or
+        /// the drain timeout passed
+        async fn shutdown(
+            &self,
+            request: tonic::Request<super::ShutdownRequest>,
+        ) -> Result<tonic::Response<super::ShutdownResponse>, tonic::Status>;
         /// Server streaming response type for the SubscribeStop method.
         type SubscribeStopStream: futures_core::Stream<
                 Item = Result<super::SubscribeStopResponse, tonic::Status>,
@@ -536,6 +583,42 @@ pub mod runtime_server {
                     };
                     Box::pin(fut)
                 }
+                "/runtime.Runtime/Shutdown" => {
+                    #[allow(non_camel_case_types)]
+                    struct ShutdownSvc<T: Runtime>(pub Arc<T>);
+                    impl<T: Runtime> tonic::server::UnaryService<super::ShutdownRequest>
+                    for ShutdownSvc<T> {
+                        type Response = super::ShutdownResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ShutdownRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).shutdown(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ShutdownSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/runtime.Runtime/SubscribeStop" => {
                     #[allow(non_camel_case_types)]
                     struct SubscribeStopSvc<T: Runtime>(pub Arc<T>);
@@ -6,7 +6,7 @@ pub mod provisioner {
     use std::fmt::Display;
 
     use shuttle_common::{
-        database::{self, AwsRdsEngine, SharedEngine},
+        database::{self, AwsRdsEngine, DedicatedEngine, SharedEngine},
         DatabaseReadyInfo,
     };
 
@@ -33,6 +33,8 @@ pub mod provisioner {
                     let engine = match engine {
                         SharedEngine::Postgres => shared::Engine::Postgres(String::new()),
                         SharedEngine::MongoDb => shared::Engine::Mongodb(String::new()),
+                        SharedEngine::Redis => shared::Engine::Redis(String::new()),
+                        SharedEngine::Qdrant => shared::Engine::Qdrant(QdrantConfig::default()),
                     };
                     database_request::DbType::Shared(Shared {
                         engine: Some(engine),
@@ -49,6 +51,15 @@ pub mod provisioner {
                         engine: Some(engine),
                     })
                 }
+                database::Type::Dedicated(engine) => {
+                    let config = DedicatedConfig {};
+                    let engine = match engine {
+                        DedicatedEngine::MongoDb => dedicated::Engine::Mongodb(config),
+                    };
+                    database_request::DbType::Dedicated(Dedicated {
+                        engine: Some(engine),
+                    })
+                }
             }
         }
     }
@@ -65,6 +76,8 @@ pub mod provisioner {
                     shared::Engine::Mongodb(_) => {
                         Some(database::Type::Shared(SharedEngine::MongoDb))
                     }
+                    shared::Engine::Redis(_) => Some(database::Type::Shared(SharedEngine::Redis)),
+                    shared::Engine::Qdrant(_) => Some(database::Type::Shared(SharedEngine::Qdrant)),
                 },
                 database_request::DbType::AwsRds(AwsRds {
                     engine: Some(engine),
@@ -77,8 +90,16 @@ pub mod provisioner {
                         Some(database::Type::AwsRds(AwsRdsEngine::MariaDB))
                     }
                 },
+                database_request::DbType::Dedicated(Dedicated {
+                    engine: Some(engine),
+                }) => match engine {
+                    dedicated::Engine::Mongodb(_) => {
+                        Some(database::Type::Dedicated(DedicatedEngine::MongoDb))
+                    }
+                },
                 database_request::DbType::Shared(Shared { engine: None })
-                | database_request::DbType::AwsRds(AwsRds { engine: None }) => None,
+                | database_request::DbType::AwsRds(AwsRds { engine: None })
+                | database_request::DbType::Dedicated(Dedicated { engine: None }) => None,
             }
         }
     }
@@ -92,12 +113,22 @@ pub mod provisioner {
             }
         }
     }
+
+    impl Display for dedicated::Engine {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Mongodb(_) => write!(f, "mongodb"),
+            }
+        }
+    }
 }
 
 pub mod runtime {
     use std::{
+        collections::HashMap,
         convert::TryFrom,
-        path::PathBuf,
+        path::{Path, PathBuf},
+        process::Stdio,
         time::{Duration, SystemTime},
     };
 
@@ -145,6 +176,7 @@ pub mod runtime {
                 line: log.line,
                 target: log.target,
                 fields: log.fields,
+                source: shuttle_common::log::Source::Tracing,
             })
         }
     }
@@ -206,6 +238,12 @@ pub mod runtime {
         }
     }
 
+    /// Start a runtime and connect to it.
+    ///
+    /// The runtime is started from `working_directory` when it is set, so that the service can
+    /// find its files at relative paths. It gets the variables in `env` on top of the environment of
+    /// this process. What it writes to stdout and stderr goes to `stdout` and `stderr`.
+    #[allow(clippy::too_many_arguments)]
     pub async fn start(
         wasm: bool,
         storage_manager_type: StorageManagerType,
@@ -213,6 +251,10 @@ pub mod runtime {
         auth_uri: Option<&String>,
         port: u16,
         get_runtime_executable: impl FnOnce() -> PathBuf,
+        working_directory: Option<&Path>,
+        env: &HashMap<String, String>,
+        stdout: Stdio,
+        stderr: Stdio,
     ) -> anyhow::Result<(
         process::Child,
         runtime_client::RuntimeClient<ClaimService<InjectPropagation<Channel>>>,
@@ -247,11 +289,19 @@ pub mod runtime {
             args
         };
 
-        let runtime = process::Command::new(runtime_executable_path)
+        let mut runtime = process::Command::new(runtime_executable_path);
+        runtime
             .args(&args)
-            .kill_on_drop(true)
-            .spawn()
-            .context("spawning runtime process")?;
+            .envs(env)
+            .stdout(stdout)
+            .stderr(stderr)
+            .kill_on_drop(true);
+
+        if let Some(working_directory) = working_directory {
+            runtime.current_dir(working_directory);
+        }
+
+        let runtime = runtime.spawn().context("spawning runtime process")?;
 
         // Sleep because the timeout below does not seem to work
         // TODO: investigate why
@@ -26,6 +26,15 @@ pub struct Args {
     #[arg(long, env = "PROVISIONER_MONGODB_URI", hide_env_values = true)]
     pub shared_mongodb_uri: String,
 
+    /// URI to connect to Redis for managing shared Redis resources
+    #[arg(long, env = "PROVISIONER_REDIS_URI", hide_env_values = true)]
+    pub shared_redis_uri: String,
+
+    /// URI to connect to Qdrant for managing shared Qdrant collections, with the API key of the
+    /// instance as the password, like `http://:api-key@qdrant:6333`
+    #[arg(long, env = "PROVISIONER_QDRANT_URI", hide_env_values = true)]
+    pub shared_qdrant_uri: String,
+
     /// Fully qualified domain name this provisioner instance is reachable at
     #[arg(long, env = "PROVISIONER_FQDN", value_parser = parse_fqdn)]
     pub fqdn: FQDN,
@@ -38,6 +47,14 @@ pub struct Args {
     #[arg(long, env = "PROVISIONER_MONGODB_ADDRESS", default_value = "mongodb")]
     pub internal_mongodb_address: String,
 
+    /// Address the provisioned Redis can be reached at on the internal network
+    #[arg(long, env = "PROVISIONER_REDIS_ADDRESS", default_value = "redis")]
+    pub internal_redis_address: String,
+
+    /// Address the provisioned Qdrant can be reached at on the internal network
+    #[arg(long, env = "PROVISIONER_QDRANT_ADDRESS", default_value = "qdrant")]
+    pub internal_qdrant_address: String,
+
     /// Address to reach the authentication service at
     #[arg(long, default_value = "http://127.0.0.1:8008")]
     pub auth_uri: Uri,
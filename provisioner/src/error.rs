@@ -1,3 +1,9 @@
+use aws_sdk_docdb::operation::{
+    create_db_cluster::CreateDBClusterError,
+    create_db_instance::CreateDBInstanceError as CreateDocDbInstanceError,
+    describe_db_clusters::DescribeDBClustersError,
+    describe_db_instances::DescribeDBInstancesError as DescribeDocDbInstancesError,
+};
 use aws_sdk_rds::{
     error::SdkError,
     operation::{
@@ -31,12 +37,30 @@ pub enum Error {
     #[error("unexpected mongodb error: {0}")]
     UnexpectedMongodb(#[from] mongodb::error::Error),
 
+    #[error("unexpected redis error: {0}")]
+    UnexpectedRedis(#[from] redis::RedisError),
+
+    #[error("unexpected qdrant error: {0}")]
+    UnexpectedQdrant(#[from] reqwest::Error),
+
     #[error("failed to create RDS instance: {0}")]
     CreateRDSInstance(#[from] SdkError<CreateDBInstanceError>),
 
     #[error("failed to get description of RDS instance: {0}")]
     DescribeRDSInstance(#[from] SdkError<DescribeDBInstancesError>),
 
+    #[error("failed to create DocumentDB cluster: {0}")]
+    CreateDocDbCluster(#[from] SdkError<CreateDBClusterError>),
+
+    #[error("failed to create DocumentDB instance: {0}")]
+    CreateDocDbInstance(#[from] SdkError<CreateDocDbInstanceError>),
+
+    #[error("failed to get description of DocumentDB cluster: {0}")]
+    DescribeDocDbCluster(#[from] SdkError<DescribeDBClustersError>),
+
+    #[error("failed to get description of DocumentDB instance: {0}")]
+    DescribeDocDbInstance(#[from] SdkError<DescribeDocDbInstancesError>),
+
     #[error["plain error: {0}"]]
     Plain(String),
 }
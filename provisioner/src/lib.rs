@@ -2,23 +2,32 @@ use std::time::Duration;
 
 pub use args::Args;
 use aws_config::timeout;
+use aws_sdk_docdb::operation::modify_db_cluster::ModifyDBClusterError;
 use aws_sdk_rds::{
     error::SdkError, operation::modify_db_instance::ModifyDBInstanceError, types::DbInstance,
     Client,
 };
 pub use error::Error;
+use jsonwebtoken::{EncodingKey, Header};
 use mongodb::{bson::doc, options::ClientOptions};
 use rand::Rng;
+use redis::AsyncCommands;
+use reqwest::{
+    header::{HeaderMap, HeaderValue},
+    StatusCode, Url,
+};
+use serde_json::json;
 use shuttle_common::claims::{Claim, Scope};
 pub use shuttle_proto::provisioner::provisioner_server::ProvisionerServer;
 use shuttle_proto::provisioner::{
-    aws_rds, database_request::DbType, shared, AwsRds, DatabaseRequest, DatabaseResponse, Shared,
+    aws_rds, database_request::DbType, dedicated, shared, AwsRds, DatabaseRequest,
+    DatabaseResponse, Dedicated, QdrantConfig, Shared,
 };
 use shuttle_proto::provisioner::{provisioner_server::Provisioner, DatabaseDeletionResponse};
 use sqlx::{postgres::PgPoolOptions, ConnectOptions, Executor, PgPool};
 use tokio::time::sleep;
 use tonic::{Request, Response, Status};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 mod args;
 mod error;
@@ -26,23 +35,39 @@ mod error;
 const AWS_RDS_CLASS: &str = "db.t4g.micro";
 const MASTER_USERNAME: &str = "master";
 const RDS_SUBNET_GROUP: &str = "shuttle_rds";
+const DOCDB_CLASS: &str = "db.t3.medium";
+const DOCDB_SUBNET_GROUP: &str = "shuttle_docdb";
+// TLS is turned off in this parameter group, so that clients don't need the certificate of AWS
+const DOCDB_PARAMETER_GROUP: &str = "shuttle-docdb";
 
 pub struct MyProvisioner {
     pool: PgPool,
     rds_client: aws_sdk_rds::Client,
+    docdb_client: aws_sdk_docdb::Client,
     mongodb_client: mongodb::Client,
+    redis_client: redis::Client,
+    qdrant_client: reqwest::Client,
+    qdrant_uri: Url,
+    qdrant_api_key: String,
     fqdn: String,
     internal_pg_address: String,
     internal_mongodb_address: String,
+    internal_redis_address: String,
+    internal_qdrant_address: String,
 }
 
 impl MyProvisioner {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         shared_pg_uri: &str,
         shared_mongodb_uri: &str,
+        shared_redis_uri: &str,
+        shared_qdrant_uri: &str,
         fqdn: String,
         internal_pg_address: String,
         internal_mongodb_address: String,
+        internal_redis_address: String,
+        internal_qdrant_address: String,
     ) -> Result<Self, Error> {
         let pool = PgPoolOptions::new()
             .min_connections(4)
@@ -53,6 +78,24 @@ impl MyProvisioner {
         let mongodb_options = ClientOptions::parse(shared_mongodb_uri).await?;
         let mongodb_client = mongodb::Client::with_options(mongodb_options)?;
 
+        let redis_client = redis::Client::open(shared_redis_uri)?;
+
+        let mut qdrant_uri = Url::parse(shared_qdrant_uri)
+            .map_err(|error| Error::Plain(format!("invalid qdrant uri: {error}")))?;
+        let qdrant_api_key = qdrant_uri.password().unwrap_or_default().to_string();
+        let _ = qdrant_uri.set_username("");
+        let _ = qdrant_uri.set_password(None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "api-key",
+            HeaderValue::from_str(&qdrant_api_key)
+                .map_err(|error| Error::Plain(format!("invalid qdrant api key: {error}")))?,
+        );
+        let qdrant_client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()?;
+
         // Default timeout is too long so lowering it
         let timeout_config = timeout::TimeoutConfig::builder()
             .operation_timeout(Duration::from_secs(120))
@@ -65,14 +108,22 @@ impl MyProvisioner {
             .await;
 
         let rds_client = aws_sdk_rds::Client::new(&aws_config);
+        let docdb_client = aws_sdk_docdb::Client::new(&aws_config);
 
         Ok(Self {
             pool,
             rds_client,
+            docdb_client,
             mongodb_client,
+            redis_client,
+            qdrant_client,
+            qdrant_uri,
+            qdrant_api_key,
             fqdn,
             internal_pg_address,
             internal_mongodb_address,
+            internal_redis_address,
+            internal_qdrant_address,
         })
     }
 
@@ -111,6 +162,33 @@ impl MyProvisioner {
                     port: "27017".to_string(),
                 })
             }
+            shared::Engine::Redis(_) => {
+                let (username, password) = self.shared_redis(project_name).await?;
+
+                Ok(DatabaseResponse {
+                    engine: "redis".to_string(),
+                    username,
+                    password,
+                    database_name: "0".to_string(),
+                    address_private: self.internal_redis_address.clone(),
+                    address_public: self.fqdn.clone(),
+                    port: "6379".to_string(),
+                })
+            }
+            shared::Engine::Qdrant(config) => {
+                let api_key = self.shared_qdrant(project_name, config).await?;
+
+                // Qdrant is reached on its gRPC port, which is the one its clients use
+                Ok(DatabaseResponse {
+                    engine: "http".to_string(),
+                    username: project_name.to_string(),
+                    password: api_key,
+                    database_name: project_name.to_string(),
+                    address_private: self.internal_qdrant_address.clone(),
+                    address_public: self.fqdn.clone(),
+                    port: "6334".to_string(),
+                })
+            }
         }
     }
 
@@ -236,6 +314,95 @@ impl MyProvisioner {
         }
     }
 
+    async fn shared_redis(&self, project_name: &str) -> Result<(String, String), Error> {
+        let username = format!("user-{project_name}");
+        let password = generate_password();
+
+        let mut conn = self.redis_client.get_async_connection().await?;
+
+        // `reset` drops the rules and password of the user if it already exists, which cycles its
+        // password. The user can only reach the keys and channels starting with the name of its
+        // project, and none of the commands which list the keys of other projects or act on the
+        // whole instance.
+        redis::cmd("ACL")
+            .arg("SETUSER")
+            .arg(&username)
+            .arg("reset")
+            .arg("on")
+            .arg(format!(">{password}"))
+            .arg(format!("~{project_name}:*"))
+            .arg(format!("&{project_name}:*"))
+            .arg("+@all")
+            .arg("-@admin")
+            .arg("-@dangerous")
+            .arg("-scan")
+            .arg("-randomkey")
+            .query_async::<_, ()>(&mut conn)
+            .await?;
+        info!("user set up");
+
+        save_redis_users(&mut conn).await;
+
+        Ok((username, password))
+    }
+
+    async fn shared_qdrant(
+        &self,
+        project_name: &str,
+        config: QdrantConfig,
+    ) -> Result<String, Error> {
+        let collection_uri = self.qdrant_collection_uri(project_name)?;
+
+        let response = self
+            .qdrant_client
+            .get(collection_uri.clone())
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            info!("creating collection");
+
+            // Only the key of the instance can create collections, so it is done here rather than
+            // by the service
+            self.qdrant_client
+                .put(collection_uri)
+                .json(&json!({
+                    "vectors": {
+                        "size": config.vector_size,
+                        "distance": config.distance,
+                    }
+                }))
+                .send()
+                .await?
+                .error_for_status()
+                .map_err(|e| Error::CreateDB(e.to_string()))?;
+        } else {
+            response.error_for_status()?;
+        }
+
+        // The key is a token signed with the key of the instance, which Qdrant trusts to only
+        // reach the collections listed in it
+        let claims = json!({
+            "access": [
+                {"collection": project_name, "access": "rw"}
+            ]
+        });
+        let api_key = jsonwebtoken::encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(self.qdrant_api_key.as_bytes()),
+        )
+        .map_err(|e| Error::CreateRole(e.to_string()))?;
+
+        Ok(api_key)
+    }
+
+    fn qdrant_collection_uri(&self, project_name: &str) -> Result<Url, Error> {
+        self.qdrant_uri
+            .join(&format!("collections/{project_name}"))
+            .map_err(|error| Error::Plain(format!("invalid qdrant collection uri: {error}")))
+    }
+
     async fn request_aws_rds(
         &self,
         project_name: &str,
@@ -320,7 +487,95 @@ impl MyProvisioner {
         })
     }
 
-    async fn delete_shared_db(
+    async fn request_dedicated_db(
+        &self,
+        project_name: &str,
+        engine: dedicated::Engine,
+    ) -> Result<DatabaseResponse, Error> {
+        match engine {
+            dedicated::Engine::Mongodb(_) => self.request_docdb(project_name).await,
+        }
+    }
+
+    async fn request_docdb(&self, project_name: &str) -> Result<DatabaseResponse, Error> {
+        let client = &self.docdb_client;
+
+        let password = generate_password();
+        let cluster_name = format!("{project_name}-mongodb");
+
+        debug!("trying to get DocumentDB cluster: {cluster_name}");
+        let cluster = client
+            .modify_db_cluster()
+            .db_cluster_identifier(&cluster_name)
+            .master_user_password(&password)
+            .apply_immediately(true)
+            .send()
+            .await;
+
+        match cluster {
+            Ok(_) => {
+                wait_for_cluster(client, &cluster_name, "resetting-master-credentials").await?;
+            }
+            Err(SdkError::ServiceError(err)) => {
+                if let ModifyDBClusterError::DbClusterNotFoundFault(_) = err.err() {
+                    debug!("creating new DocumentDB cluster {cluster_name}");
+
+                    client
+                        .create_db_cluster()
+                        .db_cluster_identifier(&cluster_name)
+                        .engine("docdb")
+                        .master_username(MASTER_USERNAME)
+                        .master_user_password(&password)
+                        .backup_retention_period(1) // The least DocumentDB allows
+                        .db_subnet_group_name(DOCDB_SUBNET_GROUP)
+                        .db_cluster_parameter_group_name(DOCDB_PARAMETER_GROUP)
+                        .send()
+                        .await?;
+
+                    // A cluster only stores the data, the queries are answered by its instances
+                    client
+                        .create_db_instance()
+                        .db_instance_identifier(&cluster_name)
+                        .db_cluster_identifier(&cluster_name)
+                        .engine("docdb")
+                        .db_instance_class(DOCDB_CLASS)
+                        .send()
+                        .await?;
+                } else {
+                    return Err(Error::Plain(format!(
+                        "got unexpected error from AWS DocumentDB service: {}",
+                        err.err()
+                    )));
+                }
+            }
+            Err(unexpected) => {
+                return Err(Error::Plain(format!(
+                    "got unexpected error from AWS during API call: {}",
+                    unexpected
+                )))
+            }
+        };
+
+        // Wait for up
+        let cluster = wait_for_cluster(client, &cluster_name, "available").await?;
+        wait_for_docdb_instance(client, &cluster_name, "available").await?;
+
+        let address = cluster.endpoint.expect("cluster to have an endpoint");
+
+        Ok(DatabaseResponse {
+            engine: "mongodb".to_string(),
+            username: cluster.master_username.expect("cluster to have a username"),
+            password,
+            // The master user is kept in the admin database, and DocumentDB does not support
+            // retryable writes which the drivers use by default
+            database_name: "mongodb?authSource=admin&retryWrites=false".to_string(),
+            address_private: address.clone(),
+            address_public: address,
+            port: cluster.port.unwrap_or(27017).to_string(),
+        })
+    }
+
+    pub async fn delete_shared_db(
         &self,
         project_name: &str,
         engine: shared::Engine,
@@ -328,6 +583,8 @@ impl MyProvisioner {
         match engine {
             shared::Engine::Postgres(_) => self.delete_pg(project_name).await?,
             shared::Engine::Mongodb(_) => self.delete_mongodb(project_name).await?,
+            shared::Engine::Redis(_) => self.delete_redis(project_name).await?,
+            shared::Engine::Qdrant(_) => self.delete_qdrant(project_name).await?,
         }
         Ok(DatabaseDeletionResponse {})
     }
@@ -379,6 +636,54 @@ impl MyProvisioner {
         Ok(())
     }
 
+    async fn delete_redis(&self, project_name: &str) -> Result<(), Error> {
+        let username = format!("user-{project_name}");
+
+        let mut conn = self.redis_client.get_async_connection().await?;
+
+        // Take the user away first, so that no keys are added while the others are deleted
+        redis::cmd("ACL")
+            .arg("DELUSER")
+            .arg(&username)
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(|e| Error::DeleteRole(e.to_string()))?;
+        save_redis_users(&mut conn).await;
+
+        let keys: Vec<String> = {
+            let mut iter = conn
+                .scan_match::<_, String>(format!("{project_name}:*"))
+                .await
+                .map_err(|e| Error::DeleteDB(e.to_string()))?;
+            let mut keys = Vec::new();
+            while let Some(key) = iter.next_item().await {
+                keys.push(key);
+            }
+            keys
+        };
+
+        for keys in keys.chunks(1000) {
+            conn.del::<_, ()>(keys)
+                .await
+                .map_err(|e| Error::DeleteDB(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    async fn delete_qdrant(&self, project_name: &str) -> Result<(), Error> {
+        // This is enough to take the data away from the keys of the project, since they can't reach
+        // anything else
+        self.qdrant_client
+            .delete(self.qdrant_collection_uri(project_name)?)
+            .send()
+            .await?
+            .error_for_status()
+            .map_err(|e| Error::DeleteDB(e.to_string()))?;
+
+        Ok(())
+    }
+
     async fn delete_aws_rds(
         &self,
         project_name: &str,
@@ -406,6 +711,59 @@ impl MyProvisioner {
 
         Ok(DatabaseDeletionResponse {})
     }
+
+    async fn delete_dedicated_db(
+        &self,
+        project_name: &str,
+        engine: dedicated::Engine,
+    ) -> Result<DatabaseDeletionResponse, Error> {
+        match engine {
+            dedicated::Engine::Mongodb(_) => self.delete_docdb(project_name).await?,
+        }
+
+        Ok(DatabaseDeletionResponse {})
+    }
+
+    async fn delete_docdb(&self, project_name: &str) -> Result<(), Error> {
+        let client = &self.docdb_client;
+        let cluster_name = format!("{project_name}-mongodb");
+
+        // A cluster can only be deleted once its instances are gone
+        let delete_result = client
+            .delete_db_instance()
+            .db_instance_identifier(&cluster_name)
+            .send()
+            .await;
+
+        if let Err(SdkError::ServiceError(err)) = delete_result {
+            if !err.err().is_db_instance_not_found_fault() {
+                return Err(Error::Plain(format!(
+                    "got unexpected error from AWS DocumentDB service: {}",
+                    err.err()
+                )));
+            }
+        }
+
+        wait_for_docdb_instance_deleted(client, &cluster_name).await?;
+
+        let delete_result = client
+            .delete_db_cluster()
+            .db_cluster_identifier(&cluster_name)
+            .skip_final_snapshot(true)
+            .send()
+            .await;
+
+        if let Err(SdkError::ServiceError(err)) = delete_result {
+            if !err.err().is_db_cluster_not_found_fault() {
+                return Err(Error::Plain(format!(
+                    "got unexpected error from AWS DocumentDB service: {}",
+                    err.err()
+                )));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[tonic::async_trait]
@@ -429,6 +787,10 @@ impl Provisioner for MyProvisioner {
                 self.request_aws_rds(&request.project_name, engine.expect("oneof to be set"))
                     .await?
             }
+            DbType::Dedicated(Dedicated { engine }) => {
+                self.request_dedicated_db(&request.project_name, engine.expect("oneof to be set"))
+                    .await?
+            }
         };
 
         Ok(Response::new(reply))
@@ -453,6 +815,10 @@ impl Provisioner for MyProvisioner {
                 self.delete_aws_rds(&request.project_name, engine.expect("oneof to be set"))
                     .await?
             }
+            DbType::Dedicated(Dedicated { engine }) => {
+                self.delete_dedicated_db(&request.project_name, engine.expect("oneof to be set"))
+                    .await?
+            }
         };
 
         Ok(Response::new(reply))
@@ -475,6 +841,17 @@ fn verify_claim<B>(request: &Request<B>) -> Result<(), Status> {
     }
 }
 
+/// Keep the users of Redis across its restarts, when it was started with an ACL file to keep them in
+async fn save_redis_users(conn: &mut redis::aio::Connection) {
+    if let Err(error) = redis::cmd("ACL")
+        .arg("SAVE")
+        .query_async::<_, ()>(conn)
+        .await
+    {
+        warn!(%error, "could not save the users of redis, they will be lost when it restarts");
+    }
+}
+
 fn generate_password() -> String {
     rand::thread_rng()
         .sample_iter(&rand::distributions::Alphanumeric)
@@ -515,6 +892,88 @@ async fn wait_for_instance(
     }
 }
 
+async fn wait_for_cluster(
+    client: &aws_sdk_docdb::Client,
+    name: &str,
+    wait_for: &str,
+) -> Result<aws_sdk_docdb::types::DbCluster, Error> {
+    debug!("waiting for {name} to enter {wait_for} state");
+    loop {
+        let cluster = client
+            .describe_db_clusters()
+            .db_cluster_identifier(name)
+            .send()
+            .await?
+            .db_clusters
+            .expect("aws to return clusters")
+            .get(0)
+            .expect("to find the cluster just created or modified")
+            .clone();
+
+        let status = cluster
+            .status
+            .as_ref()
+            .expect("cluster to have a status")
+            .clone();
+
+        if status == wait_for {
+            return Ok(cluster);
+        }
+
+        sleep(Duration::from_secs(1)).await;
+    }
+}
+
+async fn wait_for_docdb_instance(
+    client: &aws_sdk_docdb::Client,
+    name: &str,
+    wait_for: &str,
+) -> Result<(), Error> {
+    debug!("waiting for {name} to enter {wait_for} state");
+    loop {
+        let status = client
+            .describe_db_instances()
+            .db_instance_identifier(name)
+            .send()
+            .await?
+            .db_instances
+            .expect("aws to return instances")
+            .get(0)
+            .expect("to find the instance just created")
+            .db_instance_status
+            .clone()
+            .expect("instance to have a status");
+
+        if status == wait_for {
+            return Ok(());
+        }
+
+        sleep(Duration::from_secs(1)).await;
+    }
+}
+
+async fn wait_for_docdb_instance_deleted(
+    client: &aws_sdk_docdb::Client,
+    name: &str,
+) -> Result<(), Error> {
+    debug!("waiting for {name} to be deleted");
+    loop {
+        let result = client
+            .describe_db_instances()
+            .db_instance_identifier(name)
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => sleep(Duration::from_secs(5)).await,
+            Err(SdkError::ServiceError(err)) if err.err().is_db_instance_not_found_fault() => {
+                return Ok(())
+            }
+            Err(error) => return Err(error.into()),
+        }
+    }
+}
+
 fn engine_to_port(engine: aws_rds::Engine) -> String {
     match engine {
         aws_rds::Engine::Postgres(_) => "5432".to_string(),
@@ -17,9 +17,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         port,
         shared_pg_uri,
         shared_mongodb_uri,
+        shared_redis_uri,
+        shared_qdrant_uri,
         fqdn,
         internal_pg_address,
         internal_mongodb_address,
+        internal_redis_address,
+        internal_qdrant_address,
         auth_uri,
     } = Args::parse();
     let addr = SocketAddr::new(ip, port);
@@ -27,9 +31,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let provisioner = MyProvisioner::new(
         &shared_pg_uri,
         &shared_mongodb_uri,
+        &shared_redis_uri,
+        &shared_qdrant_uri,
         fqdn.to_string(),
         internal_pg_address,
         internal_mongodb_address,
+        internal_redis_address,
+        internal_qdrant_address,
     )
     .await
     .unwrap();
@@ -7,6 +7,8 @@ use std::{
 
 const PG_CONTAINER_NAME: &str = "shuttle_provisioner_test_pg";
 const MONGODB_CONTAINER_NAME: &str = "shuttle_provisioner_test_mongodb";
+const REDIS_CONTAINER_NAME: &str = "shuttle_provisioner_test_redis";
+const QDRANT_CONTAINER_NAME: &str = "shuttle_provisioner_test_qdrant";
 
 pub struct DockerInstance {
     pub container_name: &'static str,
@@ -19,12 +21,15 @@ struct Config<'a> {
     engine: &'a str,
     port: &'a str,
     env: Vec<&'a str>,
+    cmd: Vec<&'a str>,
     is_ready_cmd: Vec<&'a str>,
 }
 
 pub enum DbType {
     Postgres,
     MongoDb,
+    Redis,
+    Qdrant,
 }
 
 impl DockerInstance {
@@ -32,6 +37,7 @@ impl DockerInstance {
         let Config {
             engine,
             env,
+            cmd,
             image,
             is_ready_cmd,
             port,
@@ -47,6 +53,8 @@ impl DockerInstance {
 
         args.push(image);
 
+        args.extend(cmd);
+
         Command::new("docker").args(args).spawn().unwrap();
 
         Self::wait_ready(Duration::from_secs(120), &is_ready_cmd);
@@ -107,6 +115,7 @@ impl From<DbType> for Config<'_> {
                 engine: "postgres",
                 port: "5432",
                 env: vec!["POSTGRES_PASSWORD=password"],
+                cmd: vec![],
                 is_ready_cmd: vec!["exec", PG_CONTAINER_NAME, "pg_isready"],
             },
             DbType::MongoDb => Config {
@@ -118,6 +127,7 @@ impl From<DbType> for Config<'_> {
                     "MONGO_INITDB_ROOT_USERNAME=mongodb",
                     "MONGO_INITDB_ROOT_PASSWORD=password",
                 ],
+                cmd: vec![],
                 is_ready_cmd: vec![
                     "exec",
                     MONGODB_CONTAINER_NAME,
@@ -127,6 +137,52 @@ impl From<DbType> for Config<'_> {
                     "db",
                 ],
             },
+            DbType::Redis => Config {
+                container_name: REDIS_CONTAINER_NAME,
+                image: "docker.io/library/redis:7.0",
+                engine: "redis",
+                port: "6379",
+                env: vec![],
+                cmd: vec![
+                    "redis-server",
+                    "--user",
+                    "redis",
+                    "on",
+                    ">password",
+                    "~*",
+                    "&*",
+                    "+@all",
+                ],
+                is_ready_cmd: vec![
+                    "exec",
+                    REDIS_CONTAINER_NAME,
+                    "redis-cli",
+                    "--user",
+                    "redis",
+                    "--pass",
+                    "password",
+                    "--no-auth-warning",
+                    "ping",
+                ],
+            },
+            DbType::Qdrant => Config {
+                container_name: QDRANT_CONTAINER_NAME,
+                image: "docker.io/qdrant/qdrant:v1.9.0",
+                engine: "http",
+                port: "6333",
+                env: vec![
+                    "QDRANT__SERVICE__API_KEY=password",
+                    "QDRANT__SERVICE__JWT_RBAC=true",
+                ],
+                cmd: vec![],
+                is_ready_cmd: vec![
+                    "exec",
+                    QDRANT_CONTAINER_NAME,
+                    "bash",
+                    "-c",
+                    "echo > /dev/tcp/127.0.0.1/6333",
+                ],
+            },
         }
     }
 }
@@ -179,3 +235,24 @@ pub fn exec_mongosh(command: &str, database_name: Option<&str>) -> String {
 
     String::from_utf8(output).unwrap().trim().to_string()
 }
+
+/// Execute commands in `redis-cli` via `docker exec`
+pub fn exec_redis(command: &[&str]) -> String {
+    let output = Command::new("docker")
+        .args([
+            "exec",
+            REDIS_CONTAINER_NAME,
+            "redis-cli",
+            "--user",
+            "redis",
+            "--pass",
+            "password",
+            "--no-auth-warning",
+        ])
+        .args(command)
+        .output()
+        .unwrap()
+        .stdout;
+
+    String::from_utf8(output).unwrap().trim().to_string()
+}
@@ -1,18 +1,22 @@
 mod helpers;
 use ctor::dtor;
-use helpers::{exec_mongosh, exec_psql, DbType, DockerInstance};
+use helpers::{exec_mongosh, exec_psql, exec_redis, DbType, DockerInstance};
 use once_cell::sync::Lazy;
 use serde_json::Value;
-use shuttle_proto::provisioner::shared;
+use shuttle_proto::provisioner::{shared, QdrantConfig};
 use shuttle_provisioner::MyProvisioner;
 
 static PG: Lazy<DockerInstance> = Lazy::new(|| DockerInstance::new(DbType::Postgres));
 static MONGODB: Lazy<DockerInstance> = Lazy::new(|| DockerInstance::new(DbType::MongoDb));
+static REDIS: Lazy<DockerInstance> = Lazy::new(|| DockerInstance::new(DbType::Redis));
+static QDRANT: Lazy<DockerInstance> = Lazy::new(|| DockerInstance::new(DbType::Qdrant));
 
 #[dtor]
 fn cleanup() {
     PG.cleanup();
     MONGODB.cleanup();
+    REDIS.cleanup();
+    QDRANT.cleanup();
 }
 
 #[tokio::test]
@@ -20,9 +24,13 @@ async fn shared_db_role_does_not_exist() {
     let provisioner = MyProvisioner::new(
         &PG.uri,
         &MONGODB.uri,
+        &REDIS.uri,
+        &QDRANT.uri,
         "fqdn".to_string(),
         "pg".to_string(),
         "mongodb".to_string(),
+        "redis".to_string(),
+        "qdrant".to_string(),
     )
     .await
     .unwrap();
@@ -48,9 +56,13 @@ async fn shared_db_role_does_exist() {
     let provisioner = MyProvisioner::new(
         &PG.uri,
         &MONGODB.uri,
+        &REDIS.uri,
+        &QDRANT.uri,
         "fqdn".to_string(),
         "pg".to_string(),
         "mongodb".to_string(),
+        "redis".to_string(),
+        "qdrant".to_string(),
     )
     .await
     .unwrap();
@@ -81,9 +93,13 @@ async fn injection_safe() {
     let provisioner = MyProvisioner::new(
         &PG.uri,
         &MONGODB.uri,
+        &REDIS.uri,
+        &QDRANT.uri,
         "fqdn".to_string(),
         "pg".to_string(),
         "mongodb".to_string(),
+        "redis".to_string(),
+        "qdrant".to_string(),
     )
     .await
     .unwrap();
@@ -102,9 +118,13 @@ async fn shared_db_missing() {
     let provisioner = MyProvisioner::new(
         &PG.uri,
         &MONGODB.uri,
+        &REDIS.uri,
+        &QDRANT.uri,
         "fqdn".to_string(),
         "pg".to_string(),
         "mongodb".to_string(),
+        "redis".to_string(),
+        "qdrant".to_string(),
     )
     .await
     .unwrap();
@@ -130,9 +150,13 @@ async fn shared_db_filled() {
     let provisioner = MyProvisioner::new(
         &PG.uri,
         &MONGODB.uri,
+        &REDIS.uri,
+        &QDRANT.uri,
         "fqdn".to_string(),
         "pg".to_string(),
         "mongodb".to_string(),
+        "redis".to_string(),
+        "qdrant".to_string(),
     )
     .await
     .unwrap();
@@ -160,9 +184,13 @@ async fn shared_mongodb_role_does_not_exist() {
     let provisioner = MyProvisioner::new(
         &PG.uri,
         &MONGODB.uri,
+        &REDIS.uri,
+        &QDRANT.uri,
         "fqdn".to_string(),
         "pg".to_string(),
         "mongodb".to_string(),
+        "redis".to_string(),
+        "qdrant".to_string(),
     )
     .await
     .unwrap();
@@ -184,9 +212,13 @@ async fn shared_mongodb_role_does_exist() {
     let provisioner = MyProvisioner::new(
         &PG.uri,
         &MONGODB.uri,
+        &REDIS.uri,
+        &QDRANT.uri,
         "fqdn".to_string(),
         "pg".to_string(),
         "mongodb".to_string(),
+        "redis".to_string(),
+        "qdrant".to_string(),
     )
     .await
     .unwrap();
@@ -234,3 +266,170 @@ async fn shared_mongodb_role_does_exist() {
     let user_cycled_key = &user["credentials"]["SCRAM-SHA-256"]["storedKey"];
     assert_ne!(user_stored_key, user_cycled_key);
 }
+
+#[tokio::test]
+async fn shared_redis_user_does_not_exist() {
+    let provisioner = MyProvisioner::new(
+        &PG.uri,
+        &MONGODB.uri,
+        &REDIS.uri,
+        &QDRANT.uri,
+        "fqdn".to_string(),
+        "pg".to_string(),
+        "mongodb".to_string(),
+        "redis".to_string(),
+        "qdrant".to_string(),
+    )
+    .await
+    .unwrap();
+
+    assert!(!exec_redis(&["ACL", "USERS"]).contains("user-not_exist"));
+
+    provisioner
+        .request_shared_db("not_exist", shared::Engine::Redis(String::new()))
+        .await
+        .unwrap();
+
+    assert!(exec_redis(&["ACL", "USERS"]).contains("user-not_exist"));
+}
+
+#[tokio::test]
+async fn shared_redis_user_does_exist() {
+    let provisioner = MyProvisioner::new(
+        &PG.uri,
+        &MONGODB.uri,
+        &REDIS.uri,
+        &QDRANT.uri,
+        "fqdn".to_string(),
+        "pg".to_string(),
+        "mongodb".to_string(),
+        "redis".to_string(),
+        "qdrant".to_string(),
+    )
+    .await
+    .unwrap();
+
+    exec_redis(&["ACL", "SETUSER", "user-exist", "on", ">temp"]);
+    let user = exec_redis(&["ACL", "GETUSER", "user-exist"]);
+
+    provisioner
+        .request_shared_db("exist", shared::Engine::Redis(String::new()))
+        .await
+        .unwrap();
+
+    // Make sure password got cycled
+    assert_ne!(exec_redis(&["ACL", "GETUSER", "user-exist"]), user);
+}
+
+#[tokio::test]
+async fn shared_redis_keys_are_scoped() {
+    let provisioner = MyProvisioner::new(
+        &PG.uri,
+        &MONGODB.uri,
+        &REDIS.uri,
+        &QDRANT.uri,
+        "fqdn".to_string(),
+        "pg".to_string(),
+        "mongodb".to_string(),
+        "redis".to_string(),
+        "qdrant".to_string(),
+    )
+    .await
+    .unwrap();
+
+    let response = provisioner
+        .request_shared_db("scoped", shared::Engine::Redis(String::new()))
+        .await
+        .unwrap();
+
+    let port = REDIS.uri.rsplit(':').next().unwrap();
+    let client = redis::Client::open(format!(
+        "redis://{}:{}@localhost:{port}/0",
+        response.username, response.password
+    ))
+    .unwrap();
+    let mut conn = client.get_async_connection().await.unwrap();
+
+    let own: redis::RedisResult<()> = redis::cmd("SET")
+        .arg("scoped:key")
+        .arg("value")
+        .query_async(&mut conn)
+        .await;
+    assert!(own.is_ok());
+
+    let other: redis::RedisResult<()> = redis::cmd("SET")
+        .arg("other:key")
+        .arg("value")
+        .query_async(&mut conn)
+        .await;
+    assert!(other.is_err());
+
+    let flush: redis::RedisResult<()> = redis::cmd("FLUSHALL").query_async(&mut conn).await;
+    assert!(flush.is_err());
+
+    provisioner
+        .delete_shared_db("scoped", shared::Engine::Redis(String::new()))
+        .await
+        .unwrap();
+
+    assert_eq!(exec_redis(&["EXISTS", "scoped:key"]), "0");
+    assert!(!exec_redis(&["ACL", "USERS"]).contains("user-scoped"));
+}
+
+#[tokio::test]
+async fn shared_qdrant_key_is_scoped() {
+    let provisioner = MyProvisioner::new(
+        &PG.uri,
+        &MONGODB.uri,
+        &REDIS.uri,
+        &QDRANT.uri,
+        "fqdn".to_string(),
+        "pg".to_string(),
+        "mongodb".to_string(),
+        "redis".to_string(),
+        "qdrant".to_string(),
+    )
+    .await
+    .unwrap();
+
+    let config = QdrantConfig {
+        vector_size: 4,
+        distance: "Cosine".to_string(),
+    };
+    let response = provisioner
+        .request_shared_db("scoped", shared::Engine::Qdrant(config.clone()))
+        .await
+        .unwrap();
+    provisioner
+        .request_shared_db("other", shared::Engine::Qdrant(config))
+        .await
+        .unwrap();
+
+    let port = QDRANT.uri.rsplit(':').next().unwrap();
+    let client = reqwest::Client::new();
+    let get_collection = |name: &str| {
+        client
+            .get(format!("http://localhost:{port}/collections/{name}"))
+            .header("api-key", &response.password)
+            .send()
+    };
+
+    assert_eq!(
+        get_collection("scoped").await.unwrap().status(),
+        reqwest::StatusCode::OK
+    );
+    assert_eq!(
+        get_collection("other").await.unwrap().status(),
+        reqwest::StatusCode::FORBIDDEN
+    );
+
+    provisioner
+        .delete_shared_db("scoped", shared::Engine::Qdrant(Default::default()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        get_collection("scoped").await.unwrap().status(),
+        reqwest::StatusCode::NOT_FOUND
+    );
+}
@@ -0,0 +1,6 @@
+#![doc = include_str!("../README.md")]
+
+#[cfg(feature = "mongodb")]
+mod mongo;
+#[cfg(feature = "mongodb")]
+pub use mongo::MongoDb;
@@ -1,16 +1,19 @@
 use async_trait::async_trait;
-use bincode::{deserialize_from, serialize_into, Error as BincodeError};
+use bincode::{deserialize_from, serialize, Error as BincodeError};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use shuttle_common::project::ProjectName;
+use shuttle_common::storage_manager::{persist_path, PERSIST_QUOTA};
 use shuttle_service::Type;
 use shuttle_service::{Factory, ResourceBuilder};
 use std::fs;
 use std::fs::File;
 use std::io::BufReader;
-use std::io::BufWriter;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
+use tracing::warn;
+
+/// Extension of the files keeping the values of a persist instance
+const EXTENSION: &str = "bin";
 
 #[derive(Error, Debug)]
 pub enum PersistError {
@@ -18,52 +21,149 @@ pub enum PersistError {
     Open(std::io::Error),
     #[error("failed to create folder: {0}")]
     CreateFolder(std::io::Error),
+    #[error("failed to remove file: {0}")]
+    Remove(std::io::Error),
+    #[error("failed to list keys: {0}")]
+    List(std::io::Error),
     #[error("failed to serialize data: {0}")]
     Serialize(BincodeError),
     #[error("failed to deserialize data: {0}")]
     Deserialize(BincodeError),
+    #[error("invalid key '{0}': keys may only contain ASCII letters, digits, '-', '_' and '.'")]
+    InvalidKey(String),
+    #[error("storing this value would take up {needed} bytes, over the quota of {quota} bytes")]
+    QuotaExceeded { needed: u64, quota: u64 },
 }
 
 #[derive(Serialize)]
 pub struct Persist;
 
+/// A key-value store which keeps its values in the storage of the service, so that they outlive
+/// its deployments. Each environment of a service has its own values.
 #[derive(Deserialize, Serialize, Clone)]
 pub struct PersistInstance {
-    service_name: ProjectName,
+    path: PathBuf,
+    quota: u64,
 }
 
 impl PersistInstance {
-    pub fn save<T: Serialize>(&self, key: &str, struc: T) -> Result<(), PersistError> {
-        let storage_folder = self.get_storage_folder();
-        fs::create_dir_all(storage_folder).map_err(PersistError::CreateFolder)?;
+    /// Store `value` under `key`, replacing the value it had
+    pub fn set<T: Serialize>(&self, key: &str, value: T) -> Result<(), PersistError> {
+        let file_path = self.get_storage_file(key)?;
+        let bytes = serialize(&value).map_err(PersistError::Serialize)?;
+
+        let replaced = fs::metadata(&file_path).map(|m| m.len()).unwrap_or(0);
+        let needed = self.size()? - replaced + bytes.len() as u64;
+        if needed > self.quota {
+            return Err(PersistError::QuotaExceeded {
+                needed,
+                quota: self.quota,
+            });
+        }
+
+        fs::create_dir_all(&self.path).map_err(PersistError::CreateFolder)?;
 
-        let file_path = self.get_storage_file(key);
-        let file = File::create(file_path).map_err(PersistError::Open)?;
-        let mut writer = BufWriter::new(file);
-        Ok(serialize_into(&mut writer, &struc).map_err(PersistError::Serialize))?
+        // Write to the side first so that a failed write never leaves half a value behind
+        let temp_path = file_path.with_extension("tmp");
+        fs::write(&temp_path, bytes).map_err(PersistError::Open)?;
+        fs::rename(&temp_path, &file_path).map_err(PersistError::Open)
     }
 
-    pub fn load<T>(&self, key: &str) -> Result<T, PersistError>
-    where
-        T: DeserializeOwned,
-    {
-        let file_path = self.get_storage_file(key);
+    /// The value stored under `key`
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<T, PersistError> {
+        let file_path = self.get_storage_file(key)?;
         let file = File::open(file_path).map_err(PersistError::Open)?;
         let reader = BufReader::new(file);
-        Ok(deserialize_from(reader).map_err(PersistError::Deserialize))?
+        deserialize_from(reader).map_err(PersistError::Deserialize)
+    }
+
+    /// Remove the value stored under `key`
+    pub fn remove(&self, key: &str) -> Result<(), PersistError> {
+        let file_path = self.get_storage_file(key)?;
+        fs::remove_file(file_path).map_err(PersistError::Remove)
+    }
+
+    /// The keys which have a value, in order
+    pub fn list(&self) -> Result<Vec<String>, PersistError> {
+        let mut keys: Vec<_> = self.entries()?.into_iter().map(|(key, _)| key).collect();
+        keys.sort();
+
+        Ok(keys)
+    }
+
+    /// Bytes the stored values take up
+    pub fn size(&self) -> Result<u64, PersistError> {
+        Ok(self.entries()?.into_iter().map(|(_, bytes)| bytes).sum())
+    }
+
+    /// Same as [PersistInstance::set]
+    pub fn save<T: Serialize>(&self, key: &str, struc: T) -> Result<(), PersistError> {
+        self.set(key, struc)
     }
 
-    fn get_storage_folder(&self) -> PathBuf {
-        ["shuttle_persist", &self.service_name.to_string()]
-            .iter()
-            .collect()
+    /// Same as [PersistInstance::get]
+    pub fn load<T: DeserializeOwned>(&self, key: &str) -> Result<T, PersistError> {
+        self.get(key)
     }
 
-    fn get_storage_file(&self, key: &str) -> PathBuf {
-        let mut path = self.get_storage_folder();
-        path.push(format!("{key}.bin"));
+    fn entries(&self) -> Result<Vec<(String, u64)>, PersistError> {
+        let dir = match fs::read_dir(&self.path) {
+            Ok(dir) => dir,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(PersistError::List(error)),
+        };
+
+        let mut entries = Vec::new();
+        for entry in dir {
+            let entry = entry.map_err(PersistError::List)?;
+            let path = entry.path();
+
+            if path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+                continue;
+            }
+
+            if let Some(key) = path.file_stem().and_then(|s| s.to_str()) {
+                let bytes = entry.metadata().map_err(PersistError::List)?.len();
+                entries.push((key.to_string(), bytes));
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn get_storage_file(&self, key: &str) -> Result<PathBuf, PersistError> {
+        let is_valid = !key.is_empty()
+            && !key.starts_with('.')
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
 
-        path
+        if !is_valid {
+            return Err(PersistError::InvalidKey(key.to_string()));
+        }
+
+        Ok(self.path.join(format!("{key}.{EXTENSION}")))
+    }
+}
+
+/// Move the values kept by older versions of this crate, which were relative to the working
+/// directory of the service, to the storage of the service
+fn migrate(old_path: &Path, path: &Path) {
+    if !old_path.is_dir() || path.exists() {
+        return;
+    }
+
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::rename(old_path, path));
+
+    if let Err(error) = result {
+        warn!(
+            error = &error as &dyn std::error::Error,
+            "failed to move the values of the persist instance from {}",
+            old_path.display()
+        );
     }
 }
 
@@ -87,8 +187,18 @@ impl ResourceBuilder<PersistInstance> for Persist {
         self,
         factory: &mut dyn Factory,
     ) -> Result<Self::Output, shuttle_service::Error> {
+        let service_name = factory.get_service_name().to_string();
+        let path = persist_path(
+            &factory.get_storage_path()?,
+            &service_name,
+            factory.get_environment(),
+        );
+
+        migrate(&Path::new("shuttle_persist").join(&service_name), &path);
+
         Ok(PersistInstance {
-            service_name: factory.get_service_name(),
+            path,
+            quota: PERSIST_QUOTA,
         })
     }
 
@@ -100,14 +210,19 @@ impl ResourceBuilder<PersistInstance> for Persist {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use shuttle_common::project::ProjectName;
-    use std::str::FromStr;
+    use tempfile::TempDir;
+
+    fn persist(dir: &TempDir) -> PersistInstance {
+        PersistInstance {
+            path: dir.path().join("test"),
+            quota: PERSIST_QUOTA,
+        }
+    }
 
     #[test]
     fn test_save_and_load() {
-        let persist = PersistInstance {
-            service_name: ProjectName::from_str("test").unwrap(),
-        };
+        let dir = TempDir::new().unwrap();
+        let persist = persist(&dir);
 
         persist.save("test", "test").unwrap();
         let result: String = persist.load("test").unwrap();
@@ -116,9 +231,8 @@ mod tests {
 
     #[test]
     fn test_load_error() {
-        let persist = PersistInstance {
-            service_name: ProjectName::from_str("test").unwrap(),
-        };
+        let dir = TempDir::new().unwrap();
+        let persist = persist(&dir);
 
         // unwrapp error
         let result = persist.load::<String>("error").unwrap_err();
@@ -127,4 +241,55 @@ mod tests {
             "failed to open file: No such file or directory (os error 2)"
         );
     }
+
+    #[test]
+    fn test_list_and_remove() {
+        let dir = TempDir::new().unwrap();
+        let persist = persist(&dir);
+
+        assert!(persist.list().unwrap().is_empty());
+
+        persist.set("b", 2u32).unwrap();
+        persist.set("a", vec![1u8, 2, 3]).unwrap();
+        assert_eq!(persist.list().unwrap(), vec!["a", "b"]);
+
+        persist.remove("b").unwrap();
+        assert_eq!(persist.list().unwrap(), vec!["a"]);
+        assert!(persist.get::<u32>("b").is_err());
+    }
+
+    #[test]
+    fn test_invalid_key() {
+        let dir = TempDir::new().unwrap();
+        let persist = persist(&dir);
+
+        for key in ["", "../escape", "a/b", ".hidden"] {
+            assert!(matches!(
+                persist.set(key, 1u8),
+                Err(PersistError::InvalidKey(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_quota() {
+        let dir = TempDir::new().unwrap();
+        let persist = PersistInstance {
+            path: dir.path().join("test"),
+            quota: 16,
+        };
+
+        persist.set("small", [0u8; 8]).unwrap();
+        assert!(matches!(
+            persist.set("big", [0u8; 12]),
+            Err(PersistError::QuotaExceeded {
+                needed: 20,
+                quota: 16
+            })
+        ));
+
+        // Replacing a value only counts the difference
+        persist.set("small", [0u8; 16]).unwrap();
+        assert_eq!(persist.size().unwrap(), 16);
+    }
 }
@@ -0,0 +1,183 @@
+#![doc = include_str!("../README.md")]
+
+use async_trait::async_trait;
+use qdrant_client::{
+    client::QdrantClient,
+    qdrant::{vectors_config::Config, CreateCollection, Distance, VectorParams, VectorsConfig},
+};
+use serde::{Deserialize, Serialize};
+use shuttle_service::{
+    database, error::CustomError, DbOutput, Error, Factory, ResourceBuilder, Type,
+};
+use url::Url;
+
+#[derive(Serialize)]
+pub struct Qdrant {
+    config: QdrantInput,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct QdrantInput {
+    vector_size: u64,
+    distance: String,
+    local_uri: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct QdrantOutput {
+    db_output: DbOutput,
+    collection: String,
+    vector_size: u64,
+    distance: String,
+}
+
+/// A collection on a Qdrant instance, and what it takes to reach it
+#[derive(Clone, Debug)]
+pub struct Collection {
+    /// The gRPC endpoint of the instance
+    pub url: String,
+    /// The key which can reach this collection only, if the instance needs a key
+    pub api_key: Option<String>,
+    /// The name of the collection, which is the name of the service
+    pub name: String,
+}
+
+impl Collection {
+    /// Get a client connected to the instance of this collection
+    pub fn client(&self) -> Result<QdrantClient, Error> {
+        let client = QdrantClient::from_url(&self.url)
+            .with_api_key(self.api_key.clone())
+            .build()?;
+
+        Ok(client)
+    }
+}
+
+/// Get a [Collection] on a shared Qdrant instance from any factory
+#[async_trait]
+impl ResourceBuilder<Collection> for Qdrant {
+    const TYPE: Type = Type::Database(database::Type::Shared(database::SharedEngine::Qdrant));
+
+    type Config = QdrantInput;
+
+    type Output = QdrantOutput;
+
+    fn new() -> Self {
+        Self {
+            config: QdrantInput {
+                vector_size: 0,
+                distance: "Cosine".to_string(),
+                local_uri: None,
+            },
+        }
+    }
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    async fn output(self, factory: &mut dyn Factory) -> Result<Self::Output, Error> {
+        let QdrantInput {
+            vector_size,
+            distance,
+            local_uri,
+        } = self.config;
+
+        if vector_size == 0 {
+            return Err(Error::Database(
+                "the `vector_size` of the qdrant collection has to be set".to_string(),
+            ));
+        }
+        if Distance::from_str_name(&distance).is_none() {
+            return Err(Error::Database(format!(
+                "`{distance}` is not a distance qdrant knows, expected one of Cosine, Euclid, Dot or Manhattan"
+            )));
+        }
+
+        let db_output = match (factory.get_environment(), local_uri) {
+            (shuttle_service::Environment::Local, Some(local_uri)) => DbOutput::Local(local_uri),
+            _ => DbOutput::Info(
+                factory
+                    .get_qdrant_collection(vector_size, &distance)
+                    .await
+                    .map_err(CustomError::new)?,
+            ),
+        };
+
+        Ok(QdrantOutput {
+            db_output,
+            collection: factory.get_service_name().to_string(),
+            vector_size,
+            distance,
+        })
+    }
+
+    async fn build(build_data: &Self::Output) -> Result<Collection, Error> {
+        let connection_string = match &build_data.db_output {
+            DbOutput::Local(local_uri) => local_uri.clone(),
+            DbOutput::Info(info) => info.connection_string_private(),
+        };
+
+        // The key is kept as the password of the connection string
+        let uri = Url::parse(&connection_string).map_err(CustomError::new)?;
+        let api_key = uri.password().filter(|key| !key.is_empty());
+        let collection = Collection {
+            url: format!(
+                "{}://{}:{}",
+                uri.scheme(),
+                uri.host_str().unwrap_or("localhost"),
+                uri.port_or_known_default().unwrap_or(6334)
+            ),
+            api_key: api_key.map(ToString::to_string),
+            name: build_data.collection.clone(),
+        };
+
+        // In production the collection is created by the provisioner, since the key of the
+        // service can't create it. The local instances have no keys, so it is created here.
+        let client = collection.client()?;
+        if !client.collection_exists(&collection.name).await? {
+            let distance =
+                Distance::from_str_name(&build_data.distance).unwrap_or(Distance::Cosine);
+
+            client
+                .create_collection(&CreateCollection {
+                    collection_name: collection.name.clone(),
+                    vectors_config: Some(VectorsConfig {
+                        config: Some(Config::Params(VectorParams {
+                            size: build_data.vector_size,
+                            distance: distance.into(),
+                            ..Default::default()
+                        })),
+                    }),
+                    ..Default::default()
+                })
+                .await?;
+        }
+
+        Ok(collection)
+    }
+}
+
+impl Qdrant {
+    /// Size of the vectors which will be stored in the collection
+    pub fn vector_size(mut self, vector_size: u64) -> Self {
+        self.config.vector_size = vector_size;
+
+        self
+    }
+
+    /// How the vectors of the collection are compared, one of `Cosine` (the default), `Euclid`,
+    /// `Dot` or `Manhattan`
+    pub fn distance(mut self, distance: &str) -> Self {
+        self.config.distance = distance.to_string();
+
+        self
+    }
+
+    /// Use a custom Qdrant instance for local runs, like `http://localhost:6334`
+    pub fn local_uri(mut self, local_uri: &str) -> Self {
+        self.config.local_uri = Some(local_uri.to_string());
+
+        self
+    }
+}
@@ -8,5 +8,10 @@ pub use mongo::MongoDb;
 #[cfg(any(feature = "postgres", feature = "postgres-rustls"))]
 mod postgres;
 
+#[cfg(feature = "redis")]
+mod redis;
+#[cfg(feature = "redis")]
+pub use crate::redis::Redis;
+
 #[cfg(any(feature = "postgres", feature = "postgres-rustls"))]
 pub use postgres::Postgres;
@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use shuttle_service::{
+    database, error::CustomError, DbInput, DbOutput, Error, Factory, ResourceBuilder, Type,
+};
+
+#[derive(Serialize)]
+pub struct Redis {
+    config: DbInput,
+}
+
+/// Get a `redis::Client` from any factory
+#[async_trait]
+impl ResourceBuilder<redis::Client> for Redis {
+    const TYPE: Type = Type::Database(database::Type::Shared(database::SharedEngine::Redis));
+
+    type Config = DbInput;
+
+    type Output = DbOutput;
+
+    fn new() -> Self {
+        Self {
+            config: Default::default(),
+        }
+    }
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    async fn output(self, factory: &mut dyn Factory) -> Result<Self::Output, Error> {
+        let info = match factory.get_environment() {
+            shuttle_service::Environment::Production => DbOutput::Info(
+                factory
+                    .get_db_connection(database::Type::Shared(database::SharedEngine::Redis))
+                    .await
+                    .map_err(CustomError::new)?,
+            ),
+            shuttle_service::Environment::Local => {
+                if let Some(local_uri) = self.config.local_uri {
+                    DbOutput::Local(local_uri)
+                } else {
+                    DbOutput::Info(
+                        factory
+                            .get_db_connection(database::Type::Shared(
+                                database::SharedEngine::Redis,
+                            ))
+                            .await
+                            .map_err(CustomError::new)?,
+                    )
+                }
+            }
+        };
+        Ok(info)
+    }
+
+    async fn build(build_data: &Self::Output) -> Result<redis::Client, Error> {
+        let connection_string = match build_data {
+            DbOutput::Local(local_uri) => local_uri.clone(),
+            DbOutput::Info(info) => info.connection_string_private(),
+        };
+
+        let client = redis::Client::open(connection_string).map_err(CustomError::new)?;
+
+        Ok(client)
+    }
+}
+
+impl Redis {
+    /// Use a custom connection string for local runs
+    pub fn local_uri(mut self, local_uri: &str) -> Self {
+        self.config.local_uri = Some(local_uri.to_string());
+
+        self
+    }
+}
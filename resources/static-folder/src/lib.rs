@@ -181,12 +181,24 @@ mod tests {
             panic!("no static folder test should try to get a db connection string")
         }
 
+        async fn get_qdrant_collection(
+            &mut self,
+            _vector_size: u64,
+            _distance: &str,
+        ) -> Result<DatabaseReadyInfo, shuttle_service::Error> {
+            panic!("no static folder test should try to get a qdrant collection")
+        }
+
         async fn get_secrets(
             &mut self,
         ) -> Result<std::collections::BTreeMap<String, String>, shuttle_service::Error> {
             panic!("no static folder test should try to get secrets")
         }
 
+        fn get_args(&self) -> Vec<String> {
+            panic!("no static folder test should try to get the run args")
+        }
+
         fn get_service_name(&self) -> shuttle_service::ServiceName {
             panic!("no static folder test should try to get the service name")
         }
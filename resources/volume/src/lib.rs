@@ -0,0 +1,141 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use shuttle_common::storage_manager::VOLUME_DIRECTORY;
+use shuttle_service::{Factory, ResourceBuilder, Type};
+use std::{fs, path::PathBuf};
+use tracing::{error, trace};
+
+/// A directory which is kept across the deployments of a service
+#[derive(Serialize)]
+pub struct Volume;
+
+#[async_trait]
+impl ResourceBuilder<PathBuf> for Volume {
+    const TYPE: Type = Type::Volume;
+
+    type Config = ();
+
+    type Output = PathBuf;
+
+    fn new() -> Self {
+        Self {}
+    }
+
+    fn config(&self) -> &Self::Config {
+        &()
+    }
+
+    async fn output(
+        self,
+        factory: &mut dyn Factory,
+    ) -> Result<Self::Output, shuttle_service::Error> {
+        let volume = factory.get_storage_path()?.join(VOLUME_DIRECTORY);
+
+        trace!(?volume, "getting volume");
+
+        if let Err(error) = fs::create_dir_all(&volume) {
+            error!(
+                error = &error as &dyn std::error::Error,
+                "failed to create volume"
+            );
+            return Err(error)?;
+        }
+
+        Ok(volume)
+    }
+
+    async fn build(build_data: &Self::Output) -> Result<PathBuf, shuttle_service::Error> {
+        Ok(build_data.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use async_trait::async_trait;
+    use shuttle_service::{DatabaseReadyInfo, Factory, ResourceBuilder};
+    use tempfile::{Builder, TempDir};
+
+    use crate::Volume;
+
+    struct MockFactory {
+        temp_dir: TempDir,
+    }
+
+    impl MockFactory {
+        fn new() -> Self {
+            Self {
+                temp_dir: Builder::new().prefix("volume").tempdir().unwrap(),
+            }
+        }
+
+        fn storage_path(&self) -> PathBuf {
+            self.temp_dir.path().join("storage")
+        }
+    }
+
+    #[async_trait]
+    impl Factory for MockFactory {
+        async fn get_db_connection(
+            &mut self,
+            _db_type: shuttle_service::database::Type,
+        ) -> Result<DatabaseReadyInfo, shuttle_service::Error> {
+            panic!("no volume test should try to get a db connection string")
+        }
+
+        async fn get_qdrant_collection(
+            &mut self,
+            _vector_size: u64,
+            _distance: &str,
+        ) -> Result<DatabaseReadyInfo, shuttle_service::Error> {
+            panic!("no volume test should try to get a qdrant collection")
+        }
+
+        async fn get_secrets(
+            &mut self,
+        ) -> Result<std::collections::BTreeMap<String, String>, shuttle_service::Error> {
+            panic!("no volume test should try to get secrets")
+        }
+
+        fn get_args(&self) -> Vec<String> {
+            panic!("no volume test should try to get the run args")
+        }
+
+        fn get_service_name(&self) -> shuttle_service::ServiceName {
+            panic!("no volume test should try to get the service name")
+        }
+
+        fn get_environment(&self) -> shuttle_service::Environment {
+            panic!("no volume test should try to get the environment")
+        }
+
+        fn get_build_path(&self) -> Result<PathBuf, shuttle_service::Error> {
+            panic!("no volume test should try to get the build path")
+        }
+
+        fn get_storage_path(&self) -> Result<PathBuf, shuttle_service::Error> {
+            Ok(self.storage_path())
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_files() {
+        let mut factory = MockFactory::new();
+
+        let volume = Volume::new().output(&mut factory).await.unwrap();
+        assert_eq!(volume, factory.storage_path().join("shuttle-volume"));
+        assert!(volume.is_dir(), "expected the volume to be created");
+
+        fs::write(volume.join("note.txt"), "Hello, test!").unwrap();
+
+        // As when the service is deployed again
+        let volume = Volume::new().output(&mut factory).await.unwrap();
+        assert_eq!(
+            fs::read_to_string(volume.join("note.txt")).unwrap(),
+            "Hello, test!",
+            "expected the file to still be there"
+        );
+    }
+}
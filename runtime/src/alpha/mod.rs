@@ -25,8 +25,9 @@ use shuttle_proto::{
     runtime::{
         self,
         runtime_server::{Runtime, RuntimeServer},
-        LoadRequest, LoadResponse, LogItem, StartRequest, StartResponse, StopReason, StopRequest,
-        StopResponse, SubscribeLogsRequest, SubscribeStopRequest, SubscribeStopResponse,
+        LoadRequest, LoadResponse, LogItem, ShutdownRequest, ShutdownResponse, StartRequest,
+        StartResponse, StopReason, StopRequest, StopResponse, SubscribeLogsRequest,
+        SubscribeStopRequest, SubscribeStopResponse,
     },
 };
 use shuttle_service::{Environment, Factory, Service, ServiceName};
@@ -35,6 +36,7 @@ use tokio::sync::{
     broadcast::Sender,
     mpsc::{self, UnboundedReceiver, UnboundedSender},
 };
+use tokio::time::timeout;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{
     transport::{Endpoint, Server},
@@ -43,7 +45,11 @@ use tonic::{
 use tower::ServiceBuilder;
 use tracing::{error, info, trace, warn};
 
-use crate::{provisioner_factory::ProvisionerFactory, Logger, ResourceTracker};
+use crate::{
+    provisioner_factory::ProvisionerFactory,
+    shutdown::{self, Stop},
+    Logger, ResourceTracker,
+};
 
 use self::args::Args;
 
@@ -96,7 +102,7 @@ pub struct Alpha<L, S> {
     logs_tx: UnboundedSender<LogItem>,
     stopped_tx: Sender<(StopReason, String)>,
     provisioner_address: Endpoint,
-    kill_tx: Mutex<Option<oneshot::Sender<String>>>,
+    kill_tx: Mutex<Option<oneshot::Sender<Stop>>>,
     storage_manager: Arc<dyn StorageManager>,
     loader: Mutex<Option<L>>,
     service: Mutex<Option<S>>,
@@ -125,6 +131,31 @@ impl<L, S> Alpha<L, S> {
             env,
         }
     }
+
+    /// Tell the task running the started service to stop it, once it had `drain_timeout` to finish
+    /// its work in flight, and get whether it did. This is `None` when no service was started.
+    fn stop_service(
+        &self,
+        drain_timeout: Duration,
+    ) -> Result<Option<oneshot::Receiver<bool>>, Status> {
+        let Some(kill_tx) = self.kill_tx.lock().unwrap().deref_mut().take() else {
+            return Ok(None);
+        };
+
+        let (drained, drained_rx) = oneshot::channel();
+        if kill_tx
+            .send(Stop {
+                drain_timeout,
+                drained,
+            })
+            .is_err()
+        {
+            error!("the receiver dropped");
+            return Err(Status::internal("failed to stop deployment"));
+        }
+
+        Ok(Some(drained_rx))
+    }
 }
 
 #[async_trait]
@@ -176,6 +207,7 @@ where
             resources,
             secrets,
             service_name,
+            args,
         } = request.into_inner();
         trace!(path, "loading alpha project");
 
@@ -209,6 +241,7 @@ where
             provisioner_client,
             service_name,
             secrets,
+            args,
             self.storage_manager.clone(),
             self.env,
             claim,
@@ -353,16 +386,27 @@ where
                     }
                 },
                 message = kill_rx => {
-                    match message {
-                        Ok(_) => {
-                            stopped_tx.send((StopReason::Request, String::new())).unwrap();
-                        }
-                        Err(_) => trace!("the sender dropped")
+                    let Ok(Stop { drain_timeout, drained }) = message else {
+                        trace!("the sender dropped");
+                        background.abort();
+                        return;
                     };
 
-                    info!("will now abort the service");
-                    background.abort();
-                    background.await.unwrap().expect("to stop service");
+                    // The service gets to finish its work in flight, after which it returns from bind
+                    info!(?drain_timeout, "asking the service to shut down");
+                    shutdown::signal();
+
+                    let finished = timeout(drain_timeout, &mut background).await.is_ok();
+                    if finished {
+                        info!("service shut down");
+                    } else {
+                        info!("will now abort the service");
+                        background.abort();
+                        let _ = background.await;
+                    }
+
+                    stopped_tx.send((StopReason::Request, String::new())).unwrap();
+                    let _ = drained.send(finished);
                 }
             }
         });
@@ -373,14 +417,7 @@ where
     }
 
     async fn stop(&self, _request: Request<StopRequest>) -> Result<Response<StopResponse>, Status> {
-        let kill_tx = self.kill_tx.lock().unwrap().deref_mut().take();
-
-        if let Some(kill_tx) = kill_tx {
-            if kill_tx.send("stopping deployment".to_owned()).is_err() {
-                error!("the receiver dropped");
-                return Err(Status::internal("failed to stop deployment"));
-            }
-
+        if self.stop_service(Duration::ZERO)?.is_some() {
             Ok(Response::new(StopResponse { success: true }))
         } else {
             warn!("failed to stop deployment");
@@ -389,6 +426,27 @@ where
         }
     }
 
+    async fn shutdown(
+        &self,
+        request: Request<ShutdownRequest>,
+    ) -> Result<Response<ShutdownResponse>, Status> {
+        let ShutdownRequest { drain_timeout } = request.into_inner();
+
+        let Some(drained) = self.stop_service(Duration::from_secs(drain_timeout))? else {
+            warn!("trying to shut down a service that was not started");
+
+            return Ok(Response::new(ShutdownResponse {
+                success: false,
+                drained: false,
+            }));
+        };
+
+        Ok(Response::new(ShutdownResponse {
+            success: true,
+            drained: drained.await.unwrap_or_default(),
+        }))
+    }
+
     type SubscribeStopStream = ReceiverStream<Result<SubscribeStopResponse, Status>>;
 
     async fn subscribe_stop(
@@ -170,6 +170,152 @@
 //! $ cargo shuttle deploy --name=$PROJECT_NAME
 //! ```
 //!
+//! ##### Choose the binary to run
+//!
+//! When your crate has several binaries, the one with the same name as the crate is run. To run another one, add
+//! a `bin` entry in the `Shuttle.toml` or set `default-run` in the `Cargo.toml`:
+//!
+//! ```toml
+//! bin = "api"
+//! ```
+//!
+//! ##### Use a custom build profile
+//!
+//! Services are built with the `release` profile when deploying and the `dev` profile when running locally.
+//! To build with one of your own [profiles](https://doc.rust-lang.org/cargo/reference/profiles.html#custom-profiles) instead, add a `build.profile` entry in the `Shuttle.toml`:
+//!
+//! ```toml
+//! [build]
+//! profile = "shuttle"
+//! ```
+//!
+//! The profile has to be defined in the `Cargo.toml` at the root of your workspace:
+//!
+//! ```toml
+//! [profile.shuttle]
+//! inherits = "release"
+//! lto = "thin"
+//! strip = true
+//! ```
+//!
+//! ##### Reproducible builds
+//!
+//! To make sure a deployment is built with exactly the dependencies in your `Cargo.lock`, pass `--locked` to
+//! `cargo shuttle deploy` or `cargo shuttle run`. Use `--offline` to build without accessing the network. Both
+//! can also be set in the `Shuttle.toml`:
+//!
+//! ```toml
+//! [build]
+//! locked = true
+//! offline = true
+//! ```
+//!
+//! Deployers can be set up to build in containers with quotas, where build scripts and procedural macros cannot
+//! reach the network. Your dependencies are then downloaded before the build. When the build itself needs the
+//! network, say so with `offline = false` in the `[build]` table.
+//!
+//! ##### Set environment variables for the build
+//!
+//! Build scripts and hooks do not see the environment of the deployer, only what is needed to run a build. When they
+//! need more, like `SQLX_OFFLINE` for [sqlx](https://docs.rs/sqlx), add a `[build.env]` table to the `Shuttle.toml`:
+//!
+//! ```toml
+//! [build.env]
+//! SQLX_OFFLINE = "true"
+//! ```
+//!
+//! ##### Run commands before and after the build
+//!
+//! To generate code or assets as part of a build, add the commands to run to a `[hooks]` table in the `Shuttle.toml`.
+//! They are run with a shell from the root of your workspace, and the build fails if one of them fails:
+//!
+//! ```toml
+//! [hooks]
+//! pre_build = "cargo sqlx prepare --check"
+//! post_build = "npm run build --prefix frontend"
+//! ```
+//!
+//! ##### Ship static assets
+//!
+//! Files your service reads at runtime, like templates or a frontend, can be shipped along with it by listing
+//! globs for them in the `Shuttle.toml` next to its `Cargo.toml`:
+//!
+//! ```toml
+//! assets = ["static/**", "templates/**"]
+//! ```
+//!
+//! A deployed service is started from a directory holding these files, so they can be opened at the same
+//! relative paths as in your project, like `static/index.html`.
+//!
+//! ##### Pin the Rust toolchain
+//!
+//! Services are built with the toolchain pinned by a [`rust-toolchain.toml`](https://rust-lang.github.io/rustup/overrides.html#the-toolchain-file)
+//! at the root of your workspace, if there is one. The build fails early when that toolchain, or the `wasm32-wasi`
+//! target it needs for shuttle-next services, is not installed.
+//!
+//! ##### Optimize shuttle-next modules
+//!
+//! The wasm modules of shuttle-next services can be made smaller after they are built with a `[wasm]` table
+//! in the `Shuttle.toml`. `optimize` runs [`wasm-opt`](https://github.com/WebAssembly/binaryen) at the given
+//! level when it is installed, and `strip` removes debug info and other custom sections:
+//!
+//! ```toml
+//! [wasm]
+//! optimize = "Oz"
+//! strip = true
+//! ```
+//!
+//! ##### Health checks
+//!
+//! A deployed service is ready once it accepts connections. To have it checked by an endpoint of its own instead,
+//! add a `[health]` table to the `Shuttle.toml`. The deployer then waits for the endpoint to answer with a success
+//! status before the deployment counts as healthy, probes it every `interval` seconds after that, and restarts the
+//! service when `failure_threshold` probes in a row fail:
+//!
+//! ```toml
+//! [health]
+//! path = "/health"
+//! interval = 10
+//! failure_threshold = 3
+//! readiness_timeout = 30
+//! ```
+//!
+//! A service which does not become ready within `readiness_timeout` seconds crashes. The latest probe results are
+//! shown by `cargo shuttle deployment status`.
+//!
+//! ##### Restart policy
+//!
+//! A deployed service which crashes is started again, after waiting 1 second, then 2, 4, 8 and 16 seconds on the
+//! crashes after. A service which crashes more than 5 times within 10 minutes is crash looping, and is left crashed
+//! with the last lines it wrote to stderr in its logs. To also start it again after it finished without an error, or
+//! to never start it again, set `restart` in the `Shuttle.toml` to `always` or `never`. The default is `on-failure`:
+//!
+//! ```toml
+//! restart = "always"
+//! ```
+//!
+//! ##### Pass arguments to the service
+//!
+//! To configure a single binary differently in each environment of a project, list the arguments to start it with in
+//! a `[run]` table of the `Shuttle.toml`, with the arguments to use instead in some environments:
+//!
+//! ```toml
+//! [run]
+//! args = ["--mode", "production"]
+//!
+//! [run.environments]
+//! staging = ["--mode", "staging"]
+//! ```
+//!
+//! The `#[shuttle_runtime::main]` function gets them with the `RunArgs` resource:
+//!
+//! ```rust,ignore
+//! #[shuttle_runtime::main]
+//! async fn axum(#[shuttle_runtime::RunArgs] args: Vec<String>) -> shuttle_axum::ShuttleAxum {
+//!     // Parse `args` with clap, for example...
+//! }
+//! ```
+//!
 //! ##### Using Podman instead of Docker
 //! If you are using [Podman](https://podman.io/) instead of Docker, then `cargo shuttle run` will give
 //! `got unexpected error while inspecting docker container: error trying to connect: No such file or directory` error.
@@ -221,6 +367,8 @@ mod logger;
 mod next;
 mod provisioner_factory;
 mod resource_tracker;
+mod run_args;
+mod shutdown;
 
 pub use alpha::{start, Alpha};
 pub use async_trait::async_trait;
@@ -229,6 +377,8 @@ pub use logger::Logger;
 pub use next::{AxumWasm, NextArgs};
 pub use provisioner_factory::ProvisionerFactory;
 pub use resource_tracker::{get_resource, ResourceTracker};
+pub use run_args::RunArgs;
+pub use shutdown::shutdown_signal;
 pub use shuttle_common::storage_manager::StorageManager;
 pub use shuttle_service::{main, CustomError, Error, Factory, ResourceBuilder, Service};
 
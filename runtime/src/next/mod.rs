@@ -6,6 +6,7 @@ use std::os::unix::prelude::RawFd;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Mutex;
+use std::time::Duration;
 
 use anyhow::Context;
 use async_trait::async_trait;
@@ -17,11 +18,13 @@ use hyper::{Body, Request, Response};
 use shuttle_common::wasm::{Bytesable, Log, RequestWrapper, ResponseWrapper};
 use shuttle_proto::runtime::runtime_server::Runtime;
 use shuttle_proto::runtime::{
-    self, LoadRequest, LoadResponse, StartRequest, StartResponse, StopReason, StopRequest,
-    StopResponse, SubscribeLogsRequest, SubscribeStopRequest, SubscribeStopResponse,
+    self, LoadRequest, LoadResponse, ShutdownRequest, ShutdownResponse, StartRequest,
+    StartResponse, StopReason, StopRequest, StopResponse, SubscribeLogsRequest,
+    SubscribeStopRequest, SubscribeStopResponse,
 };
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::time::timeout;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::Status;
 use tracing::{error, trace, warn};
@@ -30,6 +33,8 @@ use wasmtime::{Engine, Linker, Module, Store};
 use wasmtime_wasi::sync::net::UnixStream as WasiUnixStream;
 use wasmtime_wasi::{WasiCtx, WasiCtxBuilder};
 
+use crate::shutdown::Stop;
+
 mod args;
 
 pub use self::args::NextArgs;
@@ -44,7 +49,7 @@ pub struct AxumWasm {
     router: Mutex<Option<Router>>,
     logs_rx: Mutex<Option<Receiver<Result<runtime::LogItem, Status>>>>,
     logs_tx: Sender<Result<runtime::LogItem, Status>>,
-    kill_tx: Mutex<Option<oneshot::Sender<String>>>,
+    kill_tx: Mutex<Option<oneshot::Sender<Stop>>>,
     stopped_tx: broadcast::Sender<(StopReason, String)>,
 }
 
@@ -68,6 +73,31 @@ impl AxumWasm {
             stopped_tx,
         }
     }
+
+    /// Tell the server of the started service to stop, once it had `drain_timeout` to finish the
+    /// requests in flight, and get whether it did. This is `None` when no service was started.
+    fn stop_service(
+        &self,
+        drain_timeout: Duration,
+    ) -> Result<Option<oneshot::Receiver<bool>>, Status> {
+        let Some(kill_tx) = self.kill_tx.lock().unwrap().deref_mut().take() else {
+            return Ok(None);
+        };
+
+        let (drained, drained_rx) = oneshot::channel();
+        if kill_tx
+            .send(Stop {
+                drain_timeout,
+                drained,
+            })
+            .is_err()
+        {
+            error!("the receiver dropped");
+            return Err(Status::internal("failed to stop deployment"));
+        }
+
+        Ok(Some(drained_rx))
+    }
 }
 
 impl Default for AxumWasm {
@@ -158,14 +188,7 @@ impl Runtime for AxumWasm {
     ) -> Result<tonic::Response<StopResponse>, Status> {
         let _request = request.into_inner();
 
-        let kill_tx = self.kill_tx.lock().unwrap().deref_mut().take();
-
-        if let Some(kill_tx) = kill_tx {
-            if kill_tx.send("stopping deployment".to_owned()).is_err() {
-                error!("the receiver dropped");
-                return Err(Status::internal("failed to stop deployment"));
-            }
-
+        if self.stop_service(Duration::ZERO)?.is_some() {
             Ok(tonic::Response::new(StopResponse { success: true }))
         } else {
             warn!("trying to stop a service that was not started");
@@ -174,6 +197,27 @@ impl Runtime for AxumWasm {
         }
     }
 
+    async fn shutdown(
+        &self,
+        request: tonic::Request<ShutdownRequest>,
+    ) -> Result<tonic::Response<ShutdownResponse>, Status> {
+        let ShutdownRequest { drain_timeout } = request.into_inner();
+
+        let Some(drained) = self.stop_service(Duration::from_secs(drain_timeout))? else {
+            warn!("trying to shut down a service that was not started");
+
+            return Ok(tonic::Response::new(ShutdownResponse {
+                success: false,
+                drained: false,
+            }));
+        };
+
+        Ok(tonic::Response::new(ShutdownResponse {
+            success: true,
+            drained: drained.await.unwrap_or_default(),
+        }))
+    }
+
     type SubscribeStopStream = ReceiverStream<Result<SubscribeStopResponse, Status>>;
 
     async fn subscribe_stop(
@@ -374,7 +418,7 @@ async fn run_until_stopped(
     router: Router,
     address: SocketAddr,
     logs_tx: Sender<Result<runtime::LogItem, Status>>,
-    kill_rx: tokio::sync::oneshot::Receiver<String>,
+    kill_rx: tokio::sync::oneshot::Receiver<Stop>,
     stopped_tx: broadcast::Sender<(StopReason, String)>,
 ) {
     let make_service = make_service_fn(move |_conn| {
@@ -400,20 +444,32 @@ async fn run_until_stopped(
         }
     });
 
-    let server = hyper::Server::bind(&address).serve(make_service);
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = hyper::Server::bind(&address)
+        .serve(make_service)
+        .with_graceful_shutdown(async {
+            let _ = shutdown_rx.await;
+        });
+    tokio::pin!(server);
 
     trace!("starting hyper server on: {}", &address);
     tokio::select! {
-        _ = server => {
+        _ = &mut server => {
             stopped_tx.send((StopReason::End, String::new())).unwrap();
             trace!("axum wasm server stopped");
         },
         message = kill_rx => {
             match message {
-                Ok(msg) =>{
+                Ok(Stop { drain_timeout, drained }) => {
+                    // The server stops taking new connections, and gets to finish the requests in
+                    // flight
+                    let _ = shutdown_tx.send(());
+                    let finished = timeout(drain_timeout, &mut server).await.is_ok();
+
                     stopped_tx.send((StopReason::Request, String::new())).unwrap();
-                    trace!("{msg}")
-                } ,
+                    trace!(finished, "stopping deployment");
+                    let _ = drained.send(finished);
+                },
                 Err(_) => {
                     stopped_tx
                         .send((StopReason::Crash, "the kill sender dropped".to_string()))
@@ -7,7 +7,10 @@ use shuttle_common::{
     storage_manager::StorageManager,
     DatabaseReadyInfo,
 };
-use shuttle_proto::provisioner::{provisioner_client::ProvisionerClient, DatabaseRequest};
+use shuttle_proto::provisioner::{
+    database_request::DbType, provisioner_client::ProvisionerClient, shared, DatabaseRequest,
+    QdrantConfig, Shared,
+};
 use shuttle_service::{Environment, Factory, ServiceName};
 use tonic::{transport::Channel, Request};
 use tracing::info;
@@ -18,6 +21,7 @@ pub struct ProvisionerFactory {
     storage_manager: Arc<dyn StorageManager>,
     provisioner_client: ProvisionerClient<ClaimService<InjectPropagation<Channel>>>,
     secrets: BTreeMap<String, String>,
+    args: Vec<String>,
     env: Environment,
     claim: Option<Claim>,
 }
@@ -27,6 +31,7 @@ impl ProvisionerFactory {
         provisioner_client: ProvisionerClient<ClaimService<InjectPropagation<Channel>>>,
         service_name: ServiceName,
         secrets: BTreeMap<String, String>,
+        args: Vec<String>,
         storage_manager: Arc<dyn StorageManager>,
         env: Environment,
         claim: Option<Claim>,
@@ -36,23 +41,21 @@ impl ProvisionerFactory {
             service_name,
             storage_manager,
             secrets,
+            args,
             env,
             claim,
         }
     }
 }
 
-#[async_trait]
-impl Factory for ProvisionerFactory {
-    async fn get_db_connection(
+impl ProvisionerFactory {
+    async fn provision(
         &mut self,
-        db_type: database::Type,
+        db_type: DbType,
     ) -> Result<DatabaseReadyInfo, shuttle_service::Error> {
-        info!("Provisioning a {db_type}. This can take a while...");
-
         let mut request = Request::new(DatabaseRequest {
             project_name: self.service_name.to_string(),
-            db_type: Some(db_type.clone().into()),
+            db_type: Some(db_type),
         });
 
         if let Some(claim) = &self.claim {
@@ -66,17 +69,54 @@ impl Factory for ProvisionerFactory {
             .map_err(shuttle_service::error::CustomError::new)?
             .into_inner();
 
-        let info: DatabaseReadyInfo = response.into();
+        Ok(response.into())
+    }
+}
+
+#[async_trait]
+impl Factory for ProvisionerFactory {
+    async fn get_db_connection(
+        &mut self,
+        db_type: database::Type,
+    ) -> Result<DatabaseReadyInfo, shuttle_service::Error> {
+        info!("Provisioning a {db_type}. This can take a while...");
+
+        let info = self.provision(db_type.into()).await?;
 
         info!("Done provisioning database");
 
         Ok(info)
     }
 
+    async fn get_qdrant_collection(
+        &mut self,
+        vector_size: u64,
+        distance: &str,
+    ) -> Result<DatabaseReadyInfo, shuttle_service::Error> {
+        info!("Provisioning a Qdrant collection");
+
+        let info = self
+            .provision(DbType::Shared(Shared {
+                engine: Some(shared::Engine::Qdrant(QdrantConfig {
+                    vector_size,
+                    distance: distance.to_string(),
+                })),
+            }))
+            .await?;
+
+        info!("Done provisioning Qdrant collection");
+
+        Ok(info)
+    }
+
     async fn get_secrets(&mut self) -> Result<BTreeMap<String, String>, shuttle_service::Error> {
         Ok(self.secrets.clone())
     }
 
+    fn get_args(&self) -> Vec<String> {
+        self.args.clone()
+    }
+
     fn get_service_name(&self) -> ServiceName {
         self.service_name.clone()
     }
@@ -0,0 +1,35 @@
+use async_trait::async_trait;
+use shuttle_common::resource::Type;
+use shuttle_service::{Factory, ResourceBuilder};
+
+/// Get the arguments from the `[run]` table of the Shuttle.toml, for the environment the service
+/// is deployed to
+pub struct RunArgs;
+
+#[async_trait]
+impl ResourceBuilder<Vec<String>> for RunArgs {
+    const TYPE: Type = Type::RunArgs;
+
+    type Config = ();
+
+    type Output = Vec<String>;
+
+    fn new() -> Self {
+        Self
+    }
+
+    fn config(&self) -> &Self::Config {
+        &()
+    }
+
+    async fn output(
+        self,
+        factory: &mut dyn Factory,
+    ) -> Result<Self::Output, shuttle_service::Error> {
+        Ok(factory.get_args())
+    }
+
+    async fn build(build_data: &Self::Output) -> Result<Vec<String>, shuttle_service::Error> {
+        Ok(build_data.clone())
+    }
+}
@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use tokio::sync::{oneshot, watch};
+
+static SHUTDOWN: Lazy<watch::Sender<bool>> = Lazy::new(|| watch::channel(false).0);
+
+/// Wait for shuttle to ask this service to shut down, like when it is stopped or a new deployment
+/// of it takes over.
+///
+/// The service should then stop taking new work and return from [`Service::bind`][crate::Service::bind]
+/// once the work in flight is done. It is stopped all the same when that takes longer than the drain
+/// timeout of the deployer. Servers with a graceful shutdown can take this as their signal:
+///
+/// ```rust,no_run
+/// # async fn serve(router: axum::Router, addr: std::net::SocketAddr) -> hyper::Result<()> {
+/// axum::Server::bind(&addr)
+///     .serve(router.into_make_service())
+///     .with_graceful_shutdown(shuttle_runtime::shutdown_signal())
+///     .await
+/// # }
+/// ```
+pub async fn shutdown_signal() {
+    let mut shutdown = SHUTDOWN.subscribe();
+
+    while !*shutdown.borrow() {
+        if shutdown.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Let the service know it should shut down
+pub(crate) fn signal() {
+    SHUTDOWN.send_replace(true);
+}
+
+/// Asks the task running a started service to stop it
+pub(crate) struct Stop {
+    /// How long the service gets to finish its work in flight
+    pub drain_timeout: Duration,
+    /// Gets whether the service finished it in time
+    pub drained: oneshot::Sender<bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::{sleep, timeout};
+
+    use super::{shutdown_signal, signal};
+
+    #[tokio::test]
+    async fn signal_wakes_waiters() {
+        let waiter = tokio::spawn(shutdown_signal());
+
+        sleep(Duration::from_millis(100)).await;
+        assert!(!waiter.is_finished());
+
+        signal();
+        timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("the waiter to be woken")
+            .unwrap();
+
+        // Services which wait once the signal was given do not wait at all
+        timeout(Duration::from_secs(1), shutdown_signal())
+            .await
+            .expect("the signal to be kept");
+    }
+}
@@ -2,6 +2,7 @@ use std::{
     collections::HashMap,
     net::{Ipv4Addr, SocketAddr},
     path::{Path, PathBuf},
+    process::Stdio,
 };
 
 use anyhow::Result;
@@ -39,7 +40,14 @@ pub async fn spawn_runtime(project_path: String, service_name: &str) -> Result<T
     let runtime_address = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), runtime_port);
 
     let (tx, _) = crossbeam_channel::unbounded();
-    let runtimes = build_workspace(Path::new(&project_path), false, tx).await?;
+    let runtimes = build_workspace(
+        Path::new(&project_path),
+        false,
+        tx,
+        &Default::default(),
+        Default::default(),
+    )
+    .await?;
 
     let secrets: HashMap<String, String> = Default::default();
 
@@ -61,6 +69,10 @@ pub async fn spawn_runtime(project_path: String, service_name: &str) -> Result<T
         None,
         runtime_port,
         runtime_path,
+        None,
+        &Default::default(),
+        Stdio::inherit(),
+        Stdio::inherit(),
     )
     .await?;
 
@@ -20,6 +20,7 @@ async fn bind_panic() {
         service_name,
         resources: Default::default(),
         secrets,
+        args: Default::default(),
     });
 
     runtime_client.load(load_request).await.unwrap();
@@ -69,6 +70,7 @@ async fn bind_panic_owned() {
         service_name,
         resources: Default::default(),
         secrets,
+        args: Default::default(),
     });
 
     runtime_client.load(load_request).await.unwrap();
@@ -115,6 +117,7 @@ async fn loader_panic() {
         service_name,
         resources: Default::default(),
         secrets,
+        args: Default::default(),
     });
 
     let load_response = runtime_client.load(load_request).await.unwrap();
@@ -146,6 +149,7 @@ async fn loader_panic_owned() {
         service_name,
         resources: Default::default(),
         secrets,
+        args: Default::default(),
     });
 
     let load_response = runtime_client.load(load_request).await.unwrap();
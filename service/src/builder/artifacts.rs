@@ -0,0 +1,389 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use ring::digest::{Context as DigestContext, SHA256};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::process::Command;
+use tracing::{debug, trace};
+
+use super::{BuildOptions, BuiltService};
+
+/// File in an entry of the store which describes the services it holds
+const MANIFEST_FILE: &str = "artifacts.json";
+
+/// A store of built executables, keyed by everything that goes into a build: the source tree,
+/// the `Cargo.lock`, the toolchain and the build options.
+///
+/// Building the same project twice gives the same executables, so a store hit lets a redeploy of
+/// an unchanged project skip compilation entirely.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArtifactStore {
+    root: PathBuf,
+}
+
+/// Which entries [ArtifactStore::collect_garbage] keeps. Entries are ranked by when they were last
+/// stored or reused.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RetentionPolicy {
+    /// Keep at most this many entries
+    pub max_entries: Option<usize>,
+    /// Remove entries which were not used for this long
+    pub max_age: Option<Duration>,
+}
+
+/// The description of a stored service, with paths relative to the workspace root so that the
+/// service can be reused from another checkout of the same project
+#[derive(Debug, Deserialize, Serialize)]
+struct StoredService {
+    executable: String,
+    is_wasm: bool,
+    package_name: String,
+    working_directory: PathBuf,
+    manifest_path: PathBuf,
+    features: Vec<String>,
+    binary_size: u64,
+}
+
+impl ArtifactStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Compute the key of a build of the workspace at `workspace_root`.
+    ///
+    /// Returns `None` when the workspace has no `Cargo.lock`, since the dependencies could change
+    /// between builds of the same sources.
+    pub async fn key(
+        workspace_root: &Path,
+        target_dir: &Path,
+        release_mode: bool,
+        options: &BuildOptions,
+    ) -> anyhow::Result<Option<String>> {
+        if !workspace_root.join("Cargo.lock").exists() {
+            return Ok(None);
+        }
+
+        let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+        let version = Command::new(rustc)
+            .arg("-vV")
+            .current_dir(workspace_root)
+            .output()
+            .await
+            .context("failed to get the rustc version")?;
+
+        let root = workspace_root.to_path_buf();
+        let target_dir = target_dir.to_path_buf();
+        let mut context = tokio::task::spawn_blocking(move || hash_tree(&root, &target_dir))
+            .await
+            .context("failed to hash the source tree")??;
+
+        context.update(&version.stdout);
+
+        // Only the options which change what is built, not how it is built
+        let settings = format!(
+            "release={release_mode} features={:?} all_features={} no_default_features={} package={:?} target={:?} image={:?}",
+            options.features,
+            options.all_features,
+            options.no_default_features,
+            options.package,
+            options.target,
+            options.sandbox.as_ref().map(|sandbox| &sandbox.image),
+        );
+        context.update(settings.as_bytes());
+
+        Ok(Some(hex(context.finish().as_ref())))
+    }
+
+    /// Get copies of the services stored under `key`, put in `target_dir` so that they can be
+    /// moved like freshly built executables
+    pub async fn get(
+        &self,
+        key: &str,
+        workspace_root: &Path,
+        target_dir: &Path,
+    ) -> anyhow::Result<Option<Vec<BuiltService>>> {
+        let entry = self.root.join(key);
+        let manifest = match fs::read(entry.join(MANIFEST_FILE)).await {
+            Ok(manifest) => manifest,
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                trace!(key, "no stored artifacts");
+                return Ok(None);
+            }
+            Err(error) => return Err(error).context("failed to read the stored artifacts"),
+        };
+
+        let stored: Vec<StoredService> =
+            serde_json::from_slice(&manifest).context("failed to parse the stored artifacts")?;
+
+        let destination = target_dir.join("shuttle-artifacts");
+        fs::create_dir_all(&destination).await?;
+
+        let mut services = Vec::with_capacity(stored.len());
+        for service in stored {
+            let executable_path = destination.join(&service.executable);
+            fs::copy(entry.join(&service.executable), &executable_path)
+                .await
+                .context("failed to copy a stored executable")?;
+
+            services.push(BuiltService {
+                features: service.features,
+                binary_size: service.binary_size,
+                ..BuiltService::new(
+                    executable_path,
+                    service.is_wasm,
+                    service.package_name,
+                    workspace_root.join(service.working_directory),
+                    workspace_root.join(service.manifest_path),
+                )
+            });
+        }
+
+        // Writing the manifest again marks the entry as used for the retention policy
+        fs::write(entry.join(MANIFEST_FILE), manifest).await?;
+        debug!(key, "reusing stored artifacts");
+
+        Ok(Some(services))
+    }
+
+    /// Store the executables of the `services` built from the workspace at `workspace_root`
+    pub async fn put(
+        &self,
+        key: &str,
+        workspace_root: &Path,
+        services: &[BuiltService],
+    ) -> anyhow::Result<()> {
+        let entry = self.root.join(key);
+        fs::create_dir_all(&entry).await?;
+
+        let mut stored = Vec::with_capacity(services.len());
+        for (index, service) in services.iter().enumerate() {
+            let file_name = service
+                .executable_path
+                .file_name()
+                .context("built executable has no file name")?
+                .to_string_lossy();
+            let executable = format!("{index}-{file_name}");
+
+            fs::copy(&service.executable_path, entry.join(&executable))
+                .await
+                .context("failed to store a built executable")?;
+
+            stored.push(StoredService {
+                executable,
+                is_wasm: service.is_wasm,
+                package_name: service.package_name.clone(),
+                working_directory: relative_to(&service.working_directory, workspace_root)?,
+                manifest_path: relative_to(&service.manifest_path, workspace_root)?,
+                features: service.features.clone(),
+                binary_size: service.binary_size,
+            });
+        }
+
+        // The manifest is written last, so that an entry is only used once it is complete
+        fs::write(entry.join(MANIFEST_FILE), serde_json::to_vec(&stored)?).await?;
+        debug!(key, "stored artifacts");
+
+        Ok(())
+    }
+
+    /// Remove the entries the `policy` does not keep, and return their keys
+    pub async fn collect_garbage(&self, policy: &RetentionPolicy) -> std::io::Result<Vec<String>> {
+        let mut entries = Vec::new();
+        let mut dir = match fs::read_dir(&self.root).await {
+            Ok(dir) => dir,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error),
+        };
+
+        while let Some(entry) = dir.next_entry().await? {
+            // Entries without a manifest are unfinished or broken, so they are never used
+            let last_used = match fs::metadata(entry.path().join(MANIFEST_FILE)).await {
+                Ok(metadata) => metadata.modified()?,
+                Err(error) if error.kind() == ErrorKind::NotFound => SystemTime::UNIX_EPOCH,
+                Err(error) => return Err(error),
+            };
+
+            entries.push((last_used, entry.file_name().to_string_lossy().to_string()));
+        }
+
+        // Most recently used first
+        entries.sort_by(|a, b| b.cmp(a));
+
+        let now = SystemTime::now();
+        let mut removed = Vec::new();
+        for (index, (last_used, key)) in entries.into_iter().enumerate() {
+            let too_many = policy.max_entries.map_or(false, |max| index >= max);
+            let too_old = policy.max_age.map_or(false, |max_age| {
+                now.duration_since(last_used)
+                    .map_or(false, |age| age > max_age)
+            });
+
+            if too_many || too_old {
+                fs::remove_dir_all(self.root.join(&key)).await?;
+                removed.push(key);
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+/// Hash every file of the source tree at `root`, leaving out the build output and git directory
+fn hash_tree(root: &Path, target_dir: &Path) -> std::io::Result<DigestContext> {
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+
+            if path.starts_with(target_dir) || entry.file_name() == ".git" {
+                continue;
+            }
+
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    // The order of read_dir is not defined, so sort to get the same hash on every system
+    files.sort();
+
+    let mut context = DigestContext::new(&SHA256);
+    for file in files {
+        let relative = file.strip_prefix(root).unwrap_or(&file);
+        context.update(relative.to_string_lossy().as_bytes());
+        context.update(&[0]);
+        context.update(&std::fs::read(&file)?);
+        context.update(&[0]);
+    }
+
+    Ok(context)
+}
+
+fn relative_to(path: &Path, root: &Path) -> anyhow::Result<PathBuf> {
+    path.strip_prefix(root)
+        .map(Path::to_path_buf)
+        .with_context(|| format!("{} is not in the workspace", path.display()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::Duration;
+
+    use tempfile::tempdir;
+
+    use super::{hash_tree, hex, ArtifactStore, RetentionPolicy};
+    use crate::builder::BuiltService;
+
+    #[tokio::test]
+    async fn put_and_get() {
+        let dir = tempdir().unwrap();
+        let workspace_root = dir.path().join("workspace");
+        let target_dir = workspace_root.join("target");
+        fs::create_dir_all(target_dir.join("release")).unwrap();
+
+        let executable_path = target_dir.join("release/service");
+        fs::write(&executable_path, "executable").unwrap();
+
+        let built = BuiltService::new(
+            executable_path,
+            false,
+            "service".to_string(),
+            workspace_root.clone(),
+            workspace_root.join("Cargo.toml"),
+        );
+
+        let store = ArtifactStore::new(dir.path().join("store"));
+        assert!(store
+            .get("key", &workspace_root, &target_dir)
+            .await
+            .unwrap()
+            .is_none());
+
+        store
+            .put("key", &workspace_root, &[built.clone()])
+            .await
+            .unwrap();
+
+        let reused = store
+            .get("key", &workspace_root, &target_dir)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(reused.len(), 1);
+        assert_eq!(reused[0].package_name, "service");
+        assert_eq!(reused[0].working_directory, workspace_root);
+        assert_ne!(reused[0].executable_path, built.executable_path);
+        assert_eq!(
+            fs::read_to_string(&reused[0].executable_path).unwrap(),
+            "executable"
+        );
+    }
+
+    #[tokio::test]
+    async fn collect_garbage() {
+        let dir = tempdir().unwrap();
+        let store = ArtifactStore::new(dir.path());
+
+        for key in ["a", "b", "c"] {
+            fs::create_dir(dir.path().join(key)).unwrap();
+            fs::write(dir.path().join(key).join(super::MANIFEST_FILE), "[]").unwrap();
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        let removed = store
+            .collect_garbage(&RetentionPolicy {
+                max_entries: Some(2),
+                max_age: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(removed, vec!["a".to_string()]);
+        assert!(dir.path().join("b").exists());
+
+        let removed = store
+            .collect_garbage(&RetentionPolicy {
+                max_entries: None,
+                max_age: Some(Duration::ZERO),
+            })
+            .await
+            .unwrap();
+        assert_eq!(removed.len(), 2);
+    }
+
+    #[test]
+    fn tree_hash_ignores_target_dir() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+
+        let hash = || {
+            hex(hash_tree(root, &root.join("target"))
+                .unwrap()
+                .finish()
+                .as_ref())
+        };
+        let before = hash();
+
+        fs::write(root.join("target/artifact"), "").unwrap();
+        assert_eq!(hash(), before);
+
+        fs::write(root.join("src/main.rs"), "fn main() { println!() }").unwrap();
+        assert_ne!(hash(), before);
+    }
+}
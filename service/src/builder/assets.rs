@@ -0,0 +1,85 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use glob::Pattern;
+use shuttle_common::shuttle_toml::ShuttleToml;
+
+/// Find the files matched by the `assets` globs in the Shuttle.toml of the service in
+/// `working_directory`.
+///
+/// The paths are relative to `working_directory`, so that the files can be put in the same place
+/// next to the deployed service. A directory which is matched, like by `static/**`, brings all the
+/// files in it.
+pub(crate) fn resolve_assets(working_directory: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    // Assets outside of the service's directory are rejected when Shuttle.toml is validated
+    let patterns = ShuttleToml::from_path(&working_directory.join("Shuttle.toml"))?.assets;
+
+    let root = Pattern::escape(&working_directory.display().to_string());
+    let mut assets = Vec::new();
+
+    for pattern in patterns {
+        let paths = glob::glob(&format!("{root}/{pattern}"))
+            .with_context(|| format!("`{pattern}` in Shuttle.toml is not a valid glob"))?;
+
+        for path in paths {
+            let path = path.context("failed to read an asset")?;
+
+            if path.is_dir() {
+                let dir = Pattern::escape(&path.display().to_string());
+                for path in glob::glob(&format!("{dir}/**/*"))? {
+                    let path = path.context("failed to read an asset")?;
+
+                    if path.is_file() {
+                        assets.push(path.strip_prefix(working_directory)?.to_path_buf());
+                    }
+                }
+            } else if path.is_file() {
+                assets.push(path.strip_prefix(working_directory)?.to_path_buf());
+            }
+        }
+    }
+
+    assets.sort();
+    assets.dedup();
+
+    Ok(assets)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use tempfile::tempdir;
+
+    use super::resolve_assets;
+
+    #[test]
+    fn resolve() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+
+        assert!(resolve_assets(root).unwrap().is_empty());
+
+        fs::create_dir_all(root.join("static/css")).unwrap();
+        fs::write(root.join("static/index.html"), "").unwrap();
+        fs::write(root.join("static/css/main.css"), "").unwrap();
+        fs::write(root.join("README.md"), "").unwrap();
+        fs::write(
+            root.join("Shuttle.toml"),
+            r#"assets = ["static/**", "static/index.html"]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            resolve_assets(root).unwrap(),
+            vec![
+                PathBuf::from("static/css/main.css"),
+                PathBuf::from("static/index.html"),
+            ]
+        );
+
+        fs::write(root.join("Shuttle.toml"), r#"assets = ["../secrets/**"]"#).unwrap();
+        assert!(resolve_assets(root).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::Context;
+use serde::Deserialize;
+use tokio::process::Command;
+use tracing::trace;
+
+/// What to do with services that depend on crates with known vulnerabilities
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AuditPolicy {
+    /// Do not check the dependencies
+    #[default]
+    Off,
+    /// Report vulnerable dependencies, but build anyway
+    Warn,
+    /// Refuse to build services with vulnerable dependencies, or when they cannot be checked
+    Deny,
+}
+
+impl FromStr for AuditPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "warn" => Ok(Self::Warn),
+            "deny" => Ok(Self::Deny),
+            other => Err(format!(
+                "`{other}` is not an audit policy, expected one of `off`, `warn` or `deny`"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AuditPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Off => write!(f, "off"),
+            Self::Warn => write!(f, "warn"),
+            Self::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+/// The vulnerabilities [RustSec](https://rustsec.org/) knows about in the dependencies of a workspace
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AuditReport {
+    pub vulnerabilities: Vec<Vulnerability>,
+}
+
+/// A dependency affected by a RustSec advisory
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Vulnerability {
+    /// The id of the advisory, like `RUSTSEC-2023-0001`
+    pub id: String,
+    pub package: String,
+    pub version: String,
+    pub title: String,
+}
+
+impl fmt::Display for Vulnerability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}: {} ({})",
+            self.package, self.version, self.title, self.id
+        )
+    }
+}
+
+/// The parts of the output of `cargo audit --json` we care about
+#[derive(Deserialize)]
+struct CargoAuditOutput {
+    vulnerabilities: CargoAuditVulnerabilities,
+}
+
+#[derive(Deserialize)]
+struct CargoAuditVulnerabilities {
+    list: Vec<CargoAuditVulnerability>,
+}
+
+#[derive(Deserialize)]
+struct CargoAuditVulnerability {
+    advisory: CargoAuditAdvisory,
+    package: CargoAuditPackage,
+}
+
+#[derive(Deserialize)]
+struct CargoAuditAdvisory {
+    id: String,
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct CargoAuditPackage {
+    name: String,
+    version: String,
+}
+
+impl AuditReport {
+    /// Check the `Cargo.lock` of the workspace at `workspace_root` with
+    /// [cargo-audit](https://crates.io/crates/cargo-audit), which has to be installed.
+    pub async fn run(workspace_root: &Path) -> anyhow::Result<Self> {
+        let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+        let mut command = Command::new(cargo);
+        command
+            .args(["audit", "--json", "--file"])
+            .arg(workspace_root.join("Cargo.lock"))
+            .current_dir(workspace_root);
+
+        trace!(?command, "running cargo audit");
+        let output = command
+            .output()
+            .await
+            .context("failed to run cargo audit")?;
+
+        // cargo audit exits with an error when it finds vulnerabilities, so only a missing report
+        // means it failed
+        Self::parse(&output.stdout).with_context(|| {
+            format!(
+                "cargo audit failed, is it installed? {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+        })
+    }
+
+    fn parse(output: &[u8]) -> anyhow::Result<Self> {
+        let output: CargoAuditOutput = serde_json::from_slice(output)?;
+
+        let vulnerabilities = output
+            .vulnerabilities
+            .list
+            .into_iter()
+            .map(|vulnerability| Vulnerability {
+                id: vulnerability.advisory.id,
+                package: vulnerability.package.name,
+                version: vulnerability.package.version,
+                title: vulnerability.advisory.title,
+            })
+            .collect();
+
+        Ok(Self { vulnerabilities })
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.vulnerabilities.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuditReport, Vulnerability};
+
+    #[test]
+    fn parse() {
+        let output = br#"{
+            "database": {"advisory-count": 560},
+            "lockfile": {"dependency-count": 190},
+            "settings": {},
+            "vulnerabilities": {
+                "found": true,
+                "count": 1,
+                "list": [{
+                    "advisory": {
+                        "id": "RUSTSEC-2020-0071",
+                        "package": "time",
+                        "title": "Potential segfault in the time crate",
+                        "url": "https://github.com/time-rs/time/issues/293"
+                    },
+                    "versions": {"patched": [">=0.2.23"], "unaffected": []},
+                    "package": {"name": "time", "version": "0.1.45"}
+                }]
+            },
+            "warnings": {}
+        }"#;
+
+        assert_eq!(
+            AuditReport::parse(output).unwrap(),
+            AuditReport {
+                vulnerabilities: vec![Vulnerability {
+                    id: "RUSTSEC-2020-0071".to_string(),
+                    package: "time".to_string(),
+                    version: "0.1.45".to_string(),
+                    title: "Potential segfault in the time crate".to_string(),
+                }]
+            }
+        );
+    }
+}
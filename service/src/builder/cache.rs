@@ -0,0 +1,145 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use ring::digest::{Context as DigestContext, SHA256};
+use tokio::fs;
+use tokio::process::Command;
+use tracing::{debug, trace};
+
+/// File in the target directory which holds the key of the build that filled it
+const KEY_FILE: &str = ".shuttle-build-cache";
+
+/// Whether a build could reuse the artifacts from a previous build
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CacheStatus {
+    /// The target directory was built with the same `Cargo.lock` and rustc version
+    Hit,
+    /// The target directory was missing or built with something else, so everything is rebuilt
+    Miss,
+}
+
+/// A cache of the target directory of a workspace.
+///
+/// The target directory is kept between builds as long as the hash of the workspace's `Cargo.lock`
+/// and the rustc version building it do not change. Otherwise it is cleared, so that a deployment
+/// never links against artifacts from another compiler or dependency tree.
+#[derive(Clone, Debug)]
+pub struct BuildCache {
+    target_dir: PathBuf,
+}
+
+impl BuildCache {
+    pub fn new(target_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            target_dir: target_dir.into(),
+        }
+    }
+
+    /// Compute the cache key for the workspace at `workspace_root`.
+    ///
+    /// Returns `None` when the workspace has no `Cargo.lock`, since there is nothing to pin the
+    /// dependencies to.
+    pub async fn key(workspace_root: &Path) -> anyhow::Result<Option<String>> {
+        let lockfile = match fs::read(workspace_root.join("Cargo.lock")).await {
+            Ok(lockfile) => lockfile,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error).context("failed to read Cargo.lock"),
+        };
+
+        let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+        let version = Command::new(rustc)
+            .arg("-vV")
+            .current_dir(workspace_root)
+            .output()
+            .await
+            .context("failed to get the rustc version")?;
+
+        let mut context = DigestContext::new(&SHA256);
+        context.update(&lockfile);
+        context.update(&version.stdout);
+
+        let key = context
+            .finish()
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+
+        Ok(Some(key))
+    }
+
+    /// Get the target directory ready for a build with `key`, clearing it if it was filled by a
+    /// build with another key.
+    pub async fn prepare(&self, key: &str) -> std::io::Result<CacheStatus> {
+        match fs::read_to_string(self.key_path()).await {
+            Ok(previous) if previous == key => {
+                debug!(key, "reusing the build cache");
+                Ok(CacheStatus::Hit)
+            }
+            Ok(previous) => {
+                debug!(key, previous, "build cache is stale");
+                self.invalidate().await?;
+                Ok(CacheStatus::Miss)
+            }
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                trace!(key, "no build cache found");
+                Ok(CacheStatus::Miss)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Record that the target directory now holds the artifacts of a successful build with `key`
+    pub async fn store(&self, key: &str) -> std::io::Result<()> {
+        fs::create_dir_all(&self.target_dir).await?;
+        fs::write(self.key_path(), key).await
+    }
+
+    /// Remove the cached target directory so that the next build starts from scratch
+    pub async fn invalidate(&self) -> std::io::Result<()> {
+        match fs::remove_dir_all(&self.target_dir).await {
+            Err(error) if error.kind() != ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    fn key_path(&self) -> PathBuf {
+        self.target_dir.join(KEY_FILE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::{BuildCache, CacheStatus};
+
+    #[tokio::test]
+    async fn prepare() {
+        let dir = tempdir().unwrap();
+        let target_dir = dir.path().join("target");
+        let cache = BuildCache::new(&target_dir);
+
+        assert_eq!(cache.prepare("a").await.unwrap(), CacheStatus::Miss);
+
+        cache.store("a").await.unwrap();
+        std::fs::write(target_dir.join("artifact"), "").unwrap();
+        assert_eq!(cache.prepare("a").await.unwrap(), CacheStatus::Hit);
+        assert!(target_dir.join("artifact").exists());
+
+        assert_eq!(cache.prepare("b").await.unwrap(), CacheStatus::Miss);
+        assert!(!target_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn key_needs_lockfile() {
+        let dir = tempdir().unwrap();
+
+        assert_eq!(BuildCache::key(dir.path()).await.unwrap(), None);
+
+        std::fs::write(dir.path().join("Cargo.lock"), "version = 3").unwrap();
+        let key = BuildCache::key(dir.path()).await.unwrap().unwrap();
+        assert_eq!(key.len(), 64);
+    }
+}
@@ -0,0 +1,99 @@
+use std::path::Path;
+
+use serde_json::Value;
+use tokio::process::Command;
+use tracing::debug;
+
+/// Hit and miss counts of a shared compiler cache, like [sccache](https://github.com/mozilla/sccache)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CompilerCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CompilerCacheStats {
+    /// Get the current statistics of the compiler cache used by `rustc_wrapper`.
+    ///
+    /// Returns `None` if the wrapper is not sccache or its statistics could not be read.
+    pub async fn fetch(rustc_wrapper: &Path) -> Option<Self> {
+        if rustc_wrapper.file_stem()? != "sccache" {
+            return None;
+        }
+
+        let output = Command::new(rustc_wrapper)
+            .args(["--show-stats", "--stats-format", "json"])
+            .output()
+            .await
+            .map_err(|error| debug!(%error, "failed to get sccache statistics"))
+            .ok()?;
+
+        if !output.status.success() {
+            debug!(status = %output.status, "sccache failed to show its statistics");
+            return None;
+        }
+
+        let stats: Value = serde_json::from_slice(&output.stdout)
+            .map_err(|error| debug!(%error, "failed to parse sccache statistics"))
+            .ok()?;
+
+        Some(Self {
+            hits: count(&stats["stats"]["cache_hits"])?,
+            misses: count(&stats["stats"]["cache_misses"])?,
+        })
+    }
+
+    /// Get the statistics of everything that happened since `earlier`.
+    ///
+    /// The cache server is shared, so this includes the work of any build running at the same time.
+    pub fn since(&self, earlier: &Self) -> Self {
+        Self {
+            hits: self.hits.saturating_sub(earlier.hits),
+            misses: self.misses.saturating_sub(earlier.misses),
+        }
+    }
+}
+
+/// Older versions of sccache report a single count while newer ones report one per language
+fn count(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(count) => count.as_u64(),
+        value => Some(
+            value["counts"]
+                .as_object()?
+                .values()
+                .filter_map(Value::as_u64)
+                .sum(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{count, CompilerCacheStats};
+
+    #[test]
+    fn count_formats() {
+        assert_eq!(count(&json!(5)), Some(5));
+        assert_eq!(
+            count(&json!({ "counts": { "Rust": 3, "C/C++": 2 }, "adv_counts": {} })),
+            Some(5)
+        );
+        assert_eq!(count(&json!(null)), None);
+    }
+
+    #[test]
+    fn since() {
+        let before = CompilerCacheStats { hits: 2, misses: 5 };
+        let after = CompilerCacheStats {
+            hits: 10,
+            misses: 6,
+        };
+
+        assert_eq!(
+            after.since(&before),
+            CompilerCacheStats { hits: 8, misses: 1 }
+        );
+    }
+}
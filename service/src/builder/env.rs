@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+
+use tokio::process::Command;
+
+/// The variables of our own environment cargo and the hooks still get when the environment is
+/// scrubbed. They are needed to find the toolchain and to reach the network, but hold no secrets.
+const INHERITED_VARS: [&str; 20] = [
+    "PATH",
+    "HOME",
+    "USER",
+    "LANG",
+    "LC_ALL",
+    "TMPDIR",
+    "CARGO_HOME",
+    "RUSTUP_HOME",
+    "RUSTUP_TOOLCHAIN",
+    "SSL_CERT_FILE",
+    "SSL_CERT_DIR",
+    "HTTP_PROXY",
+    "HTTPS_PROXY",
+    "NO_PROXY",
+    // Needed to start anything on Windows
+    "SYSTEMROOT",
+    "COMSPEC",
+    "PATHEXT",
+    "USERPROFILE",
+    "TEMP",
+    "TMP",
+];
+
+/// The environment the commands of a build, `cargo build` and the hooks, are run with
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct BuildEnv<'a> {
+    /// The `[build.env]` of the Shuttle.toml
+    vars: &'a BTreeMap<String, String>,
+    /// Whether to leave out everything of our own environment except the [INHERITED_VARS]
+    scrub: bool,
+}
+
+impl<'a> BuildEnv<'a> {
+    pub(crate) fn new(vars: &'a BTreeMap<String, String>, scrub: bool) -> Self {
+        Self { vars, scrub }
+    }
+
+    /// Set up the environment of `command`
+    pub(crate) fn apply(&self, command: &mut Command) {
+        if self.scrub {
+            command.env_clear();
+
+            for (key, value) in std::env::vars_os() {
+                if is_inherited(&key) {
+                    command.env(key, value);
+                }
+            }
+        }
+
+        command.envs(self.vars);
+    }
+}
+
+/// Whether `key` is one of the [INHERITED_VARS]
+pub(crate) fn is_inherited(key: &OsStr) -> bool {
+    // Windows does not care about the case of variable names
+    key.to_str().map_or(false, |key| {
+        INHERITED_VARS.contains(&key.to_uppercase().as_str())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::ffi::OsStr;
+
+    use tokio::process::Command;
+
+    use super::BuildEnv;
+
+    fn env_of(command: &Command) -> Vec<(&OsStr, Option<&OsStr>)> {
+        command.as_std().get_envs().collect()
+    }
+
+    #[test]
+    fn apply() {
+        std::env::set_var("SHUTTLE_BUILD_ENV_TEST_SECRET", "hunter2");
+        let vars = BTreeMap::from([("SQLX_OFFLINE".to_string(), "true".to_string())]);
+
+        let mut command = Command::new("cargo");
+        BuildEnv::new(&vars, true).apply(&mut command);
+        let env = env_of(&command);
+
+        assert!(env.contains(&(OsStr::new("SQLX_OFFLINE"), Some(OsStr::new("true")))));
+        assert!(env.iter().any(|(key, _)| *key == "PATH"));
+        assert!(!env
+            .iter()
+            .any(|(key, _)| *key == "SHUTTLE_BUILD_ENV_TEST_SECRET"));
+
+        // Without scrubbing, the environment is inherited as is
+        let mut command = Command::new("cargo");
+        BuildEnv::new(&vars, false).apply(&mut command);
+
+        assert_eq!(
+            env_of(&command),
+            vec![(OsStr::new("SQLX_OFFLINE"), Some(OsStr::new("true")))]
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::Context;
+use cargo_metadata::Message;
+use crossbeam_channel::Sender;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+use tracing::trace;
+
+use super::env::BuildEnv;
+use super::sandbox::{sandboxed, Sandbox};
+use super::{kill_process_group, send_message, BuildError};
+
+/// How long a hook may run before it is killed
+const HOOK_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Run the `command` of the `hook` from Shuttle.toml with a shell in `dir`.
+///
+/// It runs in a container of the `sandbox`, if there is one. Everything the command outputs is sent
+/// through `tx` as it runs.
+pub(crate) async fn run_hook(
+    hook: &str,
+    command: &str,
+    dir: &Path,
+    env: &BuildEnv<'_>,
+    sandbox: Option<&Sandbox<'_>>,
+    tx: &Sender<Message>,
+    cancellation_token: &CancellationToken,
+) -> Result<(), BuildError> {
+    send_message(
+        tx,
+        Message::TextLine(format!("Running {hook} hook: {command}")),
+    );
+
+    let mut shell = shell_command(command);
+    env.apply(&mut shell);
+    shell
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    // Like cargo, the hook leads its own process group so that everything it starts can be killed
+    #[cfg(unix)]
+    super::lead_process_group(&mut shell);
+
+    let (mut shell, _container) = sandboxed(sandbox, shell);
+
+    trace!(?shell, "spawning hook");
+    let mut child = shell
+        .spawn()
+        .with_context(|| format!("failed to start the {hook} hook"))?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .context("failed to capture the stdout of the hook")?;
+    let stderr = child
+        .stderr
+        .take()
+        .context("failed to capture the stderr of the hook")?;
+
+    let run = async {
+        tokio::join!(forward_lines(stdout, tx), forward_lines(stderr, tx));
+        child.wait().await
+    };
+
+    let result = tokio::select! {
+        result = timeout(HOOK_TIMEOUT, run) => result.map_err(|_| BuildError::HookFailed {
+            hook: hook.to_string(),
+            reason: format!("it did not finish within {} seconds", HOOK_TIMEOUT.as_secs()),
+        }),
+        _ = cancellation_token.cancelled() => Err(BuildError::Cancelled),
+    };
+
+    let status = match result {
+        Ok(status) => status?,
+        Err(error) => {
+            kill_process_group(&mut child).await;
+            return Err(error);
+        }
+    };
+
+    if !status.success() {
+        return Err(BuildError::HookFailed {
+            hook: hook.to_string(),
+            reason: format!("it exited with {status}"),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+async fn forward_lines(reader: impl AsyncRead + Unpin, tx: &Sender<Message>) {
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        send_message(tx, Message::TextLine(line));
+    }
+}
@@ -0,0 +1,1538 @@
+mod artifacts;
+mod assets;
+mod audit;
+mod cache;
+mod compiler_cache;
+mod env;
+mod hooks;
+mod plan;
+mod prebuilt;
+mod queue;
+mod sandbox;
+mod timings;
+mod toolchain;
+mod wasm;
+mod watch;
+
+use std::future::pending;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+pub use artifacts::{ArtifactStore, RetentionPolicy};
+pub use audit::{AuditPolicy, AuditReport, Vulnerability};
+pub use cache::{BuildCache, CacheStatus};
+use cargo::core::{Package, Shell, Verbosity, Workspace};
+use cargo::util::homedir;
+use cargo::Config;
+use cargo_metadata::diagnostic::{Diagnostic, DiagnosticLevel};
+use cargo_metadata::semver::{Version, VersionReq};
+use cargo_metadata::{Artifact, CompilerMessage, Message, MetadataCommand};
+pub use compiler_cache::CompilerCacheStats;
+use crossbeam_channel::Sender;
+use env::BuildEnv;
+use pipe::PipeWriter;
+pub use plan::{plan_workspace, BuildPlan, BuildStep, PlannedService};
+pub use prebuilt::{load_prebuilt, ArtifactMetadata, ARTIFACT_METADATA_PATH, ARTIFACT_PATH};
+pub use queue::{BuildPriority, BuildQueue, BuildSlot, QueuePosition, QueuedBuild};
+pub use sandbox::BuildSandbox;
+use sandbox::{sandboxed, Sandbox};
+use shuttle_common::project::ProjectName;
+use shuttle_common::shuttle_toml::{ShuttleToml, ShuttleTomlError};
+use thiserror::Error;
+pub use timings::CrateTiming;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::time::{sleep_until, Instant};
+pub use tokio_util::sync::CancellationToken;
+pub use toolchain::{has_active_target, rustc_version, Toolchain};
+use tracing::{debug, error, trace};
+pub use wasm::WasmOptimization;
+pub use watch::{build_workspace_watch, BuildEvent};
+
+use crate::{NEXT_NAME, RUNTIME_NAME};
+
+/// The target shuttle-next services are compiled for
+pub const NEXT_TARGET: &str = "wasm32-wasi";
+
+/// The directory inside the target directory shuttle-next services are built in
+const NEXT_TARGET_DIR: &str = "next";
+
+/// The shuttle crates which are released together and have to be of the version the services
+/// are run with
+const VERSIONED_CRATES: [&str; 3] = [RUNTIME_NAME, NEXT_NAME, "shuttle-service"];
+
+/// A target for alpha services which produces static executables that run in any Linux container
+pub const MUSL_TARGET: &str = "x86_64-unknown-linux-musl";
+
+#[derive(Clone, Debug)]
+/// This represents a compiled alpha or shuttle-next service.
+pub struct BuiltService {
+    pub executable_path: PathBuf,
+    pub is_wasm: bool,
+    pub package_name: String,
+    pub working_directory: PathBuf,
+    pub manifest_path: PathBuf,
+    /// The features which were activated on the package when it was built
+    pub features: Vec<String>,
+    /// How well the compiler cache did while building this service, when one was used
+    pub compiler_cache_stats: Option<CompilerCacheStats>,
+    /// The version of rustc which built this service
+    pub toolchain: Option<String>,
+    /// The size of the executable in bytes
+    pub binary_size: u64,
+    /// How long it took to build this service, along with any other service built with it
+    pub build_duration: Duration,
+    /// How long each crate took to compile, when [BuildOptions::timings] was set
+    pub crate_timings: Vec<CrateTiming>,
+    /// The files matched by the `assets` in Shuttle.toml, relative to the working directory, which
+    /// have to be shipped along with the executable
+    pub assets: Vec<PathBuf>,
+    /// The known vulnerabilities in the dependencies of the workspace, when they were checked
+    pub audit: Option<AuditReport>,
+    /// How much smaller the `[wasm]` table of Shuttle.toml made a shuttle-next module
+    pub wasm_optimization: Option<WasmOptimization>,
+}
+
+/// Built services are the same when they are the same output of the same package, no matter how
+/// the build went
+impl PartialEq for BuiltService {
+    fn eq(&self, other: &Self) -> bool {
+        self.executable_path == other.executable_path
+            && self.is_wasm == other.is_wasm
+            && self.package_name == other.package_name
+            && self.working_directory == other.working_directory
+            && self.manifest_path == other.manifest_path
+    }
+}
+
+impl Eq for BuiltService {}
+
+impl BuiltService {
+    pub fn new(
+        executable_path: PathBuf,
+        is_wasm: bool,
+        package_name: String,
+        working_directory: PathBuf,
+        manifest_path: PathBuf,
+    ) -> Self {
+        Self {
+            executable_path,
+            is_wasm,
+            package_name,
+            working_directory,
+            manifest_path,
+            features: Vec::new(),
+            compiler_cache_stats: None,
+            toolchain: None,
+            binary_size: 0,
+            build_duration: Duration::ZERO,
+            crate_timings: Vec::new(),
+            assets: Vec::new(),
+            audit: None,
+            wasm_optimization: None,
+        }
+    }
+
+    /// Try to get the service name of a crate from Shuttle.toml in the crate root, if it doesn't
+    /// exist get it from the Cargo.toml package name of the crate.
+    pub fn service_name(&self) -> anyhow::Result<ProjectName> {
+        let shuttle_toml = ShuttleToml::from_path(&self.working_directory.join("Shuttle.toml"))?;
+
+        match shuttle_toml.name {
+            Some(service_name) => Ok(service_name),
+            None => {
+                debug!("no name in Shuttle.toml, using the package name");
+
+                Ok(self.package_name.parse()?)
+            }
+        }
+    }
+}
+
+/// Errors that can occur while building a shuttle project
+#[derive(Debug, Error)]
+pub enum BuildError {
+    #[error("Build failed. `cargo build` exited with {status}{}", render_diagnostics(.diagnostics))]
+    Compile {
+        status: ExitStatus,
+        /// The errors rustc reported while compiling
+        diagnostics: Vec<Diagnostic>,
+    },
+    #[error("No Shuttle service named `{name}` was found in this workspace. The services are: {}", .candidates.join(", "))]
+    PackageNotFound {
+        name: String,
+        /// The names of all the services in the workspace
+        candidates: Vec<String>,
+    },
+    #[error("This workspace has several Shuttle services, please select the one to build with `--package`. The services are: {}", .0.join(", "))]
+    MultipleServices(Vec<String>),
+    #[error("No service was produced for package `{0}`. Alpha services need to be a binary and shuttle-next services need `crate-type = [\"cdylib\"]`.")]
+    MissingTarget(String),
+    #[error("The Rust toolchain needed to build this project is not installed: {0}")]
+    ToolchainMissing(String),
+    #[error("The `{0}` package depends on both `shuttle-runtime` and `shuttle-next`, so it cannot be told whether it is an alpha or a shuttle-next service. Please remove `shuttle-next` from an alpha service, or `shuttle-runtime` from a shuttle-next service.")]
+    ConflictingRuntimes(String),
+    #[error("The `{package}` service depends on `{dependency} = \"{requirement}\"`, but only version {supported} is supported. Please change it to `{dependency} = \"{supported}\"`.")]
+    UnsupportedVersion {
+        package: String,
+        dependency: String,
+        requirement: String,
+        supported: Version,
+    },
+    #[error("The artifact was built for `{target}`, but services are run on `{supported}`. Please build it with `--target {supported}`.")]
+    UnsupportedTarget { target: String, supported: String },
+    #[error("The `wasm32-wasi` target is not installed. Please install it with `rustup target add wasm32-wasi`.")]
+    WasmTargetMissing,
+    #[error("Building with `--locked` needs a Cargo.lock, but there is none in {}. Please run `cargo generate-lockfile` and include the Cargo.lock with your project.", .0.display())]
+    MissingLockfile(PathBuf),
+    #[error("Some dependencies have known vulnerabilities:{}", .0.vulnerabilities.iter().map(|vulnerability| format!("\n  {vulnerability}")).collect::<String>())]
+    VulnerableDependencies(AuditReport),
+    #[error("The `{0}` target is not installed. Please install it with `rustup target add {0}`.")]
+    TargetMissing(String),
+    #[error("The `{linker}` linker needed to build for `{target}` could not be found. Please install it or set `CARGO_TARGET_<TRIPLE>_LINKER` to another linker.")]
+    LinkerMissing { target: String, linker: String },
+    #[error("Build timed out after {} seconds", .0.as_secs())]
+    TimedOut(Duration),
+    #[error("The {hook} hook in Shuttle.toml failed, because {reason}")]
+    HookFailed { hook: String, reason: String },
+    #[error("Build was cancelled")]
+    Cancelled,
+    #[error(transparent)]
+    ShuttleToml(#[from] ShuttleTomlError),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+fn render_diagnostics(diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .filter_map(|diagnostic| diagnostic.rendered.as_deref())
+        .map(|rendered| format!("\n{rendered}"))
+        .collect()
+}
+
+/// Options to change how [build_workspace] builds the services of a project
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BuildOptions {
+    /// Features to activate, using the `package/feature` syntax for packages in a workspace
+    pub features: Vec<String>,
+    /// Activate all the features of the built packages
+    pub all_features: bool,
+    /// Do not activate the `default` feature of the built packages
+    pub no_default_features: bool,
+    /// Only reuse the target directory of a previous build when it was built from the same
+    /// `Cargo.lock` and rustc version, see [BuildCache]
+    pub use_cache: bool,
+    /// Wrapper to compile with, like `sccache`, so that compilation artifacts can be shared
+    /// between builds
+    pub rustc_wrapper: Option<PathBuf>,
+    /// Abort the build if it takes longer than this
+    pub timeout: Option<Duration>,
+    /// Only build the service with this package name
+    pub package: Option<String>,
+    /// Fail with [BuildError::MultipleServices] instead of building every service when the
+    /// workspace has several and no `package` was selected
+    pub single_service: bool,
+    /// Directory to build in, instead of the one cargo is configured to use through
+    /// `CARGO_TARGET_DIR` or `build.target-dir`
+    pub target_dir: Option<PathBuf>,
+    /// Build with the exact dependencies in `Cargo.lock`, failing if it is missing or out of date
+    pub locked: bool,
+    /// Build without accessing the network, so all dependencies have to be available already
+    pub offline: bool,
+    /// Install the toolchain pinned by a `rust-toolchain.toml`, and the `wasm32-wasi` target
+    /// shuttle-next services need, with rustup when they are missing
+    pub install_toolchain: bool,
+    /// Record how long each crate takes to compile. This needs a nightly toolchain, since
+    /// `--timings=json` is unstable
+    pub timings: bool,
+    /// Whether to check the dependencies for known vulnerabilities with cargo-audit before
+    /// building, and what to do when some are found
+    pub audit_policy: AuditPolicy,
+    /// Target triple to build alpha services for, like [MUSL_TARGET], instead of the host
+    pub target: Option<String>,
+    /// Reuse the executables of a previous build of the exact same project from this store, and
+    /// store the executables of new builds in it. Projects with build hooks are always compiled,
+    /// since their hooks can create files the services need.
+    pub artifact_store: Option<ArtifactStore>,
+    /// The version of the shuttle crates the services have to be compatible with. It is checked
+    /// before building, so that a service which cannot run is not compiled for nothing.
+    pub supported_version: Option<Version>,
+    /// Run cargo and the hooks without our own environment, so that the build scripts of a
+    /// service cannot read secrets from it. They only get what a build needs, along with the
+    /// `[build.env]` of Shuttle.toml.
+    pub scrub_env: bool,
+    /// Run cargo and the hooks in containers, with quotas and without network, instead of on this
+    /// machine. The toolchain of the image is used then, so the toolchain is not installed here.
+    pub sandbox: Option<BuildSandbox>,
+}
+
+/// Given a project directory path, builds the crate.
+///
+/// The build is aborted, killing cargo and everything it started, when `cancellation_token` is
+/// cancelled or the timeout in the `options` is reached.
+pub async fn build_workspace(
+    project_path: &Path,
+    release_mode: bool,
+    tx: Sender<Message>,
+    options: &BuildOptions,
+    cancellation_token: CancellationToken,
+) -> Result<Vec<BuiltService>, BuildError> {
+    // cargo runs from the project directory, so relative paths have to be resolved before that
+    let manifest_path = std::env::current_dir()?
+        .join(project_path)
+        .join("Cargo.toml");
+    let mut services = find_services(&manifest_path, options.target_dir.as_deref(), tx.clone())?;
+    services.select(options)?;
+    services.check_versions(options)?;
+
+    let cache = BuildCache::new(&services.target_dir);
+    let cache_key = if options.use_cache {
+        BuildCache::key(&services.workspace_root).await?
+    } else {
+        None
+    };
+
+    if let Some(key) = &cache_key {
+        let status = cache.prepare(key).await?;
+        send_message(
+            &tx,
+            Message::TextLine(match status {
+                CacheStatus::Hit => "Reusing the build cache".to_string(),
+                CacheStatus::Miss => "Build cache is empty, building from scratch".to_string(),
+            }),
+        );
+    }
+
+    let locked = options.locked || services.shuttle_toml.build.locked;
+    if locked && !services.workspace_root.join("Cargo.lock").exists() {
+        return Err(BuildError::MissingLockfile(services.workspace_root));
+    }
+
+    let audit = audit_dependencies(&services.workspace_root, options.audit_policy, &tx).await?;
+
+    // Sandboxed builds get no network unless Shuttle.toml asks for it with `offline = false`
+    let build = &services.shuttle_toml.build;
+    let offline = options.offline || build.offline.unwrap_or(options.sandbox.is_some());
+    let sandbox = options.sandbox.as_ref().map(|sandbox| Sandbox {
+        options: sandbox,
+        workspace_root: &services.workspace_root,
+        target_dir: &services.target_dir,
+        network: !offline,
+    });
+
+    let toolchain_version = if let Some(sandbox) = &sandbox {
+        sandbox.prepare().await?;
+        sandbox.rustc_version().await
+    } else {
+        if let Some(toolchain) = Toolchain::find(&services.workspace_root)? {
+            ensure_toolchain(&toolchain, !services.next.is_empty(), options, &tx).await?;
+        } else if !services.next.is_empty() {
+            ensure_wasm_target(&services.workspace_root, options, &tx).await?;
+        }
+
+        if let Some(target) = &options.target {
+            if !services.alpha.is_empty() {
+                ensure_target(&services.workspace_root, target, options, &tx).await?;
+            }
+        }
+
+        toolchain::rustc_version(&services.workspace_root).await
+    };
+
+    let hooks = &services.shuttle_toml.hooks;
+    let artifact_key = match &options.artifact_store {
+        Some(_) if hooks.pre_build.is_none() && hooks.post_build.is_none() => {
+            ArtifactStore::key(
+                &services.workspace_root,
+                &services.target_dir,
+                release_mode,
+                options,
+            )
+            .await?
+        }
+        _ => None,
+    };
+
+    let stored = match (&options.artifact_store, &artifact_key) {
+        (Some(store), Some(key)) => {
+            store
+                .get(key, &services.workspace_root, &services.target_dir)
+                .await?
+        }
+        _ => None,
+    };
+
+    let mut runtimes = match stored {
+        Some(runtimes) => {
+            send_message(
+                &tx,
+                Message::TextLine(
+                    "Nothing changed since a previous build, reusing its executables".to_string(),
+                ),
+            );
+
+            runtimes
+        }
+        None => {
+            let context = BuildContext {
+                manifest_path: &manifest_path,
+                release_mode,
+                profile: build.profile.as_deref(),
+                target_dir: &services.target_dir,
+                locked,
+                offline,
+                // Only fetching the dependencies, which runs no code of the service, can reach
+                // the network when the build cannot
+                prefetch: sandbox.is_some() && !options.offline && build.offline.is_none(),
+                options,
+                env: BuildEnv::new(&build.env, options.scrub_env),
+                sandbox,
+                deadline: options
+                    .timeout
+                    .map(|timeout| (Instant::now() + timeout, timeout)),
+                cancellation_token,
+                tx,
+            };
+
+            let runtimes = compile_services(&services, &context).await?;
+
+            if let Some(key) = &cache_key {
+                cache.store(key).await?;
+            }
+
+            if let (Some(store), Some(key)) = (&options.artifact_store, &artifact_key) {
+                store.put(key, &services.workspace_root, &runtimes).await?;
+            }
+
+            runtimes
+        }
+    };
+
+    for runtime in &mut runtimes {
+        runtime.toolchain = toolchain_version.clone();
+        runtime.assets = assets::resolve_assets(&runtime.working_directory)?;
+        runtime.audit = audit.clone();
+    }
+
+    Ok(runtimes)
+}
+
+/// Build the alpha and shuttle-next services of a workspace, running the hooks of its Shuttle.toml
+/// around the build
+async fn compile_services(
+    services: &WorkspaceServices,
+    context: &BuildContext<'_>,
+) -> Result<Vec<BuiltService>, BuildError> {
+    if context.prefetch {
+        fetch(context).await?;
+    }
+
+    let hooks = &services.shuttle_toml.hooks;
+    if let Some(pre_build) = &hooks.pre_build {
+        hooks::run_hook(
+            "pre_build",
+            pre_build,
+            &services.workspace_root,
+            &context.env,
+            context.sandbox.as_ref(),
+            &context.tx,
+            &context.cancellation_token,
+        )
+        .await?;
+    }
+
+    // Alpha and shuttle-next services are built in different target directories, so both cargo
+    // invocations can run at the same time
+    let (mut runtimes, mut next_libraries) = tokio::try_join!(
+        async {
+            if services.alpha.is_empty() {
+                Ok(Vec::new())
+            } else {
+                compile(context, &services.alpha, false).await
+            }
+        },
+        async {
+            if services.next.is_empty() {
+                Ok(Vec::new())
+            } else {
+                compile(context, &services.next, true).await
+            }
+        },
+    )?;
+
+    for library in &mut next_libraries {
+        if let Some(optimization) = wasm::post_process(
+            &library.executable_path,
+            &services.shuttle_toml.wasm,
+            &context.tx,
+        )
+        .await?
+        {
+            library.binary_size = optimization.after;
+            library.wasm_optimization = Some(optimization);
+        }
+    }
+
+    runtimes.append(&mut next_libraries);
+
+    if let Some(post_build) = &hooks.post_build {
+        hooks::run_hook(
+            "post_build",
+            post_build,
+            &services.workspace_root,
+            &context.env,
+            context.sandbox.as_ref(),
+            &context.tx,
+            &context.cancellation_token,
+        )
+        .await?;
+    }
+
+    Ok(runtimes)
+}
+
+/// Make sure alpha services can be built for `target`, which needs the target to be installed and
+/// a linker for it
+async fn ensure_target(
+    workspace_root: &Path,
+    target: &str,
+    options: &BuildOptions,
+    tx: &Sender<Message>,
+) -> Result<(), BuildError> {
+    match toolchain::has_active_target(workspace_root, target).await {
+        Some(false) if options.install_toolchain => {
+            send_message(
+                tx,
+                Message::TextLine(format!("Installing the `{target}` target")),
+            );
+            toolchain::add_target(workspace_root, target).await?;
+        }
+        Some(false) => return Err(BuildError::TargetMissing(target.to_string())),
+        // Leave it to cargo to report a missing target when rustup cannot tell
+        Some(true) | None => {}
+    }
+
+    toolchain::check_linker(target)
+        .await
+        .map_err(|linker| BuildError::LinkerMissing {
+            target: target.to_string(),
+            linker,
+        })
+}
+
+/// Check the `Cargo.lock` of the workspace for dependencies with known vulnerabilities, as the
+/// `policy` asks
+async fn audit_dependencies(
+    workspace_root: &Path,
+    policy: AuditPolicy,
+    tx: &Sender<Message>,
+) -> Result<Option<AuditReport>, BuildError> {
+    if policy == AuditPolicy::Off {
+        return Ok(None);
+    }
+
+    send_message(
+        tx,
+        Message::TextLine("Checking dependencies for known vulnerabilities".to_string()),
+    );
+
+    let report = match AuditReport::run(workspace_root).await {
+        Ok(report) => report,
+        Err(error) if policy == AuditPolicy::Warn => {
+            send_message(
+                tx,
+                Message::TextLine(format!("Skipping the dependency audit: {error:#}")),
+            );
+            return Ok(None);
+        }
+        // Not being able to check is as bad as finding vulnerabilities when they are denied
+        Err(error) => return Err(error.into()),
+    };
+
+    for vulnerability in &report.vulnerabilities {
+        send_message(
+            tx,
+            Message::TextLine(format!("Vulnerable dependency: {vulnerability}")),
+        );
+    }
+
+    if policy == AuditPolicy::Deny && !report.is_clean() {
+        return Err(BuildError::VulnerableDependencies(report));
+    }
+
+    Ok(Some(report))
+}
+
+/// Make sure the toolchain pinned by the project is installed, along with the wasm target when
+/// shuttle-next services have to be built with it
+async fn ensure_toolchain(
+    toolchain: &Toolchain,
+    wasm: bool,
+    options: &BuildOptions,
+    tx: &Sender<Message>,
+) -> Result<(), BuildError> {
+    let installed = toolchain.is_installed().await?;
+    let has_target = installed && (!wasm || toolchain.has_target(NEXT_TARGET).await?);
+
+    if has_target {
+        return Ok(());
+    }
+
+    if !options.install_toolchain {
+        return Err(if installed {
+            BuildError::WasmTargetMissing
+        } else {
+            BuildError::ToolchainMissing(format!(
+                "`{0}` is pinned by rust-toolchain.toml. Please install it with `rustup toolchain install {0}`.",
+                toolchain.channel
+            ))
+        });
+    }
+
+    send_message(
+        tx,
+        Message::TextLine(format!("Installing the `{}` toolchain", toolchain.channel)),
+    );
+
+    let targets: &[&str] = if wasm { &[NEXT_TARGET] } else { &[] };
+    toolchain.install(targets).await?;
+
+    Ok(())
+}
+
+/// Make sure the wasm target is installed before building shuttle-next services, since cargo
+/// only notices it is missing deep into the build
+async fn ensure_wasm_target(
+    workspace_root: &Path,
+    options: &BuildOptions,
+    tx: &Sender<Message>,
+) -> Result<(), BuildError> {
+    match toolchain::has_active_target(workspace_root, NEXT_TARGET).await {
+        Some(true) => Ok(()),
+        Some(false) if options.install_toolchain => {
+            send_message(
+                tx,
+                Message::TextLine(format!("Installing the `{NEXT_TARGET}` target")),
+            );
+            toolchain::add_target(workspace_root, NEXT_TARGET).await?;
+
+            Ok(())
+        }
+        Some(false) => Err(BuildError::WasmTargetMissing),
+        // Leave it to cargo to report a missing target
+        None => Ok(()),
+    }
+}
+
+/// Everything the cargo invocations of a single [build_workspace] call share
+struct BuildContext<'a> {
+    manifest_path: &'a Path,
+    release_mode: bool,
+    /// The cargo profile requested in Shuttle.toml
+    profile: Option<&'a str>,
+    /// Where the artifacts of the workspace are built
+    target_dir: &'a Path,
+    /// Whether the options or Shuttle.toml asked for a locked build
+    locked: bool,
+    /// Whether the build cannot access the network, because the options or Shuttle.toml asked for
+    /// it or because it is sandboxed
+    offline: bool,
+    /// Whether to fetch the dependencies with network before an offline build
+    prefetch: bool,
+    options: &'a BuildOptions,
+    env: BuildEnv<'a>,
+    /// The containers to run the commands of the build in, if any
+    sandbox: Option<Sandbox<'a>>,
+    /// When the whole build has to be done by, along with the timeout that set it
+    deadline: Option<(Instant, Duration)>,
+    cancellation_token: CancellationToken,
+    tx: Sender<Message>,
+}
+
+impl BuildContext<'_> {
+    /// The cargo profile to build with. A profile from Shuttle.toml takes precedence over the
+    /// default for the mode we build in.
+    fn profile(&self) -> &str {
+        match self.profile {
+            Some(profile) => profile,
+            None if self.release_mode => "release",
+            None => "dev",
+        }
+    }
+}
+
+/// A workspace member which is a shuttle service
+#[derive(Clone, Debug)]
+struct ServicePackage {
+    name: String,
+    root: PathBuf,
+    manifest_path: PathBuf,
+    /// The binary target to build for alpha services
+    bin: Option<String>,
+    /// The version requirements of the shuttle crates the service depends on, by crate name
+    shuttle_dependencies: Vec<(String, String)>,
+}
+
+impl From<&Package> for ServicePackage {
+    fn from(package: &Package) -> Self {
+        let shuttle_dependencies = package
+            .dependencies()
+            .iter()
+            .filter(|dependency| VERSIONED_CRATES.contains(&dependency.package_name().as_str()))
+            .map(|dependency| {
+                (
+                    dependency.package_name().to_string(),
+                    dependency.version_req().to_string(),
+                )
+            })
+            .collect();
+
+        Self {
+            name: package.name().to_string(),
+            root: package.root().to_path_buf(),
+            manifest_path: package.manifest_path().to_path_buf(),
+            bin: None,
+            shuttle_dependencies,
+        }
+    }
+}
+
+/// The shuttle services found in a workspace
+struct WorkspaceServices {
+    alpha: Vec<ServicePackage>,
+    next: Vec<ServicePackage>,
+    /// The build settings in the Shuttle.toml at the root of the workspace
+    shuttle_toml: ShuttleToml,
+    workspace_root: PathBuf,
+    /// Where the artifacts of the workspace are built
+    target_dir: PathBuf,
+}
+
+impl WorkspaceServices {
+    /// Only keep the service selected by the `package` of the `options`
+    fn select(&mut self, options: &BuildOptions) -> Result<(), BuildError> {
+        let candidates = || {
+            self.alpha
+                .iter()
+                .chain(&self.next)
+                .map(|package| package.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        match &options.package {
+            Some(name) => {
+                if !self
+                    .alpha
+                    .iter()
+                    .chain(&self.next)
+                    .any(|package| &package.name == name)
+                {
+                    return Err(BuildError::PackageNotFound {
+                        name: name.clone(),
+                        candidates: candidates(),
+                    });
+                }
+
+                self.alpha.retain(|package| &package.name == name);
+                self.next.retain(|package| &package.name == name);
+            }
+            None if options.single_service && self.alpha.len() + self.next.len() > 1 => {
+                return Err(BuildError::MultipleServices(candidates()));
+            }
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    /// Make sure the shuttle crates of every service are of the `supported_version` of the
+    /// `options`
+    fn check_versions(&self, options: &BuildOptions) -> Result<(), BuildError> {
+        let Some(supported) = &options.supported_version else {
+            return Ok(());
+        };
+
+        for package in self.alpha.iter().chain(&self.next) {
+            for (dependency, requirement) in &package.shuttle_dependencies {
+                // Dependencies without a version, like path and git dependencies, are left to
+                // the compiler
+                let Ok(version_req) = VersionReq::parse(requirement) else {
+                    continue;
+                };
+
+                if version_req != VersionReq::STAR && !version_req.matches(supported) {
+                    return Err(BuildError::UnsupportedVersion {
+                        package: package.name.clone(),
+                        dependency: dependency.clone(),
+                        requirement: requirement.clone(),
+                        supported: supported.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Load the workspace at `manifest_path` and find all the alpha and shuttle-next services in it.
+///
+/// The cargo [Workspace] is not `Send`, so it is kept out of the async build to not hold it across
+/// an await point.
+fn find_services(
+    manifest_path: &Path,
+    target_dir: Option<&Path>,
+    tx: Sender<Message>,
+) -> Result<WorkspaceServices, BuildError> {
+    let (read, write) = pipe::pipe();
+
+    // This needs to be on a separate thread, else deployer will block (reason currently unknown :D)
+    tokio::task::spawn_blocking(move || {
+        trace!("started thread to to capture workspace output stream");
+        for message in Message::parse_stream(read) {
+            trace!(?message, "parsed cargo message");
+            match message {
+                Ok(message) => send_message(&tx, message),
+                Err(error) => {
+                    error!("failed to parse cargo message: {error}");
+                }
+            }
+        }
+    });
+
+    let config = get_config(write, project_dir(manifest_path))?;
+    let ws = Workspace::new(manifest_path, &config)?;
+    check_no_panic(&ws)?;
+
+    let shuttle_toml = ShuttleToml::from_path(&ws.root().join("Shuttle.toml"))?;
+    if let Some(profile) = &shuttle_toml.build.profile {
+        check_profile_exists(&ws, profile)?;
+    }
+
+    let mut alpha: Vec<ServicePackage> = Vec::new();
+    let mut next: Vec<ServicePackage> = Vec::new();
+
+    for member in ws.members() {
+        if is_next(member) && is_alpha(member) {
+            return Err(BuildError::ConflictingRuntimes(member.name().to_string()));
+        }
+
+        if is_next(member) {
+            ensure_cdylib(member)?;
+            next.push(member.into());
+        } else if is_alpha(member) {
+            let bin = select_binary(member)?;
+            alpha.push(ServicePackage {
+                bin: Some(bin),
+                ..member.into()
+            });
+        }
+    }
+
+    Ok(WorkspaceServices {
+        alpha,
+        next,
+        shuttle_toml,
+        workspace_root: ws.root().to_path_buf(),
+        target_dir: match target_dir {
+            Some(target_dir) => target_dir.to_path_buf(),
+            None => ws.target_dir().into_path_unlocked(),
+        },
+    })
+}
+
+/// Run `cargo build` for the given packages, streaming its output through `tx` as it happens.
+///
+/// The paths of the built services are taken from the artifacts cargo reports, so that they are
+/// correct no matter where cargo decided to put them.
+async fn compile(
+    context: &BuildContext<'_>,
+    packages: &[ServicePackage],
+    wasm: bool,
+) -> Result<Vec<BuiltService>, BuildError> {
+    let options = context.options;
+    let (cargo, _container) = sandboxed(
+        context.sandbox.as_ref(),
+        build_command(context, packages, wasm),
+    );
+
+    let mut compiler_cache_stats = None;
+    if let Some(rustc_wrapper) = &options.rustc_wrapper {
+        compiler_cache_stats = CompilerCacheStats::fetch(rustc_wrapper).await;
+    }
+
+    let started = Instant::now();
+    let output = run_cargo(
+        cargo,
+        &context.tx,
+        &context.cancellation_token,
+        context.deadline,
+    )
+    .await?;
+    let build_duration = started.elapsed();
+
+    if let (Some(rustc_wrapper), Some(before)) = (&options.rustc_wrapper, compiler_cache_stats) {
+        compiler_cache_stats = CompilerCacheStats::fetch(rustc_wrapper)
+            .await
+            .map(|after| after.since(&before));
+    }
+
+    if let Some(toolchain) = output
+        .stderr
+        .iter()
+        .find(|line| line.starts_with("error: toolchain") && line.ends_with("is not installed"))
+    {
+        return Err(BuildError::ToolchainMissing(toolchain.clone()));
+    }
+
+    if !output.status.success() {
+        let target_missing = output.diagnostics.iter().any(|diagnostic| {
+            diagnostic
+                .children
+                .iter()
+                .any(|child| child.message.contains("target may not be installed"))
+        });
+
+        if wasm && target_missing {
+            return Err(BuildError::WasmTargetMissing);
+        }
+
+        return Err(BuildError::Compile {
+            status: output.status,
+            diagnostics: output.diagnostics,
+        });
+    }
+
+    let services: Vec<_> = output
+        .artifacts
+        .into_iter()
+        .filter_map(|artifact| {
+            let package = packages.iter().find(|package| {
+                is_same_file(&package.manifest_path, artifact.manifest_path.as_std_path())
+            })?;
+
+            let executable_path = if wasm {
+                if !artifact.target.kind.iter().any(|kind| kind == "cdylib") {
+                    return None;
+                }
+
+                artifact
+                    .filenames
+                    .into_iter()
+                    .find(|path| path.extension() == Some("wasm"))?
+            } else {
+                if !artifact.target.kind.iter().any(|kind| kind == "bin")
+                    || package.bin.as_ref() != Some(&artifact.target.name)
+                {
+                    return None;
+                }
+
+                artifact.executable?
+            };
+
+            let binary_size = std::fs::metadata(&executable_path)
+                .map(|metadata| metadata.len())
+                .unwrap_or_default();
+
+            Some(BuiltService {
+                features: artifact.features,
+                compiler_cache_stats,
+                binary_size,
+                build_duration,
+                crate_timings: output.timings.clone(),
+                ..BuiltService::new(
+                    executable_path.into_std_path_buf(),
+                    wasm,
+                    package.name.clone(),
+                    package.root.clone(),
+                    package.manifest_path.clone(),
+                )
+            })
+        })
+        .collect();
+
+    if let Some(package) = packages.iter().find(|package| {
+        !services
+            .iter()
+            .any(|service| service.package_name == package.name)
+    }) {
+        return Err(BuildError::MissingTarget(package.name.clone()));
+    }
+
+    Ok(services)
+}
+
+/// Download the dependencies of the workspace, so that it can be built without network
+async fn fetch(context: &BuildContext<'_>) -> Result<(), BuildError> {
+    send_message(
+        &context.tx,
+        Message::TextLine("Fetching dependencies".to_string()),
+    );
+
+    let mut cargo = cargo_command("fetch", context.manifest_path);
+    context.env.apply(&mut cargo);
+
+    if context.locked {
+        cargo.arg("--locked");
+    }
+
+    // Fetching runs no code of the service, so it can reach the network
+    let sandbox = context.sandbox.map(|sandbox| Sandbox {
+        network: true,
+        ..sandbox
+    });
+    let (cargo, _container) = sandboxed(sandbox.as_ref(), cargo);
+
+    let output = run_cargo(
+        cargo,
+        &context.tx,
+        &context.cancellation_token,
+        context.deadline,
+    )
+    .await?;
+
+    if !output.status.success() {
+        return Err(BuildError::Other(anyhow!(
+            "fetching the dependencies failed, `cargo fetch` exited with {}",
+            output.status
+        )));
+    }
+
+    Ok(())
+}
+
+/// Get the `cargo build` command which builds the given packages
+fn build_command(context: &BuildContext<'_>, packages: &[ServicePackage], wasm: bool) -> Command {
+    let BuildContext {
+        manifest_path,
+        release_mode,
+        locked,
+        offline,
+        options,
+        ..
+    } = *context;
+
+    let mut cargo = cargo_command("build", manifest_path);
+    context.env.apply(&mut cargo);
+    // Not `json-render-diagnostics`, which would take the diagnostics out of the messages: they
+    // are sent on with the others, and printed from the rendering rustc puts in them
+    cargo.arg("--message-format=json");
+
+    if wasm {
+        // cargo locks the target directory while building, so shuttle-next services get their
+        // own to not wait on alpha services
+        cargo
+            .arg("--target-dir")
+            .arg(context.target_dir.join(NEXT_TARGET_DIR));
+    } else if let Some(target_dir) = &options.target_dir {
+        cargo.arg("--target-dir").arg(target_dir);
+    }
+
+    cargo.arg("--profile").arg(context.profile());
+
+    // `--frozen` is the same as `--locked --offline`
+    if locked && offline {
+        cargo.arg("--frozen");
+    } else if locked {
+        cargo.arg("--locked");
+    } else if offline {
+        cargo.arg("--offline");
+    }
+
+    if options.timings {
+        cargo.args(["-Zunstable-options", "--timings=json"]);
+    }
+
+    if release_mode {
+        // This sets the max workers for cargo build to 4 for release mode (aka deployment),
+        // but leaves it as default (num cpus) for local runs
+        cargo.arg("--jobs=4");
+    }
+
+    if wasm {
+        cargo.arg("--target").arg(NEXT_TARGET);
+    } else if let Some(target) = &options.target {
+        cargo.arg("--target").arg(target);
+    }
+
+    for package in packages {
+        cargo.arg("--package").arg(&package.name);
+
+        if let Some(bin) = &package.bin {
+            cargo.arg("--bin").arg(bin);
+        }
+    }
+
+    if !options.features.is_empty() {
+        cargo.arg("--features").arg(options.features.join(","));
+    }
+
+    if options.all_features {
+        cargo.arg("--all-features");
+    }
+
+    if options.no_default_features {
+        cargo.arg("--no-default-features");
+    }
+
+    if let Some(rustc_wrapper) = &options.rustc_wrapper {
+        cargo.env("RUSTC_WRAPPER", rustc_wrapper);
+    }
+
+    cargo
+}
+
+/// Get a `cargo <subcommand>` command for the workspace at `manifest_path`.
+///
+/// It runs from the directory of the manifest, so that cargo picks up the `.cargo/config.toml`
+/// of the project and not the one of wherever we were started from.
+fn cargo_command(subcommand: &str, manifest_path: &Path) -> Command {
+    // Use the same cargo which invoked us when we are running as a cargo subcommand
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let mut command = Command::new(cargo);
+    command
+        .current_dir(project_dir(manifest_path))
+        .arg(subcommand)
+        .arg("--manifest-path")
+        .arg(manifest_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    // Make cargo the leader of a new process group, so that everything it starts can be killed
+    // together when a build is aborted
+    #[cfg(unix)]
+    lead_process_group(&mut command);
+
+    command
+}
+
+/// The result of a cargo invocation
+struct CargoOutput {
+    status: ExitStatus,
+    /// All the artifacts cargo reported while running
+    artifacts: Vec<Artifact>,
+    /// All the errors rustc reported while running
+    diagnostics: Vec<Diagnostic>,
+    /// The lines cargo wrote to stderr
+    stderr: Vec<String>,
+    /// How long each crate took to compile, if cargo was asked to report it
+    timings: Vec<CrateTiming>,
+}
+
+/// Spawn a cargo command and send every line it outputs to `tx` until it exits.
+///
+/// Lines on stdout are parsed as cargo JSON messages while the human readable status lines cargo
+/// prints to stderr are sent as [Message::TextLine]s. cargo is killed if `cancellation_token` is
+/// cancelled or the `deadline` passes first.
+async fn run_cargo(
+    mut cargo: Command,
+    tx: &Sender<Message>,
+    cancellation_token: &CancellationToken,
+    deadline: Option<(Instant, Duration)>,
+) -> Result<CargoOutput, BuildError> {
+    trace!(?cargo, "spawning cargo");
+    let mut child = cargo.spawn().map_err(|error| match error.kind() {
+        ErrorKind::NotFound => BuildError::ToolchainMissing("cargo could not be found".to_string()),
+        _ => BuildError::Io(error),
+    })?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .context("failed to capture the stdout of cargo")?;
+    let stderr = child
+        .stderr
+        .take()
+        .context("failed to capture the stderr of cargo")?;
+
+    let stderr_tx = tx.clone();
+    let stderr_handle = tokio::spawn(async move {
+        let mut stderr_lines = Vec::new();
+        let mut lines = BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            stderr_lines.push(line.clone());
+            send_message(&stderr_tx, Message::TextLine(line));
+        }
+
+        stderr_lines
+    });
+
+    let read_output = async {
+        let mut artifacts = Vec::new();
+        let mut diagnostics = Vec::new();
+        let mut timings = Vec::new();
+        let mut lines = BufReader::new(stdout).lines();
+        while let Some(line) = lines.next_line().await? {
+            if let Some(timing) = CrateTiming::parse(&line) {
+                timings.push(timing);
+                continue;
+            }
+
+            for message in Message::parse_stream(line.as_bytes()) {
+                trace!(?message, "parsed cargo message");
+                match message {
+                    Ok(message) => {
+                        match &message {
+                            Message::CompilerArtifact(artifact) => artifacts.push(artifact.clone()),
+                            Message::CompilerMessage(CompilerMessage { message, .. })
+                                if matches!(
+                                    message.level,
+                                    DiagnosticLevel::Error | DiagnosticLevel::Ice
+                                ) =>
+                            {
+                                diagnostics.push(message.clone())
+                            }
+                            _ => {}
+                        }
+
+                        send_message(tx, message);
+                    }
+                    Err(error) => {
+                        error!("failed to parse cargo message: {error}");
+                    }
+                }
+            }
+        }
+
+        let stderr = match stderr_handle.await {
+            Ok(stderr) => stderr,
+            Err(error) => {
+                error!("failed to capture the stderr of cargo: {error}");
+                Vec::new()
+            }
+        };
+
+        Ok::<_, BuildError>((artifacts, diagnostics, stderr, timings))
+    };
+
+    let deadline = async {
+        match deadline {
+            Some((deadline, timeout)) => {
+                sleep_until(deadline).await;
+                timeout
+            }
+            None => pending().await,
+        }
+    };
+
+    let (artifacts, diagnostics, stderr, timings) = tokio::select! {
+        output = read_output => output?,
+        _ = cancellation_token.cancelled() => {
+            kill_process_group(&mut child).await;
+            return Err(BuildError::Cancelled);
+        }
+        timeout = deadline => {
+            kill_process_group(&mut child).await;
+            return Err(BuildError::TimedOut(timeout));
+        }
+    };
+
+    Ok(CargoOutput {
+        status: child.wait().await?,
+        artifacts,
+        diagnostics,
+        stderr,
+        timings,
+    })
+}
+
+/// Make the process of `command` the leader of a new process group once it is spawned
+#[cfg(unix)]
+fn lead_process_group(command: &mut Command) {
+    // SAFETY: `setpgid` is async-signal-safe, so it can run between the fork and the exec
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setpgid(0, 0) == 0 {
+                Ok(())
+            } else {
+                Err(std::io::Error::last_os_error())
+            }
+        });
+    }
+}
+
+/// Kill a process which leads its own process group, like cargo, along with everything it started
+async fn kill_process_group(child: &mut Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // SAFETY: `killpg` has no memory safety requirements. The child is the leader of its own
+        // process group, so this only reaches the child and its descendants.
+        unsafe {
+            libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+        }
+    }
+
+    if let Err(error) = child.kill().await {
+        debug!(%error, "failed to kill process");
+    }
+}
+
+fn send_message(tx: &Sender<Message>, message: Message) {
+    if let Err(error) = tx.send(message) {
+        error!("failed to send cargo message on channel: {error}");
+    }
+}
+
+/// Get the directory holding the manifest at `manifest_path`
+fn project_dir(manifest_path: &Path) -> PathBuf {
+    match manifest_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Check if two paths point to the same file, even if they are not spelled the same
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    a == b
+        || matches!(
+            (a.canonicalize(), b.canonicalize()),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+/// What `cargo clean` removed
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CleanSummary {
+    /// How much smaller the target directory got
+    pub bytes_freed: u64,
+    /// The files and directories cargo removed
+    pub removed_paths: Vec<PathBuf>,
+}
+
+/// Remove the build artifacts of the project at `project_path`.
+///
+/// The output of cargo is sent as [Message::TextLine]s through `tx` while it runs, and cargo is
+/// killed when `cancellation_token` is cancelled.
+pub async fn clean_crate(
+    project_path: &Path,
+    release_mode: bool,
+    tx: Sender<Message>,
+    cancellation_token: CancellationToken,
+) -> Result<CleanSummary, BuildError> {
+    let manifest_path = std::env::current_dir()?
+        .join(project_path)
+        .join("Cargo.toml");
+    let target_dir = target_dir(&manifest_path).await?;
+    let size_before = dir_size(&target_dir).await;
+
+    let mut cargo = cargo_command("clean", &manifest_path);
+    cargo
+        .arg("--verbose")
+        .arg("--profile")
+        .arg(if release_mode { "release" } else { "dev" });
+
+    let output = run_cargo(cargo, &tx, &cancellation_token, None).await?;
+
+    if !output.status.success() {
+        return Err(anyhow!("`cargo clean` exited with {}", output.status).into());
+    }
+
+    let removed_paths = output
+        .stderr
+        .iter()
+        .filter_map(|line| line.trim().strip_prefix("Removing "))
+        .map(PathBuf::from)
+        .collect();
+
+    Ok(CleanSummary {
+        bytes_freed: size_before.saturating_sub(dir_size(&target_dir).await),
+        removed_paths,
+    })
+}
+
+/// Remove the whole target directory of the project at `project_path`, or `target_dir` when it is
+/// built there instead. Unlike [clean_crate], this also removes the incremental compilation state
+/// and the build cache, for when they got corrupted.
+pub async fn purge_crate(
+    project_path: &Path,
+    target_dir: Option<&Path>,
+) -> Result<CleanSummary, BuildError> {
+    let target_dir = match target_dir {
+        Some(target_dir) => target_dir.to_path_buf(),
+        None => {
+            let manifest_path = std::env::current_dir()?
+                .join(project_path)
+                .join("Cargo.toml");
+
+            // Nothing was built when there is no project yet
+            if !manifest_path.exists() {
+                return Ok(CleanSummary::default());
+            }
+
+            self::target_dir(&manifest_path).await?
+        }
+    };
+
+    if !target_dir.exists() {
+        return Ok(CleanSummary::default());
+    }
+
+    let bytes_freed = dir_size(&target_dir).await;
+    tokio::fs::remove_dir_all(&target_dir).await?;
+
+    Ok(CleanSummary {
+        bytes_freed,
+        removed_paths: vec![target_dir],
+    })
+}
+
+/// Ask cargo where the workspace at `manifest_path` puts its build artifacts
+async fn target_dir(manifest_path: &Path) -> anyhow::Result<PathBuf> {
+    let mut command = MetadataCommand::new();
+    command
+        .manifest_path(manifest_path)
+        .current_dir(project_dir(manifest_path))
+        .no_deps();
+
+    let metadata = tokio::task::spawn_blocking(move || command.exec())
+        .await
+        .context("failed to wait for cargo metadata")?
+        .context("failed to get cargo metadata")?;
+
+    Ok(metadata.target_directory.into_std_path_buf())
+}
+
+/// Add up the size of all the files under `path`, which is zero if it does not exist
+pub async fn dir_size(path: &Path) -> u64 {
+    fn walk(path: &Path) -> u64 {
+        let Ok(entries) = std::fs::read_dir(path) else {
+            return 0;
+        };
+
+        entries
+            .flatten()
+            .map(|entry| match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => walk(&entry.path()),
+                Ok(_) => entry.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+                Err(_) => 0,
+            })
+            .sum()
+    }
+
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || walk(&path))
+        .await
+        .unwrap_or_default()
+}
+
+/// Get the default compile config for a project in `cwd` with output redirected to writer.
+///
+/// The configuration of cargo, like `build.target-dir`, is discovered from `cwd`.
+pub fn get_config(writer: PipeWriter, cwd: PathBuf) -> anyhow::Result<Config> {
+    let mut shell = Shell::from_write(Box::new(writer));
+    shell.set_verbosity(Verbosity::Normal);
+    let homedir = homedir(&cwd).ok_or_else(|| {
+        anyhow!(
+            "Cargo couldn't find your home directory. \
+                 This probably means that $HOME was not set."
+        )
+    })?;
+
+    Ok(Config::new(shell, cwd, homedir))
+}
+
+fn is_next(package: &Package) -> bool {
+    package
+        .dependencies()
+        .iter()
+        .any(|dependency| dependency.package_name() == NEXT_NAME)
+}
+
+fn is_alpha(package: &Package) -> bool {
+    package
+        .dependencies()
+        .iter()
+        .any(|dependency| dependency.package_name() == RUNTIME_NAME)
+}
+
+/// Make sure the project is a binary for alpha projects, and pick the binary to run when it has
+/// several.
+///
+/// A `bin` in the Shuttle.toml of the package comes first, then the `default-run` of the package
+/// and then the binary with the name of the package.
+fn select_binary(package: &Package) -> anyhow::Result<String> {
+    let bins: Vec<&str> = package
+        .targets()
+        .iter()
+        .filter(|target| target.is_bin())
+        .map(|target| target.name())
+        .collect();
+
+    match bins.as_slice() {
+        [] => bail!("Your Shuttle project must be a binary."),
+        [bin] => return Ok(bin.to_string()),
+        _ => {}
+    }
+
+    let requested = match ShuttleToml::from_path(&package.root().join("Shuttle.toml"))?.bin {
+        Some(bin) => Some(bin),
+        None => package.manifest().default_run().map(ToString::to_string),
+    };
+
+    if let Some(bin) = requested {
+        if !bins.contains(&bin.as_str()) {
+            bail!(
+                "The `{bin}` binary requested for `{}` does not exist. Its binaries are: {}",
+                package.name(),
+                bins.join(", ")
+            );
+        }
+
+        return Ok(bin);
+    }
+
+    if bins.contains(&package.name().as_str()) {
+        return Ok(package.name().to_string());
+    }
+
+    bail!(
+        "Your Shuttle project has several binaries: {}. Please choose the one to run with `bin = \"name\"` in Shuttle.toml or `default-run` in Cargo.toml.",
+        bins.join(", ")
+    )
+}
+
+/// Make sure "cdylib" is set for shuttle-next projects, else set it if possible.
+fn ensure_cdylib(package: &Package) -> anyhow::Result<()> {
+    if package.targets().iter().any(|target| target.is_lib()) {
+        Ok(())
+    } else {
+        bail!("Your Shuttle next project must be a library. Please add `[lib]` to your Cargo.toml file.")
+    }
+}
+
+/// Make sure a profile requested in Shuttle.toml is either built into cargo or defined by the workspace
+fn check_profile_exists(ws: &Workspace, profile: &str) -> anyhow::Result<()> {
+    const BUILT_IN_PROFILES: [&str; 4] = ["dev", "release", "test", "bench"];
+
+    let is_custom = ws
+        .profiles()
+        .map(|profiles| profiles.get_all().contains_key(profile))
+        .unwrap_or_default();
+
+    if BUILT_IN_PROFILES.contains(&profile) || is_custom {
+        Ok(())
+    } else {
+        bail!("The `{profile}` profile set in Shuttle.toml does not exist. Please add `[profile.{profile}]` to the Cargo.toml at the root of your workspace.")
+    }
+}
+
+/// Ensure `panic = "abort"` is not set:
+fn check_no_panic(ws: &Workspace) -> anyhow::Result<()> {
+    if let Some(profiles) = ws.profiles() {
+        for profile in profiles.get_all().values() {
+            if profile.panic.as_deref() == Some("abort") {
+                return Err(anyhow!("Your Shuttle project cannot have panics that abort. Please ensure your Cargo.toml does not contain `panic = \"abort\"` for any profiles."));
+            }
+        }
+    }
+
+    Ok(())
+}
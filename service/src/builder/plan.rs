@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use cargo_metadata::Message;
+use crossbeam_channel::Sender;
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+use super::env::BuildEnv;
+use super::{
+    build_command, find_services, BuildContext, BuildError, BuildOptions, ServicePackage,
+    NEXT_TARGET, NEXT_TARGET_DIR,
+};
+
+/// What [super::build_workspace] would do to build the services of a project
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct BuildPlan {
+    pub workspace_root: PathBuf,
+    /// The cargo profile the services are built with
+    pub profile: String,
+    /// The `pre_build` hook from Shuttle.toml
+    pub pre_build: Option<String>,
+    /// The cargo invocations, which run at the same time
+    pub steps: Vec<BuildStep>,
+    /// The `post_build` hook from Shuttle.toml
+    pub post_build: Option<String>,
+}
+
+/// One cargo invocation of a [BuildPlan]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct BuildStep {
+    /// Whether this step builds shuttle-next services
+    pub is_wasm: bool,
+    /// The program and its arguments
+    pub command: Vec<String>,
+    /// The variables from the `[build.env]` of Shuttle.toml cargo is run with
+    pub env: BTreeMap<String, String>,
+    pub services: Vec<PlannedService>,
+}
+
+/// A service a [BuildStep] builds
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PlannedService {
+    pub package_name: String,
+    pub manifest_path: PathBuf,
+    /// The binary target built for alpha services
+    pub bin: Option<String>,
+    /// Where the service is expected to be built. The real path is the one cargo reports when
+    /// building, which differs when a `.cargo/config.toml` moves the artifacts.
+    pub executable_path: PathBuf,
+}
+
+/// Find out how the services of the project at `project_path` would be built, without building
+/// them.
+///
+/// The same checks as a real build are done first, so a project which cannot be built fails here
+/// in the same way, except for compile errors.
+pub async fn plan_workspace(
+    project_path: &Path,
+    release_mode: bool,
+    tx: Sender<Message>,
+    options: &BuildOptions,
+) -> Result<BuildPlan, BuildError> {
+    let manifest_path = std::env::current_dir()?
+        .join(project_path)
+        .join("Cargo.toml");
+    let mut services = find_services(&manifest_path, options.target_dir.as_deref(), tx.clone())?;
+    services.select(options)?;
+    services.check_versions(options)?;
+
+    let locked = options.locked || services.shuttle_toml.build.locked;
+    if locked && !services.workspace_root.join("Cargo.lock").exists() {
+        return Err(BuildError::MissingLockfile(services.workspace_root));
+    }
+
+    let build = &services.shuttle_toml.build;
+    let context = BuildContext {
+        manifest_path: &manifest_path,
+        release_mode,
+        profile: build.profile.as_deref(),
+        target_dir: &services.target_dir,
+        locked,
+        offline: options.offline || build.offline.unwrap_or_default(),
+        prefetch: false,
+        options,
+        env: BuildEnv::new(&build.env, options.scrub_env),
+        // The plan shows the commands as they would run on this machine
+        sandbox: None,
+        deadline: None,
+        cancellation_token: CancellationToken::new(),
+        tx,
+    };
+
+    let mut steps = Vec::new();
+    for (packages, wasm) in [(&services.alpha, false), (&services.next, true)] {
+        if packages.is_empty() {
+            continue;
+        }
+
+        let command = build_command(&context, packages, wasm);
+        let command = command.as_std();
+
+        steps.push(BuildStep {
+            is_wasm: wasm,
+            command: std::iter::once(command.get_program())
+                .chain(command.get_args())
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect(),
+            env: build.env.clone(),
+            services: packages
+                .iter()
+                .map(|package| plan_service(&context, package, wasm))
+                .collect(),
+        });
+    }
+
+    Ok(BuildPlan {
+        workspace_root: services.workspace_root.clone(),
+        profile: context.profile().to_string(),
+        pre_build: services.shuttle_toml.hooks.pre_build.clone(),
+        steps,
+        post_build: services.shuttle_toml.hooks.post_build.clone(),
+    })
+}
+
+fn plan_service(
+    context: &BuildContext<'_>,
+    package: &ServicePackage,
+    wasm: bool,
+) -> PlannedService {
+    // Cargo puts the artifacts of its built-in profiles in directories of other names
+    let profile_dir = match context.profile() {
+        "dev" | "test" => "debug",
+        "release" | "bench" => "release",
+        profile => profile,
+    };
+
+    let executable_path = if wasm {
+        context
+            .target_dir
+            .join(NEXT_TARGET_DIR)
+            .join(NEXT_TARGET)
+            .join(profile_dir)
+            .join(format!("{}.wasm", package.name.replace('-', "_")))
+    } else {
+        let mut dir = context.target_dir.to_path_buf();
+        if let Some(target) = &context.options.target {
+            dir.push(target);
+        }
+
+        let bin = package.bin.as_deref().unwrap_or(&package.name);
+        dir.join(profile_dir)
+            .join(format!("{bin}{}", std::env::consts::EXE_SUFFIX))
+    };
+
+    PlannedService {
+        package_name: package.name.clone(),
+        manifest_path: package.manifest_path.clone(),
+        bin: package.bin.clone(),
+        executable_path,
+    }
+}
+
+impl fmt::Display for BuildPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Workspace: {}", self.workspace_root.display())?;
+        writeln!(f, "Profile: {}", self.profile)?;
+
+        if let Some(pre_build) = &self.pre_build {
+            writeln!(f, "Before building, run: {pre_build}")?;
+        }
+
+        for step in &self.steps {
+            let kind = if step.is_wasm {
+                "shuttle-next"
+            } else {
+                "alpha"
+            };
+
+            writeln!(f)?;
+            writeln!(f, "Build the {kind} services with:")?;
+            for (key, value) in &step.env {
+                writeln!(f, "  {key}={value}")?;
+            }
+            writeln!(f, "  {}", step.command.join(" "))?;
+
+            for service in &step.services {
+                writeln!(
+                    f,
+                    "  - {} => {}",
+                    service.package_name,
+                    service.executable_path.display()
+                )?;
+            }
+        }
+
+        if let Some(post_build) = &self.post_build {
+            writeln!(f)?;
+            writeln!(f, "After building, run: {post_build}")?;
+        }
+
+        Ok(())
+    }
+}
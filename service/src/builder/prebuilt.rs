@@ -0,0 +1,277 @@
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use cargo_metadata::semver::Version;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tracing::debug;
+
+use super::assets::resolve_assets;
+use super::toolchain::host_target;
+use super::{BuildError, BuildOptions, BuildPlan, BuiltService, MUSL_TARGET, NEXT_TARGET};
+use crate::{NEXT_NAME, RUNTIME_NAME};
+
+/// Where the executable or wasm module of a service which was built beforehand is put in the
+/// archive of its project
+pub const ARTIFACT_PATH: &str = ".shuttle/artifact";
+/// Where the [ArtifactMetadata] of that artifact is put in the archive
+pub const ARTIFACT_METADATA_PATH: &str = ".shuttle/artifact.json";
+
+/// A service which was built before it was deployed, so that the deployer can tell whether it can
+/// run it without building it again
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ArtifactMetadata {
+    pub package_name: String,
+    /// The directory of the service, relative to the root of the workspace
+    pub working_directory: PathBuf,
+    pub is_wasm: bool,
+    /// The target triple the artifact was built for
+    pub target: String,
+    /// The version of `shuttle-runtime`, or `shuttle-next`, in the Cargo.lock of the workspace
+    pub runtime_version: Option<Version>,
+}
+
+impl ArtifactMetadata {
+    /// Describe an artifact of the service `plan` builds, which was built for `target` or else for
+    /// this machine
+    pub async fn new(plan: &BuildPlan, target: Option<String>) -> Result<Self, BuildError> {
+        // Collected so that no borrowing iterator is held across the awaits below, which would
+        // keep the future from being `Send`
+        let services: Vec<_> = plan
+            .steps
+            .iter()
+            .flat_map(|step| {
+                step.services
+                    .iter()
+                    .map(move |service| (step.is_wasm, service))
+            })
+            .collect();
+        let (is_wasm, service) = match services[..] {
+            [service] => service,
+            [] => return Err(anyhow!("there is no service in this workspace").into()),
+            _ => {
+                let names = services
+                    .iter()
+                    .map(|(_, service)| service.package_name.clone())
+                    .collect();
+
+                return Err(BuildError::MultipleServices(names));
+            }
+        };
+
+        let target = if is_wasm {
+            NEXT_TARGET.to_string()
+        } else {
+            match target {
+                Some(target) => target,
+                None => host_target()
+                    .await
+                    .ok_or_else(|| anyhow!("could not tell the target triple of this machine"))?,
+            }
+        };
+
+        let working_directory = service
+            .manifest_path
+            .parent()
+            .and_then(|dir| dir.strip_prefix(&plan.workspace_root).ok())
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+
+        Ok(Self {
+            package_name: service.package_name.clone(),
+            working_directory,
+            is_wasm,
+            target,
+            runtime_version: locked_runtime_version(&plan.workspace_root, is_wasm).await,
+        })
+    }
+
+    /// Make sure the artifact can be run by services built with `options`
+    pub async fn check(&self, options: &BuildOptions) -> Result<(), BuildError> {
+        if !self.is_wasm {
+            let supported = match &options.target {
+                Some(target) => Some(target.clone()),
+                None => host_target().await,
+            };
+
+            if let Some(supported) = supported {
+                if !runs_on(&self.target, &supported) {
+                    return Err(BuildError::UnsupportedTarget {
+                        target: self.target.clone(),
+                        supported,
+                    });
+                }
+            }
+        }
+
+        if let (Some(supported), Some(version)) =
+            (&options.supported_version, &self.runtime_version)
+        {
+            if !compatible(version, supported) {
+                return Err(BuildError::UnsupportedVersion {
+                    package: self.package_name.clone(),
+                    dependency: self.runtime_name().to_string(),
+                    requirement: version.to_string(),
+                    supported: supported.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn runtime_name(&self) -> &'static str {
+        if self.is_wasm {
+            NEXT_NAME
+        } else {
+            RUNTIME_NAME
+        }
+    }
+}
+
+/// Use the artifact in the project extracted at `project_path` instead of building its service
+pub async fn load_prebuilt(
+    project_path: &Path,
+    options: &BuildOptions,
+) -> Result<BuiltService, BuildError> {
+    let metadata = fs::read(project_path.join(ARTIFACT_METADATA_PATH)).await?;
+    let metadata: ArtifactMetadata =
+        serde_json::from_slice(&metadata).context("the artifact metadata is not valid")?;
+
+    // The metadata comes from the user, so it may not point outside of the project
+    if !metadata
+        .working_directory
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(anyhow!("the working directory of the artifact is not in the project").into());
+    }
+
+    metadata.check(options).await?;
+
+    let executable_path = project_path.join(ARTIFACT_PATH);
+    let working_directory = project_path.join(&metadata.working_directory);
+    let manifest_path = working_directory.join("Cargo.toml");
+
+    let mut service = BuiltService::new(
+        executable_path.clone(),
+        metadata.is_wasm,
+        metadata.package_name,
+        working_directory.clone(),
+        manifest_path,
+    );
+    service.binary_size = fs::metadata(&executable_path).await?.len();
+    service.assets = resolve_assets(&working_directory)?;
+
+    Ok(service)
+}
+
+/// Whether an executable built for `target` runs where services are built for `supported`
+fn runs_on(target: &str, supported: &str) -> bool {
+    // Static executables run on any x86_64 Linux
+    target == supported || (target == MUSL_TARGET && supported.starts_with("x86_64-unknown-linux-"))
+}
+
+/// Whether `version` can be used where `supported` is expected, by the rules cargo uses for
+/// caret requirements
+fn compatible(version: &Version, supported: &Version) -> bool {
+    match (supported.major, supported.minor) {
+        (0, 0) => version.major == 0 && version.minor == 0 && version.patch == supported.patch,
+        (0, minor) => version.major == 0 && version.minor == minor && version >= supported,
+        (major, _) => version.major == major && version >= supported,
+    }
+}
+
+/// Find the version of the shuttle runtime the Cargo.lock of the workspace at `workspace_root` has
+async fn locked_runtime_version(workspace_root: &Path, is_wasm: bool) -> Option<Version> {
+    let name = if is_wasm { NEXT_NAME } else { RUNTIME_NAME };
+
+    let lockfile = fs::read_to_string(workspace_root.join("Cargo.lock"))
+        .await
+        .map_err(|error| debug!(%error, "failed to read the Cargo.lock"))
+        .ok()?;
+    let lockfile: toml::Value = lockfile
+        .parse()
+        .map_err(|error| debug!(%error, "failed to parse the Cargo.lock"))
+        .ok()?;
+
+    lockfile
+        .get("package")?
+        .as_array()?
+        .iter()
+        .find(|package| package.get("name").and_then(toml::Value::as_str) == Some(name))
+        .and_then(|package| package.get("version")?.as_str()?.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use cargo_metadata::semver::Version;
+    use tempfile::tempdir;
+
+    use super::{compatible, locked_runtime_version, runs_on};
+
+    #[test]
+    fn target() {
+        assert!(runs_on(
+            "x86_64-unknown-linux-gnu",
+            "x86_64-unknown-linux-gnu"
+        ));
+        assert!(runs_on(
+            "x86_64-unknown-linux-musl",
+            "x86_64-unknown-linux-gnu"
+        ));
+        assert!(!runs_on(
+            "aarch64-unknown-linux-gnu",
+            "x86_64-unknown-linux-gnu"
+        ));
+        assert!(!runs_on("x86_64-apple-darwin", "x86_64-unknown-linux-gnu"));
+    }
+
+    #[test]
+    fn version() {
+        let supported = Version::new(0, 17, 0);
+
+        assert!(compatible(&Version::new(0, 17, 0), &supported));
+        assert!(compatible(&Version::new(0, 17, 2), &supported));
+        assert!(!compatible(&Version::new(0, 16, 0), &supported));
+        assert!(!compatible(&Version::new(0, 18, 0), &supported));
+
+        let supported = Version::new(1, 2, 0);
+        assert!(compatible(&Version::new(1, 3, 0), &supported));
+        assert!(!compatible(&Version::new(1, 1, 0), &supported));
+        assert!(!compatible(&Version::new(2, 0, 0), &supported));
+    }
+
+    #[tokio::test]
+    async fn locked_version() {
+        let dir = tempdir().unwrap();
+        assert_eq!(locked_runtime_version(dir.path(), false).await, None);
+
+        fs::write(
+            dir.path().join("Cargo.lock"),
+            r#"
+version = 3
+
+[[package]]
+name = "shuttle-next"
+version = "0.16.0"
+
+[[package]]
+name = "shuttle-runtime"
+version = "0.17.1"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            locked_runtime_version(dir.path(), false).await,
+            Some(Version::new(0, 17, 1))
+        );
+        assert_eq!(
+            locked_runtime_version(dir.path(), true).await,
+            Some(Version::new(0, 16, 0))
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use tokio::sync::watch;
+use tracing::trace;
+
+/// How urgent a build is. Builds with a higher priority always start before those with a lower
+/// one.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum BuildPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// Where a build is in a [BuildQueue]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum QueuePosition {
+    /// The build is waiting for `ahead` other builds to start first
+    Waiting { ahead: usize },
+    /// The build can start
+    Ready,
+}
+
+/// A queue which limits how many builds run at the same time.
+///
+/// Waiting builds are started by their priority first. Between builds of the same priority, the
+/// projects with the fewest running builds go first, so that one project deploying over and over
+/// cannot starve the others. Otherwise builds start in the order they were queued.
+#[derive(Clone, Debug)]
+pub struct BuildQueue {
+    state: Arc<Mutex<QueueState>>,
+}
+
+#[derive(Debug)]
+struct QueueState {
+    concurrency: usize,
+    running: usize,
+    running_per_project: HashMap<String, usize>,
+    waiting: Vec<Waiting>,
+    next_ticket: u64,
+}
+
+#[derive(Debug)]
+struct Waiting {
+    ticket: u64,
+    project: String,
+    priority: BuildPriority,
+    position: watch::Sender<QueuePosition>,
+}
+
+/// A build waiting in a [BuildQueue]. Dropping it leaves the queue.
+#[derive(Debug)]
+pub struct QueuedBuild {
+    queue: BuildQueue,
+    ticket: u64,
+    project: String,
+    position: watch::Receiver<QueuePosition>,
+    /// Whether the slot given to this build was taken by [QueuedBuild::ready]
+    taken: bool,
+}
+
+/// Permission to run a build. The next build in the queue can start when it is dropped.
+#[derive(Debug)]
+pub struct BuildSlot {
+    queue: BuildQueue,
+    project: String,
+}
+
+impl BuildQueue {
+    /// Create a queue which runs at most `concurrency` builds at the same time
+    pub fn new(concurrency: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(QueueState {
+                concurrency: concurrency.max(1),
+                running: 0,
+                running_per_project: HashMap::new(),
+                waiting: Vec::new(),
+                next_ticket: 0,
+            })),
+        }
+    }
+
+    /// Queue a build of `project`
+    pub fn enqueue(&self, project: impl Into<String>, priority: BuildPriority) -> QueuedBuild {
+        let project = project.into();
+        let mut state = self.lock();
+
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+
+        let (position, receiver) = watch::channel(QueuePosition::Waiting {
+            ahead: state.waiting.len(),
+        });
+        state.waiting.push(Waiting {
+            ticket,
+            project: project.clone(),
+            priority,
+            position,
+        });
+        trace!(ticket, %project, ?priority, "queued build");
+
+        state.dispatch();
+
+        QueuedBuild {
+            queue: self.clone(),
+            ticket,
+            project,
+            position: receiver,
+            taken: false,
+        }
+    }
+
+    /// How many builds are running
+    pub fn running(&self) -> usize {
+        self.lock().running
+    }
+
+    /// How many builds are waiting to start
+    pub fn waiting(&self) -> usize {
+        self.lock().waiting.len()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        // The state is only changed by code which does not panic, so a poisoned lock is still valid
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl QueueState {
+    /// Start waiting builds while there is room for them, then tell the others where they are
+    fn dispatch(&mut self) {
+        self.sort();
+
+        while self.running < self.concurrency && !self.waiting.is_empty() {
+            let next = self.waiting.remove(0);
+
+            self.running += 1;
+            *self
+                .running_per_project
+                .entry(next.project.clone())
+                .or_default() += 1;
+
+            trace!(ticket = next.ticket, project = %next.project, "starting build");
+            let _ = next.position.send(QueuePosition::Ready);
+
+            // Starting a build changes how the remaining builds of its project are ranked
+            self.sort();
+        }
+
+        for (ahead, waiting) in self.waiting.iter().enumerate() {
+            let position = QueuePosition::Waiting { ahead };
+            if *waiting.position.borrow() != position {
+                let _ = waiting.position.send(position);
+            }
+        }
+    }
+
+    fn sort(&mut self) {
+        let running_per_project = &self.running_per_project;
+
+        self.waiting.sort_by_key(|waiting| {
+            (
+                Reverse(waiting.priority),
+                running_per_project
+                    .get(&waiting.project)
+                    .copied()
+                    .unwrap_or_default(),
+                waiting.ticket,
+            )
+        });
+    }
+
+    fn release(&mut self, project: &str) {
+        self.running -= 1;
+
+        if let Some(running) = self.running_per_project.get_mut(project) {
+            *running -= 1;
+            if *running == 0 {
+                self.running_per_project.remove(project);
+            }
+        }
+
+        self.dispatch();
+    }
+}
+
+impl QueuedBuild {
+    /// Watch where the build is in the queue
+    pub fn position(&self) -> watch::Receiver<QueuePosition> {
+        self.position.clone()
+    }
+
+    /// Wait for the build to be allowed to start
+    pub async fn ready(mut self) -> BuildSlot {
+        while *self.position.borrow_and_update() != QueuePosition::Ready {
+            // The sender is only dropped once the build is ready, so the next loop will end
+            let _ = self.position.changed().await;
+        }
+
+        self.taken = true;
+
+        BuildSlot {
+            queue: self.queue.clone(),
+            project: self.project.clone(),
+        }
+    }
+}
+
+impl Drop for QueuedBuild {
+    fn drop(&mut self) {
+        if self.taken {
+            return;
+        }
+
+        let mut state = self.queue.lock();
+
+        if let Some(index) = state
+            .waiting
+            .iter()
+            .position(|waiting| waiting.ticket == self.ticket)
+        {
+            trace!(ticket = self.ticket, "build left the queue");
+            state.waiting.remove(index);
+            state.dispatch();
+        } else {
+            // The build was given a slot which will never be used
+            state.release(&self.project);
+        }
+    }
+}
+
+impl Drop for BuildSlot {
+    fn drop(&mut self) {
+        self.queue.lock().release(&self.project);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BuildPriority, BuildQueue, QueuePosition};
+
+    fn position(build: &super::QueuedBuild) -> QueuePosition {
+        *build.position().borrow()
+    }
+
+    #[tokio::test]
+    async fn concurrency() {
+        let queue = BuildQueue::new(1);
+
+        let first = queue.enqueue("a", BuildPriority::Normal);
+        let second = queue.enqueue("b", BuildPriority::Normal);
+        let third = queue.enqueue("c", BuildPriority::Normal);
+
+        assert_eq!(position(&first), QueuePosition::Ready);
+        assert_eq!(position(&second), QueuePosition::Waiting { ahead: 0 });
+        assert_eq!(position(&third), QueuePosition::Waiting { ahead: 1 });
+
+        let slot = first.ready().await;
+        assert_eq!(queue.running(), 1);
+        assert_eq!(queue.waiting(), 2);
+
+        // Leaving the queue moves the builds behind forward
+        drop(second);
+        assert_eq!(position(&third), QueuePosition::Waiting { ahead: 0 });
+
+        drop(slot);
+        assert_eq!(position(&third), QueuePosition::Ready);
+
+        drop(third.ready().await);
+        assert_eq!(queue.running(), 0);
+    }
+
+    #[tokio::test]
+    async fn priority_and_fairness() {
+        let queue = BuildQueue::new(1);
+
+        let running = queue.enqueue("busy", BuildPriority::Normal).ready().await;
+        let busy = queue.enqueue("busy", BuildPriority::Normal);
+        let quiet = queue.enqueue("quiet", BuildPriority::Normal);
+        let urgent = queue.enqueue("busy", BuildPriority::High);
+
+        // The urgent build goes first, then the project with no running builds
+        assert_eq!(position(&urgent), QueuePosition::Waiting { ahead: 0 });
+        assert_eq!(position(&quiet), QueuePosition::Waiting { ahead: 1 });
+        assert_eq!(position(&busy), QueuePosition::Waiting { ahead: 2 });
+
+        drop(running);
+        assert_eq!(position(&urgent), QueuePosition::Ready);
+    }
+
+    #[tokio::test]
+    async fn unused_slot_is_released() {
+        let queue = BuildQueue::new(1);
+
+        let first = queue.enqueue("a", BuildPriority::Normal);
+        let second = queue.enqueue("b", BuildPriority::Normal);
+
+        drop(first);
+        assert_eq!(position(&second), QueuePosition::Ready);
+        assert_eq!(queue.running(), 1);
+    }
+}
@@ -0,0 +1,312 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::process::Command;
+use tracing::debug;
+
+use super::env::is_inherited;
+
+/// Tells apart the containers started by this process
+static CONTAINERS: AtomicU64 = AtomicU64::new(0);
+
+/// Run the commands of a build, cargo and the hooks, in throwaway containers instead of on this
+/// machine, so that the build scripts and procedural macros of a service only get to see its own
+/// files.
+///
+/// The containers have no network, unless the Shuttle.toml of the service asks for it with
+/// `offline = false` in its `[build]` table. The dependencies are fetched in a container of their
+/// own before that, since fetching them runs no code of the service.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BuildSandbox {
+    /// The container engine to start the containers with, like `docker` or `podman`
+    pub engine: PathBuf,
+    /// The image to build in, which has to have the Rust toolchains and targets the services need
+    pub image: String,
+    /// The `CARGO_HOME` of the containers, which keeps the downloaded crates between builds
+    pub cargo_home: PathBuf,
+    /// Memory a build may use, in MiB
+    pub memory: Option<u64>,
+    /// How many cores a build may use, in thousandths of a core
+    pub millicpus: Option<u64>,
+    /// Disk space a build may use in its container, in MiB. This does not count what it writes to
+    /// the project and the target directory, and needs a storage driver of the engine with quotas.
+    pub disk: Option<u64>,
+}
+
+/// A [BuildSandbox] set up for the build of one workspace
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Sandbox<'a> {
+    pub(crate) options: &'a BuildSandbox,
+    pub(crate) workspace_root: &'a Path,
+    pub(crate) target_dir: &'a Path,
+    /// Whether the containers can reach the network
+    pub(crate) network: bool,
+}
+
+impl Sandbox<'_> {
+    /// Get a command which runs `command` in a new container, with the workspace, the target
+    /// directory and the `CARGO_HOME` mounted at the same paths, so that the paths cargo reports
+    /// also hold on this machine.
+    ///
+    /// Only the variables set on `command` itself are passed on, since our own environment has no
+    /// meaning in the container. Like the commands of a build, it gets no stdin and its output is
+    /// piped. The container is removed when the returned [Container] is dropped.
+    pub(crate) fn wrap(&self, command: Command) -> (Command, Container) {
+        let command = command.as_std();
+        let options = self.options;
+        let name = format!(
+            "shuttle-build-{}-{}",
+            std::process::id(),
+            CONTAINERS.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let mut sandboxed = Command::new(&options.engine);
+        sandboxed
+            .args(["run", "--rm", "--init", "--name", &name])
+            .args(["--cap-drop", "ALL", "--security-opt", "no-new-privileges"]);
+
+        // Files created in the mounts belong to us, not to root
+        #[cfg(unix)]
+        {
+            // SAFETY: these calls have no memory safety requirements and cannot fail
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+            sandboxed.arg("--user").arg(format!("{uid}:{gid}"));
+        }
+
+        if !self.network {
+            sandboxed.args(["--network", "none"]);
+        }
+
+        if let Some(memory) = options.memory {
+            // Without swap, so that the memory limit holds
+            sandboxed
+                .arg("--memory")
+                .arg(format!("{memory}m"))
+                .arg("--memory-swap")
+                .arg(format!("{memory}m"));
+        }
+
+        if let Some(millicpus) = options.millicpus {
+            sandboxed
+                .arg("--cpus")
+                .arg(format!("{}.{:03}", millicpus / 1000, millicpus % 1000));
+        }
+
+        if let Some(disk) = options.disk {
+            sandboxed.arg("--storage-opt").arg(format!("size={disk}m"));
+        }
+
+        for mount in self.mounts() {
+            let mut volume = OsString::from(mount);
+            volume.push(":");
+            volume.push(mount);
+            sandboxed.arg("--volume").arg(volume);
+        }
+
+        let mut cargo_home = OsString::from("CARGO_HOME=");
+        cargo_home.push(&options.cargo_home);
+        sandboxed.arg("--env").arg(cargo_home);
+
+        for (key, value) in command.get_envs() {
+            if let Some(value) = value.filter(|_| !is_inherited(key)) {
+                let mut var = key.to_os_string();
+                var.push("=");
+                var.push(value);
+                sandboxed.arg("--env").arg(var);
+            }
+        }
+
+        if let Some(dir) = command.get_current_dir() {
+            sandboxed.arg("--workdir").arg(dir);
+        }
+
+        // The program is found on the path of the image, like a `CARGO` we were given is of no use
+        let program = Path::new(command.get_program());
+        sandboxed
+            .arg(&options.image)
+            .arg(program.file_name().unwrap_or(program.as_os_str()))
+            .args(command.get_args())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        // Killing the engine does not stop the container, but this keeps it in line with the
+        // commands which are not sandboxed
+        #[cfg(unix)]
+        super::lead_process_group(&mut sandboxed);
+
+        let container = Container {
+            engine: options.engine.clone(),
+            name,
+        };
+
+        (sandboxed, container)
+    }
+
+    /// Get the version of rustc in the image, as it would build the workspace
+    pub(crate) async fn rustc_version(&self) -> Option<String> {
+        let mut rustc = Command::new("rustc");
+        rustc.arg("--version").current_dir(self.workspace_root);
+
+        let (mut rustc, _container) = self.wrap(rustc);
+        let output = rustc
+            .output()
+            .await
+            .map_err(|error| debug!(%error, "failed to get the rustc version of the image"))
+            .ok()?;
+
+        if !output.status.success() {
+            debug!(status = %output.status, "rustc of the image failed to show its version");
+            return None;
+        }
+
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// The directories the containers can read and write, which have to exist before they are
+    /// mounted
+    fn mounts(&self) -> Vec<&Path> {
+        let mut mounts = vec![self.workspace_root, self.options.cargo_home.as_path()];
+
+        if !self.target_dir.starts_with(self.workspace_root) {
+            mounts.push(self.target_dir);
+        }
+
+        mounts
+    }
+
+    /// Create the directories which are mounted in the containers
+    pub(crate) async fn prepare(&self) -> std::io::Result<()> {
+        for mount in self.mounts() {
+            tokio::fs::create_dir_all(mount).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Get `command` to run in a container of the `sandbox` when there is one, along with the container
+pub(crate) fn sandboxed(
+    sandbox: Option<&Sandbox<'_>>,
+    command: Command,
+) -> (Command, Option<Container>) {
+    match sandbox {
+        Some(sandbox) => {
+            let (command, container) = sandbox.wrap(command);
+            (command, Some(container))
+        }
+        None => (command, None),
+    }
+}
+
+/// The container of a command of a build, which is removed when this is dropped. A container
+/// which exited is gone already, but one of a build which was aborted would otherwise keep running.
+pub(crate) struct Container {
+    engine: PathBuf,
+    name: String,
+}
+
+impl Drop for Container {
+    fn drop(&mut self) {
+        let mut remove = std::process::Command::new(&self.engine);
+        remove
+            .args(["rm", "--force", &self.name])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        // Waiting on the engine in a thread of its own, since this can be dropped in async code
+        std::thread::spawn(move || {
+            if let Err(error) = remove.status() {
+                debug!(%error, "failed to remove build container");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+    use std::path::{Path, PathBuf};
+
+    use tokio::process::Command;
+
+    use super::{BuildSandbox, Sandbox};
+
+    fn args_of(command: &Command) -> Vec<&OsStr> {
+        command.as_std().get_args().collect()
+    }
+
+    #[test]
+    fn wrap() {
+        let options = BuildSandbox {
+            engine: PathBuf::from("docker"),
+            image: "rust:1.70".to_string(),
+            cargo_home: PathBuf::from("/cache/cargo"),
+            memory: Some(2048),
+            millicpus: Some(1500),
+            disk: None,
+        };
+        let sandbox = Sandbox {
+            options: &options,
+            workspace_root: Path::new("/builds/hello"),
+            target_dir: Path::new("/targets/hello"),
+            network: false,
+        };
+
+        let mut cargo = Command::new("/home/me/.cargo/bin/cargo");
+        cargo
+            .env_clear()
+            .env("PATH", "/home/me/.cargo/bin")
+            .env("SQLX_OFFLINE", "true")
+            .current_dir("/builds/hello")
+            .args(["build", "--offline"]);
+
+        let (wrapped, container) = sandbox.wrap(cargo);
+        let args = args_of(&wrapped);
+
+        assert_eq!(wrapped.as_std().get_program(), "docker");
+        assert!(args
+            .windows(2)
+            .any(|pair| pair == ["--name", container.name.as_str()]));
+        assert!(args.windows(2).any(|pair| pair == ["--network", "none"]));
+        assert!(args.windows(2).any(|pair| pair == ["--memory", "2048m"]));
+        assert!(args.windows(2).any(|pair| pair == ["--cpus", "1.500"]));
+        assert!(args
+            .windows(2)
+            .any(|pair| pair == ["--volume", "/builds/hello:/builds/hello"]));
+        assert!(args
+            .windows(2)
+            .any(|pair| pair == ["--volume", "/targets/hello:/targets/hello"]));
+        assert!(args
+            .windows(2)
+            .any(|pair| pair == ["--env", "CARGO_HOME=/cache/cargo"]));
+        assert!(args
+            .windows(2)
+            .any(|pair| pair == ["--env", "SQLX_OFFLINE=true"]));
+        assert!(args
+            .windows(2)
+            .any(|pair| pair == ["--workdir", "/builds/hello"]));
+        assert!(!args
+            .iter()
+            .any(|arg| arg.to_string_lossy().contains("PATH=")));
+        assert!(args.ends_with(&[
+            OsStr::new("rust:1.70"),
+            OsStr::new("cargo"),
+            OsStr::new("build"),
+            OsStr::new("--offline")
+        ]));
+
+        // The network is only cut off when it should be
+        let sandbox = Sandbox {
+            network: true,
+            ..sandbox
+        };
+        let (wrapped, _container) = sandbox.wrap(Command::new("cargo"));
+
+        assert!(!args_of(&wrapped).contains(&OsStr::new("--network")));
+    }
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// How long cargo took to compile one crate of a build
+#[derive(Clone, Debug, PartialEq)]
+pub struct CrateTiming {
+    /// The package the crate belongs to, like `serde 1.0.163`
+    pub package: String,
+    /// The name of the target which was compiled
+    pub target: String,
+    pub duration: Duration,
+}
+
+/// The `timing-info` message `cargo build --timings=json` outputs for every compiled crate
+#[derive(Deserialize)]
+struct TimingInfo {
+    reason: String,
+    package_id: String,
+    target: TimingTarget,
+    duration: f64,
+}
+
+#[derive(Deserialize)]
+struct TimingTarget {
+    name: String,
+}
+
+impl CrateTiming {
+    /// Parse a line of cargo output, if it is a `timing-info` message.
+    ///
+    /// These messages are not known to [cargo_metadata], so they have to be picked out by hand.
+    pub(crate) fn parse(line: &str) -> Option<Self> {
+        if !line.contains("\"timing-info\"") {
+            return None;
+        }
+
+        let info: TimingInfo = serde_json::from_str(line).ok()?;
+        if info.reason != "timing-info" {
+            return None;
+        }
+
+        // Package ids look like `serde 1.0.163 (registry+https://github.com/rust-lang/crates.io-index)`
+        let package = match info.package_id.split_once(" (") {
+            Some((package, _source)) => package.to_string(),
+            None => info.package_id,
+        };
+
+        Some(Self {
+            package,
+            target: info.target.name,
+            duration: Duration::try_from_secs_f64(info.duration).unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::CrateTiming;
+
+    #[test]
+    fn parse() {
+        let line = r#"{"reason":"timing-info","package_id":"serde 1.0.163 (registry+https://github.com/rust-lang/crates.io-index)","target":{"kind":["lib"],"crate_types":["lib"],"name":"serde","src_path":"/serde/src/lib.rs","edition":"2015","doc":true,"doctest":true,"test":true},"mode":"build","duration":1.5,"rmeta_time":0.75}"#;
+
+        assert_eq!(
+            CrateTiming::parse(line),
+            Some(CrateTiming {
+                package: "serde 1.0.163".to_string(),
+                target: "serde".to_string(),
+                duration: Duration::from_millis(1500),
+            })
+        );
+        assert_eq!(
+            CrateTiming::parse(r#"{"reason":"build-finished","success":true}"#),
+            None
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use std::fs::read_to_string;
+use std::path::Path;
+use std::process::Output;
+
+use anyhow::Context;
+use tokio::process::Command;
+use tracing::{debug, trace};
+
+/// The toolchain files rustup looks for, in the order it prefers them
+const TOOLCHAIN_FILES: [&str; 2] = ["rust-toolchain", "rust-toolchain.toml"];
+
+/// A toolchain pinned by a `rust-toolchain.toml` file
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Toolchain {
+    /// The channel to build with, like `stable`, `nightly-2023-05-01` or `1.70.0`
+    pub channel: String,
+}
+
+impl Toolchain {
+    /// Find the toolchain pinned for the workspace at `workspace_root`, if there is one.
+    pub fn find(workspace_root: &Path) -> anyhow::Result<Option<Self>> {
+        for file in TOOLCHAIN_FILES {
+            let path = workspace_root.join(file);
+            if !path.exists() {
+                continue;
+            }
+
+            let content =
+                read_to_string(&path).with_context(|| format!("failed to read {file}"))?;
+
+            return Self::parse(&content)
+                .map(Some)
+                .with_context(|| format!("failed to parse {file}"));
+        }
+
+        Ok(None)
+    }
+
+    /// Parse a toolchain file, which is either TOML or the legacy format of just a channel name
+    fn parse(content: &str) -> anyhow::Result<Self> {
+        let content = content.trim();
+
+        if !content.contains('\n') && !content.contains('=') {
+            return Ok(Self {
+                channel: content.to_string(),
+            });
+        }
+
+        let toml: toml::Value = toml::from_str(content)?;
+        let channel = toml
+            .get("toolchain")
+            .and_then(|toolchain| toolchain.get("channel"))
+            .context("couldn't find `toolchain.channel` key")?
+            .as_str()
+            .context("`toolchain.channel` key must be a string")?
+            .to_string();
+
+        Ok(Self { channel })
+    }
+
+    /// Check if rustup has this toolchain installed
+    pub async fn is_installed(&self) -> anyhow::Result<bool> {
+        let output = rustup(&["toolchain", "list"]).await?;
+        let host = host_target().await;
+
+        Ok(self.is_listed(&String::from_utf8_lossy(&output.stdout), host.as_deref()))
+    }
+
+    /// Check if this toolchain is in the output of `rustup toolchain list`, where toolchains are
+    /// named by their channel and the `host` they run on, like `nightly-x86_64-unknown-linux-gnu`.
+    /// A dated `nightly-2023-05-01` is not the `nightly` channel, so the prefix alone is not enough.
+    fn is_listed(&self, toolchains: &str, host: Option<&str>) -> bool {
+        toolchains
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .any(|name| match name.strip_prefix(self.channel.as_str()) {
+                Some("") => true,
+                Some(rest) => host.map_or(false, |host| rest.strip_prefix('-') == Some(host)),
+                None => false,
+            })
+    }
+
+    /// Check if rustup has `target` installed for this toolchain
+    pub async fn has_target(&self, target: &str) -> anyhow::Result<bool> {
+        let output = rustup(&[
+            "target",
+            "list",
+            "--installed",
+            "--toolchain",
+            self.channel.as_str(),
+        ])
+        .await?;
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .any(|line| line.trim() == target))
+    }
+
+    /// Install this toolchain with rustup, along with any `targets` it needs
+    pub async fn install(&self, targets: &[&str]) -> anyhow::Result<()> {
+        let mut args = vec![
+            "toolchain",
+            "install",
+            self.channel.as_str(),
+            "--profile",
+            "minimal",
+        ];
+        for target in targets {
+            args.extend(["--target", *target]);
+        }
+
+        let output = rustup(&args).await?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "failed to install the `{}` toolchain: {}",
+                self.channel,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Check if `target` is installed for the toolchain rustup uses in `workspace_root`.
+///
+/// Returns `None` when this cannot be known, like when rust was not installed with rustup.
+pub async fn has_active_target(workspace_root: &Path, target: &str) -> Option<bool> {
+    let output = Command::new("rustup")
+        .args(["target", "list", "--installed"])
+        .current_dir(workspace_root)
+        .output()
+        .await
+        .map_err(|error| debug!(%error, "failed to list the installed targets"))
+        .ok()?;
+
+    if !output.status.success() {
+        debug!(status = %output.status, "rustup failed to list the installed targets");
+        return None;
+    }
+
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .any(|line| line.trim() == target),
+    )
+}
+
+/// Install `target` for the toolchain rustup uses in `workspace_root`
+pub async fn add_target(workspace_root: &Path, target: &str) -> anyhow::Result<()> {
+    let output = Command::new("rustup")
+        .args(["target", "add", target])
+        .current_dir(workspace_root)
+        .output()
+        .await
+        .context("failed to run rustup, is it installed?")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "failed to install the `{target}` target: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
+
+/// Find the linker cargo will use for `target` and check that it can be run.
+///
+/// This is the one set in the `CARGO_TARGET_<TRIPLE>_LINKER` environment variable, or the system's
+/// C compiler otherwise.
+pub async fn check_linker(target: &str) -> Result<(), String> {
+    let variable = format!(
+        "CARGO_TARGET_{}_LINKER",
+        target.to_uppercase().replace(['-', '.'], "_")
+    );
+    let linker = std::env::var_os(variable).unwrap_or_else(|| "cc".into());
+
+    match Command::new(&linker).arg("--version").output().await {
+        Ok(_) => Ok(()),
+        Err(error) => {
+            debug!(%error, ?linker, "failed to run the linker");
+            Err(linker.to_string_lossy().into_owned())
+        }
+    }
+}
+
+/// Get the version of the rustc which builds the workspace at `workspace_root`, like
+/// `rustc 1.70.0 (90c541806 2023-05-31)`
+pub async fn rustc_version(workspace_root: &Path) -> Option<String> {
+    let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = Command::new(rustc)
+        .arg("--version")
+        .current_dir(workspace_root)
+        .output()
+        .await
+        .map_err(|error| debug!(%error, "failed to get the rustc version"))
+        .ok()?;
+
+    if !output.status.success() {
+        debug!(status = %output.status, "rustc failed to show its version");
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Get the target triple of this machine, like `x86_64-unknown-linux-gnu`
+pub async fn host_target() -> Option<String> {
+    let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = Command::new(rustc)
+        .arg("-vV")
+        .output()
+        .await
+        .map_err(|error| debug!(%error, "failed to get the host target"))
+        .ok()?;
+
+    if !output.status.success() {
+        debug!(status = %output.status, "rustc failed to show its host target");
+        return None;
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .map(ToString::to_string)
+}
+
+async fn rustup(args: &[&str]) -> anyhow::Result<Output> {
+    trace!(?args, "running rustup");
+    Command::new("rustup")
+        .args(args)
+        .output()
+        .await
+        .context("failed to run rustup, is it installed?")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Toolchain;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Toolchain::parse("nightly-2023-05-01\n").unwrap().channel,
+            "nightly-2023-05-01"
+        );
+        assert_eq!(
+            Toolchain::parse(
+                r#"
+[toolchain]
+channel = "1.70.0"
+targets = ["wasm32-wasi"]
+"#
+            )
+            .unwrap()
+            .channel,
+            "1.70.0"
+        );
+        assert!(Toolchain::parse("[toolchain]\nprofile = \"minimal\"").is_err());
+    }
+
+    #[test]
+    fn is_listed() {
+        let toolchains = "stable-x86_64-unknown-linux-gnu (default)\nnightly-2023-05-01-x86_64-unknown-linux-gnu\n1.70.0-x86_64-unknown-linux-gnu\n";
+        let host = Some("x86_64-unknown-linux-gnu");
+        let toolchain = |channel: &str| Toolchain {
+            channel: channel.to_string(),
+        };
+
+        assert!(toolchain("stable").is_listed(toolchains, host));
+        assert!(toolchain("nightly-2023-05-01").is_listed(toolchains, host));
+        assert!(toolchain("1.70.0").is_listed(toolchains, host));
+
+        // Only a dated nightly is installed, not the channel itself
+        assert!(!toolchain("nightly").is_listed(toolchains, host));
+        assert!(!toolchain("1.70").is_listed(toolchains, host));
+        assert!(!toolchain("stable").is_listed(toolchains, Some("aarch64-apple-darwin")));
+    }
+}
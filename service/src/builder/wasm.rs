@@ -0,0 +1,164 @@
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use cargo_metadata::Message;
+use crossbeam_channel::Sender;
+use shuttle_common::shuttle_toml::WasmConfig;
+use tokio::fs;
+use tokio::process::Command;
+use tracing::trace;
+
+use super::{send_message, BuildError};
+
+/// The id of custom sections, which hold things like debug info and names that a module does not
+/// need to run
+const CUSTOM_SECTION: u8 = 0;
+
+/// The magic number and version every wasm module starts with
+const HEADER_LEN: usize = 8;
+
+/// How much smaller post-processing made a shuttle-next module
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct WasmOptimization {
+    /// Size of the module as it was built, in bytes
+    pub before: u64,
+    /// Size of the module after post-processing, in bytes
+    pub after: u64,
+}
+
+/// Post-process the module at `path` in place as the `[wasm]` table of Shuttle.toml asks.
+///
+/// Returns `None` when nothing was asked for.
+pub(crate) async fn post_process(
+    path: &Path,
+    config: &WasmConfig,
+    tx: &Sender<Message>,
+) -> Result<Option<WasmOptimization>, BuildError> {
+    if config.optimize.is_none() && !config.strip {
+        return Ok(None);
+    }
+
+    let before = fs::metadata(path).await?.len();
+
+    if let Some(level) = &config.optimize {
+        send_message(
+            tx,
+            Message::TextLine(format!("Optimizing the wasm module with wasm-opt -{level}")),
+        );
+
+        // The module works without the optimization, so a missing wasm-opt does not fail the build
+        if let Err(error) = wasm_opt(path, level).await {
+            send_message(
+                tx,
+                Message::TextLine(format!("Skipping the wasm optimization: {error:#}")),
+            );
+        }
+    }
+
+    if config.strip {
+        let module = fs::read(path).await?;
+        let stripped = strip_custom_sections(&module).context("failed to strip the wasm module")?;
+        fs::write(path, stripped).await?;
+    }
+
+    let after = fs::metadata(path).await?.len();
+    trace!(before, after, "post-processed wasm module");
+
+    Ok(Some(WasmOptimization { before, after }))
+}
+
+async fn wasm_opt(path: &Path, level: &str) -> anyhow::Result<()> {
+    let optimized = path.with_extension("opt.wasm");
+
+    let output = Command::new("wasm-opt")
+        .arg(format!("-{level}"))
+        .arg(path)
+        .arg("-o")
+        .arg(&optimized)
+        .output()
+        .await
+        .context("failed to run wasm-opt, is binaryen installed?")?;
+
+    if !output.status.success() {
+        bail!(
+            "wasm-opt failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    fs::rename(&optimized, path).await?;
+
+    Ok(())
+}
+
+/// Remove the custom sections of a wasm module, keeping everything else as it is
+fn strip_custom_sections(module: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if module.len() < HEADER_LEN || &module[..4] != b"\0asm" {
+        bail!("not a wasm module");
+    }
+
+    let mut stripped = module[..HEADER_LEN].to_vec();
+    let mut rest = &module[HEADER_LEN..];
+
+    while let Some((&id, after_id)) = rest.split_first() {
+        let (size, size_len) = read_leb128(after_id)?;
+        let end = 1 + size_len + size as usize;
+        let section = rest
+            .get(..end)
+            .context("a section goes past the end of the module")?;
+
+        if id != CUSTOM_SECTION {
+            stripped.extend_from_slice(section);
+        }
+
+        rest = &rest[end..];
+    }
+
+    Ok(stripped)
+}
+
+/// Read an unsigned LEB128 number, returning it with how many bytes it took
+fn read_leb128(bytes: &[u8]) -> anyhow::Result<(u32, usize)> {
+    let mut value = 0u32;
+
+    for (index, byte) in bytes.iter().take(5).enumerate() {
+        value |= u32::from(byte & 0x7f) << (7 * index);
+
+        if byte & 0x80 == 0 {
+            return Ok((value, index + 1));
+        }
+    }
+
+    bail!("invalid section size")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_leb128, strip_custom_sections};
+
+    const HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+    #[test]
+    fn strip() {
+        let type_section = [0x01, 0x01, 0x00];
+        let custom_section = [0x00, 0x05, 0x04, b'n', b'a', b'm', b'e'];
+        let code_section = [0x0a, 0x01, 0x00];
+
+        let module = [&HEADER[..], &type_section, &custom_section, &code_section].concat();
+
+        assert_eq!(
+            strip_custom_sections(&module).unwrap(),
+            [&HEADER[..], &type_section, &code_section].concat()
+        );
+
+        assert!(strip_custom_sections(b"not wasm").is_err());
+        assert!(strip_custom_sections(&[&HEADER[..], &[0x01, 0x05, 0x00]].concat()).is_err());
+    }
+
+    #[test]
+    fn leb128() {
+        assert_eq!(read_leb128(&[0x05]).unwrap(), (5, 1));
+        assert_eq!(read_leb128(&[0xe5, 0x8e, 0x26]).unwrap(), (624485, 3));
+        assert!(read_leb128(&[0x80, 0x80]).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+use cargo_metadata::Message;
+use crossbeam_channel::Sender;
+use notify::{RecursiveMode, Watcher};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, trace};
+
+use super::{
+    build_workspace, find_services, BuildError, BuildOptions, BuiltService, WorkspaceServices,
+};
+
+/// How long the files have to stay unchanged before a rebuild starts
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// What happens to the builds started by [build_workspace_watch]
+#[derive(Debug)]
+pub enum BuildEvent {
+    /// A build of the named services started
+    Started { packages: Vec<String> },
+    /// The services were rebuilt
+    Finished(Vec<BuiltService>),
+    /// A build failed, the next change will start a new one
+    Failed(BuildError),
+}
+
+/// Build the services of a project, then rebuild the services affected by every change to the
+/// files of the project until `cancellation_token` is cancelled.
+///
+/// The result of each build is sent through `events`, while the output of cargo goes through `tx`
+/// like it does for [build_workspace].
+pub async fn build_workspace_watch(
+    project_path: &Path,
+    release_mode: bool,
+    tx: Sender<Message>,
+    events: Sender<BuildEvent>,
+    options: &BuildOptions,
+    cancellation_token: CancellationToken,
+) -> Result<(), BuildError> {
+    let manifest_path = std::env::current_dir()?
+        .join(project_path)
+        .join("Cargo.toml");
+    let find = || -> Result<WorkspaceServices, BuildError> {
+        let mut services =
+            find_services(&manifest_path, options.target_dir.as_deref(), tx.clone())?;
+        services.select(options)?;
+        services.check_versions(options)?;
+
+        Ok(services)
+    };
+
+    let mut services = find()?;
+
+    let (changes_tx, mut changes_rx) = unbounded_channel();
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) => {
+                let _ = changes_tx.send(event.paths);
+            }
+            Err(error) => debug!(%error, "failed to watch for changes"),
+        })
+        .context("failed to start watching the project for changes")?;
+    watcher
+        .watch(&services.workspace_root, RecursiveMode::Recursive)
+        .context("failed to start watching the project for changes")?;
+
+    // The first build is of every service
+    let mut affected: Option<Vec<String>> = None;
+
+    loop {
+        // Services are built on their own when only some of them changed
+        let (packages, builds) = match affected.take() {
+            None => (services.package_names(), vec![options.clone()]),
+            Some(packages) => {
+                let builds = packages
+                    .iter()
+                    .map(|package| BuildOptions {
+                        package: Some(package.clone()),
+                        ..options.clone()
+                    })
+                    .collect();
+
+                (packages, builds)
+            }
+        };
+
+        let finished = rebuild(
+            project_path,
+            release_mode,
+            &tx,
+            &events,
+            packages,
+            builds,
+            &cancellation_token,
+        )
+        .await;
+
+        if !finished {
+            return Ok(());
+        }
+
+        loop {
+            let changed = tokio::select! {
+                changed = next_changes(&mut changes_rx, &services.target_dir) => changed,
+                _ = cancellation_token.cancelled() => return Ok(()),
+            };
+
+            let Some(changed) = changed else {
+                debug!("stopped receiving changes to the project");
+                return Ok(());
+            };
+            trace!(?changed, "project changed");
+
+            // A changed Cargo.toml can add or remove services, so they have to be found again
+            match find() {
+                Ok(found) => {
+                    services = found;
+                    affected = affected_packages(&services, &changed);
+                    break;
+                }
+                Err(error) => {
+                    let _ = events.send(BuildEvent::Failed(error));
+                }
+            }
+        }
+    }
+}
+
+/// Run the `builds` of the `packages`. Returns false when they were cancelled.
+async fn rebuild(
+    project_path: &Path,
+    release_mode: bool,
+    tx: &Sender<Message>,
+    events: &Sender<BuildEvent>,
+    packages: Vec<String>,
+    builds: Vec<BuildOptions>,
+    cancellation_token: &CancellationToken,
+) -> bool {
+    let _ = events.send(BuildEvent::Started { packages });
+
+    let mut built = Vec::new();
+
+    for options in builds {
+        match build_workspace(
+            project_path,
+            release_mode,
+            tx.clone(),
+            &options,
+            cancellation_token.clone(),
+        )
+        .await
+        {
+            Ok(services) => built.extend(services),
+            Err(_) if cancellation_token.is_cancelled() => return false,
+            Err(error) => {
+                let _ = events.send(BuildEvent::Failed(error));
+                return true;
+            }
+        }
+    }
+
+    let _ = events.send(BuildEvent::Finished(built));
+
+    true
+}
+
+/// Wait for the next changes to the project which matter for its build
+async fn next_changes(
+    changes: &mut UnboundedReceiver<Vec<PathBuf>>,
+    target_dir: &Path,
+) -> Option<Vec<PathBuf>> {
+    let relevant = |paths: Vec<PathBuf>| {
+        paths
+            .into_iter()
+            .filter(|path| is_relevant(path, target_dir))
+    };
+
+    let mut changed = Vec::new();
+    while changed.is_empty() {
+        changed.extend(relevant(changes.recv().await?));
+    }
+
+    // Editors and formatters save files in several steps, so wait for them to finish
+    while let Ok(Some(paths)) = timeout(DEBOUNCE, changes.recv()).await {
+        changed.extend(relevant(paths));
+    }
+
+    changed.sort();
+    changed.dedup();
+
+    Some(changed)
+}
+
+/// Changes to the build output and the git directory don't need a rebuild
+fn is_relevant(path: &Path, target_dir: &Path) -> bool {
+    !path.starts_with(target_dir)
+        && !path
+            .components()
+            .any(|component| component.as_os_str() == ".git")
+}
+
+/// Find the services which have to be rebuilt after the `changed` files changed, or `None` when
+/// all of them have to be.
+fn affected_packages(services: &WorkspaceServices, changed: &[PathBuf]) -> Option<Vec<String>> {
+    let packages: Vec<_> = services.alpha.iter().chain(&services.next).collect();
+    let mut affected = BTreeSet::new();
+
+    for path in changed {
+        // Files outside of the services, like the workspace's Cargo.lock or a library the
+        // services share, can change all of them
+        let package = packages
+            .iter()
+            .filter(|package| path.starts_with(&package.root))
+            .max_by_key(|package| package.root.components().count())?;
+
+        affected.insert(package.name.clone());
+    }
+
+    Some(affected.into_iter().collect())
+}
+
+impl WorkspaceServices {
+    fn package_names(&self) -> Vec<String> {
+        self.alpha
+            .iter()
+            .chain(&self.next)
+            .map(|package| package.name.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{affected_packages, is_relevant};
+    use crate::builder::{ServicePackage, WorkspaceServices};
+
+    fn package(name: &str, root: &str) -> ServicePackage {
+        ServicePackage {
+            name: name.to_string(),
+            root: PathBuf::from(root),
+            manifest_path: Path::new(root).join("Cargo.toml"),
+            bin: None,
+            shuttle_dependencies: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn affected() {
+        let services = WorkspaceServices {
+            alpha: vec![package("alpha", "/ws/alpha")],
+            next: vec![package("next", "/ws/next")],
+            shuttle_toml: Default::default(),
+            workspace_root: PathBuf::from("/ws"),
+            target_dir: PathBuf::from("/ws/target"),
+        };
+
+        assert_eq!(
+            affected_packages(&services, &[PathBuf::from("/ws/alpha/src/main.rs")]),
+            Some(vec!["alpha".to_string()])
+        );
+        assert_eq!(
+            affected_packages(
+                &services,
+                &[
+                    PathBuf::from("/ws/next/src/lib.rs"),
+                    PathBuf::from("/ws/alpha/Cargo.toml")
+                ]
+            ),
+            Some(vec!["alpha".to_string(), "next".to_string()])
+        );
+        assert_eq!(
+            affected_packages(
+                &services,
+                &[
+                    PathBuf::from("/ws/alpha/src/main.rs"),
+                    PathBuf::from("/ws/Cargo.lock")
+                ]
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn relevant() {
+        let target_dir = Path::new("/ws/target");
+
+        assert!(is_relevant(Path::new("/ws/src/main.rs"), target_dir));
+        assert!(!is_relevant(
+            Path::new("/ws/target/debug/service"),
+            target_dir
+        ));
+        assert!(!is_relevant(Path::new("/ws/.git/index"), target_dir));
+    }
+}
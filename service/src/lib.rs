@@ -85,9 +85,20 @@ pub trait Factory: Send + Sync {
         db_type: database::Type,
     ) -> Result<DatabaseReadyInfo, crate::Error>;
 
+    /// Get a collection on the shared Qdrant instance with vectors of `vector_size`, compared by
+    /// `distance`, and a key which can only reach this collection
+    async fn get_qdrant_collection(
+        &mut self,
+        vector_size: u64,
+        distance: &str,
+    ) -> Result<DatabaseReadyInfo, crate::Error>;
+
     /// Get all the secrets for a service
     async fn get_secrets(&mut self) -> Result<BTreeMap<String, String>, crate::Error>;
 
+    /// Get the arguments from the `[run]` table of the Shuttle.toml to start the service with
+    fn get_args(&self) -> Vec<String>;
+
     /// Get the name for the service being deployed
     fn get_service_name(&self) -> ServiceName;
 
@@ -1,15 +1,43 @@
 use std::path::{Path, PathBuf};
 
-use shuttle_service::builder::{build_workspace, BuiltService};
+use shuttle_service::builder::{
+    build_workspace, plan_workspace, purge_crate, BuildError, BuildOptions, BuiltService,
+    CancellationToken,
+};
 
 #[tokio::test]
-#[should_panic(expected = "1 job failed")]
+#[should_panic(expected = "Build failed")]
 async fn not_shuttle() {
     let (tx, _) = crossbeam_channel::unbounded();
     let project_path = format!("{}/tests/resources/not-shuttle", env!("CARGO_MANIFEST_DIR"));
-    build_workspace(Path::new(&project_path), false, tx)
-        .await
-        .unwrap();
+    build_workspace(
+        Path::new(&project_path),
+        false,
+        tx,
+        &Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn not_shuttle_diagnostics() {
+    let (tx, _) = crossbeam_channel::unbounded();
+    let project_path = format!("{}/tests/resources/not-shuttle", env!("CARGO_MANIFEST_DIR"));
+
+    match build_workspace(
+        Path::new(&project_path),
+        false,
+        tx,
+        &Default::default(),
+        Default::default(),
+    )
+    .await
+    {
+        Err(BuildError::Compile { diagnostics, .. }) => assert!(!diagnostics.is_empty()),
+        other => panic!("expected a compile error, got {other:?}"),
+    }
 }
 
 #[tokio::test]
@@ -17,7 +45,15 @@ async fn not_shuttle() {
 async fn not_bin() {
     let (tx, _) = crossbeam_channel::unbounded();
     let project_path = format!("{}/tests/resources/not-bin", env!("CARGO_MANIFEST_DIR"));
-    match build_workspace(Path::new(&project_path), false, tx).await {
+    match build_workspace(
+        Path::new(&project_path),
+        false,
+        tx,
+        &Default::default(),
+        Default::default(),
+    )
+    .await
+    {
         Ok(_) => {}
         Err(e) => panic!("{}", e.to_string()),
     }
@@ -29,9 +65,15 @@ async fn is_bin() {
     let project_path = format!("{}/tests/resources/is-bin", env!("CARGO_MANIFEST_DIR"));
 
     assert_eq!(
-        build_workspace(Path::new(&project_path), false, tx)
-            .await
-            .unwrap(),
+        build_workspace(
+            Path::new(&project_path),
+            false,
+            tx,
+            &Default::default(),
+            Default::default()
+        )
+        .await
+        .unwrap(),
         vec![BuiltService::new(
             PathBuf::from(&project_path).join("target/debug/is-bin"),
             false,
@@ -42,6 +84,25 @@ async fn is_bin() {
     );
 }
 
+#[tokio::test]
+#[should_panic(expected = "The `shuttle` profile set in Shuttle.toml does not exist.")]
+async fn unknown_profile() {
+    let (tx, _) = crossbeam_channel::unbounded();
+    let project_path = format!(
+        "{}/tests/resources/unknown-profile",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    build_workspace(
+        Path::new(&project_path),
+        false,
+        tx,
+        &Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+}
+
 #[tokio::test]
 #[should_panic(expected = "failed to read")]
 async fn not_found() {
@@ -50,9 +111,15 @@ async fn not_found() {
         "{}/tests/resources/non-existing",
         env!("CARGO_MANIFEST_DIR")
     );
-    build_workspace(Path::new(&project_path), false, tx)
-        .await
-        .unwrap();
+    build_workspace(
+        Path::new(&project_path),
+        false,
+        tx,
+        &Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
 }
 
 // Test that alpha and next projects are compiled correctly. Any shared library crates should not be compiled too
@@ -62,9 +129,15 @@ async fn workspace() {
     let project_path = format!("{}/tests/resources/workspace", env!("CARGO_MANIFEST_DIR"));
 
     assert_eq!(
-        build_workspace(Path::new(&project_path), false, tx)
-            .await
-            .unwrap(),
+        build_workspace(
+            Path::new(&project_path),
+            false,
+            tx,
+            &Default::default(),
+            Default::default()
+        )
+        .await
+        .unwrap(),
         vec![
             BuiltService::new(
                 PathBuf::from(&project_path).join("target/debug/alpha"),
@@ -74,7 +147,7 @@ async fn workspace() {
                 PathBuf::from(&project_path).join("alpha/Cargo.toml")
             ),
             BuiltService::new(
-                PathBuf::from(&project_path).join("target/wasm32-wasi/debug/next.wasm"),
+                PathBuf::from(&project_path).join("target/next/wasm32-wasi/debug/next.wasm"),
                 true,
                 "next".to_string(),
                 PathBuf::from(&project_path).join("next"),
@@ -83,3 +156,289 @@ async fn workspace() {
         ]
     );
 }
+
+#[tokio::test]
+async fn cancelled() {
+    let (tx, _) = crossbeam_channel::unbounded();
+    let project_path = format!("{}/tests/resources/is-bin", env!("CARGO_MANIFEST_DIR"));
+    let cancellation_token = CancellationToken::new();
+    cancellation_token.cancel();
+
+    assert!(matches!(
+        build_workspace(
+            Path::new(&project_path),
+            false,
+            tx,
+            &Default::default(),
+            cancellation_token
+        )
+        .await,
+        Err(BuildError::Cancelled)
+    ));
+}
+
+#[tokio::test]
+async fn workspace_package() {
+    let (tx, _) = crossbeam_channel::unbounded();
+    let project_path = format!("{}/tests/resources/workspace", env!("CARGO_MANIFEST_DIR"));
+    let options = BuildOptions {
+        package: Some("next".to_string()),
+        single_service: true,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        build_workspace(
+            Path::new(&project_path),
+            false,
+            tx,
+            &options,
+            Default::default()
+        )
+        .await
+        .unwrap(),
+        vec![BuiltService::new(
+            PathBuf::from(&project_path).join("target/next/wasm32-wasi/debug/next.wasm"),
+            true,
+            "next".to_string(),
+            PathBuf::from(&project_path).join("next"),
+            PathBuf::from(&project_path).join("next/Cargo.toml")
+        )]
+    );
+}
+
+#[tokio::test]
+#[should_panic(
+    expected = "No Shuttle service named `missing` was found in this workspace. The services are: alpha, next"
+)]
+async fn workspace_unknown_package() {
+    let (tx, _) = crossbeam_channel::unbounded();
+    let project_path = format!("{}/tests/resources/workspace", env!("CARGO_MANIFEST_DIR"));
+    let options = BuildOptions {
+        package: Some("missing".to_string()),
+        ..Default::default()
+    };
+
+    build_workspace(
+        Path::new(&project_path),
+        false,
+        tx,
+        &options,
+        Default::default(),
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+#[should_panic(
+    expected = "please select the one to build with `--package`. The services are: alpha, next"
+)]
+async fn workspace_multiple_services() {
+    let (tx, _) = crossbeam_channel::unbounded();
+    let project_path = format!("{}/tests/resources/workspace", env!("CARGO_MANIFEST_DIR"));
+    let options = BuildOptions {
+        single_service: true,
+        ..Default::default()
+    };
+
+    build_workspace(
+        Path::new(&project_path),
+        false,
+        tx,
+        &options,
+        Default::default(),
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn target_dir() {
+    let (tx, _) = crossbeam_channel::unbounded();
+    let project_path = format!("{}/tests/resources/is-bin", env!("CARGO_MANIFEST_DIR"));
+    let target_dir = tempfile::tempdir().unwrap();
+    let options = BuildOptions {
+        target_dir: Some(target_dir.path().to_path_buf()),
+        ..Default::default()
+    };
+
+    let services = build_workspace(
+        Path::new(&project_path),
+        false,
+        tx,
+        &options,
+        Default::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        services[0].executable_path,
+        target_dir.path().join("debug/is-bin")
+    );
+}
+
+#[tokio::test]
+#[should_panic(expected = "Building with `--locked` needs a Cargo.lock")]
+async fn missing_lockfile() {
+    let (tx, _) = crossbeam_channel::unbounded();
+    let project_path = format!(
+        "{}/tests/resources/missing-lockfile",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    build_workspace(
+        Path::new(&project_path),
+        false,
+        tx,
+        &Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+#[should_panic(expected = "The pre_build hook in Shuttle.toml failed, because it exited with")]
+async fn failing_hook() {
+    let (tx, _) = crossbeam_channel::unbounded();
+    let project_path = format!(
+        "{}/tests/resources/failing-hook",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    build_workspace(
+        Path::new(&project_path),
+        false,
+        tx,
+        &Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+#[should_panic(expected = "Your Shuttle project has several binaries: api, worker.")]
+async fn multiple_bins() {
+    let (tx, _) = crossbeam_channel::unbounded();
+    let project_path = format!(
+        "{}/tests/resources/multiple-bins",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    build_workspace(
+        Path::new(&project_path),
+        false,
+        tx,
+        &Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn conflicting_runtimes() {
+    let (tx, _) = crossbeam_channel::unbounded();
+    let project_path = format!(
+        "{}/tests/resources/conflicting-runtimes",
+        env!("CARGO_MANIFEST_DIR")
+    );
+
+    match build_workspace(
+        Path::new(&project_path),
+        false,
+        tx,
+        &Default::default(),
+        Default::default(),
+    )
+    .await
+    {
+        Err(BuildError::ConflictingRuntimes(package)) => {
+            assert_eq!(package, "conflicting-runtimes")
+        }
+        other => panic!("expected a conflict between the runtimes, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn unsupported_version() {
+    let (tx, _) = crossbeam_channel::unbounded();
+    let project_path = format!(
+        "{}/tests/resources/unsupported-version",
+        env!("CARGO_MANIFEST_DIR")
+    );
+
+    match build_workspace(
+        Path::new(&project_path),
+        false,
+        tx,
+        &BuildOptions {
+            supported_version: Some("0.17.0".parse().unwrap()),
+            ..Default::default()
+        },
+        Default::default(),
+    )
+    .await
+    {
+        Err(BuildError::UnsupportedVersion {
+            dependency,
+            requirement,
+            ..
+        }) => {
+            assert_eq!(dependency, "shuttle-runtime");
+            assert_eq!(requirement, "^0.1.0");
+        }
+        other => panic!("expected an unsupported version, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn plan() {
+    let (tx, _) = crossbeam_channel::unbounded();
+    let project_path = format!("{}/tests/resources/workspace", env!("CARGO_MANIFEST_DIR"));
+
+    let plan = plan_workspace(Path::new(&project_path), false, tx, &Default::default())
+        .await
+        .unwrap();
+
+    assert_eq!(plan.profile, "dev");
+    assert_eq!(plan.steps.len(), 2);
+
+    let alpha = &plan.steps[0];
+    assert!(!alpha.is_wasm);
+    assert!(alpha.command.contains(&"--bin".to_string()));
+    assert_eq!(
+        alpha.services[0].executable_path,
+        PathBuf::from(&project_path).join("target/debug/alpha")
+    );
+
+    let next = &plan.steps[1];
+    assert!(next.is_wasm);
+    assert!(next.command.contains(&"wasm32-wasi".to_string()));
+    assert_eq!(
+        next.services[0].executable_path,
+        PathBuf::from(&project_path).join("target/next/wasm32-wasi/debug/next.wasm")
+    );
+}
+
+#[tokio::test]
+async fn purge() {
+    let target_dir = tempfile::Builder::new()
+        .prefix("shuttle-purge")
+        .tempdir()
+        .unwrap()
+        .into_path();
+    std::fs::create_dir_all(target_dir.join("release/incremental")).unwrap();
+    std::fs::write(target_dir.join("release/incremental/state"), b"corrupted").unwrap();
+
+    let summary = purge_crate(Path::new("not-a-project"), Some(&target_dir))
+        .await
+        .unwrap();
+
+    assert_eq!(summary.bytes_freed, 9);
+    assert_eq!(summary.removed_paths, vec![target_dir.clone()]);
+    assert!(!target_dir.exists());
+
+    let summary = purge_crate(Path::new("not-a-project"), None).await.unwrap();
+    assert_eq!(summary, Default::default());
+}
@@ -0,0 +1,6 @@
+shuttle_next::app! {
+    #[shuttle_next::endpoint(method = get, route = "/")]
+    async fn hello() -> &'static str {
+        "Hello, World!"
+    }
+}
@@ -23,10 +23,12 @@ pub struct AxumService<S = ()>(pub axum::Router<S>);
 #[shuttle_runtime::async_trait]
 impl shuttle_runtime::Service for AxumService {
     /// Takes the router that is returned by the user in their [shuttle_runtime::main] function
-    /// and binds to an address passed in by shuttle.
+    /// and binds to an address passed in by shuttle. The requests in flight are finished when
+    /// shuttle shuts the service down.
     async fn bind(mut self, addr: SocketAddr) -> Result<(), Error> {
         axum::Server::bind(&addr)
             .serve(self.0.into_make_service())
+            .with_graceful_shutdown(shuttle_runtime::shutdown_signal())
             .await
             .map_err(CustomError::new)?;
 